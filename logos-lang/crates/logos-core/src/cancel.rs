@@ -0,0 +1,49 @@
+//! Cancellation of long-running operations
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A flag the host raises to stop an operation; clones share the flag.
+///
+/// Long operations check it between units of work (a file, a document) and
+/// return what they have so far once it is raised.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the operations checking this token to stop
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Lower the flag again, for the next operation
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clones_share_the_flag() {
+        let token = CancellationToken::new();
+        let host = token.clone();
+        assert!(!token.is_cancelled());
+
+        host.cancel();
+        assert!(token.is_cancelled());
+
+        token.reset();
+        assert!(!host.is_cancelled());
+    }
+}
@@ -43,6 +43,9 @@ pub struct Diagnostic {
     /// Related information
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub related_information: Vec<DiagnosticRelatedInformation>,
+    /// Edits that resolve the diagnostic, offered as quick fixes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fixes: Vec<DiagnosticFix>,
 }
 
 impl Diagnostic {
@@ -54,6 +57,7 @@ impl Diagnostic {
             source: None,
             message,
             related_information: Vec::new(),
+            fixes: Vec::new(),
         }
     }
 
@@ -65,6 +69,7 @@ impl Diagnostic {
             source: None,
             message,
             related_information: Vec::new(),
+            fixes: Vec::new(),
         }
     }
 
@@ -76,6 +81,7 @@ impl Diagnostic {
             source: None,
             message,
             related_information: Vec::new(),
+            fixes: Vec::new(),
         }
     }
 
@@ -87,6 +93,7 @@ impl Diagnostic {
             source: None,
             message,
             related_information: Vec::new(),
+            fixes: Vec::new(),
         }
     }
 
@@ -104,6 +111,21 @@ impl Diagnostic {
         self.related_information = related;
         self
     }
+
+    pub fn with_fix(mut self, fix: DiagnosticFix) -> Self {
+        self.fixes.push(fix);
+        self
+    }
+}
+
+/// Replace `range` with `new_text` to resolve a diagnostic
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticFix {
+    /// Title of the code action, e.g. "Remove unused import"
+    pub title: String,
+    pub range: Range,
+    pub new_text: String,
 }
 
 /// Related information for a diagnostic
@@ -1,11 +1,13 @@
 //! Logos Core - Core types and interfaces for the language service
 
+pub mod cancel;
 pub mod diagnostic;
 pub mod document;
 pub mod position;
 pub mod symbol;
 
-pub use diagnostic::{Diagnostic, DiagnosticSeverity};
+pub use cancel::CancellationToken;
+pub use diagnostic::{Diagnostic, DiagnosticFix, DiagnosticSeverity};
 pub use document::Document;
 pub use position::{Location, Position, Range};
 pub use symbol::{Symbol, SymbolKind};
\ No newline at end of file
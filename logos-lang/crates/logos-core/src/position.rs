@@ -2,6 +2,28 @@
 
 use serde::{Deserialize, Serialize};
 
+/// Convert a UTF-16 column within a line to a byte index, clamped to the line
+pub fn column_to_byte(line: &str, column: u32) -> usize {
+    let mut units = 0;
+    for (byte, ch) in line.char_indices() {
+        // A column inside a surrogate pair snaps to the character's start
+        if units + ch.len_utf16() > column as usize {
+            return byte;
+        }
+        units += ch.len_utf16();
+    }
+    line.len()
+}
+
+/// Convert a byte index within a line to a UTF-16 column
+pub fn byte_to_column(line: &str, byte: usize) -> u32 {
+    let mut byte = byte.min(line.len());
+    while !line.is_char_boundary(byte) {
+        byte -= 1;
+    }
+    line[..byte].encode_utf16().count() as u32
+}
+
 /// A position in a text document (0-indexed)
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Position {
@@ -19,6 +41,38 @@ impl Position {
     pub fn zero() -> Self {
         Self { line: 0, column: 0 }
     }
+
+    /// The position right after `text` when it is inserted here
+    pub fn advanced(self, text: &str) -> Self {
+        match text.rfind('\n') {
+            Some(i) => Self::new(
+                self.line + text.matches('\n').count() as u32,
+                text[i + 1..].encode_utf16().count() as u32,
+            ),
+            None => Self::new(self.line, self.column + text.encode_utf16().count() as u32),
+        }
+    }
+
+    /// Where this position lands once `range` is replaced with `new_text`.
+    ///
+    /// Positions before the range stay put and positions at or after its
+    /// end move with the text following it, so an insertion at the
+    /// position pushes it forward. A position inside the replaced text has
+    /// nothing left to point at and moves to the start of the edit.
+    pub fn shifted(self, range: Range, new_text: &str) -> Self {
+        if self < range.start {
+            return self;
+        }
+        if self < range.end {
+            return range.start;
+        }
+        let new_end = range.start.advanced(new_text);
+        if self.line == range.end.line {
+            Self::new(new_end.line, new_end.column + (self.column - range.end.column))
+        } else {
+            Self::new(self.line - range.end.line + new_end.line, self.column)
+        }
+    }
 }
 
 impl Default for Position {
@@ -72,13 +126,27 @@ impl Range {
         }
     }
 
-    /// Check if this range contains the given position
+    /// Check if this range contains the given position. Ranges are
+    /// half-open: `end` is the first position after the range.
     pub fn contains(&self, pos: Position) -> bool {
         self.start <= pos && pos < self.end
     }
 
-    /// Check if this range overlaps with another range
+    /// Check if `other` lies entirely within this range
+    pub fn contains_range(&self, other: &Range) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    /// Check if this range overlaps with another range. Ranges that only
+    /// touch (one ends where the other starts) do not overlap; an empty range
+    /// (a cursor) overlaps the ranges containing its position.
     pub fn overlaps(&self, other: &Range) -> bool {
+        if self.is_empty() {
+            return other.contains(self.start) || self == other;
+        }
+        if other.is_empty() {
+            return self.contains(other.start);
+        }
         self.start < other.end && other.start < self.end
     }
 
@@ -86,6 +154,21 @@ impl Range {
     pub fn is_empty(&self) -> bool {
         self.start == self.end
     }
+
+    /// This range once `range` is replaced with `new_text`; both ends move
+    /// as in [`Position::shifted`]
+    pub fn shifted(self, range: Range, new_text: &str) -> Self {
+        Self::new(self.start.shifted(range, new_text), self.end.shifted(range, new_text))
+    }
+
+    /// This range once all of `edits` are applied. Edit ranges refer to the
+    /// text before any of them, as in an LSP edit list, and must not overlap.
+    pub fn remapped<'a>(self, edits: impl IntoIterator<Item = (Range, &'a str)>) -> Self {
+        let mut edits: Vec<(Range, &str)> = edits.into_iter().collect();
+        // From the last edit back, so each edit's range is still valid
+        edits.sort_by_key(|edit| std::cmp::Reverse(edit.0.start));
+        edits.into_iter().fold(self, |range, (edit, text)| range.shifted(edit, text))
+    }
 }
 
 
@@ -124,4 +207,70 @@ mod tests {
         assert!(!range.contains(Position::new(1, 10)));
         assert!(!range.contains(Position::new(0, 5)));
     }
+
+    #[test]
+    fn test_range_overlaps_half_open() {
+        let statement = Range::from_coords(1, 0, 2, 0);
+        let name = Range::from_coords(1, 4, 1, 7);
+        assert!(statement.overlaps(&name));
+        assert!(name.overlaps(&statement));
+        assert!(name.overlaps(&name));
+
+        // Touching ranges don't overlap
+        assert!(!statement.overlaps(&Range::from_coords(2, 0, 2, 3)));
+        assert!(!Range::from_coords(1, 0, 1, 4).overlaps(&name));
+        assert!(!name.overlaps(&Range::from_coords(1, 7, 1, 9)));
+
+        // A cursor overlaps the range it is in, including at its start
+        assert!(name.overlaps(&Range::point(1, 4)));
+        assert!(Range::point(1, 5).overlaps(&name));
+        assert!(!name.overlaps(&Range::point(1, 7)));
+        assert!(Range::point(1, 7).overlaps(&Range::point(1, 7)));
+    }
+
+    #[test]
+    fn test_position_shifted_by_edit() {
+        let tracked = Position::new(2, 8);
+
+        // Before: an edit on an earlier line that adds a line
+        assert_eq!(tracked.shifted(Range::from_coords(0, 0, 0, 3), "a\nb"), Position::new(3, 8));
+        // Before, on the same line: the column moves by the length change
+        assert_eq!(tracked.shifted(Range::from_coords(2, 2, 2, 6), "x"), Position::new(2, 5));
+        // Before, joining lines: the column continues after the new text
+        assert_eq!(tracked.shifted(Range::from_coords(1, 4, 2, 2), "é"), Position::new(1, 11));
+        // At the position: an insertion pushes it forward
+        assert_eq!(tracked.shifted(Range::point(2, 8), "ab"), Position::new(2, 10));
+        // Inside the replaced text
+        assert_eq!(tracked.shifted(Range::from_coords(2, 4, 3, 0), "\n"), Position::new(2, 4));
+        // After: unaffected
+        assert_eq!(tracked.shifted(Range::from_coords(2, 9, 4, 0), ""), tracked);
+        assert_eq!(tracked.shifted(Range::from_coords(3, 0, 3, 1), "\n\n"), tracked);
+    }
+
+    #[test]
+    fn test_range_remapped_through_edits() {
+        // `let total = a + b;` on line 1, with edits before, around and after it
+        let tracked = Range::from_coords(1, 4, 1, 9);
+        let edits = [
+            (Range::from_coords(1, 12, 1, 17), "sum(a, b)"),
+            (Range::from_coords(0, 0, 0, 0), "// header\n"),
+            (Range::from_coords(1, 0, 1, 3), "const"),
+        ];
+        assert_eq!(tracked.remapped(edits), Range::from_coords(2, 6, 2, 11));
+        assert_eq!(tracked.remapped([]), tracked);
+
+        // An edit inside the range moves only its end
+        assert_eq!(tracked.shifted(Range::from_coords(1, 5, 1, 7), "\n"), Range::from_coords(1, 4, 2, 2));
+    }
+
+    #[test]
+    fn test_range_contains_range() {
+        let statement = Range::from_coords(1, 0, 2, 0);
+        let name = Range::from_coords(1, 4, 1, 7);
+        assert!(statement.contains_range(&name));
+        assert!(name.contains_range(&name));
+        assert!(statement.contains_range(&Range::point(2, 0)));
+        assert!(!name.contains_range(&statement));
+        assert!(!name.contains_range(&Range::from_coords(1, 5, 1, 8)));
+    }
 }
\ No newline at end of file
@@ -1,9 +1,11 @@
 //! Analysis handlers: TODO items, unused symbols
 
+use std::collections::HashMap;
+
 use serde_json::{json, Value};
-use logos_index::TodoKind;
+use logos_index::{ExclusionPatterns, TodoItem};
 
-use crate::protocol::{DocumentSymbolParams, RequestId, Response};
+use crate::protocol::{DocumentSymbolParams, DuplicateBlocksParams, ExclusionPatternsParams, RequestId, Response, TodosGroupedByFileParams, UnusedSymbolsParams, WorkspaceDeadCodeParams};
 use crate::state::State;
 
 /// Handle logos/getTodoItems
@@ -22,25 +24,7 @@ pub fn get_todo_items(state: &State, params: &Value, id: Option<RequestId>) -> R
     let uri = &params.text_document.uri;
     let todos = state.todo_index.get_document_todos(uri);
 
-    let items: Vec<_> = todos.iter().map(|todo| {
-        json!({
-            "kind": todo_kind_to_string(todo.kind),
-            "text": todo.text,
-            "author": todo.author,
-            "priority": todo.priority,
-            "line": todo.line,
-            "range": {
-                "start": {
-                    "line": todo.range.start.line,
-                    "character": todo.range.start.column
-                },
-                "end": {
-                    "line": todo.range.end.line,
-                    "character": todo.range.end.column
-                }
-            }
-        })
-    }).collect();
+    let items: Vec<_> = todos.iter().map(todo_json).collect();
 
     Response::success(id, json!(items))
 }
@@ -52,7 +36,7 @@ pub fn get_all_todo_items(state: &State, id: Option<RequestId>) -> Response {
     let items: Vec<_> = todos.iter().map(|(uri, todo)| {
         json!({
             "uri": uri,
-            "kind": todo_kind_to_string(todo.kind),
+            "kind": todo.kind,
             "text": todo.text,
             "author": todo.author,
             "priority": todo.priority,
@@ -73,29 +57,130 @@ pub fn get_all_todo_items(state: &State, id: Option<RequestId>) -> Response {
     Response::success(id, json!(items))
 }
 
+/// Handle logos/getTodosGroupedByFile
+pub fn get_todos_grouped_by_file(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: TodosGroupedByFileParams = if params.is_null() {
+        TodosGroupedByFileParams::default()
+    } else {
+        match serde_json::from_value(params.clone()) {
+            Ok(p) => p,
+            Err(e) => {
+                return Response::error(
+                    id,
+                    crate::protocol::error_codes::INVALID_PARAMS,
+                    format!("Invalid getTodosGroupedByFile params: {}", e),
+                );
+            }
+        }
+    };
+
+    // Keys keep their insertion order, so the busiest file comes first
+    let groups: serde_json::Map<String, Value> = state
+        .todo_index
+        .todos_by_file(params.kind)
+        .into_iter()
+        .map(|(uri, items)| {
+            let items: Vec<_> = items.into_iter().map(todo_json).collect();
+            (uri.to_string(), json!({"count": items.len(), "items": items}))
+        })
+        .collect();
+
+    Response::success(id, Value::Object(groups))
+}
+
+/// Handle logos/getTodoChanges
+pub fn get_todo_changes(state: &mut State, id: Option<RequestId>) -> Response {
+    let changes = state.todo_index.take_changes();
+
+    Response::success(id, json!({
+        "added": changes.added,
+        "removed": changes.removed,
+        "changed": changes.changed,
+        "generation": changes.generation
+    }))
+}
+
 /// Handle logos/getTodoStats
 pub fn get_todo_stats(state: &State, id: Option<RequestId>) -> Response {
-    let count_by_kind = state.todo_index.count_by_kind();
-
-    let stats = json!({
-        "total": state.todo_index.todo_count(),
-        "byKind": {
-            "todo": count_by_kind.get(&TodoKind::Todo).unwrap_or(&0),
-            "fixme": count_by_kind.get(&TodoKind::Fixme).unwrap_or(&0),
-            "hack": count_by_kind.get(&TodoKind::Hack).unwrap_or(&0),
-            "xxx": count_by_kind.get(&TodoKind::Xxx).unwrap_or(&0),
-            "note": count_by_kind.get(&TodoKind::Note).unwrap_or(&0),
-            "bug": count_by_kind.get(&TodoKind::Bug).unwrap_or(&0),
-            "optimize": count_by_kind.get(&TodoKind::Optimize).unwrap_or(&0)
+    Response::success(id, json!(state.todo_index.stats()))
+}
+
+fn todo_json(todo: &TodoItem) -> Value {
+    json!({
+        "kind": todo.kind,
+        "text": todo.text,
+        "author": todo.author,
+        "priority": todo.priority,
+        "line": todo.line,
+        "range": {
+            "start": {
+                "line": todo.range.start.line,
+                "character": todo.range.start.column
+            },
+            "end": {
+                "line": todo.range.end.line,
+                "character": todo.range.end.column
+            }
         }
-    });
+    })
+}
+
+/// Handle logos/getCommentFoldingRanges
+pub fn get_comment_folding_ranges(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: DocumentSymbolParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid getCommentFoldingRanges params: {}", e),
+            );
+        }
+    };
 
-    Response::success(id, stats)
+    let doc = match state.get_document(&params.text_document.uri) {
+        Some(d) => d,
+        None => return Response::success(id, json!([])),
+    };
+
+    let blocks = state.todo_index.comment_blocks(doc.content(), Some(&doc.language_id));
+    let ranges: Vec<_> = blocks.iter().filter(|block| block.is_foldable()).map(|block| {
+        json!({
+            "startLine": block.start_line,
+            "endLine": block.end_line,
+            "kind": "comment",
+            "hasTodos": block.has_todos(),
+            "todoKinds": block.todo_kinds
+        })
+    }).collect();
+
+    Response::success(id, json!(ranges))
+}
+
+/// Handle logos/setExclusionPatterns
+pub fn set_exclusion_patterns(state: &mut State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: ExclusionPatternsParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid setExclusionPatterns params: {}", e),
+            );
+        }
+    };
+
+    let exclusions = ExclusionPatterns::new(&params.patterns);
+    let patterns: Vec<&str> = exclusions.patterns().collect();
+    let result = json!({ "patterns": patterns });
+    state.set_exclusion_patterns(exclusions);
+
+    Response::success(id, result)
 }
 
 /// Handle logos/getUnusedSymbols
 pub fn get_unused_symbols(state: &State, params: &Value, id: Option<RequestId>) -> Response {
-    let params: DocumentSymbolParams = match serde_json::from_value(params.clone()) {
+    let params: UnusedSymbolsParams = match serde_json::from_value(params.clone()) {
         Ok(p) => p,
         Err(e) => {
             return Response::error(
@@ -113,52 +198,174 @@ pub fn get_unused_symbols(state: &State, params: &Value, id: Option<RequestId>)
         None => return Response::success(id, json!([])),
     };
 
-    let symbols: Vec<_> = state.symbol_index.get_document_symbols(uri)
-        .iter()
-        .map(|s| logos_core::Symbol {
-            name: s.name.clone(),
-            kind: s.kind,
-            range: s.range,
-            selection_range: s.selection_range,
-            detail: None,
-            children: Vec::new(),
-        })
-        .collect();
+    let mut detector = logos_semantic::UnusedDetector::new().with_include_exported(params.include_exported);
+    for prefix in &params.ignore_prefixes {
+        detector.ignore_pattern(prefix);
+    }
+    for pattern in &params.ignore_regexes {
+        if let Err(e) = detector.ignore_regex(pattern) {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid ignore regex '{}': {}", pattern, e),
+            );
+        }
+    }
 
-    let mut detector = logos_semantic::UnusedDetector::new();
+    let symbols = state.unused_candidates(uri, &mut detector);
     let unused = detector.analyze(&symbols, doc.content());
 
-    let items: Vec<_> = unused.iter().map(|item| {
-        json!({
-            "kind": format!("{:?}", item.kind).to_lowercase(),
-            "name": item.name,
-            "canRemove": item.can_remove,
-            "fixAction": item.fix_action,
-            "range": {
-                "start": {
-                    "line": item.range.start.line,
-                    "character": item.range.start.column
-                },
-                "end": {
-                    "line": item.range.end.line,
-                    "character": item.range.end.column
-                }
+    let items: Vec<_> = unused.iter().map(unused_item_json).collect();
+
+    Response::success(id, json!(items))
+}
+
+/// Handle logos/getWorkspaceDeadCode
+pub fn get_workspace_dead_code(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: WorkspaceDeadCodeParams = if params.is_null() {
+        WorkspaceDeadCodeParams::default()
+    } else {
+        match serde_json::from_value(params.clone()) {
+            Ok(p) => p,
+            Err(e) => {
+                return Response::error(
+                    id,
+                    crate::protocol::error_codes::INVALID_PARAMS,
+                    format!("Invalid getWorkspaceDeadCode params: {}", e),
+                );
             }
+        }
+    };
+
+    let mut symbols_by_uri = HashMap::new();
+    let mut refs_by_uri = HashMap::new();
+    let mut options = logos_semantic::WorkspaceOptions {
+        include_exported: params.include_exported,
+        cancellation: state.cancellation().clone(),
+        ..Default::default()
+    };
+    for uri in state.get_open_documents() {
+        if state.is_excluded(&uri) {
+            continue;
+        }
+        let doc = match state.get_document(&uri) {
+            Some(doc) => doc,
+            None => continue,
+        };
+        let symbols = state.unused_candidates(&uri, &mut logos_semantic::UnusedDetector::new());
+        if let Some(analysis) = state.analyses.get(&uri) {
+            let exported = analysis.symbols.iter().filter(|s| s.exported).map(|s| s.name.clone()).collect();
+            options.exported.insert(uri.clone(), exported);
+        }
+        symbols_by_uri.insert(uri.clone(), symbols);
+        refs_by_uri.insert(uri, doc.content().to_string());
+    }
+
+    let dead = logos_semantic::analyze_workspace(&symbols_by_uri, &refs_by_uri, &options);
+    if options.cancellation.is_cancelled() {
+        return cancelled(id);
+    }
+    let items: Vec<_> = dead.iter().flat_map(|(uri, items)| {
+        items.iter().map(move |item| {
+            let mut value = unused_item_json(item);
+            value["uri"] = json!(uri);
+            value
         })
     }).collect();
 
     Response::success(id, json!(items))
 }
 
-fn todo_kind_to_string(kind: TodoKind) -> &'static str {
-    match kind {
-        TodoKind::Todo => "todo",
-        TodoKind::Fixme => "fixme",
-        TodoKind::Hack => "hack",
-        TodoKind::Xxx => "xxx",
-        TodoKind::Note => "note",
-        TodoKind::Bug => "bug",
-        TodoKind::Optimize => "optimize",
-        TodoKind::Custom => "custom",
+fn unused_item_json(item: &logos_semantic::UnusedItem) -> Value {
+    json!({
+        "kind": format!("{:?}", item.kind).to_lowercase(),
+        "name": item.name,
+        "canRemove": item.can_remove,
+        "fixAction": item.fix_action,
+        "fix": item.fix.as_ref().map(|fix| json!({
+            "range": {
+                "start": {"line": fix.range.start.line, "character": fix.range.start.column},
+                "end": {"line": fix.range.end.line, "character": fix.range.end.column}
+            },
+            "newText": fix.new_text
+        })),
+        "range": {
+            "start": {
+                "line": item.range.start.line,
+                "character": item.range.start.column
+            },
+            "end": {
+                "line": item.range.end.line,
+                "character": item.range.end.column
+            }
+        }
+    })
+}
+
+/// Handle logos/getDuplicateBlocks
+pub fn get_duplicate_blocks(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: DuplicateBlocksParams = if params.is_null() {
+        DuplicateBlocksParams::default()
+    } else {
+        match serde_json::from_value(params.clone()) {
+            Ok(p) => p,
+            Err(e) => {
+                return Response::error(
+                    id,
+                    crate::protocol::error_codes::INVALID_PARAMS,
+                    format!("Invalid getDuplicateBlocks params: {}", e),
+                );
+            }
+        }
+    };
+
+    let mut detector = logos_semantic::DuplicateDetector::new(params.min_tokens)
+        .with_cancellation(state.cancellation().clone());
+    for uri in state.get_open_documents() {
+        if state.cancellation().is_cancelled() {
+            return cancelled(id);
+        }
+        if state.is_excluded(&uri) {
+            continue;
+        }
+        if let Some(doc) = state.get_document(&uri) {
+            if let Some(language) = logos_parser::LanguageId::from_str(&doc.language_id) {
+                detector.add_file(&uri, doc.content(), language);
+            }
+        }
+    }
+
+    let groups = detector.find();
+    if state.cancellation().is_cancelled() {
+        return cancelled(id);
     }
+    let groups: Vec<_> = groups.iter().map(|group| {
+        let locations: Vec<_> = group.locations.iter().map(|location| {
+            json!({
+                "uri": location.uri,
+                "range": {
+                    "start": {
+                        "line": location.range.start.line,
+                        "character": location.range.start.column
+                    },
+                    "end": {
+                        "line": location.range.end.line,
+                        "character": location.range.end.column
+                    }
+                }
+            })
+        }).collect();
+        json!({
+            "locations": locations,
+            "tokenCount": group.token_count,
+            "similarity": group.similarity
+        })
+    }).collect();
+
+    Response::success(id, json!(groups))
+}
+
+/// The reply to a request the host cancelled while it ran
+fn cancelled(id: Option<RequestId>) -> Response {
+    Response::error(id, crate::protocol::error_codes::REQUEST_CANCELLED, "Request cancelled".to_string())
 }
@@ -1,6 +1,7 @@
 //! Call Hierarchy handler (LSP 3.16+)
 //!
-//! Provides incoming/outgoing call hierarchy for Smart mode.
+//! Provides incoming/outgoing call hierarchy for Smart mode, and
+//! position-based incoming/outgoing calls over the open documents.
 
 use serde_json::{json, Value};
 use logos_core::Position;
@@ -8,7 +9,7 @@ use logos_core::Position;
 use crate::protocol::{
     CallHierarchyItem, CallHierarchyIncomingCall, CallHierarchyIncomingCallsParams,
     CallHierarchyOutgoingCall, CallHierarchyOutgoingCallsParams, CallHierarchyPrepareParams,
-    RequestId, Response, SerializablePosition, SerializableRange,
+    RequestId, Response, SerializablePosition, SerializableRange, TextDocumentPositionParams,
 };
 use crate::state::State;
 
@@ -192,6 +193,99 @@ pub fn handle_outgoing_calls(state: &State, params: &Value, id: Option<RequestId
     Response::success(id, json!(outgoing_calls))
 }
 
+/// Handle logos/getIncomingCalls
+pub fn get_incoming_calls(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: TextDocumentPositionParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid getIncomingCalls params: {}", e),
+            );
+        }
+    };
+
+    let uri = &params.text_document.uri;
+    let position = Position::new(params.position.line, params.position.character);
+
+    let target = match logos_index::call_hierarchy::callable_at(&state.analyses, uri, position) {
+        Some(s) => s,
+        None => return Response::success(id, json!([])),
+    };
+
+    let incoming_calls: Vec<CallHierarchyIncomingCall> =
+        logos_index::call_hierarchy::incoming_calls(&state.analyses, target)
+            .iter()
+            .map(|call| CallHierarchyIncomingCall {
+                from: symbol_to_item(call.from),
+                from_ranges: call.from_ranges.iter().map(range_to_serializable).collect(),
+            })
+            .collect();
+
+    Response::success(id, json!(incoming_calls))
+}
+
+/// Handle logos/getOutgoingCalls
+pub fn get_outgoing_calls(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: TextDocumentPositionParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid getOutgoingCalls params: {}", e),
+            );
+        }
+    };
+
+    let uri = &params.text_document.uri;
+    let position = Position::new(params.position.line, params.position.character);
+
+    let source = match logos_index::call_hierarchy::callable_at(&state.analyses, uri, position) {
+        Some(s) => s,
+        None => return Response::success(id, json!([])),
+    };
+
+    let outgoing_calls: Vec<CallHierarchyOutgoingCall> =
+        logos_index::call_hierarchy::outgoing_calls(&state.analyses, source)
+            .iter()
+            .map(|call| {
+                let to = match call.to {
+                    Some(symbol) => symbol_to_item(symbol),
+                    // Callee outside the open documents: point at the first call site
+                    None => CallHierarchyItem {
+                        name: call.callee_name.clone(),
+                        kind: symbol_kind_to_lsp(logos_core::SymbolKind::Function),
+                        detail: None,
+                        uri: uri.clone(),
+                        range: range_to_serializable(&call.from_ranges[0]),
+                        selection_range: range_to_serializable(&call.from_ranges[0]),
+                        data: None,
+                    },
+                };
+                CallHierarchyOutgoingCall {
+                    to,
+                    from_ranges: call.from_ranges.iter().map(range_to_serializable).collect(),
+                }
+            })
+            .collect();
+
+    Response::success(id, json!(outgoing_calls))
+}
+
+fn symbol_to_item(symbol: &logos_index::SmartSymbol) -> CallHierarchyItem {
+    CallHierarchyItem {
+        name: symbol.name.clone(),
+        kind: symbol_kind_to_lsp(symbol.kind),
+        detail: Some(symbol.qualified_name.clone()),
+        uri: symbol.location.uri.clone(),
+        range: range_to_serializable(&symbol.location.range),
+        selection_range: range_to_serializable(&symbol.location.selection_range),
+        data: Some(json!({ "symbolId": symbol.id.0 })),
+    }
+}
+
 fn range_to_serializable(range: &logos_core::Range) -> SerializableRange {
     SerializableRange {
         start: SerializablePosition {
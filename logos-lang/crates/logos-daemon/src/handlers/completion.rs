@@ -1,10 +1,17 @@
 //! Completion handler
 
-use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::path::Path;
+
+use serde_json::Value;
 use logos_core::SymbolKind;
+use logos_index::member_completion::{member_access, members_of, receiver_type};
+use logos_index::IndexedSymbol;
+use logos_parser::LanguageId;
 
 use crate::protocol::{TextDocumentPositionParams, RequestId, Response};
 use crate::state::State;
+use crate::types::{to_value, CompletionItem, CompletionList};
 
 /// Handle textDocument/completion
 pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response {
@@ -24,46 +31,190 @@ pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response
     let doc = match state.get_document(uri) {
         Some(d) => d,
         None => {
-            return Response::success(id, json!({ "isIncomplete": false, "items": [] }));
+            return Response::success(id, to_value(&CompletionList { is_incomplete: false, items: Vec::new() }));
         }
     };
 
-    let mut completions = Vec::new();
+    // After `.`, `->` or `::`, offer the members of the receiver's type
+    let position = logos_core::Position::new(params.position.line, params.position.character);
+    let offset = doc.offset_at(position);
+    let prefix = offset.map_or("", |offset| typed_prefix(doc.content(), offset));
+    let members = offset.map(|offset| member_completions(state, doc.content(), offset));
+    if let Some(items) = members.filter(|items| !items.is_empty()) {
+        return Response::success(id, to_value(&filter_by_prefix(items, prefix)));
+    }
 
-    // Add keyword completions based on language
-    let keywords = match doc.language_id.as_str() {
-        "python" => logos_parser::python::get_keywords(),
-        "go" => logos_parser::go::get_keywords(),
-        "rust" => logos_parser::rust_lang::get_keywords(),
-        "c" => logos_parser::c::get_keywords(),
-        "cpp" => logos_parser::cpp::get_keywords(),
-        "java" => logos_parser::java::get_keywords(),
-        "javascript" => logos_parser::javascript::get_keywords(),
-        "typescript" => logos_parser::typescript::get_keywords(),
-        _ => &[],
-    };
+    // Keywords and snippets, the symbols of the document, then the top-level
+    // symbols of the other documents in the same language
+    let mut completions = Vec::new();
 
+    let keywords = state.adapters.keywords(&doc.language_id);
     for kw in keywords {
-        completions.push(json!({
-            "label": kw,
-            "kind": 14, // Keyword
-            "detail": "keyword"
-        }));
+        completions.push(CompletionItem {
+            label: kw.to_string(),
+            kind: 14, // Keyword
+            detail: Some("keyword".to_string()),
+            insert_text: None,
+            insert_text_format: None,
+            sort_text: Some(sort_text(KEYWORD, kw)),
+        });
+    }
+
+    // Snippets for the language's control structures and declarations
+    for snippet in logos_index::snippets::snippets(&doc.language_id) {
+        completions.push(CompletionItem {
+            label: snippet.label.to_string(),
+            kind: 15, // Snippet
+            detail: Some(snippet.detail.to_string()),
+            insert_text: Some(snippet.body.to_string()),
+            insert_text_format: Some(2), // Snippet
+            sort_text: Some(sort_text(KEYWORD, snippet.label)),
+        });
     }
 
-    // Add symbols from index
     for symbol in state.symbol_index.get_document_symbols(uri) {
-        completions.push(json!({
-            "label": symbol.name,
-            "kind": symbol_kind_to_completion_kind(symbol.kind),
-            "detail": format!("{:?}", symbol.kind)
-        }));
+        completions.push(symbol_item(symbol, LOCAL));
     }
 
-    Response::success(id, json!({
-        "isIncomplete": false,
-        "items": completions
-    }))
+    let language = LanguageId::from_str(&doc.language_id);
+    let mut others: Vec<&str> = state
+        .symbol_index
+        .documents()
+        .filter(|other| *other != uri && !state.is_excluded(other))
+        .filter(|other| language.is_some() && language_of(state, other) == language)
+        .collect();
+    others.sort();
+    for other in others {
+        for symbol in state.symbol_index.get_document_symbols(other) {
+            if symbol.container.is_none() {
+                completions.push(symbol_item(symbol, WORKSPACE));
+            }
+        }
+    }
+
+    // The first item of each label and kind wins, so locals shadow workspace
+    // symbols; `sortText` puts them before the keywords
+    let mut seen = HashSet::new();
+    completions.retain(|item| seen.insert((item.label.clone(), item.kind)));
+
+    Response::success(id, to_value(&filter_by_prefix(completions, prefix)))
+}
+
+/// Most items returned for a non-empty prefix; the list is then marked
+/// incomplete, so the editor asks again as the prefix grows
+const MAX_FILTERED_ITEMS: usize = 100;
+
+/// The part of the identifier before `offset` typed so far
+fn typed_prefix(source: &str, offset: usize) -> &str {
+    let before = source.get(..offset).unwrap_or("");
+    &before[before.trim_end_matches(|c: char| c.is_alphanumeric() || c == '_').len()..]
+}
+
+/// The items whose label matches `prefix`, prefix matches before fuzzy ones;
+/// all of them for an empty prefix
+fn filter_by_prefix(items: Vec<CompletionItem>, prefix: &str) -> CompletionList {
+    if prefix.is_empty() {
+        return CompletionList { is_incomplete: false, items };
+    }
+    let mut matched: Vec<(bool, CompletionItem)> = items
+        .into_iter()
+        .filter_map(|item| Some((fuzzy_match(&item.label, prefix)?, item)))
+        .collect();
+    matched.sort_by_key(|(fuzzy, _)| *fuzzy);
+
+    let is_incomplete = matched.len() > MAX_FILTERED_ITEMS;
+    matched.truncate(MAX_FILTERED_ITEMS);
+    CompletionList {
+        is_incomplete,
+        items: matched.into_iter().map(|(_, item)| item).collect(),
+    }
+}
+
+/// Whether `label` matches `prefix`, ignoring case: `Some(false)` when it
+/// starts with it, `Some(true)` when the prefix runs along the starts of its
+/// words (`gU` in `getUser`, `pa` in `parse_args`), `None` otherwise
+fn fuzzy_match(label: &str, prefix: &str) -> Option<bool> {
+    if label.to_lowercase().starts_with(&prefix.to_lowercase()) {
+        return Some(false);
+    }
+
+    let label: Vec<char> = label.chars().collect();
+    let word_start = |i: usize| {
+        i == 0 || (label[i].is_uppercase() && !label[i - 1].is_uppercase()) || (label[i - 1] == '_' && label[i] != '_')
+    };
+    let mut at = 0;
+    for (n, wanted) in prefix.chars().enumerate() {
+        let same = |c: char| c.to_lowercase().eq(wanted.to_lowercase());
+        // The next character of the current word, or the start of a later one
+        at = if n > 0 && at < label.len() && same(label[at]) {
+            at
+        } else {
+            (at..label.len()).find(|&i| word_start(i) && same(label[i]))?
+        } + 1;
+    }
+    Some(true)
+}
+
+/// `sortText` groups, in the order the editor lists them
+const LOCAL: u8 = 0;
+const KEYWORD: u8 = 1;
+const WORKSPACE: u8 = 2;
+
+fn sort_text(group: u8, label: &str) -> String {
+    format!("{}_{}", group, label)
+}
+
+fn symbol_item(symbol: &IndexedSymbol, group: u8) -> CompletionItem {
+    CompletionItem {
+        label: symbol.name.clone(),
+        kind: symbol_kind_to_completion_kind(symbol.kind),
+        detail: Some(format!("{:?}", symbol.kind)),
+        insert_text: None,
+        insert_text_format: None,
+        sort_text: Some(sort_text(group, &symbol.name)),
+    }
+}
+
+/// The language of an indexed document: its language id when it is open,
+/// otherwise its extension
+fn language_of(state: &State, uri: &str) -> Option<LanguageId> {
+    match state.get_document(uri) {
+        Some(doc) => LanguageId::from_str(&doc.language_id),
+        None => Path::new(uri).extension().and_then(|ext| LanguageId::from_extension(&ext.to_string_lossy())),
+    }
+}
+
+/// Members of the type of the receiver before `offset`, from the analyses
+/// of the open documents
+fn member_completions(state: &State, source: &str, offset: usize) -> Vec<CompletionItem> {
+    let access = match member_access(source, offset) {
+        Some(access) => access,
+        None => return Vec::new(),
+    };
+    let ty = match receiver_type(source, &access, offset) {
+        Some(ty) => ty,
+        None => return Vec::new(),
+    };
+
+    let mut uris: Vec<&String> = state.analyses.keys().collect();
+    uris.sort();
+    let mut items: Vec<CompletionItem> = Vec::new();
+    for uri in uris {
+        for member in members_of(&state.analyses[uri], &ty) {
+            if items.iter().any(|item| item.label == member.name) {
+                continue;
+            }
+            items.push(CompletionItem {
+                label: member.name.clone(),
+                kind: symbol_kind_to_completion_kind(member.kind),
+                detail: Some(format!("{:?}", member.kind)),
+                insert_text: None,
+                insert_text_format: None,
+                sort_text: None,
+            });
+        }
+    }
+    items
 }
 
 fn symbol_kind_to_completion_kind(kind: SymbolKind) -> u32 {
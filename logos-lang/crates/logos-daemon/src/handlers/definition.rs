@@ -1,10 +1,11 @@
 //! Definition handler
 
-use serde_json::{json, Value};
+use serde_json::Value;
 use logos_core::Position;
 
 use crate::protocol::{TextDocumentPositionParams, RequestId, Response};
 use crate::state::State;
+use crate::types::{to_value, DefinitionResult};
 
 /// Handle textDocument/definition
 pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response {
@@ -23,18 +24,9 @@ pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response
     let position = Position::new(params.position.line, params.position.character);
 
     if let Some(symbol) = state.symbol_index.find_at_position(uri, position) {
-        let definition = json!({
-            "uri": symbol.uri,
-            "range": {
-                "start": {
-                    "line": symbol.range.start.line,
-                    "character": symbol.range.start.column
-                },
-                "end": {
-                    "line": symbol.range.end.line,
-                    "character": symbol.range.end.column
-                }
-            }
+        let definition = to_value(&DefinitionResult {
+            uri: symbol.uri.clone(),
+            range: symbol.range.into(),
         });
         return Response::success(id, definition);
     }
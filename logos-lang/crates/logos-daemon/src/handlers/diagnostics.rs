@@ -1,9 +1,11 @@
 //! Diagnostics handler
 
-use serde_json::{json, Value};
+use logos_core::Diagnostic;
+use serde_json::{json, Map, Value};
 
-use crate::protocol::{DocumentSymbolParams, RequestId, Response};
+use crate::protocol::{DocumentSymbolParams, RequestId, Response, WorkspaceDiagnosticsParams};
 use crate::state::State;
+use crate::types::{to_value, DiagnosticMarker};
 
 /// Handle textDocument/diagnostic
 pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response {
@@ -18,12 +20,58 @@ pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response
         }
     };
 
-    let _uri = &params.text_document.uri;
+    let items: Vec<Value> = state
+        .document_diagnostics(&params.text_document.uri)
+        .iter()
+        .map(diagnostic_to_json)
+        .collect();
 
-    // For now, return empty diagnostics
-    // Future: integrate with parser errors and semantic analysis
     Response::success(id, json!({
         "kind": "full",
-        "items": []
+        "items": items
     }))
 }
+
+/// Handle logos/getWorkspaceDiagnostics: the diagnostics of every open,
+/// non-excluded document, keyed by URI
+pub fn get_workspace_diagnostics(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: WorkspaceDiagnosticsParams = if params.is_null() {
+        WorkspaceDiagnosticsParams::default()
+    } else {
+        match serde_json::from_value(params.clone()) {
+            Ok(p) => p,
+            Err(e) => {
+                return Response::error(
+                    id,
+                    crate::protocol::error_codes::INVALID_PARAMS,
+                    format!("Invalid getWorkspaceDiagnostics params: {}", e),
+                );
+            }
+        }
+    };
+
+    let mut result = Map::new();
+    for uri in state.get_open_documents() {
+        if state.is_excluded(&uri) {
+            continue;
+        }
+
+        let mut diagnostics = state.document_diagnostics(&uri);
+        if params.include_todos {
+            diagnostics.extend(state.todo_index.get_document_todos(&uri).iter().map(|todo| {
+                Diagnostic::info(todo.range, format!("{}: {}", todo.kind.as_str(), todo.text))
+                    .with_code(todo.kind.as_str().to_lowercase())
+                    .with_source("logos-index".to_string())
+            }));
+        }
+
+        let markers: Vec<Value> = diagnostics.iter().map(diagnostic_to_json).collect();
+        result.insert(uri, Value::Array(markers));
+    }
+
+    Response::success(id, Value::Object(result))
+}
+
+fn diagnostic_to_json(diagnostic: &Diagnostic) -> Value {
+    to_value(&DiagnosticMarker::from(diagnostic))
+}
@@ -1,9 +1,12 @@
 //! Document synchronization handlers
 
 use log::debug;
-use serde_json::Value;
+use serde_json::{json, Value};
 
-use crate::protocol::{DidOpenTextDocumentParams, DidChangeTextDocumentParams, DidCloseTextDocumentParams};
+use crate::protocol::{
+    BatchDocument, DidOpenTextDocumentParams, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DocumentSymbolParams, OpenDocumentsParams, RequestId, Response,
+};
 use crate::state::State;
 
 /// Handle textDocument/didOpen
@@ -22,6 +25,40 @@ pub fn did_open(state: &mut State, params: &Value) {
     state.open_document(doc.uri, doc.language_id, doc.text);
 }
 
+/// Handle logos/openDocuments: open and index many documents in one request
+pub fn open_documents(state: &mut State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: OpenDocumentsParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid openDocuments params: {}", e),
+            );
+        }
+    };
+
+    let mut documents = Vec::new();
+    let mut errors = Vec::new();
+    for (index, entry) in params.documents.into_iter().enumerate() {
+        match serde_json::from_value::<BatchDocument>(entry) {
+            Ok(doc) => documents.push((doc.uri, doc.language_id, doc.content)),
+            Err(e) => errors.push(json!({"index": index, "error": e.to_string()})),
+        }
+    }
+
+    debug!("Opening {} documents", documents.len());
+    let requested = documents.len();
+    let failed = state.open_documents(documents);
+    errors.extend(failed.iter().map(|(uri, error)| json!({"uri": uri, "error": error})));
+    let failed_uris: std::collections::HashSet<&str> = failed.iter().map(|(uri, _)| uri.as_str()).collect();
+
+    Response::success(id, json!({
+        "indexed": requested - failed_uris.len(),
+        "errors": errors
+    }))
+}
+
 /// Handle textDocument/didChange
 pub fn did_change(state: &mut State, params: &Value) {
     let params: DidChangeTextDocumentParams = match serde_json::from_value(params.clone()) {
@@ -54,3 +91,47 @@ pub fn did_close(state: &mut State, params: &Value) {
 
     state.close_document(&params.text_document.uri);
 }
+
+/// Handle logos/getDocumentLanguage: the language an open document is
+/// treated as, after falling back to its extension
+pub fn get_document_language(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: DocumentSymbolParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid getDocumentLanguage params: {}", e),
+            );
+        }
+    };
+
+    match state.get_document(&params.text_document.uri) {
+        Some(doc) => Response::success(id, json!({
+            "languageId": doc.language_id,
+            "supported": logos_parser::LanguageId::from_str(&doc.language_id).is_some()
+        })),
+        None => Response::success(id, Value::Null),
+    }
+}
+
+/// Handle logos/forceIndex: parse, index and scan an open document even if
+/// it is over the size limit
+pub fn force_index(state: &mut State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: DocumentSymbolParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid forceIndex params: {}", e),
+            );
+        }
+    };
+
+    if state.force_index(&params.text_document.uri) {
+        Response::success(id, json!({"success": true}))
+    } else {
+        Response::success(id, json!({"success": false, "error": "Document not found"}))
+    }
+}
@@ -1,10 +1,11 @@
 //! Hover handler
 
-use serde_json::{json, Value};
+use serde_json::Value;
 use logos_core::Position;
 
 use crate::protocol::{TextDocumentPositionParams, RequestId, Response};
 use crate::state::State;
+use crate::types::{to_value, HoverResult, MarkupContent};
 
 /// Handle textDocument/hover
 pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response {
@@ -22,25 +23,83 @@ pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response
     let uri = &params.text_document.uri;
     let position = Position::new(params.position.line, params.position.character);
 
+    if let Some(hover) = todo_hover(state, uri, position) {
+        return Response::success(id, hover);
+    }
+
+    if let Some(hover) = adapter_hover(state, uri, position) {
+        return Response::success(id, hover);
+    }
+
     if let Some(symbol) = state.symbol_index.find_at_position(uri, position) {
-        let hover = json!({
-            "contents": {
-                "kind": "markdown",
-                "value": format!("**{}** ({})", symbol.name, format!("{:?}", symbol.kind))
-            },
-            "range": {
-                "start": {
-                    "line": symbol.selection_range.start.line,
-                    "character": symbol.selection_range.start.column
-                },
-                "end": {
-                    "line": symbol.selection_range.end.line,
-                    "character": symbol.selection_range.end.column
-                }
-            }
+        let hover = to_value(&HoverResult {
+            contents: MarkupContent::markdown(format!("**{}** ({:?})", symbol.name, symbol.kind)),
+            range: symbol.selection_range.into(),
         });
         return Response::success(id, hover);
     }
 
     Response::null_result(id)
 }
+
+/// Hover for a TODO marker on the line under the cursor: its kind, author,
+/// priority and issue references
+fn todo_hover(state: &State, uri: &str, position: Position) -> Option<Value> {
+    let todo = state
+        .todo_index
+        .get_document_todos(uri)
+        .iter()
+        .find(|todo| todo.range.start.line == position.line)?;
+
+    let mut value = format!("**{}**", todo.kind.as_str());
+    if let Some(author) = &todo.author {
+        value.push_str(&format!(" assigned to `{}`", author));
+    }
+    let urgent = if todo.priority > todo.kind.priority() { " (urgent)" } else { "" };
+    value.push_str(&format!("\n\nPriority: {}/5{}", todo.priority, urgent));
+    if !todo.issue_refs.is_empty() {
+        let ids: Vec<&str> = todo.issue_refs.iter().map(|issue| issue.id.as_str()).collect();
+        value.push_str(&format!("\n\nIssues: {}", ids.join(", ")));
+    }
+    if !todo.text.is_empty() {
+        value.push_str(&format!("\n\n{}", todo.text));
+    }
+
+    Some(to_value(&HoverResult {
+        contents: MarkupContent::markdown(value),
+        range: todo.range.into(),
+    }))
+}
+
+/// Hover from the adapter analyses: the symbol declared at the position or,
+/// for a usage, the declaration of the name under the cursor
+fn adapter_hover(state: &State, uri: &str, position: Position) -> Option<Value> {
+    let doc = state.get_document(uri)?;
+    let (symbol, range) = match state
+        .analyses
+        .get(uri)
+        .and_then(|result| logos_index::hover::symbol_at(result, position))
+    {
+        Some(symbol) => (symbol, symbol.location.selection_range),
+        None => {
+            let (name, range) = logos_refactor::rename::identifier_at(doc.content(), position)?;
+            // Prefer a declaration in the same document
+            let symbol = state
+                .analyses
+                .iter()
+                .flat_map(|(result_uri, result)| result.symbols.iter().map(move |s| (result_uri, s)))
+                .filter(|(_, s)| s.name == name)
+                .min_by_key(|(result_uri, _)| result_uri.as_str() != uri)?
+                .1;
+            (symbol, range)
+        }
+    };
+
+    let declaring = state.get_document(&symbol.location.uri)?;
+    let value = logos_index::hover::hover_markdown(symbol, declaring.content(), &declaring.language_id);
+
+    Some(to_value(&HoverResult {
+        contents: MarkupContent::markdown(value),
+        range: range.into(),
+    }))
+}
@@ -0,0 +1,55 @@
+//! Implementation handler
+
+use serde_json::{json, Value};
+use logos_core::Position;
+
+use crate::protocol::{TextDocumentPositionParams, RequestId, Response};
+use crate::state::State;
+
+/// Handle textDocument/implementation
+pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: TextDocumentPositionParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid implementation params: {}", e),
+            );
+        }
+    };
+
+    let uri = &params.text_document.uri;
+    let position = Position::new(params.position.line, params.position.character);
+
+    let doc = match state.get_document(uri) {
+        Some(d) => d,
+        None => return Response::success(id, json!([])),
+    };
+
+    let name = match logos_index::navigation::identifier_at(doc.content(), position) {
+        Some(name) => name,
+        None => return Response::success(id, json!([])),
+    };
+
+    let locations: Vec<_> = logos_index::navigation::find_implementations(&state.analyses, &name)
+        .iter()
+        .map(|loc| {
+            json!({
+                "uri": loc.uri,
+                "range": {
+                    "start": {
+                        "line": loc.range.start.line,
+                        "character": loc.range.start.column
+                    },
+                    "end": {
+                        "line": loc.range.end.line,
+                        "character": loc.range.end.column
+                    }
+                }
+            })
+        })
+        .collect();
+
+    Response::success(id, json!(locations))
+}
@@ -0,0 +1,69 @@
+//! Inlay hint handler
+
+use serde_json::Value;
+use logos_core::{Position, Range, SymbolKind};
+use logos_index::SmartSymbol;
+
+use crate::protocol::{InlayHintParams, RequestId, Response};
+use crate::state::State;
+use crate::types::{to_value, InlayHint};
+
+/// Handle textDocument/inlayHint
+pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: InlayHintParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid inlay hint params: {}", e),
+            );
+        }
+    };
+
+    let uri = &params.text_document.uri;
+    let (doc, result) = match (state.get_document(uri), state.analyses.get(uri)) {
+        (Some(doc), Some(result)) => (doc, result),
+        _ => return Response::success(id, to_value(&Vec::<InlayHint>::new())),
+    };
+    let range = Range::new(
+        Position::new(params.range.start.line, params.range.start.character),
+        Position::new(params.range.end.line, params.range.end.character),
+    );
+
+    // The type of each variable whose declaration doesn't spell it out, such
+    // as one typed by a Python `# type:` comment
+    let mut hints: Vec<InlayHint> = result
+        .symbols
+        .iter()
+        .filter(|symbol| matches!(symbol.kind, SymbolKind::Variable | SymbolKind::Constant))
+        .filter(|symbol| range.contains(symbol.location.selection_range.end))
+        .filter_map(|symbol| {
+            let ty = &symbol.type_info.as_ref()?.type_expr;
+            if type_is_written(doc.content(), symbol, ty) {
+                return None;
+            }
+            Some(InlayHint {
+                position: symbol.location.selection_range.end.into(),
+                label: format!(": {}", ty),
+                kind: 1, // Type
+            })
+        })
+        .collect();
+    hints.sort_by_key(|hint| (hint.position.line, hint.position.character));
+
+    Response::success(id, to_value(&hints))
+}
+
+/// Whether `ty` is written next to the symbol's name: `x: int`, `var x int`
+/// or `int x`
+fn type_is_written(source: &str, symbol: &SmartSymbol, ty: &str) -> bool {
+    let name = symbol.location.selection_range;
+    let Some(line) = source.lines().nth(name.start.line as usize) else {
+        return false;
+    };
+    let before = line.get(..name.start.column as usize).unwrap_or("");
+    let after = if name.end.line == name.start.line { line.get(name.end.column as usize..).unwrap_or("") } else { "" };
+    let after = after.trim_start();
+    after.strip_prefix(':').unwrap_or(after).trim_start().starts_with(ty) || before.trim_end().ends_with(ty)
+}
@@ -27,6 +27,42 @@ pub fn initialize(state: &mut State, params: &Value, id: Option<RequestId>) -> R
     state.root_path = params.root_path.or(params.root_uri);
     state.initialized = true;
 
+    // The best-effort brace-language adapter can be turned off by the client
+    if params.initialization_options["fallbackAdapter"] == json!(false) {
+        info!("  Fallback adapter disabled");
+        state.adapters.set_fallback(None);
+    }
+
+    // Documents over this many bytes are only stored; `null` lifts the limit
+    match &params.initialization_options["maxFileBytes"] {
+        Value::Null if params.initialization_options.get("maxFileBytes").is_some() => {
+            info!("  File size limit disabled");
+            state.set_max_file_bytes(None);
+        }
+        Value::Number(max) if max.as_u64().is_some() => {
+            info!("  File size limit: {} bytes", max);
+            state.set_max_file_bytes(max.as_u64().map(|max| max as usize));
+        }
+        _ => {}
+    }
+
+    // Includes are only checked once a grouping is configured: `true` for
+    // the default, or a list such as `["own", "system", "thirdParty", "local"]`
+    match &params.initialization_options["includeOrder"] {
+        Value::Bool(true) => {
+            info!("  Include order: default");
+            state.set_include_order(Some(logos_semantic::IncludeOrder::default()));
+        }
+        order @ Value::Array(_) => match serde_json::from_value::<logos_semantic::IncludeOrder>(order.clone()) {
+            Ok(order) => {
+                info!("  Include order: {:?}", order.0);
+                state.set_include_order(Some(order));
+            }
+            Err(e) => info!("  Ignoring includeOrder: {}", e),
+        },
+        _ => {}
+    }
+
     // Return server capabilities
     let capabilities = json!({
         "capabilities": {
@@ -42,10 +78,20 @@ pub fn initialize(state: &mut State, params: &Value, id: Option<RequestId>) -> R
                 "resolveProvider": false
             },
             "hoverProvider": true,
+            "inlayHintProvider": true,
             "definitionProvider": true,
+            "typeDefinitionProvider": true,
+            "implementationProvider": true,
             "referencesProvider": true,
             "documentSymbolProvider": true,
             "workspaceSymbolProvider": true,
+            "semanticTokensProvider": {
+                "legend": {
+                    "tokenTypes": logos_index::SemanticTokenType::LEGEND,
+                    "tokenModifiers": []
+                },
+                "full": true
+            },
             "renameProvider": {
                 "prepareProvider": true
             },
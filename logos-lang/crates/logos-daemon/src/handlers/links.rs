@@ -0,0 +1,41 @@
+//! Document links handler
+//!
+//! Include/import paths that resolve to a file, and issue references in
+//! TODO comments.
+
+use serde_json::{json, Value};
+
+use crate::protocol::{DocumentLinksParams, RequestId, Response};
+use crate::state::State;
+use crate::types::{to_value, DocumentLinkResult};
+
+/// Handle logos/getDocumentLinks
+pub fn get_document_links(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: DocumentLinksParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid getDocumentLinks params: {}", e),
+            );
+        }
+    };
+
+    let uri = &params.text_document.uri;
+    let doc = match state.get_document(uri) {
+        Some(d) => d,
+        None => return Response::success(id, json!([])),
+    };
+
+    let mut links = Vec::new();
+    if let (Some(adapter), Some(result)) = (state.adapter_for(uri), state.analyses.get(uri)) {
+        links.extend(logos_index::links::import_links(adapter, uri, doc.content(), &result.imports));
+    }
+    let todos = state.todo_index.get_document_todos(uri);
+    links.extend(logos_index::links::issue_links(todos, &params.issue_link_templates));
+    links.sort_by_key(|link| (link.range.start.line, link.range.start.column));
+
+    let links: Vec<DocumentLinkResult> = links.iter().map(DocumentLinkResult::from).collect();
+    Response::success(id, to_value(&links))
+}
@@ -4,9 +4,14 @@ pub mod lifecycle;
 pub mod document;
 pub mod completion;
 pub mod definition;
+pub mod type_definition;
+pub mod implementation;
 pub mod references;
 pub mod hover;
+pub mod inlay_hints;
+pub mod links;
 pub mod symbols;
+pub mod semantic_tokens;
 pub mod rename;
 pub mod diagnostics;
 pub mod refactor;
@@ -6,6 +6,7 @@ use serde_json::{json, Value};
 
 use crate::protocol::{RequestId, Response, SetModeParams};
 use crate::state::State;
+use crate::types::to_value;
 
 /// Handle logos/setMode
 pub fn handle_set_mode(state: &mut State, params: &Value, id: Option<RequestId>) -> Response {
@@ -79,3 +80,8 @@ pub fn handle_get_index_stats(state: &State, _params: &Value, id: Option<Request
         )
     }
 }
+
+/// Handle logos/getServiceStats
+pub fn handle_get_service_stats(state: &State, _params: &Value, id: Option<RequestId>) -> Response {
+    Response::success(id, to_value(&state.stats()))
+}
@@ -2,7 +2,11 @@
 
 use serde_json::{json, Value};
 
-use crate::protocol::{RefactorParams, ExtractVariableParams, ExtractMethodParams, RequestId, Response};
+use logos_refactor::extract_parameter::CallSite;
+use logos_refactor::move_member::ClassParent;
+use logos_refactor::move_symbol::{FileMove, ModuleFile, ModuleImport};
+
+use crate::protocol::{RefactorParams, ConvertFunctionParams, ExtractVariableParams, ExtractVariableMultiParams, ExtractIntoExistingParams, ExtractMethodParams, ExtractParameterParams, MoveSymbolParams, NormalizeStyleParams, OrganizeImportsParams, SurroundWithParams, TextDocumentPositionParams, RequestId, Response};
 use crate::state::State;
 
 /// Handle logos/getRefactorActions
@@ -89,10 +93,22 @@ pub fn extract_variable(state: &State, params: &Value, id: Option<RequestId>) ->
         params.range.end.character,
     );
 
-    let ctx = logos_refactor::RefactorContext::new(doc.content(), uri, selection, language);
+    let ctx = logos_refactor::RefactorContext::new(doc.content(), uri, selection, language).with_naming(params.naming);
 
-    match logos_refactor::extract_variable::extract(&ctx, &params.variable_name) {
-        Ok(result) => {
+    let extracted = match (params.variable_name, params.constant) {
+        (Some(name), false) => {
+            logos_refactor::extract_variable::extract(&ctx, &name, params.force).map(|result| (name, result))
+        }
+        (Some(name), true) => logos_refactor::extract_variable::extract_constant(&ctx, &name).map(|result| (name, result)),
+        (None, false) => {
+            let name = logos_refactor::analysis::suggest_variable_name_with(ctx.selected_text(), language, &ctx.naming);
+            logos_refactor::extract_variable::extract(&ctx, &name, params.force).map(|result| (name, result))
+        }
+        (None, true) => logos_refactor::extract_variable::extract_constant_with_suggestion(&ctx),
+    };
+
+    match extracted {
+        Ok((variable_name, result)) => {
             let edits: Vec<_> = result.edits.iter().map(|edit| {
                 json!({
                     "range": {
@@ -112,14 +128,149 @@ pub fn extract_variable(state: &State, params: &Value, id: Option<RequestId>) ->
             Response::success(id, json!({
                 "success": true,
                 "edits": edits,
+                "workspaceEdit": result.workspace_edit(uri).to_json(),
                 "description": result.description,
+                "variableName": variable_name,
                 "generatedCode": result.generated_code
             }))
         }
         Err(e) => {
             Response::success(id, json!({
                 "success": false,
-                "error": e.to_string()
+                "error": e.to_string(),
+                "ranges": error_ranges(&e)
+            }))
+        }
+    }
+}
+
+/// Handle logos/extractVariableMulti
+pub fn extract_variable_multi(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: ExtractVariableMultiParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid extractVariableMulti params: {}", e),
+            );
+        }
+    };
+
+    let uri = &params.text_document.uri;
+
+    let doc = match state.get_document(uri) {
+        Some(d) => d,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "Document not found"}));
+        }
+    };
+
+    let language = match logos_parser::LanguageId::from_str(&doc.language_id) {
+        Some(l) => l,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "Unsupported language"}));
+        }
+    };
+
+    let ranges: Vec<logos_core::Range> = params
+        .ranges
+        .iter()
+        .map(|range| {
+            logos_core::Range::from_coords(range.start.line, range.start.character, range.end.line, range.end.character)
+        })
+        .collect();
+    let first = ranges.first().copied().unwrap_or_default();
+    let ctx = logos_refactor::RefactorContext::new(doc.content(), uri, first, language);
+
+    match logos_refactor::extract_variable::extract_at(&ctx, &ranges, &params.variable_name) {
+        Ok(result) => {
+            let edits: Vec<_> = result.edits.iter().map(|edit| {
+                json!({
+                    "range": range_to_json(edit.range),
+                    "newText": edit.new_text
+                })
+            }).collect();
+
+            Response::success(id, json!({
+                "success": true,
+                "edits": edits,
+                "workspaceEdit": result.workspace_edit(uri).to_json(),
+                "description": result.description,
+                "variableName": params.variable_name,
+                "generatedCode": result.generated_code
+            }))
+        }
+        Err(e) => {
+            Response::success(id, json!({
+                "success": false,
+                "error": e.to_string(),
+                "ranges": error_ranges(&e)
+            }))
+        }
+    }
+}
+
+/// Handle logos/extractIntoExisting
+pub fn extract_into_existing(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: ExtractIntoExistingParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid extractIntoExisting params: {}", e),
+            );
+        }
+    };
+
+    let uri = &params.text_document.uri;
+
+    let doc = match state.get_document(uri) {
+        Some(d) => d,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "Document not found"}));
+        }
+    };
+
+    let language = match logos_parser::LanguageId::from_str(&doc.language_id) {
+        Some(l) => l,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "Unsupported language"}));
+        }
+    };
+
+    let selection = logos_core::Range::from_coords(
+        params.range.start.line,
+        params.range.start.character,
+        params.range.end.line,
+        params.range.end.character,
+    );
+    let ctx = logos_refactor::RefactorContext::new(doc.content(), uri, selection, language);
+
+    match logos_refactor::extract_variable::extract_into_existing(&ctx, &params.existing_var_name) {
+        Ok(result) => {
+            let edits: Vec<_> = result.edits.iter().map(|edit| {
+                json!({
+                    "range": range_to_json(edit.range),
+                    "newText": edit.new_text
+                })
+            }).collect();
+
+            Response::success(id, json!({
+                "success": true,
+                "edits": edits,
+                "workspaceEdit": result.workspace_edit(uri).to_json(),
+                "description": result.description,
+                "variableName": params.existing_var_name,
+                "generatedCode": result.generated_code
+            }))
+        }
+        Err(e) => {
+            Response::success(id, json!({
+                "success": false,
+                "error": e.to_string(),
+                "ranges": error_ranges(&e)
             }))
         }
     }
@@ -161,10 +312,15 @@ pub fn extract_method(state: &State, params: &Value, id: Option<RequestId>) -> R
         params.range.end.character,
     );
 
-    let ctx = logos_refactor::RefactorContext::new(doc.content(), uri, selection, language);
+    let ctx = logos_refactor::RefactorContext::new(doc.content(), uri, selection, language).with_naming(params.naming);
 
-    match logos_refactor::extract_method::extract(&ctx, &params.method_name) {
-        Ok(result) => {
+    let extracted = match params.method_name {
+        Some(name) => logos_refactor::extract_method::extract(&ctx, &name).map(|result| (name, result)),
+        None => logos_refactor::extract_method::extract_with_suggestion(&ctx),
+    };
+
+    match extracted {
+        Ok((method_name, result)) => {
             let edits: Vec<_> = result.edits.iter().map(|edit| {
                 json!({
                     "range": {
@@ -184,14 +340,17 @@ pub fn extract_method(state: &State, params: &Value, id: Option<RequestId>) -> R
             Response::success(id, json!({
                 "success": true,
                 "edits": edits,
+                "workspaceEdit": result.workspace_edit(uri).to_json(),
                 "description": result.description,
+                "methodName": method_name,
                 "generatedCode": result.generated_code
             }))
         }
         Err(e) => {
             Response::success(id, json!({
                 "success": false,
-                "error": e.to_string()
+                "error": e.to_string(),
+                "ranges": error_ranges(&e)
             }))
         }
     }
@@ -235,7 +394,12 @@ pub fn can_safe_delete(state: &State, params: &Value, id: Option<RequestId>) ->
 
     let ctx = logos_refactor::RefactorContext::new(doc.content(), uri, selection, language);
 
-    match logos_refactor::safe_delete::analyze(&ctx) {
+    let analysis = match symbol_scope(state, uri, selection) {
+        Some(scope) => logos_refactor::safe_delete::analyze_with_scope(&ctx, &scope),
+        None => logos_refactor::safe_delete::analyze(&ctx),
+    };
+
+    match analysis {
         Ok(analysis) => {
             let usages: Vec<_> = analysis.usages.iter().map(|loc| {
                 json!({
@@ -307,8 +471,22 @@ pub fn safe_delete(state: &State, params: &Value, id: Option<RequestId>) -> Resp
 
     let ctx = logos_refactor::RefactorContext::new(doc.content(), uri, selection, language);
 
-    match logos_refactor::safe_delete::delete(&ctx) {
-        Ok(result) => {
+    // Other open documents of the language may use or import the symbol
+    let others: Vec<(String, &str)> = state
+        .get_open_documents()
+        .into_iter()
+        .filter(|other| other != uri)
+        .filter_map(|other| {
+            let other_doc = state.get_document(&other)?;
+            (other_doc.language_id == doc.language_id).then_some((other, other_doc.content()))
+        })
+        .collect();
+    let others: Vec<ModuleFile> = others.iter().map(|(other, source)| module_file(state, other, source)).collect();
+    let scope = symbol_scope(state, uri, selection);
+
+    match logos_refactor::safe_delete::delete_in_workspace(&ctx, scope.as_ref(), &others) {
+        Ok(deleted) => {
+            let result = deleted.result;
             let edits: Vec<_> = result.edits.iter().map(|edit| {
                 json!({
                     "range": {
@@ -328,6 +506,7 @@ pub fn safe_delete(state: &State, params: &Value, id: Option<RequestId>) -> Resp
             Response::success(id, json!({
                 "success": true,
                 "edits": edits,
+                "workspaceEdit": deleted.edit.to_json(),
                 "description": result.description
             }))
         }
@@ -349,3 +528,908 @@ pub fn safe_delete(state: &State, params: &Value, id: Option<RequestId>) -> Resp
         }
     }
 }
+
+/// Handle logos/safeDeletePreview
+pub fn safe_delete_preview(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: RefactorParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid safeDeletePreview params: {}", e),
+            );
+        }
+    };
+
+    let uri = &params.text_document.uri;
+
+    let doc = match state.get_document(uri) {
+        Some(d) => d,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "Document not found"}));
+        }
+    };
+
+    let language = match logos_parser::LanguageId::from_str(&doc.language_id) {
+        Some(l) => l,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "Unsupported language"}));
+        }
+    };
+
+    let selection = logos_core::Range::from_coords(
+        params.range.start.line,
+        params.range.start.character,
+        params.range.end.line,
+        params.range.end.character,
+    );
+
+    let ctx = logos_refactor::RefactorContext::new(doc.content(), uri, selection, language);
+
+    let preview = match symbol_scope(state, uri, selection) {
+        Some(scope) => logos_refactor::safe_delete::preview_with_scope(&ctx, &scope),
+        None => logos_refactor::safe_delete::preview(&ctx),
+    };
+
+    match preview {
+        Ok(preview) => {
+            let edits: Vec<_> = preview.result.edits.iter().map(|edit| {
+                json!({
+                    "range": {
+                        "start": {
+                            "line": edit.range.start.line,
+                            "character": edit.range.start.column
+                        },
+                        "end": {
+                            "line": edit.range.end.line,
+                            "character": edit.range.end.column
+                        }
+                    },
+                    "newText": edit.new_text
+                })
+            }).collect();
+
+            Response::success(id, json!({
+                "success": true,
+                "edits": edits,
+                "description": preview.result.description,
+                "newText": preview.new_text
+            }))
+        }
+        Err(e) => {
+            let usages: Vec<_> = match &e {
+                logos_refactor::RefactorError::SymbolInUse(usages) => usages.iter().map(|loc| {
+                    json!({
+                        "uri": loc.uri,
+                        "range": {
+                            "start": {
+                                "line": loc.range.start.line,
+                                "character": loc.range.start.column
+                            },
+                            "end": {
+                                "line": loc.range.end.line,
+                                "character": loc.range.end.column
+                            }
+                        }
+                    })
+                }).collect(),
+                _ => Vec::new(),
+            };
+
+            Response::success(id, json!({
+                "success": false,
+                "error": e.to_string(),
+                "usages": usages
+            }))
+        }
+    }
+}
+
+/// Handle logos/moveSymbolToFile
+pub fn move_symbol_to_file(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: MoveSymbolParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid moveSymbolToFile params: {}", e),
+            );
+        }
+    };
+
+    let uri = &params.text_document.uri;
+    let target_uri = &params.target_uri;
+
+    let doc = match state.get_document(uri) {
+        Some(d) => d,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "Document not found"}));
+        }
+    };
+
+    let language = match logos_parser::LanguageId::from_str(&doc.language_id) {
+        Some(l) => l,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "Unsupported language"}));
+        }
+    };
+
+    let target_doc = state.get_document(target_uri);
+    if target_doc.is_some_and(|t| t.language_id != doc.language_id) {
+        return Response::success(id, json!({"success": false, "error": "Target file is in another language"}));
+    }
+
+    let position = logos_core::Position::new(params.position.line, params.position.character);
+    let symbol = state.analyses.get(uri).and_then(|result| {
+        result
+            .symbols
+            .iter()
+            .find(|s| s.parent.is_none() && s.location.range.contains(position))
+    });
+    let symbol = match symbol {
+        Some(s) => s,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "No top-level symbol at position"}));
+        }
+    };
+
+    let from = module_file(state, uri, doc.content());
+    let to = module_file(state, target_uri, target_doc.map(|t| t.content()).unwrap_or(""));
+
+    match logos_refactor::move_symbol::plan(language, &from, &to, &symbol.name, symbol.location.selection_range) {
+        Ok(plan) => Response::success(id, json!({
+            "success": true,
+            "files": [file_move_to_json(&plan.source), file_move_to_json(&plan.target)],
+            "workspaceEdit": plan.workspace_edit().to_json(),
+            "description": format!("Move '{}' to {}", symbol.name, target_uri)
+        })),
+        Err(e) => Response::success(id, json!({
+            "success": false,
+            "error": e.to_string()
+        })),
+    }
+}
+
+/// Handle logos/organizeImports
+pub fn organize_imports(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: OrganizeImportsParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid organizeImports params: {}", e),
+            );
+        }
+    };
+
+    let uri = &params.text_document.uri;
+
+    let doc = match state.get_document(uri) {
+        Some(d) => d,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "Document not found"}));
+        }
+    };
+
+    let language = match logos_parser::LanguageId::from_str(&doc.language_id) {
+        Some(l) => l,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "Unsupported language"}));
+        }
+    };
+
+    let file = module_file(state, uri, doc.content());
+    let ctx = logos_refactor::RefactorContext::new(doc.content(), uri, logos_core::Range::point(0, 0), language);
+
+    match logos_refactor::organize_imports::organize(&ctx, &file.imports, &params.order) {
+        Ok(result) => {
+            let edits: Vec<_> = result.edits.iter().map(|edit| {
+                json!({
+                    "range": range_to_json(edit.range),
+                    "newText": edit.new_text
+                })
+            }).collect();
+
+            Response::success(id, json!({
+                "success": true,
+                "edits": edits,
+                "workspaceEdit": result.workspace_edit(uri).to_json(),
+                "description": result.description
+            }))
+        }
+        Err(e) => {
+            Response::success(id, json!({
+                "success": false,
+                "error": e.to_string()
+            }))
+        }
+    }
+}
+
+/// Handle logos/normalizeStyle
+pub fn normalize_style(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: NormalizeStyleParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid normalizeStyle params: {}", e),
+            );
+        }
+    };
+
+    let uri = &params.text_document.uri;
+
+    let doc = match state.get_document(uri) {
+        Some(d) => d,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "Document not found"}));
+        }
+    };
+
+    let language = match logos_parser::LanguageId::from_str(&doc.language_id) {
+        Some(l) => l,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "Unsupported language"}));
+        }
+    };
+
+    let ctx = logos_refactor::RefactorContext::new(doc.content(), uri, logos_core::Range::point(0, 0), language);
+
+    match logos_refactor::style::normalize(&ctx, &params.options) {
+        Ok(result) => {
+            let edits: Vec<_> = result.edits.iter().map(|edit| {
+                json!({
+                    "range": range_to_json(edit.range),
+                    "newText": edit.new_text
+                })
+            }).collect();
+
+            Response::success(id, json!({
+                "success": true,
+                "edits": edits,
+                "workspaceEdit": result.workspace_edit(uri).to_json(),
+                "description": result.description
+            }))
+        }
+        Err(e) => {
+            Response::success(id, json!({
+                "success": false,
+                "error": e.to_string()
+            }))
+        }
+    }
+}
+
+/// Handle logos/surroundWith
+pub fn surround_with(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: SurroundWithParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid surroundWith params: {}", e),
+            );
+        }
+    };
+
+    let uri = &params.text_document.uri;
+
+    let doc = match state.get_document(uri) {
+        Some(d) => d,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "Document not found"}));
+        }
+    };
+
+    let language = match logos_parser::LanguageId::from_str(&doc.language_id) {
+        Some(l) => l,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "Unsupported language"}));
+        }
+    };
+
+    let selection = logos_core::Range::from_coords(
+        params.range.start.line,
+        params.range.start.character,
+        params.range.end.line,
+        params.range.end.character,
+    );
+
+    let ctx = logos_refactor::RefactorContext::new(doc.content(), uri, selection, language);
+
+    match logos_refactor::surround_with::surround(&ctx, params.kind) {
+        Ok(surrounded) => {
+            let edits: Vec<_> = surrounded.result.edits.iter().map(|edit| {
+                json!({
+                    "range": range_to_json(edit.range),
+                    "newText": edit.new_text
+                })
+            }).collect();
+            let placeholders: Vec<_> = surrounded.placeholders.iter().map(|&range| range_to_json(range)).collect();
+
+            Response::success(id, json!({
+                "success": true,
+                "edits": edits,
+                "workspaceEdit": surrounded.result.workspace_edit(uri).to_json(),
+                "description": surrounded.result.description,
+                "placeholders": placeholders
+            }))
+        }
+        Err(e) => {
+            Response::success(id, json!({
+                "success": false,
+                "error": e.to_string()
+            }))
+        }
+    }
+}
+
+/// Handle logos/getDeclarationConversions
+pub fn get_declaration_conversions(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: TextDocumentPositionParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid getDeclarationConversions params: {}", e),
+            );
+        }
+    };
+
+    let uri = &params.text_document.uri;
+
+    let (doc, language) = match state
+        .get_document(uri)
+        .and_then(|doc| Some((doc, logos_parser::LanguageId::from_str(&doc.language_id)?)))
+    {
+        Some(found) => found,
+        None => return Response::success(id, json!([])),
+    };
+
+    let position = logos_core::Range::from_coords(
+        params.position.line,
+        params.position.character,
+        params.position.line,
+        params.position.character,
+    );
+    let ctx = logos_refactor::RefactorContext::new(doc.content(), uri, position, language);
+
+    let conversions: Vec<_> = logos_refactor::convert_declaration::conversions(&ctx)
+        .iter()
+        .map(|conversion| {
+            json!({
+                "id": conversion.id,
+                "title": conversion.title,
+                "edits": [{
+                    "range": range_to_json(conversion.edit.range),
+                    "newText": conversion.edit.new_text
+                }]
+            })
+        })
+        .collect();
+
+    Response::success(id, json!(conversions))
+}
+
+/// Handle logos/convertFunction
+pub fn convert_function(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: ConvertFunctionParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid convertFunction params: {}", e),
+            );
+        }
+    };
+
+    let uri = &params.text_document.uri;
+
+    let doc = match state.get_document(uri) {
+        Some(d) => d,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "Document not found"}));
+        }
+    };
+
+    let language = match logos_parser::LanguageId::from_str(&doc.language_id) {
+        Some(l) => l,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "Unsupported language"}));
+        }
+    };
+
+    let position = logos_core::Range::from_coords(
+        params.position.line,
+        params.position.character,
+        params.position.line,
+        params.position.character,
+    );
+    let ctx = logos_refactor::RefactorContext::new(doc.content(), uri, position, language);
+
+    match logos_refactor::convert_function::convert(&ctx, params.target_form) {
+        Ok(result) => {
+            let edits: Vec<_> = result.edits.iter().map(|edit| {
+                json!({
+                    "range": range_to_json(edit.range),
+                    "newText": edit.new_text
+                })
+            }).collect();
+
+            Response::success(id, json!({
+                "success": true,
+                "edits": edits,
+                "workspaceEdit": result.workspace_edit(uri).to_json(),
+                "description": result.description
+            }))
+        }
+        Err(e) => {
+            Response::success(id, json!({
+                "success": false,
+                "error": e.to_string()
+            }))
+        }
+    }
+}
+
+/// Handle logos/convertString
+pub fn convert_string(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: RefactorParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid convertString params: {}", e),
+            );
+        }
+    };
+
+    let uri = &params.text_document.uri;
+
+    let doc = match state.get_document(uri) {
+        Some(d) => d,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "Document not found"}));
+        }
+    };
+
+    let language = match logos_parser::LanguageId::from_str(&doc.language_id) {
+        Some(l) => l,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "Unsupported language"}));
+        }
+    };
+
+    let selection = logos_core::Range::from_coords(
+        params.range.start.line,
+        params.range.start.character,
+        params.range.end.line,
+        params.range.end.character,
+    );
+    let ctx = logos_refactor::RefactorContext::new(doc.content(), uri, selection, language);
+
+    match logos_refactor::convert_string::convert(&ctx) {
+        Ok(result) => {
+            let edits: Vec<_> = result.edits.iter().map(|edit| {
+                json!({
+                    "range": range_to_json(edit.range),
+                    "newText": edit.new_text
+                })
+            }).collect();
+
+            Response::success(id, json!({
+                "success": true,
+                "edits": edits,
+                "workspaceEdit": result.workspace_edit(uri).to_json(),
+                "description": result.description
+            }))
+        }
+        Err(e) => {
+            Response::success(id, json!({
+                "success": false,
+                "error": e.to_string(),
+                "ranges": error_ranges(&e)
+            }))
+        }
+    }
+}
+
+/// Handle logos/pullMemberUp
+pub fn pull_member_up(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: TextDocumentPositionParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid pullMemberUp params: {}", e),
+            );
+        }
+    };
+
+    let uri = &params.text_document.uri;
+
+    let doc = match state.get_document(uri) {
+        Some(d) => d,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "Document not found"}));
+        }
+    };
+
+    let language = match logos_parser::LanguageId::from_str(&doc.language_id) {
+        Some(l) => l,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "Unsupported language"}));
+        }
+    };
+
+    let parents: Vec<ClassParent> = state
+        .analyses
+        .get(uri)
+        .map(|analysis| {
+            analysis
+                .type_relations
+                .iter()
+                .filter(|relation| !relation.is_implements)
+                .map(|relation| ClassParent {
+                    class: relation.child_name.clone(),
+                    parent: relation.parent_name.clone(),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let position = logos_core::Range::from_coords(
+        params.position.line,
+        params.position.character,
+        params.position.line,
+        params.position.character,
+    );
+    let ctx = logos_refactor::RefactorContext::new(doc.content(), uri, position, language);
+
+    match logos_refactor::move_member::pull_up(&ctx, &parents) {
+        Ok(result) => {
+            let edits: Vec<_> = result.edits.iter().map(|edit| {
+                json!({
+                    "range": range_to_json(edit.range),
+                    "newText": edit.new_text
+                })
+            }).collect();
+
+            Response::success(id, json!({
+                "success": true,
+                "edits": edits,
+                "workspaceEdit": result.workspace_edit(uri).to_json(),
+                "description": result.description
+            }))
+        }
+        Err(e) => {
+            Response::success(id, json!({
+                "success": false,
+                "error": e.to_string()
+            }))
+        }
+    }
+}
+
+/// Handle logos/inlineMethod
+pub fn inline_method(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: TextDocumentPositionParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid inlineMethod params: {}", e),
+            );
+        }
+    };
+
+    let uri = &params.text_document.uri;
+
+    let doc = match state.get_document(uri) {
+        Some(d) => d,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "Document not found"}));
+        }
+    };
+
+    let language = match logos_parser::LanguageId::from_str(&doc.language_id) {
+        Some(l) => l,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "Unsupported language"}));
+        }
+    };
+
+    let position = logos_core::Range::from_coords(
+        params.position.line,
+        params.position.character,
+        params.position.line,
+        params.position.character,
+    );
+    let ctx = logos_refactor::RefactorContext::new(doc.content(), uri, position, language);
+
+    match logos_refactor::inline_method::inline(&ctx) {
+        Ok(result) => {
+            let edits: Vec<_> = result.edits.iter().map(|edit| {
+                json!({
+                    "range": range_to_json(edit.range),
+                    "newText": edit.new_text
+                })
+            }).collect();
+
+            Response::success(id, json!({
+                "success": true,
+                "edits": edits,
+                "workspaceEdit": result.workspace_edit(uri).to_json(),
+                "description": result.description
+            }))
+        }
+        Err(e) => {
+            Response::success(id, json!({
+                "success": false,
+                "error": e.to_string()
+            }))
+        }
+    }
+}
+
+/// Handle logos/splitDeclaration
+pub fn split_declaration(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    declaration_at_position(state, params, id, "splitDeclaration", logos_refactor::split_declaration::split)
+}
+
+/// Handle logos/joinDeclaration
+pub fn join_declaration(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    declaration_at_position(state, params, id, "joinDeclaration", logos_refactor::split_declaration::join)
+}
+
+/// Run a split or join on the declaration at the request's position
+fn declaration_at_position(
+    state: &State,
+    params: &Value,
+    id: Option<RequestId>,
+    method: &str,
+    refactor: fn(&logos_refactor::RefactorContext) -> Result<logos_refactor::RefactorResult, logos_refactor::RefactorError>,
+) -> Response {
+    let params: TextDocumentPositionParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid {} params: {}", method, e),
+            );
+        }
+    };
+
+    let uri = &params.text_document.uri;
+
+    let doc = match state.get_document(uri) {
+        Some(d) => d,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "Document not found"}));
+        }
+    };
+
+    let language = match logos_parser::LanguageId::from_str(&doc.language_id) {
+        Some(l) => l,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "Unsupported language"}));
+        }
+    };
+
+    let position = logos_core::Range::from_coords(
+        params.position.line,
+        params.position.character,
+        params.position.line,
+        params.position.character,
+    );
+    let ctx = logos_refactor::RefactorContext::new(doc.content(), uri, position, language);
+
+    match refactor(&ctx) {
+        Ok(result) => {
+            let edits: Vec<_> = result.edits.iter().map(|edit| {
+                json!({
+                    "range": range_to_json(edit.range),
+                    "newText": edit.new_text
+                })
+            }).collect();
+
+            Response::success(id, json!({
+                "success": true,
+                "edits": edits,
+                "workspaceEdit": result.workspace_edit(uri).to_json(),
+                "description": result.description
+            }))
+        }
+        Err(e) => {
+            Response::success(id, json!({
+                "success": false,
+                "error": e.to_string()
+            }))
+        }
+    }
+}
+
+/// Handle logos/extractParameter
+pub fn extract_parameter(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: ExtractParameterParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid extractParameter params: {}", e),
+            );
+        }
+    };
+
+    let uri = &params.text_document.uri;
+
+    let doc = match state.get_document(uri) {
+        Some(d) => d,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "Document not found"}));
+        }
+    };
+
+    let language = match logos_parser::LanguageId::from_str(&doc.language_id) {
+        Some(l) => l,
+        None => {
+            return Response::success(id, json!({"success": false, "error": "Unsupported language"}));
+        }
+    };
+
+    let selection = logos_core::Range::from_coords(
+        params.range.start.line,
+        params.range.start.character,
+        params.range.end.line,
+        params.range.end.character,
+    );
+    let ctx = logos_refactor::RefactorContext::new(doc.content(), uri, selection, language);
+
+    // Call sites in this document, from its analysis
+    let calls: Vec<CallSite> = state
+        .analyses
+        .get(uri)
+        .map(|analysis| {
+            analysis
+                .calls
+                .iter()
+                .map(|call| CallSite {
+                    callee: call.callee_name.clone(),
+                    range: call.location,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    match logos_refactor::extract_parameter::extract(&ctx, &params.param_name, &calls) {
+        Ok(result) => {
+            let edits: Vec<_> = result.edits.iter().map(|edit| {
+                json!({
+                    "range": range_to_json(edit.range),
+                    "newText": edit.new_text
+                })
+            }).collect();
+
+            Response::success(id, json!({
+                "success": true,
+                "edits": edits,
+                "workspaceEdit": result.workspace_edit(uri).to_json(),
+                "description": result.description
+            }))
+        }
+        Err(e) => {
+            Response::success(id, json!({
+                "success": false,
+                "error": e.to_string()
+            }))
+        }
+    }
+}
+
+/// The imports and top-level names of a document, from its analysis
+fn module_file<'a>(state: &State, uri: &'a str, source: &'a str) -> ModuleFile<'a> {
+    let result = state.analyses.get(uri);
+    ModuleFile {
+        uri,
+        source,
+        imports: result
+            .map(|r| {
+                r.imports
+                    .iter()
+                    .map(|import| ModuleImport {
+                        module_path: import.module_path.clone(),
+                        items: import.items.iter().map(|i| (i.name.clone(), i.alias.clone())).collect(),
+                        range: import.location,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        declarations: result
+            .map(|r| r.symbols.iter().filter(|s| s.parent.is_none()).map(|s| s.name.clone()).collect())
+            .unwrap_or_default(),
+    }
+}
+
+fn file_move_to_json(file: &FileMove) -> Value {
+    let edits: Vec<_> = file.edits.iter().map(|edit| {
+        json!({
+            "range": {
+                "start": {
+                    "line": edit.range.start.line,
+                    "character": edit.range.start.column
+                },
+                "end": {
+                    "line": edit.range.end.line,
+                    "character": edit.range.end.column
+                }
+            },
+            "newText": edit.new_text
+        })
+    }).collect();
+
+    json!({
+        "uri": file.uri,
+        "edits": edits,
+        "newText": file.new_text
+    })
+}
+
+/// The source ranges a refactoring error points at
+fn error_ranges(error: &logos_refactor::RefactorError) -> Vec<Value> {
+    error.ranges().into_iter().map(range_to_json).collect()
+}
+
+fn range_to_json(range: logos_core::Range) -> Value {
+    json!({
+        "start": {
+            "line": range.start.line,
+            "character": range.start.column
+        },
+        "end": {
+            "line": range.end.line,
+            "character": range.end.column
+        }
+    })
+}
+
+/// Look up the indexed symbol under the selection to disambiguate same-named symbols
+fn symbol_scope(
+    state: &State,
+    uri: &str,
+    selection: logos_core::Range,
+) -> Option<logos_refactor::safe_delete::SymbolScope> {
+    let result = state.analyses.get(uri)?;
+    let symbol = result.symbols.iter().find(|s| {
+        s.location.selection_range.overlaps(&selection)
+    })?;
+
+    let parent = symbol
+        .parent
+        .and_then(|id| result.symbols.iter().find(|s| s.id == id))
+        .filter(|p| logos_index::navigation::is_type_kind(p.kind));
+
+    Some(logos_refactor::safe_delete::SymbolScope {
+        qualified_name: symbol.qualified_name.clone(),
+        is_member: parent.is_some(),
+        member_scope: parent.map(|p| p.location.range),
+        other_declarations: result
+            .symbols
+            .iter()
+            .filter(|s| s.name == symbol.name && s.id != symbol.id)
+            .map(|s| s.location.selection_range)
+            .collect(),
+    })
+}
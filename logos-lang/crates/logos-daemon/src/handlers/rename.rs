@@ -2,9 +2,11 @@
 
 use serde_json::{json, Value};
 use std::collections::HashMap;
-use logos_core::Position;
+use logos_core::{Position, Range};
+use logos_refactor::rename::{RenameDocument, RenamePlan};
+use logos_refactor::RefactorError;
 
-use crate::protocol::{TextDocumentPositionParams, RenameParams, RequestId, Response};
+use crate::protocol::{TextDocumentPositionParams, RenameFileParams, RenameParams, RequestId, Response};
 use crate::state::State;
 
 /// Handle textDocument/prepareRename
@@ -23,19 +25,19 @@ pub fn prepare(state: &State, params: &Value, id: Option<RequestId>) -> Response
     let uri = &params.text_document.uri;
     let position = Position::new(params.position.line, params.position.character);
 
-    if let Some(symbol) = state.symbol_index.find_at_position(uri, position) {
+    let target = state
+        .symbol_index
+        .find_at_position(uri, position)
+        .map(|symbol| (symbol.name.clone(), symbol.selection_range))
+        .or_else(|| {
+            let doc = state.get_document(uri)?;
+            logos_refactor::rename::identifier_at(doc.content(), position)
+        });
+
+    if let Some((name, range)) = target {
         let result = json!({
-            "range": {
-                "start": {
-                    "line": symbol.selection_range.start.line,
-                    "character": symbol.selection_range.start.column
-                },
-                "end": {
-                    "line": symbol.selection_range.end.line,
-                    "character": symbol.selection_range.end.column
-                }
-            },
-            "placeholder": symbol.name
+            "range": range_to_json(range),
+            "placeholder": name
         });
         return Response::success(id, result);
     }
@@ -56,44 +58,178 @@ pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response
         }
     };
 
+    let plan = match rename_plan(state, &params) {
+        Ok(Some(plan)) => plan,
+        Ok(None) => return Response::null_result(id),
+        Err(e) => return Response::error(id, crate::protocol::error_codes::INVALID_PARAMS, error_message(&e)),
+    };
+
+    let workspace_edit = plan.workspace_edit().to_json();
+
+    Response::success(id, workspace_edit)
+}
+
+/// Handle logos/updateReferencesForRename: rewrite the imports of open
+/// documents that resolve to a file being moved from `oldUri` to `newUri`
+pub fn update_references_for_rename(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: RenameFileParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid updateReferencesForRename params: {}", e),
+            );
+        }
+    };
+
+    let (old_path, new_path) = match (params.old_uri.strip_prefix("file://"), params.new_uri.strip_prefix("file://")) {
+        (Some(old), Some(new)) => (std::path::Path::new(old), std::path::Path::new(new)),
+        _ => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                "Only file:// URIs can be renamed".to_string(),
+            );
+        }
+    };
+
+    let mut changes: HashMap<String, Vec<Value>> = HashMap::new();
+    for uri in state.get_open_documents() {
+        let (doc, analysis, adapter) = match (state.get_document(&uri), state.analyses.get(&uri), state.adapter_for(&uri)) {
+            (Some(doc), Some(analysis), Some(adapter)) => (doc, analysis, adapter),
+            _ => continue,
+        };
+        let edits = logos_index::file_rename::import_path_edits(
+            adapter,
+            &uri,
+            doc.content(),
+            &analysis.imports,
+            old_path,
+            new_path,
+        );
+        if !edits.is_empty() {
+            let edits = edits
+                .iter()
+                .map(|edit| json!({ "range": range_to_json(edit.range), "newText": edit.new_text }))
+                .collect();
+            changes.insert(uri, edits);
+        }
+    }
+
+    Response::success(id, json!({ "changes": changes }))
+}
+
+/// Handle logos/renamePreview
+pub fn preview(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: RenameParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid renamePreview params: {}", e),
+            );
+        }
+    };
+
+    let plan = match rename_plan(state, &params) {
+        Ok(Some(plan)) => plan,
+        Ok(None) => {
+            return Response::success(id, json!({"success": false, "error": "No symbol at position"}));
+        }
+        Err(e) => {
+            let conflicts: Vec<Value> = match &e {
+                RefactorError::NameCollision(_, locations) => locations
+                    .iter()
+                    .map(|loc| json!({ "uri": loc.uri, "range": range_to_json(loc.range) }))
+                    .collect(),
+                _ => Vec::new(),
+            };
+            return Response::success(id, json!({"success": false, "error": error_message(&e), "conflicts": conflicts}));
+        }
+    };
+
+    let files: Vec<_> = plan
+        .files
+        .iter()
+        .map(|file| {
+            json!({
+                "uri": file.uri,
+                "edits": file.edits.iter().map(edit_to_json).collect::<Vec<_>>(),
+                "newText": file.new_text
+            })
+        })
+        .collect();
+
+    Response::success(id, json!({
+        "success": true,
+        "files": files,
+        "fileCount": plan.files.len(),
+        "occurrenceCount": plan.occurrences()
+    }))
+}
+
+/// Resolve the symbol at the position and plan its rename over all open
+/// documents; shared by rename and its preview
+fn rename_plan(state: &State, params: &RenameParams) -> Result<Option<RenamePlan>, RefactorError> {
     let uri = &params.text_document.uri;
     let position = Position::new(params.position.line, params.position.character);
-    let new_name = &params.new_name;
 
-    // Find the symbol at the given position
-    let symbol = match state.symbol_index.find_at_position(uri, position) {
-        Some(s) => s,
-        None => return Response::null_result(id),
+    let doc = match state.get_document(uri) {
+        Some(d) => d,
+        None => return Ok(None),
     };
+    if logos_refactor::rename::identifier_at(doc.content(), position).is_none() {
+        return Ok(None);
+    }
 
-    let old_name = symbol.name.clone();
-
-    // Find all references to this symbol
-    let references = state.symbol_index.search(&old_name);
+    let uris = state.get_open_documents();
+    let documents: Vec<RenameDocument> = uris
+        .iter()
+        .filter_map(|uri| {
+            let doc = state.get_document(uri)?;
+            Some(RenameDocument {
+                uri,
+                source: doc.content(),
+                language: logos_parser::LanguageId::from_str(&doc.language_id),
+            })
+        })
+        .collect();
 
-    // Group edits by document URI
-    let mut changes: HashMap<String, Vec<Value>> = HashMap::new();
+    logos_refactor::rename::plan(&documents, uri, position, &params.new_name).map(Some)
+}
 
-    for s in references {
-        let edit = json!({
-            "range": {
-                "start": {
-                    "line": s.selection_range.start.line,
-                    "character": s.selection_range.start.column
-                },
-                "end": {
-                    "line": s.selection_range.end.line,
-                    "character": s.selection_range.end.column
-                }
-            },
-            "newText": new_name
-        });
-        changes.entry(s.uri.clone()).or_default().push(edit);
+/// The error text, listing where a colliding name is defined
+fn error_message(e: &RefactorError) -> String {
+    match e {
+        RefactorError::NameCollision(name, locations) => {
+            let locations: Vec<_> = locations
+                .iter()
+                .map(|loc| format!("{}:{}:{}", loc.uri, loc.range.start.line + 1, loc.range.start.column + 1))
+                .collect();
+            format!("'{}' is already defined at: {}", name, locations.join(", "))
+        }
+        _ => e.to_string(),
     }
+}
 
-    let workspace_edit = json!({
-        "changes": changes
-    });
+fn edit_to_json(edit: &logos_refactor::TextEdit) -> Value {
+    json!({
+        "range": range_to_json(edit.range),
+        "newText": edit.new_text
+    })
+}
 
-    Response::success(id, workspace_edit)
+fn range_to_json(range: Range) -> Value {
+    json!({
+        "start": {
+            "line": range.start.line,
+            "character": range.start.column
+        },
+        "end": {
+            "line": range.end.line,
+            "character": range.end.column
+        }
+    })
 }
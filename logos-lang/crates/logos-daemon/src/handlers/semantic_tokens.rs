@@ -0,0 +1,29 @@
+//! Semantic tokens handler
+
+use serde_json::{json, Value};
+
+use crate::protocol::{SemanticTokensParams, RequestId, Response};
+use crate::state::State;
+
+/// Handle textDocument/semanticTokens/full
+pub fn full(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: SemanticTokensParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid semanticTokens params: {}", e),
+            );
+        }
+    };
+
+    let uri = &params.text_document.uri;
+
+    let tokens = match (state.get_document(uri), state.adapter_for(uri), state.analyses.get(uri)) {
+        (Some(doc), Some(adapter), Some(analysis)) => adapter.semantic_tokens(doc.content(), analysis),
+        _ => Vec::new(),
+    };
+
+    Response::success(id, json!({ "data": logos_index::semantic_tokens::encode(&tokens) }))
+}
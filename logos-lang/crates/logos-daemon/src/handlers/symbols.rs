@@ -1,5 +1,6 @@
 //! Symbol handlers
 
+use logos_core::Range;
 use serde_json::{json, Value};
 
 use crate::protocol::{DocumentSymbolParams, WorkspaceSymbolParams, RequestId, Response};
@@ -20,30 +21,43 @@ pub fn document_symbols(state: &State, params: &Value, id: Option<RequestId>) ->
 
     let uri = &params.text_document.uri;
 
-    let symbols: Vec<_> = state.symbol_index.get_document_symbols(uri)
-        .iter()
-        .map(|s| {
+    // Symbols come from the document's language adapter when it has one
+    let entries: Vec<(&str, logos_core::SymbolKind, Range, Range)> = match state.analyses.get(uri) {
+        Some(analysis) => analysis
+            .symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.kind, s.location.range, s.location.selection_range))
+            .collect(),
+        None => state.symbol_index.get_document_symbols(uri)
+            .iter()
+            .map(|s| (s.name.as_str(), s.kind, s.range, s.selection_range))
+            .collect(),
+    };
+
+    let symbols: Vec<_> = entries
+        .into_iter()
+        .map(|(name, kind, range, selection_range)| {
             json!({
-                "name": s.name,
-                "kind": s.kind.to_monaco_kind(),
+                "name": name,
+                "kind": kind.to_monaco_kind(),
                 "range": {
                     "start": {
-                        "line": s.range.start.line,
-                        "character": s.range.start.column
+                        "line": range.start.line,
+                        "character": range.start.column
                     },
                     "end": {
-                        "line": s.range.end.line,
-                        "character": s.range.end.column
+                        "line": range.end.line,
+                        "character": range.end.column
                     }
                 },
                 "selectionRange": {
                     "start": {
-                        "line": s.selection_range.start.line,
-                        "character": s.selection_range.start.column
+                        "line": selection_range.start.line,
+                        "character": selection_range.start.column
                     },
                     "end": {
-                        "line": s.selection_range.end.line,
-                        "character": s.selection_range.end.column
+                        "line": selection_range.end.line,
+                        "character": selection_range.end.column
                     }
                 }
             })
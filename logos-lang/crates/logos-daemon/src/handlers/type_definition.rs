@@ -0,0 +1,59 @@
+//! Type definition handler
+
+use serde_json::{json, Value};
+use logos_core::Position;
+
+use crate::protocol::{TextDocumentPositionParams, RequestId, Response};
+use crate::state::State;
+
+/// Handle textDocument/typeDefinition
+pub fn handle(state: &State, params: &Value, id: Option<RequestId>) -> Response {
+    let params: TextDocumentPositionParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response::error(
+                id,
+                crate::protocol::error_codes::INVALID_PARAMS,
+                format!("Invalid typeDefinition params: {}", e),
+            );
+        }
+    };
+
+    let uri = &params.text_document.uri;
+    let position = Position::new(params.position.line, params.position.character);
+
+    let doc = match state.get_document(uri) {
+        Some(d) => d,
+        None => return Response::null_result(id),
+    };
+
+    let name = match logos_index::navigation::identifier_at(doc.content(), position) {
+        Some(name) => name,
+        None => return Response::null_result(id),
+    };
+
+    let locations: Vec<_> = logos_index::navigation::find_type_definition(&state.analyses, doc.content(), &name)
+        .iter()
+        .map(|loc| {
+            json!({
+                "uri": loc.uri,
+                "range": {
+                    "start": {
+                        "line": loc.range.start.line,
+                        "character": loc.range.start.column
+                    },
+                    "end": {
+                        "line": loc.range.end.line,
+                        "character": loc.range.end.column
+                    }
+                }
+            })
+        })
+        .collect();
+
+    if locations.is_empty() {
+        return Response::null_result(id);
+    }
+
+    Response::success(id, json!(locations))
+}
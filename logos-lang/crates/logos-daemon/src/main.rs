@@ -6,9 +6,19 @@ mod protocol;
 mod server;
 mod state;
 mod handlers;
+mod types;
 
 use std::io::{self, BufRead, BufReader, Read, Write};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
 use log::{info, error, debug};
+use logos_core::CancellationToken;
+use serde_json::Value;
+
+/// Id of the request being handled, for matching `$/cancelRequest`
+type InFlight = Arc<Mutex<Option<Value>>>;
 
 fn main() {
     // Initialize logger
@@ -18,12 +28,67 @@ fn main() {
 
     info!("logos-daemon starting...");
 
-    let stdin = io::stdin();
     let stdout = io::stdout();
-    let mut reader = BufReader::new(stdin.lock());
     let mut stdout = stdout.lock();
 
     let mut server = server::Server::new();
+    let cancellation = server.cancellation();
+    let in_flight: InFlight = Arc::new(Mutex::new(None));
+
+    // Messages are read on their own thread so that a cancellation reaches
+    // the request it targets while that request still runs
+    let (sender, receiver) = mpsc::channel();
+    {
+        let cancellation = cancellation.clone();
+        let in_flight = Arc::clone(&in_flight);
+        thread::spawn(move || read_messages(sender, cancellation, in_flight));
+    }
+
+    for body in receiver {
+        debug!("Received: {}", body);
+
+        cancellation.reset();
+        *in_flight.lock().unwrap() = request_id(&body);
+
+        // Handle message
+        let response = server.handle_message(&body);
+        *in_flight.lock().unwrap() = None;
+
+        if let Some(response) = response {
+            let response_bytes = response.as_bytes();
+            let header = format!("Content-Length: {}\r\n\r\n", response_bytes.len());
+
+            if let Err(e) = stdout.write_all(header.as_bytes()) {
+                error!("Error writing header: {}", e);
+                break;
+            }
+            if let Err(e) = stdout.write_all(response_bytes) {
+                error!("Error writing body: {}", e);
+                break;
+            }
+            if let Err(e) = stdout.flush() {
+                error!("Error flushing stdout: {}", e);
+                break;
+            }
+
+            debug!("Sent: {}", response);
+        }
+
+        // Check if we should exit
+        if server.should_exit() {
+            info!("Exit requested, shutting down");
+            break;
+        }
+    }
+
+    info!("logos-daemon stopped");
+}
+
+/// Read messages from stdin and pass them on until EOF. A `$/cancelRequest`
+/// for the request in flight raises `cancellation` right away.
+fn read_messages(sender: Sender<String>, cancellation: CancellationToken, in_flight: InFlight) {
+    let stdin = io::stdin();
+    let mut reader = BufReader::new(stdin.lock());
 
     loop {
         // Read header
@@ -54,37 +119,38 @@ fn main() {
             }
         };
 
-        debug!("Received: {}", body);
-
-        // Handle message
-        if let Some(response) = server.handle_message(&body) {
-            let response_bytes = response.as_bytes();
-            let header = format!("Content-Length: {}\r\n\r\n", response_bytes.len());
-
-            if let Err(e) = stdout.write_all(header.as_bytes()) {
-                error!("Error writing header: {}", e);
-                break;
+        if let Some(id) = cancelled_request(&body) {
+            if in_flight.lock().unwrap().as_ref() == Some(&id) {
+                info!("Cancelling request {}", id);
+                cancellation.cancel();
             }
-            if let Err(e) = stdout.write_all(response_bytes) {
-                error!("Error writing body: {}", e);
-                break;
-            }
-            if let Err(e) = stdout.flush() {
-                error!("Error flushing stdout: {}", e);
-                break;
-            }
-
-            debug!("Sent: {}", response);
         }
 
-        // Check if we should exit
-        if server.should_exit() {
-            info!("Exit requested, shutting down");
+        if sender.send(body).is_err() {
             break;
         }
     }
+}
 
-    info!("logos-daemon stopped");
+/// The id of a request, if the message is one
+fn request_id(body: &str) -> Option<Value> {
+    #[derive(serde::Deserialize)]
+    struct Envelope {
+        id: Option<Value>,
+    }
+    serde_json::from_str::<Envelope>(body).ok()?.id
+}
+
+/// The id a `$/cancelRequest` notification cancels
+fn cancelled_request(body: &str) -> Option<Value> {
+    if !body.contains("$/cancelRequest") {
+        return None;
+    }
+    let message: Value = serde_json::from_str(body).ok()?;
+    if message["method"] != "$/cancelRequest" {
+        return None;
+    }
+    message["params"].get("id").cloned()
 }
 
 /// Read LSP-style header and return content length
@@ -1,5 +1,7 @@
 //! JSON-RPC 2.0 protocol types
 
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -82,6 +84,7 @@ pub mod error_codes {
     pub const METHOD_NOT_FOUND: i32 = -32601;
     pub const INVALID_PARAMS: i32 = -32602;
     pub const INTERNAL_ERROR: i32 = -32603;
+    pub const REQUEST_CANCELLED: i32 = -32800;
 }
 
 /// JSON-RPC notification (no id, no response expected)
@@ -112,6 +115,8 @@ pub struct InitializeParams {
     pub root_uri: Option<String>,
     #[serde(default)]
     pub capabilities: Value,
+    #[serde(default)]
+    pub initialization_options: Value,
 }
 
 #[derive(Debug, Deserialize)]
@@ -136,14 +141,14 @@ pub struct TextDocumentItem {
     pub text: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Position {
     pub line: u32,
     pub character: u32,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Range {
     pub start: Position,
@@ -164,6 +169,22 @@ pub struct DidOpenTextDocumentParams {
     pub text_document: TextDocumentItem,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenDocumentsParams {
+    /// Each entry is parsed separately so one bad entry doesn't fail the batch
+    pub documents: Vec<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchDocument {
+    pub uri: String,
+    pub language_id: String,
+    #[serde(alias = "text")]
+    pub content: String,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DidChangeTextDocumentParams {
@@ -192,12 +213,26 @@ pub struct RenameParams {
     pub new_name: String,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenameFileParams {
+    pub old_uri: String,
+    pub new_uri: String,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DocumentSymbolParams {
     pub text_document: TextDocumentIdentifier,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlayHintParams {
+    pub text_document: TextDocumentIdentifier,
+    pub range: Range,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct WorkspaceSymbolParams {
@@ -218,15 +253,182 @@ pub struct RefactorParams {
 pub struct ExtractVariableParams {
     pub text_document: TextDocumentIdentifier,
     pub range: Range,
+    /// Omit to suggest a name
+    #[serde(default)]
+    pub variable_name: Option<String>,
+    /// Extract even if the expression has side effects
+    #[serde(default)]
+    pub force: bool,
+    /// Declare a constant instead of a variable
+    #[serde(default)]
+    pub constant: bool,
+    /// Casing of suggested names
+    #[serde(default)]
+    pub naming: logos_refactor::naming::NamingConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractVariableMultiParams {
+    pub text_document: TextDocumentIdentifier,
+    /// Every occurrence to replace; all must hold the same expression
+    pub ranges: Vec<Range>,
     pub variable_name: String,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractIntoExistingParams {
+    pub text_document: TextDocumentIdentifier,
+    pub range: Range,
+    /// A variable declared in scope before the statement using the expression
+    pub existing_var_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractParameterParams {
+    pub text_document: TextDocumentIdentifier,
+    pub range: Range,
+    pub param_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnusedSymbolsParams {
+    pub text_document: TextDocumentIdentifier,
+    /// Also report exported/public items
+    #[serde(default)]
+    pub include_exported: bool,
+    /// Ignore names starting with any of these prefixes (in addition to `_`)
+    #[serde(default)]
+    pub ignore_prefixes: Vec<String>,
+    /// Ignore names matching any of these regular expressions
+    #[serde(default)]
+    pub ignore_regexes: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExclusionPatternsParams {
+    /// Globs over document URIs, e.g. `**/node_modules/**`; empty clears
+    pub patterns: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TodosGroupedByFileParams {
+    /// Only count TODOs of this kind, e.g. `"optimize"`
+    #[serde(default)]
+    pub kind: Option<logos_index::TodoKind>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceDiagnosticsParams {
+    /// Also report TODO/FIXME markers as information diagnostics
+    #[serde(default)]
+    pub include_todos: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateBlocksParams {
+    /// Smallest duplicated run to report, in tokens
+    #[serde(default = "default_min_tokens")]
+    pub min_tokens: usize,
+}
+
+impl Default for DuplicateBlocksParams {
+    fn default() -> Self {
+        Self { min_tokens: default_min_tokens() }
+    }
+}
+
+fn default_min_tokens() -> usize {
+    30
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceDeadCodeParams {
+    /// Also report exported/public items no open document references
+    #[serde(default)]
+    pub include_exported: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentLinksParams {
+    pub text_document: TextDocumentIdentifier,
+    /// Issue URL templates by project key; `#` for `#123` references, `*`
+    /// for any key. `{id}`, `{project}` and `{number}` are replaced.
+    #[serde(default)]
+    pub issue_link_templates: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticTokensParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ExtractMethodParams {
     pub text_document: TextDocumentIdentifier,
     pub range: Range,
-    pub method_name: String,
+    /// Omit to use a default name, numbered to avoid existing functions
+    #[serde(default)]
+    pub method_name: Option<String>,
+    /// Casing of the default name
+    #[serde(default)]
+    pub naming: logos_refactor::naming::NamingConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveSymbolParams {
+    pub text_document: TextDocumentIdentifier,
+    pub position: Position,
+    /// File to move the symbol to; need not be open
+    pub target_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrganizeImportsParams {
+    pub text_document: TextDocumentIdentifier,
+    /// Order of the import groups; std, third-party, local by default
+    #[serde(default)]
+    pub order: logos_refactor::organize_imports::ImportOrder,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NormalizeStyleParams {
+    pub text_document: TextDocumentIdentifier,
+    /// Quotes and semicolons to apply; parts left out are not touched
+    #[serde(default)]
+    pub options: logos_refactor::style::NormalizeOptions,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SurroundWithParams {
+    pub text_document: TextDocumentIdentifier,
+    pub range: Range,
+    /// "if", "try", "for" or "while"
+    pub kind: logos_refactor::surround_with::SurroundKind,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConvertFunctionParams {
+    pub text_document: TextDocumentIdentifier,
+    pub position: Position,
+    /// "declaration", "arrow" or "method"
+    pub target_form: logos_refactor::convert_function::FunctionForm,
 }
 
 // Call hierarchy types (LSP 3.16+)
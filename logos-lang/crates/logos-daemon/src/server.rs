@@ -1,8 +1,8 @@
 //! JSON-RPC server implementation
 
-use log::{info, warn, debug};
+use log::{info, warn, debug, error};
 
-use crate::protocol::{Request, Response, error_codes};
+use crate::protocol::{Request, RequestId, Response, error_codes};
 use crate::state::State;
 use crate::handlers;
 
@@ -22,6 +22,13 @@ impl Server {
         }
     }
 
+    /// The token that cancels the request being handled; the reader thread
+    /// raises it on `$/cancelRequest` without waiting for the request to end,
+    /// and lowers it before each message
+    pub fn cancellation(&self) -> logos_core::CancellationToken {
+        self.state.cancellation().clone()
+    }
+
     /// Handle an incoming JSON-RPC message
     pub fn handle_message(&mut self, message: &str) -> Option<String> {
         // Parse the message
@@ -40,8 +47,8 @@ impl Server {
 
         debug!("Handling method: {}", request.method);
 
-        // Dispatch to handler
-        let response = self.dispatch(&request);
+        // Dispatch to handler; a panicking handler fails its request only
+        let response = catch_panic(request.id.clone(), &request.method, || self.dispatch(&request));
 
         // If this was a notification (no id), don't send a response
         request.id.as_ref()?;
@@ -67,6 +74,10 @@ impl Server {
                 info!("Shutdown requested");
                 Response::null_result(id)
             }
+            "$/cancelRequest" => {
+                // Acted on by the reader thread as it arrives, see main.rs
+                Response::null_result(id)
+            }
             "exit" => {
                 self.should_exit = true;
                 info!("Exit requested");
@@ -86,6 +97,15 @@ impl Server {
                 handlers::document::did_close(&mut self.state, &request.params);
                 Response::null_result(id)
             }
+            "logos/openDocuments" => {
+                handlers::document::open_documents(&mut self.state, &request.params, id)
+            }
+            "logos/forceIndex" => {
+                handlers::document::force_index(&mut self.state, &request.params, id)
+            }
+            "logos/getDocumentLanguage" => {
+                handlers::document::get_document_language(&self.state, &request.params, id)
+            }
 
             // Language features
             "textDocument/completion" => {
@@ -94,12 +114,24 @@ impl Server {
             "textDocument/definition" => {
                 handlers::definition::handle(&self.state, &request.params, id)
             }
+            "textDocument/typeDefinition" => {
+                handlers::type_definition::handle(&self.state, &request.params, id)
+            }
+            "textDocument/implementation" => {
+                handlers::implementation::handle(&self.state, &request.params, id)
+            }
             "textDocument/references" => {
                 handlers::references::handle(&self.state, &request.params, id)
             }
             "textDocument/hover" => {
                 handlers::hover::handle(&self.state, &request.params, id)
             }
+            "textDocument/inlayHint" => {
+                handlers::inlay_hints::handle(&self.state, &request.params, id)
+            }
+            "textDocument/semanticTokens/full" => {
+                handlers::semantic_tokens::full(&self.state, &request.params, id)
+            }
             "textDocument/documentSymbol" => {
                 handlers::symbols::document_symbols(&self.state, &request.params, id)
             }
@@ -117,14 +149,26 @@ impl Server {
             "textDocument/diagnostic" => {
                 handlers::diagnostics::handle(&self.state, &request.params, id)
             }
+            "logos/getWorkspaceDiagnostics" => {
+                handlers::diagnostics::get_workspace_diagnostics(&self.state, &request.params, id)
+            }
 
             // Refactoring
             "logos/getRefactorActions" => {
                 handlers::refactor::get_actions(&self.state, &request.params, id)
             }
+            "logos/extractVariableMulti" => {
+                handlers::refactor::extract_variable_multi(&self.state, &request.params, id)
+            }
+            "logos/extractIntoExisting" => {
+                handlers::refactor::extract_into_existing(&self.state, &request.params, id)
+            }
             "logos/extractVariable" => {
                 handlers::refactor::extract_variable(&self.state, &request.params, id)
             }
+            "logos/extractParameter" => {
+                handlers::refactor::extract_parameter(&self.state, &request.params, id)
+            }
             "logos/extractMethod" => {
                 handlers::refactor::extract_method(&self.state, &request.params, id)
             }
@@ -134,6 +178,48 @@ impl Server {
             "logos/safeDelete" => {
                 handlers::refactor::safe_delete(&self.state, &request.params, id)
             }
+            "logos/safeDeletePreview" => {
+                handlers::refactor::safe_delete_preview(&self.state, &request.params, id)
+            }
+            "logos/moveSymbolToFile" => {
+                handlers::refactor::move_symbol_to_file(&self.state, &request.params, id)
+            }
+            "logos/organizeImports" => {
+                handlers::refactor::organize_imports(&self.state, &request.params, id)
+            }
+            "logos/normalizeStyle" => {
+                handlers::refactor::normalize_style(&self.state, &request.params, id)
+            }
+            "logos/surroundWith" => {
+                handlers::refactor::surround_with(&self.state, &request.params, id)
+            }
+            "logos/getDeclarationConversions" => {
+                handlers::refactor::get_declaration_conversions(&self.state, &request.params, id)
+            }
+            "logos/convertFunction" => {
+                handlers::refactor::convert_function(&self.state, &request.params, id)
+            }
+            "logos/convertString" => {
+                handlers::refactor::convert_string(&self.state, &request.params, id)
+            }
+            "logos/pullMemberUp" => {
+                handlers::refactor::pull_member_up(&self.state, &request.params, id)
+            }
+            "logos/inlineMethod" => {
+                handlers::refactor::inline_method(&self.state, &request.params, id)
+            }
+            "logos/splitDeclaration" => {
+                handlers::refactor::split_declaration(&self.state, &request.params, id)
+            }
+            "logos/joinDeclaration" => {
+                handlers::refactor::join_declaration(&self.state, &request.params, id)
+            }
+            "logos/renamePreview" => {
+                handlers::rename::preview(&self.state, &request.params, id)
+            }
+            "logos/updateReferencesForRename" => {
+                handlers::rename::update_references_for_rename(&self.state, &request.params, id)
+            }
 
             // Analysis
             "logos/getTodoItems" => {
@@ -142,12 +228,33 @@ impl Server {
             "logos/getAllTodoItems" => {
                 handlers::analysis::get_all_todo_items(&self.state, id)
             }
+            "logos/getTodosGroupedByFile" => {
+                handlers::analysis::get_todos_grouped_by_file(&self.state, &request.params, id)
+            }
+            "logos/getTodoChanges" => {
+                handlers::analysis::get_todo_changes(&mut self.state, id)
+            }
             "logos/getTodoStats" => {
                 handlers::analysis::get_todo_stats(&self.state, id)
             }
+            "logos/getCommentFoldingRanges" => {
+                handlers::analysis::get_comment_folding_ranges(&self.state, &request.params, id)
+            }
+            "logos/getDocumentLinks" => {
+                handlers::links::get_document_links(&self.state, &request.params, id)
+            }
+            "logos/setExclusionPatterns" => {
+                handlers::analysis::set_exclusion_patterns(&mut self.state, &request.params, id)
+            }
             "logos/getUnusedSymbols" => {
                 handlers::analysis::get_unused_symbols(&self.state, &request.params, id)
             }
+            "logos/getWorkspaceDeadCode" => {
+                handlers::analysis::get_workspace_dead_code(&self.state, &request.params, id)
+            }
+            "logos/getDuplicateBlocks" => {
+                handlers::analysis::get_duplicate_blocks(&self.state, &request.params, id)
+            }
 
             // Call Hierarchy (Smart mode)
             "textDocument/prepareCallHierarchy" => {
@@ -160,6 +267,13 @@ impl Server {
                 handlers::call_hierarchy::handle_outgoing_calls(&self.state, &request.params, id)
             }
 
+            "logos/getIncomingCalls" => {
+                handlers::call_hierarchy::get_incoming_calls(&self.state, &request.params, id)
+            }
+            "logos/getOutgoingCalls" => {
+                handlers::call_hierarchy::get_outgoing_calls(&self.state, &request.params, id)
+            }
+
             // Mode switching
             "logos/setMode" => {
                 handlers::mode::handle_set_mode(&mut self.state, &request.params, id)
@@ -170,6 +284,9 @@ impl Server {
             "logos/getIndexStats" => {
                 handlers::mode::handle_get_index_stats(&self.state, &request.params, id)
             }
+            "logos/getServiceStats" => {
+                handlers::mode::handle_get_service_stats(&self.state, &request.params, id)
+            }
 
             // Unknown method
             _ => {
@@ -189,8 +306,186 @@ impl Server {
     }
 }
 
+/// Run a handler, turning a panic into an internal error response
+fn catch_panic(id: Option<RequestId>, method: &str, handler: impl FnOnce() -> Response) -> Response {
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(handler)) {
+        Ok(response) => response,
+        Err(panic) => {
+            let reason = panic
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            error!("Handler for {} panicked: {}", method, reason);
+            Response::error(
+                id,
+                error_codes::INTERNAL_ERROR,
+                format!("Internal error in {}: {}", method, reason),
+            )
+        }
+    }
+}
+
 impl Default for Server {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_handler_panic_becomes_error_response() {
+        let response = catch_panic(Some(RequestId::Number(7)), "logos/test", || panic!("boom"));
+        let error = response.error.unwrap();
+        assert_eq!(error.code, error_codes::INTERNAL_ERROR);
+        assert_eq!(error.message, "Internal error in logos/test: boom");
+        assert!(matches!(response.id, Some(RequestId::Number(7))));
+
+        // The server keeps answering afterwards
+        let mut server = Server::new();
+        let reply = server.handle_message(r#"{"jsonrpc":"2.0","id":1,"method":"logos/getMode"}"#).unwrap();
+        assert!(reply.contains("\"result\""));
+    }
+
+    #[test]
+    fn test_hover_on_todo() {
+        let mut server = Server::new();
+        let open = json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": {"textDocument": {
+                "uri": "file:///a.ts",
+                "languageId": "typescript",
+                "version": 1,
+                "text": "function run() {\n    // TODO!(john): retry on timeout, see #42\n}\n"
+            }}
+        });
+        server.handle_message(&open.to_string());
+
+        let hover = json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "textDocument/hover",
+            "params": {"textDocument": {"uri": "file:///a.ts"}, "position": {"line": 1, "character": 30}}
+        });
+        let reply: Value = serde_json::from_str(&server.handle_message(&hover.to_string()).unwrap()).unwrap();
+        assert_eq!(
+            reply["result"]["contents"]["value"],
+            "**TODO** assigned to `john`\n\nPriority: 3/5 (urgent)\n\nIssues: #42\n\nretry on timeout, see #42"
+        );
+        assert_eq!(reply["result"]["range"]["start"], json!({"line": 1, "character": 7}));
+    }
+
+    #[test]
+    fn test_inlay_hints_for_type_comments() {
+        let mut server = Server::new();
+        let open = json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": {"textDocument": {
+                "uri": "file:///a.py",
+                "languageId": "python",
+                "version": 1,
+                "text": "users = load()  # type: List[User]\ncount: int = 0\n"
+            }}
+        });
+        server.handle_message(&open.to_string());
+
+        let hints = json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "textDocument/inlayHint",
+            "params": {
+                "textDocument": {"uri": "file:///a.py"},
+                "range": {"start": {"line": 0, "character": 0}, "end": {"line": 2, "character": 0}}
+            }
+        });
+        let reply: Value = serde_json::from_str(&server.handle_message(&hints.to_string()).unwrap()).unwrap();
+        assert_eq!(
+            reply["result"],
+            json!([{"position": {"line": 0, "character": 5}, "label": ": List[User]", "kind": 1}])
+        );
+        assert!(server.state.todo_index.get_document_todos("file:///a.py").is_empty());
+    }
+
+    #[test]
+    fn test_todos_grouped_by_file() {
+        let mut server = Server::new();
+        for (uri, text) in [
+            ("file:///a.rs", "// OPTIMIZE: cache this\n// TODO: rename\n"),
+            ("file:///b.rs", "// OPTIMIZE: batch\n// OPTIMIZE: avoid clone\n"),
+        ] {
+            let open = json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/didOpen",
+                "params": {"textDocument": {"uri": uri, "languageId": "rust", "version": 1, "text": text}}
+            });
+            server.handle_message(&open.to_string());
+        }
+
+        let request = r#"{"jsonrpc":"2.0","id":2,"method":"logos/getTodosGroupedByFile","params":{"kind":"optimize"}}"#;
+        let reply: Value = serde_json::from_str(&server.handle_message(request).unwrap()).unwrap();
+        let groups = reply["result"].as_object().unwrap();
+        assert_eq!(groups.keys().collect::<Vec<_>>(), vec!["file:///b.rs", "file:///a.rs"]);
+        assert_eq!(groups["file:///b.rs"]["count"], 2);
+        assert_eq!(groups["file:///a.rs"]["items"][0]["text"], "cache this");
+    }
+
+    #[test]
+    fn test_cancelled_workspace_query() {
+        let mut server = Server::new();
+        let open = json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": {"textDocument": {
+                "uri": "file:///a.ts",
+                "languageId": "typescript",
+                "version": 1,
+                "text": "function helper() {}\n"
+            }}
+        });
+        server.handle_message(&open.to_string());
+        let dead_code = r#"{"jsonrpc":"2.0","id":2,"method":"logos/getWorkspaceDeadCode"}"#;
+
+        server.cancellation().cancel();
+        let reply: Value = serde_json::from_str(&server.handle_message(dead_code).unwrap()).unwrap();
+        assert_eq!(reply["error"]["code"], error_codes::REQUEST_CANCELLED);
+
+        server.cancellation().reset();
+        let reply: Value = serde_json::from_str(&server.handle_message(dead_code).unwrap()).unwrap();
+        assert_eq!(reply["result"][0]["name"], "helper");
+    }
+
+    #[test]
+    fn test_unused_import_diagnostic_carries_its_removal() {
+        let mut server = Server::new();
+        let open = json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": {"textDocument": {
+                "uri": "file:///a.ts",
+                "languageId": "typescript",
+                "version": 1,
+                "text": "import { a, b } from './m';\nconsole.log(a);\n"
+            }}
+        });
+        server.handle_message(&open.to_string());
+
+        let request = r#"{"jsonrpc":"2.0","id":2,"method":"textDocument/diagnostic","params":{"textDocument":{"uri":"file:///a.ts"}}}"#;
+        let reply: Value = serde_json::from_str(&server.handle_message(request).unwrap()).unwrap();
+        let items = reply["result"]["items"].as_array().unwrap();
+        let unused = items.iter().find(|item| item["code"] == "unused-import").unwrap();
+        assert_eq!(
+            unused["data"]["fixes"],
+            json!([{
+                "title": "Remove unused import",
+                "range": {"start": {"line": 0, "character": 10}, "end": {"line": 0, "character": 13}},
+                "newText": ""
+            }])
+        );
+    }
+}
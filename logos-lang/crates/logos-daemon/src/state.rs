@@ -1,10 +1,18 @@
 //! Global state management for the language service
 
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::Arc;
-use logos_core::Document;
-use logos_index::{ProjectIndexer, SymbolIndex, TodoIndex};
+use logos_core::{CancellationToken, Diagnostic, Document, Symbol, SymbolKind};
+use logos_index::{AdapterRegistry, AnalysisResult, ExclusionPatterns, LanguageAdapter, ProjectIndexer, SymbolIndex, TodoIndex};
+use logos_semantic::IncludeOrder;
+
+use crate::types::ServiceStats;
+
+/// Default for `State::set_max_file_bytes`: above any hand-written source
+/// file, below the minified bundles that would stall the worker
+pub const DEFAULT_MAX_FILE_BYTES: usize = 5 * 1024 * 1024;
 
 /// Intelligence mode
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -29,6 +37,18 @@ pub struct State {
     pub symbol_index: SymbolIndex,
     /// TODO index
     pub todo_index: TodoIndex,
+    /// Adapter analysis of each open document
+    pub analyses: HashMap<String, AnalysisResult>,
+    /// Language adapters used to analyze open documents
+    pub adapters: AdapterRegistry,
+    /// Documents left out of workspace-wide queries
+    exclusions: ExclusionPatterns,
+    /// Parse and unused-symbol diagnostics per document, computed on first
+    /// request and dropped when the document changes
+    diagnostics: RefCell<HashMap<String, Vec<Diagnostic>>>,
+    /// Lookups in `diagnostics` that found an entry, and that did not
+    diagnostics_hits: Cell<u64>,
+    diagnostics_misses: Cell<u64>,
     /// Project indexer (Smart mode)
     pub project_indexer: Option<Arc<ProjectIndexer>>,
     /// Current intelligence mode
@@ -37,6 +57,16 @@ pub struct State {
     pub initialized: bool,
     /// Root path of the workspace
     pub root_path: Option<String>,
+    /// Raised by the host to stop the workspace-wide operation in flight
+    cancellation: CancellationToken,
+    /// Documents larger than this are stored but not parsed, indexed or
+    /// scanned for TODOs; `None` for no limit
+    max_file_bytes: Option<usize>,
+    /// Documents indexed whatever their size, see `force_index`
+    forced: HashSet<String>,
+    /// Grouping the includes of C/C++ documents are checked against; `None`
+    /// leaves them unchecked
+    include_order: Option<IncludeOrder>,
 }
 
 impl State {
@@ -45,13 +75,30 @@ impl State {
             documents: HashMap::new(),
             symbol_index: SymbolIndex::new(),
             todo_index: TodoIndex::new(),
+            analyses: HashMap::new(),
+            adapters: Self::default_adapters(),
+            exclusions: ExclusionPatterns::default(),
+            diagnostics: RefCell::new(HashMap::new()),
+            diagnostics_hits: Cell::new(0),
+            diagnostics_misses: Cell::new(0),
             project_indexer: None,
             mode: IntelligenceMode::Basic,
             initialized: false,
             root_path: None,
+            cancellation: CancellationToken::new(),
+            max_file_bytes: Some(DEFAULT_MAX_FILE_BYTES),
+            forced: HashSet::new(),
+            include_order: None,
         }
     }
 
+    /// The built-in adapters, with the brace-language adapter as fallback
+    fn default_adapters() -> AdapterRegistry {
+        let mut adapters = AdapterRegistry::with_builtins();
+        adapters.set_fallback(Some(Box::new(logos_index::BraceLanguageAdapter::new())));
+        adapters
+    }
+
     /// Switch to Smart mode and start indexing
     pub fn enable_smart_mode(&mut self) -> Result<(), String> {
         if self.mode == IntelligenceMode::Smart {
@@ -65,7 +112,10 @@ impl State {
             let root_path = PathBuf::from(root);
             if root_path.exists() {
                 log::info!("Starting Smart mode indexing for: {}", root);
-                match indexer.index_directory(&root_path) {
+                match indexer.index_directory_cancellable(&root_path, &self.cancellation) {
+                    Ok(stats) if stats.cancelled => {
+                        log::info!("Indexing cancelled after {} files", stats.files_indexed);
+                    }
                     Ok(stats) => {
                         log::info!(
                             "Indexed {} files, {} symbols, {} imports",
@@ -86,6 +136,13 @@ impl State {
         Ok(())
     }
 
+    /// The token the workspace-wide operations check (workspace dead code,
+    /// duplicate detection, batch indexing); its clones raise the same flag,
+    /// so the host can cancel from another thread
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
     /// Switch to Basic mode
     pub fn enable_basic_mode(&mut self) {
         self.project_indexer = None;
@@ -104,33 +161,141 @@ impl State {
 
     /// Open a document
     pub fn open_document(&mut self, uri: String, language_id: String, content: String) {
-        let doc = Document::new(uri.clone(), language_id, content.clone());
-        self.documents.insert(uri.clone(), doc);
-        // Index TODOs
-        self.todo_index.index_document(&uri, &content);
+        self.insert_document(uri.clone(), language_id, content);
+        self.analyze_document(&uri);
+        self.reindex_file(&uri);
+    }
+
+    /// Open many documents at once, analyzing them in parallel. Returns the
+    /// documents that failed to index with the reason.
+    pub fn open_documents(&mut self, documents: Vec<(String, String, String)>) -> Vec<(String, String)> {
+        let uris: Vec<String> = documents.iter().map(|(uri, _, _)| uri.clone()).collect();
+        for (uri, language_id, content) in documents {
+            self.insert_document(uri, language_id, content);
+        }
+
+        let mut errors = Vec::new();
+        let (mut analyses, failed) = self.analyze_parallel(&uris);
+        for uri in &uris {
+            self.analyses.remove(uri);
+            if self.is_skipped(uri) {
+                self.symbol_index.remove_document(uri);
+            }
+        }
+        for (uri, analysis) in &mut analyses {
+            self.symbol_index.apply_analysis(uri, analysis);
+        }
+        self.analyses.extend(analyses);
+        errors.extend(failed.into_iter().map(|uri| (uri, "Analysis failed".to_string())));
 
         // Re-index in Smart mode
         if let Some(ref indexer) = self.project_indexer {
-            if let Some(path) = uri_to_path(&uri) {
-                let _ = indexer.reindex_file(&path);
+            for uri in &uris {
+                if self.cancellation.is_cancelled() {
+                    break;
+                }
+                if self.is_skipped(uri) {
+                    continue;
+                }
+                if let Some(path) = uri_to_path(uri) {
+                    if let Err(e) = indexer.reindex_file(&path) {
+                        errors.push((uri.clone(), e));
+                    }
+                }
             }
         }
+
+        errors
     }
 
-    /// Update a document
-    pub fn update_document(&mut self, uri: &str, content: String) {
-        if let Some(doc) = self.documents.get_mut(uri) {
-            doc.set_content(content.clone());
+    /// Store a document and index its TODOs. An empty or unknown language id
+    /// is replaced by the language of the URI's extension.
+    fn insert_document(&mut self, uri: String, language_id: String, content: String) {
+        let language_id = match self.adapters.resolve_language(&language_id, &uri) {
+            Some(resolved) if resolved != language_id => resolved.to_string(),
+            _ => language_id,
+        };
+        self.diagnostics.get_mut().remove(&uri);
+        let doc = Document::new(uri.clone(), language_id, content);
+        self.documents.insert(uri.clone(), doc);
+        self.index_todos(&uri);
+    }
+
+    /// Scan an open document for TODOs, or drop its TODOs if it is skipped
+    fn index_todos(&mut self, uri: &str) {
+        if self.is_skipped(uri) {
+            self.todo_index.remove_document(uri);
+        } else if let Some(doc) = self.documents.get(uri) {
+            self.todo_index.index_document_with_language(uri, &doc.language_id, doc.content());
         }
-        // Re-index TODOs
-        self.todo_index.index_document(uri, &content);
+    }
 
-        // Re-index in Smart mode
-        if let Some(ref indexer) = self.project_indexer {
-            if let Some(path) = uri_to_path(uri) {
-                let _ = indexer.reindex_file(&path);
+    /// Re-index an open document's file in Smart mode, unless it is skipped
+    fn reindex_file(&self, uri: &str) {
+        if self.is_skipped(uri) {
+            return;
+        }
+        if let (Some(indexer), Some(path)) = (&self.project_indexer, uri_to_path(uri)) {
+            let _ = indexer.reindex_file(&path);
+        }
+    }
+
+    /// Analyze open documents on worker threads; returns the analyses and
+    /// the documents whose analysis panicked
+    fn analyze_parallel(&self, uris: &[String]) -> (Vec<(String, AnalysisResult)>, Vec<String>) {
+        let jobs: Vec<(&str, &dyn LanguageAdapter, &str)> = uris
+            .iter()
+            .filter(|uri| !self.is_skipped(uri))
+            .filter_map(|uri| {
+                let adapter = self.adapter_for(uri)?;
+                Some((uri.as_str(), adapter, self.documents.get(uri)?.content()))
+            })
+            .collect();
+        if jobs.is_empty() {
+            return (Vec::new(), Vec::new());
+        }
+
+        let threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        let chunk_size = jobs.len().div_ceil(threads);
+
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = jobs
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    let handle = scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|(uri, adapter, content)| (uri.to_string(), adapter.analyze(uri, content)))
+                            .collect::<Vec<_>>()
+                    });
+                    (chunk, handle)
+                })
+                .collect();
+
+            let mut analyses = Vec::new();
+            let mut failed = Vec::new();
+            for (chunk, handle) in handles {
+                match handle.join() {
+                    Ok(results) => analyses.extend(results),
+                    Err(_) => failed.extend(chunk.iter().map(|(uri, _, _)| uri.to_string())),
+                }
             }
+            (analyses, failed)
+        })
+    }
+
+    /// Update a document
+    pub fn update_document(&mut self, uri: &str, content: String) {
+        self.diagnostics.get_mut().remove(uri);
+        // Re-index TODOs
+        if let Some(doc) = self.documents.get_mut(uri) {
+            doc.set_content(content);
+            self.index_todos(uri);
+        } else {
+            self.todo_index.index_document(uri, &content);
         }
+        self.analyze_document(uri);
+        self.reindex_file(uri);
     }
 
     /// Close a document
@@ -138,6 +303,214 @@ impl State {
         self.documents.remove(uri);
         self.symbol_index.remove_document(uri);
         self.todo_index.remove_document(uri);
+        self.analyses.remove(uri);
+        self.diagnostics.get_mut().remove(uri);
+        self.forced.remove(uri);
+    }
+
+    /// Set the size above which documents are stored but not parsed, indexed
+    /// or scanned, and apply it to the open documents; `None` for no limit
+    pub fn set_max_file_bytes(&mut self, max_file_bytes: Option<usize>) {
+        self.max_file_bytes = max_file_bytes;
+        for uri in self.get_open_documents() {
+            self.diagnostics.get_mut().remove(&uri);
+            self.index_todos(&uri);
+            self.analyze_document(&uri);
+        }
+    }
+
+    /// Check the includes of C/C++ documents against `order`, or stop
+    /// checking them with `None`
+    pub fn set_include_order(&mut self, order: Option<IncludeOrder>) {
+        self.include_order = order;
+        self.diagnostics.get_mut().clear();
+    }
+
+    /// Whether an open document is over the size limit and not forced
+    pub fn is_skipped(&self, uri: &str) -> bool {
+        match (self.max_file_bytes, self.documents.get(uri)) {
+            (Some(max), Some(doc)) => doc.content().len() > max && !self.forced.contains(uri),
+            _ => false,
+        }
+    }
+
+    /// Parse, index and scan an open document whatever its size, now and
+    /// after later edits. Returns false if the document is not open.
+    pub fn force_index(&mut self, uri: &str) -> bool {
+        if !self.documents.contains_key(uri) {
+            return false;
+        }
+        self.forced.insert(uri.to_string());
+        self.diagnostics.get_mut().remove(uri);
+        self.index_todos(uri);
+        self.analyze_document(uri);
+        self.reindex_file(uri);
+        true
+    }
+
+    /// Run the matching language adapter over an open document
+    fn analyze_document(&mut self, uri: &str) {
+        let doc = match self.documents.get(uri) {
+            Some(doc) if !self.is_skipped(uri) => doc,
+            Some(_) => {
+                self.symbol_index.remove_document(uri);
+                self.analyses.remove(uri);
+                return;
+            }
+            None => return,
+        };
+
+        match self.adapter_for(uri) {
+            Some(adapter) => {
+                let mut result = adapter.analyze(uri, doc.content());
+                self.symbol_index.apply_analysis(uri, &mut result);
+                self.analyses.insert(uri.to_string(), result);
+            }
+            None => {
+                self.symbol_index.remove_document(uri);
+                self.analyses.remove(uri);
+            }
+        }
+    }
+
+    /// The language adapter for an open document: by language id, then by
+    /// extension, then the fallback adapter
+    pub fn adapter_for(&self, uri: &str) -> Option<&dyn LanguageAdapter> {
+        let doc = self.documents.get(uri)?;
+        self.adapters.for_document(&doc.language_id, uri)
+    }
+
+    /// Leave documents matching these globs out of workspace-wide TODO and
+    /// symbol queries
+    pub fn set_exclusion_patterns(&mut self, exclusions: ExclusionPatterns) {
+        self.todo_index.set_exclusions(exclusions.clone());
+        self.symbol_index.set_exclusions(exclusions.clone());
+        self.exclusions = exclusions;
+    }
+
+    /// Whether the document is left out of workspace-wide queries
+    pub fn is_excluded(&self, uri: &str) -> bool {
+        self.exclusions.is_excluded(uri)
+    }
+
+    /// The symbols of a document to check for unused ones. Uses the adapter
+    /// analysis when there is one, which also marks exported symbols on
+    /// `detector`.
+    pub fn unused_candidates(&self, uri: &str, detector: &mut logos_semantic::UnusedDetector) -> Vec<Symbol> {
+        match self.analyses.get(uri) {
+            Some(analysis) => {
+                let mut symbols: Vec<Symbol> = analysis
+                    .symbols
+                    .iter()
+                    .map(|s| {
+                        if s.exported {
+                            detector.mark_exported(&s.name);
+                        }
+                        Symbol {
+                            name: s.name.clone(),
+                            kind: s.kind,
+                            range: s.location.range,
+                            selection_range: s.location.selection_range,
+                            detail: None,
+                            children: Vec::new(),
+                        }
+                    })
+                    .collect();
+                // Each name an import binds is checked on its own, so one
+                // unused name in `from x import (a, b)` is reported by itself
+                let language_id = self.adapter_for(uri).map(|adapter| adapter.language_id().to_string()).unwrap_or_default();
+                for import in &analysis.imports {
+                    for (name, item) in import.bindings(&language_id) {
+                        detector.mark_import_statement(name, import.location);
+                        symbols.push(Symbol {
+                            name: name.to_string(),
+                            kind: SymbolKind::Module,
+                            range: item.range,
+                            selection_range: item.range,
+                            detail: None,
+                            children: Vec::new(),
+                        });
+                    }
+                }
+                symbols
+            }
+            None => self.symbol_index.get_document_symbols(uri)
+                .iter()
+                .map(|s| Symbol {
+                    name: s.name.clone(),
+                    kind: s.kind,
+                    range: s.range,
+                    selection_range: s.selection_range,
+                    detail: None,
+                    children: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Parse errors and unused-symbol hints for an open document, cached
+    /// until the document is updated or closed
+    pub fn document_diagnostics(&self, uri: &str) -> Vec<Diagnostic> {
+        // A cache already borrowed further up the stack is bypassed, not
+        // waited on: the diagnostics are then computed without caching
+        if let Some(cached) = self.diagnostics.try_borrow().ok().and_then(|cache| cache.get(uri).cloned()) {
+            self.diagnostics_hits.set(self.diagnostics_hits.get() + 1);
+            return cached;
+        }
+        let doc = match self.documents.get(uri) {
+            Some(doc) => doc,
+            None => return Vec::new(),
+        };
+        if self.is_skipped(uri) {
+            let message = format!(
+                "File is {} bytes, over the {} byte limit: parsing, indexing and TODO scanning are skipped",
+                doc.content().len(),
+                self.max_file_bytes.unwrap_or_default()
+            );
+            return vec![Diagnostic::info(logos_core::Range::default(), message)
+                .with_code("file-too-large".to_string())
+                .with_source("logos-daemon".to_string())];
+        }
+        self.diagnostics_misses.set(self.diagnostics_misses.get() + 1);
+
+        let mut diagnostics = Vec::new();
+        if let Some(language) = logos_parser::LanguageId::from_str(&doc.language_id) {
+            let mut parser = logos_parser::LanguageParser::new();
+            if parser.set_language(language).is_ok() {
+                if let Ok(tree) = parser.parse(doc.content(), None) {
+                    diagnostics.extend(logos_parser::extract_parse_errors(&tree, doc.content()));
+                }
+            }
+            if let (Some(order), Some(analysis)) = (&self.include_order, self.analyses.get(uri)) {
+                diagnostics.extend(logos_semantic::check_include_order(uri, doc.content(), language, &analysis.imports, order));
+            }
+        }
+
+        let mut detector = logos_semantic::UnusedDetector::new();
+        let symbols = self.unused_candidates(uri, &mut detector);
+        diagnostics.extend(detector.analyze(&symbols, doc.content()).iter().map(|item| item.to_diagnostic()));
+
+        if let Ok(mut cache) = self.diagnostics.try_borrow_mut() {
+            cache.insert(uri.to_string(), diagnostics.clone());
+        }
+        diagnostics
+    }
+
+    /// Counts and sizes for monitoring; cheap enough to poll
+    pub fn stats(&self) -> ServiceStats {
+        let text_bytes: usize = self.documents.values().map(|doc| doc.content().len()).sum();
+        let indexed_symbols = self.symbol_index.symbol_count();
+        let todo_entries: usize = self.documents.keys().map(|uri| self.todo_index.get_document_todos(uri).len()).sum();
+        ServiceStats {
+            open_documents: self.documents.len(),
+            indexed_symbols,
+            todos: self.todo_index.todo_count(),
+            approximate_memory_bytes: text_bytes
+                + indexed_symbols * std::mem::size_of::<logos_index::IndexedSymbol>()
+                + todo_entries * std::mem::size_of::<logos_index::TodoItem>(),
+            diagnostics_cache_hits: self.diagnostics_hits.get(),
+            diagnostics_cache_misses: self.diagnostics_misses.get(),
+        }
     }
 
     /// Get a document by URI
@@ -145,9 +518,12 @@ impl State {
         self.documents.get(uri)
     }
 
-    /// Get all open document URIs
+    /// Get all open document URIs, sorted; JSON objects keyed by them keep
+    /// this order
     pub fn get_open_documents(&self) -> Vec<String> {
-        self.documents.keys().cloned().collect()
+        let mut uris: Vec<String> = self.documents.keys().cloned().collect();
+        uris.sort();
+        uris
     }
 }
 
@@ -165,3 +541,425 @@ fn uri_to_path(uri: &str) -> Option<PathBuf> {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use logos_semantic::IncludeGroup;
+
+    fn documents() -> Vec<(String, String, String)> {
+        vec![
+            (
+                "file:///a.py".to_string(),
+                "python".to_string(),
+                "def greet(name):\n    # TODO: localize\n    return name\n".to_string(),
+            ),
+            (
+                "file:///b.rs".to_string(),
+                "rust".to_string(),
+                "// FIXME: handle errors\nfn parse() {}\nstruct Config {}\n".to_string(),
+            ),
+            (
+                "file:///c.txt".to_string(),
+                "plaintext".to_string(),
+                "TODO: not code\n".to_string(),
+            ),
+        ]
+    }
+
+    fn summary(state: &State) -> Vec<(String, Vec<String>, usize)> {
+        let mut uris = state.get_open_documents();
+        uris.sort();
+        uris.into_iter()
+            .map(|uri| {
+                let mut names: Vec<String> = state
+                    .analyses
+                    .get(&uri)
+                    .map(|a| a.symbols.iter().map(|s| s.name.clone()).collect())
+                    .unwrap_or_default();
+                names.sort();
+                let todos = state.todo_index.get_document_todos(&uri).len();
+                (uri, names, todos)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_open_documents_matches_sequential_opens() {
+        let mut sequential = State::new();
+        for (uri, language_id, content) in documents() {
+            sequential.open_document(uri, language_id, content);
+        }
+
+        let mut batch = State::new();
+        let errors = batch.open_documents(documents());
+        assert!(errors.is_empty());
+
+        let expected = summary(&sequential);
+        assert_eq!(summary(&batch), expected);
+        assert!(expected[0].1.contains(&"greet".to_string()));
+        assert!(expected[1].1.contains(&"Config".to_string()));
+        assert_eq!(batch.todo_index.todo_count(), sequential.todo_index.todo_count());
+    }
+
+    #[test]
+    fn test_edits_update_symbol_search() {
+        let mut state = State::new();
+        let uri = "file:///src/lib.rs";
+        state.open_document(uri.to_string(), "rust".to_string(), "fn parse_config() {}\nfn parse_args() {}\n".to_string());
+        state.update_document(uri, "fn load_config() {}\nfn parse_args() {}\n".to_string());
+
+        let search = |state: &State, query: &str| {
+            let params = serde_json::json!({ "query": query });
+            let result = crate::handlers::symbols::workspace_symbols(state, &params, None).result.unwrap();
+            let mut names: Vec<String> = result
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|s| s["name"].as_str().unwrap().to_string())
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(search(&state, "load"), vec!["load_config"]);
+        assert_eq!(search(&state, "parse"), vec!["parse_args"]);
+        assert!(search(&state, "parse_config").is_empty());
+
+        state.close_document(uri);
+        assert!(search(&state, "parse").is_empty());
+    }
+
+    #[test]
+    fn test_for_snippet_completion_by_language() {
+        let mut state = State::new();
+        state.open_document("file:///a.py".to_string(), "python".to_string(), "fo\n".to_string());
+        state.open_document("file:///a.js".to_string(), "javascript".to_string(), "fo\n".to_string());
+
+        let for_snippet = |uri: &str| {
+            let params = serde_json::json!({ "textDocument": { "uri": uri }, "position": { "line": 0, "character": 2 } });
+            let result = crate::handlers::completion::handle(&state, &params, None).result.unwrap();
+            let items = result["items"].as_array().unwrap().clone();
+            // The keyword completion stays next to the snippet
+            assert!(items.iter().any(|item| item["label"] == "for" && item["kind"] == 14));
+            items.into_iter().find(|item| item["label"] == "for" && item["kind"] == 15).unwrap()
+        };
+
+        let python = for_snippet("file:///a.py");
+        assert_eq!(python["insertTextFormat"], 2);
+        assert_eq!(python["insertText"], "for ${1:item} in ${2:items}:\n\t${0:pass}");
+
+        let javascript = for_snippet("file:///a.js");
+        assert_eq!(javascript["insertTextFormat"], 2);
+        assert_eq!(javascript["insertText"], "for (let ${1:i} = 0; ${1:i} < ${2:length}; ${1:i}++) {\n\t$0\n}");
+    }
+
+    #[test]
+    fn test_completions_deduplicated_and_sorted() {
+        let mut state = State::new();
+        state.open_document("file:///a.py".to_string(), "python".to_string(), "total = 0\nname = 'x'\nto\n".to_string());
+        state.open_document(
+            "file:///b.py".to_string(),
+            "python".to_string(),
+            "total = 1\n\ndef name():\n    inner = 2\n".to_string(),
+        );
+        state.open_document("file:///c.js".to_string(), "javascript".to_string(), "function other() {}\n".to_string());
+
+        let params = serde_json::json!({ "textDocument": { "uri": "file:///a.py" }, "position": { "line": 2, "character": 0 } });
+        let result = crate::handlers::completion::handle(&state, &params, None).result.unwrap();
+        let items = result["items"].as_array().unwrap();
+
+        let mut seen = std::collections::HashSet::new();
+        for item in items {
+            assert!(seen.insert((item["label"].clone(), item["kind"].clone())), "duplicate {}", item);
+        }
+        let sort_text = |label: &str, kind: u32| {
+            items
+                .iter()
+                .find(|item| item["label"] == label && item["kind"] == kind)
+                .map(|item| item["sortText"].as_str().unwrap().to_string())
+        };
+
+        // The local `total` replaces the workspace one
+        assert_eq!(sort_text("total", 6).as_deref(), Some("0_total"));
+        assert!(sort_text("name", 6).unwrap() < sort_text("name", 3).unwrap());
+        assert!(sort_text("name", 6).unwrap() < sort_text("for", 14).unwrap());
+        assert!(sort_text("for", 14).unwrap() < sort_text("name", 3).unwrap());
+        // Locals of other functions and symbols of other languages are left out
+        assert_eq!(sort_text("inner", 6), None);
+        assert_eq!(sort_text("other", 3), None);
+    }
+
+    #[test]
+    fn test_completions_filtered_by_prefix() {
+        let mut state = State::new();
+        let uri = "file:///app.js";
+        let content = "function getUser() {}\nfunction getName() {}\nfunction target() {}\nfunction forget() {}\nget\ngN\n\n";
+        state.open_document(uri.to_string(), "javascript".to_string(), content.to_string());
+        let complete = |state: &State, line: u32, character: u32| {
+            let params = serde_json::json!({ "textDocument": { "uri": uri }, "position": { "line": line, "character": character } });
+            crate::handlers::completion::handle(state, &params, None).result.unwrap()
+        };
+        let labels = |result: &serde_json::Value| -> Vec<String> {
+            result["items"].as_array().unwrap().iter().map(|item| item["label"].as_str().unwrap().to_string()).collect()
+        };
+
+        let filtered = labels(&complete(&state, 4, 3));
+        assert!(filtered.contains(&"getUser".to_string()) && filtered.contains(&"getName".to_string()));
+        assert!(filtered.iter().all(|label| label.to_lowercase().starts_with("get")), "{:?}", filtered);
+
+        // Along the starts of words
+        assert_eq!(labels(&complete(&state, 5, 2)), vec!["getName"]);
+
+        // Everything on an empty prefix
+        let all = labels(&complete(&state, 6, 0));
+        assert!(all.contains(&"target".to_string()) && all.contains(&"function".to_string()));
+
+        // Capped, and marked incomplete
+        let many: String = (0..150).map(|i| format!("function get{}() {{}}\n", i)).collect();
+        state.update_document(uri, format!("{}get", many));
+        let result = complete(&state, 150, 3);
+        assert_eq!(labels(&result).len(), 100);
+        assert_eq!(result["isIncomplete"], true);
+    }
+
+    #[test]
+    fn test_language_from_extension() {
+        let mut state = State::new();
+        for (uri, content) in [
+            ("file:///web/app.ts", "export function greet(name: string) {}\n"),
+            ("file:///tools/run.py", "def main():\n    pass\n"),
+            ("file:///lib/vector.cc", "int dot(int a, int b) { return a * b; }\n"),
+        ] {
+            state.open_document(uri.to_string(), String::new(), content.to_string());
+        }
+
+        assert_eq!(state.get_document("file:///web/app.ts").unwrap().language_id, "typescript");
+        assert_eq!(state.get_document("file:///tools/run.py").unwrap().language_id, "python");
+        assert_eq!(state.get_document("file:///lib/vector.cc").unwrap().language_id, "cpp");
+        let names: Vec<&str> = ["file:///web/app.ts", "file:///tools/run.py", "file:///lib/vector.cc"]
+            .iter()
+            .flat_map(|uri| state.symbol_index.get_document_symbols(uri))
+            .map(|symbol| symbol.name.as_str())
+            .collect();
+        assert_eq!(names, ["greet", "main", "dot"]);
+    }
+
+    #[test]
+    fn test_service_stats() {
+        let mut state = State::new();
+        assert_eq!(state.stats().open_documents, 0);
+
+        state.open_document(
+            "file:///a.py".to_string(),
+            "python".to_string(),
+            "# TODO: one
+def f():
+    pass
+
+class C:
+    pass
+".to_string(),
+        );
+        state.open_document(
+            "file:///b.rs".to_string(),
+            "rust".to_string(),
+            "// FIXME: two
+// TODO: three
+fn g() {}
+".to_string(),
+        );
+        state.document_diagnostics("file:///a.py");
+        state.document_diagnostics("file:///a.py");
+
+        let stats = state.stats();
+        assert_eq!(stats.open_documents, 2);
+        assert_eq!(stats.indexed_symbols, 3);
+        assert_eq!(stats.todos, 3);
+        assert!(stats.approximate_memory_bytes > 80);
+        assert_eq!((stats.diagnostics_cache_hits, stats.diagnostics_cache_misses), (1, 1));
+
+        let response = crate::handlers::mode::handle_get_service_stats(&state, &serde_json::Value::Null, None);
+        assert_eq!(response.result.unwrap()["indexedSymbols"], 3);
+    }
+
+    #[test]
+    fn test_oversized_documents_skipped_until_forced() {
+        let mut state = State::new();
+        state.set_max_file_bytes(Some(64));
+        let uri = "file:///bundle.rs";
+        let content = format!("// TODO: split\nfn helper() {{}}\n// {}\n", "x".repeat(64));
+        state.open_document(uri.to_string(), "rust".to_string(), content);
+
+        assert!(state.is_skipped(uri));
+        assert!(state.symbol_index.get_document_symbols(uri).is_empty());
+        assert!(state.todo_index.get_document_todos(uri).is_empty());
+        let diagnostics = state.document_diagnostics(uri);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code.as_deref(), Some("file-too-large"));
+
+        assert!(state.force_index(uri));
+        assert!(!state.is_skipped(uri));
+        assert_eq!(state.symbol_index.get_document_symbols(uri)[0].name, "helper");
+        assert_eq!(state.todo_index.get_document_todos(uri).len(), 1);
+        assert!(state.document_diagnostics(uri).iter().all(|d| d.code.as_deref() != Some("file-too-large")));
+        assert!(!state.force_index("file:///missing.rs"));
+    }
+
+    #[test]
+    fn test_include_order_checked_once_configured() {
+        let mut state = State::new();
+        let uri = "file:///src/widget.cpp";
+        let content = "#include <vector>\n#include \"widget.h\"\n\nint main() {}\n";
+        state.open_document(uri.to_string(), "cpp".to_string(), content.to_string());
+        let include_hints = |state: &State| {
+            state
+                .document_diagnostics(uri)
+                .into_iter()
+                .filter(|d| d.code.as_deref() == Some("include-order"))
+                .count()
+        };
+        assert_eq!(include_hints(&state), 0);
+
+        state.set_include_order(Some(IncludeOrder::default()));
+        assert_eq!(include_hints(&state), 1);
+
+        state.set_include_order(Some(IncludeOrder(vec![IncludeGroup::System])));
+        assert_eq!(include_hints(&state), 0);
+    }
+
+    #[test]
+    fn test_workspace_diagnostics_aggregate() {
+        let mut state = State::new();
+        state.open_document("file:///src/broken.py".to_string(), "python".to_string(), "def greet(:\n    return 1\n".to_string());
+        state.open_document(
+            "file:///src/lib.rs".to_string(),
+            "rust".to_string(),
+            "// TODO: document\nfn helper() {}\npub fn run() {}\n".to_string(),
+        );
+        state.open_document("file:///vendor/dep.rs".to_string(), "rust".to_string(), "fn unused() {}\n".to_string());
+        state.set_exclusion_patterns(ExclusionPatterns::new(["vendor/"]));
+
+        let params = serde_json::json!({ "includeTodos": true });
+        let response = crate::handlers::diagnostics::get_workspace_diagnostics(&state, &params, None);
+        let result = response.result.unwrap();
+        let files = result.as_object().unwrap();
+        assert_eq!(files.keys().collect::<Vec<_>>(), vec!["file:///src/broken.py", "file:///src/lib.rs"]);
+
+        let broken = files["file:///src/broken.py"].as_array().unwrap();
+        assert!(!broken.is_empty());
+        assert!(broken.iter().all(|m| m["severity"] == 1 && m["source"] == "logos-parser"));
+
+        let lib = files["file:///src/lib.rs"].as_array().unwrap();
+        let codes: Vec<&str> = lib.iter().map(|m| m["code"].as_str().unwrap()).collect();
+        assert_eq!(codes, vec!["unused-function", "todo"]);
+        assert!(lib[0]["message"].as_str().unwrap().contains("helper"));
+        assert_eq!(lib[0]["range"]["start"]["line"], 1);
+
+        // Cached until the document changes
+        assert!(state.diagnostics.borrow().contains_key("file:///src/broken.py"));
+        state.update_document("file:///src/broken.py", "def greet():\n    return 1\n".to_string());
+        assert!(!state.diagnostics.borrow().contains_key("file:///src/broken.py"));
+        assert!(state.document_diagnostics("file:///src/broken.py").iter().all(|d| d.source.as_deref() != Some("logos-parser")));
+    }
+
+    #[test]
+    fn test_partially_unused_grouped_imports() {
+        let unused_imports = |uri: &str, language_id: &str, content: &str| {
+            let mut state = State::new();
+            state.open_document(uri.to_string(), language_id.to_string(), content.to_string());
+            state
+                .document_diagnostics(uri)
+                .into_iter()
+                .filter(|d| d.message.contains("import"))
+                .map(|d| (d.range.start.line, d.range.start.column, d.range.end.column))
+                .collect::<Vec<_>>()
+        };
+
+        let python = "from os.path import (\n    join,\n    exists,\n    basename as base,\n)\nprint(join('a', 'b'), base('c'))\n";
+        assert_eq!(unused_imports("file:///a.py", "python", python), vec![(2, 4, 10)]);
+
+        let typescript = "import { readFile, writeFile as write } from 'fs';\nexport const load = () => readFile('a');\n";
+        assert_eq!(unused_imports("file:///a.ts", "typescript", typescript), vec![(0, 19, 37)]);
+
+        let go = "package main\n\nimport (\n\t\"fmt\"\n\t\"net/http\"\n\t_ \"embed\"\n)\n\nfunc main() { fmt.Println() }\n";
+        assert_eq!(unused_imports("file:///main.go", "go", go), vec![(4, 1, 11)]);
+    }
+
+    #[test]
+    fn test_registered_adapter_provides_document_symbols() {
+        struct RecipeAdapter;
+
+        impl LanguageAdapter for RecipeAdapter {
+            fn language_id(&self) -> &str {
+                "recipe"
+            }
+
+            fn file_extensions(&self) -> &[&str] {
+                &["recipe"]
+            }
+
+            fn keywords(&self) -> &[&str] {
+                &["step"]
+            }
+
+            fn analyze(&self, uri: &str, source: &str) -> AnalysisResult {
+                let mut result = AnalysisResult::default();
+                for (line, text) in source.lines().enumerate() {
+                    if let Some(name) = text.strip_prefix("step ") {
+                        let range = logos_core::Range::from_coords(line as u32, 5, line as u32, text.len() as u32);
+                        let location = logos_index::make_location(uri, range, range);
+                        result.symbols.push(
+                            logos_index::SymbolBuilder::new(name, logos_core::SymbolKind::Function, location).build(),
+                        );
+                    }
+                }
+                result
+            }
+        }
+
+        let mut state = State::new();
+        state.adapters.register(Box::new(RecipeAdapter));
+        state.open_document(
+            "file:///cake.recipe".to_string(),
+            "recipe".to_string(),
+            "step mix\nstep bake\n".to_string(),
+        );
+
+        let params = serde_json::json!({ "textDocument": { "uri": "file:///cake.recipe" } });
+        let response = crate::handlers::symbols::document_symbols(&state, &params, None);
+        let result = response.result.unwrap();
+        let names: Vec<&str> = result.as_array().unwrap().iter().map(|s| s["name"].as_str().unwrap()).collect();
+        assert_eq!(names, vec!["mix", "bake"]);
+        assert_eq!(result[1]["selectionRange"]["start"], serde_json::json!({ "line": 1, "character": 5 }));
+
+        let params = serde_json::json!({
+            "textDocument": { "uri": "file:///cake.recipe" },
+            "position": { "line": 0, "character": 0 }
+        });
+        let response = crate::handlers::completion::handle(&state, &params, None);
+        assert_eq!(response.result.unwrap()["items"][0]["label"], "step");
+    }
+
+    #[test]
+    fn test_document_diagnostics_under_cache_contention() {
+        let mut state = State::new();
+        let uri = "file:///src/lib.rs";
+        state.open_document(uri.to_string(), "rust".to_string(), "fn helper() {}\nfn broken( {}\n".to_string());
+
+        // Re-entered while the cache is being written
+        let expected = {
+            let _writer = state.diagnostics.borrow_mut();
+            state.document_diagnostics(uri)
+        };
+        assert!(!expected.is_empty());
+        assert!(state.diagnostics.borrow().is_empty());
+
+        // Re-entered while the cache is being read
+        {
+            let _reader = state.diagnostics.borrow();
+            assert_eq!(state.document_diagnostics(uri).len(), expected.len());
+        }
+        assert_eq!(state.document_diagnostics(uri).len(), expected.len());
+        assert!(state.diagnostics.borrow().contains_key(uri));
+    }
+}
@@ -0,0 +1,274 @@
+//! Typed results
+//!
+//! Results the handlers serialize instead of building JSON by hand, so the
+//! shapes stay in step with the Rust side and client types can be generated
+//! from them. Field names are camelCase on the wire.
+
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::{Position, Range};
+
+impl From<logos_core::Position> for Position {
+    fn from(position: logos_core::Position) -> Self {
+        Self {
+            line: position.line,
+            character: position.column,
+        }
+    }
+}
+
+impl From<logos_core::Range> for Range {
+    fn from(range: logos_core::Range) -> Self {
+        Self {
+            start: range.start.into(),
+            end: range.end.into(),
+        }
+    }
+}
+
+/// Result of textDocument/completion
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionList {
+    pub is_incomplete: bool,
+    pub items: Vec<CompletionItem>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionItem {
+    pub label: String,
+    /// LSP `CompletionItemKind`
+    pub kind: u32,
+    pub detail: Option<String>,
+    /// Text inserted instead of the label
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub insert_text: Option<String>,
+    /// LSP `InsertTextFormat`: 2 when `insert_text` is a snippet
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub insert_text_format: Option<u32>,
+    /// Key the client orders items by, instead of the label
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort_text: Option<String>,
+}
+
+/// An entry of the result of textDocument/inlayHint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlayHint {
+    pub position: Position,
+    pub label: String,
+    /// LSP `InlayHintKind`: 1 type, 2 parameter
+    pub kind: u32,
+}
+
+/// Result of textDocument/hover
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HoverResult {
+    pub contents: MarkupContent,
+    pub range: Range,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarkupContent {
+    /// `markdown` or `plaintext`
+    pub kind: String,
+    pub value: String,
+}
+
+impl MarkupContent {
+    pub fn markdown(value: String) -> Self {
+        Self {
+            kind: "markdown".to_string(),
+            value,
+        }
+    }
+}
+
+/// Result of textDocument/definition
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DefinitionResult {
+    pub uri: String,
+    pub range: Range,
+}
+
+/// An entry of the result of logos/getDocumentLinks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentLinkResult {
+    pub range: Range,
+    pub target: String,
+}
+
+impl From<&logos_index::links::DocumentLink> for DocumentLinkResult {
+    fn from(link: &logos_index::links::DocumentLink) -> Self {
+        Self {
+            range: link.range.into(),
+            target: link.target.clone(),
+        }
+    }
+}
+
+/// A diagnostic as reported by textDocument/diagnostic and
+/// logos/getWorkspaceDiagnostics
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticMarker {
+    pub range: Range,
+    /// LSP `DiagnosticSeverity`: 1 error, 2 warning, 3 information, 4 hint
+    pub severity: u32,
+    pub code: Option<String>,
+    pub source: Option<String>,
+    pub message: String,
+    /// What the client needs for the diagnostic's code actions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<DiagnosticData>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticData {
+    pub fixes: Vec<QuickFix>,
+}
+
+/// A code action resolving a diagnostic with one edit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuickFix {
+    pub title: String,
+    pub range: Range,
+    pub new_text: String,
+}
+
+impl From<&logos_core::Diagnostic> for DiagnosticMarker {
+    fn from(diagnostic: &logos_core::Diagnostic) -> Self {
+        let fixes: Vec<QuickFix> = diagnostic
+            .fixes
+            .iter()
+            .map(|fix| QuickFix {
+                title: fix.title.clone(),
+                range: fix.range.into(),
+                new_text: fix.new_text.clone(),
+            })
+            .collect();
+        Self {
+            range: diagnostic.range.into(),
+            severity: diagnostic.severity as u32,
+            code: diagnostic.code.clone(),
+            source: diagnostic.source.clone(),
+            message: diagnostic.message.clone(),
+            data: (!fixes.is_empty()).then_some(DiagnosticData { fixes }),
+        }
+    }
+}
+
+/// Result of logos/getServiceStats
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceStats {
+    pub open_documents: usize,
+    pub indexed_symbols: usize,
+    /// TODOs in the documents not excluded from workspace queries
+    pub todos: usize,
+    /// Document text plus the fixed size of each index entry, in bytes;
+    /// heap data the entries point to is not counted
+    pub approximate_memory_bytes: usize,
+    /// Diagnostics requests answered from the per-document cache
+    pub diagnostics_cache_hits: u64,
+    /// Diagnostics requests that had to compute them
+    pub diagnostics_cache_misses: u64,
+}
+
+/// Serialize a result for a response
+pub fn to_value<T: Serialize>(result: &T) -> serde_json::Value {
+    // Plain structs of strings and numbers always serialize
+    serde_json::to_value(result).unwrap_or(serde_json::Value::Null)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::DeserializeOwned;
+    use serde_json::{json, Value};
+
+    /// Serialize `result`, check its keys and that it reads back unchanged
+    fn round_trip<T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug>(result: &T, keys: &[&str]) -> Value {
+        let value = to_value(result);
+        let mut actual: Vec<&str> = value.as_object().unwrap().keys().map(|k| k.as_str()).collect();
+        actual.sort();
+        let mut expected = keys.to_vec();
+        expected.sort();
+        assert_eq!(actual, expected);
+        assert_eq!(&serde_json::from_value::<T>(value.clone()).unwrap(), result);
+        value
+    }
+
+    fn range() -> Range {
+        logos_core::Range::from_coords(1, 2, 3, 4).into()
+    }
+
+    #[test]
+    fn test_completion_round_trip() {
+        let list = CompletionList {
+            is_incomplete: false,
+            items: vec![CompletionItem {
+                label: "fn".to_string(),
+                kind: 14,
+                detail: Some("keyword".to_string()),
+                insert_text: None,
+                insert_text_format: None,
+                sort_text: None,
+            }],
+        };
+        let value = round_trip(&list, &["isIncomplete", "items"]);
+        round_trip(&list.items[0], &["label", "kind", "detail"]);
+        assert_eq!(value["items"][0]["kind"], 14);
+
+        let snippet = CompletionItem {
+            label: "if".to_string(),
+            kind: 15,
+            detail: Some("if statement".to_string()),
+            insert_text: Some("if ${1:condition}:\n\t${0:pass}".to_string()),
+            insert_text_format: Some(2),
+            sort_text: Some("1_if".to_string()),
+        };
+        round_trip(&snippet, &["label", "kind", "detail", "insertText", "insertTextFormat", "sortText"]);
+    }
+
+    #[test]
+    fn test_hover_and_definition_round_trip() {
+        let hover = HoverResult {
+            contents: MarkupContent::markdown("**run**".to_string()),
+            range: range(),
+        };
+        let value = round_trip(&hover, &["contents", "range"]);
+        assert_eq!(value["contents"], json!({ "kind": "markdown", "value": "**run**" }));
+        assert_eq!(value["range"], json!({ "start": { "line": 1, "character": 2 }, "end": { "line": 3, "character": 4 } }));
+
+        let definition = DefinitionResult {
+            uri: "file:///a.rs".to_string(),
+            range: range(),
+        };
+        round_trip(&definition, &["uri", "range"]);
+
+        let link = DocumentLinkResult {
+            range: range(),
+            target: "file:///util.h".to_string(),
+        };
+        round_trip(&link, &["range", "target"]);
+    }
+
+    #[test]
+    fn test_diagnostic_marker_round_trip() {
+        let diagnostic = logos_core::Diagnostic::warning(logos_core::Range::from_coords(1, 2, 3, 4), "unused".to_string())
+            .with_code("unused-variable".to_string());
+        let marker = DiagnosticMarker::from(&diagnostic);
+        let value = round_trip(&marker, &["range", "severity", "code", "source", "message"]);
+        assert_eq!(value["severity"], 2);
+        assert_eq!(value["code"], "unused-variable");
+        assert_eq!(value["source"], Value::Null);
+    }
+}
@@ -3,10 +3,12 @@
 //! Defines the interface for language-specific symbol extraction and analysis.
 //! Each language implements this trait to provide Smart Mode indexing.
 
+use crate::semantic_tokens::SemanticToken;
 use crate::symbol_table::{
     SmartSymbol, SymbolId, SymbolLocation, SymbolReference, TypeInfo, Visibility,
 };
-use logos_core::{Range, SymbolKind};
+use logos_core::{Range, Symbol, SymbolKind};
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
 /// Import information extracted from source
@@ -31,6 +33,40 @@ pub struct ImportItem {
     pub alias: Option<String>,
     /// Whether this is a type import
     pub is_type: bool,
+    /// The item within the import statement, e.g. `foo as bar` in
+    /// `import { baz, foo as bar }`
+    pub range: Range,
+}
+
+impl ImportInfo {
+    /// The names the import binds in the importing file, each with its item.
+    /// Only Python, Go and ES module imports bind one name per item; other
+    /// languages, wildcards and side-effect imports (`_` and `.` in Go) give
+    /// none.
+    pub fn bindings(&self, language_id: &str) -> Vec<(&str, &ImportItem)> {
+        self.items
+            .iter()
+            .filter_map(|item| {
+                let name = match item.alias.as_deref() {
+                    Some("_" | ".") => return None,
+                    Some(alias) => alias,
+                    None if item.name == "*" => return None,
+                    None => match language_id {
+                        // `import os.path` binds `os`
+                        "python" if item.name == self.module_path => item.name.split('.').next()?,
+                        "python" | "typescript" | "javascript" => &item.name,
+                        // A package is named by the last element of its path
+                        "go" => item.name.rsplit('/').next()?,
+                        _ => return None,
+                    },
+                };
+                Some((name, item))
+            })
+            // Paths whose last element isn't an identifier (`yaml.v2`) don't
+            // tell the package name
+            .filter(|(name, _)| name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$'))
+            .collect()
+    }
 }
 
 /// Export information extracted from source
@@ -93,6 +129,89 @@ pub struct AnalysisResult {
     pub references: Vec<SymbolReference>,
 }
 
+impl AnalysisResult {
+    /// The symbols as a tree of `Symbol`s, children nested under their parent
+    pub fn document_symbols(&self) -> Vec<Symbol> {
+        let ids: HashSet<SymbolId> = self.symbols.iter().map(|s| s.id).collect();
+        let mut by_parent: HashMap<Option<SymbolId>, Vec<&SmartSymbol>> = HashMap::new();
+        for symbol in &self.symbols {
+            let parent = symbol.parent.filter(|p| ids.contains(p));
+            by_parent.entry(parent).or_default().push(symbol);
+        }
+
+        fn build(by_parent: &HashMap<Option<SymbolId>, Vec<&SmartSymbol>>, parent: Option<SymbolId>) -> Vec<Symbol> {
+            by_parent
+                .get(&parent)
+                .map(|symbols| {
+                    symbols
+                        .iter()
+                        .map(|s| Symbol {
+                            name: s.name.clone(),
+                            kind: s.kind,
+                            range: s.location.range,
+                            selection_range: s.location.selection_range,
+                            detail: None,
+                            children: build(by_parent, Some(s.id)),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default()
+        }
+        build(&by_parent, None)
+    }
+
+    /// The symbols in the order of `document_symbols`, each with its parent
+    /// and its qualified name: the adapter's, or else the names of its
+    /// parents and its own joined with `.`
+    pub(crate) fn symbols_in_tree_order(&self) -> Vec<(&SmartSymbol, Option<&SmartSymbol>, String)> {
+        let by_id: HashMap<SymbolId, &SmartSymbol> = self.symbols.iter().map(|s| (s.id, s)).collect();
+        let mut by_parent: HashMap<Option<SymbolId>, Vec<&SmartSymbol>> = HashMap::new();
+        for symbol in &self.symbols {
+            let parent = symbol.parent.filter(|p| by_id.contains_key(p));
+            by_parent.entry(parent).or_default().push(symbol);
+        }
+
+        let mut ordered = Vec::with_capacity(self.symbols.len());
+        let mut stack: Vec<(&SmartSymbol, Option<&SmartSymbol>, String)> = by_parent
+            .get(&None)
+            .map(|roots| roots.iter().rev().map(|s| (*s, None, String::new())).collect())
+            .unwrap_or_default();
+        while let Some((symbol, parent, prefix)) = stack.pop() {
+            let qualified = if !symbol.qualified_name.is_empty() {
+                symbol.qualified_name.clone()
+            } else if prefix.is_empty() {
+                symbol.name.clone()
+            } else {
+                format!("{}.{}", prefix, symbol.name)
+            };
+            if let Some(children) = by_parent.get(&Some(symbol.id)) {
+                stack.extend(children.iter().rev().map(|child| (*child, Some(symbol), qualified.clone())));
+            }
+            ordered.push((symbol, parent, qualified));
+        }
+        ordered
+    }
+
+    /// Rewrite the symbol ids of the result through `ids`; ids not in it stay
+    pub(crate) fn remap_ids(&mut self, ids: &HashMap<SymbolId, SymbolId>) {
+        let remap = |id: &mut SymbolId| {
+            if let Some(kept) = ids.get(id) {
+                *id = *kept;
+            }
+        };
+        for symbol in &mut self.symbols {
+            remap(&mut symbol.id);
+            if let Some(parent) = &mut symbol.parent {
+                remap(parent);
+            }
+            symbol.children.iter_mut().for_each(remap);
+        }
+        for reference in &mut self.references {
+            remap(&mut reference.symbol_id);
+        }
+    }
+}
+
 /// Language adapter trait for Smart Mode indexing
 pub trait LanguageAdapter: Send + Sync {
     /// Returns the language identifier (e.g., "typescript", "rust")
@@ -110,9 +229,20 @@ pub trait LanguageAdapter: Send + Sync {
         }
     }
 
+    /// Keywords offered as completions; none by default
+    fn keywords(&self) -> &[&str] {
+        &[]
+    }
+
     /// Analyze a source file and extract symbols, imports, exports, calls, etc.
     fn analyze(&self, uri: &str, source: &str) -> AnalysisResult;
 
+    /// Classify the identifiers of a source file for semantic highlighting.
+    /// Adapters without a classifier return no tokens.
+    fn semantic_tokens(&self, _source: &str, _analysis: &AnalysisResult) -> Vec<SemanticToken> {
+        Vec::new()
+    }
+
     /// Resolve an import path to an absolute file path
     fn resolve_import(&self, from_file: &Path, import_path: &str) -> Option<std::path::PathBuf> {
         // Default implementation for relative imports
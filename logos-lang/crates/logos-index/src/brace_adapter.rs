@@ -0,0 +1,225 @@
+//! Brace Language Fallback Adapter
+//!
+//! Best-effort indexer for curly-brace languages without a dedicated adapter
+//! (Kotlin, C#, Swift, Scala, ...). There is no parser behind it: top-level
+//! declarations are found with a keyword regex and their extent by brace
+//! matching, skipping strings and comments.
+//! - Symbols: top-level function/class/struct-like declarations with a body
+//! - Imports, calls, type relations: none
+
+use crate::adapter::{AnalysisResult, LanguageAdapter, SymbolBuilder, make_location};
+use crate::symbol_table::Visibility;
+use logos_core::{Range, SymbolKind};
+use regex::Regex;
+
+pub struct BraceLanguageAdapter {
+    declaration: Regex,
+}
+
+impl BraceLanguageAdapter {
+    pub fn new() -> Self {
+        let declaration = Regex::new(
+            r"^\s*((?:(?:public|private|protected|internal|export|static|abstract|final|open|sealed|data|async|pub|inline|override)\s+)*)(function|fun|func|fn|def|class|object|record|struct|interface|trait|protocol|enum|module|namespace)\s+([A-Za-z_][A-Za-z0-9_]*)",
+        )
+        .unwrap();
+        Self { declaration }
+    }
+}
+
+impl Default for BraceLanguageAdapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LanguageAdapter for BraceLanguageAdapter {
+    fn language_id(&self) -> &str {
+        "brace"
+    }
+
+    /// None: the adapter is only used as a registered fallback
+    fn file_extensions(&self) -> &[&str] {
+        &[]
+    }
+
+    fn analyze(&self, uri: &str, source: &str) -> AnalysisResult {
+        let mut result = AnalysisResult::default();
+        let lines = code_lines(source);
+
+        let mut depth = 0i32;
+        for (line_no, code) in lines.iter().enumerate() {
+            if depth == 0 {
+                if let Some(caps) = self.declaration.captures(code) {
+                    let name = caps.get(3).unwrap();
+                    // Declarations without a body are not indexed
+                    if let Some(end) = body_end(&lines, line_no, name.end()) {
+                        let kind = declaration_kind(&caps[2]);
+                        let private = caps[1].split_whitespace().any(|m| m == "private");
+                        let selection = Range::from_coords(
+                            line_no as u32,
+                            name.start() as u32,
+                            line_no as u32,
+                            name.end() as u32,
+                        );
+                        let range = Range::from_coords(line_no as u32, 0, end.0 as u32, end.1 as u32);
+
+                        result.symbols.push(
+                            SymbolBuilder::new(name.as_str(), kind, make_location(uri, range, selection))
+                                .exported(!private)
+                                .visibility(if private { Visibility::Private } else { Visibility::Public })
+                                .qualified_name(name.as_str())
+                                .build(),
+                        );
+                    }
+                }
+            }
+            depth += brace_delta(code);
+        }
+
+        result
+    }
+}
+
+fn declaration_kind(keyword: &str) -> SymbolKind {
+    match keyword {
+        "class" | "object" | "record" => SymbolKind::Class,
+        "struct" => SymbolKind::Struct,
+        "interface" | "trait" | "protocol" => SymbolKind::Interface,
+        "enum" => SymbolKind::Enum,
+        "module" | "namespace" => SymbolKind::Namespace,
+        _ => SymbolKind::Function,
+    }
+}
+
+/// The source lines with strings and comments blanked out (byte columns kept)
+fn code_lines(source: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut in_block_comment = false;
+
+    for line in source.lines() {
+        let mut code = String::with_capacity(line.len());
+        let mut in_string: Option<char> = None;
+        let mut chars = line.char_indices().peekable();
+
+        while let Some((i, ch)) = chars.next() {
+            let blank = " ".repeat(ch.len_utf8());
+            if in_block_comment {
+                if line[i..].starts_with("*/") {
+                    in_block_comment = false;
+                    chars.next();
+                    code.push_str("  ");
+                } else {
+                    code.push_str(&blank);
+                }
+            } else if let Some(quote) = in_string {
+                if ch == '\\' {
+                    chars.next();
+                    code.push_str("  ");
+                    continue;
+                }
+                if ch == quote {
+                    in_string = None;
+                }
+                code.push_str(&blank);
+            } else if line[i..].starts_with("//") {
+                code.push_str(&" ".repeat(line.len() - i));
+                break;
+            } else if line[i..].starts_with("/*") {
+                in_block_comment = true;
+                chars.next();
+                code.push_str("  ");
+            } else if matches!(ch, '"' | '\'' | '`') {
+                in_string = Some(ch);
+                code.push_str(&blank);
+            } else {
+                code.push(ch);
+            }
+        }
+        lines.push(code);
+    }
+    lines
+}
+
+fn brace_delta(code: &str) -> i32 {
+    code.chars().fold(0, |d, c| match c {
+        '{' => d + 1,
+        '}' => d - 1,
+        _ => d,
+    })
+}
+
+/// End (line, column past the closing brace) of the body that opens after
+/// column `from` of `start`, within the declaration's first two lines
+fn body_end(lines: &[String], start: usize, from: usize) -> Option<(usize, usize)> {
+    let mut depth = 0i32;
+    let mut opened = false;
+
+    for (line_no, code) in lines.iter().enumerate().skip(start) {
+        let offset = if line_no == start { from } else { 0 };
+        for (i, ch) in code[offset..].char_indices() {
+            match ch {
+                '{' => {
+                    depth += 1;
+                    opened = true;
+                }
+                '}' if opened => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some((line_no, offset + i + 1));
+                    }
+                }
+                // `fun f(): Int;` declares without a body
+                ';' if !opened => return None,
+                _ => {}
+            }
+        }
+        if !opened && line_no > start {
+            return None;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_made_up_language_symbols() {
+        let source = r#"
+// class Commented { }
+public class Greeter {
+    fun greet(name: String) {
+        print("}{ class Inner {")
+    }
+}
+
+private fun helper(x: Int): Int
+{
+    return x * 2
+}
+
+abstract fun declared(): Int;
+struct Point { x: Int, y: Int }
+"#;
+        let adapter = BraceLanguageAdapter::new();
+        let result = adapter.analyze("file:///test.made", source);
+        let names: Vec<(&str, SymbolKind)> = result.symbols.iter().map(|s| (s.name.as_str(), s.kind)).collect();
+        assert_eq!(
+            names,
+            vec![
+                ("Greeter", SymbolKind::Class),
+                ("helper", SymbolKind::Function),
+                ("Point", SymbolKind::Struct),
+            ]
+        );
+
+        let greeter = &result.symbols[0];
+        assert_eq!(greeter.location.range, Range::from_coords(2, 0, 6, 1));
+        assert_eq!(greeter.location.selection_range, Range::from_coords(2, 13, 2, 20));
+
+        let helper = &result.symbols[1];
+        assert_eq!(helper.visibility, Visibility::Private);
+        assert_eq!(helper.location.range.end.line, 11);
+    }
+}
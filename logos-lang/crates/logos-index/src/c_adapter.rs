@@ -28,7 +28,7 @@ impl CAdapter {
     }
 
     fn parse(&self, source: &str) -> Option<Tree> {
-        let mut parser = self.parser.lock().ok()?;
+        let mut parser = self.parser.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
         parser.parse(source, None)
     }
 }
@@ -42,6 +42,10 @@ impl LanguageAdapter for CAdapter {
         &["c", "h"]
     }
 
+    fn keywords(&self) -> &[&str] {
+        logos_parser::c::get_keywords()
+    }
+
     fn analyze(&self, uri: &str, source: &str) -> AnalysisResult {
         let tree = match self.parse(source) {
             Some(t) => t,
@@ -114,6 +118,7 @@ fn analyze_include(node: &Node, ctx: &mut AnalysisContext) {
                     name: rest.to_string(),
                     alias: None,
                     is_type: false,
+                    range: node_to_range(node),
                 }],
                 is_type_only: false,
                 location: node_to_range(node),
@@ -0,0 +1,253 @@
+//! Call hierarchy over per-document analysis results
+//!
+//! Resolves the `CallInfo` collected by the language adapters against the
+//! symbols of the analyzed documents by name, narrowing by qualifier when the
+//! call site spells one out.
+
+use crate::adapter::{AnalysisResult, CallInfo};
+use crate::symbol_table::SmartSymbol;
+use logos_core::{Position, Range, SymbolKind};
+use std::collections::HashMap;
+
+/// A caller of the target symbol together with its call sites
+#[derive(Debug)]
+pub struct IncomingCall<'a> {
+    /// The function or method containing the calls
+    pub from: &'a SmartSymbol,
+    /// Ranges of the call expressions inside `from`
+    pub from_ranges: Vec<Range>,
+}
+
+/// A call made from inside the source symbol
+#[derive(Debug)]
+pub struct OutgoingCall<'a> {
+    /// Name of the callee as written at the call site (without qualifier)
+    pub callee_name: String,
+    /// The resolved callee, if it is defined in an analyzed document
+    pub to: Option<&'a SmartSymbol>,
+    /// Ranges of the call expressions
+    pub from_ranges: Vec<Range>,
+}
+
+/// Whether a symbol kind can contain calls
+pub fn is_callable_kind(kind: SymbolKind) -> bool {
+    matches!(
+        kind,
+        SymbolKind::Function | SymbolKind::Method | SymbolKind::Constructor
+    )
+}
+
+/// Strip the receiver or path qualifier from a callee: `demo::User::greet` -> `greet`
+pub fn callee_base_name(callee: &str) -> &str {
+    let callee = callee.split('<').next().unwrap_or(callee);
+    callee
+        .rsplit(['.', ':', '>'])
+        .next()
+        .unwrap_or(callee)
+        .trim()
+}
+
+/// Find the callable symbol under the cursor: one whose name contains the
+/// position, or else the innermost one whose body does
+pub fn callable_at<'a>(
+    analyses: &'a HashMap<String, AnalysisResult>,
+    uri: &str,
+    position: Position,
+) -> Option<&'a SmartSymbol> {
+    let result = analyses.get(uri)?;
+    let callables = || result.symbols.iter().filter(|s| is_callable_kind(s.kind));
+
+    callables()
+        .find(|s| s.location.selection_range.contains(position))
+        .or_else(|| innermost(callables().filter(|s| s.location.range.contains(position))))
+}
+
+/// Whether a call site refers to `target`
+pub fn call_matches(call: &CallInfo, target: &SmartSymbol) -> bool {
+    if callee_base_name(&call.callee_name) != target.name {
+        return false;
+    }
+
+    // A `::` path must agree with the target's qualified name; member calls
+    // (`obj.m()`, `ptr->m()`) can't be narrowed without type information
+    match call.qualified_name.as_deref() {
+        Some(path) if path.contains("::") && target.qualified_name.contains("::") => {
+            path.ends_with(&target.qualified_name) || target.qualified_name.ends_with(path)
+        }
+        _ => true,
+    }
+}
+
+/// Find all calls to `target` across the analyzed documents, grouped by caller
+pub fn incoming_calls<'a>(
+    analyses: &'a HashMap<String, AnalysisResult>,
+    target: &SmartSymbol,
+) -> Vec<IncomingCall<'a>> {
+    let mut incoming: Vec<IncomingCall<'a>> = Vec::new();
+
+    for result in analyses.values() {
+        for call in result.calls.iter().filter(|c| call_matches(c, target)) {
+            let caller = match enclosing_callable(result, call.location) {
+                Some(caller) => caller,
+                None => continue,
+            };
+
+            match incoming.iter_mut().find(|c| c.from.id == caller.id) {
+                Some(existing) => existing.from_ranges.push(call.location),
+                None => incoming.push(IncomingCall {
+                    from: caller,
+                    from_ranges: vec![call.location],
+                }),
+            }
+        }
+    }
+
+    incoming.sort_by(|a, b| {
+        a.from
+            .location
+            .uri
+            .cmp(&b.from.location.uri)
+            .then_with(|| a.from.location.range.start.cmp(&b.from.location.range.start))
+    });
+    incoming
+}
+
+/// Find the calls made directly inside `source` (not inside nested functions)
+pub fn outgoing_calls<'a>(
+    analyses: &'a HashMap<String, AnalysisResult>,
+    source: &SmartSymbol,
+) -> Vec<OutgoingCall<'a>> {
+    let result = match analyses.get(&source.location.uri) {
+        Some(result) => result,
+        None => return Vec::new(),
+    };
+
+    let mut outgoing: Vec<OutgoingCall<'a>> = Vec::new();
+
+    for call in &result.calls {
+        let in_source = enclosing_callable(result, call.location).map(|s| s.id) == Some(source.id);
+        if !in_source {
+            continue;
+        }
+
+        let callee_name = callee_base_name(&call.callee_name).to_string();
+        let to = resolve_callee(analyses, call, &source.location.uri);
+
+        let existing = outgoing.iter_mut().find(|c| match (c.to, to) {
+            (Some(a), Some(b)) => a.id == b.id,
+            (None, None) => c.callee_name == callee_name,
+            _ => false,
+        });
+        match existing {
+            Some(existing) => existing.from_ranges.push(call.location),
+            None => outgoing.push(OutgoingCall {
+                callee_name,
+                to,
+                from_ranges: vec![call.location],
+            }),
+        }
+    }
+
+    outgoing
+}
+
+/// Resolve a call to a callable symbol, preferring the caller's own document
+fn resolve_callee<'a>(
+    analyses: &'a HashMap<String, AnalysisResult>,
+    call: &CallInfo,
+    uri: &str,
+) -> Option<&'a SmartSymbol> {
+    let find_in = |result: &'a AnalysisResult| {
+        result
+            .symbols
+            .iter()
+            .find(|s| is_callable_kind(s.kind) && call_matches(call, s))
+    };
+
+    analyses.get(uri).and_then(find_in).or_else(|| {
+        let mut uris: Vec<&String> = analyses.keys().filter(|u| u.as_str() != uri).collect();
+        uris.sort();
+        uris.into_iter().find_map(|u| find_in(&analyses[u]))
+    })
+}
+
+/// The innermost callable symbol whose range contains `range`
+fn enclosing_callable(result: &AnalysisResult, range: Range) -> Option<&SmartSymbol> {
+    innermost(result.symbols.iter().filter(|s| {
+        is_callable_kind(s.kind)
+            && s.location.range.start <= range.start
+            && range.end <= s.location.range.end
+    }))
+}
+
+fn innermost<'a>(symbols: impl Iterator<Item = &'a SmartSymbol>) -> Option<&'a SmartSymbol> {
+    symbols.max_by_key(|s| s.location.range.start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter::LanguageAdapter;
+    use crate::cpp_adapter::CppAdapter;
+
+    const FIXTURE: &str = r#"
+namespace demo {
+  class User {
+    public:
+      void greet() {}
+  };
+}
+
+void welcome(demo::User& user) {
+  user.greet();
+  log();
+}
+
+void run() {
+  demo::User user;
+  user.greet();
+  welcome(user);
+}
+"#;
+
+    fn analyze() -> HashMap<String, AnalysisResult> {
+        let adapter = CppAdapter::new().unwrap();
+        let mut analyses = HashMap::new();
+        analyses.insert("file:///test.cpp".to_string(), adapter.analyze("file:///test.cpp", FIXTURE));
+        analyses
+    }
+
+    #[test]
+    fn test_callee_base_name() {
+        assert_eq!(callee_base_name("demo::User::greet"), "greet");
+        assert_eq!(callee_base_name("user.greet"), "greet");
+        assert_eq!(callee_base_name("ptr->greet"), "greet");
+        assert_eq!(callee_base_name("helper"), "helper");
+    }
+
+    #[test]
+    fn test_incoming_calls() {
+        let analyses = analyze();
+        // Cursor on `greet` in the class body
+        let greet = callable_at(&analyses, "file:///test.cpp", Position::new(4, 12)).unwrap();
+        assert_eq!(greet.name, "greet");
+
+        let incoming = incoming_calls(&analyses, greet);
+        let callers: Vec<&str> = incoming.iter().map(|c| c.from.name.as_str()).collect();
+        assert_eq!(callers, vec!["welcome", "run"]);
+        assert_eq!(incoming[0].from_ranges.len(), 1);
+        assert_eq!(incoming[0].from_ranges[0].start.line, 9);
+    }
+
+    #[test]
+    fn test_outgoing_calls() {
+        let analyses = analyze();
+        let run = callable_at(&analyses, "file:///test.cpp", Position::new(13, 6)).unwrap();
+        assert_eq!(run.name, "run");
+
+        let outgoing = outgoing_calls(&analyses, run);
+        let callees: Vec<&str> = outgoing.iter().map(|c| c.callee_name.as_str()).collect();
+        assert_eq!(callees, vec!["greet", "welcome"]);
+        assert!(outgoing.iter().all(|c| c.to.is_some()));
+    }
+}
@@ -2,13 +2,16 @@
 //!
 //! Scans source code for TODO, FIXME, HACK, XXX, NOTE and other comment markers.
 
+use crate::exclude::ExclusionPatterns;
 use logos_core::Range;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use memchr::memmem;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, BufRead};
 
-/// The kind of TODO comment marker
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// The kind of TODO comment marker; ordered as declared
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum TodoKind {
     Todo,
@@ -22,6 +25,18 @@ pub enum TodoKind {
 }
 
 impl TodoKind {
+    /// Every kind, in declaration order
+    pub const ALL: &'static [TodoKind] = &[
+        TodoKind::Todo,
+        TodoKind::Fixme,
+        TodoKind::Hack,
+        TodoKind::Xxx,
+        TodoKind::Note,
+        TodoKind::Bug,
+        TodoKind::Optimize,
+        TodoKind::Custom,
+    ];
+
     pub fn as_str(&self) -> &'static str {
         match self {
             TodoKind::Todo => "TODO",
@@ -51,7 +66,7 @@ impl TodoKind {
 }
 
 /// A TODO item found in source code
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TodoItem {
     /// The kind of TODO marker
     pub kind: TodoKind,
@@ -65,6 +80,204 @@ pub struct TodoItem {
     pub priority: u8,
     /// The line number (1-indexed)
     pub line: u32,
+    /// Issue references in the text, like `#123` or `JIRA-456`
+    pub issue_refs: Vec<IssueRef>,
+}
+
+/// A reference to an issue tracker entry in a TODO comment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IssueRef {
+    /// The reference as written, like `#123` or `JIRA-456`
+    pub id: String,
+    /// The project key of `JIRA-456`; `None` for `#123`
+    pub project: Option<String>,
+    /// The issue number
+    pub number: String,
+    /// Location in the file
+    pub range: Range,
+}
+
+impl IssueRef {
+    /// The URL of the issue, from the template for its project key (`#` for
+    /// `#123` references, `*` for any key). `{id}`, `{project}` and
+    /// `{number}` in the template are replaced.
+    pub fn url(&self, templates: &HashMap<String, String>) -> Option<String> {
+        let key = self.project.as_deref().unwrap_or("#");
+        let template = templates.get(key).or_else(|| self.project.as_ref().and(templates.get("*")))?;
+        Some(
+            template
+                .replace("{id}", &self.id)
+                .replace("{project}", self.project.as_deref().unwrap_or(""))
+                .replace("{number}", &self.number),
+        )
+    }
+}
+
+/// A run of consecutive comment-only lines; a block comment and the line
+/// comments directly above or below it form one run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommentBlock {
+    /// First line (0-indexed)
+    pub start_line: u32,
+    /// Last line (0-indexed), inclusive
+    pub end_line: u32,
+    /// Kinds of the TODO markers in the block, in order of appearance
+    pub todo_kinds: Vec<TodoKind>,
+}
+
+impl CommentBlock {
+    /// Whether the block spans more than one line
+    pub fn is_foldable(&self) -> bool {
+        self.end_line > self.start_line
+    }
+
+    pub fn has_todos(&self) -> bool {
+        !self.todo_kinds.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommentStyle {
+    /// Line comment prefixes, e.g. `//`
+    pub line: Vec<String>,
+    /// Block comment delimiters, e.g. `("/*", "*/")`
+    pub block: Vec<(String, String)>,
+    /// Characters that delimit string literals
+    pub quotes: Vec<char>,
+}
+
+impl CommentStyle {
+    pub fn new(line: &[&str], block: &[(&str, &str)], quotes: &[char]) -> Self {
+        Self {
+            line: line.iter().map(|s| s.to_string()).collect(),
+            block: block
+                .iter()
+                .map(|(open, close)| (open.to_string(), close.to_string()))
+                .collect(),
+            quotes: quotes.to_vec(),
+        }
+    }
+
+    /// C-family syntax: `//` and `/* */`, with `'…'` char literals
+    pub fn c_like() -> Self {
+        Self::new(&["//"], &[("/*", "*/")], &['"', '\''])
+    }
+
+    /// Shell-like syntax: `#`
+    pub fn hash() -> Self {
+        Self::new(&["#"], &[], &['"', '\''])
+    }
+
+    /// Every known comment syntax, for documents of unknown language
+    pub fn union() -> Self {
+        Self::new(&["//", "#", "--", ";"], &[("/*", "*/")], &['"'])
+    }
+
+    /// Built-in styles keyed by language id
+    pub fn defaults() -> HashMap<String, CommentStyle> {
+        let js = Self::new(&["//"], &[("/*", "*/")], &['"', '\'', '`']);
+        let entries = [
+            ("c", Self::c_like()),
+            ("cpp", Self::c_like()),
+            ("java", Self::c_like()),
+            // `'` also starts a lifetime, which has no closing quote
+            ("rust", Self::new(&["//"], &[("/*", "*/")], &['"'])),
+            ("go", Self::new(&["//"], &[("/*", "*/")], &['"', '`'])),
+            ("javascript", js.clone()),
+            ("javascriptreact", js.clone()),
+            ("typescript", js.clone()),
+            ("typescriptreact", js),
+            ("python", Self::hash()),
+            ("shellscript", Self::hash()),
+            ("ruby", Self::hash()),
+            ("lua", Self::new(&["--"], &[("--[[", "]]")], &['"', '\''])),
+            ("sql", Self::new(&["--"], &[("/*", "*/")], &['\''])),
+            ("lisp", Self::new(&[";"], &[("#|", "|#")], &['"'])),
+            ("asm", Self::new(&[";", "#"], &[], &['"'])),
+        ];
+        entries
+            .into_iter()
+            .map(|(lang, style)| (lang.to_string(), style))
+            .collect()
+    }
+}
+
+/// The TODOs a priority rule applies to; unset fields match any
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PriorityCondition {
+    pub kind: Option<TodoKind>,
+    /// The author in `TODO(author)`, compared case-insensitively
+    pub author: Option<String>,
+    /// The marker keyword as written, e.g. a custom `SECURITY`, compared
+    /// case-insensitively
+    pub marker: Option<String>,
+    /// Whether the marker is followed by `!`
+    pub urgent: Option<bool>,
+}
+
+impl PriorityCondition {
+    fn matches(&self, kind: TodoKind, marker: &str, author: Option<&str>, urgent: bool) -> bool {
+        self.kind.is_none_or(|k| k == kind)
+            && self.marker.as_ref().is_none_or(|m| m.eq_ignore_ascii_case(marker))
+            && self
+                .author
+                .as_ref()
+                .is_none_or(|a| author.is_some_and(|author| a.eq_ignore_ascii_case(author)))
+            && self.urgent.is_none_or(|u| u == urgent)
+    }
+}
+
+/// How a priority rule changes the priority of the TODOs it matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PriorityChange {
+    /// Use this priority
+    Set(u8),
+    /// Add this much to the priority so far
+    Raise(u8),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriorityRule {
+    pub when: PriorityCondition,
+    pub priority: PriorityChange,
+}
+
+/// Rules adjusting the priority of a TODO after the base priority of its
+/// kind. Every matching rule applies, in order; the result is capped at 5.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PriorityRules(pub Vec<PriorityRule>);
+
+impl Default for PriorityRules {
+    /// `TODO!` is one more urgent than `TODO`
+    fn default() -> Self {
+        Self(vec![PriorityRule {
+            when: PriorityCondition {
+                urgent: Some(true),
+                ..PriorityCondition::default()
+            },
+            priority: PriorityChange::Raise(1),
+        }])
+    }
+}
+
+impl PriorityRules {
+    /// The priority of a TODO whose kind has priority `base`
+    pub fn apply(&self, base: u8, kind: TodoKind, marker: &str, author: Option<&str>, urgent: bool) -> u8 {
+        let priority = self
+            .0
+            .iter()
+            .filter(|rule| rule.when.matches(kind, marker, author, urgent))
+            .fold(base, |priority, rule| match rule.priority {
+                PriorityChange::Set(value) => value,
+                PriorityChange::Raise(by) => priority.saturating_add(by),
+            });
+        priority.min(5)
+    }
 }
 
 /// Configuration for the comment scanner
@@ -74,6 +287,12 @@ pub struct ScannerConfig {
     pub custom_patterns: Vec<String>,
     /// Whether to scan inside multi-line comments
     pub scan_multiline: bool,
+    /// Comment syntax per language id
+    pub comment_styles: HashMap<String, CommentStyle>,
+    /// Most TODOs reported per file; the scan stops once it is reached
+    pub max_todos_per_file: Option<usize>,
+    /// Adjustments to the priority of each kind
+    pub priority_rules: PriorityRules,
 }
 
 impl Default for ScannerConfig {
@@ -81,17 +300,44 @@ impl Default for ScannerConfig {
         Self {
             custom_patterns: Vec::new(),
             scan_multiline: true,
+            comment_styles: CommentStyle::defaults(),
+            max_todos_per_file: None,
+            priority_rules: PriorityRules::default(),
         }
     }
 }
 
+/// Result of scanning a file line by line
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StreamScan {
+    pub todos: Vec<TodoItem>,
+    /// Lines read
+    pub lines: usize,
+    /// Lines skipped without running the comment scan or the regex, because
+    /// no marker keyword occurs in them
+    pub quick_rejected: usize,
+    /// Whether the scan stopped at `max_todos_per_file`
+    pub truncated: bool,
+}
+
 /// Scanner for TODO/FIXME comments in source code
 #[derive(Debug)]
 pub struct CommentScanner {
     /// Compiled regex pattern for matching TODO markers
     pattern: Regex,
+    /// Issue references: `#123`, `JIRA-456`
+    issue_pattern: Regex,
     /// Map of marker strings to TodoKind
     kind_map: HashMap<String, TodoKind>,
+    /// Comment syntax per language id
+    comment_styles: HashMap<String, CommentStyle>,
+    /// Whether block comments are followed across lines
+    scan_multiline: bool,
+    /// Finders for the marker keywords, to skip lines without any
+    keyword_finders: Vec<memmem::Finder<'static>>,
+    /// Most TODOs reported per file
+    max_todos: Option<usize>,
+    priority_rules: PriorityRules,
 }
 
 impl Default for CommentScanner {
@@ -117,74 +363,204 @@ impl CommentScanner {
             kind_map.insert(pattern.to_uppercase(), TodoKind::Custom);
         }
 
-        // Build the regex pattern
-        // Matches: // TODO: text, /* TODO: text */, # TODO: text, -- TODO: text
+        // Build the regex pattern, applied to comment text only
         // Also matches: TODO(author): text, TODO!: text (urgent)
         let keywords: Vec<&str> = kind_map.keys().map(|s| s.as_str()).collect();
         let keywords_pattern = keywords.join("|");
 
         // Pattern explanation:
-        // (?://|/\*|#|--|;)?\s*  - Optional comment prefix
-        // (TODO|FIXME|...)       - The keyword
+        // \b(TODO|FIXME|...)\b   - The keyword
         // (!)?                   - Optional urgency marker
         // (?:\(([^)]+)\))?       - Optional (author) group
         // [:\s]+                 - Colon or whitespace separator
         // (.*)                   - The TODO text
         let pattern_str = format!(
-            r"(?://|/\*|#|--|;)?\s*\b({})\b(!)?(?:\(([^)]+)\))?[:\s]+(.*)$",
+            r"\b({})\b(!)?(?:\(([^)]+)\))?[:\s]+(.*)$",
             keywords_pattern
         );
 
         let pattern = Regex::new(&pattern_str).expect("Invalid regex pattern");
+        let issue_pattern = Regex::new(r"(?:^|[^\w#])(#(\d+)|([A-Z][A-Z0-9]+)-(\d+))\b").expect("Invalid regex pattern");
+
+        let keyword_finders = kind_map
+            .keys()
+            .map(|keyword| memmem::Finder::new(keyword.as_bytes()).into_owned())
+            .collect();
+
+        Self {
+            pattern,
+            issue_pattern,
+            kind_map,
+            comment_styles: config.comment_styles.clone(),
+            scan_multiline: config.scan_multiline,
+            keyword_finders,
+            max_todos: config.max_todos_per_file,
+            priority_rules: config.priority_rules.clone(),
+        }
+    }
 
-        Self { pattern, kind_map }
+    /// Comment syntax for a language id, or the union of all known syntaxes
+    pub fn comment_style(&self, language_id: Option<&str>) -> CommentStyle {
+        language_id
+            .and_then(|lang| self.comment_styles.get(lang))
+            .cloned()
+            .unwrap_or_else(CommentStyle::union)
     }
 
-    /// Scan a source file for TODO comments
-    pub fn scan_file(&self, source: &str, _uri: &str) -> Vec<TodoItem> {
-        let mut todos = Vec::new();
+    /// Scan a source file for TODO comments, inferring the language from the URI
+    pub fn scan_file(&self, source: &str, uri: &str) -> Vec<TodoItem> {
+        self.scan_file_with_language(source, uri, language_from_uri(uri))
+    }
+
+    /// Scan a source file for TODO comments using the comment syntax of `language_id`
+    pub fn scan_file_with_language(
+        &self,
+        source: &str,
+        _uri: &str,
+        language_id: Option<&str>,
+    ) -> Vec<TodoItem> {
+        let mut scan = LineScan::new(self, language_id);
+        for line in source.lines() {
+            if !scan.line(line) {
+                break;
+            }
+        }
+        scan.result.todos
+    }
+
+    /// Scan a file line by line from `reader`, reusing one line buffer, so
+    /// that a large file is never held in memory as a whole
+    pub fn scan_reader<R: BufRead>(&self, mut reader: R, language_id: Option<&str>) -> io::Result<StreamScan> {
+        let mut scan = LineScan::new(self, language_id);
+        let mut buffer = String::new();
+        loop {
+            buffer.clear();
+            if reader.read_line(&mut buffer)? == 0 {
+                break;
+            }
+            let line = buffer.strip_suffix('\n').unwrap_or(&buffer);
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            if !scan.line(line) {
+                break;
+            }
+        }
+        Ok(scan.result)
+    }
+
+    /// Whether a marker keyword occurs anywhere in `line`
+    fn may_contain_marker(&self, line: &str) -> bool {
+        self.keyword_finders.iter().any(|finder| finder.find(line.as_bytes()).is_some())
+    }
+
+    /// Comment blocks of a source file, using the comment syntax of
+    /// `language_id`. Lines with code besides the comment end a block.
+    pub fn comment_blocks(&self, source: &str, language_id: Option<&str>) -> Vec<CommentBlock> {
+        let style = self.comment_style(language_id);
+        let mut blocks: Vec<CommentBlock> = Vec::new();
+        let mut current: Option<CommentBlock> = None;
+        let mut open_block: Option<String> = None;
 
         for (line_idx, line) in source.lines().enumerate() {
-            if let Some(captures) = self.pattern.captures(line) {
-                let keyword = captures.get(1).map(|m| m.as_str().to_uppercase());
-                let urgent = captures.get(2).is_some();
-                let author = captures.get(3).map(|m| m.as_str().to_string());
-                let text = captures.get(4).map(|m| m.as_str().trim().to_string()).unwrap_or_default();
-
-                if let Some(keyword) = keyword {
-                    if let Some(&kind) = self.kind_map.get(&keyword) {
-                        let match_start = captures.get(1).unwrap().start();
-                        let match_end = captures.get(4).map(|m| m.end()).unwrap_or(captures.get(1).unwrap().end());
-
-                        let priority = if urgent {
-                            (kind.priority() + 1).min(5)
-                        } else {
-                            kind.priority()
-                        };
-
-                        todos.push(TodoItem {
-                            kind,
-                            text,
-                            range: Range {
-                                start: logos_core::Position {
-                                    line: line_idx as u32,
-                                    column: match_start as u32,
-                                },
-                                end: logos_core::Position {
-                                    line: line_idx as u32,
-                                    column: match_end as u32,
-                                },
-                            },
-                            author,
-                            priority,
-                            line: (line_idx + 1) as u32,
-                        });
-                    }
+            let in_block = open_block.is_some();
+            let regions = comment_regions(line, &style, &mut open_block);
+            // A line of just `/*` has no comment text but opens a block
+            let has_comment = in_block || open_block.is_some() || !regions.is_empty();
+            if !self.scan_multiline {
+                open_block = None;
+            }
+
+            let is_comment = has_comment && is_comment_only(line, &regions, &style);
+            if !is_comment {
+                blocks.extend(current.take());
+                continue;
+            }
+
+            let block = current.get_or_insert_with(|| CommentBlock {
+                start_line: line_idx as u32,
+                end_line: line_idx as u32,
+                todo_kinds: Vec::new(),
+            });
+            block.end_line = line_idx as u32;
+            for &(start, end) in &regions {
+                if let Some(todo) = self.match_todo(&line[start..end], start, line_idx) {
+                    block.todo_kinds.push(todo.kind);
                 }
             }
         }
+        blocks.extend(current);
+        blocks
+    }
 
-        todos
+    /// Match a TODO marker in the text of a comment starting at column `offset`
+    fn match_todo(&self, comment: &str, offset: usize, line_idx: usize) -> Option<TodoItem> {
+        // A PEP 484 type comment names a type, even one called `TODO`
+        if comment.trim_start().starts_with("type:") {
+            return None;
+        }
+        let captures = self.pattern.captures(comment)?;
+        let keyword = captures.get(1)?;
+        let kind = *self.kind_map.get(&keyword.as_str().to_uppercase())?;
+        let urgent = captures.get(2).is_some();
+        let author = captures.get(3).map(|m| m.as_str().to_string());
+        let text = captures
+            .get(4)
+            .map(|m| m.as_str().trim().to_string())
+            .unwrap_or_default();
+
+        let match_start = offset + keyword.start();
+        let match_end = offset + captures.get(4).map(|m| m.end()).unwrap_or(keyword.end());
+        let issue_refs = captures
+            .get(4)
+            .map(|m| self.issue_refs(m.as_str(), offset + m.start(), line_idx))
+            .unwrap_or_default();
+
+        let priority = self
+            .priority_rules
+            .apply(kind.priority(), kind, keyword.as_str(), author.as_deref(), urgent);
+
+        Some(TodoItem {
+            kind,
+            text,
+            range: Range {
+                start: logos_core::Position {
+                    line: line_idx as u32,
+                    column: match_start as u32,
+                },
+                end: logos_core::Position {
+                    line: line_idx as u32,
+                    column: match_end as u32,
+                },
+            },
+            author,
+            priority,
+            line: (line_idx + 1) as u32,
+            issue_refs,
+        })
+    }
+
+    /// Issue references in TODO text starting at column `offset`
+    fn issue_refs(&self, text: &str, offset: usize, line_idx: usize) -> Vec<IssueRef> {
+        self.issue_pattern
+            .captures_iter(text)
+            .filter_map(|captures| {
+                let whole = captures.get(1)?;
+                let (project, number) = match captures.get(2) {
+                    Some(number) => (None, number),
+                    None => (captures.get(3).map(|m| m.as_str().to_string()), captures.get(4)?),
+                };
+                Some(IssueRef {
+                    id: whole.as_str().to_string(),
+                    project,
+                    number: number.as_str().to_string(),
+                    range: Range::from_coords(
+                        line_idx as u32,
+                        (offset + whole.start()) as u32,
+                        line_idx as u32,
+                        (offset + whole.end()) as u32,
+                    ),
+                })
+            })
+            .collect()
     }
 
     /// Scan multiple files and return all TODO items
@@ -200,13 +576,215 @@ impl CommentScanner {
     }
 }
 
+/// The state of a scan between lines
+struct LineScan<'a> {
+    scanner: &'a CommentScanner,
+    style: CommentStyle,
+    /// Closing delimiter of a block comment continuing onto the next line
+    open_block: Option<String>,
+    result: StreamScan,
+}
+
+impl<'a> LineScan<'a> {
+    fn new(scanner: &'a CommentScanner, language_id: Option<&str>) -> Self {
+        Self {
+            scanner,
+            style: scanner.comment_style(language_id),
+            open_block: None,
+            result: StreamScan::default(),
+        }
+    }
+
+    /// Scan the next line; `false` once the TODO limit is reached
+    fn line(&mut self, line: &str) -> bool {
+        let line_idx = self.result.lines;
+        self.result.lines += 1;
+
+        // Without a keyword, only a block comment opening or closing matters
+        if !self.scanner.may_contain_marker(line) {
+            let changes_block = match &self.open_block {
+                Some(close) => line.contains(close.as_str()),
+                None => self.style.block.iter().any(|(open, _)| line.contains(open.as_str())),
+            };
+            if changes_block {
+                comment_regions(line, &self.style, &mut self.open_block);
+            } else {
+                self.result.quick_rejected += 1;
+            }
+        } else {
+            for (start, end) in comment_regions(line, &self.style, &mut self.open_block) {
+                if let Some(todo) = self.scanner.match_todo(&line[start..end], start, line_idx) {
+                    if self.scanner.max_todos.is_some_and(|max| self.result.todos.len() >= max) {
+                        self.result.truncated = true;
+                        return false;
+                    }
+                    self.result.todos.push(todo);
+                }
+            }
+        }
+        if !self.scanner.scan_multiline {
+            self.open_block = None;
+        }
+        true
+    }
+}
+
+/// Guess a language id from a URI's file extension
+fn language_from_uri(uri: &str) -> Option<&'static str> {
+    let ext = uri.rsplit('.').next()?;
+    let lang = match ext {
+        "c" | "h" => "c",
+        "cpp" | "cc" | "cxx" | "hpp" | "hxx" | "hh" => "cpp",
+        "java" => "java",
+        "rs" => "rust",
+        "go" => "go",
+        "js" | "jsx" | "mjs" | "cjs" => "javascript",
+        "ts" | "tsx" | "mts" | "cts" => "typescript",
+        "py" | "pyi" | "pyw" => "python",
+        "sh" | "bash" | "zsh" => "shellscript",
+        "rb" => "ruby",
+        "lua" => "lua",
+        "sql" => "sql",
+        "lisp" | "el" | "clj" | "scm" => "lisp",
+        "asm" | "s" => "asm",
+        _ => return None,
+    };
+    Some(lang)
+}
+
+/// Find the byte ranges of comment text in a line.
+///
+/// `open_block` carries the closing delimiter of a block comment that
+/// continues from a previous line.
+fn comment_regions(
+    line: &str,
+    style: &CommentStyle,
+    open_block: &mut Option<String>,
+) -> Vec<(usize, usize)> {
+    let mut regions = Vec::new();
+    let mut pos = 0;
+    let mut quote: Option<char> = None;
+
+    while pos < line.len() {
+        let rest = &line[pos..];
+
+        if let Some(close) = open_block.clone() {
+            match rest.find(close.as_str()) {
+                Some(idx) => {
+                    regions.push((pos, pos + idx));
+                    pos += idx + close.len();
+                    *open_block = None;
+                }
+                None => {
+                    regions.push((pos, line.len()));
+                    break;
+                }
+            }
+            continue;
+        }
+
+        let ch = rest.chars().next().unwrap();
+        if let Some(q) = quote {
+            if ch == '\\' {
+                pos += ch.len_utf8();
+                pos += rest[ch.len_utf8()..].chars().next().map_or(0, |c| c.len_utf8());
+                continue;
+            }
+            if ch == q {
+                quote = None;
+            }
+            pos += ch.len_utf8();
+            continue;
+        }
+
+        if style.quotes.contains(&ch) {
+            quote = Some(ch);
+            pos += ch.len_utf8();
+            continue;
+        }
+
+        // Block openers are checked first so `/*` isn't mistaken for `/`
+        if let Some((open, close)) = style.block.iter().find(|(open, _)| rest.starts_with(open.as_str())) {
+            *open_block = Some(close.clone());
+            pos += open.len();
+            continue;
+        }
+
+        if let Some(prefix) = style.line.iter().find(|p| rest.starts_with(p.as_str())) {
+            regions.push((pos + prefix.len(), line.len()));
+            break;
+        }
+
+        pos += ch.len_utf8();
+    }
+
+    regions
+}
+
+/// Whether everything on a line outside its comment regions is whitespace
+/// or comment delimiters
+fn is_comment_only(line: &str, regions: &[(usize, usize)], style: &CommentStyle) -> bool {
+    let mut code = String::new();
+    let mut pos = 0;
+    for &(start, end) in regions {
+        code.push_str(&line[pos..start]);
+        code.push(' ');
+        pos = end;
+    }
+    code.push_str(&line[pos..]);
+
+    for (open, close) in &style.block {
+        code = code.replace(open.as_str(), " ").replace(close.as_str(), " ");
+    }
+    for prefix in &style.line {
+        code = code.replace(prefix.as_str(), " ");
+    }
+    code.trim().is_empty()
+}
+
+/// Documents whose TODOs changed since the previous `take_changes` call
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TodoChanges {
+    /// Documents that gained TODOs
+    pub added: Vec<String>,
+    /// Documents that no longer have any TODOs
+    pub removed: Vec<String>,
+    /// Documents whose TODOs were modified
+    pub changed: Vec<String>,
+    /// Index generation at the time of the query
+    pub generation: u64,
+}
+
+impl TodoChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// TODO totals, serialized as `{"total": 3, "byKind": {"todo": 2, "fixme": 1, ...}}`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TodoStats {
+    pub total: usize,
+    /// Count for every kind in `TodoKind::ALL`, zero included
+    pub by_kind: BTreeMap<TodoKind, usize>,
+}
+
 /// Index for storing and querying TODO items across a project
 #[derive(Debug, Default)]
 pub struct TodoIndex {
     /// TODOs indexed by document URI
     by_document: HashMap<String, Vec<TodoItem>>,
+    /// Generation at which each document's TODOs last changed
+    doc_generations: HashMap<String, u64>,
+    /// Document generations as of the last `take_changes` call
+    reported: HashMap<String, u64>,
+    /// Monotonic counter bumped on every TODO change
+    generation: u64,
     /// Scanner instance
     scanner: CommentScanner,
+    /// Documents left out of the workspace-wide queries
+    exclusions: ExclusionPatterns,
 }
 
 impl TodoIndex {
@@ -216,24 +794,90 @@ impl TodoIndex {
 
     pub fn with_config(config: &ScannerConfig) -> Self {
         Self {
-            by_document: HashMap::new(),
             scanner: CommentScanner::new(config),
+            ..Self::default()
         }
     }
 
-    /// Index a document for TODOs
+    /// Index a document for TODOs, inferring its language from the URI
     pub fn index_document(&mut self, uri: &str, source: &str) {
         let todos = self.scanner.scan_file(source, uri);
+        self.store_document(uri, todos);
+    }
+
+    /// Index a document for TODOs using the comment syntax of `language_id`
+    pub fn index_document_with_language(&mut self, uri: &str, language_id: &str, source: &str) {
+        let todos = self.scanner.scan_file_with_language(source, uri, Some(language_id));
+        self.store_document(uri, todos);
+    }
+
+    fn store_document(&mut self, uri: &str, todos: Vec<TodoItem>) {
         if todos.is_empty() {
-            self.by_document.remove(uri);
-        } else {
-            self.by_document.insert(uri.to_string(), todos);
+            self.remove_document(uri);
+            return;
         }
+
+        if self.by_document.get(uri) == Some(&todos) {
+            return;
+        }
+
+        self.generation += 1;
+        self.doc_generations.insert(uri.to_string(), self.generation);
+        self.by_document.insert(uri.to_string(), todos);
     }
 
     /// Remove a document from the index
     pub fn remove_document(&mut self, uri: &str) {
-        self.by_document.remove(uri);
+        if self.by_document.remove(uri).is_some() {
+            self.generation += 1;
+            self.doc_generations.remove(uri);
+        }
+    }
+
+    /// Current generation of the index
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Get the documents whose TODOs changed since the previous call
+    pub fn take_changes(&mut self) -> TodoChanges {
+        let mut changes = TodoChanges {
+            generation: self.generation,
+            ..TodoChanges::default()
+        };
+
+        for (uri, generation) in &self.doc_generations {
+            match self.reported.get(uri) {
+                None => changes.added.push(uri.clone()),
+                Some(reported) if reported != generation => changes.changed.push(uri.clone()),
+                Some(_) => {}
+            }
+        }
+        for uri in self.reported.keys() {
+            if !self.doc_generations.contains_key(uri) {
+                changes.removed.push(uri.clone());
+            }
+        }
+
+        changes.added.sort();
+        changes.removed.sort();
+        changes.changed.sort();
+
+        self.reported = self.doc_generations.clone();
+        changes
+    }
+
+    /// Exclude matching documents from the workspace-wide queries
+    /// (`get_all_todos`, `todo_count`, `count_by_kind`); they stay indexed
+    /// and are still returned by `get_document_todos`
+    pub fn set_exclusions(&mut self, exclusions: ExclusionPatterns) {
+        self.exclusions = exclusions;
+    }
+
+    fn included_documents(&self) -> impl Iterator<Item = (&String, &Vec<TodoItem>)> {
+        self.by_document
+            .iter()
+            .filter(|(uri, _)| !self.exclusions.is_excluded(uri))
     }
 
     /// Get all TODOs for a specific document
@@ -244,7 +888,7 @@ impl TodoIndex {
     /// Get all TODOs across all indexed documents
     pub fn get_all_todos(&self) -> Vec<(&str, &TodoItem)> {
         let mut todos = Vec::new();
-        for (uri, items) in &self.by_document {
+        for (uri, items) in self.included_documents() {
             for item in items {
                 todos.push((uri.as_str(), item));
             }
@@ -266,21 +910,56 @@ impl TodoIndex {
             .collect()
     }
 
+    /// Get TODOs grouped by document, optionally limited to one kind; files
+    /// with the most TODOs come first, ties broken by URI
+    pub fn todos_by_file(&self, kind: Option<TodoKind>) -> Vec<(&str, Vec<&TodoItem>)> {
+        let mut groups: Vec<(&str, Vec<&TodoItem>)> = self
+            .included_documents()
+            .map(|(uri, items)| {
+                let items: Vec<&TodoItem> = items
+                    .iter()
+                    .filter(|item| kind.is_none_or(|kind| item.kind == kind))
+                    .collect();
+                (uri.as_str(), items)
+            })
+            .filter(|(_, items)| !items.is_empty())
+            .collect();
+        groups.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(b.0)));
+        groups
+    }
+
     /// Get total count of TODOs
     pub fn todo_count(&self) -> usize {
-        self.by_document.values().map(|v| v.len()).sum()
+        self.included_documents().map(|(_, v)| v.len()).sum()
     }
 
-    /// Get count by kind
-    pub fn count_by_kind(&self) -> HashMap<TodoKind, usize> {
-        let mut counts = HashMap::new();
-        for items in self.by_document.values() {
+    /// Get count by kind, for the kinds that occur
+    pub fn count_by_kind(&self) -> BTreeMap<TodoKind, usize> {
+        let mut counts = BTreeMap::new();
+        for (_, items) in self.included_documents() {
             for item in items {
                 *counts.entry(item.kind).or_insert(0) += 1;
             }
         }
         counts
     }
+
+    /// Comment blocks of a document, using the index's scanner configuration
+    pub fn comment_blocks(&self, source: &str, language_id: Option<&str>) -> Vec<CommentBlock> {
+        self.scanner.comment_blocks(source, language_id)
+    }
+
+    /// Totals for the workspace-wide queries, with a count for every kind
+    pub fn stats(&self) -> TodoStats {
+        let counts = self.count_by_kind();
+        TodoStats {
+            total: counts.values().sum(),
+            by_kind: TodoKind::ALL
+                .iter()
+                .map(|kind| (*kind, counts.get(kind).copied().unwrap_or(0)))
+                .collect(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -322,6 +1001,84 @@ fn main() {
         assert!(todos[0].priority > TodoKind::Todo.priority());
     }
 
+    #[test]
+    fn test_priority_rules() {
+        let mut rules = PriorityRules::default();
+        rules.0.push(PriorityRule {
+            when: PriorityCondition {
+                kind: Some(TodoKind::Fixme),
+                author: Some("alice".to_string()),
+                ..PriorityCondition::default()
+            },
+            priority: PriorityChange::Set(5),
+        });
+        let scanner = CommentScanner::new(&ScannerConfig {
+            priority_rules: rules,
+            ..ScannerConfig::default()
+        });
+        let source = "// FIXME(Alice): leaks on reconnect\n// FIXME(bob): leaks too\n// TODO!(alice): tidy up\n";
+        let priorities: Vec<u8> = scanner.scan_file(source, "test.rs").iter().map(|t| t.priority).collect();
+        assert_eq!(priorities, vec![5, TodoKind::Fixme.priority(), TodoKind::Todo.priority() + 1]);
+
+        // Rules as a host would send them
+        let rules: PriorityRules = serde_json::from_value(serde_json::json!([
+            { "when": { "marker": "fixme", "urgent": true }, "priority": { "raise": 2 } }
+        ]))
+        .unwrap();
+        assert_eq!(rules.apply(4, TodoKind::Fixme, "FIXME", None, true), 5);
+        assert_eq!(rules.apply(4, TodoKind::Fixme, "FIXME", None, false), 4);
+    }
+
+    #[test]
+    fn test_streaming_scan_of_large_file() {
+        let mut source = String::new();
+        for i in 0..50_000 {
+            match i % 1000 {
+                0 => source.push_str(&format!("// TODO: item {}\n", i)),
+                // A block comment opened on a line without a marker
+                500 => source.push_str("/* generated\n   FIXME: inside block */\n"),
+                _ => source.push_str(&format!("let value_{} = compute({});\n", i, i)),
+            }
+        }
+
+        let scanner = CommentScanner::default();
+        let scan = scanner.scan_reader(source.as_bytes(), Some("javascript")).unwrap();
+        assert_eq!(scan.lines, 50_050);
+        assert_eq!(scan.todos.len(), 100);
+        assert_eq!(scan.todos, scanner.scan_file_with_language(&source, "gen.js", Some("javascript")));
+        assert_eq!(scan.todos[1].kind, TodoKind::Fixme);
+        assert_eq!(scan.todos[1].line, 502);
+        // All but the 50 marker lines and the 50 block openers skip the scan
+        assert_eq!(scan.quick_rejected, 50_050 - 150);
+        assert!(!scan.truncated);
+
+        let capped = CommentScanner::new(&ScannerConfig {
+            max_todos_per_file: Some(10),
+            ..ScannerConfig::default()
+        });
+        let scan = capped.scan_reader(source.as_bytes(), Some("javascript")).unwrap();
+        assert_eq!(scan.todos.len(), 10);
+        assert!(scan.truncated);
+        assert!(scan.lines < 10_000);
+    }
+
+    #[test]
+    fn test_scan_issue_refs() {
+        let scanner = CommentScanner::default();
+        let source = "// TODO: see #12 and JIRA-456, not a#3 or ##4";
+        let todos = scanner.scan_file(source, "test.rs");
+        let ids: Vec<&str> = todos[0].issue_refs.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["#12", "JIRA-456"]);
+        assert_eq!(todos[0].issue_refs[0].range, Range::from_coords(0, 13, 0, 16));
+
+        let templates = HashMap::from([("JIRA".to_string(), "https://jira.example.com/browse/{id}".to_string())]);
+        assert_eq!(todos[0].issue_refs[0].url(&templates), None);
+        assert_eq!(
+            todos[0].issue_refs[1].url(&templates).unwrap(),
+            "https://jira.example.com/browse/JIRA-456"
+        );
+    }
+
     #[test]
     fn test_scan_python_comment() {
         let scanner = CommentScanner::default();
@@ -331,6 +1088,71 @@ fn main() {
         assert_eq!(todos[0].text, "Python todo");
     }
 
+    #[test]
+    fn test_type_comment_is_not_a_todo() {
+        let scanner = CommentScanner::default();
+        let source = "note = make()  # type: NOTE | None\nx = 1  # TODO: check x\n";
+        let todos = scanner.scan_file(source, "test.py");
+        assert_eq!(todos.len(), 1);
+        assert_eq!(todos[0].text, "check x");
+    }
+
+    #[test]
+    fn test_semicolon_is_not_a_comment_in_c_like_languages() {
+        let scanner = CommentScanner::default();
+        let source = "int x = 1; TODO: not a comment";
+        assert!(scanner.scan_file(source, "test.c").is_empty());
+        assert!(scanner.scan_file_with_language(source, "a", Some("javascript")).is_empty());
+
+        // Lisp does use `;`
+        let todos = scanner.scan_file_with_language("(foo) ; TODO: real", "a", Some("lisp"));
+        assert_eq!(todos.len(), 1);
+    }
+
+    #[test]
+    fn test_char_literal_is_not_a_comment() {
+        let scanner = CommentScanner::default();
+        let source = "char c = '\\''; char* s = \"/*\"; // TODO: yes";
+        for path in ["test.c", "test.cpp", "Test.java"] {
+            let todos = scanner.scan_file(source, path);
+            assert_eq!(todos.len(), 1, "{path}");
+            assert_eq!(todos[0].text, "yes");
+        }
+        let todos = scanner.scan_file("char c = '\"'; // TODO: yes", "test.c");
+        assert_eq!(todos.len(), 1);
+
+        // Rust lifetimes don't open a string
+        let todos = scanner.scan_file("fn f<'a>(x: &'a str) {} // TODO: yes", "test.rs");
+        assert_eq!(todos.len(), 1);
+    }
+
+    #[test]
+    fn test_hash_inside_string_is_not_a_comment() {
+        let scanner = CommentScanner::default();
+        let source = "x = f\"#{TODO: nope}\"  # TODO: yes";
+        let todos = scanner.scan_file(source, "test.py");
+        assert_eq!(todos.len(), 1);
+        assert_eq!(todos[0].text, "yes");
+    }
+
+    #[test]
+    fn test_block_comment_across_lines() {
+        let scanner = CommentScanner::default();
+        let source = "/*\n * FIXME: inside block\n */\nint y; // TODO: after";
+        let todos = scanner.scan_file(source, "test.c");
+        assert_eq!(todos.len(), 2);
+        assert_eq!(todos[0].kind, TodoKind::Fixme);
+        assert_eq!(todos[0].line, 2);
+        assert_eq!(todos[1].text, "after");
+    }
+
+    #[test]
+    fn test_unknown_language_uses_union() {
+        let scanner = CommentScanner::default();
+        let todos = scanner.scan_file("-- TODO: sql-ish\n; NOTE: asm-ish", "notes.txt");
+        assert_eq!(todos.len(), 2);
+    }
+
     #[test]
     fn test_todo_index() {
         let mut index = TodoIndex::new();
@@ -341,4 +1163,126 @@ fn main() {
         assert_eq!(index.get_document_todos("a.rs").len(), 2);
         assert_eq!(index.get_document_todos("b.rs").len(), 1);
     }
+
+    #[test]
+    fn test_todo_changes() {
+        let mut index = TodoIndex::new();
+        index.index_document("a.rs", "// TODO: First");
+        index.index_document("b.rs", "// NOTE: Second");
+
+        let changes = index.take_changes();
+        assert_eq!(changes.added, vec!["a.rs", "b.rs"]);
+        assert!(changes.changed.is_empty());
+
+        index.index_document("a.rs", "// TODO: First, revised");
+        index.index_document("b.rs", "// NOTE: Second");
+
+        let changes = index.take_changes();
+        assert!(changes.added.is_empty());
+        assert!(changes.removed.is_empty());
+        assert_eq!(changes.changed, vec!["a.rs"]);
+
+        assert!(index.take_changes().is_empty());
+    }
+
+    #[test]
+    fn test_todo_changes_removed() {
+        let mut index = TodoIndex::new();
+        index.index_document("a.rs", "// TODO: First");
+        index.take_changes();
+
+        index.index_document("a.rs", "fn main() {}");
+        let changes = index.take_changes();
+        assert_eq!(changes.removed, vec!["a.rs"]);
+        assert_eq!(index.todo_count(), 0);
+    }
+
+    #[test]
+    fn test_todos_by_file() {
+        let mut index = TodoIndex::new();
+        index.index_document("a.rs", "// OPTIMIZE: cache this\n// TODO: rename\n");
+        index.index_document("b.rs", "// OPTIMIZE: batch\n// OPTIMIZE: avoid clone\n");
+        index.index_document("c.rs", "// FIXME: unrelated\n");
+
+        let groups = index.todos_by_file(Some(TodoKind::Optimize));
+        let summary: Vec<(&str, usize)> = groups.iter().map(|(uri, items)| (*uri, items.len())).collect();
+        assert_eq!(summary, vec![("b.rs", 2), ("a.rs", 1)]);
+        assert!(groups.iter().flat_map(|(_, items)| items).all(|item| item.kind == TodoKind::Optimize));
+
+        let all: Vec<&str> = index.todos_by_file(None).iter().map(|(uri, _)| *uri).collect();
+        assert_eq!(all, vec!["a.rs", "b.rs", "c.rs"]);
+    }
+
+    #[test]
+    fn test_all_kinds_in_declaration_order() {
+        // Exhaustive: a new kind doesn't compile until it has a position here,
+        // and the loop below then fails until it is listed in ALL
+        let position = |kind: TodoKind| match kind {
+            TodoKind::Todo => 0,
+            TodoKind::Fixme => 1,
+            TodoKind::Hack => 2,
+            TodoKind::Xxx => 3,
+            TodoKind::Note => 4,
+            TodoKind::Bug => 5,
+            TodoKind::Optimize => 6,
+            TodoKind::Custom => 7,
+        };
+        for (i, kind) in TodoKind::ALL.iter().enumerate() {
+            assert_eq!(position(*kind), i);
+        }
+        assert_eq!(TodoKind::ALL.len(), 8);
+        assert!(TodoKind::ALL.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_stats_cover_every_kind() {
+        let mut index = TodoIndex::new();
+        index.index_document("a.rs", "// FIXME: one\n// TODO: two\n// TODO: three\n");
+
+        let stats = index.stats();
+        assert_eq!(stats.total, 3);
+        assert_eq!(stats.by_kind.keys().copied().collect::<Vec<_>>(), TodoKind::ALL);
+
+        let json = serde_json::to_value(&stats).unwrap();
+        let keys: Vec<&String> = json["byKind"].as_object().unwrap().keys().collect();
+        let expected: Vec<String> = TodoKind::ALL.iter().map(|k| k.as_str().to_lowercase()).collect();
+        assert_eq!(keys.len(), expected.len());
+        assert!(expected.iter().all(|k| keys.contains(&k)));
+        assert_eq!(json["byKind"]["todo"], 2);
+        assert_eq!(json["byKind"]["custom"], 0);
+        assert_eq!(json["total"], 3);
+    }
+
+    #[test]
+    fn test_comment_blocks() {
+        let scanner = CommentScanner::default();
+        let source = "/*\n * Parses the header.\n *\n * FIXME: handle empty input\n */\nint parse(); // not a block\n\n// one line\nint x; /* trailing\n   comment */ int y;\n";
+        let blocks = scanner.comment_blocks(source, Some("c"));
+
+        let folds: Vec<&CommentBlock> = blocks.iter().filter(|b| b.is_foldable()).collect();
+        assert_eq!(
+            folds,
+            vec![&CommentBlock {
+                start_line: 0,
+                end_line: 4,
+                todo_kinds: vec![TodoKind::Fixme],
+            }]
+        );
+        assert!(folds[0].has_todos());
+
+        // `// one line` stands alone; lines sharing code with a comment are not blocks
+        let singles: Vec<(u32, bool)> = blocks
+            .iter()
+            .filter(|b| !b.is_foldable())
+            .map(|b| (b.start_line, b.has_todos()))
+            .collect();
+        assert_eq!(singles, vec![(7, false)]);
+
+        // Line comments run together
+        let blocks = scanner.comment_blocks("# a\n# TODO: b\n# c\nx = 1\n", Some("python"));
+        assert_eq!(blocks.len(), 1);
+        assert_eq!((blocks[0].start_line, blocks[0].end_line), (0, 2));
+        assert_eq!(blocks[0].todo_kinds, vec![TodoKind::Todo]);
+    }
+
 }
@@ -1,18 +1,26 @@
 //! C++ Language Adapter
 //!
 //! Pragmatic indexer for C++:
-//! - Symbols: function definitions, class/struct, namespaces (best-effort)
+//! - Symbols: function definitions, class/struct, namespaces, templates (best-effort)
 //! - Imports: #include directives
 //! - Calls: call_expression nodes (best-effort)
+//! - Type relations: base classes of class/struct
+//! - Preprocessor conditionals: with configured macros, only the selected
+//!   `#ifdef`/`#if` branch is indexed; without, every branch is
 
-use crate::adapter::{AnalysisResult, CallInfo, ImportInfo, ImportItem, LanguageAdapter, SymbolBuilder, make_location};
+use crate::adapter::{AnalysisResult, CallInfo, ImportInfo, ImportItem, LanguageAdapter, SymbolBuilder, TypeRelation, make_location};
+use crate::semantic_tokens::{SemanticToken, SemanticTokenType};
 use crate::symbol_table::Visibility;
+use logos_core::position::byte_to_column;
 use logos_core::{Position, Range, SymbolKind};
+use std::collections::HashSet;
 use std::path::Path;
 use tree_sitter::{Node, Parser, Tree};
 
 pub struct CppAdapter {
     parser: std::sync::Mutex<Parser>,
+    /// Macros considered defined, or `None` to index every branch
+    defines: Option<HashSet<String>>,
 }
 
 impl CppAdapter {
@@ -23,11 +31,23 @@ impl CppAdapter {
             .map_err(|e| format!("Failed to set C++ language: {}", e))?;
         Ok(Self {
             parser: std::sync::Mutex::new(parser),
+            defines: None,
         })
     }
 
+    /// Index only the conditional branches these macros select. Conditions
+    /// that cannot be evaluated keep all their branches.
+    pub fn with_defines<I, S>(mut self, defines: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.defines = Some(defines.into_iter().map(Into::into).collect());
+        self
+    }
+
     fn parse(&self, source: &str) -> Option<Tree> {
-        let mut parser = self.parser.lock().ok()?;
+        let mut parser = self.parser.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
         parser.parse(source, None)
     }
 }
@@ -41,6 +61,10 @@ impl LanguageAdapter for CppAdapter {
         &["cpp", "cc", "cxx", "hpp", "hxx", "hh", "h"]
     }
 
+    fn keywords(&self) -> &[&str] {
+        logos_parser::cpp::get_keywords()
+    }
+
     fn analyze(&self, uri: &str, source: &str) -> AnalysisResult {
         let tree = match self.parse(source) {
             Some(t) => t,
@@ -52,12 +76,24 @@ impl LanguageAdapter for CppAdapter {
             source,
             result: AnalysisResult::default(),
             scope_stack: Vec::new(),
+            defines: self.defines.as_ref(),
         };
 
         analyze_node(&tree.root_node(), &mut ctx);
         ctx.result
     }
 
+    fn semantic_tokens(&self, source: &str, analysis: &AnalysisResult) -> Vec<SemanticToken> {
+        let tree = match self.parse(source) {
+            Some(t) => t,
+            None => return Vec::new(),
+        };
+
+        let mut tokens = Vec::new();
+        classify_node(&tree.root_node(), source, analysis, &HashSet::new(), &mut tokens);
+        tokens
+    }
+
     fn resolve_import(&self, from_file: &Path, import_path: &str) -> Option<std::path::PathBuf> {
         // For `#include "x.h"` try relative to file dir
         if !(import_path.starts_with('"') && import_path.ends_with('"')) {
@@ -78,6 +114,7 @@ struct AnalysisContext<'a> {
     source: &'a str,
     result: AnalysisResult,
     scope_stack: Vec<ScopeInfo>,
+    defines: Option<&'a HashSet<String>>,
 }
 
 struct ScopeInfo {
@@ -124,7 +161,9 @@ fn analyze_node(node: &Node, ctx: &mut AnalysisContext) {
             }
         }
         "namespace_definition" => analyze_namespace(node, ctx),
+        "template_declaration" => analyze_template(node, ctx),
         "call_expression" => analyze_call(node, ctx),
+        "preproc_ifdef" | "preproc_if" if ctx.defines.is_some() => analyze_conditional(node, ctx),
         _ => {
             for i in 0..node.named_child_count() {
                 if let Some(child) = node.named_child(i) {
@@ -135,6 +174,112 @@ fn analyze_node(node: &Node, ctx: &mut AnalysisContext) {
     }
 }
 
+/// Index the branch of a conditional (or of its `#elif`/`#else` chain) the
+/// configured macros select
+fn analyze_conditional(node: &Node, ctx: &mut AnalysisContext) {
+    let defines = match ctx.defines {
+        Some(defines) => defines,
+        None => return,
+    };
+    let alternative = node.child_by_field_name("alternative");
+    let active = condition_value(node, ctx.source, defines);
+    if active == Some(false) {
+        if let Some(alternative) = alternative {
+            analyze_conditional(&alternative, ctx);
+        }
+        return;
+    }
+
+    // An unknown condition keeps both branches
+    let skipped: Vec<_> = ["name", "condition", "alternative"]
+        .iter()
+        .filter_map(|field| node.child_by_field_name(field))
+        .map(|n| n.id())
+        .collect();
+    for i in 0..node.named_child_count() {
+        if let Some(child) = node.named_child(i) {
+            if !skipped.contains(&child.id()) {
+                analyze_node(&child, ctx);
+            }
+        }
+    }
+    if active.is_none() {
+        if let Some(alternative) = alternative {
+            analyze_conditional(&alternative, ctx);
+        }
+    }
+}
+
+/// Whether a conditional branch is taken, if it can be told
+fn condition_value(node: &Node, source: &str, defines: &HashSet<String>) -> Option<bool> {
+    match node.kind() {
+        "preproc_ifdef" | "preproc_elifdef" => {
+            let directive = node.child(0)?.kind();
+            let name = &source[node.child_by_field_name("name")?.byte_range()];
+            Some(defines.contains(name) != directive.ends_with("ndef"))
+        }
+        "preproc_if" | "preproc_elif" => evaluate(&node.child_by_field_name("condition")?, source, defines),
+        "preproc_else" => Some(true),
+        _ => None,
+    }
+}
+
+/// Best-effort value of an `#if` expression: `defined`, macro names,
+/// integers, `!`, `&&`, `||` and parentheses
+fn evaluate(node: &Node, source: &str, defines: &HashSet<String>) -> Option<bool> {
+    match node.kind() {
+        "preproc_defined" => {
+            let name = find_first_named_of_kinds(*node, &["identifier"])?;
+            Some(defines.contains(&source[name.byte_range()]))
+        }
+        "identifier" => Some(defines.contains(&source[node.byte_range()])),
+        "number_literal" => source[node.byte_range()].parse::<i64>().ok().map(|n| n != 0),
+        "parenthesized_expression" => evaluate(&node.named_child(0)?, source, defines),
+        "unary_expression" if node.child_by_field_name("operator")?.kind() == "!" => {
+            evaluate(&node.child_by_field_name("argument")?, source, defines).map(|v| !v)
+        }
+        "binary_expression" => {
+            let left = evaluate(&node.child_by_field_name("left")?, source, defines);
+            let right = evaluate(&node.child_by_field_name("right")?, source, defines);
+            match node.child_by_field_name("operator")?.kind() {
+                "&&" => match (left, right) {
+                    (Some(false), _) | (_, Some(false)) => Some(false),
+                    (Some(true), Some(true)) => Some(true),
+                    _ => None,
+                },
+                "||" => match (left, right) {
+                    (Some(true), _) | (_, Some(true)) => Some(true),
+                    (Some(false), Some(false)) => Some(false),
+                    _ => None,
+                },
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// `template<typename T> class Box {}`: index the wrapped declaration and record
+/// the template header as the documentation of the first symbol it produces,
+/// leaving its type information to the declaration
+fn analyze_template(node: &Node, ctx: &mut AnalysisContext) {
+    let params_node = node.child_by_field_name("parameters");
+    let header = params_node.map(|list| format!("template{}", ctx.get_text(&list)));
+
+    let first = ctx.result.symbols.len();
+    for i in 0..node.named_child_count() {
+        if let Some(child) = node.named_child(i) {
+            if Some(child) != params_node {
+                analyze_node(&child, ctx);
+            }
+        }
+    }
+
+    if let (Some(symbol), Some(header)) = (ctx.result.symbols.get_mut(first), header) {
+        symbol.documentation = Some(header);
+    }
+}
+
 fn analyze_class_decl(node: &Node, ctx: &mut AnalysisContext) {
     // class_declaration/struct_declaration 是前向声明，通常没有 body
     let keyword = if node.kind() == "struct_declaration" { "struct" } else { "class" };
@@ -181,6 +326,7 @@ fn analyze_include(node: &Node, ctx: &mut AnalysisContext) {
                     name: rest.to_string(),
                     alias: None,
                     is_type: false,
+                    range: node_to_range(node),
                 }],
                 is_type_only: false,
                 location: node_to_range(node),
@@ -190,6 +336,17 @@ fn analyze_include(node: &Node, ctx: &mut AnalysisContext) {
 }
 
 fn analyze_function(node: &Node, ctx: &mut AnalysisContext) {
+    // Out-of-class definitions name the function with a qualified identifier: `void User::greet()`
+    let qualified = node
+        .child_by_field_name("declarator")
+        .and_then(|d| find_first_named_of_kinds(d, &["function_declarator"]).or(Some(d)))
+        .and_then(|d| d.child_by_field_name("declarator"))
+        .filter(|d| d.kind() == "qualified_identifier");
+    if let Some(qualified) = qualified {
+        analyze_qualified_function(node, &qualified, ctx);
+        return;
+    }
+
     // function_definition 在全局作用域：function_declarator 包含 identifier
     let name_node = node
         .child_by_field_name("declarator")
@@ -220,6 +377,59 @@ fn analyze_function(node: &Node, ctx: &mut AnalysisContext) {
     }
 }
 
+/// An out-of-class definition such as `void User::greet() {}` or `demo::User::User() {}`:
+/// a method of the scope's class, or a function if the scope is a known namespace
+fn analyze_qualified_function(node: &Node, qualified: &Node, ctx: &mut AnalysisContext) {
+    // Nested scopes nest the qualified identifiers: `a::B::f` = (a, (B, f))
+    let mut name_node = *qualified;
+    while name_node.kind() == "qualified_identifier" {
+        name_node = match name_node.child_by_field_name("name") {
+            Some(n) => n,
+            None => return,
+        };
+    }
+
+    let name = ctx.get_text(&name_node);
+    let full = ctx.get_text(qualified);
+    let scope = strip_template_args(full[..full.len() - name.len()].trim_end_matches("::"));
+    let owner = scope.rsplit("::").next().unwrap_or(&scope).to_string();
+    let qualified_name = ctx.qualified_name(&format!("{}::{}", scope, name));
+    let owner_qualified = ctx.qualified_name(&scope);
+
+    let owner_symbol = ctx
+        .result
+        .symbols
+        .iter()
+        .find(|s| s.name == owner && (s.qualified_name == owner_qualified || s.qualified_name == scope));
+    let is_namespace = owner_symbol.is_some_and(|s| s.kind == SymbolKind::Namespace);
+    let parent = owner_symbol.filter(|_| !is_namespace).map(|s| s.id);
+
+    let kind = if is_namespace { SymbolKind::Function } else { SymbolKind::Method };
+    let location = make_location(&ctx.uri, node_to_range(node), node_to_range(&name_node));
+    let mut builder = SymbolBuilder::new(name, kind, location)
+        .exported(true)
+        .visibility(Visibility::Public)
+        .qualified_name(qualified_name.clone());
+    if let Some(parent) = parent {
+        builder = builder.parent(parent);
+    }
+
+    let symbol = builder.build();
+    let symbol_id = symbol.id;
+    ctx.result.symbols.push(symbol);
+
+    if let Some(body) = node.child_by_field_name("body") {
+        // Scope the body under the qualified path so nested names resolve to the class
+        let saved = std::mem::take(&mut ctx.scope_stack);
+        ctx.scope_stack.push(ScopeInfo {
+            symbol_id,
+            name: qualified_name,
+        });
+        analyze_node(&body, ctx);
+        ctx.scope_stack = saved;
+    }
+}
+
 fn analyze_class_or_struct(node: &Node, ctx: &mut AnalysisContext) {
     // 根据实际 AST：class_specifier 的直接子节点 type_identifier 是类名
     let name_node = node
@@ -258,6 +468,15 @@ fn analyze_class_or_struct(node: &Node, ctx: &mut AnalysisContext) {
     let symbol_id = symbol.id;
     ctx.result.symbols.push(symbol);
 
+    // Base classes: `class Derived : public Base, private Other`
+    for i in 0..node.named_child_count() {
+        if let Some(child) = node.named_child(i) {
+            if child.kind() == "base_class_clause" {
+                analyze_base_classes(&child, ctx, &name);
+            }
+        }
+    }
+
     // 分析类体：提取字段和方法
     if let Some(body) = node.child_by_field_name("body") {
         ctx.scope_stack.push(ScopeInfo {
@@ -279,6 +498,32 @@ fn analyze_class_or_struct(node: &Node, ctx: &mut AnalysisContext) {
     }
 }
 
+fn analyze_base_classes(node: &Node, ctx: &mut AnalysisContext, child_name: &str) {
+    for i in 0..node.named_child_count() {
+        if let Some(base) = node.named_child(i) {
+            if !matches!(base.kind(), "type_identifier" | "qualified_identifier" | "template_type") {
+                continue;
+            }
+            let text = ctx.get_text(&base);
+            let parent_name = text
+                .split('<')
+                .next()
+                .unwrap_or(&text)
+                .rsplit("::")
+                .next()
+                .unwrap_or(&text)
+                .trim()
+                .to_string();
+            ctx.result.type_relations.push(TypeRelation {
+                child_name: child_name.to_string(),
+                parent_name,
+                is_implements: false,
+                location: node_to_range(&base),
+            });
+        }
+    }
+}
+
 fn analyze_class_body(node: &Node, ctx: &mut AnalysisContext, default_visibility: Visibility) {
     // 根据 AST：class_specifier 的 body 字段是 field_declaration_list
     // field_declaration_list 包含 access_specifier, field_declaration, function_definition
@@ -435,6 +680,21 @@ fn analyze_namespace(node: &Node, ctx: &mut AnalysisContext) {
     }
 }
 
+/// `Box<T>::Inner<U>` -> `Box::Inner`
+fn strip_template_args(text: &str) -> String {
+    let mut depth = 0usize;
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '<' => depth += 1,
+            '>' => depth = depth.saturating_sub(1),
+            _ if depth == 0 => out.push(ch),
+            _ => {}
+        }
+    }
+    out
+}
+
 fn find_first_named_of_kinds<'a>(node: Node<'a>, kinds: &[&str]) -> Option<Node<'a>> {
     // 先检查直接子节点
     for i in 0..node.named_child_count() {
@@ -514,6 +774,135 @@ fn find_identifier_in_declarator<'a>(node: Node<'a>) -> Option<Node<'a>> {
     None
 }
 
+fn classify_node(
+    node: &Node,
+    source: &str,
+    analysis: &AnalysisResult,
+    params: &HashSet<String>,
+    tokens: &mut Vec<SemanticToken>,
+) {
+    // Parameters are visible in the whole function definition
+    let scoped_params;
+    let params = if node.kind() == "function_definition" || node.kind() == "lambda_expression" {
+        let mut names = params.clone();
+        names.extend(parameter_names(node, source));
+        scoped_params = names;
+        &scoped_params
+    } else {
+        params
+    };
+
+    if let Some(token_type) = classify_identifier(node, source, analysis, params) {
+        // Tree-sitter columns are bytes, LSP columns UTF-16 code units
+        let start = node.start_position();
+        let line = &source[node.start_byte() - start.column..];
+        tokens.push(SemanticToken {
+            line: start.row as u32,
+            start: byte_to_column(line, start.column),
+            length: source[node.byte_range()].encode_utf16().count() as u32,
+            token_type,
+        });
+        return;
+    }
+
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        classify_node(&child, source, analysis, params, tokens);
+    }
+}
+
+fn classify_identifier(
+    node: &Node,
+    source: &str,
+    analysis: &AnalysisResult,
+    params: &HashSet<String>,
+) -> Option<SemanticTokenType> {
+    let parent_kind = node.parent().map(|p| p.kind());
+    let symbol_kind = |name: &str, kinds: &[SymbolKind]| {
+        analysis.symbols.iter().any(|s| s.name == name && kinds.contains(&s.kind))
+    };
+
+    match node.kind() {
+        "namespace_identifier" => Some(SemanticTokenType::Namespace),
+        "type_identifier" => Some(SemanticTokenType::Class),
+        "field_identifier" => {
+            if parent_kind == Some("function_declarator") || is_callee(node) {
+                Some(SemanticTokenType::Method)
+            } else {
+                Some(SemanticTokenType::Property)
+            }
+        }
+        "identifier" => {
+            let name = node.utf8_text(source.as_bytes()).ok()?;
+            let token_type = if parent_kind == Some("function_declarator") || is_callee(node) {
+                let is_method = symbol_kind(name, &[SymbolKind::Method])
+                    && !symbol_kind(name, &[SymbolKind::Function]);
+                if is_method {
+                    SemanticTokenType::Method
+                } else {
+                    SemanticTokenType::Function
+                }
+            } else if params.contains(name) {
+                SemanticTokenType::Parameter
+            } else if symbol_kind(name, &[SymbolKind::Class, SymbolKind::Struct]) {
+                SemanticTokenType::Class
+            } else if symbol_kind(name, &[SymbolKind::Namespace]) {
+                SemanticTokenType::Namespace
+            } else if symbol_kind(name, &[SymbolKind::Function]) {
+                SemanticTokenType::Function
+            } else {
+                SemanticTokenType::Variable
+            };
+            Some(token_type)
+        }
+        _ => None,
+    }
+}
+
+/// Whether `node` names the function of a call: `f()`, `obj.f()`, `ns::f()`
+fn is_callee(node: &Node) -> bool {
+    let mut current = *node;
+    while let Some(parent) = current.parent() {
+        match parent.kind() {
+            // Only the member or last path segment names the callee, not the receiver
+            "qualified_identifier" | "template_function" if is_field(&parent, "name", &current) => {
+                current = parent
+            }
+            "field_expression" if is_field(&parent, "field", &current) => current = parent,
+            "call_expression" => {
+                return parent.child_by_field_name("function").map(|f| f.id()) == Some(current.id());
+            }
+            _ => return false,
+        }
+    }
+    false
+}
+
+fn is_field(parent: &Node, field: &str, child: &Node) -> bool {
+    parent.child_by_field_name(field).map(|f| f.id()) == Some(child.id())
+}
+
+fn parameter_names(node: &Node, source: &str) -> Vec<String> {
+    let params = node
+        .child_by_field_name("declarator")
+        .and_then(|d| find_first_named_of_kinds(d, &["parameter_list"]));
+
+    let mut names = Vec::new();
+    if let Some(params) = params {
+        let mut cursor = params.walk();
+        for param in params.named_children(&mut cursor) {
+            let name = param
+                .child_by_field_name("declarator")
+                .and_then(find_identifier_in_declarator)
+                .and_then(|id| id.utf8_text(source.as_bytes()).ok());
+            if let Some(name) = name {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names
+}
+
 fn node_to_range(node: &Node) -> Range {
     let start = node.start_position();
     let end = node.end_position();
@@ -558,6 +947,88 @@ int greet() { return 0; }
         assert!(result.symbols.iter().any(|s| s.name == "greet" && s.kind == SymbolKind::Function), "Should have function greet");
     }
 
+    #[test]
+    fn cpp_out_of_class_method_definition() {
+        let adapter = CppAdapter::new().unwrap();
+        let src = r#"
+namespace demo {
+  class User {
+    public:
+      void greet();
+  };
+
+  void User::greet() { log(); }
+}
+
+namespace util { }
+void util::helper() {}
+"#;
+        let result = adapter.analyze("file:///test.cpp", src);
+        let greet = result
+            .symbols
+            .iter()
+            .find(|s| s.name == "greet" && s.kind == SymbolKind::Method)
+            .expect("definition should be indexed as a method");
+        assert_eq!(greet.qualified_name, "demo::User::greet");
+        let user = result.symbols.iter().find(|s| s.name == "User").unwrap();
+        assert_eq!(greet.parent, Some(user.id));
+        assert!(!result.symbols.iter().any(|s| s.name == "User" && s.kind == SymbolKind::Function));
+
+        let helper = result.symbols.iter().find(|s| s.name == "helper").unwrap();
+        assert_eq!(helper.kind, SymbolKind::Function);
+        assert_eq!(helper.qualified_name, "util::helper");
+
+        // Calls in the body are attributed to the method
+        assert!(result.calls.iter().any(|c| c.callee_name == "log"));
+    }
+
+    #[test]
+    fn cpp_template_class() {
+        let adapter = CppAdapter::new().unwrap();
+        let src = r#"
+template<typename T, int N = 4>
+class Box {
+  public:
+    T get() { return value; }
+  private:
+    T value;
+};
+
+template<typename T>
+T Box<T>::peek() { return value; }
+"#;
+        let result = adapter.analyze("file:///test.cpp", src);
+        let boxed = result.symbols.iter().find(|s| s.name == "Box").expect("Box should be indexed");
+        assert_eq!(boxed.kind, SymbolKind::Class);
+        assert_eq!(boxed.documentation.as_deref(), Some("template<typename T, int N = 4>"));
+        assert!(boxed.type_info.is_none());
+
+        let get = result.symbols.iter().find(|s| s.name == "get").unwrap();
+        assert_eq!(get.qualified_name, "Box::get");
+
+        let peek = result.symbols.iter().find(|s| s.name == "peek").unwrap();
+        assert_eq!(peek.kind, SymbolKind::Method);
+        assert_eq!(peek.qualified_name, "Box::peek");
+        assert_eq!(peek.parent, Some(boxed.id));
+    }
+
+    #[test]
+    fn cpp_template_function() {
+        let adapter = CppAdapter::new().unwrap();
+        let src = r#"
+namespace util {
+  template<class T>
+  T max(T a, T b) { return compare(a, b) ? a : b; }
+}
+"#;
+        let result = adapter.analyze("file:///test.cpp", src);
+        let max = result.symbols.iter().find(|s| s.name == "max").expect("max should be indexed");
+        assert_eq!(max.kind, SymbolKind::Function);
+        assert_eq!(max.qualified_name, "util::max");
+        assert_eq!(max.documentation.as_deref(), Some("template<class T>"));
+        assert!(result.calls.iter().any(|c| c.callee_name == "compare"));
+    }
+
     #[test]
     fn cpp_class_with_private_members() {
         let adapter = CppAdapter::new().unwrap();
@@ -588,5 +1059,65 @@ class MyClass {
         assert_eq!(public_method.kind, SymbolKind::Method);
         assert_eq!(public_method.visibility, Visibility::Public);
     }
-}
 
+    #[test]
+    fn cpp_base_classes_are_type_relations() {
+        let adapter = CppAdapter::new().unwrap();
+        let src = r#"
+class Shape {};
+class Circle : public Shape {};
+struct Square : Shape, public ns::Drawable {};
+"#;
+        let result = adapter.analyze("file:///test.cpp", src);
+        assert!(result
+            .type_relations
+            .iter()
+            .any(|r| r.child_name == "Circle" && r.parent_name == "Shape" && !r.is_implements));
+        assert!(result
+            .type_relations
+            .iter()
+            .any(|r| r.child_name == "Square" && r.parent_name == "Drawable"));
+    }
+
+    #[test]
+    fn cpp_ifdef_selects_configured_branch() {
+        let src = r#"
+#pragma once
+#ifndef RENDERER_H
+#define RENDERER_H
+
+#ifdef USE_GL
+class Renderer { void draw_gl(); };
+#else
+class Renderer { void draw_soft(); };
+#endif
+
+#if defined(DEBUG) && !USE_GL
+void dump() {}
+#elif VERSION > 2
+void trace() {}
+#endif
+
+#endif
+"#;
+        let names = |adapter: CppAdapter| -> Vec<String> {
+            let result = adapter.analyze("file:///renderer.h", src);
+            result.symbols.iter().map(|s| s.name.clone()).collect()
+        };
+
+        // Without configured macros every branch is indexed
+        let all = names(CppAdapter::new().unwrap());
+        assert_eq!(all.iter().filter(|n| *n == "Renderer").count(), 2);
+        assert!(all.contains(&"dump".to_string()) && all.contains(&"trace".to_string()));
+
+        let gl = names(CppAdapter::new().unwrap().with_defines(["USE_GL"]));
+        assert_eq!(gl.iter().filter(|n| *n == "Renderer").count(), 1);
+        assert!(gl.contains(&"draw_gl".to_string()) && !gl.contains(&"draw_soft".to_string()));
+        // `VERSION > 2` cannot be evaluated, so `trace` stays
+        assert!(!gl.contains(&"dump".to_string()) && gl.contains(&"trace".to_string()));
+
+        let debug = names(CppAdapter::new().unwrap().with_defines(["DEBUG"]));
+        assert!(debug.contains(&"draw_soft".to_string()) && !debug.contains(&"draw_gl".to_string()));
+        assert!(debug.contains(&"dump".to_string()) && !debug.contains(&"trace".to_string()));
+    }
+}
@@ -0,0 +1,141 @@
+//! Exclusion Patterns
+//!
+//! Gitignore-style globs over document URIs, used to keep vendored and
+//! generated files out of workspace-wide queries.
+//! - `*` and `?` match within one path segment, `**` across segments
+//! - A pattern matches at any directory level: `node_modules/**` and
+//!   `**/node_modules/**` are the same, `*.generated.*` matches a file name
+//! - A trailing `/` excludes everything below the directory
+
+use regex::Regex;
+
+#[derive(Debug, Clone, Default)]
+pub struct ExclusionPatterns {
+    patterns: Vec<(String, Regex)>,
+}
+
+impl ExclusionPatterns {
+    pub fn new<I, S>(patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let patterns = patterns
+            .into_iter()
+            .map(|p| p.as_ref().trim().to_string())
+            .filter(|p| !p.is_empty())
+            .map(|p| {
+                let regex = glob_to_regex(&p);
+                (p, regex)
+            })
+            .collect();
+        Self { patterns }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// The patterns as given
+    pub fn patterns(&self) -> impl Iterator<Item = &str> {
+        self.patterns.iter().map(|(p, _)| p.as_str())
+    }
+
+    /// Whether any pattern matches the URI's path
+    pub fn is_excluded(&self, uri: &str) -> bool {
+        if self.patterns.is_empty() {
+            return false;
+        }
+        let path = uri.strip_prefix("file://").unwrap_or(uri);
+        self.patterns.iter().any(|(_, regex)| regex.is_match(path))
+    }
+}
+
+fn glob_to_regex(glob: &str) -> Regex {
+    let mut glob = glob.trim_start_matches('/').to_string();
+    if glob.ends_with('/') {
+        glob.push_str("**");
+    }
+
+    let mut pattern = String::from("^(?:.*/)?");
+    let mut rest = glob.strip_prefix("**/").unwrap_or(&glob);
+    while let Some(ch) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix("**/") {
+            pattern.push_str("(?:.*/)?");
+            rest = after;
+            continue;
+        }
+        if let Some(after) = rest.strip_prefix("**") {
+            pattern.push_str(".*");
+            rest = after;
+            continue;
+        }
+        match ch {
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            _ => pattern.push_str(&regex::escape(ch.encode_utf8(&mut [0; 4]))),
+        }
+        rest = &rest[ch.len_utf8()..];
+    }
+    pattern.push('$');
+
+    // Every character outside the wildcards is escaped
+    Regex::new(&pattern).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comments::{TodoIndex, TodoKind};
+    use crate::SymbolIndex;
+    use logos_core::{Range, Symbol, SymbolKind};
+
+    #[test]
+    fn test_glob_matching() {
+        let patterns = ExclusionPatterns::new(["**/node_modules/**", "*.generated.*", "build/", "src/gen?.rs"]);
+
+        assert!(patterns.is_excluded("file:///proj/node_modules/lib/index.js"));
+        assert!(patterns.is_excluded("file:///proj/api.generated.ts"));
+        assert!(patterns.is_excluded("file:///proj/out/types.generated.d.ts"));
+        assert!(patterns.is_excluded("file:///proj/build/main.o"));
+        assert!(patterns.is_excluded("file:///proj/src/gen1.rs"));
+
+        assert!(!patterns.is_excluded("file:///proj/src/main.ts"));
+        assert!(!patterns.is_excluded("file:///proj/my_node_modules/index.js"));
+        assert!(!patterns.is_excluded("file:///proj/generated/api.ts"));
+        assert!(!patterns.is_excluded("file:///proj/src/gen10.rs"));
+        assert!(!ExclusionPatterns::default().is_excluded("file:///proj/a.rs"));
+    }
+
+    #[test]
+    fn test_excluded_todos_omitted_from_aggregates() {
+        let mut index = TodoIndex::new();
+        index.index_document("file:///proj/src/main.rs", "// TODO: mine\n");
+        index.index_document("file:///proj/node_modules/dep/index.js", "// FIXME: theirs\n// TODO: theirs\n");
+        index.set_exclusions(ExclusionPatterns::new(["**/node_modules/**"]));
+
+        let all = index.get_all_todos();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].0, "file:///proj/src/main.rs");
+        assert_eq!(index.todo_count(), 1);
+        assert_eq!(index.count_by_kind().get(&TodoKind::Fixme), None);
+
+        // Still available when asked for directly
+        assert_eq!(index.get_document_todos("file:///proj/node_modules/dep/index.js").len(), 2);
+    }
+
+    #[test]
+    fn test_excluded_symbols_omitted_from_search() {
+        let range = Range::from_coords(0, 0, 0, 10);
+        let symbol = Symbol::new("parseConfig".to_string(), SymbolKind::Function, range, range);
+        let mut index = SymbolIndex::new();
+        index.index_document("file:///proj/src/config.ts", std::slice::from_ref(&symbol));
+        index.index_document("file:///proj/src/config.generated.ts", std::slice::from_ref(&symbol));
+        index.set_exclusions(ExclusionPatterns::new(["*.generated.*"]));
+
+        let results = index.search("parse");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].uri, "file:///proj/src/config.ts");
+        assert_eq!(index.get_document_symbols("file:///proj/src/config.generated.ts").len(), 1);
+    }
+}
@@ -0,0 +1,209 @@
+//! File Rename
+//!
+//! When a file moves, the imports that resolve to it (through the adapter's
+//! `resolve_import`) are rewritten to the path of its new location, relative
+//! to the importing file. Supported for `#include "..."`, relative ES module
+//! specifiers and relative Python imports.
+
+use crate::adapter::{ImportInfo, LanguageAdapter};
+use logos_core::{Position, Range};
+use std::path::{Component, Path, PathBuf};
+
+/// A rewritten import path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportPathEdit {
+    /// The importing document
+    pub uri: String,
+    /// The path as written, without quotes
+    pub range: Range,
+    pub new_text: String,
+}
+
+/// Edits to the imports of one document that point at `old_path` so they
+/// point at `new_path`
+pub fn import_path_edits(
+    adapter: &dyn LanguageAdapter,
+    uri: &str,
+    source: &str,
+    imports: &[ImportInfo],
+    old_path: &Path,
+    new_path: &Path,
+) -> Vec<ImportPathEdit> {
+    let file = match uri.strip_prefix("file://") {
+        Some(path) => Path::new(path),
+        None => return Vec::new(),
+    };
+
+    let mut edits = Vec::new();
+    for import in imports {
+        let resolved = match adapter.resolve_import(file, &import.module_path) {
+            Some(resolved) => resolved,
+            None => continue,
+        };
+        if normalize(&resolved) != normalize(old_path) {
+            continue;
+        }
+        let written = import.module_path.trim_matches(|c| c == '"' || c == '\'');
+        let new_text = match rewritten(adapter.language_id(), file, written, new_path) {
+            Some(text) => text,
+            None => continue,
+        };
+        if let Some(range) = path_range(source, import.location, written) {
+            edits.push(ImportPathEdit {
+                uri: uri.to_string(),
+                range,
+                new_text,
+            });
+        }
+    }
+    edits
+}
+
+/// How `importer` refers to `target`, in the style of the path it wrote
+fn rewritten(language_id: &str, importer: &Path, written: &str, target: &Path) -> Option<String> {
+    let relative = relative_path(importer.parent()?, target);
+    let segments: Vec<String> = relative
+        .components()
+        .map(|c| match c {
+            Component::ParentDir => "..".to_string(),
+            c => c.as_os_str().to_string_lossy().into_owned(),
+        })
+        .collect();
+
+    match language_id {
+        "c" | "cpp" => Some(segments.join("/")),
+        "typescript" | "javascript" => {
+            let mut path = segments.join("/");
+            // Keep the extension only if the import spelled one out
+            let has_extension = Path::new(written).extension().is_some();
+            if !has_extension {
+                if let Some(ext) = target.extension() {
+                    path.truncate(path.len() - ext.len() - 1);
+                }
+                if let Some(dir) = path.strip_suffix("/index").filter(|_| !written.ends_with("index")) {
+                    path = dir.to_string();
+                }
+            }
+            if !path.starts_with('.') {
+                path.insert_str(0, "./");
+            }
+            Some(path)
+        }
+        "python" => {
+            let ups = segments.iter().take_while(|s| *s == "..").count();
+            let mut names: Vec<&str> = segments[ups..].iter().map(String::as_str).collect();
+            if names.last() == Some(&"__init__.py") {
+                names.pop();
+            }
+            if let Some(last) = names.last_mut() {
+                *last = last.strip_suffix(".py").unwrap_or(last);
+            }
+            Some(format!("{}{}", ".".repeat(ups + 1), names.join(".")))
+        }
+        _ => None,
+    }
+}
+
+/// The range of `written` within an import statement
+pub(crate) fn path_range(source: &str, statement: Range, written: &str) -> Option<Range> {
+    let lines: Vec<&str> = source.split('\n').collect();
+    for line in statement.start.line..=statement.end.line {
+        let text = lines.get(line as usize)?;
+        let is_path_char = |c: char| c.is_alphanumeric() || matches!(c, '_' | '.' | '/' | '-');
+        let found = text.match_indices(written).find(|(i, _)| {
+            !text[..*i].ends_with(is_path_char) && !text[i + written.len()..].starts_with(is_path_char)
+        });
+        if let Some((column, _)) = found {
+            return Some(Range::new(
+                Position::new(line, column as u32),
+                Position::new(line, (column + written.len()) as u32),
+            ));
+        }
+    }
+    None
+}
+
+/// `path` without `.` and `..` components
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+/// `to` relative to the directory `from`
+fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let from = normalize(from);
+    let to = normalize(to);
+    let common = from.components().zip(to.components()).take_while(|(a, b)| a == b).count();
+    let mut relative = PathBuf::new();
+    for _ in from.components().skip(common) {
+        relative.push("..");
+    }
+    for component in to.components().skip(common) {
+        relative.push(component);
+    }
+    relative
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CppAdapter;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_moving_a_header_updates_includes() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/util.h"), "int helper();\n").unwrap();
+
+        let uri = format!("file://{}", root.join("src/main.cpp").display());
+        let source = "#include <vector>\n#include \"util.h\"\n#include \"other.h\"\n";
+        let adapter = CppAdapter::new().unwrap();
+        let imports = adapter.analyze(&uri, source).imports;
+
+        let edits = import_path_edits(
+            &adapter,
+            &uri,
+            source,
+            &imports,
+            &root.join("src/util.h"),
+            &root.join("include/common/util.h"),
+        );
+        assert_eq!(
+            edits,
+            vec![ImportPathEdit {
+                uri,
+                range: Range::from_coords(1, 10, 1, 16),
+                new_text: "../include/common/util.h".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_rewritten_paths() {
+        let importer = Path::new("/p/src/app/main.ts");
+        let target = Path::new("/p/src/lib/format.ts");
+        assert_eq!(rewritten("typescript", importer, "./format", target).unwrap(), "../lib/format");
+        assert_eq!(rewritten("typescript", importer, "./format.ts", target).unwrap(), "../lib/format.ts");
+        assert_eq!(
+            rewritten("typescript", importer, "./lib", Path::new("/p/src/app/lib/index.ts")).unwrap(),
+            "./lib"
+        );
+
+        let importer = Path::new("/p/pkg/sub/views.py");
+        assert_eq!(rewritten("python", importer, ".models", Path::new("/p/pkg/sub/db.py")).unwrap(), ".db");
+        assert_eq!(rewritten("python", importer, ".models", Path::new("/p/pkg/core/models.py")).unwrap(), "..core.models");
+        assert_eq!(rewritten("rust", importer, "models", Path::new("/p/pkg/models.rs")), None);
+    }
+}
@@ -28,7 +28,7 @@ impl GoAdapter {
     }
 
     fn parse(&self, source: &str) -> Option<Tree> {
-        let mut parser = self.parser.lock().ok()?;
+        let mut parser = self.parser.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
         parser.parse(source, None)
     }
 }
@@ -42,6 +42,10 @@ impl LanguageAdapter for GoAdapter {
         &["go"]
     }
 
+    fn keywords(&self) -> &[&str] {
+        logos_parser::go::get_keywords()
+    }
+
     fn analyze(&self, uri: &str, source: &str) -> AnalysisResult {
         let tree = match self.parse(source) {
             Some(t) => t,
@@ -144,6 +148,7 @@ fn analyze_import(node: &Node, ctx: &mut AnalysisContext) {
                     name: module_path,
                     alias,
                     is_type: false,
+                    range: node_to_range(&spec),
                 }],
                 is_type_only: false,
                 location: node_to_range(&spec),
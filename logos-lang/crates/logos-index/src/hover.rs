@@ -0,0 +1,227 @@
+//! Hover contents
+//!
+//! Markdown hover for symbols found by the language adapters: the
+//! declaration's signature in a fenced code block followed by the doc
+//! comment written above it.
+
+use crate::adapter::AnalysisResult;
+use crate::symbol_table::SmartSymbol;
+use logos_core::Position;
+
+/// The innermost symbol whose name range contains `position`
+pub fn symbol_at(result: &AnalysisResult, position: Position) -> Option<&SmartSymbol> {
+    result
+        .symbols
+        .iter()
+        .filter(|s| s.location.selection_range.contains(position))
+        .min_by_key(|s| {
+            let r = s.location.range;
+            (r.end.line - r.start.line, r.end.column.saturating_sub(r.start.column))
+        })
+}
+
+/// Signature of the symbol's declaration: its source text up to the body,
+/// with whitespace collapsed (`void greet(const std::string& name)`)
+pub fn signature(source: &str, symbol: &SmartSymbol) -> Option<String> {
+    let range = symbol.location.range;
+    let lines: Vec<&str> = source.lines().collect();
+    let mut text = String::new();
+
+    for line_no in range.start.line..=range.end.line {
+        let line = *lines.get(line_no as usize)?;
+        let start = if line_no == range.start.line { range.start.column as usize } else { 0 };
+        let end = if line_no == range.end.line { (range.end.column as usize).min(line.len()) } else { line.len() };
+        let part = line.get(start.min(end)..end)?;
+
+        // The body starts at the first `{`, `;` or (Python) line-ending `:`
+        if let Some(cut) = part.find(['{', ';']) {
+            text.push_str(&part[..cut]);
+            break;
+        }
+        if part.trim_end().ends_with(':') && !part.trim_end().ends_with("::") {
+            text.push_str(part.trim_end().trim_end_matches(':'));
+            break;
+        }
+        text.push_str(part);
+        text.push(' ');
+    }
+
+    let signature = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if signature.is_empty() {
+        None
+    } else {
+        Some(signature)
+    }
+}
+
+/// The doc comment directly above `line`: `///`, `//!`, `/** */` or `#` lines,
+/// with the comment markers removed
+pub fn doc_comment(source: &str, line: u32) -> Option<String> {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut doc: Vec<String> = Vec::new();
+    let mut index = line as usize;
+
+    while index > 0 {
+        index -= 1;
+        let text = lines.get(index)?.trim();
+
+        if let Some(rest) = text.strip_prefix("///").or_else(|| text.strip_prefix("//!")) {
+            doc.push(rest.trim().to_string());
+        } else if let Some(rest) = hash_comment(text) {
+            doc.push(rest.to_string());
+        } else if text.ends_with("*/") {
+            // Walk up to the start of the block; only `/**` blocks are docs
+            let mut block = vec![text];
+            while !block.last()?.starts_with("/*") {
+                if index == 0 {
+                    return None;
+                }
+                index -= 1;
+                block.push(lines[index].trim());
+            }
+            if !block.last()?.starts_with("/**") {
+                break;
+            }
+            for part in block {
+                let part = part.trim_start_matches("/**").trim_end_matches("*/").trim();
+                let part = part.strip_prefix('*').unwrap_or(part).trim();
+                doc.push(part.to_string());
+            }
+        } else {
+            break;
+        }
+    }
+
+    doc.reverse();
+    while doc.first().is_some_and(|l| l.is_empty()) {
+        doc.remove(0);
+    }
+    while doc.last().is_some_and(|l| l.is_empty()) {
+        doc.pop();
+    }
+
+    if doc.is_empty() {
+        None
+    } else {
+        Some(doc.join("\n"))
+    }
+}
+
+/// `# text` comment; preprocessor lines and attributes (`#include`, `#[derive]`) are not
+fn hash_comment(text: &str) -> Option<&str> {
+    let rest = text.strip_prefix('#')?;
+    if rest.is_empty() || rest.starts_with(' ') {
+        Some(rest.trim())
+    } else {
+        None
+    }
+}
+
+/// Markdown hover for a symbol declared in `source`; `language` tags the
+/// signature's code block. Without a signature or doc comment this is just
+/// `**name** (Kind)`.
+pub fn hover_markdown(symbol: &SmartSymbol, source: &str, language: &str) -> String {
+    let mut signature = signature(source, symbol);
+    // A Python variable typed by a `# type:` comment after the assignment
+    if let (Some(text), Some(ty), "python") = (&mut signature, &symbol.type_info, language) {
+        if !text.contains(&format!(": {}", ty.type_expr)) {
+            text.push_str(&format!("  # type: {}", ty.type_expr));
+        }
+    }
+    let signature = signature.or_else(|| symbol.type_info.as_ref().map(|t| t.type_expr.clone()));
+    let doc = symbol
+        .documentation
+        .clone()
+        .or_else(|| doc_comment(source, symbol.location.range.start.line));
+
+    if signature.is_none() && doc.is_none() {
+        return format!("**{}** ({:?})", symbol.name, symbol.kind);
+    }
+
+    let mut sections = Vec::new();
+    if let Some(signature) = signature {
+        sections.push(format!("```{}\n{}\n```", language, signature));
+    }
+    if let Some(doc) = doc {
+        sections.push(doc);
+    }
+    sections.join("\n\n---\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter::LanguageAdapter;
+    use crate::cpp_adapter::CppAdapter;
+
+    const FIXTURE: &str = r#"
+class User {
+  public:
+    /// Greets the user by name.
+    /// Returns nothing.
+    void greet(const std::string& name,
+               int times) {
+      log(name);
+    }
+
+    /**
+     * Resets the counters.
+     */
+    void reset() {}
+
+    void plain() {}
+};
+"#;
+
+    fn hover_for(name: &str) -> String {
+        let adapter = CppAdapter::new().unwrap();
+        let result = adapter.analyze("file:///user.cpp", FIXTURE);
+        let symbol = result.symbols.iter().find(|s| s.name == name).unwrap();
+        hover_markdown(symbol, FIXTURE, "cpp")
+    }
+
+    #[test]
+    fn test_cpp_method_hover() {
+        assert_eq!(
+            hover_for("greet"),
+            "```cpp\nvoid greet(const std::string& name, int times)\n```\n\n---\n\nGreets the user by name.\nReturns nothing."
+        );
+        assert_eq!(hover_for("reset"), "```cpp\nvoid reset()\n```\n\n---\n\nResets the counters.");
+        assert_eq!(hover_for("plain"), "```cpp\nvoid plain()\n```");
+    }
+
+    #[test]
+    fn test_hover_without_source_falls_back() {
+        let adapter = CppAdapter::new().unwrap();
+        let result = adapter.analyze("file:///user.cpp", FIXTURE);
+        let symbol = result.symbols.iter().find(|s| s.name == "greet").unwrap();
+        assert_eq!(hover_markdown(symbol, "", "cpp"), "**greet** (Method)");
+    }
+
+    #[test]
+    fn test_python_type_comment_hover() {
+        let adapter = crate::python_adapter::PythonAdapter::new().unwrap();
+        let source = "users = load()  # type: List[User]\ncount: int = 0\n";
+        let result = adapter.analyze("file:///app.py", source);
+        let hover = |name: &str| hover_markdown(result.symbols.iter().find(|s| s.name == name).unwrap(), source, "python");
+        assert_eq!(hover("users"), "```python\nusers = load()  # type: List[User]\n```");
+        assert_eq!(hover("count"), "```python\ncount: int = 0\n```");
+    }
+
+    #[test]
+    fn test_symbol_at() {
+        let adapter = CppAdapter::new().unwrap();
+        let result = adapter.analyze("file:///user.cpp", FIXTURE);
+        assert_eq!(symbol_at(&result, Position::new(5, 10)).unwrap().name, "greet");
+        assert!(symbol_at(&result, Position::new(7, 6)).is_none());
+    }
+
+    #[test]
+    fn test_doc_comment_styles() {
+        let source = "#include <x>\n# Adds numbers\ndef add(a, b):\n    pass\n//! not attached\n\nint x;\n";
+        assert_eq!(doc_comment(source, 2).as_deref(), Some("Adds numbers"));
+        assert_eq!(doc_comment(source, 0), None);
+        assert_eq!(doc_comment(source, 6), None);
+        assert_eq!(doc_comment("/* plain */\nint y;\n", 1), None);
+    }
+}
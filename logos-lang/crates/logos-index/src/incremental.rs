@@ -1,9 +1,30 @@
 //! Incremental index updates
 
-use crate::SymbolIndex;
+use crate::{SymbolId, SymbolIndex};
 use logos_core::Symbol;
 use std::collections::HashSet;
 
+/// How the symbols of a document changed in an update of the index, by the
+/// ids they have after it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolDiff {
+    /// New symbols, with fresh ids
+    pub added: Vec<SymbolId>,
+    /// Symbols no longer in the document
+    pub removed: Vec<SymbolId>,
+    /// Symbols kept with a new range
+    pub moved: Vec<SymbolId>,
+    /// Symbols kept where they were
+    pub unchanged: Vec<SymbolId>,
+}
+
+impl SymbolDiff {
+    /// Whether the update added, removed or moved nothing
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.moved.is_empty()
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct ChangeTracker {
     modified: HashSet<String>,
@@ -59,7 +80,7 @@ impl IncrementalIndexer {
         let modified: Vec<_> = self.tracker.modified_documents().map(String::from).collect();
         for uri in modified {
             if let Some(symbols) = get_symbols(&uri) {
-                index.index_document(&uri, &symbols);
+                index.update_document(&uri, &symbols);
             }
         }
         self.tracker.clear();
@@ -71,3 +92,35 @@ impl IncrementalIndexer {
 impl Default for IncrementalIndexer {
     fn default() -> Self { Self::new() }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LanguageAdapter, PythonAdapter};
+
+    #[test]
+    fn test_symbol_ids_survive_edits() {
+        let adapter = PythonAdapter::new().unwrap();
+        let uri = "file:///app.py";
+        let mut index = SymbolIndex::new();
+        let id_of = |index: &SymbolIndex, name: &str| index.get_document_symbols(uri).iter().find(|s| s.name == name).map(|s| s.id);
+
+        let mut before = adapter.analyze(uri, "def load():\n    pass\n\ndef save():\n    pass\n");
+        assert_eq!(index.apply_analysis(uri, &mut before).added.len(), 2);
+        let save = id_of(&index, "save").unwrap();
+
+        // `check` is new and pushes `save` down; `load` is gone
+        let mut after = adapter.analyze(uri, "def check():\n    pass\n\n\ndef save():\n    pass\n");
+        let diff = index.apply_analysis(uri, &mut after);
+        let check = id_of(&index, "check").unwrap();
+
+        assert_eq!(id_of(&index, "save"), Some(save));
+        assert_eq!(diff.moved, vec![save]);
+        assert_eq!(diff.added, vec![check]);
+        assert!(before.symbols.iter().all(|s| s.id != check));
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(id_of(&index, "load"), None);
+        // The analysis carries the kept ids too
+        assert!(after.symbols.iter().any(|s| s.name == "save" && s.id == save));
+    }
+}
@@ -11,10 +11,40 @@ use crate::python_adapter::PythonAdapter;
 use crate::rust_adapter::RustAdapter;
 use crate::symbol_table::{CallSite, CallType, ProjectIndex};
 use crate::typescript_adapter::TypeScriptAdapter;
+use logos_core::CancellationToken;
 use std::fs;
 use std::path::Path;
 use std::sync::Arc;
 
+/// Create the built-in language adapters
+pub fn builtin_adapters() -> Vec<Box<dyn LanguageAdapter>> {
+    let mut adapters: Vec<Box<dyn LanguageAdapter>> = Vec::new();
+
+    if let Ok(ts_adapter) = TypeScriptAdapter::new() {
+        adapters.push(Box::new(ts_adapter));
+    }
+    if let Ok(py_adapter) = PythonAdapter::new() {
+        adapters.push(Box::new(py_adapter));
+    }
+    if let Ok(go_adapter) = GoAdapter::new() {
+        adapters.push(Box::new(go_adapter));
+    }
+    if let Ok(rust_adapter) = RustAdapter::new() {
+        adapters.push(Box::new(rust_adapter));
+    }
+    if let Ok(c_adapter) = CAdapter::new() {
+        adapters.push(Box::new(c_adapter));
+    }
+    if let Ok(cpp_adapter) = CppAdapter::new() {
+        adapters.push(Box::new(cpp_adapter));
+    }
+    if let Ok(java_adapter) = JavaAdapter::new() {
+        adapters.push(Box::new(java_adapter));
+    }
+
+    adapters
+}
+
 /// Project indexer that coordinates language adapters
 pub struct ProjectIndexer {
     /// The project index containing all indexed data
@@ -25,35 +55,10 @@ pub struct ProjectIndexer {
 
 impl ProjectIndexer {
     pub fn new() -> Self {
-        let mut indexer = Self {
+        Self {
             index: Arc::new(ProjectIndex::new()),
-            adapters: Vec::new(),
-        };
-
-        // Register built-in adapters
-        if let Ok(ts_adapter) = TypeScriptAdapter::new() {
-            indexer.register_adapter(Box::new(ts_adapter));
-        }
-        if let Ok(py_adapter) = PythonAdapter::new() {
-            indexer.register_adapter(Box::new(py_adapter));
-        }
-        if let Ok(go_adapter) = GoAdapter::new() {
-            indexer.register_adapter(Box::new(go_adapter));
-        }
-        if let Ok(rust_adapter) = RustAdapter::new() {
-            indexer.register_adapter(Box::new(rust_adapter));
-        }
-        if let Ok(c_adapter) = CAdapter::new() {
-            indexer.register_adapter(Box::new(c_adapter));
-        }
-        if let Ok(cpp_adapter) = CppAdapter::new() {
-            indexer.register_adapter(Box::new(cpp_adapter));
+            adapters: builtin_adapters(),
         }
-        if let Ok(java_adapter) = JavaAdapter::new() {
-            indexer.register_adapter(Box::new(java_adapter));
-        }
-
-        indexer
     }
 
     /// Register a language adapter
@@ -160,18 +165,28 @@ impl ProjectIndexer {
 
     /// Index a directory recursively
     pub fn index_directory(&self, dir: &Path) -> Result<IndexingStats, String> {
+        self.index_directory_cancellable(dir, &CancellationToken::new())
+    }
+
+    /// Index a directory recursively, stopping between files once `cancel`
+    /// is raised; the files indexed until then stay in the index
+    pub fn index_directory_cancellable(&self, dir: &Path, cancel: &CancellationToken) -> Result<IndexingStats, String> {
         let mut stats = IndexingStats::default();
 
-        self.index_directory_recursive(dir, &mut stats)?;
+        self.index_directory_recursive(dir, cancel, &mut stats)?;
+        stats.cancelled = cancel.is_cancelled();
 
         Ok(stats)
     }
 
-    fn index_directory_recursive(&self, dir: &Path, stats: &mut IndexingStats) -> Result<(), String> {
+    fn index_directory_recursive(&self, dir: &Path, cancel: &CancellationToken, stats: &mut IndexingStats) -> Result<(), String> {
         let entries = fs::read_dir(dir)
             .map_err(|e| format!("Failed to read directory {:?}: {}", dir, e))?;
 
         for entry in entries.flatten() {
+            if cancel.is_cancelled() {
+                break;
+            }
             let path = entry.path();
 
             // Skip hidden files and common ignored directories
@@ -188,7 +203,7 @@ impl ProjectIndexer {
             }
 
             if path.is_dir() {
-                self.index_directory_recursive(&path, stats)?;
+                self.index_directory_recursive(&path, cancel, stats)?;
             } else if path.is_file()
                 && self.find_adapter(&path).is_some() {
                     match self.index_file(&path) {
@@ -243,6 +258,8 @@ pub struct IndexingStats {
     pub calls_found: usize,
     pub type_relations_found: usize,
     pub errors: Vec<String>,
+    /// Whether indexing stopped early on cancellation
+    pub cancelled: bool,
 }
 
 /// Convert a file path to a URI
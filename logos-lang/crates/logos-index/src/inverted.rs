@@ -11,23 +11,35 @@ impl InvertedIndex {
     pub fn new() -> Self { Self::default() }
 
     pub fn add(&mut self, name: &str, uri: &str) {
-        let name_lower = name.to_lowercase();
-        self.index.entry(name_lower.clone()).or_default().insert(uri.to_string());
-        for i in 2..=name_lower.len() {
-            let prefix = &name_lower[..i];
-            self.index.entry(prefix.to_string()).or_default().insert(uri.to_string());
+        for key in keys(name) {
+            self.index.entry(key).or_default().insert(uri.to_string());
         }
     }
 
     pub fn remove(&mut self, name: &str, uri: &str) {
-        let name_lower = name.to_lowercase();
-        for i in 2..=name_lower.len() {
-            let prefix = &name_lower[..i];
-            if let Some(uris) = self.index.get_mut(prefix) {
-                uris.remove(uri);
-                if uris.is_empty() {
-                    self.index.remove(prefix);
-                }
+        for key in keys(name) {
+            self.remove_key(&key, uri);
+        }
+    }
+
+    /// Replace the names of `uri`: only keys no new name shares are
+    /// dropped, and only keys no old name had are added
+    pub fn replace(&mut self, uri: &str, old_names: &[&str], new_names: &[&str]) {
+        let old_keys: HashSet<String> = old_names.iter().flat_map(|name| keys(name)).collect();
+        let new_keys: HashSet<String> = new_names.iter().flat_map(|name| keys(name)).collect();
+        for key in old_keys.difference(&new_keys) {
+            self.remove_key(key, uri);
+        }
+        for key in new_keys.difference(&old_keys) {
+            self.index.entry(key.clone()).or_default().insert(uri.to_string());
+        }
+    }
+
+    fn remove_key(&mut self, key: &str, uri: &str) {
+        if let Some(uris) = self.index.get_mut(key) {
+            uris.remove(uri);
+            if uris.is_empty() {
+                self.index.remove(key);
             }
         }
     }
@@ -39,3 +51,34 @@ impl InvertedIndex {
 
     pub fn clear(&mut self) { self.index.clear(); }
 }
+
+/// The lowercased name and its prefixes of two or more characters
+fn keys(name: &str) -> Vec<String> {
+    let name_lower = name.to_lowercase();
+    let mut keys: Vec<String> = name_lower
+        .char_indices()
+        .skip(2)
+        .map(|(i, _)| name_lower[..i].to_string())
+        .collect();
+    keys.push(name_lower);
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_keeps_shared_prefixes() {
+        let mut index = InvertedIndex::new();
+        index.add("format", "a");
+        index.add("foo", "a");
+        index.add("x", "a");
+
+        index.replace("a", &["format", "foo", "x"], &["format", "fold"]);
+        assert_eq!(index.search("fo"), vec!["a"]);
+        assert_eq!(index.search("fold"), vec!["a"]);
+        assert!(index.search("foo").is_empty());
+        assert!(index.search("x").is_empty());
+    }
+}
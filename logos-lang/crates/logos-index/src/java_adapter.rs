@@ -5,8 +5,9 @@
 //! - Imports: import declarations
 //! - Exports: public/protected treated as exported (best-effort)
 //! - Calls: method_invocation nodes (best-effort)
+//! - Type relations: extends/implements clauses
 
-use crate::adapter::{AnalysisResult, CallInfo, ImportInfo, ImportItem, LanguageAdapter, SymbolBuilder, make_location};
+use crate::adapter::{AnalysisResult, CallInfo, ImportInfo, ImportItem, LanguageAdapter, SymbolBuilder, TypeRelation, make_location};
 use crate::symbol_table::{SymbolId, Visibility};
 use logos_core::{Position, Range, SymbolKind};
 use std::path::Path;
@@ -28,7 +29,7 @@ impl JavaAdapter {
     }
 
     fn parse(&self, source: &str) -> Option<Tree> {
-        let mut parser = self.parser.lock().ok()?;
+        let mut parser = self.parser.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
         parser.parse(source, None)
     }
 }
@@ -42,6 +43,10 @@ impl LanguageAdapter for JavaAdapter {
         &["java"]
     }
 
+    fn keywords(&self) -> &[&str] {
+        logos_parser::java::get_keywords()
+    }
+
     fn analyze(&self, uri: &str, source: &str) -> AnalysisResult {
         let tree = match self.parse(source) {
             Some(t) => t,
@@ -164,6 +169,7 @@ fn analyze_import(node: &Node, ctx: &mut AnalysisContext) {
             name: module_path,
             alias: None,
             is_type: true,
+            range: node_to_range(node),
         }],
         is_type_only: true,
         location: node_to_range(node),
@@ -188,6 +194,18 @@ fn analyze_class(node: &Node, ctx: &mut AnalysisContext, kind: SymbolKind) {
     let id = sym.id;
     ctx.result.symbols.push(sym);
 
+    for i in 0..node.named_child_count() {
+        if let Some(clause) = node.named_child(i) {
+            match clause.kind() {
+                "superclass" | "extends_interfaces" => {
+                    analyze_supertypes(&clause, ctx, &name, false)
+                }
+                "super_interfaces" => analyze_supertypes(&clause, ctx, &name, true),
+                _ => {}
+            }
+        }
+    }
+
     if let Some(body) = node.child_by_field_name("body") {
         ctx.scope_stack.push(ScopeInfo { symbol_id: id, name });
         for i in 0..body.named_child_count() {
@@ -199,6 +217,28 @@ fn analyze_class(node: &Node, ctx: &mut AnalysisContext, kind: SymbolKind) {
     }
 }
 
+fn analyze_supertypes(node: &Node, ctx: &mut AnalysisContext, child_name: &str, is_implements: bool) {
+    for i in 0..node.named_child_count() {
+        if let Some(child) = node.named_child(i) {
+            match child.kind() {
+                "type_identifier" | "scoped_type_identifier" | "generic_type" => {
+                    let text = ctx.get_text(&child);
+                    let base = text.split('<').next().unwrap_or(&text);
+                    let parent_name = base.rsplit('.').next().unwrap_or(base).trim().to_string();
+                    ctx.result.type_relations.push(TypeRelation {
+                        child_name: child_name.to_string(),
+                        parent_name,
+                        is_implements,
+                        location: node_to_range(&child),
+                    });
+                }
+                // `implements A, B` wraps the types in a type_list
+                _ => analyze_supertypes(&child, ctx, child_name, is_implements),
+            }
+        }
+    }
+}
+
 fn analyze_method(node: &Node, ctx: &mut AnalysisContext) {
     let name_node = node.child_by_field_name("name");
     let name_node = match name_node {
@@ -320,5 +360,26 @@ public class User {
         assert!(result.symbols.iter().any(|s| s.name == "greet"));
         assert!(result.calls.len() >= 1);
     }
-}
 
+    #[test]
+    fn java_extends_and_implements() {
+        let adapter = JavaAdapter::new().unwrap();
+        let src = r#"
+public class Circle extends Shape implements Drawable, Comparable<Circle> {
+}
+"#;
+        let result = adapter.analyze("file:///Circle.java", src);
+        assert!(result
+            .type_relations
+            .iter()
+            .any(|r| r.parent_name == "Shape" && !r.is_implements));
+        assert!(result
+            .type_relations
+            .iter()
+            .any(|r| r.parent_name == "Drawable" && r.is_implements));
+        assert!(result
+            .type_relations
+            .iter()
+            .any(|r| r.parent_name == "Comparable" && r.is_implements));
+    }
+}
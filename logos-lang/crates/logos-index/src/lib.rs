@@ -1,16 +1,27 @@
 //! Logos Index - Symbol indexing for fast lookup
 
 pub mod adapter;
+pub mod brace_adapter;
+pub mod call_hierarchy;
 pub mod comments;
 pub mod c_adapter;
 pub mod cpp_adapter;
+pub mod exclude;
+pub mod file_rename;
 pub mod go_adapter;
+pub mod hover;
 pub mod incremental;
 pub mod indexer;
 pub mod inverted;
 pub mod java_adapter;
+pub mod links;
+pub mod member_completion;
+pub mod navigation;
 pub mod python_adapter;
+pub mod registry;
 pub mod rust_adapter;
+pub mod semantic_tokens;
+pub mod snippets;
 pub mod symbol_table;
 pub mod typescript_adapter;
 
@@ -18,40 +29,59 @@ pub use adapter::{
     AnalysisResult, CallInfo, ExportInfo, ImportInfo, ImportItem, LanguageAdapter,
     SymbolBuilder, TypeRelation, make_location,
 };
-pub use comments::{CommentScanner, ScannerConfig, TodoIndex, TodoItem, TodoKind};
+pub use brace_adapter::BraceLanguageAdapter;
+pub use comments::{
+    CommentBlock, CommentScanner, CommentStyle, IssueRef, PriorityChange, PriorityCondition, PriorityRule, PriorityRules,
+    ScannerConfig, StreamScan, TodoChanges, TodoIndex, TodoItem, TodoKind, TodoStats,
+};
 pub use c_adapter::CAdapter;
 pub use cpp_adapter::CppAdapter;
+pub use exclude::ExclusionPatterns;
+pub use incremental::SymbolDiff;
 pub use go_adapter::GoAdapter;
-pub use indexer::{IndexingStats, ProjectIndexer};
+pub use indexer::{builtin_adapters, IndexingStats, ProjectIndexer};
 pub use java_adapter::JavaAdapter;
 pub use python_adapter::PythonAdapter;
+pub use registry::AdapterRegistry;
 pub use rust_adapter::RustAdapter;
+pub use semantic_tokens::{SemanticToken, SemanticTokenType};
 pub use symbol_table::{
     Attribute, CallGraph, CallSite, CallType, DependencyGraph, ProjectIndex, SmartSymbol, SymbolId,
     SymbolLocation, SymbolReference, SymbolTable, TypeHierarchy, TypeInfo, Visibility,
 };
 pub use typescript_adapter::TypeScriptAdapter;
 use logos_core::{Position, Range, Symbol, SymbolKind};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 #[derive(Debug, Clone)]
 pub struct IndexedSymbol {
+    /// Kept across updates of the document for as long as the qualified
+    /// name and kind stay the same
+    pub id: SymbolId,
     pub name: String,
     pub kind: SymbolKind,
     pub uri: String,
     pub range: Range,
     pub selection_range: Range,
     pub container: Option<String>,
+    /// The names of the enclosing symbols and the symbol's own, joined with
+    /// `.`, or the qualified name the language adapter gave it
+    pub qualified_name: String,
 }
 
 impl IndexedSymbol {
     pub fn from_symbol(symbol: &Symbol, uri: &str, container: Option<String>) -> Self {
         Self {
+            id: SymbolId::new(),
             name: symbol.name.clone(),
             kind: symbol.kind,
             uri: uri.to_string(),
             range: symbol.range,
             selection_range: symbol.selection_range,
+            qualified_name: match &container {
+                Some(container) => format!("{}.{}", container, symbol.name),
+                None => symbol.name.clone(),
+            },
             container,
         }
     }
@@ -61,26 +91,107 @@ impl IndexedSymbol {
 pub struct SymbolIndex {
     by_document: HashMap<String, Vec<IndexedSymbol>>,
     inverted: inverted::InvertedIndex,
+    /// Documents left out of `search`
+    exclusions: ExclusionPatterns,
 }
 
 impl SymbolIndex {
     pub fn new() -> Self { Self::default() }
 
     pub fn index_document(&mut self, uri: &str, symbols: &[Symbol]) {
-        self.remove_document(uri);
+        self.update_document(uri, symbols);
+    }
+
+    /// Replace the symbols of a document. The name index is only touched
+    /// for names that were added or removed, so an edit that keeps the
+    /// names costs no more than rebuilding the document's own list.
+    pub fn update_document(&mut self, uri: &str, symbols: &[Symbol]) -> SymbolDiff {
         let mut indexed = Vec::new();
         self.index_symbols_recursive(uri, symbols, None, &mut indexed);
-        for symbol in &indexed {
-            self.inverted.add(&symbol.name, uri);
+        self.replace_document(uri, indexed)
+    }
+
+    /// Apply a new analysis of a document as a diff against the indexed
+    /// symbols: those whose qualified name and kind are unchanged keep their
+    /// ids wherever they moved, and `result` is rewritten to use them, so
+    /// ids held across edits (call hierarchy items, rename) stay valid. New
+    /// symbols keep the ids the adapter gave them.
+    pub fn apply_analysis(&mut self, uri: &str, result: &mut AnalysisResult) -> SymbolDiff {
+        let indexed: Vec<IndexedSymbol> = result
+            .symbols_in_tree_order()
+            .into_iter()
+            .map(|(symbol, parent, qualified_name)| IndexedSymbol {
+                id: symbol.id,
+                name: symbol.name.clone(),
+                kind: symbol.kind,
+                uri: uri.to_string(),
+                range: symbol.location.range,
+                selection_range: symbol.location.selection_range,
+                container: parent.map(|p| p.name.clone()),
+                qualified_name,
+            })
+            .collect();
+        let fresh: Vec<SymbolId> = indexed.iter().map(|s| s.id).collect();
+
+        let diff = self.replace_document(uri, indexed);
+        let ids: HashMap<SymbolId, SymbolId> = fresh
+            .into_iter()
+            .zip(self.get_document_symbols(uri).iter().map(|s| s.id))
+            .filter(|(fresh, kept)| fresh != kept)
+            .collect();
+        result.remap_ids(&ids);
+        diff
+    }
+
+    /// Store `indexed` as the symbols of a document, keeping the id of each
+    /// symbol whose qualified name and kind were there before; symbols
+    /// sharing both are matched in order
+    fn replace_document(&mut self, uri: &str, mut indexed: Vec<IndexedSymbol>) -> SymbolDiff {
+        let old = self.by_document.remove(uri).unwrap_or_default();
+        let mut previous: HashMap<(&str, SymbolKind), VecDeque<&IndexedSymbol>> = HashMap::new();
+        for symbol in &old {
+            previous.entry((symbol.qualified_name.as_str(), symbol.kind)).or_default().push_back(symbol);
+        }
+
+        let mut diff = SymbolDiff::default();
+        let mut kept = HashSet::new();
+        for symbol in &mut indexed {
+            let before = previous
+                .get_mut(&(symbol.qualified_name.as_str(), symbol.kind))
+                .and_then(|candidates| candidates.pop_front());
+            match before {
+                Some(before) => {
+                    symbol.id = before.id;
+                    kept.insert(before.id);
+                    if before.range == symbol.range && before.selection_range == symbol.selection_range {
+                        diff.unchanged.push(symbol.id);
+                    } else {
+                        diff.moved.push(symbol.id);
+                    }
+                }
+                None => diff.added.push(symbol.id),
+            }
         }
+        diff.removed = old.iter().map(|s| s.id).filter(|id| !kept.contains(id)).collect();
+
+        let old_names: Vec<&str> = old.iter().map(|s| s.name.as_str()).collect();
+        let new_names: Vec<&str> = indexed.iter().map(|s| s.name.as_str()).collect();
+        self.inverted.replace(uri, &old_names, &new_names);
         self.by_document.insert(uri.to_string(), indexed);
+        diff
     }
 
-    fn index_symbols_recursive(&self, uri: &str, symbols: &[Symbol], container: Option<&str>, indexed: &mut Vec<IndexedSymbol>) {
+    /// `container` is the name and qualified name of the enclosing symbol
+    fn index_symbols_recursive(&self, uri: &str, symbols: &[Symbol], container: Option<(&str, &str)>, indexed: &mut Vec<IndexedSymbol>) {
         for symbol in symbols {
-            indexed.push(IndexedSymbol::from_symbol(symbol, uri, container.map(String::from)));
+            let mut entry = IndexedSymbol::from_symbol(symbol, uri, container.map(|(name, _)| name.to_string()));
+            if let Some((_, qualified)) = container {
+                entry.qualified_name = format!("{}.{}", qualified, symbol.name);
+            }
+            let qualified = entry.qualified_name.clone();
+            indexed.push(entry);
             if !symbol.children.is_empty() {
-                self.index_symbols_recursive(uri, &symbol.children, Some(&symbol.name), indexed);
+                self.index_symbols_recursive(uri, &symbol.children, Some((&symbol.name, &qualified)), indexed);
             }
         }
     }
@@ -97,10 +208,18 @@ impl SymbolIndex {
         self.by_document.get(uri).map(|v| v.as_slice()).unwrap_or(&[])
     }
 
+    /// Exclude matching documents from `search`; they stay indexed
+    pub fn set_exclusions(&mut self, exclusions: ExclusionPatterns) {
+        self.exclusions = exclusions;
+    }
+
     pub fn search(&self, query: &str) -> Vec<&IndexedSymbol> {
         let uris = self.inverted.search(query);
         let mut results = Vec::new();
         for uri in uris {
+            if self.exclusions.is_excluded(&uri) {
+                continue;
+            }
             if let Some(symbols) = self.by_document.get(&uri) {
                 for symbol in symbols {
                     if symbol.name.to_lowercase().contains(&query.to_lowercase()) {
@@ -0,0 +1,107 @@
+//! Document Links
+//!
+//! Clickable ranges of a document: import paths that resolve to a file
+//! (through the adapter's `resolve_import`) link to that file, and issue
+//! references in TODO comments link to the issue tracker through URL
+//! templates per project key (see `IssueRef::url`).
+
+use crate::adapter::{ImportInfo, LanguageAdapter};
+use crate::comments::TodoItem;
+use crate::file_rename::path_range;
+use logos_core::Range;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// A link from a range of a document to a URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentLink {
+    pub range: Range,
+    pub target: String,
+}
+
+/// Links from the import paths of a document to the files they resolve to
+pub fn import_links(adapter: &dyn LanguageAdapter, uri: &str, source: &str, imports: &[ImportInfo]) -> Vec<DocumentLink> {
+    let file = match uri.strip_prefix("file://") {
+        Some(path) => Path::new(path),
+        None => return Vec::new(),
+    };
+
+    imports
+        .iter()
+        .filter_map(|import| {
+            let resolved = adapter.resolve_import(file, &import.module_path)?;
+            let written = import.module_path.trim_matches(|c| c == '"' || c == '\'');
+            Some(DocumentLink {
+                range: path_range(source, import.location, written)?,
+                target: format!("file://{}", resolved.display()),
+            })
+        })
+        .collect()
+}
+
+/// Links from the issue references in TODO items to their URLs; references
+/// without a template for their project key are left out
+pub fn issue_links(todos: &[TodoItem], templates: &HashMap<String, String>) -> Vec<DocumentLink> {
+    todos
+        .iter()
+        .flat_map(|todo| &todo.issue_refs)
+        .filter_map(|issue| {
+            Some(DocumentLink {
+                range: issue.range,
+                target: issue.url(templates)?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CommentScanner, CppAdapter};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_include_links_to_resolved_file() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("util.h"), "int helper();\n").unwrap();
+
+        let uri = format!("file://{}", root.join("main.cpp").display());
+        let source = "#include <vector>\n#include \"util.h\"\n#include \"missing.h\"\n";
+        let adapter = CppAdapter::new().unwrap();
+        let imports = adapter.analyze(&uri, source).imports;
+
+        assert_eq!(
+            import_links(&adapter, &uri, source, &imports),
+            vec![DocumentLink {
+                range: Range::from_coords(1, 10, 1, 16),
+                target: format!("file://{}", root.join("util.h").display()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_issue_links_use_templates() {
+        let source = "// TODO: drop after #42 lands\n// FIXME(ana): JIRA-7 and OPS-9\n";
+        let todos = CommentScanner::default().scan_file(source, "main.rs");
+        let templates = HashMap::from([
+            ("#".to_string(), "https://github.com/acme/app/issues/{number}".to_string()),
+            ("JIRA".to_string(), "https://acme.atlassian.net/browse/{id}".to_string()),
+        ]);
+
+        assert_eq!(
+            issue_links(&todos, &templates),
+            vec![
+                DocumentLink {
+                    range: Range::from_coords(0, 20, 0, 23),
+                    target: "https://github.com/acme/app/issues/42".to_string(),
+                },
+                DocumentLink {
+                    range: Range::from_coords(1, 15, 1, 21),
+                    target: "https://acme.atlassian.net/browse/JIRA-7".to_string(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,171 @@
+//! Member completion
+//!
+//! Completion right after `.`, `->` or `::`: the receiver before the
+//! operator is resolved to a type name from its declaration earlier in the
+//! document, and the type's members are the symbols the adapter analysis
+//! nests inside a type or namespace of that name.
+
+use crate::adapter::AnalysisResult;
+use crate::symbol_table::{SmartSymbol, SymbolId};
+use logos_core::SymbolKind;
+use regex::Regex;
+
+/// A member access being typed, e.g. `user.na`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemberAccess {
+    /// The identifier before the operator
+    pub receiver: String,
+    /// `.`, `->` or `::`
+    pub operator: &'static str,
+    /// The part of the member name typed so far
+    pub prefix: String,
+}
+
+/// The member access ending at byte `offset`, if the cursor follows one
+pub fn member_access(source: &str, offset: usize) -> Option<MemberAccess> {
+    let before = source.get(..offset)?;
+    let prefix_start = before.trim_end_matches(is_identifier_char).len();
+    let prefix = &before[prefix_start..];
+
+    let rest = &before[..prefix_start];
+    let operator = ["->", "::", "."].into_iter().find(|op| rest.ends_with(op))?;
+    let rest = &rest[..rest.len() - operator.len()];
+    let receiver = &rest[rest.trim_end_matches(is_identifier_char).len()..];
+    if receiver.is_empty() || receiver.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+
+    Some(MemberAccess {
+        receiver: receiver.to_string(),
+        operator,
+        prefix: prefix.to_string(),
+    })
+}
+
+/// The type of `access.receiver`, from its last declaration before `offset`.
+///
+/// The receiver of `::` is itself the type or namespace.
+pub fn receiver_type(source: &str, access: &MemberAccess, offset: usize) -> Option<String> {
+    if access.operator == "::" {
+        return Some(access.receiver.clone());
+    }
+
+    let before = source.get(..offset)?;
+    let name = regex::escape(&access.receiver);
+    // Each pattern captures the type and the receiver's name
+    let patterns = [
+        // `User user;`, `const User& user`, `std::vector<User> *user =`
+        format!(r"\b([A-Za-z_]\w*)\s*(?:<[^;{{}}()]*>)?[\s*&]*(?:const\s+)?[\s*&]*\b({})\s*[;=({{\[,)]", name),
+        // `user = new User(...)`, `auto user = User{{...}}`, `user = User(...)`
+        format!(r"\b({})\s*=\s*(?:new\s+)?([A-Za-z_]\w*)\s*[({{]", name),
+        // `user: User`
+        format!(r"\b({})\s*:\s*([A-Za-z_]\w*)", name),
+    ];
+
+    // The last declaration wins; of those at the same place, the first pattern
+    let mut found: Option<(usize, String)> = None;
+    for (i, pattern) in patterns.iter().enumerate() {
+        let re = match Regex::new(pattern) {
+            Ok(re) => re,
+            Err(_) => continue,
+        };
+        let (ty_group, name_group) = if i == 0 { (1, 2) } else { (2, 1) };
+        for captures in re.captures_iter(before) {
+            let (ty, at) = match (captures.get(ty_group), captures.get(name_group)) {
+                (Some(ty), Some(name)) => (ty.as_str(), name.start()),
+                _ => continue,
+            };
+            if NOT_TYPES.contains(&ty) || found.as_ref().is_some_and(|(s, _)| *s >= at) {
+                continue;
+            }
+            found = Some((at, ty.to_string()));
+        }
+    }
+    found.map(|(_, ty)| ty)
+}
+
+/// Words that can precede a name without being its type
+const NOT_TYPES: &[&str] = &[
+    "return", "const", "else", "case", "new", "delete", "throw", "typeof", "sizeof", "in", "of", "and", "or",
+    "not", "is", "await", "yield", "var", "let",
+];
+
+/// The members of every type or namespace named `container` in `result`,
+/// in source order
+pub fn members_of<'a>(result: &'a AnalysisResult, container: &str) -> Vec<&'a SmartSymbol> {
+    let containers: Vec<SymbolId> = result
+        .symbols
+        .iter()
+        .filter(|s| s.name == container && is_container_kind(s.kind))
+        .map(|s| s.id)
+        .collect();
+    result
+        .symbols
+        .iter()
+        .filter(|s| s.parent.is_some_and(|parent| containers.contains(&parent)))
+        .collect()
+}
+
+fn is_container_kind(kind: SymbolKind) -> bool {
+    matches!(
+        kind,
+        SymbolKind::Class
+            | SymbolKind::Struct
+            | SymbolKind::Interface
+            | SymbolKind::Enum
+            | SymbolKind::Namespace
+            | SymbolKind::Module
+    )
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CppAdapter, LanguageAdapter};
+
+    #[test]
+    fn test_cpp_member_completion() {
+        let source = "class User {\npublic:\n  std::string name;\n  void greet() const {}\n};\nstruct Point { int x; int y; };\nint main() {\n  Point origin;\n  const User& user = lookup();\n  user.\n}\n";
+        let uri = "file:///main.cpp";
+        let adapter = CppAdapter::new().unwrap();
+        let result = adapter.analyze(uri, source);
+
+        let offset = source.find("user.\n").unwrap() + "user.".len();
+        let access = member_access(source, offset).unwrap();
+        assert_eq!(
+            access,
+            MemberAccess {
+                receiver: "user".to_string(),
+                operator: ".",
+                prefix: String::new(),
+            }
+        );
+        let ty = receiver_type(source, &access, offset).unwrap();
+        assert_eq!(ty, "User");
+
+        let members: Vec<&str> = members_of(&result, &ty).iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(members, vec!["name", "greet"]);
+    }
+
+    #[test]
+    fn test_member_access_contexts() {
+        let source = "p->ve";
+        let access = member_access(source, source.len()).unwrap();
+        assert_eq!((access.receiver.as_str(), access.operator, access.prefix.as_str()), ("p", "->", "ve"));
+
+        let source = "std::";
+        let access = member_access(source, source.len()).unwrap();
+        assert_eq!(receiver_type(source, &access, source.len()).unwrap(), "std");
+
+        assert!(member_access("return us", 9).is_none());
+        assert!(member_access("1.", 2).is_none());
+
+        let source = "const user = new User();\nuser.";
+        let access = member_access(source, source.len()).unwrap();
+        assert_eq!(receiver_type(source, &access, source.len()).unwrap(), "User");
+    }
+}
@@ -0,0 +1,248 @@
+//! Type navigation
+//!
+//! Go-to-type-definition and go-to-implementations over per-document
+//! analysis results produced by the language adapters.
+
+use crate::adapter::AnalysisResult;
+use crate::symbol_table::SmartSymbol;
+use logos_core::position::column_to_byte;
+use logos_core::{Location, Position, SymbolKind};
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// A possibly qualified name: `foo::User`, `pkg.User`
+static PATH: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[A-Za-z_][A-Za-z0-9_]*(?:(?:::|\.)[A-Za-z_][A-Za-z0-9_]*)*").unwrap());
+
+/// What follows a variable's name in a declaration that gives its type,
+/// with the type in group 1
+static TYPED_AFTER_NAME: LazyLock<[Regex; 3]> = LazyLock::new(|| {
+    [
+        // Annotations: `name: Type` (Rust, TypeScript, Python)
+        r"^\s*\??:\s*([&*\w\s:.<>\[\]]+)",
+        // Constructors: `name = new Type(` (TypeScript, Java, C++)
+        r"^\s*=\s*new\s+([\w.:]+)",
+        // Struct literals and associated functions: `name = Type::new()`, `name = Type {`
+        r"^\s*:?=\s*&?([A-Z]\w*)\s*(?:::|\{|\()",
+    ]
+    .map(|pattern| Regex::new(pattern).unwrap())
+});
+
+/// C-family declarations: `Type name;`, `Type* name = ...`, split into the
+/// type before the name and the punctuation after it
+static TYPE_BEFORE_NAME: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\b([A-Z][\w:<>]*)\s*[*&]?\s+$").unwrap());
+static C_DECLARATOR_END: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s*[;=({,)]").unwrap());
+
+/// Whether a symbol kind declares a type
+pub fn is_type_kind(kind: SymbolKind) -> bool {
+    matches!(
+        kind,
+        SymbolKind::Class
+            | SymbolKind::Struct
+            | SymbolKind::Interface
+            | SymbolKind::Enum
+            | SymbolKind::TypeParameter
+    )
+}
+
+/// Reduce a type expression to the name of its outermost type:
+/// `&mut foo::User<T>` -> `User`, `User[]` -> `User`, `const User*` -> `User`
+pub fn base_type_name(type_expr: &str) -> Option<String> {
+    let qualifiers = ["mut", "const", "dyn", "impl", "readonly", "volatile", "struct", "class"];
+
+    let path = PATH
+        .find_iter(type_expr)
+        .map(|m| m.as_str())
+        .find(|word| !qualifiers.contains(word))?;
+    Some(path.rsplit([':', '.']).next().unwrap_or(path).to_string())
+}
+
+/// Get the identifier under a position (UTF-16 columns, as in LSP)
+pub fn identifier_at(source: &str, position: Position) -> Option<String> {
+    let line = source.lines().nth(position.line as usize)?;
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+
+    let col = column_to_byte(line, position.column);
+    let start = line[..col].trim_end_matches(is_ident).len();
+    let end = col + (line[col..].len() - line[col..].trim_start_matches(is_ident).len());
+
+    if start == end {
+        None
+    } else {
+        Some(line[start..end].to_string())
+    }
+}
+
+/// Infer the declared type of a variable from its declaration in the source
+pub fn declared_type_in_source(source: &str, name: &str) -> Option<String> {
+    // Where `name` starts as a whole word
+    let starts: Vec<usize> = source
+        .match_indices(name)
+        .map(|(start, _)| start)
+        .filter(|&start| !source[..start].ends_with(|c: char| c.is_alphanumeric() || c == '_'))
+        .collect();
+    let after = |start: usize| &source[start + name.len()..];
+
+    let declared = TYPED_AFTER_NAME
+        .iter()
+        .map(|re| starts.iter().find_map(|&start| re.captures(after(start))?.get(1)))
+        .chain(std::iter::once(starts.iter().find_map(|&start| {
+            if !C_DECLARATOR_END.is_match(after(start)) {
+                return None;
+            }
+            TYPE_BEFORE_NAME.captures(&source[..start])?.get(1)
+        })));
+    declared.flatten().find_map(|ty| base_type_name(ty.as_str()))
+}
+
+/// Find the definitions of the type denoted by `name`.
+///
+/// If `name` is itself a type, its declarations are returned. Otherwise the
+/// type is taken from the symbol's type info or, failing that, inferred from
+/// its declaration in `source`.
+pub fn find_type_definition(
+    analyses: &HashMap<String, AnalysisResult>,
+    source: &str,
+    name: &str,
+) -> Vec<Location> {
+    let types = find_type_symbols(analyses, name);
+    if !types.is_empty() {
+        return types;
+    }
+
+    let type_name = analyses
+        .values()
+        .flat_map(|result| result.symbols.iter())
+        .filter(|s| s.name == name)
+        .find_map(|s| s.type_info.as_ref())
+        .and_then(|info| base_type_name(&info.type_expr))
+        .or_else(|| declared_type_in_source(source, name));
+
+    match type_name {
+        Some(type_name) => find_type_symbols(analyses, &type_name),
+        None => Vec::new(),
+    }
+}
+
+/// Find the types that implement or extend the type named `type_name`
+pub fn find_implementations(
+    analyses: &HashMap<String, AnalysisResult>,
+    type_name: &str,
+) -> Vec<Location> {
+    let mut locations = Vec::new();
+
+    for (uri, result) in analyses {
+        for relation in result.type_relations.iter().filter(|r| r.parent_name == type_name) {
+            let implementors: Vec<&SmartSymbol> = analyses
+                .values()
+                .flat_map(|r| r.symbols.iter())
+                .filter(|s| s.name == relation.child_name && is_type_kind(s.kind))
+                .collect();
+
+            if implementors.is_empty() {
+                // Implementor not indexed: point at the relation itself
+                locations.push(Location::new(uri.clone(), relation.location));
+            }
+            for symbol in implementors {
+                let location = Location::new(symbol.location.uri.clone(), symbol.location.range);
+                if !locations.contains(&location) {
+                    locations.push(location);
+                }
+            }
+        }
+    }
+
+    sort_locations(&mut locations);
+    locations
+}
+
+fn find_type_symbols(analyses: &HashMap<String, AnalysisResult>, name: &str) -> Vec<Location> {
+    let mut locations: Vec<Location> = analyses
+        .values()
+        .flat_map(|result| result.symbols.iter())
+        .filter(|s| s.name == name && is_type_kind(s.kind))
+        .map(|s| Location::new(s.location.uri.clone(), s.location.range))
+        .collect();
+    sort_locations(&mut locations);
+    locations
+}
+
+fn sort_locations(locations: &mut [Location]) {
+    locations.sort_by(|a, b| a.uri.cmp(&b.uri).then_with(|| a.range.start.cmp(&b.range.start)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter::LanguageAdapter;
+    use crate::rust_adapter::RustAdapter;
+    use crate::typescript_adapter::TypeScriptAdapter;
+
+    fn analyze(adapter: &dyn LanguageAdapter, uri: &str, source: &str) -> HashMap<String, AnalysisResult> {
+        let mut analyses = HashMap::new();
+        analyses.insert(uri.to_string(), adapter.analyze(uri, source));
+        analyses
+    }
+
+    #[test]
+    fn test_base_type_name() {
+        assert_eq!(base_type_name("&mut foo::User<T>").as_deref(), Some("User"));
+        assert_eq!(base_type_name("const Shape*").as_deref(), Some("Shape"));
+        assert_eq!(base_type_name("User[]").as_deref(), Some("User"));
+        assert_eq!(base_type_name("  "), None);
+    }
+
+    #[test]
+    fn test_declared_type_in_source() {
+        let src = "first: Cat\nuser: Account\n";
+        assert_eq!(declared_type_in_source(src, "user").as_deref(), Some("Account"));
+        assert_eq!(declared_type_in_source("const u = new models.User();", "u").as_deref(), Some("User"));
+        assert_eq!(declared_type_in_source("let p = Point { x: 1 };", "p").as_deref(), Some("Point"));
+        assert_eq!(declared_type_in_source("Shape* shape = make();", "shape").as_deref(), Some("Shape"));
+        assert_eq!(declared_type_in_source("superuser: Admin", "user"), None);
+    }
+
+    #[test]
+    fn test_rust_trait_implementations() {
+        let src = "trait Shape {}\nstruct Circle;\nstruct Square;\nimpl Shape for Circle {}\nimpl Shape for Square {}\n";
+        let analyses = analyze(&RustAdapter::new().unwrap(), "file:///shapes.rs", src);
+
+        let impls = find_implementations(&analyses, "Shape");
+        assert_eq!(impls.len(), 2);
+        assert_eq!(impls[0].range.start.line, 1); // Circle
+        assert_eq!(impls[1].range.start.line, 2); // Square
+    }
+
+    #[test]
+    fn test_typescript_interface_implementation() {
+        let src = "interface Greeter {\n  greet(): void;\n}\nclass English implements Greeter {\n  greet() {}\n}\n";
+        let analyses = analyze(&TypeScriptAdapter::new().unwrap(), "file:///greet.ts", src);
+
+        let impls = find_implementations(&analyses, "Greeter");
+        assert_eq!(impls.len(), 1);
+        assert_eq!(impls[0].range.start.line, 3);
+    }
+
+    #[test]
+    fn test_type_definition_from_declaration() {
+        let src = "struct User { name: String }\nfn main() {\n    let user: User = make();\n}\n";
+        let analyses = analyze(&RustAdapter::new().unwrap(), "file:///main.rs", src);
+
+        let word = identifier_at(src, Position::new(2, 9)).unwrap();
+        assert_eq!(word, "user");
+
+        let defs = find_type_definition(&analyses, src, &word);
+        assert_eq!(defs.len(), 1);
+        assert_eq!(defs[0].range.start.line, 0);
+    }
+
+    #[test]
+    fn test_identifier_at_counts_utf16_columns() {
+        // The emoji takes two UTF-16 units: `user` spans columns 14 to 18
+        let src = "let s = \"😀\"; user.save();";
+        assert_eq!(identifier_at(src, Position::new(0, 14)).as_deref(), Some("user"));
+        assert_eq!(identifier_at(src, Position::new(0, 18)).as_deref(), Some("user"));
+        assert_eq!(identifier_at(src, Position::new(0, 19)).as_deref(), Some("save"));
+    }
+}
@@ -30,7 +30,7 @@ impl PythonAdapter {
     }
 
     fn parse(&self, source: &str) -> Option<Tree> {
-        let mut parser = self.parser.lock().ok()?;
+        let mut parser = self.parser.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
         parser.parse(source, None)
     }
 }
@@ -50,6 +50,10 @@ impl LanguageAdapter for PythonAdapter {
         &["py", "pyi", "pyw"]
     }
 
+    fn keywords(&self) -> &[&str] {
+        logos_parser::python::get_keywords()
+    }
+
     fn analyze(&self, uri: &str, source: &str) -> AnalysisResult {
         let tree = match self.parse(source) {
             Some(t) => t,
@@ -187,6 +191,7 @@ fn analyze_import(node: &Node, ctx: &mut AnalysisContext) {
                             name: module_path,
                             alias: None,
                             is_type: false,
+                            range: node_to_range(&child),
                         }],
                         is_type_only: false,
                         location: node_to_range(node),
@@ -207,6 +212,7 @@ fn analyze_import(node: &Node, ctx: &mut AnalysisContext) {
                                 name: module_path,
                                 alias,
                                 is_type: false,
+                                range: node_to_range(&child),
                             }],
                             is_type_only: false,
                             location: node_to_range(node),
@@ -233,8 +239,12 @@ fn analyze_import_from(node: &Node, ctx: &mut AnalysisContext) {
         location: node_to_range(node),
     };
 
+    let module_node = node.child_by_field_name("module_name");
     for i in 0..node.named_child_count() {
         if let Some(child) = node.named_child(i) {
+            if Some(child) == module_node {
+                continue;
+            }
             match child.kind() {
                 "dotted_name" | "identifier" => {
                     let name = ctx.get_text(&child);
@@ -242,6 +252,7 @@ fn analyze_import_from(node: &Node, ctx: &mut AnalysisContext) {
                         name,
                         alias: None,
                         is_type: false,
+                        range: node_to_range(&child),
                     });
                 }
                 "aliased_import" => {
@@ -257,6 +268,7 @@ fn analyze_import_from(node: &Node, ctx: &mut AnalysisContext) {
                             name,
                             alias,
                             is_type: false,
+                            range: node_to_range(&child),
                         });
                     }
                 }
@@ -265,6 +277,7 @@ fn analyze_import_from(node: &Node, ctx: &mut AnalysisContext) {
                         name: "*".to_string(),
                         alias: None,
                         is_type: false,
+                        range: node_to_range(&child),
                     });
                 }
                 _ => {}
@@ -494,6 +507,12 @@ fn analyze_assignment(node: &Node, ctx: &mut AnalysisContext) {
                 .visibility(visibility)
                 .qualified_name(ctx.qualified_name(&name));
 
+            // `x: int = 0`, or the older `x = 0  # type: int`
+            let annotation = node.child_by_field_name("type").map(|t| ctx.get_text(&t));
+            if let Some(ty) = annotation.or_else(|| type_comment(ctx.source, node.end_byte())) {
+                builder = builder.type_info(TypeInfo::simple(ty));
+            }
+
             // Module-level public variables are exported
             if !name.starts_with('_') {
                 builder = builder.exported(true);
@@ -536,6 +555,19 @@ fn analyze_assignment(node: &Node, ctx: &mut AnalysisContext) {
     }
 }
 
+/// The type of a PEP 484 type comment ending the line at `offset`;
+/// `# type: ignore` names none
+fn type_comment(source: &str, offset: usize) -> Option<String> {
+    let rest = &source[offset..];
+    let rest = &rest[..rest.find('\n').unwrap_or(rest.len())];
+    let ty = rest.trim_start().strip_prefix('#')?.trim_start().strip_prefix("type:")?;
+    let ty = ty.split('#').next().unwrap_or("").trim();
+    if ty.is_empty() || ty == "ignore" || ty.starts_with("ignore[") {
+        return None;
+    }
+    Some(ty.to_string())
+}
+
 fn analyze_call(node: &Node, ctx: &mut AnalysisContext) {
     if let Some(function) = node.child_by_field_name("function") {
         let (callee_name, qualified_name) = match function.kind() {
@@ -603,6 +635,19 @@ def greet(name: str) -> str:
         assert!(result.symbols[0].exported);
     }
 
+    #[test]
+    fn test_parses_after_a_panic_while_parsing() {
+        let adapter = PythonAdapter::new().unwrap();
+        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _parser = adapter.parser.lock().unwrap();
+            panic!("handler panicked while parsing");
+        }));
+        assert!(adapter.parser.is_poisoned());
+
+        let result = adapter.analyze("file:///test.py", "def greet():\n    pass\n");
+        assert_eq!(result.symbols.len(), 1);
+    }
+
     #[test]
     fn test_class_with_methods() {
         let adapter = PythonAdapter::new().unwrap();
@@ -650,6 +695,14 @@ from ..parent import something
 
         let sys_import = result.imports.iter().find(|i| i.module_path == "sys").unwrap();
         assert_eq!(sys_import.items[0].alias, Some("system".to_string()));
+
+        // One item per name, with its own range; the module isn't one
+        let typing = result.imports.iter().find(|i| i.module_path == "typing").unwrap();
+        let items: Vec<(&str, Range)> = typing.items.iter().map(|i| (i.name.as_str(), i.range)).collect();
+        assert_eq!(
+            items,
+            vec![("Optional", Range::from_coords(4, 19, 4, 27)), ("List", Range::from_coords(4, 29, 4, 33))]
+        );
     }
 
     #[test]
@@ -695,4 +748,19 @@ _private_var = 42
         let private_var = result.symbols.iter().find(|s| s.name == "_private_var").unwrap();
         assert_eq!(private_var.visibility, Visibility::Private);
     }
+
+    #[test]
+    fn test_type_comments() {
+        let adapter = PythonAdapter::new().unwrap();
+        let source = "users = load()  # type: List[User]\ncount: int = 0\nraw = read()  # type: ignore\n";
+        let result = adapter.analyze("file:///test.py", source);
+
+        let type_of = |name: &str| {
+            let symbol = result.symbols.iter().find(|s| s.name == name).unwrap();
+            symbol.type_info.as_ref().map(|t| t.type_expr.clone())
+        };
+        assert_eq!(type_of("users").as_deref(), Some("List[User]"));
+        assert_eq!(type_of("count").as_deref(), Some("int"));
+        assert_eq!(type_of("raw"), None);
+    }
 }
@@ -0,0 +1,192 @@
+//! Adapter Registry
+//!
+//! The language adapters a service analyzes documents with, keyed by
+//! language id. Lookups go by language id, then by file extension, then to
+//! the fallback adapter, so a new language only needs an adapter registered
+//! here.
+
+use crate::adapter::LanguageAdapter;
+use crate::indexer::builtin_adapters;
+use std::collections::HashMap;
+use std::path::Path;
+
+#[derive(Default)]
+pub struct AdapterRegistry {
+    adapters: Vec<Box<dyn LanguageAdapter>>,
+    /// Keyword lists for language ids without an adapter of their own
+    keywords: HashMap<String, &'static [&'static str]>,
+    /// Best-effort adapter for documents no registered adapter handles
+    fallback: Option<Box<dyn LanguageAdapter>>,
+}
+
+impl AdapterRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The built-in adapters. JavaScript is analyzed by the TypeScript
+    /// adapter but keeps its own keywords.
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        for adapter in builtin_adapters() {
+            registry.register(adapter);
+        }
+        registry.set_keywords("javascript", logos_parser::javascript::get_keywords());
+        registry
+    }
+
+    /// Register an adapter, replacing any with the same language id
+    pub fn register(&mut self, adapter: Box<dyn LanguageAdapter>) {
+        match self.adapters.iter().position(|a| a.language_id() == adapter.language_id()) {
+            Some(i) => self.adapters[i] = adapter,
+            None => self.adapters.push(adapter),
+        }
+    }
+
+    /// Keywords for a language id, overriding those of its adapter
+    pub fn set_keywords(&mut self, language_id: &str, keywords: &'static [&'static str]) {
+        self.keywords.insert(language_id.to_string(), keywords);
+    }
+
+    /// Set the adapter used when nothing else matches; `None` leaves such
+    /// documents without an adapter
+    pub fn set_fallback(&mut self, adapter: Option<Box<dyn LanguageAdapter>>) {
+        self.fallback = adapter;
+    }
+
+    /// The adapter registered for a language id
+    pub fn get(&self, language_id: &str) -> Option<&dyn LanguageAdapter> {
+        self.adapters
+            .iter()
+            .find(|a| a.language_id() == language_id)
+            .map(|a| a.as_ref())
+    }
+
+    /// The adapter for a document: by language id (or the language it is an
+    /// alias of), then by the URI's extension, then the fallback adapter
+    pub fn for_document(&self, language_id: &str, uri: &str) -> Option<&dyn LanguageAdapter> {
+        self.get(language_id)
+            .or_else(|| self.get(canonical(language_id)))
+            .or_else(|| {
+                self.adapters
+                    .iter()
+                    .find(|a| a.can_handle(Path::new(uri)))
+                    .map(|a| a.as_ref())
+            })
+            .or(self.fallback.as_deref())
+    }
+
+    /// The language id a document is treated as: `language_id` when it names
+    /// a known language, else that of the adapter for the URI's extension.
+    /// `None` when neither is known.
+    pub fn resolve_language<'a>(&'a self, language_id: &'a str, uri: &str) -> Option<&'a str> {
+        let known = self.get(language_id).is_some()
+            || self.keywords.contains_key(language_id)
+            || logos_parser::LanguageId::from_str(language_id).is_some();
+        if known {
+            return Some(language_id);
+        }
+        self.adapters
+            .iter()
+            .find(|a| a.can_handle(Path::new(uri)))
+            .map(|a| a.language_id())
+    }
+
+    /// Completion keywords for a language id
+    pub fn keywords(&self, language_id: &str) -> &[&str] {
+        let language_id = match self.keywords.contains_key(language_id) || self.get(language_id).is_some() {
+            true => language_id,
+            false => canonical(language_id),
+        };
+        match self.keywords.get(language_id) {
+            Some(keywords) => keywords,
+            None => self.get(language_id).map(|a| a.keywords()).unwrap_or(&[]),
+        }
+    }
+
+    /// Language ids with a registered adapter
+    pub fn language_ids(&self) -> impl Iterator<Item = &str> {
+        self.adapters.iter().map(|a| a.language_id())
+    }
+}
+
+/// The canonical id of a language id alias such as `typescriptreact`
+fn canonical(language_id: &str) -> &str {
+    logos_parser::LanguageId::from_str(language_id).map(|l| l.as_str()).unwrap_or(language_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter::AnalysisResult;
+    use crate::BraceLanguageAdapter;
+
+    struct ToyAdapter(&'static str);
+
+    impl LanguageAdapter for ToyAdapter {
+        fn language_id(&self) -> &str {
+            self.0
+        }
+
+        fn file_extensions(&self) -> &[&str] {
+            &["toy"]
+        }
+
+        fn keywords(&self) -> &[&str] {
+            &["toy"]
+        }
+
+        fn analyze(&self, _uri: &str, _source: &str) -> AnalysisResult {
+            AnalysisResult::default()
+        }
+    }
+
+    #[test]
+    fn test_lookup_order() {
+        let mut registry = AdapterRegistry::with_builtins();
+        registry.set_fallback(Some(Box::new(BraceLanguageAdapter::new())));
+
+        assert_eq!(registry.for_document("python", "file:///a.txt").unwrap().language_id(), "python");
+        assert_eq!(registry.for_document("javascript", "file:///a.js").unwrap().language_id(), "typescript");
+        assert_eq!(registry.for_document("kotlin", "file:///a.kt").unwrap().language_id(), "brace");
+
+        registry.set_fallback(None);
+        assert!(registry.for_document("kotlin", "file:///a.kt").is_none());
+    }
+
+    #[test]
+    fn test_resolve_language() {
+        let registry = AdapterRegistry::with_builtins();
+        assert_eq!(registry.resolve_language("", "file:///src/app.ts"), Some("typescript"));
+        assert_eq!(registry.resolve_language("plaintext", "file:///main.py"), Some("python"));
+        assert_eq!(registry.resolve_language("", "file:///lib/vector.cc"), Some("cpp"));
+        // A known id wins over the extension
+        assert_eq!(registry.resolve_language("javascriptreact", "file:///a.ts"), Some("javascriptreact"));
+        assert_eq!(registry.resolve_language("", "file:///notes.txt"), None);
+    }
+
+    #[test]
+    fn test_register_replaces_and_provides_keywords() {
+        let mut registry = AdapterRegistry::with_builtins();
+        let count = registry.language_ids().count();
+
+        registry.register(Box::new(ToyAdapter("toy")));
+        assert_eq!(registry.language_ids().count(), count + 1);
+        assert_eq!(registry.keywords("toy"), ["toy"]);
+        assert_eq!(registry.for_document("plaintext", "file:///a.toy").unwrap().language_id(), "toy");
+
+        registry.register(Box::new(ToyAdapter("python")));
+        assert_eq!(registry.language_ids().count(), count + 1);
+        assert_eq!(registry.keywords("python"), ["toy"]);
+
+        assert!(registry.keywords("javascript").contains(&"function"));
+        assert!(!registry.keywords("javascript").contains(&"interface"));
+        assert!(registry.keywords("typescript").contains(&"interface"));
+        assert!(registry.keywords("kotlin").is_empty());
+
+        // Aliases resolve to the language they name
+        assert!(registry.keywords("typescriptreact").contains(&"interface"));
+        assert!(!registry.keywords("javascriptreact").contains(&"interface"));
+        assert_eq!(registry.for_document("python3", "untitled:1").unwrap().language_id(), "python");
+    }
+}
@@ -5,8 +5,9 @@
 //! - Imports: use declarations (best-effort string extraction)
 //! - Exports: inferred from `pub` visibility (best-effort)
 //! - Calls: call_expression (best-effort)
+//! - Type relations: `impl Trait for Type`
 
-use crate::adapter::{AnalysisResult, CallInfo, ImportInfo, ImportItem, LanguageAdapter, SymbolBuilder, make_location};
+use crate::adapter::{AnalysisResult, CallInfo, ImportInfo, ImportItem, LanguageAdapter, SymbolBuilder, TypeRelation, make_location};
 use crate::symbol_table::{SymbolId, Visibility};
 use logos_core::{Position, Range, SymbolKind};
 use std::path::Path;
@@ -28,7 +29,7 @@ impl RustAdapter {
     }
 
     fn parse(&self, source: &str) -> Option<Tree> {
-        let mut parser = self.parser.lock().ok()?;
+        let mut parser = self.parser.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
         parser.parse(source, None)
     }
 }
@@ -42,6 +43,10 @@ impl LanguageAdapter for RustAdapter {
         &["rs"]
     }
 
+    fn keywords(&self) -> &[&str] {
+        logos_parser::rust_lang::get_keywords()
+    }
+
     fn analyze(&self, uri: &str, source: &str) -> AnalysisResult {
         let tree = match self.parse(source) {
             Some(t) => t,
@@ -121,6 +126,7 @@ fn analyze_node(node: &Node, ctx: &mut AnalysisContext) {
         "mod_item" => analyze_mod(node, ctx),
         "const_item" => analyze_const(node, ctx),
         "static_item" => analyze_static(node, ctx),
+        "impl_item" => analyze_impl(node, ctx),
 
         "call_expression" => analyze_call(node, ctx),
 
@@ -153,6 +159,7 @@ fn analyze_use(node: &Node, ctx: &mut AnalysisContext) {
             name: module_path,
             alias: None,
             is_type: false,
+            range: node_to_range(node),
         }],
         is_type_only: false,
         location: node_to_range(node),
@@ -268,6 +275,39 @@ fn analyze_static(node: &Node, ctx: &mut AnalysisContext) {
     let _ = push_symbol(ctx, name, SymbolKind::Variable, node, &name_node, exported);
 }
 
+fn analyze_impl(node: &Node, ctx: &mut AnalysisContext) {
+    // `impl Trait for Type` records an implements relation; inherent impls don't
+    if let (Some(trait_node), Some(type_node)) =
+        (node.child_by_field_name("trait"), node.child_by_field_name("type"))
+    {
+        ctx.result.type_relations.push(TypeRelation {
+            child_name: base_type_name(&ctx.get_text(&type_node)),
+            parent_name: base_type_name(&ctx.get_text(&trait_node)),
+            is_implements: true,
+            location: node_to_range(node),
+        });
+    }
+
+    if let Some(body) = node.child_by_field_name("body") {
+        for i in 0..body.named_child_count() {
+            if let Some(child) = body.named_child(i) {
+                analyze_node(&child, ctx);
+            }
+        }
+    }
+}
+
+/// Strip generic arguments and path prefix: `std::fmt::Display<T>` -> `Display`
+fn base_type_name(text: &str) -> String {
+    let without_generics = text.split('<').next().unwrap_or(text);
+    without_generics
+        .rsplit("::")
+        .next()
+        .unwrap_or(without_generics)
+        .trim()
+        .to_string()
+}
+
 fn analyze_call(node: &Node, ctx: &mut AnalysisContext) {
     if let Some(function) = node.child_by_field_name("function") {
         let text = ctx.get_text(&function);
@@ -331,5 +371,27 @@ pub const MAX: usize = 10;
         assert!(result.symbols.iter().any(|s| s.name == "helper"));
         assert!(result.calls.len() >= 1);
     }
-}
 
+    #[test]
+    fn rust_trait_impls_are_type_relations() {
+        let adapter = RustAdapter::new().unwrap();
+        let src = r#"
+trait Shape {}
+struct Circle;
+struct Square;
+impl Shape for Circle {}
+impl<T> std::fmt::Debug for Wrapper<T> {}
+impl Square {}
+"#;
+        let result = adapter.analyze("file:///test.rs", src);
+        assert_eq!(result.type_relations.len(), 2);
+        assert!(result
+            .type_relations
+            .iter()
+            .any(|r| r.child_name == "Circle" && r.parent_name == "Shape" && r.is_implements));
+        assert!(result
+            .type_relations
+            .iter()
+            .any(|r| r.child_name == "Wrapper" && r.parent_name == "Debug"));
+    }
+}
@@ -0,0 +1,191 @@
+//! Semantic tokens
+//!
+//! Token classification produced by the language adapters and the LSP
+//! delta encoding used to send it to the client.
+
+/// Token types, in legend order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SemanticTokenType {
+    Namespace,
+    Class,
+    Function,
+    Method,
+    Variable,
+    Parameter,
+    Property,
+}
+
+impl SemanticTokenType {
+    /// Legend announced in the server capabilities; a token's type is its index here
+    pub const LEGEND: &'static [&'static str] = &[
+        "namespace",
+        "class",
+        "function",
+        "method",
+        "variable",
+        "parameter",
+        "property",
+    ];
+
+    const ALL: [SemanticTokenType; 7] = [
+        Self::Namespace,
+        Self::Class,
+        Self::Function,
+        Self::Method,
+        Self::Variable,
+        Self::Parameter,
+        Self::Property,
+    ];
+
+    /// Index of this type in `LEGEND`
+    pub fn index(self) -> u32 {
+        Self::ALL.iter().position(|t| *t == self).unwrap() as u32
+    }
+
+    /// Type for a legend index
+    pub fn from_index(index: u32) -> Option<Self> {
+        Self::ALL.get(index as usize).copied()
+    }
+}
+
+/// A classified identifier, with UTF-16 columns and length as in LSP
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SemanticToken {
+    pub line: u32,
+    pub start: u32,
+    pub length: u32,
+    pub token_type: SemanticTokenType,
+}
+
+/// Encode tokens as the LSP integer array: for each token
+/// `[deltaLine, deltaStart, length, tokenType, tokenModifiers]`, where
+/// `deltaStart` is relative to the previous token only on the same line
+pub fn encode(tokens: &[SemanticToken]) -> Vec<u32> {
+    let mut sorted = tokens.to_vec();
+    sorted.sort_by_key(|t| (t.line, t.start));
+    sorted.dedup_by_key(|t| (t.line, t.start));
+
+    let mut data = Vec::with_capacity(sorted.len() * 5);
+    let (mut prev_line, mut prev_start) = (0, 0);
+    for token in sorted {
+        let delta_line = token.line - prev_line;
+        let delta_start = if delta_line == 0 {
+            token.start - prev_start
+        } else {
+            token.start
+        };
+        data.extend_from_slice(&[delta_line, delta_start, token.length, token.token_type.index(), 0]);
+        prev_line = token.line;
+        prev_start = token.start;
+    }
+    data
+}
+
+/// Decode an LSP integer array back into tokens
+pub fn decode(data: &[u32]) -> Vec<SemanticToken> {
+    let mut tokens = Vec::with_capacity(data.len() / 5);
+    let (mut line, mut start) = (0, 0);
+    for chunk in data.chunks_exact(5) {
+        if chunk[0] > 0 {
+            line += chunk[0];
+            start = chunk[1];
+        } else {
+            start += chunk[1];
+        }
+        if let Some(token_type) = SemanticTokenType::from_index(chunk[3]) {
+            tokens.push(SemanticToken {
+                line,
+                start,
+                length: chunk[2],
+                token_type,
+            });
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter::LanguageAdapter;
+    use crate::cpp_adapter::CppAdapter;
+
+    const FIXTURE: &str = r#"
+namespace demo {
+  class User {
+    public:
+      void greet() {}
+  };
+}
+
+void welcome(demo::User& user) {
+  user.greet();
+}
+"#;
+
+    fn token_at(tokens: &[SemanticToken], source: &str, name: &str, line: u32) -> Option<SemanticTokenType> {
+        let text: Vec<&str> = source.lines().collect();
+        tokens
+            .iter()
+            .find(|t| {
+                t.line == line
+                    && text[t.line as usize].get(t.start as usize..(t.start + t.length) as usize) == Some(name)
+            })
+            .map(|t| t.token_type)
+    }
+
+    #[test]
+    fn test_encode_roundtrip() {
+        let tokens = vec![
+            SemanticToken { line: 2, start: 4, length: 3, token_type: SemanticTokenType::Variable },
+            SemanticToken { line: 0, start: 1, length: 2, token_type: SemanticTokenType::Class },
+            SemanticToken { line: 2, start: 10, length: 1, token_type: SemanticTokenType::Method },
+        ];
+        let data = encode(&tokens);
+        assert_eq!(&data[..5], &[0, 1, 2, 1, 0]);
+        assert_eq!(&data[5..10], &[2, 4, 3, 4, 0]);
+        assert_eq!(&data[10..], &[0, 6, 1, 3, 0]);
+
+        let mut expected = tokens.clone();
+        expected.sort_by_key(|t| (t.line, t.start));
+        assert_eq!(decode(&data), expected);
+    }
+
+    #[test]
+    fn test_cpp_semantic_tokens() {
+        let adapter = CppAdapter::new().unwrap();
+        let analysis = adapter.analyze("file:///test.cpp", FIXTURE);
+        let data = encode(&adapter.semantic_tokens(FIXTURE, &analysis));
+        let tokens = decode(&data);
+
+        assert_eq!(token_at(&tokens, FIXTURE, "demo", 1), Some(SemanticTokenType::Namespace));
+        assert_eq!(token_at(&tokens, FIXTURE, "User", 2), Some(SemanticTokenType::Class));
+        assert_eq!(token_at(&tokens, FIXTURE, "greet", 4), Some(SemanticTokenType::Method));
+        assert_eq!(token_at(&tokens, FIXTURE, "welcome", 8), Some(SemanticTokenType::Function));
+        assert_eq!(token_at(&tokens, FIXTURE, "User", 8), Some(SemanticTokenType::Class));
+        assert_eq!(token_at(&tokens, FIXTURE, "user", 8), Some(SemanticTokenType::Parameter));
+        assert_eq!(token_at(&tokens, FIXTURE, "user", 9), Some(SemanticTokenType::Parameter));
+        assert_eq!(token_at(&tokens, FIXTURE, "greet", 9), Some(SemanticTokenType::Method));
+    }
+
+    #[test]
+    fn test_cpp_semantic_tokens_count_utf16_columns() {
+        let adapter = CppAdapter::new().unwrap();
+        let source = "const char* s = \"h\u{e9}llo\"; int value = 1;\n";
+        let analysis = adapter.analyze("file:///test.cpp", source);
+        let tokens = decode(&encode(&adapter.semantic_tokens(source, &analysis)));
+
+        // `é` is two bytes but one UTF-16 code unit
+        let value = tokens.iter().find(|t| t.start == 29).unwrap();
+        assert_eq!((value.line, value.length), (0, 5));
+        assert!(!tokens.iter().any(|t| t.start == 30));
+    }
+
+    #[test]
+    fn test_unsupported_language_is_empty() {
+        let adapter = crate::python_adapter::PythonAdapter::new().unwrap();
+        let source = "def f(x):\n    return x\n";
+        let analysis = adapter.analyze("file:///test.py", source);
+        assert!(adapter.semantic_tokens(source, &analysis).is_empty());
+    }
+}
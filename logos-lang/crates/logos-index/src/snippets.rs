@@ -0,0 +1,118 @@
+//! Snippets
+//!
+//! Completion snippets for the control structures and declarations of each
+//! language. Bodies use the LSP snippet syntax: `${1:name}` tab stops with
+//! placeholders, `$0` for the final cursor position, and `\t` for one level
+//! of indentation, which the client converts to the document's own.
+
+use logos_parser::LanguageId;
+
+/// A completion snippet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Snippet {
+    /// The word the snippet completes, e.g. `for`
+    pub label: &'static str,
+    /// A short description shown next to the label
+    pub detail: &'static str,
+    /// The text inserted, in LSP snippet syntax
+    pub body: &'static str,
+}
+
+const fn snippet(label: &'static str, detail: &'static str, body: &'static str) -> Snippet {
+    Snippet { label, detail, body }
+}
+
+const PYTHON: &[Snippet] = &[
+    snippet("for", "for loop", "for ${1:item} in ${2:items}:\n\t${0:pass}"),
+    snippet("if", "if statement", "if ${1:condition}:\n\t${0:pass}"),
+    snippet("while", "while loop", "while ${1:condition}:\n\t${0:pass}"),
+    snippet("def", "function definition", "def ${1:name}(${2}):\n\t${0:pass}"),
+    snippet("class", "class definition", "class ${1:Name}:\n\tdef __init__(self${2}):\n\t\t${0:pass}"),
+];
+
+const JAVASCRIPT: &[Snippet] = &[
+    snippet("for", "for loop", "for (let ${1:i} = 0; ${1:i} < ${2:length}; ${1:i}++) {\n\t$0\n}"),
+    snippet("forof", "for...of loop", "for (const ${1:item} of ${2:items}) {\n\t$0\n}"),
+    snippet("if", "if statement", "if (${1:condition}) {\n\t$0\n}"),
+    snippet("while", "while loop", "while (${1:condition}) {\n\t$0\n}"),
+    snippet("function", "function declaration", "function ${1:name}(${2}) {\n\t$0\n}"),
+    snippet("class", "class declaration", "class ${1:Name} {\n\tconstructor(${2}) {\n\t\t$0\n\t}\n}"),
+];
+
+const RUST: &[Snippet] = &[
+    snippet("for", "for loop", "for ${1:item} in ${2:iter} {\n\t$0\n}"),
+    snippet("if", "if expression", "if ${1:condition} {\n\t$0\n}"),
+    snippet("while", "while loop", "while ${1:condition} {\n\t$0\n}"),
+    snippet("fn", "function", "fn ${1:name}(${2}) {\n\t$0\n}"),
+    snippet("struct", "struct", "struct ${1:Name} {\n\t$0\n}"),
+];
+
+const GO: &[Snippet] = &[
+    snippet("for", "for loop", "for ${1:i} := 0; ${1:i} < ${2:n}; ${1:i}++ {\n\t$0\n}"),
+    snippet("forr", "for range loop", "for ${1:_}, ${2:v} := range ${3:items} {\n\t$0\n}"),
+    snippet("if", "if statement", "if ${1:condition} {\n\t$0\n}"),
+    snippet("func", "function", "func ${1:name}(${2}) {\n\t$0\n}"),
+    snippet("struct", "struct type", "type ${1:Name} struct {\n\t$0\n}"),
+];
+
+const JAVA: &[Snippet] = &[
+    snippet("for", "for loop", "for (int ${1:i} = 0; ${1:i} < ${2:length}; ${1:i}++) {\n\t$0\n}"),
+    snippet("if", "if statement", "if (${1:condition}) {\n\t$0\n}"),
+    snippet("while", "while loop", "while (${1:condition}) {\n\t$0\n}"),
+    snippet("method", "method", "public ${1:void} ${2:name}(${3}) {\n\t$0\n}"),
+    snippet("class", "class", "public class ${1:Name} {\n\t$0\n}"),
+];
+
+const C: &[Snippet] = &[
+    snippet("for", "for loop", "for (int ${1:i} = 0; ${1:i} < ${2:n}; ${1:i}++) {\n\t$0\n}"),
+    snippet("if", "if statement", "if (${1:condition}) {\n\t$0\n}"),
+    snippet("while", "while loop", "while (${1:condition}) {\n\t$0\n}"),
+    snippet("func", "function", "${1:void} ${2:name}(${3}) {\n\t$0\n}"),
+    snippet("struct", "struct", "struct ${1:Name} {\n\t$0\n};"),
+];
+
+const CPP: &[Snippet] = &[
+    snippet("for", "for loop", "for (int ${1:i} = 0; ${1:i} < ${2:n}; ${1:i}++) {\n\t$0\n}"),
+    snippet("if", "if statement", "if (${1:condition}) {\n\t$0\n}"),
+    snippet("while", "while loop", "while (${1:condition}) {\n\t$0\n}"),
+    snippet("func", "function", "${1:void} ${2:name}(${3}) {\n\t$0\n}"),
+    snippet("class", "class", "class ${1:Name} {\npublic:\n\t$0\n};"),
+];
+
+/// The snippets for a language id or one of its aliases; none for unknown
+/// languages
+pub fn snippets(language_id: &str) -> &'static [Snippet] {
+    match LanguageId::from_str(language_id) {
+        Some(LanguageId::Python) => PYTHON,
+        Some(LanguageId::JavaScript | LanguageId::TypeScript) => JAVASCRIPT,
+        Some(LanguageId::Rust) => RUST,
+        Some(LanguageId::Go) => GO,
+        Some(LanguageId::Java) => JAVA,
+        Some(LanguageId::C) => C,
+        Some(LanguageId::Cpp) => CPP,
+        None => &[],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snippets_by_language() {
+        let body = |language_id: &str, label: &str| {
+            snippets(language_id).iter().find(|s| s.label == label).map(|s| s.body)
+        };
+        assert_eq!(body("python", "for"), Some("for ${1:item} in ${2:items}:\n\t${0:pass}"));
+        assert_eq!(body("typescriptreact", "for"), body("javascript", "for"));
+        assert_eq!(body("rust", "def"), None);
+        assert!(snippets("plaintext").is_empty());
+
+        // Every snippet ends with a final cursor position
+        for language_id in ["python", "javascript", "rust", "go", "java", "c", "cpp"] {
+            for snippet in snippets(language_id) {
+                assert!(snippet.body.contains("$0") || snippet.body.contains("${0"), "{}", snippet.body);
+            }
+        }
+    }
+}
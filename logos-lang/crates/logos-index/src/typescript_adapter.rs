@@ -30,7 +30,7 @@ impl TypeScriptAdapter {
     }
 
     fn parse(&self, source: &str) -> Option<Tree> {
-        let mut parser = self.parser.lock().ok()?;
+        let mut parser = self.parser.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
         parser.parse(source, None)
     }
 }
@@ -50,6 +50,10 @@ impl LanguageAdapter for TypeScriptAdapter {
         &["ts", "tsx", "js", "jsx", "mts", "mjs", "cts", "cjs"]
     }
 
+    fn keywords(&self) -> &[&str] {
+        logos_parser::typescript::get_keywords()
+    }
+
     fn analyze(&self, uri: &str, source: &str) -> AnalysisResult {
         let tree = match self.parse(source) {
             Some(t) => t,
@@ -216,6 +220,7 @@ fn analyze_import(node: &Node, ctx: &mut AnalysisContext) {
                             name: "*".to_string(),
                             alias: Some(ctx.get_text(&name)),
                             is_type: import.is_type_only,
+                            range: node_to_range(&child),
                         });
                     }
                 }
@@ -239,6 +244,7 @@ fn analyze_import_clause(node: &Node, ctx: &mut AnalysisContext, import: &mut Im
                         name: "default".to_string(),
                         alias: Some(ctx.get_text(&child)),
                         is_type: import.is_type_only,
+                        range: node_to_range(&child),
                     });
                 }
                 "named_imports" => {
@@ -257,6 +263,7 @@ fn analyze_import_clause(node: &Node, ctx: &mut AnalysisContext, import: &mut Im
                                         name,
                                         alias,
                                         is_type: import.is_type_only,
+                                        range: node_to_range(&spec),
                                     });
                                 }
                             }
@@ -269,6 +276,7 @@ fn analyze_import_clause(node: &Node, ctx: &mut AnalysisContext, import: &mut Im
                             name: "*".to_string(),
                             alias: Some(ctx.get_text(&name)),
                             is_type: import.is_type_only,
+                            range: node_to_range(&child),
                         });
                     }
                 }
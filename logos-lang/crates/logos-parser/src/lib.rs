@@ -38,18 +38,44 @@ pub enum LanguageId {
 }
 
 impl LanguageId {
+    /// The language ids `from_str` accepts, in lower case: the canonical
+    /// names of `as_str`, the ids editors send (`typescriptreact`, `python3`)
+    /// and common short names
+    pub const ALIASES: &'static [(&'static str, LanguageId)] = &[
+        ("python", Self::Python),
+        ("python3", Self::Python),
+        ("python2", Self::Python),
+        ("py", Self::Python),
+        ("go", Self::Go),
+        ("golang", Self::Go),
+        ("rust", Self::Rust),
+        ("rs", Self::Rust),
+        ("c", Self::C),
+        ("cpp", Self::Cpp),
+        ("c++", Self::Cpp),
+        ("cxx", Self::Cpp),
+        ("cc", Self::Cpp),
+        ("cuda-cpp", Self::Cpp),
+        ("java", Self::Java),
+        ("javascript", Self::JavaScript),
+        ("javascriptreact", Self::JavaScript),
+        ("js", Self::JavaScript),
+        ("jsx", Self::JavaScript),
+        ("node", Self::JavaScript),
+        ("typescript", Self::TypeScript),
+        ("typescriptreact", Self::TypeScript),
+        ("ts", Self::TypeScript),
+        ("tsx", Self::TypeScript),
+    ];
+
+    /// The language of a language id, ignoring case and surrounding
+    /// whitespace; see `ALIASES`
     pub fn from_str(s: &str) -> Option<Self> {
-        match s.to_lowercase().as_str() {
-            "python" | "py" => Some(Self::Python),
-            "go" | "golang" => Some(Self::Go),
-            "rust" | "rs" => Some(Self::Rust),
-            "c" => Some(Self::C),
-            "cpp" | "c++" | "cxx" => Some(Self::Cpp),
-            "java" => Some(Self::Java),
-            "javascript" | "js" => Some(Self::JavaScript),
-            "typescript" | "ts" => Some(Self::TypeScript),
-            _ => None,
-        }
+        let id = s.trim().to_lowercase();
+        Self::ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == id)
+            .map(|(_, language)| *language)
     }
 
     pub fn from_extension(ext: &str) -> Option<Self> {
@@ -78,6 +104,17 @@ impl LanguageId {
             Self::TypeScript => "typescript",
         }
     }
+
+    /// Regex matching identifier-like tokens of the language. Group 1 holds
+    /// the name when the token is a plain identifier; tokens that only look
+    /// like one (Rust lifetimes `'a` and macro names `foo!`) match without it.
+    pub fn identifier_pattern(&self) -> &'static str {
+        match self {
+            Self::Python | Self::Go | Self::C | Self::Cpp | Self::Java => r"\b([a-zA-Z_][a-zA-Z0-9_]*)\b",
+            Self::Rust => r"'[a-zA-Z_][a-zA-Z0-9_]*\b|\b[a-zA-Z_][a-zA-Z0-9_]*!(?:[^=]|$)|\b(?:r#)?([a-zA-Z_][a-zA-Z0-9_]*)\b",
+            Self::JavaScript | Self::TypeScript => r"\b([a-zA-Z_$][a-zA-Z0-9_$]*)\b",
+        }
+    }
 }
 
 /// Multi-language parser wrapper
@@ -153,36 +190,48 @@ impl Default for LanguageParser {
 }
 
 /// Extract diagnostics from parse errors in the tree
+///
+/// One error per stretch of broken input: an ERROR node's nested errors are
+/// part of it, and ERROR/MISSING nodes separated by nothing but whitespace
+/// are reported together, at their combined range.
 pub fn extract_parse_errors(tree: &Tree, source: &str) -> Vec<Diagnostic> {
-    let mut diagnostics = Vec::new();
+    let mut errors = Vec::new();
     let mut cursor = tree.walk();
+    collect_error_nodes(&mut cursor, &mut errors);
+
+    let mut runs: Vec<Vec<Node>> = Vec::new();
+    for node in errors {
+        match runs.last_mut() {
+            Some(run) if is_adjacent(run[run.len() - 1], node, source) => run.push(node),
+            _ => runs.push(vec![node]),
+        }
+    }
 
-    extract_errors_recursive(&mut cursor, source, &mut diagnostics);
-    diagnostics
+    runs.iter()
+        .map(|run| {
+            let (first, last) = (run[0], run[run.len() - 1]);
+            let range = Range::new(point_to_position(first.start_position()), point_to_position(last.end_position()));
+            let message = if run.len() == 1 && first.is_missing() {
+                format!("Missing '{}'", first.kind())
+            } else {
+                format!("Syntax error near '{}'", token_near(source, first.start_byte(), last.end_byte()))
+            };
+            Diagnostic::error(range, message).with_source("logos-parser".to_string())
+        })
+        .collect()
 }
 
-fn extract_errors_recursive(
-    cursor: &mut tree_sitter::TreeCursor,
-    source: &str,
-    diagnostics: &mut Vec<Diagnostic>,
-) {
+/// The outermost ERROR and MISSING nodes, in source order
+fn collect_error_nodes<'t>(cursor: &mut tree_sitter::TreeCursor<'t>, errors: &mut Vec<Node<'t>>) {
     let node = cursor.node();
-
     if node.is_error() || node.is_missing() {
-        let range = node_to_range(&node);
-        let message = if node.is_missing() {
-            format!("Missing {}", node.kind())
-        } else {
-            "Syntax error".to_string()
-        };
-        diagnostics.push(
-            Diagnostic::error(range, message).with_source("logos-parser".to_string()),
-        );
+        errors.push(node);
+        return;
     }
 
     if cursor.goto_first_child() {
         loop {
-            extract_errors_recursive(cursor, source, diagnostics);
+            collect_error_nodes(cursor, errors);
             if !cursor.goto_next_sibling() {
                 break;
             }
@@ -191,6 +240,26 @@ fn extract_errors_recursive(
     }
 }
 
+/// Whether `next` follows `previous` with only whitespace in between
+fn is_adjacent(previous: Node, next: Node, source: &str) -> bool {
+    next.start_byte() <= previous.end_byte()
+        || source
+            .get(previous.end_byte()..next.start_byte())
+            .is_some_and(|gap| gap.trim().is_empty())
+}
+
+/// The first token of `start..end` as written, or of the input after it
+/// when the span is empty; shortened to keep messages on one line
+fn token_near(source: &str, start: usize, end: usize) -> String {
+    let text = source.get(start..end).unwrap_or("");
+    let text = if text.trim().is_empty() { source.get(end..).unwrap_or("") } else { text };
+    let token = text.split_whitespace().next().unwrap_or("end of file");
+    match token.char_indices().nth(20) {
+        Some((cut, _)) => format!("{}...", &token[..cut]),
+        None => token.to_string(),
+    }
+}
+
 /// Convert tree-sitter node range to logos Range
 pub fn node_to_range(node: &Node) -> Range {
     let start = node.start_position();
@@ -218,6 +287,18 @@ mod tests {
         assert_eq!(LanguageId::from_str("Python"), Some(LanguageId::Python));
         assert_eq!(LanguageId::from_str("rust"), Some(LanguageId::Rust));
         assert_eq!(LanguageId::from_str("unknown"), None);
+
+        // Editor ids and other spellings
+        assert_eq!(LanguageId::from_str("typescriptreact"), Some(LanguageId::TypeScript));
+        assert_eq!(LanguageId::from_str("javascriptreact"), Some(LanguageId::JavaScript));
+        assert_eq!(LanguageId::from_str("c++"), Some(LanguageId::Cpp));
+        assert_eq!(LanguageId::from_str(" PYTHON3 "), Some(LanguageId::Python));
+        assert_eq!(LanguageId::from_str("GoLang"), Some(LanguageId::Go));
+
+        // Every language is known by its canonical name
+        for (_, language) in LanguageId::ALIASES {
+            assert_eq!(LanguageId::from_str(language.as_str()), Some(*language));
+        }
     }
 
     #[test]
@@ -227,6 +308,26 @@ mod tests {
         assert_eq!(LanguageId::from_extension("ts"), Some(LanguageId::TypeScript));
     }
 
+    #[test]
+    fn test_parse_errors_coalesced() {
+        let mut parser = LanguageParser::new();
+        parser.set_language(LanguageId::Cpp).unwrap();
+
+        let source = "int main() {\n    int x = 1\n    return x;\n}\n";
+        let tree = parser.parse(source, None).unwrap();
+        let errors = extract_parse_errors(&tree, source);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].range, Range::from_coords(1, 13, 1, 13));
+        assert_eq!(errors[0].message, "Missing ';'");
+
+        // A run of stray tokens is one error, not one per token
+        let source = "int main() {\n    foo(1 2 3 4);\n}\n";
+        let tree = parser.parse(source, None).unwrap();
+        let errors = extract_parse_errors(&tree, source);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Syntax error near '2'");
+    }
+
     #[test]
     #[cfg(not(target_arch = "wasm32"))]
     fn test_parse_python() {
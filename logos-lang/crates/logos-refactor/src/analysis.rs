@@ -3,10 +3,15 @@
 //! This module provides utilities for analyzing code structure,
 //! identifying expressions, statements, and their relationships.
 
+use crate::naming::{words, Case, NamingConfig};
+use crate::operators::{operators_in, OperatorKind};
+use crate::{offset_to_position, RefactorContext, RefactorError};
 use logos_core::{Position, Range};
-use logos_parser::LanguageId;
+use logos_parser::{LanguageId, LanguageParser};
 use regex::Regex;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
+use tree_sitter::Node;
 
 /// Represents an expression found in the code
 #[derive(Debug, Clone)]
@@ -146,7 +151,179 @@ fn is_valid_go_expression(text: &str) -> bool {
 
 /// Check if a string has balanced delimiters
 pub fn has_balanced_delimiters(text: &str) -> bool {
-    let mut stack: Vec<char> = Vec::new();
+    unbalanced_delimiter(text).is_none()
+}
+
+/// A bracket or quote in a selection without its counterpart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnbalancedDelimiter {
+    /// Byte offset of the delimiter in the text
+    pub offset: usize,
+    /// The delimiter as written
+    pub delimiter: char,
+    /// The delimiter that would balance it
+    pub expected: char,
+    /// Whether the delimiter opens something that is never closed, rather
+    /// than closing something never opened
+    pub is_open: bool,
+}
+
+impl UnbalancedDelimiter {
+    /// What is wrong, and how to fix the selection
+    pub fn message(&self) -> String {
+        match (self.is_open, matches!(self.delimiter, '"' | '\'' | '`')) {
+            (true, true) => format!(
+                "string opened by {} is never closed; extend the selection to include the closing {}",
+                self.delimiter, self.expected
+            ),
+            (true, false) => format!(
+                "'{}' is never closed; extend the selection to include the matching '{}'",
+                self.delimiter, self.expected
+            ),
+            (false, _) => format!(
+                "'{}' has no matching '{}'; extend the selection to include it",
+                self.delimiter, self.expected
+            ),
+        }
+    }
+}
+
+/// The first delimiter that is not balanced in `text`: a closing bracket
+/// without (or with the wrong) opening one, else the innermost bracket or
+/// string left open
+pub fn unbalanced_delimiter(text: &str) -> Option<UnbalancedDelimiter> {
+    let mut stack: Vec<(usize, char)> = Vec::new();
+    let mut string: Option<(usize, char)> = None;
+    let mut prev_char = ' ';
+
+    for (i, ch) in text.char_indices() {
+        if let Some((_, quote)) = string {
+            if ch == quote && prev_char != '\\' {
+                string = None;
+            }
+        } else {
+            match ch {
+                '"' | '\'' | '`' => string = Some((i, ch)),
+                '(' | '[' | '{' => stack.push((i, ch)),
+                ')' | ']' | '}' => {
+                    let open = matching_delimiter(ch);
+                    match stack.pop() {
+                        Some((_, c)) if c == open => {}
+                        // `foo(bar[0)`: the innermost bracket is the one left open
+                        Some((offset, c)) => {
+                            return Some(UnbalancedDelimiter {
+                                offset,
+                                delimiter: c,
+                                expected: matching_delimiter(c),
+                                is_open: true,
+                            })
+                        }
+                        None => {
+                            return Some(UnbalancedDelimiter {
+                                offset: i,
+                                delimiter: ch,
+                                expected: open,
+                                is_open: false,
+                            })
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        prev_char = ch;
+    }
+
+    string.or(stack.pop()).map(|(offset, c)| UnbalancedDelimiter {
+        offset,
+        delimiter: c,
+        expected: matching_delimiter(c),
+        is_open: true,
+    })
+}
+
+fn matching_delimiter(ch: char) -> char {
+    match ch {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        quote => quote,
+    }
+}
+
+/// `CannotExtract`-style error for a selection with an unbalanced delimiter;
+/// `text` starts at byte `start` of the source
+pub(crate) fn unbalanced_selection(ctx: &RefactorContext, start: usize, text: &str) -> Option<RefactorError> {
+    let unbalanced = unbalanced_delimiter(text)?;
+    let offset = start + unbalanced.offset;
+    let range = Range::new(
+        offset_to_position(ctx.source, offset),
+        offset_to_position(ctx.source, offset + unbalanced.delimiter.len_utf8()),
+    );
+    Some(RefactorError::UnbalancedDelimiter(unbalanced.message(), range))
+}
+
+/// Method names that mutate their receiver in common standard libraries
+pub(crate) const MUTATING_METHODS: &[&str] = &[
+    "push", "pop", "append", "insert", "remove", "extend", "clear", "shift", "unshift",
+    "splice", "sort", "reverse", "fill", "set", "delete", "add", "discard", "update",
+    "setdefault", "popitem", "truncate", "drain", "retain", "dedup", "push_back",
+    "pop_back", "push_front", "pop_front", "emplace", "emplace_back", "erase",
+];
+
+/// A call of one of `MUTATING_METHODS`: `.push(`
+static MUTATING_CALL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(&format!(r"\.\s*({})\s*\(", MUTATING_METHODS.join("|"))).unwrap());
+
+/// Check whether an expression may have side effects when evaluated.
+///
+/// This is a heuristic: it flags assignment operators, increment/decrement
+/// and calls to well-known mutating methods. String literal contents are ignored.
+/// Python assignments are found in the syntax tree, where the `=` of a
+/// keyword argument (`sorted(xs, key=len)`) assigns nothing.
+pub fn has_side_effects(text: &str, language: LanguageId) -> bool {
+    let writes = match python_assigns(text, language) {
+        Some(assigns) => assigns,
+        None => operators_in(text, language)
+            .iter()
+            .any(|&(_, _, kind)| matches!(kind, OperatorKind::Assignment | OperatorKind::Increment)),
+    };
+    if writes {
+        return true;
+    }
+
+    MUTATING_CALL.is_match(&strip_string_literals(text))
+}
+
+/// Whether Python `text` contains an assignment, augmented assignment or
+/// `:=`; `None` for other languages and text that does not parse
+fn python_assigns(text: &str, language: LanguageId) -> Option<bool> {
+    if language != LanguageId::Python {
+        return None;
+    }
+    let mut parser = LanguageParser::new();
+    parser.set_language(language).ok()?;
+    let tree = parser.parse(text, None).ok()?;
+    if tree.root_node().has_error() {
+        return None;
+    }
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        if matches!(node.kind(), "assignment" | "augmented_assignment" | "named_expression") {
+            return Some(true);
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+    }
+    Some(false)
+}
+
+/// Replace the contents of string literals with spaces, keeping the quotes
+fn strip_string_literals(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
     let mut in_string = false;
     let mut string_char = '"';
     let mut prev_char = ' ';
@@ -155,57 +332,48 @@ pub fn has_balanced_delimiters(text: &str) -> bool {
         if in_string {
             if ch == string_char && prev_char != '\\' {
                 in_string = false;
+                result.push(ch);
+            } else {
+                result.push(' ');
             }
         } else {
-            match ch {
-                '"' | '\'' | '`' => {
-                    in_string = true;
-                    string_char = ch;
-                }
-                '(' | '[' | '{' => stack.push(ch),
-                ')' => {
-                    if stack.pop() != Some('(') {
-                        return false;
-                    }
-                }
-                ']' => {
-                    if stack.pop() != Some('[') {
-                        return false;
-                    }
-                }
-                '}' => {
-                    if stack.pop() != Some('{') {
-                        return false;
-                    }
-                }
-                _ => {}
+            if ch == '"' || ch == '\'' || ch == '`' {
+                in_string = true;
+                string_char = ch;
             }
+            result.push(ch);
         }
         prev_char = ch;
     }
 
-    stack.is_empty() && !in_string
+    result
 }
 
+/// Each language's `identifier_pattern`, compiled once
+static IDENTIFIERS: LazyLock<HashMap<LanguageId, Regex>> = LazyLock::new(|| {
+    LanguageId::ALIASES
+        .iter()
+        .filter(|(alias, language)| *alias == language.as_str())
+        .map(|&(_, language)| (language, Regex::new(language.identifier_pattern()).unwrap()))
+        .collect()
+});
+
 /// Find all variable references in a code snippet
 pub fn find_variable_references(text: &str, language: LanguageId) -> HashSet<String> {
     let mut variables = HashSet::new();
 
-    // Basic identifier pattern (works for most languages)
-    let pattern = match language {
-        LanguageId::Python => r"\b([a-zA-Z_][a-zA-Z0-9_]*)\b",
-        LanguageId::Rust => r"\b([a-zA-Z_][a-zA-Z0-9_]*)\b",
-        LanguageId::Go => r"\b([a-zA-Z_][a-zA-Z0-9_]*)\b",
-        _ => r"\b([a-zA-Z_$][a-zA-Z0-9_$]*)\b",
-    };
-
-    let re = Regex::new(pattern).unwrap();
+    // The language's identifiers; lifetimes, macro names and the like match
+    // without a name
+    let re = &IDENTIFIERS[&language];
 
     // Language keywords to exclude
     let keywords = get_language_keywords(language);
 
     for cap in re.captures_iter(text) {
-        let name = cap.get(1).unwrap().as_str();
+        let name = match cap.get(1) {
+            Some(name) => name.as_str(),
+            None => continue,
+        };
         if !keywords.contains(name) && !is_builtin(name, language) {
             variables.insert(name.to_string());
         }
@@ -214,6 +382,11 @@ pub fn find_variable_references(text: &str, language: LanguageId) -> HashSet<Str
     variables
 }
 
+/// Whether `name` is a keyword of the language
+pub fn is_keyword(name: &str, language: LanguageId) -> bool {
+    get_language_keywords(language).contains(name)
+}
+
 /// Get keywords for a language
 fn get_language_keywords(language: LanguageId) -> HashSet<&'static str> {
     match language {
@@ -253,7 +426,30 @@ fn get_language_keywords(language: LanguageId) -> HashSet<&'static str> {
         .iter()
         .cloned()
         .collect(),
-        _ => HashSet::new(),
+        LanguageId::Java => [
+            "abstract", "assert", "boolean", "break", "byte", "case", "catch", "char", "class",
+            "const", "continue", "default", "do", "double", "else", "enum", "extends", "false",
+            "final", "finally", "float", "for", "goto", "if", "implements", "import",
+            "instanceof", "int", "interface", "long", "native", "new", "null", "package",
+            "private", "protected", "public", "return", "short", "static", "super", "switch",
+            "synchronized", "this", "throw", "throws", "transient", "true", "try", "var",
+            "void", "volatile", "while",
+        ]
+        .iter()
+        .cloned()
+        .collect(),
+        LanguageId::C | LanguageId::Cpp => [
+            "auto", "bool", "break", "case", "catch", "char", "class", "const", "constexpr",
+            "continue", "default", "delete", "do", "double", "else", "enum", "extern", "false",
+            "float", "for", "goto", "if", "inline", "int", "long", "namespace", "new",
+            "nullptr", "operator", "private", "protected", "public", "register", "return",
+            "short", "signed", "sizeof", "static", "struct", "switch", "template", "this",
+            "throw", "true", "try", "typedef", "typename", "union", "unsigned", "using",
+            "virtual", "void", "volatile", "while",
+        ]
+        .iter()
+        .cloned()
+        .collect(),
     }
 }
 
@@ -284,6 +480,8 @@ fn is_builtin(name: &str, language: LanguageId) -> bool {
                 name,
                 "println" | "print" | "format" | "vec" | "String" | "Vec" | "Box" | "Rc" | "Arc"
                     | "Option" | "Result" | "Some" | "None" | "Ok" | "Err"
+                    // Conventional constructor name, confusing as a binding
+                    | "new"
             )
         }
         LanguageId::Go => {
@@ -298,9 +496,47 @@ fn is_builtin(name: &str, language: LanguageId) -> bool {
     }
 }
 
-/// Generate a suggested variable name based on the expression
+/// Generate a suggested variable name based on the expression.
+///
+/// The result is always a valid identifier that is neither a keyword nor a
+/// builtin of the language.
 pub fn suggest_variable_name(text: &str, language: LanguageId) -> String {
+    suggest_variable_name_with(text, language, &NamingConfig::default())
+}
+
+/// Like `suggest_variable_name`, in the casing `naming` sets for variables
+pub fn suggest_variable_name_with(text: &str, language: LanguageId, naming: &NamingConfig) -> String {
+    suggest_name(text, language, naming.variable)
+}
+
+/// A suggested name for the expression, recased when `case` is set
+pub(crate) fn suggest_name(text: &str, language: LanguageId, case: Option<Case>) -> String {
+    let raw = suggest_raw_name(text, language);
+    let name = match case {
+        Some(case) => case.apply(&raw),
+        None => raw,
+    };
+    to_valid_name(&name, language, case.unwrap_or_else(|| Case::default_for(language)))
+}
+
+fn suggest_raw_name(text: &str, language: LanguageId) -> String {
     let trimmed = text.trim();
+    let kinds: HashSet<OperatorKind> = operators_in(trimmed, language)
+        .into_iter()
+        .map(|(_, _, kind)| kind)
+        .collect();
+
+    // `cout << x` and `a..b` read as comparisons or property access otherwise
+    if kinds.contains(&OperatorKind::Stream) {
+        return "stream".to_string();
+    }
+    if kinds.contains(&OperatorKind::Range) {
+        return "range".to_string();
+    }
+
+    if let Some(name) = suggest_condition_name(trimmed, language) {
+        return name;
+    }
 
     // Check for method calls
     if let Some(captures) = Regex::new(r"\.(\w+)\s*\(").unwrap().captures(trimmed) {
@@ -320,12 +556,12 @@ pub fn suggest_variable_name(text: &str, language: LanguageId) -> String {
     }
 
     // Check for binary operations
-    if Regex::new(r"[+\-*/]").unwrap().is_match(trimmed) {
+    if kinds.contains(&OperatorKind::Arithmetic) {
         return default_name(language);
     }
 
     // Check for comparisons
-    if Regex::new(r"[<>=!]=?").unwrap().is_match(trimmed) {
+    if kinds.contains(&OperatorKind::Comparison) || kinds.contains(&OperatorKind::Logical) {
         return "condition".to_string();
     }
 
@@ -333,6 +569,110 @@ pub fn suggest_variable_name(text: &str, language: LanguageId) -> String {
     default_name(language)
 }
 
+/// Name a simple boolean expression with an `is`/`has`/`not` prefix:
+/// `!done` -> `notDone`, `arr.length > 0` -> `hasArr`, `x == null` -> `isXNull`.
+/// Compound conditions (`&&`, `||`) are left to the caller.
+fn suggest_condition_name(text: &str, language: LanguageId) -> Option<String> {
+    if Regex::new(r"&&|\|\||\band\b|\bor\b").unwrap().is_match(text) {
+        return None;
+    }
+
+    // Negation: `!done`, `not done`
+    if let Some(captures) = Regex::new(r"^(?:!\s*|not\s+)(.+)$").unwrap().captures(text) {
+        let operand = captures.get(1).unwrap().as_str().trim();
+        let operand = operand.trim_start_matches('(').trim_end_matches(')');
+        let mut words = words(operand_name(operand)?);
+        if words.first().map(String::as_str) == Some("is") {
+            words.remove(0);
+        }
+        return Some(join_words("not", &words, language));
+    }
+
+    let captures = Regex::new(r"^(.+?)\s*(===|!==|==|!=|>=|<=|>|<|\bis\s+not\b|\bis\b)\s*(.+)$")
+        .unwrap()
+        .captures(text)?;
+    let (left, op, right) = (
+        captures.get(1).unwrap().as_str(),
+        captures.get(2).unwrap().as_str(),
+        captures.get(3).unwrap().as_str().trim(),
+    );
+
+    // Size checks: `arr.length > 0`, `len(items) == 0`, `v.len() != 0`
+    let size_re = Regex::new(r"^(?:len\((.+)\)|(.+?)\.(?:length|size\(\)|size|count|len\(\)))$").unwrap();
+    if let Some(size) = size_re.captures(left.trim()) {
+        let collection = size.get(1).or_else(|| size.get(2)).unwrap().as_str();
+        let words = words(operand_name(collection)?);
+        return match (op, right) {
+            (">" | "!=" | "!==", "0") | (">=", "1") => Some(join_words("has", &words, language)),
+            ("==" | "===", "0") | ("<", "1") => {
+                let mut words = words;
+                words.push("empty".to_string());
+                Some(join_words("is", &words, language))
+            }
+            _ => None,
+        };
+    }
+
+    // Null checks: `x == null`, `x is None`, `x != nil`
+    if matches!(right, "null" | "undefined" | "None" | "nil" | "nullptr") {
+        let words = words(operand_name(left)?);
+        return match op {
+            "==" | "===" | "is" => {
+                let mut words = words;
+                words.push(right.trim_end_matches("ptr").to_lowercase());
+                Some(join_words("is", &words, language))
+            }
+            _ => Some(join_words("has", &words, language)),
+        };
+    }
+
+    None
+}
+
+/// The identifier naming an operand: the last member of a chain (`user.name` -> `name`)
+fn operand_name(expr: &str) -> Option<&str> {
+    Regex::new(r"(\w+)\s*(?:\(\s*\))?\s*$")
+        .unwrap()
+        .captures(expr.trim())
+        .map(|c| c.get(1).unwrap().as_str())
+        .filter(|name| !name.starts_with(|c: char| c.is_ascii_digit()))
+}
+
+/// Join a prefix and words in the language's variable casing
+fn join_words(prefix: &str, words: &[String], language: LanguageId) -> String {
+    let mut parts = vec![prefix.to_string()];
+    parts.extend(words.iter().cloned());
+    Case::default_for(language).join(&parts)
+}
+
+/// Make a suggested name usable: prefix names starting with a digit and
+/// suffix ones that collide with a keyword or builtin, in the casing `case`
+fn to_valid_name(name: &str, language: LanguageId, case: Case) -> String {
+    let mut name = name.to_string();
+
+    if name.is_empty() {
+        return case.apply(&default_name(language));
+    }
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        let prefix = match case {
+            Case::Camel => "value",
+            Case::Pascal => "Value",
+            Case::Snake => "value_",
+            Case::ScreamingSnake => "VALUE_",
+        };
+        name.insert_str(0, prefix);
+    }
+    if get_language_keywords(language).contains(name.as_str()) || is_builtin(&name, language) {
+        name.push_str(match case {
+            Case::Camel | Case::Pascal => "Value",
+            Case::Snake => "_value",
+            Case::ScreamingSnake => "_VALUE",
+        });
+    }
+
+    name
+}
+
 fn to_variable_case(name: &str, language: LanguageId) -> String {
     match language {
         LanguageId::Python | LanguageId::Rust => {
@@ -375,11 +715,18 @@ fn default_name(language: LanguageId) -> String {
 }
 
 /// Find the insertion point for a new variable declaration
+///
+/// This is the start of the line holding the statement that contains the
+/// expression; the declaration carries its own indentation.
 pub fn find_declaration_insertion_point(
     source: &str,
     expression_range: Range,
     language: LanguageId,
 ) -> Position {
+    if let Some(statement) = enclosing_statement(source, expression_range.start, language) {
+        return Position::new(statement.start.line, 0);
+    }
+
     let lines: Vec<&str> = source.lines().collect();
     let expr_line = expression_range.start.line as usize;
 
@@ -440,10 +787,355 @@ pub fn find_declaration_insertion_point(
         }
     }
 
-    // Get the indentation of the target line
-    let indent = lines[target_line].len() - lines[target_line].trim_start().len();
+    Position::new(target_line as u32, 0)
+}
+
+/// Find the smallest statement or declaration containing `position`, using
+/// the tree-sitter grammar of the language.
+///
+/// Returns `None` when the position is not inside a statement (e.g. between
+/// top-level items) or the source cannot be parsed, so callers can fall back
+/// to their own heuristics.
+pub fn enclosing_statement(source: &str, position: Position, language: LanguageId) -> Option<Range> {
+    let offset = crate::position_to_offset(source, position);
+    let (start, end) = statement_bytes(source, offset, language)?;
+    Some(Range::new(
+        crate::offset_to_position(source, start),
+        crate::offset_to_position(source, end),
+    ))
+}
+
+/// Byte span of the smallest statement containing the byte `offset`
+pub(crate) fn statement_bytes(source: &str, offset: usize, language: LanguageId) -> Option<(usize, usize)> {
+    let mut parser = LanguageParser::new();
+    parser.set_language(language).ok()?;
+    let tree = parser.parse(source, None).ok()?;
+
+    // Whitespace belongs to the enclosing block; look at the next token instead
+    let offset = offset + (source[offset.min(source.len())..].len()
+        - source[offset.min(source.len())..].trim_start().len());
+    let mut node = tree.root_node().descendant_for_byte_range(offset, offset)?;
+
+    loop {
+        if is_statement_kind(node.kind()) {
+            return Some((node.start_byte(), node.end_byte()));
+        }
+        node = node.parent()?;
+    }
+}
+
+/// The condition of the innermost conditional expression (`c ? a : b`,
+/// `a if c else b`, a Rust `if` expression) that has `start..end` in one of
+/// its branches, as a byte span. Only conditionals inside the statement
+/// around the span count: a declaration before that statement would run
+/// the branch whatever the condition.
+pub(crate) fn conditional_around(source: &str, start: usize, end: usize, language: LanguageId) -> Option<(usize, usize)> {
+    let mut parser = LanguageParser::new();
+    parser.set_language(language).ok()?;
+    let tree = parser.parse(source, None).ok()?;
+
+    let mut current = tree.root_node().descendant_for_byte_range(start, end)?.parent();
+    while let Some(node) = current {
+        if is_statement_kind(node.kind()) {
+            return None;
+        }
+        if matches!(node.kind(), "ternary_expression" | "conditional_expression" | "if_expression") {
+            // Python's `a if c else b` has no field names
+            let condition = node.child_by_field_name("condition").or_else(|| {
+                (language == LanguageId::Python).then(|| node.named_child(1)).flatten()
+            })?;
+            let in_condition = condition.start_byte() <= start && end <= condition.end_byte();
+            let covers_node = start <= node.start_byte() && node.end_byte() <= end;
+            if !in_condition && !covers_node {
+                return Some((condition.start_byte(), condition.end_byte()));
+            }
+        }
+        current = node.parent();
+    }
+    None
+}
+
+/// Byte spans of the string literals and comments overlapping `start..end`,
+/// outermost only (a template literal's substitutions are part of it)
+pub(crate) fn literal_spans(source: &str, start: usize, end: usize, language: LanguageId) -> Vec<(usize, usize)> {
+    node_spans(source, start, end, language, |kind| kind.contains("string") || kind.contains("comment"))
+}
+
+/// Byte spans of the comments overlapping `start..end`
+pub(crate) fn comment_spans(source: &str, start: usize, end: usize, language: LanguageId) -> Vec<(usize, usize)> {
+    node_spans(source, start, end, language, |kind| kind.contains("comment"))
+}
+
+/// Byte spans of the outermost nodes overlapping `start..end` whose kind
+/// satisfies `wanted`
+fn node_spans(
+    source: &str,
+    start: usize,
+    end: usize,
+    language: LanguageId,
+    wanted: impl Fn(&str) -> bool,
+) -> Vec<(usize, usize)> {
+    let mut parser = LanguageParser::new();
+    if parser.set_language(language).is_err() {
+        return Vec::new();
+    }
+    let tree = match parser.parse(source, None) {
+        Ok(tree) => tree,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut spans = Vec::new();
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        if node.end_byte() <= start || node.start_byte() >= end {
+            continue;
+        }
+        if wanted(node.kind()) {
+            spans.push((node.start_byte(), node.end_byte()));
+            continue;
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.children(&mut cursor));
+    }
+    spans.sort();
+    spans
+}
+
+/// Whether `name`, declared at `decl_range`, is assigned again in its scope.
+///
+/// The scope is the enclosing block, or the enclosing function for Python
+/// and JavaScript `var`. Plain and compound assignments, `++`/`--` and
+/// Python `for`/`as` targets count; strings, comments, member assignments
+/// (`obj.name = ...`) and the declaration itself do not.
+pub fn is_reassigned(source: &str, name: &str, decl_range: Range, language: LanguageId) -> bool {
+    let decl_start = crate::position_to_offset(source, decl_range.start);
+    let decl_end = crate::position_to_offset(source, decl_range.end).max(decl_start);
+    let (start, end) = declaration_scope(source, decl_start, decl_end, language).unwrap_or((0, source.len()));
+
+    // Blank out everything that cannot reassign the variable
+    let mut code = source.as_bytes()[start..end].to_vec();
+    let mut blanked = literal_spans(source, start, end, language);
+    blanked.push((decl_start, decl_end));
+    for (s, e) in blanked {
+        for byte in &mut code[s.clamp(start, end) - start..e.clamp(start, end) - start] {
+            *byte = b' ';
+        }
+    }
+    // Only whole characters and tokens were blanked
+    let code = String::from_utf8(code).unwrap_or_default();
+
+    let name = regex::escape(name);
+    let operators = match language {
+        LanguageId::Go => r"(?::|[-+*/%&|^]|<<|>>|&\^)?",
+        LanguageId::Python => r"(?::|[-+*/%&|^@]|<<|>>|\*\*|//)?",
+        _ => r"(?:[-+*/%&|^]|<<|>>|>>>|\*\*|&&|\|\||\?\?)?",
+    };
+    let mut patterns = vec![
+        format!(r"\b{}\s*{}=(?:[^=>]|$)", name, operators),
+        format!(r"\b{}\s*(?:\+\+|--)|(?:\+\+|--)\s*{}\b", name, name),
+    ];
+    if language == LanguageId::Python {
+        patterns.push(format!(r"\bfor\s+(?:\w+\s*,\s*)*{}\b|\bas\s+{}\b", name, name));
+        patterns.push(format!(r"\b{}\s*:[^=\n]+=(?:[^=]|$)", name));
+    }
+
+    patterns.iter().filter_map(|p| Regex::new(p).ok()).any(|re| {
+        re.find_iter(&code).any(|m| {
+            // `obj.name = ...` and `obj->name = ...` assign a member
+            let before = code[..m.start()].trim_end();
+            !(before.ends_with('.') || before.ends_with("->"))
+        })
+    })
+}
+
+/// The innermost function, method or closure whose parameters or body hold
+/// `start..end`. A function's own name belongs to the scope around it.
+pub(crate) fn function_around(root: Node, start: usize, end: usize) -> Option<Node> {
+    let inside = |node: Option<Node>| node.is_some_and(|n| n.start_byte() <= start && end <= n.end_byte());
+    let mut current = root.descendant_for_byte_range(start, end)?.parent();
+    while let Some(node) = current {
+        let kind = node.kind();
+        let is_function = ["function", "method", "constructor", "lambda", "closure", "func_literal"]
+            .iter()
+            .any(|k| kind.contains(k));
+        if is_function {
+            // C and C++ keep the parameters in the declarator
+            let parameters = node
+                .child_by_field_name("parameters")
+                .or_else(|| node.child_by_field_name("parameter"))
+                .or_else(|| node.child_by_field_name("declarator").and_then(|d| d.child_by_field_name("parameters")));
+            if inside(node.child_by_field_name("body")) || inside(parameters) {
+                return Some(node);
+            }
+        }
+        current = node.parent();
+    }
+    None
+}
+
+/// Byte span of the scope a declaration at `start..end` belongs to
+fn declaration_scope(source: &str, start: usize, end: usize, language: LanguageId) -> Option<(usize, usize)> {
+    let mut parser = LanguageParser::new();
+    parser.set_language(language).ok()?;
+    let tree = parser.parse(source, None).ok()?;
+    let node = tree.root_node().descendant_for_byte_range(start, end)?;
+
+    let function_scoped = language == LanguageId::Python
+        || (matches!(language, LanguageId::JavaScript | LanguageId::TypeScript) && source[start..].starts_with("var"));
+    let is_function = |kind: &str| kind.contains("function") || kind.contains("method") || kind.contains("lambda");
+    let is_block = |kind: &str| {
+        matches!(kind, "block" | "statement_block" | "compound_statement" | "class_definition" | "class_body")
+    };
+
+    let mut current = node.parent();
+    while let Some(scope) = current {
+        let kind = scope.kind();
+        if is_function(kind) || (!function_scoped && is_block(kind)) {
+            return Some((scope.start_byte(), scope.end_byte()));
+        }
+        current = scope.parent();
+    }
+    Some((0, source.len()))
+}
+
+/// A local declaration found by `declaration_in_scope`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct LocalDeclaration {
+    /// Byte span of the declaring statement
+    pub statement: (usize, usize),
+    /// Whether the variable may be assigned again: not `const`/`final`, and
+    /// in Rust `mut` or declared without a value
+    pub assignable: bool,
+}
+
+/// The nearest declaration of `name` that ends before byte `at` and whose
+/// scope holds `at`. A Python assignment counts as a declaration.
+pub(crate) fn declaration_in_scope(source: &str, name: &str, at: usize, language: LanguageId) -> Option<LocalDeclaration> {
+    let mut parser = LanguageParser::new();
+    parser.set_language(language).ok()?;
+    let tree = parser.parse(source, None).ok()?;
+
+    let constant = Regex::new(r"\b(?:const|final)\b").unwrap();
+    let mutable = Regex::new(r"\bmut\b").unwrap();
+    let mut found = None;
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        if node.start_byte() >= at {
+            continue;
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.children(&mut cursor));
+
+        let (binding, statement) = match node.kind() {
+            "variable_declarator" | "init_declarator" | "var_spec" => {
+                let field = if node.kind() == "init_declarator" { "declarator" } else { "name" };
+                (node.child_by_field_name(field), node.parent().unwrap_or(node))
+            }
+            "let_declaration" => (node.child_by_field_name("pattern"), node),
+            "short_var_declaration" => (node.child_by_field_name("left"), node),
+            // `int result;` has the name itself as its declarator
+            "declaration" => (node.child_by_field_name("declarator").filter(|d| d.kind() == "identifier"), node),
+            "assignment" if language == LanguageId::Python => {
+                let left = node
+                    .child_by_field_name("left")
+                    .filter(|left| matches!(left.kind(), "identifier" | "pattern_list" | "tuple_pattern"));
+                (left, node.parent().unwrap_or(node))
+            }
+            _ => continue,
+        };
+        let Some(binding) = binding else { continue };
+        if statement.end_byte() > at {
+            continue;
+        }
+
+        let mut names = Vec::new();
+        collect_identifiers(binding, source, &mut names);
+        if !names.contains(&name) {
+            continue;
+        }
+        let (start, end) = (statement.start_byte(), statement.end_byte());
+        let (scope_start, scope_end) = declaration_scope(source, start, end, language).unwrap_or((0, source.len()));
+        if !(scope_start <= at && at <= scope_end) {
+            continue;
+        }
 
-    Position::new(target_line as u32, indent as u32)
+        let modifiers = &source[start..binding.start_byte()];
+        let immutable = language == LanguageId::Rust
+            && !mutable.is_match(modifiers)
+            && node.child_by_field_name("value").is_some();
+        let declaration = LocalDeclaration {
+            statement: (start, end),
+            assignable: !(constant.is_match(modifiers) || immutable),
+        };
+        if found.is_none_or(|f: LocalDeclaration| f.statement.0 < start) {
+            found = Some(declaration);
+        }
+    }
+    found
+}
+
+/// The expression body of the innermost function or closure around
+/// `start..end` whose parameters bind one of `names`, as a byte span.
+///
+/// `None` if no enclosing function binds them, or the one that does has a
+/// block body: a statement inside it can then take a declaration without
+/// moving the names out of scope.
+pub(crate) fn closure_expression_body(
+    source: &str,
+    start: usize,
+    end: usize,
+    names: &HashSet<String>,
+    language: LanguageId,
+) -> Option<(usize, usize)> {
+    let mut parser = LanguageParser::new();
+    parser.set_language(language).ok()?;
+    let tree = parser.parse(source, None).ok()?;
+    let mut current = tree.root_node().descendant_for_byte_range(start, end);
+
+    while let Some(node) = current {
+        let kind = node.kind();
+        let is_function = kind.contains("function") || kind.contains("lambda") || kind.contains("closure") || kind.contains("method");
+        let parameters = node.child_by_field_name("parameters").or_else(|| node.child_by_field_name("parameter"));
+        if let (true, Some(parameters)) = (is_function, parameters) {
+            let mut bound = Vec::new();
+            collect_identifiers(parameters, source, &mut bound);
+            if bound.iter().any(|name| names.contains(*name)) {
+                let body = node.child_by_field_name("body")?;
+                let is_block = matches!(body.kind(), "block" | "statement_block" | "compound_statement");
+                return (!is_block && body.start_byte() <= start && end <= body.end_byte())
+                    .then(|| (body.start_byte(), body.end_byte()));
+            }
+        }
+        current = node.parent();
+    }
+    None
+}
+
+/// Identifiers below `node`, leaving out type annotations and default values
+pub(crate) fn collect_identifiers<'s>(node: tree_sitter::Node, source: &'s str, names: &mut Vec<&'s str>) {
+    if node.kind() == "identifier" || node.kind() == "shorthand_property_identifier_pattern" {
+        names.push(&source[node.byte_range()]);
+        return;
+    }
+    let mut cursor = node.walk();
+    for (i, child) in node.children(&mut cursor).enumerate() {
+        let field = node.field_name_for_child(i as u32);
+        if !matches!(field, Some("type" | "value" | "default_value")) {
+            collect_identifiers(child, source, names);
+        }
+    }
+}
+
+/// Node kinds that form a statement or declaration across the supported grammars
+pub(crate) fn is_statement_kind(kind: &str) -> bool {
+    if kind.contains("parameter") || kind == "compound_statement" {
+        return false;
+    }
+    kind.ends_with("_statement")
+        || kind.ends_with("_declaration")
+        || kind.ends_with("_definition")
+        || kind.ends_with("_item")
+        || kind == "declaration"
 }
 
 #[cfg(test)]
@@ -459,6 +1151,58 @@ mod tests {
         assert!(!has_balanced_delimiters("foo(bar[0)"));
     }
 
+    #[test]
+    fn test_unbalanced_delimiter_position() {
+        let unbalanced = unbalanced_delimiter("foo(a, b").unwrap();
+        assert_eq!((unbalanced.offset, unbalanced.delimiter, unbalanced.expected, unbalanced.is_open), (3, '(', ')', true));
+
+        let unbalanced = unbalanced_delimiter("a, b) + c").unwrap();
+        assert_eq!((unbalanced.offset, unbalanced.delimiter, unbalanced.is_open), (4, ')', false));
+
+        let unbalanced = unbalanced_delimiter("foo(bar[0)").unwrap();
+        assert_eq!((unbalanced.offset, unbalanced.delimiter), (7, '['));
+
+        assert_eq!(unbalanced_delimiter("f(\"(\")").map(|u| u.offset), None);
+        assert_eq!(unbalanced_delimiter("say('hi").map(|u| u.offset), Some(4));
+    }
+
+    #[test]
+    fn test_is_reassigned_javascript() {
+        let source = "function f(items) {\n  let total = 0;\n  let label = \"total = \";\n  // label = null\n  for (const item of items) {\n    total += item;\n  }\n  items.label = total;\n  return label + total;\n}\n";
+        let declaration = |line| Range::from_coords(line, 2, line, 100);
+        assert!(is_reassigned(source, "total", declaration(1), LanguageId::JavaScript));
+        assert!(!is_reassigned(source, "label", declaration(2), LanguageId::JavaScript));
+
+        let source = "let count = 0;\ncount++;\nlet done = count === 1;\n";
+        assert!(is_reassigned(source, "count", Range::from_coords(0, 0, 0, 14), LanguageId::JavaScript));
+        assert!(!is_reassigned(source, "done", Range::from_coords(2, 0, 2, 23), LanguageId::JavaScript));
+    }
+
+    #[test]
+    fn test_is_reassigned_python() {
+        let source = "def f(rows):\n    total = 0\n    name = \"total = 1\"\n    for row in rows:\n        total += row\n    self.name = name\n    return name == total\n";
+        let declaration = |line| Range::from_coords(line, 4, line, 100);
+        assert!(is_reassigned(source, "total", declaration(1), LanguageId::Python));
+        assert!(!is_reassigned(source, "name", declaration(2), LanguageId::Python));
+
+        let source = "def g(rows):\n    row = None\n    for row in rows:\n        pass\n";
+        assert!(is_reassigned(source, "row", declaration(1), LanguageId::Python));
+    }
+
+    #[test]
+    fn test_find_variable_references_rust() {
+        let refs = find_variable_references("fn f<'a>(s: &'a str) -> usize { println!(\"{}\", s); vec![n].len() + (m!=k) as usize }", LanguageId::Rust);
+        for name in ["s", "n", "m", "k", "len"] {
+            assert!(refs.contains(name), "{}", name);
+        }
+        for name in ["a", "println", "vec"] {
+            assert!(!refs.contains(name), "{}", name);
+        }
+
+        // The `r` of a raw identifier is not a name of its own
+        assert!(!find_variable_references("r#try + 1", LanguageId::Rust).contains("r"));
+    }
+
     #[test]
     fn test_is_valid_expression() {
         assert!(is_valid_expression("a + b", LanguageId::JavaScript));
@@ -468,6 +1212,24 @@ mod tests {
         assert!(!is_valid_expression("function foo", LanguageId::JavaScript));
     }
 
+    #[test]
+    fn test_has_side_effects() {
+        assert!(has_side_effects("i++", LanguageId::JavaScript));
+        assert!(has_side_effects("x = 5", LanguageId::JavaScript));
+        assert!(has_side_effects("total += n", LanguageId::Rust));
+        assert!(has_side_effects("arr.push(x)", LanguageId::JavaScript));
+        assert!(has_side_effects("list.append(x)", LanguageId::Python));
+        assert!(!has_side_effects("a == b", LanguageId::JavaScript));
+        assert!(!has_side_effects("a >= b && c != d", LanguageId::JavaScript));
+        assert!(!has_side_effects("(x) => x * 2", LanguageId::JavaScript));
+        assert!(!has_side_effects("\"a = b\" + c", LanguageId::JavaScript));
+        assert!(!has_side_effects("obj.getName()", LanguageId::JavaScript));
+        assert!(!has_side_effects("sorted(xs, key=len)", LanguageId::Python));
+        assert!(!has_side_effects("dict(a=1, b=f(c=2))", LanguageId::Python));
+        assert!(has_side_effects("(n := len(xs))", LanguageId::Python));
+        assert!(has_side_effects("total += n", LanguageId::Python));
+    }
+
     #[test]
     fn test_suggest_variable_name() {
         assert_eq!(
@@ -483,4 +1245,68 @@ mod tests {
             "name"
         );
     }
+
+    #[test]
+    fn test_suggest_condition_name() {
+        assert_eq!(suggest_variable_name("!done", LanguageId::JavaScript), "notDone");
+        assert_eq!(suggest_variable_name("not done", LanguageId::Python), "not_done");
+        assert_eq!(suggest_variable_name("!user.isReady", LanguageId::JavaScript), "notReady");
+        assert_eq!(suggest_variable_name("arr.length > 0", LanguageId::JavaScript), "hasArr");
+        assert_eq!(suggest_variable_name("len(items) == 0", LanguageId::Python), "is_items_empty");
+        assert_eq!(suggest_variable_name("user == null", LanguageId::Java), "isUserNull");
+        assert_eq!(suggest_variable_name("user is not None", LanguageId::Python), "has_user");
+        assert_eq!(suggest_variable_name("x.isReady", LanguageId::JavaScript), "isReady");
+        assert_eq!(suggest_variable_name("a > b && c", LanguageId::JavaScript), "condition");
+    }
+
+    #[test]
+    fn test_enclosing_statement_javascript() {
+        let source = "function f(a, b) {\n    if (a) {\n        const x = g(a + b, 1);\n    }\n}\n";
+        // Inside `a + b`, nested in a call in a declaration in an `if`
+        let statement = enclosing_statement(source, Position::new(2, 22), LanguageId::JavaScript).unwrap();
+        assert_eq!(statement, Range::from_coords(2, 8, 2, 30));
+    }
+
+    #[test]
+    fn test_enclosing_statement_python() {
+        let source = "def f(a, b):\n    for x in a:\n        total = g(x * b,\n                  1)\n";
+        // Inside `x * b` on a statement spanning two lines
+        let statement = enclosing_statement(source, Position::new(2, 18), LanguageId::Python).unwrap();
+        assert_eq!(statement, Range::from_coords(2, 8, 3, 20));
+    }
+
+    #[test]
+    fn test_declaration_insertion_point_multiline_statement() {
+        let source = "def f(a):\n    total = g(a,\n              a * 2)\n";
+        let expression = Range::from_coords(2, 14, 2, 19); // "a * 2"
+        assert_eq!(
+            find_declaration_insertion_point(source, expression, LanguageId::Python),
+            Position::new(1, 0)
+        );
+    }
+
+    #[test]
+    fn test_suggest_variable_name_avoids_keywords() {
+        assert_eq!(suggest_variable_name("node.type()", LanguageId::Python), "type_value");
+        assert_eq!(suggest_variable_name("builder.new()", LanguageId::Rust), "new_value");
+        assert_eq!(suggest_variable_name("options.default", LanguageId::JavaScript), "defaultValue");
+        assert_eq!(suggest_variable_name("obj.class", LanguageId::Java), "classValue");
+    }
+
+    #[test]
+    fn test_suggest_variable_name_not_starting_with_digit() {
+        assert_eq!(suggest_variable_name("pair.0", LanguageId::Rust), "value_0");
+    }
+
+    #[test]
+    fn test_suggest_variable_name_by_operator() {
+        assert_eq!(suggest_variable_name("cout << x", LanguageId::Cpp), "stream");
+        assert_eq!(suggest_variable_name("std::cout << x.size()", LanguageId::Cpp), "stream");
+        assert_eq!(suggest_variable_name("a << b", LanguageId::C), "extracted");
+        assert_eq!(suggest_variable_name("a..b", LanguageId::Rust), "range");
+        assert_eq!(suggest_variable_name("0..=items.len()", LanguageId::Rust), "range");
+        assert!(!has_side_effects("0..=n", LanguageId::Rust));
+        assert!(!has_side_effects("s.split('=')", LanguageId::Rust));
+        assert!(has_side_effects("total <<= 1", LanguageId::Cpp));
+    }
 }
@@ -0,0 +1,323 @@
+//! Convert Declaration Refactoring
+//!
+//! Quick conversions of the declaration under the cursor:
+//! - JavaScript/TypeScript: `var` → `let`/`const`, `let` → `const` and
+//!   `const` → `let`. `const` is only offered for initialized variables that
+//!   are never reassigned in their scope; `var` only for declarations at the
+//!   top of their function, not used before and not redeclared, where block
+//!   scoping cannot change the meaning.
+//! - Python: add a type annotation inferred from a literal value, or remove
+//!   an existing one
+
+use crate::analysis::{is_reassigned, literal_spans};
+use crate::{offset_to_position, RefactorContext, RefactorError, RefactorResult, TextEdit};
+use logos_core::Range;
+use logos_parser::{LanguageId, LanguageParser};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tree_sitter::Node;
+
+/// A conversion offered for a declaration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Conversion {
+    /// e.g. `let-to-const`, `add-annotation`
+    pub id: String,
+    pub title: String,
+    pub edit: TextEdit,
+}
+
+/// The legal conversions for the declaration at the start of the selection
+pub fn conversions(ctx: &RefactorContext) -> Vec<Conversion> {
+    let mut parser = LanguageParser::new();
+    if parser.set_language(ctx.language).is_err() {
+        return Vec::new();
+    }
+    let tree = match parser.parse(ctx.source, None) {
+        Ok(tree) => tree,
+        Err(_) => return Vec::new(),
+    };
+    let offset = ctx.selection_bytes.start;
+    let node = match tree.root_node().descendant_for_byte_range(offset, offset) {
+        Some(node) => node,
+        None => return Vec::new(),
+    };
+
+    match ctx.language {
+        LanguageId::JavaScript | LanguageId::TypeScript => js_conversions(ctx, node),
+        LanguageId::Python => python_conversions(ctx, node),
+        _ => Vec::new(),
+    }
+}
+
+/// Apply the conversion `id` to the declaration at the selection
+pub fn convert(ctx: &RefactorContext, id: &str) -> Result<RefactorResult, RefactorError> {
+    let conversion = conversions(ctx)
+        .into_iter()
+        .find(|c| c.id == id)
+        .ok_or_else(|| RefactorError::Unsupported(format!("'{}' does not apply to this declaration", id)))?;
+    Ok(RefactorResult::new(vec![conversion.edit], conversion.title))
+}
+
+fn js_conversions(ctx: &RefactorContext, node: Node) -> Vec<Conversion> {
+    let declaration = match ancestor(node, &["lexical_declaration", "variable_declaration"]) {
+        Some(declaration) => declaration,
+        None => return Vec::new(),
+    };
+    let keyword = match declaration.child(0) {
+        Some(keyword) => keyword,
+        None => return Vec::new(),
+    };
+    let scope = match declaration.parent() {
+        Some(scope) => scope,
+        None => return Vec::new(),
+    };
+
+    let mut names = Vec::new();
+    let mut initialized = true;
+    let mut cursor = declaration.walk();
+    for declarator in declaration.named_children(&mut cursor).filter(|n| n.kind() == "variable_declarator") {
+        initialized &= declarator.child_by_field_name("value").is_some();
+        if let Some(name) = declarator.child_by_field_name("name") {
+            binding_names(name, ctx.source, &mut names);
+        }
+    }
+
+    let code = scope_code(ctx, scope, declaration);
+    let declaration_range = node_range(ctx, declaration);
+    let reassigned = names
+        .iter()
+        .any(|n| is_reassigned(ctx.source, n, declaration_range, ctx.language));
+    let can_be_const = initialized && !reassigned;
+
+    let keyword_range = node_range(ctx, keyword);
+    let offer = |id: &str, to: &str| Conversion {
+        id: id.to_string(),
+        title: format!("Convert '{}' to '{}'", keyword.kind(), to),
+        edit: TextEdit::replace(keyword_range, to.to_string()),
+    };
+
+    match keyword.kind() {
+        "const" => vec![offer("const-to-let", "let")],
+        "let" if can_be_const => vec![offer("let-to-const", "const")],
+        "var" if is_function_top_level(scope) && !names.iter().any(|n| code.uses_before(n) || code.redeclares(n)) => {
+            let mut offers = vec![offer("var-to-let", "let")];
+            if can_be_const {
+                offers.push(offer("var-to-const", "const"));
+            }
+            offers
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn python_conversions(ctx: &RefactorContext, node: Node) -> Vec<Conversion> {
+    let assignment = match ancestor(node, &["assignment"]) {
+        Some(assignment) => assignment,
+        None => return Vec::new(),
+    };
+    let (left, right) = match (assignment.child_by_field_name("left"), assignment.child_by_field_name("right")) {
+        (Some(left), Some(right)) if left.kind() == "identifier" => (left, right),
+        _ => return Vec::new(),
+    };
+    let name = &ctx.source[left.byte_range()];
+
+    match assignment.child_by_field_name("type") {
+        Some(annotation) => {
+            let range = Range::new(
+                offset_to_position(ctx.source, left.end_byte()),
+                offset_to_position(ctx.source, annotation.end_byte()),
+            );
+            vec![Conversion {
+                id: "remove-annotation".to_string(),
+                title: format!("Remove type annotation of '{}'", name),
+                edit: TextEdit::replace(range, String::new()),
+            }]
+        }
+        None => match literal_type(right) {
+            Some(ty) => vec![Conversion {
+                id: "add-annotation".to_string(),
+                title: format!("Annotate '{}' as '{}'", name, ty),
+                edit: TextEdit::insert(offset_to_position(ctx.source, left.end_byte()), format!(": {}", ty)),
+            }],
+            None => Vec::new(),
+        },
+    }
+}
+
+/// The Python type of a literal expression
+fn literal_type(node: Node) -> Option<&'static str> {
+    match node.kind() {
+        "integer" => Some("int"),
+        "float" => Some("float"),
+        "string" | "concatenated_string" => Some("str"),
+        "true" | "false" => Some("bool"),
+        "list" => Some("list"),
+        "dictionary" => Some("dict"),
+        "set" => Some("set"),
+        "tuple" => Some("tuple"),
+        _ => None,
+    }
+}
+
+/// The names bound by a declarator name or destructuring pattern
+fn binding_names(node: Node, source: &str, names: &mut Vec<String>) {
+    match node.kind() {
+        "identifier" | "shorthand_property_identifier_pattern" => names.push(source[node.byte_range()].to_string()),
+        // `{ key: value = default }` binds `value` only
+        "pair_pattern" => {
+            if let Some(value) = node.child_by_field_name("value") {
+                binding_names(value, source, names);
+            }
+        }
+        _ => {
+            let mut cursor = node.walk();
+            for child in node.named_children(&mut cursor) {
+                // Default values are expressions, not bindings
+                if node.kind() == "assignment_pattern" && node.child_by_field_name("right") == Some(child) {
+                    continue;
+                }
+                binding_names(child, source, names);
+            }
+        }
+    }
+}
+
+/// The nearest node of one of `kinds`, starting at `node`
+fn ancestor<'t>(node: Node<'t>, kinds: &[&str]) -> Option<Node<'t>> {
+    let mut current = Some(node);
+    while let Some(n) = current {
+        if kinds.contains(&n.kind()) {
+            return Some(n);
+        }
+        current = n.parent();
+    }
+    None
+}
+
+/// Whether `scope` is a function body or the program, where `let` scoping
+/// matches `var` scoping
+fn is_function_top_level(scope: Node) -> bool {
+    match scope.kind() {
+        "program" => true,
+        "statement_block" => scope.parent().is_some_and(|p| {
+            matches!(
+                p.kind(),
+                "function_declaration" | "function_expression" | "function" | "arrow_function" | "method_definition" | "generator_function_declaration"
+            )
+        }),
+        _ => false,
+    }
+}
+
+fn node_range(ctx: &RefactorContext, node: Node) -> Range {
+    Range::new(
+        offset_to_position(ctx.source, node.start_byte()),
+        offset_to_position(ctx.source, node.end_byte()),
+    )
+}
+
+/// The code of a scope around a declaration, with strings and comments
+/// blanked out
+struct ScopeCode {
+    before: String,
+    after: String,
+}
+
+impl ScopeCode {
+    fn uses_before(&self, name: &str) -> bool {
+        word(name).is_match(&self.before)
+    }
+
+    fn redeclares(&self, name: &str) -> bool {
+        let pattern = format!(r"\b(?:var|let|const)\s+{}\b", regex::escape(name));
+        let redeclaration = Regex::new(&pattern).unwrap();
+        redeclaration.is_match(&self.before) || redeclaration.is_match(&self.after)
+    }
+}
+
+fn word(name: &str) -> Regex {
+    Regex::new(&format!(r"\b{}\b", regex::escape(name))).unwrap()
+}
+
+fn scope_code(ctx: &RefactorContext, scope: Node, declaration: Node) -> ScopeCode {
+    let start = scope.start_byte();
+    let end = scope.end_byte();
+    let mut code = ctx.source.as_bytes()[start..end].to_vec();
+    for (s, e) in literal_spans(ctx.source, start, end, ctx.language) {
+        for byte in &mut code[s.max(start) - start..e.min(end) - start] {
+            *byte = b' ';
+        }
+    }
+    // Only whole characters were blanked
+    let code = String::from_utf8(code).unwrap_or_default();
+    let split = declaration.start_byte() - start;
+    let after = declaration.end_byte() - start;
+    ScopeCode {
+        before: code[..split].to_string(),
+        after: code[after..].to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apply_edits;
+
+    fn offered(source: &str, line: u32, column: u32, language: LanguageId) -> Vec<String> {
+        let ctx = RefactorContext::new(source, "test", Range::from_coords(line, column, line, column), language);
+        conversions(&ctx).into_iter().map(|c| c.id).collect()
+    }
+
+    fn converted(source: &str, line: u32, column: u32, language: LanguageId, id: &str) -> String {
+        let ctx = RefactorContext::new(source, "test", Range::from_coords(line, column, line, column), language);
+        apply_edits(source, &convert(&ctx, id).unwrap().edits)
+    }
+
+    #[test]
+    fn test_let_to_const_when_never_reassigned() {
+        let source = "function f(items) {\n  let total = items.length;\n  // total = 0\n  const label = \"total = \" + total;\n  return label;\n}\n";
+        assert_eq!(offered(source, 1, 6, LanguageId::JavaScript), vec!["let-to-const"]);
+        assert_eq!(
+            converted(source, 1, 2, LanguageId::JavaScript, "let-to-const"),
+            source.replace("let total", "const total")
+        );
+        assert_eq!(offered(source, 3, 8, LanguageId::JavaScript), vec!["const-to-let"]);
+    }
+
+    #[test]
+    fn test_reassignment_blocks_const() {
+        let source = "function f(items) {\n  let count = 0;\n  for (const item of items) {\n    count += item;\n  }\n  let { a, b: renamed } = items;\n  renamed++;\n  return count;\n}\n";
+        assert!(offered(source, 1, 6, LanguageId::JavaScript).is_empty());
+        assert!(offered(source, 5, 8, LanguageId::JavaScript).is_empty());
+
+        let ctx = RefactorContext::new(source, "test", Range::from_coords(1, 6, 1, 6), LanguageId::JavaScript);
+        assert!(matches!(convert(&ctx, "let-to-const"), Err(RefactorError::Unsupported(_))));
+
+        // Uninitialized `let` cannot become `const`
+        assert!(offered("let pending;\nuse(pending);\n", 0, 4, LanguageId::JavaScript).is_empty());
+    }
+
+    #[test]
+    fn test_var_conversions() {
+        let source = "function f() {\n  var a = 1;\n  var b = 2;\n  b = a;\n  if (a) {\n    var c = 3;\n  }\n  return c;\n}\n";
+        assert_eq!(offered(source, 1, 6, LanguageId::JavaScript), vec!["var-to-let", "var-to-const"]);
+        assert_eq!(offered(source, 2, 6, LanguageId::JavaScript), vec!["var-to-let"]);
+        // Block-scoping `c` would break the use after the block
+        assert!(offered(source, 5, 8, LanguageId::JavaScript).is_empty());
+    }
+
+    #[test]
+    fn test_python_annotations() {
+        let source = "def f():\n    retries = 3\n    name: str = \"x\"\n    value = compute()\n";
+        assert_eq!(
+            converted(source, 1, 4, LanguageId::Python, "add-annotation"),
+            source.replace("retries = 3", "retries: int = 3")
+        );
+        assert_eq!(
+            converted(source, 2, 4, LanguageId::Python, "remove-annotation"),
+            source.replace("name: str =", "name =")
+        );
+        assert!(offered(source, 3, 4, LanguageId::Python).is_empty());
+    }
+}
@@ -0,0 +1,389 @@
+//! Convert Function Refactoring
+//!
+//! JavaScript/TypeScript conversions between the ways of writing a function:
+//! - `function foo() {}` ↔ `const foo = () => {};`
+//! - `foo() {}` ↔ `foo: () => {}` in object literals
+//!
+//! Parameters, type annotations, `async` and the body are kept; an
+//! expression-bodied arrow gets a block with a `return`. Conversions that
+//! would change what `this` or `arguments` refer to are refused, as is
+//! turning a declaration that is called before it (hoisting) into a `const`.
+
+use crate::{offset_to_position, RefactorContext, RefactorError, RefactorResult, TextEdit};
+use logos_core::Range;
+use logos_parser::{LanguageId, LanguageParser};
+use serde::{Deserialize, Serialize};
+use tree_sitter::Node;
+
+/// The form to convert a function to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FunctionForm {
+    /// `function foo() {}`
+    Declaration,
+    /// `const foo = () => {};`, or `foo: () => {}` in an object literal
+    Arrow,
+    /// `foo() {}` in an object literal
+    Method,
+}
+
+impl FunctionForm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FunctionForm::Declaration => "function declaration",
+            FunctionForm::Arrow => "arrow function",
+            FunctionForm::Method => "method shorthand",
+        }
+    }
+}
+
+/// The pieces of a function, as written
+struct Parts<'s> {
+    name: &'s str,
+    is_async: bool,
+    type_parameters: &'s str,
+    /// With parentheses
+    parameters: String,
+    /// Including the leading `:`
+    return_type: &'s str,
+    /// A statement block, or the expression of an arrow
+    body: Node<'s>,
+}
+
+/// Convert the function at the start of the selection to `target`
+pub fn convert(ctx: &RefactorContext, target: FunctionForm) -> Result<RefactorResult, RefactorError> {
+    if !matches!(ctx.language, LanguageId::JavaScript | LanguageId::TypeScript) {
+        return Err(RefactorError::Unsupported(format!(
+            "Function conversions are not available for {}",
+            ctx.language.as_str()
+        )));
+    }
+
+    let mut parser = LanguageParser::new();
+    parser
+        .set_language(ctx.language)
+        .map_err(|e| RefactorError::ParseError(e.to_string()))?;
+    let tree = parser
+        .parse(ctx.source, None)
+        .map_err(|e| RefactorError::ParseError(e.to_string()))?;
+    let offset = ctx.selection_bytes.start;
+    let node = tree
+        .root_node()
+        .descendant_for_byte_range(offset, offset)
+        .ok_or_else(|| RefactorError::InvalidSelection("No function at the cursor".to_string()))?;
+    let (target_node, current) = function_at(node)
+        .ok_or_else(|| RefactorError::InvalidSelection("No function at the cursor".to_string()))?;
+
+    if current == target {
+        return Err(RefactorError::Unsupported(format!("Already a {}", target.as_str())));
+    }
+    let in_object = matches!(target_node.kind(), "pair" | "method_definition");
+    let allowed = match target {
+        FunctionForm::Declaration => !in_object,
+        FunctionForm::Arrow => true,
+        FunctionForm::Method => in_object,
+    };
+    if !allowed {
+        return Err(RefactorError::Unsupported(format!(
+            "Cannot convert a {} to a {} here",
+            current.as_str(),
+            target.as_str()
+        )));
+    }
+
+    let function = function_node(target_node)
+        .ok_or_else(|| RefactorError::InvalidSelection("No function at the cursor".to_string()))?;
+    let parts = parts(ctx.source, target_node, function)?;
+
+    // Arrows take `this` and `arguments` from the enclosing function
+    let is_arrow = function.kind() == "arrow_function";
+    if is_arrow != (target == FunctionForm::Arrow) {
+        if let Some(word) = binding_use(parts.body, ctx.source) {
+            return Err(RefactorError::Unsupported(format!(
+                "The function uses '{}', which would refer to something else after the conversion",
+                word
+            )));
+        }
+    }
+    // `const` is not hoisted like a function declaration
+    if target_node.kind() == "function_declaration" && used_before(target_node, parts.name, ctx.source) {
+        return Err(RefactorError::Unsupported(format!(
+            "'{}' is called before its declaration, which only works for a function declaration",
+            parts.name
+        )));
+    }
+
+    let indent = line_indent(ctx.source, target_node.start_byte());
+    let text = write(ctx.source, &parts, target, in_object, target_node, indent);
+    let range = Range::new(
+        offset_to_position(ctx.source, target_node.start_byte()),
+        offset_to_position(ctx.source, target_node.end_byte()),
+    );
+    Ok(RefactorResult::new(
+        vec![TextEdit::replace(range, text)],
+        format!("Convert '{}' to {}", parts.name, target.as_str()),
+    ))
+}
+
+/// The innermost function around `node` that can be converted, with its form
+fn function_at(node: Node) -> Option<(Node, FunctionForm)> {
+    let mut current = Some(node);
+    while let Some(n) = current {
+        match n.kind() {
+            "function_declaration" => return Some((n, FunctionForm::Declaration)),
+            "method_definition" if n.parent().is_some_and(|p| p.kind() == "object") => {
+                return Some((n, FunctionForm::Method))
+            }
+            "pair" | "lexical_declaration" | "variable_declaration" => {
+                if let Some(function) = function_node(n) {
+                    // `foo: function () {}` and `const foo = function () {}`
+                    // convert like the form they are closest to
+                    let form = match (function.kind(), n.kind()) {
+                        ("arrow_function", _) => FunctionForm::Arrow,
+                        (_, "pair") => FunctionForm::Method,
+                        _ => FunctionForm::Declaration,
+                    };
+                    return Some((n, form));
+                }
+            }
+            // Other functions are not converted from their inside
+            "class_body" | "generator_function_declaration" => return None,
+            _ => {}
+        }
+        current = n.parent();
+    }
+    None
+}
+
+/// The function node of a declaration, method, or the value of a pair or
+/// single declarator
+fn function_node(node: Node) -> Option<Node> {
+    let is_function = |n: &Node| matches!(n.kind(), "arrow_function" | "function_expression" | "function");
+    match node.kind() {
+        "function_declaration" | "method_definition" => Some(node),
+        "pair" => node.child_by_field_name("value").filter(is_function),
+        "lexical_declaration" | "variable_declaration" => {
+            let mut cursor = node.walk();
+            let declarators: Vec<Node> = node
+                .named_children(&mut cursor)
+                .filter(|n| n.kind() == "variable_declarator")
+                .collect();
+            match declarators.as_slice() {
+                [declarator] if declarator.child_by_field_name("name")?.kind() == "identifier" => {
+                    declarator.child_by_field_name("value").filter(is_function)
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn parts<'s>(source: &'s str, outer: Node<'s>, function: Node<'s>) -> Result<Parts<'s>, RefactorError> {
+    let text = |n: Node| &source[n.byte_range()];
+    let name_node = match outer.kind() {
+        "pair" => outer.child_by_field_name("key"),
+        "lexical_declaration" | "variable_declaration" => {
+            let mut cursor = outer.walk();
+            let declarator = outer.named_children(&mut cursor).find(|n| n.kind() == "variable_declarator");
+            declarator.and_then(|d| d.child_by_field_name("name"))
+        }
+        _ => function.child_by_field_name("name"),
+    };
+    let name = name_node
+        .map(text)
+        .ok_or_else(|| RefactorError::Unsupported("Anonymous functions cannot be converted".to_string()))?;
+
+    let mut is_async = false;
+    let mut cursor = function.walk();
+    for child in function.children(&mut cursor) {
+        match child.kind() {
+            "async" => is_async = true,
+            "*" => return Err(RefactorError::Unsupported("Generators cannot be arrow functions".to_string())),
+            "get" | "set" if function.kind() == "method_definition" => {
+                return Err(RefactorError::Unsupported("Accessors cannot be converted".to_string()))
+            }
+            _ => {}
+        }
+    }
+
+    let parameters = match (function.child_by_field_name("parameters"), function.child_by_field_name("parameter")) {
+        (Some(parameters), _) => text(parameters).to_string(),
+        (None, Some(parameter)) => format!("({})", text(parameter)),
+        (None, None) => "()".to_string(),
+    };
+    let body = function
+        .child_by_field_name("body")
+        .ok_or_else(|| RefactorError::Unsupported("The function has no body".to_string()))?;
+
+    Ok(Parts {
+        name,
+        is_async,
+        type_parameters: function.child_by_field_name("type_parameters").map(text).unwrap_or(""),
+        parameters,
+        return_type: function.child_by_field_name("return_type").map(text).unwrap_or(""),
+        body,
+    })
+}
+
+/// The function in `target` form
+fn write(source: &str, parts: &Parts, target: FunctionForm, in_object: bool, outer: Node, indent: &str) -> String {
+    let async_prefix = if parts.is_async { "async " } else { "" };
+    let signature = format!("{}{}{}", parts.type_parameters, parts.parameters, parts.return_type);
+    let body = &source[parts.body.byte_range()];
+
+    if target == FunctionForm::Arrow {
+        let arrow = format!("{}{} => {}", async_prefix, signature, body);
+        return if in_object {
+            format!("{}: {}", parts.name, arrow)
+        } else {
+            let keyword = match outer.kind() {
+                "lexical_declaration" | "variable_declaration" => outer.child(0).map(|k| &source[k.byte_range()]),
+                _ => None,
+            };
+            format!("{} {} = {};", keyword.unwrap_or("const"), parts.name, arrow)
+        };
+    }
+
+    let block = if parts.body.kind() == "statement_block" {
+        body.to_string()
+    } else {
+        let unit = if indent.contains('\t') { "\t" } else { "  " };
+        format!("{{\n{}{}return {};\n{}}}", indent, unit, body, indent)
+    };
+    match target {
+        FunctionForm::Method => format!("{}{}{} {}", async_prefix, parts.name, signature, block),
+        _ => format!("{}function {}{} {}", async_prefix, parts.name, signature, block),
+    }
+}
+
+/// The first `this` or `arguments` in `body` that belongs to the function
+/// itself, not to a nested non-arrow function
+fn binding_use(body: Node, source: &str) -> Option<&'static str> {
+    let mut stack = vec![body];
+    while let Some(node) = stack.pop() {
+        match node.kind() {
+            "this" => return Some("this"),
+            "identifier" if &source[node.byte_range()] == "arguments" => return Some("arguments"),
+            "function_declaration" | "function_expression" | "function" | "method_definition" | "class_body"
+            | "generator_function" | "generator_function_declaration"
+                if node != body =>
+            {
+                continue
+            }
+            _ => {}
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.children(&mut cursor));
+    }
+    None
+}
+
+/// Whether `name` is called before `declaration` outside any function,
+/// i.e. while the code around the declaration first runs
+fn used_before(declaration: Node, name: &str, source: &str) -> bool {
+    let scope = match declaration.parent() {
+        Some(scope) => scope,
+        None => return false,
+    };
+    let mut stack = vec![scope];
+    while let Some(node) = stack.pop() {
+        if node.start_byte() >= declaration.start_byte() {
+            continue;
+        }
+        if node != scope
+            && matches!(
+                node.kind(),
+                "function_declaration" | "function_expression" | "function" | "arrow_function" | "method_definition" | "class_body"
+            )
+        {
+            continue;
+        }
+        if node.kind() == "identifier" && &source[node.byte_range()] == name {
+            return true;
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.children(&mut cursor));
+    }
+    false
+}
+
+fn line_indent(source: &str, offset: usize) -> &str {
+    let start = source[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line = &source[start..];
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apply_edits;
+
+    fn converted(source: &str, line: u32, column: u32, language: LanguageId, target: FunctionForm) -> Result<String, RefactorError> {
+        let ctx = RefactorContext::new(source, "test", Range::from_coords(line, column, line, column), language);
+        Ok(apply_edits(source, &convert(&ctx, target)?.edits))
+    }
+
+    #[test]
+    fn test_declaration_to_arrow_and_back() {
+        let source = "export async function total(items: Item[]): Promise<number> {\n  return sum(items);\n}\n";
+        let arrow = converted(source, 0, 25, LanguageId::TypeScript, FunctionForm::Arrow).unwrap();
+        assert_eq!(
+            arrow,
+            "export const total = async (items: Item[]): Promise<number> => {\n  return sum(items);\n};\n"
+        );
+        let back = converted(&arrow, 0, 15, LanguageId::TypeScript, FunctionForm::Declaration).unwrap();
+        assert_eq!(back, source);
+
+        // Expression bodies get a block
+        let source = "function f() {\n  const double = x => x * 2;\n}\n";
+        assert_eq!(
+            converted(source, 1, 10, LanguageId::JavaScript, FunctionForm::Declaration).unwrap(),
+            "function f() {\n  function double(x) {\n    return x * 2;\n  }\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_method_shorthand() {
+        let source = "const api = {\n  load(id) {\n    return fetch(id);\n  },\n  save: (item) => store(item),\n};\n";
+        assert_eq!(
+            converted(source, 1, 3, LanguageId::JavaScript, FunctionForm::Arrow).unwrap(),
+            "const api = {\n  load: (id) => {\n    return fetch(id);\n  },\n  save: (item) => store(item),\n};\n"
+        );
+        assert_eq!(
+            converted(source, 4, 3, LanguageId::JavaScript, FunctionForm::Method).unwrap(),
+            "const api = {\n  load(id) {\n    return fetch(id);\n  },\n  save(item) {\n    return store(item);\n  },\n};\n"
+        );
+        assert!(matches!(
+            converted(source, 1, 3, LanguageId::JavaScript, FunctionForm::Declaration),
+            Err(RefactorError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_binding_caveats() {
+        let source = "function log() {\n  console.log(arguments);\n}\n";
+        assert!(matches!(
+            converted(source, 0, 10, LanguageId::JavaScript, FunctionForm::Arrow),
+            Err(RefactorError::Unsupported(_))
+        ));
+
+        // `this` inside a nested function is that function's own
+        let source = "function run() {\n  return [1].map(function () { return this; });\n}\n";
+        assert!(converted(source, 0, 10, LanguageId::JavaScript, FunctionForm::Arrow).is_ok());
+
+        let source = "const bound = () => this.value;\n";
+        assert!(matches!(
+            converted(source, 0, 7, LanguageId::JavaScript, FunctionForm::Declaration),
+            Err(RefactorError::Unsupported(_))
+        ));
+
+        // Hoisting: the call runs before a `const` would be initialized
+        let source = "start();\nfunction start() {}\nfunction later() { start(); }\n";
+        assert!(matches!(
+            converted(source, 1, 10, LanguageId::JavaScript, FunctionForm::Arrow),
+            Err(RefactorError::Unsupported(_))
+        ));
+        assert!(converted(source, 2, 10, LanguageId::JavaScript, FunctionForm::Arrow).is_ok());
+    }
+}
@@ -0,0 +1,484 @@
+//! Convert String Concatenation
+//!
+//! Rewrite a `+` concatenation of string literals and expressions as a
+//! JavaScript/TypeScript template literal (`"Hello " + name` becomes
+//! `` `Hello ${name}` ``) or a Python f-string (`"x" + str(y)` becomes
+//! `f"x{y}"`). The concatenation is the selection, or the outermost one
+//! around the cursor; it is only offered when an operand is a string literal.
+//!
+//! Operands are split at the top-level `+` of the text, skipping brackets and
+//! the string literals and comments the parser reports. A selection mixing
+//! `+` with operators of lower precedence (`-`, comparisons, `? :`, ...) is
+//! not a concatenation of its operands and is left alone, as is a JavaScript
+//! one whose first two operands are not strings: they are added as numbers.
+
+use crate::analysis::{has_balanced_delimiters, literal_spans, unbalanced_selection};
+use crate::{offset_to_position, RefactorContext, RefactorError, RefactorResult, TextEdit};
+use logos_core::Range;
+use logos_parser::{LanguageId, LanguageParser};
+use regex::Regex;
+use tree_sitter::Node;
+
+/// An operand of the concatenation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operand<'a> {
+    /// A plain string literal: its quote and the text between the quotes
+    Literal(char, &'a str),
+    /// A template literal, without the backticks
+    Template(&'a str),
+    /// Anything else
+    Expression(&'a str),
+}
+
+/// Whether the selection or cursor is on a concatenation that can be
+/// converted
+pub fn can_convert(ctx: &RefactorContext) -> Result<bool, RefactorError> {
+    Ok(plan(ctx)?.is_some())
+}
+
+/// Replace the concatenation with a template literal or f-string
+pub fn convert(ctx: &RefactorContext) -> Result<RefactorResult, RefactorError> {
+    let (range, text) = plan(ctx)?.ok_or_else(|| {
+        RefactorError::InvalidSelection("Not a concatenation with a string literal".to_string())
+    })?;
+    let description = match ctx.language {
+        LanguageId::Python => "Convert to f-string",
+        _ => "Convert to template literal",
+    };
+    Ok(RefactorResult::new(vec![TextEdit::replace(range, text)], description.to_string()))
+}
+
+/// The range of the concatenation and its replacement; `None` if there is
+/// no concatenation with a string literal to convert
+fn plan(ctx: &RefactorContext) -> Result<Option<(Range, String)>, RefactorError> {
+    if !matches!(ctx.language, LanguageId::Python | LanguageId::JavaScript | LanguageId::TypeScript) {
+        return Ok(None);
+    }
+    let (start, end) = match concatenation_bytes(ctx)? {
+        Some(bytes) => bytes,
+        None => return Ok(None),
+    };
+    let text = &ctx.source[start..end];
+    if let Some(error) = unbalanced_selection(ctx, start, text) {
+        return Err(error);
+    }
+
+    // String literals and comments, relative to the text
+    let spans: Vec<(usize, usize)> = literal_spans(ctx.source, start, end, ctx.language)
+        .into_iter()
+        .map(|(s, e)| (s.max(start) - start, e.min(end) - start))
+        .collect();
+    let operands = match split_operands(text, &spans, ctx.language) {
+        Some(operands) => operands,
+        None => return Ok(None),
+    };
+    let operands = operands
+        .into_iter()
+        .map(|(offset, operand)| classify(operand, offset, &spans, ctx.language))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let first_string = match operands.iter().position(|o| !matches!(o, Operand::Expression(_))) {
+        Some(index) => index,
+        None => return Ok(None),
+    };
+    let replacement = match ctx.language {
+        LanguageId::Python => f_string(&operands)?,
+        _ => {
+            if first_string > 1 {
+                return Err(RefactorError::Unsupported(
+                    "The operands before the first string are added as numbers".to_string(),
+                ));
+            }
+            template_literal(&operands)
+        }
+    };
+    let range = Range::new(offset_to_position(ctx.source, start), offset_to_position(ctx.source, end));
+    Ok(Some((range, replacement)))
+}
+
+/// Byte range of the selected expression, or of the outermost `+`
+/// expression around an empty selection
+fn concatenation_bytes(ctx: &RefactorContext) -> Result<Option<(usize, usize)>, RefactorError> {
+    let selected = ctx.selected_text();
+    if !selected.trim().is_empty() {
+        let text = selected.trim_end().trim_end_matches(';').trim_end();
+        let start = ctx.selection_bytes.start + (text.len() - text.trim_start().len());
+        return Ok(Some((start, ctx.selection_bytes.start + text.len())));
+    }
+
+    let mut parser = LanguageParser::new();
+    parser
+        .set_language(ctx.language)
+        .map_err(|e| RefactorError::ParseError(e.to_string()))?;
+    let tree = parser
+        .parse(ctx.source, None)
+        .map_err(|e| RefactorError::ParseError(e.to_string()))?;
+    let offset = ctx.selection_bytes.start;
+    let mut node = tree.root_node().descendant_for_byte_range(offset, offset);
+    while let Some(n) = node {
+        if is_addition(n, ctx.source) {
+            break;
+        }
+        node = n.parent();
+    }
+    let mut outermost = match node {
+        Some(node) => node,
+        None => return Ok(None),
+    };
+    while let Some(parent) = outermost.parent().filter(|p| is_addition(*p, ctx.source)) {
+        outermost = parent;
+    }
+    Ok(Some((outermost.start_byte(), outermost.end_byte())))
+}
+
+/// Whether `node` is a binary `+`
+fn is_addition(node: Node, source: &str) -> bool {
+    matches!(node.kind(), "binary_expression" | "binary_operator")
+        && node
+            .child_by_field_name("operator")
+            .is_some_and(|op| &source[op.start_byte()..op.end_byte()] == "+")
+}
+
+/// The trimmed operands of the top-level `+` in `text`, with their byte
+/// offsets; `None` if there is no such `+`, or other operators would bind
+/// more loosely than it
+fn split_operands<'a>(
+    text: &'a str,
+    spans: &[(usize, usize)],
+    language: LanguageId,
+) -> Option<Vec<(usize, &'a str)>> {
+    let bytes = text.as_bytes();
+    let exponent = Regex::new(r"(?:^|[^\w.])\d[\d.]*[eE]$").unwrap();
+    let mut cuts = Vec::new();
+    let mut depth = 0i32;
+    let mut i = 0;
+    // Whether an operand has started since the last cut, making `+` binary
+    let mut has_operand = false;
+    while i < bytes.len() {
+        if let Some((_, e)) = spans.iter().find(|(s, _)| *s == i) {
+            i = *e;
+            has_operand = true;
+            continue;
+        }
+        let c = bytes[i];
+        match c {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth -= 1,
+            _ if depth > 0 || c.is_ascii_whitespace() => {}
+            b'+' if has_operand => {
+                let next = bytes.get(i + 1).copied();
+                if matches!(next, Some(b'+' | b'=')) || bytes[i - 1] == b'+' {
+                    return None;
+                }
+                if !exponent.is_match(&text[..i]) {
+                    cuts.push(i);
+                    has_operand = false;
+                    i += 1;
+                    continue;
+                }
+            }
+            // `?.` is optional chaining, not a conditional
+            b'?' if bytes.get(i + 1) == Some(&b'.') && !bytes.get(i + 2).is_some_and(u8::is_ascii_digit) => {}
+            b'-' | b'<' | b'>' | b'=' | b'!' | b'&' | b'|' | b'?' | b':' | b',' | b';' if has_operand => {
+                // `!` and `-` open an operand; once it started they are binary
+                return None;
+            }
+            _ => {}
+        }
+        if depth == 0 && !c.is_ascii_whitespace() {
+            has_operand = true;
+        }
+        i += 1;
+    }
+    if cuts.is_empty() {
+        return None;
+    }
+
+    let words = match language {
+        LanguageId::Python => Regex::new(r"\b(?:and|or|not|in|is|if|else|lambda)\b").unwrap(),
+        _ => Regex::new(r"\b(?:in|instanceof)\b").unwrap(),
+    };
+    let mut operands = Vec::new();
+    let starts = std::iter::once(0).chain(cuts.iter().map(|cut| cut + 1));
+    let ends = cuts.iter().copied().chain(std::iter::once(text.len()));
+    for (start, end) in starts.zip(ends) {
+        let raw = &text[start..end];
+        let operand = raw.trim();
+        let offset = start + (raw.len() - raw.trim_start().len());
+        if operand.is_empty() || words.is_match(&top_level(operand, offset, spans)) {
+            return None;
+        }
+        operands.push((offset, operand));
+    }
+    Some(operands)
+}
+
+/// `operand` with its strings and bracketed parts blanked out
+fn top_level(operand: &str, offset: usize, spans: &[(usize, usize)]) -> String {
+    let mut depth = 0;
+    operand
+        .char_indices()
+        .map(|(i, c)| {
+            let at = offset + i;
+            if spans.iter().any(|(s, e)| *s <= at && at < *e) {
+                return ' ';
+            }
+            match c {
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth -= 1,
+                _ if depth == 0 => return c,
+                _ => {}
+            }
+            ' '
+        })
+        .collect()
+}
+
+/// What kind of operand `operand`, at `offset` of the text, is
+fn classify<'a>(
+    operand: &'a str,
+    offset: usize,
+    spans: &[(usize, usize)],
+    language: LanguageId,
+) -> Result<Operand<'a>, RefactorError> {
+    let end = offset + operand.len();
+    let is_literal = spans.iter().any(|(s, e)| *s == offset && *e == end);
+    if !is_literal {
+        if spans.iter().any(|(s, e)| *s >= offset && *e <= end && matches!(operand.as_bytes()[s - offset], b'/' | b'#')) {
+            return Err(RefactorError::Unsupported("Comments inside the concatenation".to_string()));
+        }
+        return Ok(Operand::Expression(operand));
+    }
+
+    let quote_at = operand.find(['\'', '"', '`']).unwrap_or(0);
+    let prefix = &operand[..quote_at];
+    let quote = operand[quote_at..].chars().next().unwrap_or('"');
+    let body = &operand[quote_at + 1..operand.len() - 1];
+    // Python's implicit concatenation of adjacent literals is one span
+    if operand.len() < quote_at + 2 || !operand.ends_with(quote) || has_unescaped(body, quote) {
+        return Err(RefactorError::Unsupported("Adjacent string literals".to_string()));
+    }
+    match language {
+        LanguageId::Python => {
+            if operand[quote_at..].starts_with(&quote.to_string().repeat(3)) {
+                return Err(RefactorError::Unsupported("Triple-quoted strings".to_string()));
+            }
+            if !prefix.is_empty() && !prefix.eq_ignore_ascii_case("u") {
+                return Err(RefactorError::Unsupported(format!("Strings with the '{}' prefix", prefix)));
+            }
+            Ok(Operand::Literal(quote, body))
+        }
+        _ if quote == '`' => Ok(Operand::Template(body)),
+        _ => Ok(Operand::Literal(quote, body)),
+    }
+}
+
+/// Whether `quote` appears in `body` without a backslash before it
+fn has_unescaped(body: &str, quote: char) -> bool {
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            c if c == quote => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// The operands as one template literal
+fn template_literal(operands: &[Operand]) -> String {
+    let mut body = String::new();
+    for operand in operands {
+        match operand {
+            Operand::Literal(_, text) => {
+                let mut chars = text.chars().peekable();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => match chars.next() {
+                            // Quotes need no escape between backticks
+                            Some(q @ ('\'' | '"')) => body.push(q),
+                            Some(escaped) => {
+                                body.push('\\');
+                                body.push(escaped);
+                            }
+                            None => body.push('\\'),
+                        },
+                        '`' => body.push_str("\\`"),
+                        '{' => push_raw(&mut body, "{"),
+                        c => body.push(c),
+                    }
+                }
+            }
+            Operand::Template(text) => push_raw(&mut body, text),
+            Operand::Expression(expression) => {
+                body.push_str("${");
+                body.push_str(expression);
+                body.push('}');
+            }
+        }
+    }
+    format!("`{}`", body)
+}
+
+/// Append template text to `body`, escaping a `$` it would turn into `${`
+fn push_raw(body: &mut String, text: &str) {
+    if text.starts_with('{') && body.ends_with('$') {
+        let backslashes = body[..body.len() - 1].chars().rev().take_while(|c| *c == '\\').count();
+        if backslashes % 2 == 0 {
+            body.insert(body.len() - 1, '\\');
+        }
+    }
+    body.push_str(text);
+}
+
+/// The operands as one f-string
+fn f_string(operands: &[Operand]) -> Result<String, RefactorError> {
+    let str_call = Regex::new(r"^str\((.*)\)$").unwrap();
+    let expressions: Vec<&str> = operands
+        .iter()
+        .filter_map(|operand| match operand {
+            Operand::Expression(expression) => Some(
+                str_call
+                    .captures(expression)
+                    .map(|c| c.get(1).unwrap().as_str().trim())
+                    .filter(|inner| !inner.is_empty() && !inner.contains(',') && has_balanced_delimiters(inner))
+                    .unwrap_or(expression),
+            ),
+            _ => None,
+        })
+        .collect();
+    // Before Python 3.12 the expressions may not contain backslashes,
+    // comments or the quote of the f-string
+    if expressions.iter().any(|e| e.contains(['\\', '#'])) {
+        return Err(RefactorError::Unsupported(
+            "An expression contains a backslash or '#', which f-strings do not allow".to_string(),
+        ));
+    }
+    let preferred = operands
+        .iter()
+        .find_map(|operand| match operand {
+            Operand::Literal(quote, _) => Some(*quote),
+            _ => None,
+        })
+        .unwrap_or('"');
+    let other = if preferred == '"' { '\'' } else { '"' };
+    let quote = [preferred, other]
+        .into_iter()
+        .find(|q| !expressions.iter().any(|e| e.contains(*q)))
+        .ok_or_else(|| RefactorError::Unsupported("The expressions use both kinds of quote".to_string()))?;
+
+    let mut body = String::new();
+    let mut expressions = expressions.into_iter();
+    for operand in operands {
+        match operand {
+            Operand::Literal(_, text) => {
+                let mut chars = text.chars();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => match chars.next() {
+                            Some(q @ ('\'' | '"')) if q != quote => body.push(q),
+                            Some(escaped) => {
+                                body.push('\\');
+                                body.push(escaped);
+                            }
+                            None => body.push('\\'),
+                        },
+                        '{' => body.push_str("{{"),
+                        '}' => body.push_str("}}"),
+                        c if c == quote => {
+                            body.push('\\');
+                            body.push(c);
+                        }
+                        c => body.push(c),
+                    }
+                }
+            }
+            Operand::Template(_) => {}
+            Operand::Expression(_) => {
+                let expression = expressions.next().unwrap_or_default();
+                // `{{` would be a literal brace
+                let pad = if expression.starts_with('{') { " " } else { "" };
+                body.push_str(&format!("{{{}{}{}}}", pad, expression, pad));
+            }
+        }
+    }
+    Ok(format!("f{}{}{}", quote, body, quote))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apply_edits;
+
+    fn converted(source: &str, selection: &str, language: LanguageId) -> Result<String, RefactorError> {
+        let start = source.find(selection).unwrap();
+        let range = Range::new(offset_to_position(source, start), offset_to_position(source, start + selection.len()));
+        let ctx = RefactorContext::new(source, "test", range, language);
+        Ok(apply_edits(source, &convert(&ctx)?.edits))
+    }
+
+    #[test]
+    fn test_concatenation_to_template_literal() {
+        let source = "const s = \"Hello \" + name + \"!\";\n";
+        assert_eq!(
+            converted(source, "\"Hello \" + name + \"!\"", LanguageId::JavaScript).unwrap(),
+            "const s = `Hello ${name}!`;\n"
+        );
+
+        // Nested quotes lose their escapes; backticks and `${` gain one
+        let source = r#"log('He said "hi" to ' + user.name + '\'s `dog` ${x}' + `, ${n} times`);"#;
+        assert_eq!(
+            converted(source, &source[4..source.len() - 2], LanguageId::TypeScript).unwrap(),
+            r#"log(`He said "hi" to ${user.name}'s \`dog\` \${x}, ${n} times`);"#
+        );
+
+        // At the cursor, the outermost concatenation
+        let source = "const s = \"a\" + (b - 1) * 2 + f(\"c\", d);\n";
+        let ctx = RefactorContext::new(source, "test", Range::from_coords(0, 16, 0, 16), LanguageId::JavaScript);
+        assert!(can_convert(&ctx).unwrap());
+        assert_eq!(
+            apply_edits(source, &convert(&ctx).unwrap().edits),
+            "const s = `a${(b - 1) * 2}${f(\"c\", d)}`;\n"
+        );
+    }
+
+    #[test]
+    fn test_concatenation_to_f_string() {
+        let source = "label = \"x\" + str(y)\n";
+        assert_eq!(converted(source, "\"x\" + str(y)", LanguageId::Python).unwrap(), "label = f\"x{y}\"\n");
+
+        // The quote changes when an expression uses it
+        let source = "msg = \"Hi {name}, \" + data[\"user\"] + \"'s \\\"turn\\\"\"\n";
+        assert_eq!(
+            converted(source, source[6..].trim_end(), LanguageId::Python).unwrap(),
+            "msg = f'Hi {{name}}, {data[\"user\"]}\\'s \"turn\"'\n"
+        );
+        assert!(matches!(
+            converted("s = \"a\" + d[\"k\"] + e['k']\n", "\"a\" + d[\"k\"] + e['k']", LanguageId::Python),
+            Err(RefactorError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_not_offered() {
+        let offered = |source: &str, selection: &str, language: LanguageId| {
+            let start = source.find(selection).unwrap();
+            let range = Range::new(offset_to_position(source, start), offset_to_position(source, start + selection.len()));
+            can_convert(&RefactorContext::new(source, "test", range, language))
+        };
+        // No string literal, or not only a concatenation
+        assert!(!offered("x = a + b\n", "a + b", LanguageId::Python).unwrap());
+        assert!(!offered("x = \"a\" + b - c;\n", "\"a\" + b - c", LanguageId::JavaScript).unwrap());
+        assert!(!offered("x = ok ? \"a\" + b : c;\n", "ok ? \"a\" + b : c", LanguageId::JavaScript).unwrap());
+        assert!(!offered("x = \"a\" + b if ok else c\n", "\"a\" + b if ok else c", LanguageId::Python).unwrap());
+        // `1 + 2 + "x"` is "3x"
+        assert!(matches!(
+            offered("x = 1 + 2 + \"x\";\n", "1 + 2 + \"x\"", LanguageId::JavaScript),
+            Err(RefactorError::Unsupported(_))
+        ));
+        assert!(offered("x = n + \"x\" + a?.b;\n", "n + \"x\" + a?.b", LanguageId::JavaScript).unwrap());
+    }
+}
@@ -0,0 +1,164 @@
+//! Unified diff rendering
+//!
+//! Line-based diff (longest common subsequence) between two texts, rendered
+//! in the unified format understood by `patch` and code review tools.
+
+/// Lines of context around each change
+pub const DEFAULT_CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Render a unified diff of `original` -> `modified` with `context` lines of
+/// context. Returns an empty string when the texts are equal.
+pub fn unified_diff(original: &str, modified: &str, context: usize) -> String {
+    let old: Vec<&str> = original.split_inclusive('\n').collect();
+    let new: Vec<&str> = modified.split_inclusive('\n').collect();
+    let ops = diff_lines(&old, &new);
+
+    if ops.iter().all(|(op, _, _)| *op == Op::Equal) {
+        return String::new();
+    }
+
+    let mut out = String::from("--- original\n+++ modified\n");
+    for hunk in hunks(&ops, context) {
+        let slice = &ops[hunk.clone()];
+        let (old_start, new_start) = (slice[0].1, slice[0].2);
+        let old_len = slice.iter().filter(|(op, _, _)| *op != Op::Insert).count();
+        let new_len = slice.iter().filter(|(op, _, _)| *op != Op::Delete).count();
+
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_len),
+            hunk_range(new_start, new_len)
+        ));
+        for &(op, i, j) in slice {
+            let (prefix, line) = match op {
+                Op::Equal => (' ', old[i]),
+                Op::Delete => ('-', old[i]),
+                Op::Insert => ('+', new[j]),
+            };
+            out.push(prefix);
+            out.push_str(line);
+            if !line.ends_with('\n') {
+                out.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+    out
+}
+
+/// `start,len` with 1-based start; an empty range names the line before it
+fn hunk_range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, len),
+    }
+}
+
+/// Edit script as `(op, old_index, new_index)`; indices point at the next
+/// line of each side for ops that don't consume it
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<(Op, usize, usize)> {
+    // Common prefix and suffix don't need the quadratic table
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+
+    // lcs[i][j] = LCS length of a[i..] and b[j..]
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops: Vec<(Op, usize, usize)> = (0..prefix).map(|i| (Op::Equal, i, i)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            ops.push((Op::Equal, prefix + i, prefix + j));
+            i += 1;
+            j += 1;
+        } else if j < b.len() && (i == a.len() || lcs[i][j + 1] > lcs[i + 1][j]) {
+            ops.push((Op::Insert, prefix + i, prefix + j));
+            j += 1;
+        } else {
+            ops.push((Op::Delete, prefix + i, prefix + j));
+            i += 1;
+        }
+    }
+    ops.extend((0..suffix).map(|k| (Op::Equal, prefix + a.len() + k, prefix + b.len() + k)));
+    ops
+}
+
+/// Group changes into op index ranges, each padded with `context` equal lines
+fn hunks(ops: &[(Op, usize, usize)], context: usize) -> Vec<std::ops::Range<usize>> {
+    let mut hunks: Vec<std::ops::Range<usize>> = Vec::new();
+
+    for (k, (op, _, _)) in ops.iter().enumerate() {
+        if *op == Op::Equal {
+            continue;
+        }
+        let start = k.saturating_sub(context);
+        let end = (k + 1 + context).min(ops.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.end => last.end = end,
+            _ => hunks.push(start..end),
+        }
+    }
+
+    hunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_texts() {
+        assert_eq!(unified_diff("a\nb\n", "a\nb\n", DEFAULT_CONTEXT), "");
+    }
+
+    #[test]
+    fn test_separate_hunks() {
+        let old: String = (1..=12).map(|i| format!("{}\n", i)).collect();
+        let new = old.replacen("2\n", "two\n", 1).replace("11\n", "eleven\n");
+
+        assert_eq!(
+            unified_diff(&old, &new, 1),
+            "--- original\n+++ modified\n\
+             @@ -1,3 +1,3 @@\n 1\n-2\n+two\n 3\n\
+             @@ -10,3 +10,3 @@\n 10\n-11\n+eleven\n 12\n"
+        );
+    }
+
+    #[test]
+    fn test_missing_final_newline() {
+        assert_eq!(
+            unified_diff("a\nb", "a\nc", DEFAULT_CONTEXT),
+            "--- original\n+++ modified\n@@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n+c\n\\ No newline at end of file\n"
+        );
+    }
+
+    #[test]
+    fn test_pure_insertion_header() {
+        assert_eq!(
+            unified_diff("a\n", "a\nb\n", 0),
+            "--- original\n+++ modified\n@@ -1,0 +2 @@\n+b\n"
+        );
+    }
+}
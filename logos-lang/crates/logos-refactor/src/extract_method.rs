@@ -7,8 +7,9 @@
 //! 3. Checking for control flow issues (return, break, continue)
 //! 4. Generating the new method with appropriate parameters and return type
 
-use crate::analysis::{find_variable_references, has_balanced_delimiters};
-use crate::{RefactorContext, RefactorError, RefactorResult, TextEdit};
+use crate::analysis::{enclosing_statement, find_variable_references, unbalanced_selection};
+use crate::style::CodeStyle;
+use crate::{column_to_byte, offset_to_position, position_to_offset, RefactorContext, RefactorError, RefactorResult, TextEdit};
 use logos_core::{Position, Range};
 use logos_parser::LanguageId;
 use regex::Regex;
@@ -38,6 +39,8 @@ pub struct ExtractMethodAnalysis {
     pub has_continue: bool,
     /// Issues that prevent extraction
     pub issues: Vec<String>,
+    /// Ranges of the statements causing the issues
+    pub issue_ranges: Vec<Range>,
 }
 
 impl ExtractMethodAnalysis {
@@ -52,14 +55,13 @@ pub fn can_extract(ctx: &RefactorContext) -> Result<bool, RefactorError> {
 
     // Empty selection
     if selected.is_empty() {
-        return Err(RefactorError::NoExpression);
+        return Err(RefactorError::NoExpression("the selection is empty or only whitespace".to_string()));
     }
 
     // Check for balanced delimiters
-    if !has_balanced_delimiters(selected) {
-        return Err(RefactorError::CannotExtract(
-            "Selection has unbalanced brackets or quotes".to_string(),
-        ));
+    let leading = ctx.selected_text().len() - ctx.selected_text().trim_start().len();
+    if let Some(error) = unbalanced_selection(ctx, ctx.selection_bytes.start + leading, selected) {
+        return Err(error);
     }
 
     // Analyze the selection
@@ -68,6 +70,7 @@ pub fn can_extract(ctx: &RefactorContext) -> Result<bool, RefactorError> {
     if !analysis.can_extract() {
         return Err(RefactorError::ControlFlowIssue(
             analysis.issues.join(", "),
+            analysis.issue_ranges,
         ));
     }
 
@@ -84,18 +87,34 @@ pub fn analyze(ctx: &RefactorContext) -> Result<ExtractMethodAnalysis, RefactorE
         has_break: false,
         has_continue: false,
         issues: Vec::new(),
+        issue_ranges: Vec::new(),
     };
 
     // Check for control flow statements
-    analysis.has_return = Regex::new(r"\breturn\b").unwrap().is_match(selected);
-    analysis.has_break = Regex::new(r"\bbreak\b").unwrap().is_match(selected);
-    analysis.has_continue = Regex::new(r"\bcontinue\b").unwrap().is_match(selected);
+    let returns = keyword_ranges(ctx, "return");
+    let breaks = keyword_ranges(ctx, "break");
+    let continues = keyword_ranges(ctx, "continue");
+    analysis.has_return = !returns.is_empty();
+    analysis.has_break = !breaks.is_empty();
+    analysis.has_continue = !continues.is_empty();
 
     if analysis.has_break {
         analysis.issues.push("Selection contains 'break' statement".to_string());
+        analysis.issue_ranges.extend(breaks);
     }
     if analysis.has_continue {
         analysis.issues.push("Selection contains 'continue' statement".to_string());
+        analysis.issue_ranges.extend(continues);
+    }
+
+    // A return is only kept by the call (`return extracted()`) when nothing follows it
+    let early_returns: Vec<Range> = returns
+        .into_iter()
+        .filter(|range| !ends_selection(ctx, *range))
+        .collect();
+    if !early_returns.is_empty() {
+        analysis.issues.push("Selection contains a 'return' before its end".to_string());
+        analysis.issue_ranges.extend(early_returns);
     }
 
     // Find variables used in selection
@@ -127,6 +146,31 @@ pub fn analyze(ctx: &RefactorContext) -> Result<ExtractMethodAnalysis, RefactorE
     Ok(analysis)
 }
 
+/// Ranges of the keyword `word` in the selection
+fn keyword_ranges(ctx: &RefactorContext, word: &str) -> Vec<Range> {
+    let re = Regex::new(&format!(r"\b{}\b", word)).unwrap();
+    let base = ctx.selection_bytes.start;
+    re.find_iter(ctx.selected_text())
+        .map(|m| {
+            Range::new(
+                offset_to_position(ctx.source, base + m.start()),
+                offset_to_position(ctx.source, base + m.end()),
+            )
+        })
+        .collect()
+}
+
+/// Whether only closing braces and whitespace follow the statement at `range`
+fn ends_selection(ctx: &RefactorContext, range: Range) -> bool {
+    let start = position_to_offset(ctx.source, range.end);
+    let rest = &ctx.source[start.min(ctx.selection_bytes.end)..ctx.selection_bytes.end];
+    let rest = match rest.find(['\n', ';']) {
+        Some(i) => &rest[i + 1..],
+        None => "",
+    };
+    rest.chars().all(|c| c.is_whitespace() || c == '}' || c == ';')
+}
+
 fn get_text_before(source: &str, selection: Range) -> String {
     let lines: Vec<&str> = source.lines().collect();
     let mut result = String::new();
@@ -136,7 +180,7 @@ fn get_text_before(source: &str, selection: Range) -> String {
             result.push_str(line);
             result.push('\n');
         } else if i == selection.start.line as usize {
-            let end = (selection.start.column as usize).min(line.len());
+            let end = column_to_byte(line, selection.start.column);
             result.push_str(&line[..end]);
             break;
         }
@@ -154,7 +198,7 @@ fn get_text_after(source: &str, selection: Range) -> String {
             result.push_str(line);
             result.push('\n');
         } else if i == selection.end.line as usize {
-            let start = (selection.end.column as usize).min(line.len());
+            let start = column_to_byte(line, selection.end.column);
             result.push_str(&line[start..]);
             result.push('\n');
         }
@@ -164,14 +208,14 @@ fn get_text_after(source: &str, selection: Range) -> String {
 }
 
 /// Find variables that are modified (assigned) in the code
-fn find_modified_variables(text: &str, language: LanguageId) -> HashSet<String> {
+pub(crate) fn find_modified_variables(text: &str, language: LanguageId) -> HashSet<String> {
     let mut modified = HashSet::new();
 
-    // Pattern for assignments: identifier = something (but not ==, ===, etc.)
+    // Pattern for assignments: identifier = something, including compound
+    // (`+=`, `<<=`) and Go's `:=`, but not comparisons (`==`, `<=`) or `=>`
     let pattern = match language {
-        LanguageId::Python => r"(\w+)\s*(?<![=!<>])=(?![=])",
-        LanguageId::Go => r"(\w+)\s*:?=",
-        _ => r"(\w+)\s*(?<![=!<>])=(?![=])",
+        LanguageId::Go => r"(\w+)\s*(?::|[-+*/%&|^]|<<|>>|&\^)?=(?:[^=>]|$)",
+        _ => r"(\w+)\s*(?:[-+*/%&|^]|<<|>>|\*\*|//)?=(?:[^=>]|$)",
     };
 
     if let Ok(re) = Regex::new(pattern) {
@@ -195,13 +239,51 @@ fn find_modified_variables(text: &str, language: LanguageId) -> HashSet<String>
     modified
 }
 
-/// Extract the selection into a new method
+/// Extract the selection into a new method.
+///
+/// Fails with `CannotExtract` if a function or method named `method_name`
+/// already exists in the scope the new method would be added to.
 pub fn extract(ctx: &RefactorContext, method_name: &str) -> Result<RefactorResult, RefactorError> {
     can_extract(ctx)?;
 
+    if scope_function_names(ctx).contains(method_name) {
+        return Err(RefactorError::CannotExtract(format!(
+            "A function named '{}' already exists in this scope",
+            method_name
+        )));
+    }
+
+    extract_unchecked(ctx, method_name)
+}
+
+/// Extract with a default method name in the casing of `ctx.naming`,
+/// numbered to avoid existing functions
+pub fn extract_with_suggestion(ctx: &RefactorContext) -> Result<(String, RefactorResult), RefactorError> {
+    can_extract(ctx)?;
+
+    let base = ctx.naming.function_case(ctx.language).apply("extracted_method");
+    let name = unique_method_name(ctx, &base);
+    let result = extract_unchecked(ctx, &name)?;
+    Ok((name, result))
+}
+
+/// `base`, or `base2`, `base3`, ... if that name is already taken in scope
+pub fn unique_method_name(ctx: &RefactorContext, base: &str) -> String {
+    let taken = scope_function_names(ctx);
+    if !taken.contains(base) {
+        return base.to_string();
+    }
+    (2..)
+        .map(|n| format!("{}{}", base, n))
+        .find(|name| !taken.contains(name))
+        .unwrap()
+}
+
+fn extract_unchecked(ctx: &RefactorContext, method_name: &str) -> Result<RefactorResult, RefactorError> {
+
     let analysis = analyze(ctx)?;
     let selected = ctx.selected_text();
-    let indent = ctx.indentation_at(ctx.selection.start.line);
+    let indent = ctx.indentation_at(statement_line(ctx));
 
     // Generate the new method
     let method_code = generate_method(
@@ -223,6 +305,11 @@ pub fn extract(ctx: &RefactorContext, method_name: &str) -> Result<RefactorResul
         &indent,
     );
 
+    // Match the file's quotes and semicolons
+    let style = CodeStyle::detect(ctx.source, ctx.language);
+    let method_code = style.apply(&method_code, ctx.language);
+    let call_code = style.apply(&call_code, ctx.language);
+
     // Find insertion point for the new method (after current function or at end of file)
     let method_insert_pos = find_method_insertion_point(ctx);
 
@@ -415,56 +502,206 @@ fn indent_code(code: &str, indent: &str) -> String {
         .join("\n")
 }
 
-/// Find the insertion point for the new method
-fn find_method_insertion_point(ctx: &RefactorContext) -> Position {
+/// A class-like body (class, struct, interface, impl or trait block)
+struct ClassScope {
+    /// First and last line of the body
+    body: (usize, usize),
+    /// Nesting depth of the members: brace depth, or indentation for Python
+    member_depth: usize,
+}
+
+/// Names of the functions that share a scope with the extracted method:
+/// the methods of the enclosing class if the selection is inside one,
+/// otherwise the free functions of the file
+pub fn scope_function_names(ctx: &RefactorContext) -> HashSet<String> {
     let lines: Vec<&str> = ctx.source.lines().collect();
-    let current_line = ctx.selection.start.line as usize;
-
-    // Find the end of the current function/method
-    let mut brace_depth = 0;
-    let mut in_function = false;
-
-    for (i, line) in lines.iter().enumerate().skip(current_line) {
-        let trimmed = line.trim();
-
-        // Detect function start
-        if !in_function
-            && (trimmed.starts_with("function ")
-                || trimmed.starts_with("fn ")
-                || trimmed.starts_with("def ")
-                || trimmed.starts_with("func "))
-        {
-            in_function = true;
+    let depths = line_depths(&lines, ctx.language);
+    let classes = find_class_scopes(&lines, &depths, ctx.language);
+
+    let innermost_class = |line: usize| {
+        classes
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.body.0 <= line && line <= c.body.1)
+            .max_by_key(|(_, c)| c.body.0)
+            .map(|(i, _)| i)
+    };
+    let target = innermost_class(ctx.selection.start.line as usize);
+
+    let free_re = Regex::new(free_function_pattern(ctx.language)).unwrap();
+    let method_re = Regex::new(method_pattern(ctx.language)).unwrap();
+    let keywords = ["if", "for", "while", "switch", "catch", "return", "new", "else", "do", "sizeof"];
+
+    let mut names = HashSet::new();
+    for (i, line) in lines.iter().enumerate() {
+        let captures = match innermost_class(i) {
+            // Nested functions are local to their parent, so only top-level ones clash
+            None if target.is_none() && depths[i] == 0 => free_re.captures(line),
+            Some(class) if Some(class) == target && depths[i] == classes[class].member_depth => {
+                method_re.captures(line)
+            }
+            _ => None,
+        };
+        if let Some(name) = captures.and_then(|c| c.get(1).or_else(|| c.get(2))) {
+            if !keywords.contains(&name.as_str()) {
+                names.insert(name.as_str().to_string());
+            }
         }
+    }
 
-        // Track braces
-        for ch in line.chars() {
-            match ch {
-                '{' => brace_depth += 1,
-                '}' => {
-                    brace_depth -= 1;
-                    if brace_depth == 0 && in_function {
-                        // Found the end of the function
-                        return Position::new(i as u32 + 1, 0);
-                    }
+    names
+}
+
+fn free_function_pattern(language: LanguageId) -> &'static str {
+    match language {
+        LanguageId::Python => r"^\s*(?:async\s+)?def\s+(\w+)",
+        LanguageId::Rust => r"\bfn\s+(\w+)",
+        // Functions only: methods with a receiver live in the type's scope
+        LanguageId::Go => r"^\s*func\s+(\w+)",
+        LanguageId::C | LanguageId::Cpp | LanguageId::Java => {
+            r"^\s*(?:[\w:<>,*&]+\s+)+[*&]*(\w+)\s*\([^;]*$"
+        }
+        _ => {
+            r"\bfunction\s*\*?\s*([A-Za-z_$][\w$]*)|\b(?:const|let|var)\s+([A-Za-z_$][\w$]*)\s*=\s*(?:async\s*)?(?:function\b|\([^)]*\)\s*=>|\w+\s*=>)"
+        }
+    }
+}
+
+fn method_pattern(language: LanguageId) -> &'static str {
+    match language {
+        LanguageId::Python => r"^\s*(?:async\s+)?def\s+(\w+)",
+        LanguageId::Rust => r"\bfn\s+(\w+)",
+        _ => {
+            r"^\s*(?:(?:public|private|protected|static|async|abstract|final|override|virtual|get|set)\s+)*(?:[\w<>\[\],.?:*&]+\s+)*\*?([A-Za-z_$][\w$]*)\s*(?:<[^>]*>)?\s*\("
+        }
+    }
+}
+
+/// Nesting depth at the start of each line: brace depth for brace languages,
+/// indentation width for Python (`usize::MAX` for blank lines)
+fn line_depths(lines: &[&str], language: LanguageId) -> Vec<usize> {
+    if language == LanguageId::Python {
+        return lines
+            .iter()
+            .map(|line| {
+                if line.trim().is_empty() {
+                    usize::MAX
+                } else {
+                    line.len() - line.trim_start().len()
                 }
+            })
+            .collect();
+    }
+
+    let mut depths = Vec::with_capacity(lines.len());
+    let mut depth = 0i32;
+    for line in lines {
+        depths.push(depth.max(0) as usize);
+        depth += brace_depth_change(line, language);
+    }
+    depths
+}
+
+/// Net `{`/`}` count of a line, ignoring strings and line comments
+fn brace_depth_change(line: &str, language: LanguageId) -> i32 {
+    let mut change = 0;
+    let mut in_string: Option<char> = None;
+    let mut prev = ' ';
+
+    for ch in line.chars() {
+        if let Some(quote) = in_string {
+            if ch == quote && prev != '\\' {
+                in_string = None;
+            }
+        } else {
+            match ch {
+                // `'` starts lifetimes and labels in Rust
+                '\'' if language == LanguageId::Rust => {}
+                '"' | '\'' | '`' => in_string = Some(ch),
+                '/' if prev == '/' => break,
+                '{' => change += 1,
+                '}' => change -= 1,
                 _ => {}
             }
         }
+        prev = ch;
+    }
 
-        // For Python, look for dedent
-        if ctx.language == LanguageId::Python && in_function
-            && i > current_line && !trimmed.is_empty() {
-                let indent_level = line.len() - line.trim_start().len();
-                let base_indent = lines[current_line].len() - lines[current_line].trim_start().len();
-                if indent_level <= base_indent {
-                    return Position::new(i as u32, 0);
-                }
+    change
+}
+
+fn find_class_scopes(lines: &[&str], depths: &[usize], language: LanguageId) -> Vec<ClassScope> {
+    let header_re = Regex::new(
+        r"^\s*(?:(?:export|default|public|private|protected|abstract|final|static|pub(?:\([^)]*\))?|unsafe)\s+)*(?:class|struct|interface|impl|trait)\b",
+    )
+    .unwrap();
+    let mut classes = Vec::new();
+
+    for (h, line) in lines.iter().enumerate() {
+        if !header_re.is_match(line) || line.trim_end().ends_with(';') {
+            continue;
+        }
+        let depth = depths[h];
+
+        if language == LanguageId::Python {
+            let body: Vec<usize> = (h + 1..lines.len())
+                .take_while(|&j| depths[j] == usize::MAX || depths[j] > depth)
+                .filter(|&j| depths[j] != usize::MAX)
+                .collect();
+            if let (Some(&first), Some(&last)) = (body.first(), body.last()) {
+                classes.push(ClassScope {
+                    body: (first, last),
+                    member_depth: depths[first],
+                });
             }
+            continue;
+        }
+
+        // The body opens on the header line or a following one (Allman style)
+        let open = match (h..lines.len().min(h + 3)).find(|&j| depths[j] + brace_delta(lines[j], language) > depth) {
+            Some(open) => open,
+            None => continue,
+        };
+        let end = (open + 1..lines.len())
+            .find(|&j| depths[j] <= depth)
+            .unwrap_or(lines.len());
+        if open + 1 < end {
+            classes.push(ClassScope {
+                body: (open + 1, end - 1),
+                member_depth: depth + 1,
+            });
+        }
     }
 
-    // Default: end of file
-    Position::new(lines.len() as u32, 0)
+    classes
+}
+
+fn brace_delta(line: &str, language: LanguageId) -> usize {
+    brace_depth_change(line, language).max(0) as usize
+}
+
+/// First line of the statement the selection starts in; a selection may begin
+/// on a continuation line, which doesn't carry the statement's indentation
+fn statement_line(ctx: &RefactorContext) -> u32 {
+    enclosing_statement(ctx.source, ctx.selection.start, ctx.language)
+        .map(|statement| statement.start.line)
+        .unwrap_or(ctx.selection.start.line)
+}
+
+/// Find the insertion point for the new method: the line after the
+/// outermost function around the selection, or the end of the file
+fn find_method_insertion_point(ctx: &RefactorContext) -> Position {
+    let mut function = None;
+    let mut inner = ctx.enclosing_function();
+    while let Some(range) = inner {
+        function = Some(range);
+        inner = RefactorContext::new(ctx.source, ctx.uri, range, ctx.language).enclosing_function();
+    }
+
+    match function {
+        Some(range) => Position::new(range.end.line + 1, 0),
+        None => Position::new(ctx.source.lines().count() as u32, 0),
+    }
 }
 
 #[cfg(test)]
@@ -477,11 +714,13 @@ mod tests {
 
     #[test]
     fn test_find_modified_variables() {
-        let code = "x = 1; y += 2; z++;";
+        let code = "x = 1; y += 2; z++; if (a == b && c <= d) {}";
         let modified = find_modified_variables(code, LanguageId::JavaScript);
         assert!(modified.contains("x"));
         assert!(modified.contains("y"));
         assert!(modified.contains("z"));
+        assert!(!modified.contains("a"));
+        assert!(!modified.contains("c"));
     }
 
     #[test]
@@ -500,7 +739,35 @@ mod tests {
         let selection = Range::from_coords(0, 10, 0, 16); // "break;"
         let ctx = make_ctx(source, selection, LanguageId::JavaScript);
 
-        assert!(can_extract(&ctx).is_err());
+        let err = can_extract(&ctx).unwrap_err();
+        assert_eq!(err.ranges(), vec![Range::from_coords(0, 10, 0, 15)]);
+    }
+
+    #[test]
+    fn test_control_flow_issue_ranges() {
+        let source = "function f(x) {\n    if (x) return 1;\n    log(x);\n}\n";
+        let selection = Range::from_coords(1, 4, 2, 11);
+        let ctx = make_ctx(source, selection, LanguageId::JavaScript);
+
+        let err = can_extract(&ctx).unwrap_err();
+        assert!(matches!(&err, RefactorError::ControlFlowIssue(_, _)));
+        assert_eq!(err.ranges(), vec![Range::from_coords(1, 11, 1, 17)]);
+
+        // A trailing return is kept by returning the call's result
+        let selection = Range::from_coords(1, 4, 1, 20);
+        let ctx = make_ctx(source, selection, LanguageId::JavaScript);
+        assert!(can_extract(&ctx).is_ok());
+    }
+
+    #[test]
+    fn test_extract_follows_semicolon_free_style() {
+        let source = "function main() {\n    const x = 1\n    console.log(x)\n    console.log('done')\n}\n";
+        let selection = Range::from_coords(2, 4, 3, 23);
+        let ctx = make_ctx(source, selection, LanguageId::JavaScript);
+
+        let result = extract(&ctx, "report").unwrap();
+        assert_eq!(result.edits[0].new_text, "report(x)");
+        assert!(!result.generated_code.unwrap().contains(';'));
     }
 
     #[test]
@@ -517,4 +784,54 @@ mod tests {
         assert!(code.contains("function extracted(x)"));
         assert!(code.contains("console.log(x)"));
     }
+
+    #[test]
+    fn test_name_collision_free_function() {
+        let source = "function extractedMethod() {}\n\nfunction main() {\n    console.log(1);\n}\n";
+        let selection = Range::from_coords(3, 4, 3, 19); // "console.log(1);"
+        let ctx = make_ctx(source, selection, LanguageId::JavaScript);
+
+        assert!(matches!(
+            extract(&ctx, "extractedMethod"),
+            Err(RefactorError::CannotExtract(_))
+        ));
+        assert!(extract(&ctx, "logOne").is_ok());
+
+        let (name, _) = extract_with_suggestion(&ctx).unwrap();
+        assert_eq!(name, "extractedMethod2");
+
+        // Exported Go style
+        let naming = crate::naming::NamingConfig {
+            function: Some(crate::naming::Case::Pascal),
+            ..Default::default()
+        };
+        let (name, _) = extract_with_suggestion(&make_ctx(source, selection, LanguageId::JavaScript).with_naming(naming)).unwrap();
+        assert_eq!(name, "ExtractedMethod");
+    }
+
+    #[test]
+    fn test_name_collision_class_method() {
+        let source = "class A {\n    helper() {}\n    run() {\n        console.log(1);\n    }\n}\n\nfunction other() {}\n";
+        let selection = Range::from_coords(3, 8, 3, 23); // "console.log(1);"
+        let ctx = make_ctx(source, selection, LanguageId::JavaScript);
+
+        let names = scope_function_names(&ctx);
+        assert!(names.contains("helper"));
+        assert!(names.contains("run"));
+        // Free functions don't share the class's scope
+        assert!(!names.contains("other"));
+
+        assert!(matches!(extract(&ctx, "helper"), Err(RefactorError::CannotExtract(_))));
+        assert!(extract(&ctx, "other").is_ok());
+    }
+
+    #[test]
+    fn test_name_collision_python_method() {
+        let source = "class A:\n    def extracted_method(self):\n        pass\n\n    def run(self):\n        print(1)\n";
+        let selection = Range::from_coords(5, 8, 5, 16); // "print(1)"
+        let ctx = RefactorContext::new(source, "test.py", selection, LanguageId::Python);
+
+        assert_eq!(unique_method_name(&ctx, "extracted_method"), "extracted_method2");
+        assert_eq!(unique_method_name(&ctx, "log_one"), "log_one");
+    }
 }
@@ -0,0 +1,293 @@
+//! Extract Parameter Refactoring
+//!
+//! Promote an expression inside a function body to a new parameter of the
+//! function: the expression is replaced with the parameter, the parameter is
+//! appended to the signature, and every call in the document passes the
+//! expression as the new last argument. The calls come from the adapter
+//! analysis (`CallInfo`).
+//!
+//! The expression is evaluated at the call site instead of in the body, so
+//! it may not use the function's parameters, locals or receiver, nor have
+//! side effects. Only Python, JavaScript and TypeScript are supported: the
+//! other languages would need the parameter's type.
+
+use crate::analysis::{collect_identifiers, find_variable_references, has_side_effects, is_keyword, is_valid_expression};
+use crate::inline_method::{
+    call_arguments, callee_name, definition_name, enclosing, is_call, is_definition, node_range, parameter_names, text,
+    within,
+};
+use crate::rename::find_occurrences;
+use crate::{offset_to_position, RefactorContext, RefactorError, RefactorResult, TextEdit};
+use logos_core::{Position, Range};
+use logos_parser::{LanguageId, LanguageParser};
+use regex::Regex;
+use std::collections::HashSet;
+use tree_sitter::Node;
+
+/// A call in the document, as an adapter's `CallInfo` reports it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallSite {
+    /// Name of the called function, without receiver
+    pub callee: String,
+    /// The call expression, with byte columns
+    pub range: Range,
+}
+
+/// Extract the selected expression into the parameter `param_name` of the
+/// enclosing function, passing it at each of `calls` to that function
+pub fn extract(ctx: &RefactorContext, param_name: &str, calls: &[CallSite]) -> Result<RefactorResult, RefactorError> {
+    if !matches!(ctx.language, LanguageId::Python | LanguageId::JavaScript | LanguageId::TypeScript) {
+        return Err(RefactorError::Unsupported(format!(
+            "Extract Parameter needs the parameter's type in {}",
+            ctx.language.as_str()
+        )));
+    }
+    let ident_re = Regex::new(r"^[A-Za-z_$][A-Za-z0-9_$]*$").unwrap();
+    if !ident_re.is_match(param_name) || is_keyword(param_name, ctx.language) {
+        return Err(RefactorError::InvalidSelection(format!("'{}' is not a valid parameter name", param_name)));
+    }
+
+    let source = ctx.source;
+    let selected = &source[ctx.selection_bytes.clone()];
+    let start = ctx.selection_bytes.start + (selected.len() - selected.trim_start().len());
+    let end = (ctx.selection_bytes.end - (selected.len() - selected.trim_end().len())).max(start);
+    let expression_text = &source[start..end];
+    if expression_text.is_empty() || !is_valid_expression(expression_text, ctx.language) {
+        return Err(RefactorError::NoExpression("the selection is not an expression".to_string()));
+    }
+
+    let mut parser = LanguageParser::new();
+    parser
+        .set_language(ctx.language)
+        .map_err(|e| RefactorError::ParseError(e.to_string()))?;
+    let tree = parser
+        .parse(source, None)
+        .map_err(|e| RefactorError::ParseError(e.to_string()))?;
+    let expression = tree
+        .root_node()
+        .descendant_for_byte_range(start, end)
+        .filter(|n| n.start_byte() == start && n.end_byte() == end)
+        .ok_or_else(|| RefactorError::NoExpression("the selection does not cover a whole expression".to_string()))?;
+
+    let function = enclosing(expression, is_definition)
+        .ok_or_else(|| RefactorError::InvalidSelection("The selection is not inside a function".to_string()))?;
+    let body = function
+        .child_by_field_name("body")
+        .filter(|body| within(expression, *body))
+        .ok_or_else(|| RefactorError::InvalidSelection("The selection is not inside a function body".to_string()))?;
+    let name = text(source, definition_name(function).unwrap_or(function));
+    let parameters = parameter_names(function, source).map_err(|_| {
+        RefactorError::Unsupported(
+            "Functions with default, optional, rest or destructured parameters are not supported".to_string(),
+        )
+    })?;
+
+    // The value is computed by the caller from here on
+    let mut bound: HashSet<&str> = parameters.iter().map(String::as_str).collect();
+    collect_bound_names(body, source, &mut bound);
+    let references = find_variable_references(expression_text, ctx.language);
+    if let Some(local) = references.iter().find(|r| bound.contains(r.as_str())) {
+        return Err(RefactorError::CannotExtract(format!(
+            "'{}' is local to '{}' and not known at its call sites",
+            local, name
+        )));
+    }
+    let receiver = Regex::new(r"\b(?:this|self|super)\b").unwrap();
+    if receiver.is_match(expression_text) {
+        return Err(RefactorError::CannotExtract(format!(
+            "The expression uses the receiver of '{}'",
+            name
+        )));
+    }
+    if has_side_effects(expression_text, ctx.language) {
+        return Err(RefactorError::HasSideEffects);
+    }
+    let function_range = node_range(source, function);
+    if parameters.iter().any(|p| p == param_name)
+        || find_occurrences(source, param_name, Some(ctx.language))
+            .iter()
+            .any(|r| function_range.contains_range(r))
+    {
+        return Err(RefactorError::InvalidSelection(format!(
+            "'{}' is already used in '{}'",
+            param_name, name
+        )));
+    }
+
+    let mut edits = vec![
+        TextEdit::replace(node_range(source, expression), param_name.to_string()),
+        append_to_list(source, parameter_list(function)?, param_name),
+    ];
+
+    let mut updated = 0;
+    for site in calls.iter().filter(|site| site.callee == name) {
+        let call = match call_at(tree.root_node(), source, site, name) {
+            Some(call) => call,
+            None => continue,
+        };
+        // A call inside the extracted expression goes away with it
+        if within(call, expression) {
+            continue;
+        }
+        let arguments = call_arguments(call).map_err(|_| {
+            RefactorError::Unsupported("Calls with spread or keyword arguments are not supported".to_string())
+        })?;
+        if arguments.len() != parameters.len() {
+            return Err(RefactorError::Unsupported(format!(
+                "A call of '{}' passes {} arguments for {} parameters",
+                name,
+                arguments.len(),
+                parameters.len()
+            )));
+        }
+        let list = call
+            .child_by_field_name("arguments")
+            .ok_or_else(|| RefactorError::Unsupported(format!("A call of '{}' has no argument list", name)))?;
+        edits.push(append_to_list(source, list, expression_text));
+        updated += 1;
+    }
+
+    let description = match updated {
+        1 => format!("Extract parameter '{}' of '{}', updating 1 call", param_name, name),
+        n => format!("Extract parameter '{}' of '{}', updating {} calls", param_name, name, n),
+    };
+    Ok(RefactorResult::new(TextEdit::normalize(edits)?, description))
+}
+
+/// The parameter list of a function definition
+fn parameter_list(function: Node) -> Result<Node, RefactorError> {
+    function
+        .child_by_field_name("parameters")
+        .ok_or_else(|| RefactorError::Unsupported("The function has no parameter list".to_string()))
+}
+
+/// The edit adding `item` at the end of a parenthesized list
+fn append_to_list(source: &str, list: Node, item: &str) -> TextEdit {
+    let mut cursor = list.walk();
+    let last = list
+        .named_children(&mut cursor)
+        .filter(|n| !n.kind().contains("comment"))
+        .last();
+    match last {
+        Some(last) => TextEdit::insert(offset_to_position(source, last.end_byte()), format!(", {}", item)),
+        // Right after the `(`
+        None => TextEdit::insert(offset_to_position(source, list.start_byte() + 1), item.to_string()),
+    }
+}
+
+/// The call of `name` that `site` reports
+fn call_at<'t>(root: Node<'t>, source: &str, site: &CallSite, name: &str) -> Option<Node<'t>> {
+    let start = byte_offset(source, site.range.start);
+    let end = byte_offset(source, site.range.end).max(start);
+    let node = root.descendant_for_byte_range(start, end)?;
+    enclosing(node, |n| is_call(n) && callee_name(n).is_some_and(|callee| text(source, callee) == name))
+}
+
+/// Byte offset of a position with a byte column
+fn byte_offset(source: &str, position: Position) -> usize {
+    let line_start: usize = source
+        .split('\n')
+        .take(position.line as usize)
+        .map(|line| line.len() + 1)
+        .sum();
+    (line_start + position.column as usize).min(source.len())
+}
+
+/// Names declared or assigned below `node`
+fn collect_bound_names<'s>(node: Node, source: &'s str, bound: &mut HashSet<&'s str>) {
+    let target = match node.kind() {
+        "variable_declarator" => node.child_by_field_name("name"),
+        "assignment" | "augmented_assignment" | "assignment_expression" | "for_statement" | "for_in_statement" => {
+            node.child_by_field_name("left")
+        }
+        "function_declaration" | "function_definition" | "class_declaration" | "class_definition" => {
+            node.child_by_field_name("name")
+        }
+        _ => None,
+    };
+    if let Some(target) = target {
+        let mut names = Vec::new();
+        collect_identifiers(target, source, &mut names);
+        bound.extend(names);
+    }
+    if is_definition(node) || node.kind().contains("function") || node.kind() == "lambda" {
+        if let Some(parameters) = node.child_by_field_name("parameters") {
+            let mut names = Vec::new();
+            collect_identifiers(parameters, source, &mut names);
+            bound.extend(names);
+        }
+    }
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect_bound_names(child, source, bound);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apply_edits;
+
+    /// The calls of `callee` in `source`, as an adapter would report them
+    fn calls(source: &str, callee: &str) -> Vec<CallSite> {
+        let pattern = Regex::new(&format!(r"\b{}\([^)]*\)", callee)).unwrap();
+        source
+            .split('\n')
+            .enumerate()
+            .flat_map(|(line, text)| {
+                pattern
+                    .find_iter(text)
+                    .map(|m| CallSite {
+                        callee: callee.to_string(),
+                        range: Range::from_coords(line as u32, m.start() as u32, line as u32, m.end() as u32),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    fn extracted(source: &str, selection: &str, param_name: &str, language: LanguageId) -> Result<String, RefactorError> {
+        let start = source.find(selection).unwrap();
+        let range = Range::new(offset_to_position(source, start), offset_to_position(source, start + selection.len()));
+        let ctx = RefactorContext::new(source, "test", range, language);
+        let name = "greet";
+        Ok(apply_edits(source, &extract(&ctx, param_name, &calls(source, name))?.edits))
+    }
+
+    #[test]
+    fn test_extract_parameter_updates_every_call() {
+        let source = "function greet(name) {\n  return \"Hello, \" + name;\n}\n\ngreet(\"Ada\");\nconsole.log(greet(user.name));\n";
+        assert_eq!(
+            extracted(source, "\"Hello, \"", "greeting", LanguageId::JavaScript).unwrap(),
+            "function greet(name, greeting) {\n  return greeting + name;\n}\n\ngreet(\"Ada\", \"Hello, \");\nconsole.log(greet(user.name, \"Hello, \"));\n"
+        );
+
+        // Python, with a function that takes no parameters yet
+        let source = "def greet():\n    print(\"hi\")\n\ngreet()\ngreet()\n";
+        assert_eq!(
+            extracted(source, "\"hi\"", "text", LanguageId::Python).unwrap(),
+            "def greet(text):\n    print(text)\n\ngreet(\"hi\")\ngreet(\"hi\")\n"
+        );
+    }
+
+    #[test]
+    fn test_extract_parameter_refusals() {
+        let source = "function greet(name) {\n  const prefix = \"Hi \";\n  return prefix + name.trim() + queue.pop();\n}\n";
+        assert!(matches!(
+            extracted(source, "name.trim()", "trimmed", LanguageId::JavaScript),
+            Err(RefactorError::CannotExtract(_))
+        ));
+        assert!(matches!(
+            extracted(source, "\"Hi \"", "prefix", LanguageId::JavaScript),
+            Err(RefactorError::InvalidSelection(_))
+        ));
+        assert!(matches!(
+            extracted(source, "queue.pop()", "next", LanguageId::JavaScript),
+            Err(RefactorError::HasSideEffects)
+        ));
+        assert!(matches!(
+            extracted("fn greet() -> i32 {\n    1\n}\n", "1", "one", LanguageId::Rust),
+            Err(RefactorError::Unsupported(_))
+        ));
+    }
+}
@@ -5,21 +5,90 @@
 //! and the expression is replaced with a reference to the variable.
 
 use crate::analysis::{
-    find_declaration_insertion_point, is_valid_expression,
-    suggest_variable_name,
+    closure_expression_body, comment_spans, conditional_around, declaration_in_scope,
+    find_declaration_insertion_point, find_variable_references, has_side_effects, is_valid_expression, literal_spans,
+    statement_bytes, suggest_name, suggest_variable_name_with, unbalanced_selection, MUTATING_METHODS,
 };
-use crate::{RefactorContext, RefactorError, RefactorResult, TextEdit};
-use logos_core::{Position, Range};
-use logos_parser::LanguageId;
+use crate::style::CodeStyle;
+use crate::surround_with::indent_unit;
+use crate::{offset_to_position, position_to_offset, RefactorContext, RefactorError, RefactorResult, TextEdit};
+use logos_core::Range;
+use logos_parser::{LanguageId, LanguageParser};
 use regex::Regex;
 
 /// Check if the selection can be extracted to a variable
 pub fn can_extract(ctx: &RefactorContext) -> Result<bool, RefactorError> {
+    check_extract(&expression_context(ctx)?, false)
+}
+
+/// Narrow the selection to the expression itself: surrounding whitespace, a
+/// trailing line comment and the statement terminator are left in place.
+/// A selection of nothing but whitespace and comments has no expression.
+fn expression_context<'a>(ctx: &RefactorContext<'a>) -> Result<RefactorContext<'a>, RefactorError> {
+    let selected = ctx.selected_text();
+    if selected.trim().is_empty() {
+        return Err(RefactorError::NoExpression("the selection is empty or only whitespace".to_string()));
+    }
+    let (start, end) = (ctx.selection_bytes.start, ctx.selection_bytes.end);
+    let comments = comment_spans(ctx.source, start, end, ctx.language);
+    let in_comment = |i: usize| comments.iter().any(|&(s, e)| s <= start + i && start + i < e);
+    if selected.char_indices().all(|(i, ch)| ch.is_whitespace() || in_comment(i)) {
+        return Err(RefactorError::NoExpression("the selection is only a comment".to_string()));
+    }
+
+    let mut text = selected.trim_end();
+
+    let last_line_start = text.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    if let Some(comment) = line_comment_start(&text[last_line_start..], ctx.language) {
+        text = &text[..last_line_start + comment];
+    }
+    let text = text.trim_end().trim_end_matches(';').trim_end();
+    let leading = text.len() - text.trim_start().len();
+
+    let start = ctx.selection_bytes.start + leading;
+    let end = ctx.selection_bytes.start + text.len();
+    Ok(RefactorContext::with_byte_range(ctx.source, ctx.uri, start, end.max(start), ctx.language).with_naming(ctx.naming))
+}
+
+/// Byte index of a line comment in `line`, ignoring markers inside strings
+fn line_comment_start(line: &str, language: LanguageId) -> Option<usize> {
+    let marker = if language == LanguageId::Python { "#" } else { "//" };
+    let mut in_string: Option<char> = None;
+    let mut prev = ' ';
+
+    for (i, ch) in line.char_indices() {
+        if let Some(quote) = in_string {
+            if ch == quote && prev != '\\' {
+                in_string = None;
+            }
+        } else if line[i..].starts_with(marker) {
+            return Some(i);
+        } else if matches!(ch, '"' | '`') || (ch == '\'' && language != LanguageId::Rust) {
+            in_string = Some(ch);
+        }
+        prev = ch;
+    }
+
+    None
+}
+
+/// Check extractability, optionally skipping the side-effect check
+fn check_extract(ctx: &RefactorContext, force: bool) -> Result<bool, RefactorError> {
     let selected = ctx.selected_text().trim();
 
     // Empty selection
     if selected.is_empty() {
-        return Err(RefactorError::NoExpression);
+        return Err(RefactorError::NoExpression("the selection is empty or only whitespace".to_string()));
+    }
+
+    let boundaries = statement_boundaries(ctx);
+    if !boundaries.is_empty() {
+        return Err(RefactorError::MultipleStatements(boundaries));
+    }
+
+    let leading = ctx.selected_text().len() - ctx.selected_text().trim_start().len();
+    if let Some(error) = unbalanced_selection(ctx, ctx.selection_bytes.start + leading, selected) {
+        return Err(error);
     }
 
     // Check if it's a valid expression
@@ -29,9 +98,228 @@ pub fn can_extract(ctx: &RefactorContext) -> Result<bool, RefactorError> {
         ));
     }
 
+    // Extracting a mutating expression would change evaluation order
+    if !force && has_side_effects(selected, ctx.language) {
+        return Err(RefactorError::HasSideEffects);
+    }
+
+    // The declaration goes before the statement, so it would evaluate a
+    // branch whichever way the condition goes
+    let start = ctx.selection_bytes.start + leading;
+    if let Some((from, to)) = conditional_around(ctx.source, start, start + selected.len(), ctx.language) {
+        return Err(RefactorError::ControlFlowIssue(
+            format!(
+                "'{}' is a branch of a conditional and only runs when '{}' selects it; a variable would always evaluate it",
+                selected,
+                &ctx.source[from..to]
+            ),
+            vec![Range::new(offset_to_position(ctx.source, from), offset_to_position(ctx.source, to))],
+        ));
+    }
+
+    if ctx.language == LanguageId::Rust {
+        if let Some(error) = ownership_issue(ctx, start, selected) {
+            return Err(error);
+        }
+    }
+
     Ok(true)
 }
 
+/// Rust: a `let` must not change what the expression borrows or moves.
+/// - A `&mut` borrow would last to the end of the block instead of the
+///   statement, locking its place for everything after it
+/// - A shared borrow of a place may not outlive later changes to the place
+/// - A place used as a method receiver or field base is only borrowed
+///   there; the `let` would move it
+/// - A value made inside a loop, declared before the loop, would be made
+///   once and moved on the first iteration
+///
+/// `selected` starts at byte `start` of the source.
+fn ownership_issue(ctx: &RefactorContext, start: usize, selected: &str) -> Option<RefactorError> {
+    let range = |from: usize, to: usize| {
+        Range::new(offset_to_position(ctx.source, from), offset_to_position(ctx.source, to))
+    };
+    let end = start + selected.len();
+    let insert = find_declaration_insertion_point(ctx.source, ctx.selection, ctx.language);
+    if let Some((loop_start, header_end)) = loop_around(ctx.source, start, end) {
+        if position_to_offset(ctx.source, insert) <= loop_start {
+            return Some(RefactorError::ControlFlowIssue(
+                format!(
+                    "'{}' runs on every iteration of '{}'; a variable before the loop would be moved by the first",
+                    selected,
+                    &ctx.source[loop_start..header_end]
+                ),
+                vec![range(loop_start, header_end)],
+            ));
+        }
+    }
+
+    let place = Regex::new(r"^\*?\s*([A-Za-z_]\w*)(?:\s*\.\s*\w+|\s*\[[^\[\]]*\])*$").unwrap();
+
+    if selected.strip_prefix("&mut").is_some_and(|rest| rest.starts_with(char::is_whitespace)) {
+        return Some(RefactorError::ControlFlowIssue(
+            format!(
+                "'{}' is a mutable borrow; in a variable it would last to the end of the block",
+                selected
+            ),
+            vec![range(start, start + "&mut".len())],
+        ));
+    }
+
+    if let Some(borrowed) = selected.strip_prefix('&') {
+        let root = place.captures(borrowed.trim_start())?.get(1)?.as_str();
+        let block_end = enclosing_block_end(ctx.source, end);
+        let changes: Vec<Range> = changes_to(root, &ctx.source[end..block_end])
+            .into_iter()
+            .map(|(from, to)| range(end + from, end + to))
+            .collect();
+        if changes.is_empty() {
+            return None;
+        }
+        return Some(RefactorError::ControlFlowIssue(
+            format!("'{}' changes while the borrow '{}' would still be alive", root, selected),
+            changes,
+        ));
+    }
+
+    let after = ctx.source[end..].trim_start();
+    let borrowed_after = (after.starts_with('.') && !after.starts_with("..")) || after.starts_with('[');
+    if place.is_match(selected) && borrowed_after {
+        return Some(RefactorError::ControlFlowIssue(
+            format!("'{}' is only borrowed here and a variable would take ownership of it; extract '&{}' instead", selected, selected),
+            vec![range(start, end)],
+        ));
+    }
+
+    None
+}
+
+/// The innermost loop of the function around `start..end` that runs it on
+/// every iteration, as the byte span of the loop and of its header
+fn loop_around(source: &str, start: usize, end: usize) -> Option<(usize, usize)> {
+    let mut parser = LanguageParser::new();
+    parser.set_language(LanguageId::Rust).ok()?;
+    let tree = parser.parse(source, None).ok()?;
+
+    let mut current = tree.root_node().descendant_for_byte_range(start, end)?.parent();
+    while let Some(node) = current {
+        if matches!(node.kind(), "function_item" | "closure_expression") {
+            return None;
+        }
+        if matches!(node.kind(), "for_expression" | "while_expression" | "loop_expression") {
+            // A `for` evaluates its iterator once
+            let in_iterator = node
+                .child_by_field_name("value")
+                .is_some_and(|value| value.start_byte() <= start && end <= value.end_byte());
+            if !in_iterator {
+                let body_start = node.child_by_field_name("body").map_or(node.end_byte(), |body| body.start_byte());
+                let header = source[node.start_byte()..body_start].trim_end();
+                return Some((node.start_byte(), node.start_byte() + header.len()));
+            }
+        }
+        current = node.parent();
+    }
+    None
+}
+
+/// Byte index of the `}` closing the block around byte `from`, or the end of
+/// the source
+fn enclosing_block_end(source: &str, from: usize) -> usize {
+    let mut depth = 0usize;
+    for (i, ch) in source[from..].char_indices() {
+        match ch {
+            '{' => depth += 1,
+            '}' if depth == 0 => return from + i,
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    source.len()
+}
+
+/// Byte spans in `text` that assign to `root` or a part of it, borrow it
+/// mutably or call a mutating method on it
+fn changes_to(root: &str, text: &str) -> Vec<(usize, usize)> {
+    let root = regex::escape(root);
+    let path = r"(?:\s*\.\s*\w+|\s*\[[^\[\]]*\])*";
+    let methods = format!("{}|iter_mut|get_mut|as_mut|entry|swap|resize|split_off|sort_by|sort_by_key|sort_unstable", MUTATING_METHODS.join("|"));
+    let patterns = [
+        format!(r"(?:^|[^.\w])(&mut\s+{}\b)", root),
+        format!(r"(?:^|[^.\w])({}\b{})\s*(?:<<|>>|[-+*/%&|^])?=[^=>]", root, path),
+        format!(r"(?:^|[^.\w])({}\b{}\s*\.\s*(?:{}))\s*\(", root, path, methods),
+    ];
+
+    let mut spans: Vec<(usize, usize)> = patterns
+        .iter()
+        .flat_map(|pattern| {
+            let re = Regex::new(pattern).unwrap();
+            re.captures_iter(text)
+                .filter_map(|c| c.get(1).map(|m| (m.start(), m.end())))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    spans.sort();
+    spans.dedup();
+    spans
+}
+
+/// Statement separators at the top level of the selection: `;`, or a line
+/// break in Python
+fn statement_boundaries(ctx: &RefactorContext) -> Vec<Range> {
+    let text = ctx.selected_text();
+    let mut boundaries = Vec::new();
+    let mut depth = 0i32;
+    let mut in_string: Option<char> = None;
+    let mut prev = ' ';
+
+    for (i, ch) in text.char_indices() {
+        if let Some(quote) = in_string {
+            if ch == quote && prev != '\\' {
+                in_string = None;
+            }
+        } else {
+            let is_boundary = match ch {
+                '"' | '`' => {
+                    in_string = Some(ch);
+                    false
+                }
+                '\'' if ctx.language != LanguageId::Rust => {
+                    in_string = Some(ch);
+                    false
+                }
+                '(' | '[' | '{' => {
+                    depth += 1;
+                    false
+                }
+                ')' | ']' | '}' => {
+                    depth -= 1;
+                    false
+                }
+                ';' => depth == 0,
+                // A backslash continues the line; the last line has no successor
+                '\n' => {
+                    ctx.language == LanguageId::Python
+                        && depth == 0
+                        && prev != '\\'
+                        && !text[i..].trim().is_empty()
+                }
+                _ => false,
+            };
+            if is_boundary {
+                let start = ctx.selection_bytes.start + i;
+                boundaries.push(Range::new(
+                    offset_to_position(ctx.source, start),
+                    offset_to_position(ctx.source, start + 1),
+                ));
+            }
+        }
+        prev = ch;
+    }
+
+    boundaries
+}
+
 /// Find all occurrences of the same expression in the source
 pub fn find_occurrences(ctx: &RefactorContext) -> Vec<Range> {
     let selected = ctx.selected_text();
@@ -48,24 +336,11 @@ pub fn find_occurrences(ctx: &RefactorContext) -> Vec<Range> {
     let pattern = format!(r"(?m){}", escaped);
 
     if let Ok(re) = Regex::new(&pattern) {
-        let lines: Vec<&str> = ctx.source.lines().collect();
-        let mut line_offsets: Vec<usize> = Vec::new();
-        let mut offset = 0;
-
-        for line in &lines {
-            line_offsets.push(offset);
-            offset += line.len() + 1; // +1 for newline
-        }
-
         for m in re.find_iter(ctx.source) {
-            let start_offset = m.start();
-            let end_offset = m.end();
-
-            // Convert byte offsets to line/column
-            let start_pos = offset_to_position(&line_offsets, &lines, start_offset);
-            let end_pos = offset_to_position(&line_offsets, &lines, end_offset);
-
-            occurrences.push(Range::new(start_pos, end_pos));
+            occurrences.push(Range::new(
+                offset_to_position(ctx.source, m.start()),
+                offset_to_position(ctx.source, m.end()),
+            ));
         }
     }
 
@@ -76,53 +351,247 @@ pub fn find_occurrences(ctx: &RefactorContext) -> Vec<Range> {
     occurrences
 }
 
-fn offset_to_position(line_offsets: &[usize], lines: &[&str], offset: usize) -> Position {
-    for (i, &line_offset) in line_offsets.iter().enumerate() {
-        let line_end = if i + 1 < line_offsets.len() {
-            line_offsets[i + 1] - 1
-        } else {
-            line_offset + lines.get(i).map(|l| l.len()).unwrap_or(0)
-        };
+/// Extract the selected expression into a variable
+///
+/// Expressions with side effects are refused unless `force` is set.
+pub fn extract(
+    ctx: &RefactorContext,
+    variable_name: &str,
+    force: bool,
+) -> Result<RefactorResult, RefactorError> {
+    extract_declaration(ctx, variable_name, force, false)
+}
+
+/// Extract the selected expression into a local constant
+///
+/// The expression may not read variables. Rust is not supported: its
+/// constants need a type.
+pub fn extract_constant(ctx: &RefactorContext, constant_name: &str) -> Result<RefactorResult, RefactorError> {
+    if ctx.language == LanguageId::Rust {
+        return Err(RefactorError::Unsupported("Rust constants need a type".to_string()));
+    }
+    let selected = expression_context(ctx)?;
+    let references = find_variable_references(selected.selected_text(), ctx.language);
+    if let Some(variable) = references.iter().min() {
+        return Err(RefactorError::CannotExtract(format!(
+            "The expression reads '{}' and is not a constant",
+            variable
+        )));
+    }
+    extract_declaration(ctx, constant_name, false, true)
+}
+
+/// Extract the same expression at each of `ranges` into one variable,
+/// declared before the earliest of them
+///
+/// `ctx` supplies the document; its own selection is ignored. Every range
+/// must hold the same expression, and each later site must still be in the
+/// block the declaration goes into.
+pub fn extract_at(ctx: &RefactorContext, ranges: &[Range], variable_name: &str) -> Result<RefactorResult, RefactorError> {
+    let mut sites = ranges
+        .iter()
+        .map(|range| {
+            expression_context(&RefactorContext::new(ctx.source, ctx.uri, *range, ctx.language).with_naming(ctx.naming))
+        })
+        .collect::<Result<Vec<RefactorContext>, RefactorError>>()?;
+    sites.sort_by_key(|site| site.selection_bytes.start);
+    sites.dedup_by_key(|site| site.selection_bytes.clone());
+    let first = sites
+        .first()
+        .ok_or_else(|| RefactorError::InvalidSelection("No ranges to extract".to_string()))?;
+
+    let expression = first.selected_text().trim();
+    for site in &sites {
+        check_extract(site, false)?;
+        let text = site.selected_text().trim();
+        if text != expression {
+            return Err(RefactorError::InvalidSelection(format!(
+                "The ranges hold different expressions: '{}' and '{}'",
+                expression, text
+            )));
+        }
+        let (start, end) = (site.selection_bytes.start, site.selection_bytes.end);
+        let free_variables = find_variable_references(text, ctx.language);
+        if closure_expression_body(ctx.source, start, end, &free_variables, ctx.language).is_some() {
+            return Err(RefactorError::CannotExtract(
+                "An occurrence uses a closure parameter and cannot share a variable".to_string(),
+            ));
+        }
+    }
+
+    let insert_pos = find_declaration_insertion_point(ctx.source, first.selection, ctx.language);
+    let indent = first.indentation_at(insert_pos.line);
+    for site in &sites[1..] {
+        let site_pos = find_declaration_insertion_point(ctx.source, site.selection, ctx.language);
+        if !stays_in_block(ctx.source, insert_pos.line, site_pos.line, &indent) {
+            return Err(RefactorError::CannotExtract(format!(
+                "The occurrence on line {} is outside the block of the declaration",
+                site.selection.start.line + 1
+            )));
+        }
+    }
+
+    let style = CodeStyle::detect(ctx.source, ctx.language);
+    let terminator = style.terminator();
+    let value = multiline_value(first, &indent);
+    let declaration = style.apply(
+        &generate_declaration(variable_name, &value, ctx.language, &indent, false),
+        ctx.language,
+    );
+
+    let mut edits = vec![TextEdit::insert(insert_pos, declaration.clone())];
+    for site in &sites {
+        let suffix = missing_terminator(site, site.selection, terminator);
+        edits.push(TextEdit::replace(site.selection, format!("{}{}", variable_name, suffix)));
+    }
+
+    Ok(RefactorResult::new(
+        TextEdit::normalize(edits)?,
+        format!(
+            "Extract {} occurrences of '{}' to variable '{}'",
+            sites.len(),
+            expression,
+            variable_name
+        ),
+    )
+    .with_generated_code(declaration))
+}
+
+/// Assign the selected expression to `variable_name`, an existing variable,
+/// just before the statement using it, and replace the expression with the
+/// variable
+///
+/// The variable must be declared before that statement, in a scope around
+/// it, and be assignable. The statement may not use the variable elsewhere:
+/// it would then see the new value.
+pub fn extract_into_existing(ctx: &RefactorContext, variable_name: &str) -> Result<RefactorResult, RefactorError> {
+    let ctx = &expression_context(ctx)?;
+    check_extract(ctx, false)?;
 
-        if offset <= line_end {
-            let column = offset - line_offset;
-            return Position::new(i as u32, column as u32);
+    let trimmed = ctx.selected_text().trim();
+    let free_variables = find_variable_references(trimmed, ctx.language);
+    let (start, end) = (ctx.selection_bytes.start, ctx.selection_bytes.end);
+    if closure_expression_body(ctx.source, start, end, &free_variables, ctx.language).is_some() {
+        return Err(RefactorError::CannotExtract(
+            "The expression uses a closure parameter and cannot be assigned outside the closure".to_string(),
+        ));
+    }
+
+    let insert_pos = find_declaration_insertion_point(ctx.source, ctx.selection, ctx.language);
+    let insert_offset = position_to_offset(ctx.source, insert_pos);
+    let declaration = declaration_in_scope(ctx.source, variable_name, insert_offset, ctx.language).ok_or_else(|| {
+        RefactorError::CannotExtract(format!("'{}' is not declared in scope", variable_name))
+    })?;
+    if !declaration.assignable {
+        return Err(RefactorError::CannotExtract(format!("'{}' cannot be assigned", variable_name)));
+    }
+
+    if let Some((statement_start, statement_end)) = statement_bytes(ctx.source, start, ctx.language) {
+        let uses = Regex::new(&format!(r"(?:^|[^.\w]){}\b", regex::escape(variable_name))).unwrap();
+        let elsewhere: Vec<Range> = [(statement_start, start), (end, statement_end)]
+            .into_iter()
+            .flat_map(|(from, to)| {
+                uses.find_iter(&ctx.source[from..to])
+                    .map(|m| {
+                        let name_end = from + m.end();
+                        (name_end - variable_name.len(), name_end)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .map(|(from, to)| Range::new(offset_to_position(ctx.source, from), offset_to_position(ctx.source, to)))
+            .collect();
+        if !elsewhere.is_empty() {
+            return Err(RefactorError::ControlFlowIssue(
+                format!("The statement also uses '{}', which would then hold the expression", variable_name),
+                elsewhere,
+            ));
         }
     }
 
-    Position::new(0, 0)
+    let indent = ctx.indentation_at(insert_pos.line);
+    let style = CodeStyle::detect(ctx.source, ctx.language);
+    let value = multiline_value(ctx, &indent);
+    let assignment = style.apply(
+        &format!("{}{} = {}{}\n", indent, variable_name, value, style.terminator()),
+        ctx.language,
+    );
+
+    let occurrence = Range::new(offset_to_position(ctx.source, start), offset_to_position(ctx.source, end));
+    let suffix = missing_terminator(ctx, occurrence, style.terminator());
+    let edits = vec![
+        TextEdit::replace(occurrence, format!("{}{}", variable_name, suffix)),
+        TextEdit::insert(insert_pos, assignment.clone()),
+    ];
+
+    Ok(RefactorResult::new(
+        edits,
+        format!("Assign '{}' to existing variable '{}'", trimmed, variable_name),
+    )
+    .with_generated_code(assignment))
 }
 
-/// Extract the selected expression into a variable
-pub fn extract(ctx: &RefactorContext, variable_name: &str) -> Result<RefactorResult, RefactorError> {
-    can_extract(ctx)?;
+/// Whether the lines from `from` to `to` stay in the block whose statements
+/// are indented by `indent`: none of them leaves it for a shallower one
+fn stays_in_block(source: &str, from: u32, to: u32, indent: &str) -> bool {
+    source
+        .lines()
+        .skip(from as usize)
+        .take((to - from) as usize + 1)
+        .filter(|line| !line.trim().is_empty())
+        .all(|line| line.len() - line.trim_start().len() >= indent.len())
+}
+
+fn extract_declaration(
+    ctx: &RefactorContext,
+    variable_name: &str,
+    force: bool,
+    constant: bool,
+) -> Result<RefactorResult, RefactorError> {
+    let ctx = &expression_context(ctx)?;
+    check_extract(ctx, force)?;
 
     let selected = ctx.selected_text();
     let trimmed = selected.trim();
 
+    // A closure parameter in the expression keeps the declaration inside the
+    // closure; an expression body becomes a block to hold it
+    let free_variables = find_variable_references(trimmed, ctx.language);
+    let (start, end) = (ctx.selection_bytes.start, ctx.selection_bytes.end);
+    if let Some(body) = closure_expression_body(ctx.source, start, end, &free_variables, ctx.language) {
+        return extract_into_closure(ctx, variable_name, body, constant);
+    }
+
     // Find where to insert the declaration
     let insert_pos = find_declaration_insertion_point(ctx.source, ctx.selection, ctx.language);
 
     // Get indentation for the new line
     let indent = ctx.indentation_at(insert_pos.line);
 
-    // Generate the declaration statement
-    let declaration = generate_declaration(variable_name, trimmed, ctx.language, &indent);
-
-    // Find all occurrences to replace (currently just the selected one)
-    let occurrences = vec![ctx.selection]; // Could use find_occurrences for replace all
-
-    // Create edits (in reverse order so offsets remain valid)
-    let mut edits = Vec::new();
-
-    // First, add replacements for all occurrences (in reverse order)
-    let mut sorted_occurrences = occurrences.clone();
-    sorted_occurrences.sort_by(|a, b| b.start.cmp(&a.start));
+    // Generate the declaration statement, in the file's style
+    let style = CodeStyle::detect(ctx.source, ctx.language);
+    let terminator = style.terminator();
+    let value = multiline_value(ctx, &indent);
+    let declaration = style.apply(
+        &generate_declaration(variable_name, &value, ctx.language, &indent, constant),
+        ctx.language,
+    );
 
-    for occurrence in sorted_occurrences {
-        edits.push(TextEdit::replace(occurrence, variable_name.to_string()));
+    // Replace exactly the selected bytes, never a text match elsewhere
+    let occurrence = Range::new(
+        offset_to_position(ctx.source, ctx.selection_bytes.start),
+        offset_to_position(ctx.source, ctx.selection_bytes.end),
+    );
+    let replaced = &ctx.source[position_to_offset(ctx.source, occurrence.start)..position_to_offset(ctx.source, occurrence.end)];
+    if replaced != trimmed {
+        return Err(RefactorError::InvalidSelection(format!(
+            "The selection maps to '{}' instead of '{}'",
+            replaced, trimmed
+        )));
     }
 
+    let suffix = missing_terminator(ctx, occurrence, terminator);
+    let mut edits = vec![TextEdit::replace(occurrence, format!("{}{}", variable_name, suffix))];
+
     // Then add the declaration at the insertion point
     edits.push(TextEdit::insert(insert_pos, declaration.clone()));
 
@@ -133,38 +602,163 @@ pub fn extract(ctx: &RefactorContext, variable_name: &str) -> Result<RefactorRes
     .with_generated_code(declaration))
 }
 
-/// Generate a variable declaration statement
-fn generate_declaration(name: &str, value: &str, language: LanguageId, indent: &str) -> String {
-    match language {
-        LanguageId::Python => {
-            format!("{}{} = {}\n", indent, name, value)
+/// Extract into the expression body `body` of a closure, which becomes a
+/// block declaring the variable and returning the rest of the expression
+fn extract_into_closure(
+    ctx: &RefactorContext,
+    variable_name: &str,
+    body: (usize, usize),
+    constant: bool,
+) -> Result<RefactorResult, RefactorError> {
+    if ctx.language == LanguageId::Python {
+        return Err(RefactorError::CannotExtract(
+            "The expression uses a lambda parameter, and a lambda cannot hold a declaration".to_string(),
+        ));
+    }
+
+    let body_start = offset_to_position(ctx.source, body.0);
+    let indent = ctx.indentation_at(body_start.line);
+    let inner = format!("{}{}", indent, indent_unit(ctx.source, &indent, ctx.language));
+    let style = CodeStyle::detect(ctx.source, ctx.language);
+
+    let value = multiline_value(ctx, &inner);
+    let declaration = style.apply(
+        &generate_declaration(variable_name, &value, ctx.language, &inner, constant),
+        ctx.language,
+    );
+    let rest = format!(
+        "{}{}{}",
+        &ctx.source[body.0..ctx.selection_bytes.start],
+        variable_name,
+        &ctx.source[ctx.selection_bytes.end..body.1]
+    );
+    let result = match ctx.language {
+        LanguageId::Rust => format!("{}{}", inner, rest),
+        _ => format!("{}return {}{}", inner, rest, style.terminator()),
+    };
+    let block = format!("{{\n{}{}\n{}}}", declaration, result, indent);
+
+    let range = Range::new(body_start, offset_to_position(ctx.source, body.1));
+    Ok(RefactorResult::new(
+        vec![TextEdit::replace(range, block)],
+        format!("Extract '{}' to variable '{}'", ctx.selected_text().trim(), variable_name),
+    )
+    .with_generated_code(declaration))
+}
+
+/// The selected expression as it will read in the declaration. Line breaks
+/// are kept; continuation lines move with the expression's first line to
+/// the declaration's indentation. Lines inside string literals and comments
+/// are left untouched.
+fn multiline_value(ctx: &RefactorContext, indent: &str) -> String {
+    let selected = ctx.selected_text();
+    let value = selected.trim();
+    if !value.contains('\n') {
+        return value.to_string();
+    }
+
+    let start = ctx.selection_bytes.start + (selected.len() - selected.trim_start().len());
+    let literals = literal_spans(ctx.source, start, start + value.len(), ctx.language);
+    let in_literal = |i: usize| literals.iter().any(|&(s, e)| s <= start + i && start + i < e);
+
+    let base_indent = ctx.indentation_at(ctx.selection.start.line);
+    let mut result = String::with_capacity(value.len());
+    let mut offset = 0;
+    for (n, line) in value.split('\n').enumerate() {
+        let mut text = line;
+        // `offset - 1` is the line break before this line
+        if n > 0 && !in_literal(offset - 1) {
+            if let Some(rest) = line.strip_prefix(base_indent.as_str()) {
+                result.push_str(indent);
+                text = rest;
+            }
         }
-        LanguageId::JavaScript => {
-            format!("{}const {} = {};\n", indent, name, value)
+        result.push_str(text);
+        result.push('\n');
+        offset += line.len() + 1;
+    }
+    result.pop();
+    result
+}
+
+/// The terminator to write after the variable when `occurrence` is a whole
+/// statement that has none, e.g. `g(a + b)` as the last statement of a block
+fn missing_terminator(ctx: &RefactorContext, occurrence: Range, terminator: &'static str) -> &'static str {
+    if terminator.is_empty() {
+        return "";
+    }
+    let start = position_to_offset(ctx.source, occurrence.start);
+    let end = position_to_offset(ctx.source, occurrence.end);
+
+    let line_start = ctx.source[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let before = ctx.source[line_start..start].trim_end();
+    if !(before.is_empty() || before.ends_with(['{', '}', ';'])) {
+        return "";
+    }
+
+    // Nothing but a comment may follow on the line, and the block or file
+    // must end next: otherwise the statement may continue on the next line
+    let line_end = ctx.source[end..].find('\n').map(|i| end + i).unwrap_or(ctx.source.len());
+    let rest = &ctx.source[end..line_end];
+    let rest = match line_comment_start(rest, ctx.language) {
+        Some(comment) => &rest[..comment],
+        None => rest,
+    };
+    let next = ctx.source[line_end..].trim_start();
+    if rest.trim().is_empty() && (next.is_empty() || next.starts_with('}')) {
+        terminator
+    } else {
+        ""
+    }
+}
+
+/// Generate a variable declaration statement, or a constant one where the
+/// language can declare a constant without its type
+fn generate_declaration(name: &str, value: &str, language: LanguageId, indent: &str, constant: bool) -> String {
+    match (language, constant) {
+        (LanguageId::Python, _) => {
+            format!("{}{} = {}\n", indent, name, value)
         }
-        LanguageId::TypeScript => {
+        (LanguageId::JavaScript | LanguageId::TypeScript, _) => {
             format!("{}const {} = {};\n", indent, name, value)
         }
-        LanguageId::Rust => {
+        (LanguageId::Rust, _) => {
             format!("{}let {} = {};\n", indent, name, value)
         }
-        LanguageId::Go => {
+        (LanguageId::Go, false) => {
             format!("{}{} := {}\n", indent, name, value)
         }
-        LanguageId::Java => {
+        (LanguageId::Go, true) => {
+            format!("{}const {} = {}\n", indent, name, value)
+        }
+        (LanguageId::Java, false) => {
             format!("{}var {} = {};\n", indent, name, value)
         }
-        LanguageId::C | LanguageId::Cpp => {
+        (LanguageId::Java, true) => {
+            format!("{}final var {} = {};\n", indent, name, value)
+        }
+        (LanguageId::C | LanguageId::Cpp, false) => {
             format!("{}auto {} = {};\n", indent, name, value)
         }
+        (LanguageId::C | LanguageId::Cpp, true) => {
+            format!("{}const auto {} = {};\n", indent, name, value)
+        }
     }
 }
 
-/// Extract with suggested variable name
+/// Extract with suggested variable name, in the casing of `ctx.naming`
 pub fn extract_with_suggestion(ctx: &RefactorContext) -> Result<(String, RefactorResult), RefactorError> {
     let selected = ctx.selected_text();
-    let suggested_name = suggest_variable_name(selected, ctx.language);
-    let result = extract(ctx, &suggested_name)?;
+    let suggested_name = suggest_variable_name_with(selected, ctx.language, &ctx.naming);
+    let result = extract(ctx, &suggested_name, false)?;
+    Ok((suggested_name, result))
+}
+
+/// Extract a constant with a suggested name, in the casing of `ctx.naming`
+pub fn extract_constant_with_suggestion(ctx: &RefactorContext) -> Result<(String, RefactorResult), RefactorError> {
+    let selected = ctx.selected_text();
+    let suggested_name = suggest_name(selected, ctx.language, Some(ctx.naming.constant_case(ctx.language)));
+    let result = extract_constant(ctx, &suggested_name)?;
     Ok((suggested_name, result))
 }
 
@@ -176,6 +770,18 @@ mod tests {
         RefactorContext::new(source, "test.js", selection, language)
     }
 
+    #[test]
+    fn test_find_occurrences_across_empty_and_last_lines() {
+        let source = "let s = \"é\" + n * 2;\n\nlet t = n * 2";
+        let ctx = make_ctx(source, Range::from_coords(0, 14, 0, 19), LanguageId::JavaScript);
+        assert_eq!(ctx.selected_text(), "n * 2");
+
+        assert_eq!(
+            find_occurrences(&ctx),
+            vec![Range::from_coords(0, 14, 0, 19), Range::from_coords(2, 8, 2, 13)]
+        );
+    }
+
     #[test]
     fn test_can_extract_simple_expression() {
         let source = "let x = a + b;";
@@ -194,46 +800,500 @@ mod tests {
         assert!(can_extract(&ctx).is_err());
     }
 
+    #[test]
+    fn test_cannot_extract_increment() {
+        let source = "foo(i++);";
+        let selection = Range::from_coords(0, 4, 0, 7); // "i++"
+        let ctx = make_ctx(source, selection, LanguageId::JavaScript);
+
+        assert!(matches!(can_extract(&ctx), Err(RefactorError::HasSideEffects)));
+    }
+
+    #[test]
+    fn test_cannot_extract_mutating_call() {
+        let source = "print(list.append(x))";
+        let selection = Range::from_coords(0, 6, 0, 20); // "list.append(x)"
+        let ctx = make_ctx(source, selection, LanguageId::Python);
+
+        assert!(matches!(can_extract(&ctx), Err(RefactorError::HasSideEffects)));
+        assert!(extract(&ctx, "appended", false).is_err());
+        assert!(extract(&ctx, "appended", true).is_ok());
+    }
+
+    #[test]
+    fn test_unbalanced_selection() {
+        let source = "let x = foo(a, b) + 1;";
+        let selection = Range::from_coords(0, 8, 0, 16); // "foo(a, b"
+        let ctx = make_ctx(source, selection, LanguageId::JavaScript);
+
+        let err = can_extract(&ctx).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Cannot extract: '(' is never closed; extend the selection to include the matching ')'"
+        );
+        assert_eq!(err.ranges(), vec![Range::from_coords(0, 11, 0, 12)]);
+
+        let selection = Range::from_coords(0, 12, 0, 21); // "a, b) + 1"
+        let ctx = make_ctx(source, selection, LanguageId::JavaScript);
+        assert_eq!(
+            can_extract(&ctx).unwrap_err().to_string(),
+            "Cannot extract: ')' has no matching '('; extend the selection to include it"
+        );
+    }
+
+    #[test]
+    fn test_extract_inside_closure_using_its_parameter() {
+        let source = "function f(items, tax) {\n  const totals = items.map(item => item.price * item.qty + tax);\n  return totals;\n}\n";
+        assert_eq!(
+            extracted(source, Range::from_coords(1, 35, 1, 56), LanguageId::JavaScript),
+            "function f(items, tax) {\n  const totals = items.map(item => {\n    const v = item.price * item.qty;\n    return v + tax;\n  });\n  return totals;\n}\n"
+        );
+
+        // Without the parameter, the declaration goes before the statement
+        assert_eq!(
+            extracted(source, Range::from_coords(1, 59, 1, 62), LanguageId::JavaScript),
+            "function f(items, tax) {\n  const v = tax;\n  const totals = items.map(item => item.price * item.qty + v);\n  return totals;\n}\n"
+        );
+
+        let source = "fn f(xs: &[i32]) -> Vec<i32> {\n    xs.iter().map(|x| x * 2 + 1).collect()\n}\n";
+        assert_eq!(
+            extracted(source, Range::from_coords(1, 22, 1, 27), LanguageId::Rust),
+            "fn f(xs: &[i32]) -> Vec<i32> {\n    xs.iter().map(|x| {\n        let v = x * 2;\n        v + 1\n    }).collect()\n}\n"
+        );
+
+        let source = "totals = map(lambda item: item.price * 2, items)\n";
+        let ctx = make_ctx(source, Range::from_coords(0, 26, 0, 40), LanguageId::Python);
+        assert!(matches!(extract(&ctx, "v", false), Err(RefactorError::CannotExtract(_))));
+    }
+
+    #[test]
+    fn test_rust_borrows_and_moves() {
+        // A call result is safe to extract
+        let source = "fn total(v: &Vec<u32>) -> usize {\n    report(expensive(v));\n    v.len()\n}\n";
+        let ctx = make_ctx(source, Range::from_coords(1, 11, 1, 23), LanguageId::Rust);
+        let result = extract(&ctx, "cost", false).unwrap();
+        assert_eq!(
+            crate::apply_edits(source, &result.edits),
+            "fn total(v: &Vec<u32>) -> usize {\n    let cost = expensive(v);\n    report(cost);\n    v.len()\n}\n"
+        );
+
+        // A `&mut` temporary is not
+        let source = "fn bump(v: &mut Vec<u32>) {\n    reset(&mut v[0]);\n    v.push(1);\n}\n";
+        let ctx = make_ctx(source, Range::from_coords(1, 10, 1, 19), LanguageId::Rust);
+        let err = extract(&ctx, "first", false).unwrap_err();
+        assert!(matches!(err, RefactorError::ControlFlowIssue(..)));
+        assert_eq!(err.ranges(), vec![Range::from_coords(1, 10, 1, 14)]);
+
+        // Nor is a shared borrow of a place changed later on
+        let source = "fn show(v: &mut Vec<u32>) {\n    print(&v[0]);\n    v.push(1);\n}\n";
+        let ctx = make_ctx(source, Range::from_coords(1, 10, 1, 15), LanguageId::Rust);
+        assert_eq!(can_extract(&ctx).unwrap_err().ranges(), vec![Range::from_coords(2, 4, 2, 10)]);
+        let source = "fn show(v: &Vec<u32>) {\n    print(&v[0]);\n    print(&v[1]);\n}\n";
+        let ctx = make_ctx(source, Range::from_coords(1, 10, 1, 15), LanguageId::Rust);
+        assert!(can_extract(&ctx).is_ok());
+
+        // Nor a value moved on each iteration of a loop the declaration would precede
+        let source = "fn feed(x: String) {\n    for i in 0..3 { consume(x.clone()); }\n}\n";
+        let ctx = make_ctx(source, Range::from_coords(1, 28, 1, 37), LanguageId::Rust);
+        assert_eq!(can_extract(&ctx).unwrap_err().ranges(), vec![Range::from_coords(1, 4, 1, 17)]);
+        let source = "fn feed(x: String) {\n    for i in 0..3 {\n        consume(x.clone());\n    }\n}\n";
+        let ctx = make_ctx(source, Range::from_coords(2, 16, 2, 25), LanguageId::Rust);
+        let result = extract(&ctx, "copy", false).unwrap();
+        assert_eq!(
+            crate::apply_edits(source, &result.edits),
+            "fn feed(x: String) {\n    for i in 0..3 {\n        let copy = x.clone();\n        consume(copy);\n    }\n}\n"
+        );
+
+        // Nor a place that is only borrowed by the method call on it
+        let source = "fn count(s: &Store) -> usize {\n    total(s.items.len())\n}\n";
+        let ctx = make_ctx(source, Range::from_coords(1, 10, 1, 17), LanguageId::Rust);
+        let err = can_extract(&ctx).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Control flow issue: 's.items' is only borrowed here and a variable would take ownership of it; extract '&s.items' instead"
+        );
+    }
+
+    #[test]
+    fn test_multiple_statements_range() {
+        let source = "const a = f(x); g(\"a;b\", y);\n";
+        let selection = Range::from_coords(0, 10, 0, 29);
+        let ctx = make_ctx(source, selection, LanguageId::JavaScript);
+
+        let err = can_extract(&ctx).unwrap_err();
+        assert!(matches!(&err, RefactorError::MultipleStatements(_)));
+        assert_eq!(err.ranges(), vec![Range::from_coords(0, 14, 0, 15)]);
+    }
+
+    #[test]
+    fn test_multiple_statements_python() {
+        let source = "a = f(x,\n      y)\nb = g(a)\n";
+        let ctx = make_ctx(source, Range::from_coords(0, 4, 2, 8), LanguageId::Python);
+        assert_eq!(can_extract(&ctx).unwrap_err().ranges(), vec![Range::from_coords(1, 8, 2, 0)]);
+
+        // A bracketed continuation is one expression
+        let ctx = make_ctx(source, Range::from_coords(0, 4, 1, 8), LanguageId::Python);
+        assert!(can_extract(&ctx).is_ok());
+    }
+
     #[test]
     fn test_extract_javascript() {
         let source = "console.log(a + b);";
         let selection = Range::from_coords(0, 12, 0, 17); // "a + b"
         let ctx = make_ctx(source, selection, LanguageId::JavaScript);
 
-        let result = extract(&ctx, "sum").unwrap();
+        let result = extract(&ctx, "sum", false).unwrap();
         assert!(result.edits.len() >= 2); // declaration + replacement
     }
 
+    #[test]
+    fn test_extract_repeated_text_on_the_line() {
+        // `price` also ends `unit_price` and follows a non-ASCII string
+        let source = "function f() {\n    return \"€\" + unit_price + price * 2;\n}\n";
+        let selection = Range::from_coords(1, 30, 1, 35); // the second "price"
+        let ctx = make_ctx(source, selection, LanguageId::JavaScript);
+        assert_eq!(ctx.selected_text(), "price");
+
+        let result = extract(&ctx, "base", false).unwrap();
+        assert_eq!(
+            crate::apply_edits(source, &result.edits),
+            "function f() {\n    const base = price;\n    return \"€\" + unit_price + base * 2;\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_ternary_branch_not_hoisted() {
+        let source = "function f(cached) {\n    const value = cached ? cached.value : expensive();\n    return value;\n}\n";
+        let branch = make_ctx(source, Range::from_coords(1, 42, 1, 53), LanguageId::JavaScript);
+        assert_eq!(branch.selected_text(), "expensive()");
+        assert!(matches!(
+            extract(&branch, "computed", true),
+            Err(RefactorError::ControlFlowIssue(_, ranges)) if ranges == vec![Range::from_coords(1, 18, 1, 24)]
+        ));
+        let consequence = make_ctx(source, Range::from_coords(1, 27, 1, 39), LanguageId::JavaScript);
+        assert!(matches!(can_extract(&consequence), Err(RefactorError::ControlFlowIssue(..))));
+
+        // The condition and the whole conditional are evaluated either way
+        let condition = make_ctx(source, Range::from_coords(1, 18, 1, 24), LanguageId::JavaScript);
+        assert!(can_extract(&condition).is_ok());
+        let whole = make_ctx(source, Range::from_coords(1, 18, 1, 53), LanguageId::JavaScript);
+        assert!(can_extract(&whole).is_ok());
+
+        let source = "def f(cached):\n    value = cached.value if cached else expensive()\n    return value\n";
+        let branch = make_ctx(source, Range::from_coords(1, 40, 1, 51), LanguageId::Python);
+        assert_eq!(branch.selected_text(), "expensive()");
+        assert!(matches!(
+            extract(&branch, "computed", true),
+            Err(RefactorError::ControlFlowIssue(_, ranges)) if ranges == vec![Range::from_coords(1, 28, 1, 34)]
+        ));
+    }
+
+    #[test]
+    fn test_comment_only_selections() {
+        let no_expression = |source: &str, selection: Range, language: LanguageId| {
+            let ctx = make_ctx(source, selection, language);
+            matches!(can_extract(&ctx), Err(RefactorError::NoExpression(message)) if message.contains("comment"))
+        };
+
+        let source = "function f() {\n    // total of a and b\n    /* the sum,\n       doubled */\n    return (a + b) * 2;\n}\n";
+        assert!(no_expression(source, Range::from_coords(1, 4, 1, 23), LanguageId::JavaScript));
+        // Inside a block comment, and a fragment running into its end
+        assert!(no_expression(source, Range::from_coords(2, 7, 2, 14), LanguageId::JavaScript));
+        assert!(no_expression(source, Range::from_coords(2, 11, 3, 17), LanguageId::JavaScript));
+        assert!(can_extract(&make_ctx(source, Range::from_coords(4, 11, 4, 18), LanguageId::JavaScript)).is_ok());
+
+        let source = "def f(a, b):\n    # total of a and b\n    # before doubling\n    return (a + b) * 2\n";
+        assert!(no_expression(source, Range::from_coords(1, 4, 1, 22), LanguageId::Python));
+        assert!(no_expression(source, Range::from_coords(1, 10, 2, 12), LanguageId::Python));
+
+        let blank = make_ctx(source, Range::from_coords(0, 12, 1, 4), LanguageId::Python);
+        assert!(matches!(can_extract(&blank), Err(RefactorError::NoExpression(message)) if message.contains("whitespace")));
+    }
+
+    #[test]
+    fn test_extract_into_existing_variable() {
+        let source = "function f(items) {\n    let result;\n    log(items.length * 2);\n    return result;\n}\nfunction g(items) {\n    const total = 0;\n    log(items.length * 2, total);\n}\n";
+        let ctx = make_ctx(source, Range::from_coords(2, 8, 2, 24), LanguageId::JavaScript);
+        assert_eq!(ctx.selected_text(), "items.length * 2");
+
+        let result = extract_into_existing(&ctx, "result").unwrap();
+        assert_eq!(
+            crate::apply_edits(source, &result.edits),
+            "function f(items) {\n    let result;\n    result = items.length * 2;\n    log(result);\n    return result;\n}\nfunction g(items) {\n    const total = 0;\n    log(items.length * 2, total);\n}\n"
+        );
+        assert!(matches!(extract_into_existing(&ctx, "missing"), Err(RefactorError::CannotExtract(_))));
+
+        // Declared in another function, a constant, or used by the statement too
+        let other = make_ctx(source, Range::from_coords(7, 8, 7, 24), LanguageId::JavaScript);
+        assert!(matches!(extract_into_existing(&other, "result"), Err(RefactorError::CannotExtract(_))));
+        assert!(matches!(extract_into_existing(&other, "total"), Err(RefactorError::CannotExtract(_))));
+        let source = "function g(items) {\n    let total = 0;\n    log(items.length * 2, total);\n}\n";
+        let ctx = make_ctx(source, Range::from_coords(2, 8, 2, 24), LanguageId::JavaScript);
+        assert!(matches!(
+            extract_into_existing(&ctx, "total"),
+            Err(RefactorError::ControlFlowIssue(_, ranges)) if ranges == vec![Range::from_coords(2, 26, 2, 31)]
+        ));
+
+        let source = "fn f(items: &[u32]) -> usize {\n    let result;\n    log(items.len() * 2);\n    result\n}\n";
+        let ctx = make_ctx(source, Range::from_coords(2, 8, 2, 23), LanguageId::Rust);
+        let result = extract_into_existing(&ctx, "result").unwrap();
+        assert!(crate::apply_edits(source, &result.edits).contains("    result = items.len() * 2;\n    log(result);\n"));
+
+        // A file without semicolons gets none
+        let source = "function f(items) {\n  let result\n  log(items.length * 2)\n  return result\n}\n";
+        let ctx = make_ctx(source, Range::from_coords(2, 6, 2, 22), LanguageId::JavaScript);
+        let result = extract_into_existing(&ctx, "result").unwrap();
+        assert!(crate::apply_edits(source, &result.edits).contains("  result = items.length * 2\n  log(result)\n"));
+    }
+
+    #[test]
+    fn test_extract_at_chosen_ranges() {
+        let source = "function f(items) {\n    log(items.length);\n    if (items.length > 2) {\n        return items.length * 2;\n    }\n    return items.length;\n}\n";
+        let ranges = [
+            Range::from_coords(3, 15, 3, 27),
+            Range::from_coords(1, 8, 1, 20),
+            Range::from_coords(2, 8, 2, 20),
+        ];
+        let ctx = make_ctx(source, ranges[0], LanguageId::JavaScript);
+
+        // The last `items.length` was not chosen and stays
+        let result = extract_at(&ctx, &ranges, "count").unwrap();
+        assert_eq!(
+            crate::apply_edits(source, &result.edits),
+            "function f(items) {\n    const count = items.length;\n    log(count);\n    if (count > 2) {\n        return count * 2;\n    }\n    return items.length;\n}\n"
+        );
+
+        let mismatched = [ranges[1], Range::from_coords(2, 8, 2, 24)];
+        assert!(matches!(
+            extract_at(&ctx, &mismatched, "count"),
+            Err(RefactorError::InvalidSelection(_))
+        ));
+        // A declaration inside the `if` would not reach the last line
+        let outside = [ranges[0], Range::from_coords(5, 11, 5, 23)];
+        assert!(matches!(extract_at(&ctx, &outside, "count"), Err(RefactorError::CannotExtract(_))));
+    }
+
+    #[test]
+    fn test_extract_indented_statement() {
+        let source = "function f() {\n    console.log(a +\n        b);\n}\n";
+        let selection = Range::from_coords(1, 16, 2, 9); // "a +\n        b"
+        let ctx = make_ctx(source, selection, LanguageId::JavaScript);
+
+        let result = extract(&ctx, "sum", false).unwrap();
+        assert_eq!(
+            crate::apply_edits(source, &result.edits),
+            "function f() {\n    const sum = a +\n        b;\n    console.log(sum);\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_extract_keeps_trailing_comment() {
+        let source = "const y = a + b; // important\n";
+        // Selection runs past the expression into the comment
+        let selection = Range::from_coords(0, 10, 0, 29);
+        let ctx = make_ctx(source, selection, LanguageId::JavaScript);
+
+        let result = extract(&ctx, "sum", false).unwrap();
+        assert_eq!(
+            crate::apply_edits(source, &result.edits),
+            "const sum = a + b;\nconst y = sum; // important\n"
+        );
+    }
+
+    #[test]
+    fn test_extract_keeps_trailing_hash_comment() {
+        let source = "total = price * qty  # before tax\n";
+        let selection = Range::from_coords(0, 8, 0, 33);
+        let ctx = make_ctx(source, selection, LanguageId::Python);
+
+        let result = extract(&ctx, "subtotal", false).unwrap();
+        assert_eq!(result.generated_code.as_deref(), Some("subtotal = price * qty\n"));
+        assert_eq!(
+            crate::apply_edits(source, &result.edits),
+            "subtotal = price * qty\ntotal = subtotal  # before tax\n"
+        );
+    }
+
+    #[test]
+    fn test_comment_marker_in_string_is_kept() {
+        let source = "const u = \"http://x\" + path; // fetch\n";
+        let selection = Range::from_coords(0, 10, 0, 37);
+        let ctx = make_ctx(source, selection, LanguageId::JavaScript);
+
+        assert_eq!(expression_context(&ctx).unwrap().selected_text(), "\"http://x\" + path");
+    }
+
     #[test]
     fn test_extract_python() {
         let source = "print(x * 2)";
         let selection = Range::from_coords(0, 6, 0, 11); // "x * 2"
         let ctx = make_ctx(source, selection, LanguageId::Python);
 
-        let result = extract(&ctx, "doubled").unwrap();
+        let result = extract(&ctx, "doubled", false).unwrap();
 
         // Check that we have the right declaration format
         let declaration = result.generated_code.unwrap();
         assert!(declaration.contains("doubled = x * 2"));
     }
 
+    fn extracted(source: &str, selection: Range, language: LanguageId) -> String {
+        let ctx = make_ctx(source, selection, language);
+        let result = extract(&ctx, "v", true).unwrap();
+        crate::apply_edits(source, &result.edits)
+    }
+
+    #[test]
+    fn test_whole_statement_keeps_terminator_js() {
+        // Selected with its terminator
+        let source = "function f() {\n  g(a + b);\n}\n";
+        assert_eq!(
+            extracted(source, Range::from_coords(1, 2, 1, 11), LanguageId::JavaScript),
+            "function f() {\n  const v = g(a + b);\n  v;\n}\n"
+        );
+
+        // The last statement of the block without one
+        let source = "function f() {\n  let x = 1;\n  g(a + b)\n}\n";
+        assert_eq!(
+            extracted(source, Range::from_coords(2, 2, 2, 10), LanguageId::JavaScript),
+            "function f() {\n  let x = 1;\n  const v = g(a + b);\n  v;\n}\n"
+        );
+
+        // Semicolon-free files stay that way
+        let source = "function f() {\n  g(a + b)\n}\n";
+        assert_eq!(
+            extracted(source, Range::from_coords(1, 2, 1, 10), LanguageId::JavaScript),
+            "function f() {\n  const v = g(a + b)\n  v\n}\n"
+        );
+
+        // ... and so do the file's quotes
+        let source = "const keys = ['x', 'y', 'z']\nfunction f() {\n  g(\"a\" + b)\n}\n";
+        assert_eq!(
+            extracted(source, Range::from_coords(2, 2, 2, 14), LanguageId::JavaScript),
+            "const keys = ['x', 'y', 'z']\nfunction f() {\n  const v = g('a' + b)\n  v\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_whole_statement_keeps_terminator_c() {
+        let source = "int f() {\n  g(a + b); // call\n  return 0;\n}\n";
+        assert_eq!(
+            extracted(source, Range::from_coords(1, 2, 1, 19), LanguageId::C),
+            "int f() {\n  auto v = g(a + b);\n  v; // call\n  return 0;\n}\n"
+        );
+
+        // A statement continuing on the next line gets no terminator
+        let source = "int f() {\n  y = g(a + b)\n    + 1;\n}\n";
+        assert_eq!(
+            extracted(source, Range::from_coords(1, 6, 1, 14), LanguageId::C),
+            "int f() {\n  auto v = g(a + b);\n  y = v\n    + 1;\n}\n"
+        );
+    }
+
+    fn assert_parses(source: &str, language: LanguageId) {
+        let mut parser = logos_parser::LanguageParser::new();
+        parser.set_language(language).unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        assert!(!tree.root_node().has_error(), "does not parse:\n{}", source);
+    }
+
+    #[test]
+    fn test_extract_multiline_method_chain() {
+        let source = "function f() {\n  render(\n    items\n      .filter(x => x.ok)\n      .map(x => x.id),\n  );\n}\n";
+        let result = extracted(source, Range::from_coords(2, 4, 4, 21), LanguageId::JavaScript);
+        assert_eq!(
+            result,
+            "function f() {\n  const v = items\n    .filter(x => x.ok)\n    .map(x => x.id);\n  render(\n    v,\n  );\n}\n"
+        );
+        assert_parses(&result, LanguageId::JavaScript);
+    }
+
+    #[test]
+    fn test_extract_multiline_array_keeps_string_content() {
+        let source = "function f() {\n  use(\n    [\n      `first\n      second`,\n      2,\n    ],\n  );\n}\n";
+        let result = extracted(source, Range::from_coords(2, 4, 6, 5), LanguageId::JavaScript);
+        assert_eq!(
+            result,
+            "function f() {\n  const v = [\n    `first\n      second`,\n    2,\n  ];\n  use(\n    v,\n  );\n}\n"
+        );
+        assert_parses(&result, LanguageId::JavaScript);
+
+    }
+
     #[test]
     fn test_generate_declaration() {
         assert_eq!(
-            generate_declaration("x", "1 + 2", LanguageId::JavaScript, "  "),
+            generate_declaration("x", "1 + 2", LanguageId::JavaScript, "  ", false),
             "  const x = 1 + 2;\n"
         );
         assert_eq!(
-            generate_declaration("x", "1 + 2", LanguageId::Python, "    "),
+            generate_declaration("x", "1 + 2", LanguageId::Python, "    ", false),
             "    x = 1 + 2\n"
         );
         assert_eq!(
-            generate_declaration("x", "1 + 2", LanguageId::Rust, ""),
+            generate_declaration("x", "1 + 2", LanguageId::Rust, "", false),
             "let x = 1 + 2;\n"
         );
         assert_eq!(
-            generate_declaration("x", "1 + 2", LanguageId::Go, "\t"),
+            generate_declaration("x", "1 + 2", LanguageId::Go, "\t", false),
             "\tx := 1 + 2\n"
         );
+        assert_eq!(
+            generate_declaration("X", "1 + 2", LanguageId::Go, "\t", true),
+            "\tconst X = 1 + 2\n"
+        );
+        assert_eq!(
+            generate_declaration("X", "1 + 2", LanguageId::Cpp, "", true),
+            "const auto X = 1 + 2;\n"
+        );
+    }
+
+    #[test]
+    fn test_naming_overrides() {
+        use crate::naming::{Case, NamingConfig};
+
+        let source = "function f(user) {\n    return user.getName().trim();\n}\n";
+        let selection = Range::from_coords(1, 11, 1, 25); // "user.getName()"
+        let naming = NamingConfig {
+            variable: Some(Case::Pascal),
+            ..Default::default()
+        };
+        let ctx = make_ctx(source, selection, LanguageId::JavaScript).with_naming(naming);
+        let (name, result) = extract_with_suggestion(&ctx).unwrap();
+        assert_eq!(name, "GetName");
+        assert_eq!(
+            crate::apply_edits(source, &result.edits),
+            "function f(user) {\n    const GetName = user.getName();\n    return GetName.trim();\n}\n"
+        );
+
+        let source = "def wait():\n    sleep(60 * 1000)\n";
+        let selection = Range::from_coords(1, 10, 1, 19); // "60 * 1000"
+        let naming = NamingConfig {
+            constant: Some(Case::ScreamingSnake),
+            ..Default::default()
+        };
+        let ctx = make_ctx(source, selection, LanguageId::Python).with_naming(naming);
+        let (name, result) = extract_constant_with_suggestion(&ctx).unwrap();
+        assert_eq!(name, "EXTRACTED");
+        assert_eq!(
+            crate::apply_edits(source, &result.edits),
+            "def wait():\n    EXTRACTED = 60 * 1000\n    sleep(EXTRACTED)\n"
+        );
+        // A name given explicitly is used as written
+        let result = extract_constant(&ctx, "delayMs").unwrap();
+        assert!(result.generated_code.as_deref().unwrap().contains("delayMs = 60 * 1000"));
+
+        // Keyword collisions get a suffix in the same casing
+        assert_eq!(suggest_name("obj.none", LanguageId::Python, Some(Case::Pascal)), "NoneValue");
+    }
+
+    #[test]
+    fn test_extract_constant_refusals() {
+        let source = "function f(n) {\n    return n * 60;\n}\n";
+        let ctx = make_ctx(source, Range::from_coords(1, 11, 1, 17), LanguageId::JavaScript);
+        assert!(matches!(extract_constant(&ctx, "MINUTES"), Err(RefactorError::CannotExtract(_))));
+
+        let source = "fn f() -> u32 {\n    60 * 60\n}\n";
+        let ctx = make_ctx(source, Range::from_coords(1, 4, 1, 11), LanguageId::Rust);
+        assert!(matches!(extract_constant(&ctx, "HOUR"), Err(RefactorError::Unsupported(_))));
     }
 }
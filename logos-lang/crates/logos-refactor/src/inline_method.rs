@@ -0,0 +1,766 @@
+//! Inline Method Refactoring
+//!
+//! The inverse of Extract Method: calls are replaced with the body of the
+//! function, arguments substituted for parameters.
+//! - A body that is a single `return expr` (or a Rust tail expression) is
+//!   inlined as the expression, wherever the call is
+//! - A body without `return` is inlined as its statements, for calls that
+//!   are statements of their own
+//!
+//! Invoked on a call, that call is inlined; invoked on the definition, every
+//! call in the document is. Once no call is left the definition is deleted
+//! like Safe Delete would. Recursive functions, several returns, default or
+//! rest parameters, and arguments with side effects that would run a
+//! different number of times are refused. So are bodies that assign a
+//! parameter, declare a name the caller already uses, or read a name the
+//! caller binds to something else.
+
+use crate::analysis::{function_around, has_side_effects};
+use crate::rename::{declared_names, find_occurrences};
+use crate::safe_delete::deletion_range;
+use crate::{apply_edits, offset_to_position, RefactorContext, RefactorError, RefactorResult, TextEdit};
+use logos_core::Range;
+use logos_parser::{LanguageId, LanguageParser};
+use std::collections::HashMap;
+use tree_sitter::Node;
+
+/// What a function's body inlines as
+enum Body<'t> {
+    /// The returned expression
+    Expression(Node<'t>),
+    /// Statements, none of them a `return`
+    Statements(Vec<Node<'t>>),
+}
+
+/// A function definition, ready to inline
+struct Definition<'t> {
+    node: Node<'t>,
+    name: Node<'t>,
+    parameters: Vec<String>,
+    body: Body<'t>,
+    uses_receiver: bool,
+}
+
+/// Whether the call or definition at the selection can be inlined; `Ok(false)`
+/// when the cursor is on neither, so the action is not offered at all
+pub fn can_inline(ctx: &RefactorContext) -> Result<bool, RefactorError> {
+    let mut parser = LanguageParser::new();
+    parser
+        .set_language(ctx.language)
+        .map_err(|e| RefactorError::ParseError(e.to_string()))?;
+    let tree = parser
+        .parse(ctx.source, None)
+        .map_err(|e| RefactorError::ParseError(e.to_string()))?;
+    let offset = ctx.selection_bytes.start;
+    let Some(node) = tree.root_node().descendant_for_byte_range(offset, offset) else {
+        return Ok(false);
+    };
+    let on_definition = enclosing(node, is_definition)
+        .is_some_and(|d| definition_name(d).is_some_and(|n| n.byte_range().contains(&offset)));
+    let on_call = enclosing(node, |n| is_call(n) && callee_name(n).is_some()).is_some();
+    if !on_definition && !on_call {
+        return Ok(false);
+    }
+    inline(ctx).map(|_| true)
+}
+
+/// Inline the call at the selection, or every call of the function whose
+/// definition is at the selection
+pub fn inline(ctx: &RefactorContext) -> Result<RefactorResult, RefactorError> {
+    let mut parser = LanguageParser::new();
+    parser
+        .set_language(ctx.language)
+        .map_err(|e| RefactorError::ParseError(e.to_string()))?;
+    let tree = parser
+        .parse(ctx.source, None)
+        .map_err(|e| RefactorError::ParseError(e.to_string()))?;
+    let offset = ctx.selection_bytes.start;
+    let node = tree
+        .root_node()
+        .descendant_for_byte_range(offset, offset)
+        .ok_or_else(|| RefactorError::InvalidSelection("No call or function at the cursor".to_string()))?;
+
+    let (name, calls) = match enclosing(node, is_definition).filter(|d| definition_name(*d).is_some_and(|n| n.byte_range().contains(&offset))) {
+        Some(definition) => {
+            let name = text(ctx.source, definition_name(definition).unwrap_or(definition));
+            let mut calls = Vec::new();
+            collect_calls(tree.root_node(), ctx.source, name, &mut calls);
+            calls.retain(|call| !within(*call, definition));
+            (name, calls)
+        }
+        None => {
+            let call = enclosing(node, |n| is_call(n) && callee_name(n).is_some())
+                .ok_or_else(|| RefactorError::InvalidSelection("No call or function at the cursor".to_string()))?;
+            let name = text(ctx.source, callee_name(call).unwrap_or(call));
+            (name, vec![call])
+        }
+    };
+    if calls.is_empty() {
+        return Err(RefactorError::InvalidSelection(format!("'{}' is never called", name)));
+    }
+
+    let definition = find_definition(tree.root_node(), ctx.source, name, ctx.language)?;
+    let mut edits = Vec::new();
+    for call in &calls {
+        edits.push(inline_call(ctx, &definition, *call)?);
+    }
+
+    // Delete the definition once nothing mentions it
+    let inlined = apply_edits(ctx.source, &TextEdit::normalize(edits.clone())?);
+    let definition_range = node_range(ctx.source, definition.node);
+    let own = find_occurrences(ctx.source, name, Some(ctx.language))
+        .into_iter()
+        .filter(|r| definition_range.contains_range(r))
+        .count();
+    let deleted = find_occurrences(&inlined, name, Some(ctx.language)).len() == own;
+    if deleted {
+        let selection = node_range(ctx.source, definition.name);
+        let definition_ctx = RefactorContext::new(ctx.source, ctx.uri, selection, ctx.language);
+        let mut range = deletion_range(&definition_ctx);
+        // Take the blank lines separating it from the next code along
+        let lines: Vec<&str> = ctx.source.split('\n').collect();
+        while range.end.column == 0
+            && (range.end.line as usize) + 1 < lines.len()
+            && lines[range.end.line as usize].trim().is_empty()
+        {
+            range.end.line += 1;
+        }
+        edits.push(TextEdit::delete(range));
+    }
+
+    let description = match (calls.len(), deleted) {
+        (1, true) => format!("Inline '{}' and remove it", name),
+        (1, false) => format!("Inline call of '{}'", name),
+        (n, true) => format!("Inline {} calls of '{}' and remove it", n, name),
+        (n, false) => format!("Inline {} calls of '{}'", n, name),
+    };
+    Ok(RefactorResult::new(TextEdit::normalize(edits)?, description))
+}
+
+/// The edit replacing `call` with the body of `definition`
+fn inline_call(ctx: &RefactorContext, definition: &Definition, call: Node) -> Result<TextEdit, RefactorError> {
+    let source = ctx.source;
+    let arguments = call_arguments(call)?;
+    if arguments.len() != definition.parameters.len() {
+        return Err(RefactorError::Unsupported(format!(
+            "The call passes {} arguments for {} parameters",
+            arguments.len(),
+            definition.parameters.len()
+        )));
+    }
+    if definition.uses_receiver && !through_receiver(call, source) {
+        return Err(RefactorError::Unsupported(
+            "The body uses 'this'/'self', which the call site does not have".to_string(),
+        ));
+    }
+    check_names(ctx, definition, call)?;
+    let arguments: HashMap<&str, Node> = definition
+        .parameters
+        .iter()
+        .map(String::as_str)
+        .zip(arguments)
+        .collect();
+
+    let (start, end, statement) = match &definition.body {
+        Body::Expression(expression) => (expression.start_byte(), expression.end_byte(), None),
+        Body::Statements(statements) => {
+            let statement = call
+                .parent()
+                .filter(|p| p.kind() == "expression_statement" || (p.kind() == "block" && ctx.language == LanguageId::Rust))
+                .ok_or_else(|| {
+                    RefactorError::Unsupported("Only calls that are statements can inline a body without 'return'".to_string())
+                })?;
+            match (statements.first(), statements.last()) {
+                (Some(first), Some(last)) => (first.start_byte(), last.end_byte(), Some(statement)),
+                // An empty body: the call goes away
+                _ => (0, 0, Some(statement)),
+            }
+        }
+    };
+
+    // Every parameter use is replaced by its argument
+    let mut uses: Vec<(usize, usize, &str)> = Vec::new();
+    collect_identifiers(definition.node, source, start, end, &mut uses);
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    uses.retain(|(_, _, name)| arguments.contains_key(name));
+    for (_, _, name) in &uses {
+        *counts.entry(name).or_insert(0) += 1;
+    }
+    for (name, argument) in &arguments {
+        let count = counts.get(name).copied().unwrap_or(0);
+        if count != 1 && !is_pure(*argument, source, ctx.language) {
+            return Err(RefactorError::HasSideEffects);
+        }
+    }
+
+    let mut body = String::new();
+    let mut last = start;
+    for (s, e, name) in &uses {
+        body.push_str(&source[last..*s]);
+        let argument = arguments[name];
+        let argument_text = text(source, argument);
+        if is_simple(argument) {
+            body.push_str(argument_text);
+        } else {
+            body.push_str(&format!("({})", argument_text));
+        }
+        last = *e;
+    }
+    body.push_str(&source[last..end]);
+
+    match statement {
+        None => {
+            let expression = match &definition.body {
+                Body::Expression(expression) if !is_simple(*expression) && needs_parentheses(call) => format!("({})", body),
+                _ => body,
+            };
+            Ok(TextEdit::replace(node_range(source, call), expression))
+        }
+        Some(statement) => {
+            let indent = line_indent(source, statement.start_byte());
+            let body_indent = line_indent(source, start);
+            let lines: Vec<String> = body
+                .split('\n')
+                .enumerate()
+                .map(|(i, line)| match i {
+                    0 => line.to_string(),
+                    _ => format!("{}{}", indent, line.strip_prefix(body_indent).unwrap_or(line.trim_start())),
+                })
+                .collect();
+            let (range, text) = if body.is_empty() {
+                // Remove the whole line of the call
+                let line_start = source[..statement.start_byte()].rfind('\n').map(|i| i + 1).unwrap_or(0);
+                let line_end = source[statement.end_byte()..]
+                    .find('\n')
+                    .map(|i| statement.end_byte() + i + 1)
+                    .unwrap_or(source.len());
+                (Range::new(offset_to_position(source, line_start), offset_to_position(source, line_end)), String::new())
+            } else {
+                (node_range(source, statement), lines.join("\n"))
+            };
+            Ok(TextEdit::replace(range, text))
+        }
+    }
+}
+
+/// Refuse a body whose names would mean something else at `call`: a
+/// parameter assigned in the body would assign the argument, a local would
+/// clash with a name of the caller, and a free name would be captured by a
+/// binding of the caller
+fn check_names(ctx: &RefactorContext, definition: &Definition, call: Node) -> Result<(), RefactorError> {
+    let source = ctx.source;
+    let name = text(source, definition.name);
+    let body = match definition.node.child_by_field_name("body") {
+        Some(body) => body,
+        None => return Ok(()),
+    };
+
+    let mut assigned = Vec::new();
+    collect_assigned(body, source, &mut assigned);
+    if let Some(parameter) = definition.parameters.iter().find(|p| assigned.contains(&p.as_str())) {
+        return Err(RefactorError::Unsupported(format!(
+            "'{}' assigns its parameter '{}'; inlined, that would assign the argument",
+            name, parameter
+        )));
+    }
+
+    let mut root = call;
+    while let Some(parent) = root.parent() {
+        root = parent;
+    }
+    let scope = function_around(root, call.start_byte(), call.end_byte()).unwrap_or(root);
+    let mut caller = Vec::new();
+    collect_identifiers(scope, source, scope.start_byte(), scope.end_byte(), &mut caller);
+    caller.retain(|&(start, end, _)| !(definition.node.start_byte() <= start && end <= definition.node.end_byte()));
+
+    let locals = declared_names(body, source, ctx.language);
+    if let Body::Statements(_) = definition.body {
+        if let Some(local) = locals.iter().find(|local| caller.iter().any(|(_, _, n)| n == *local)) {
+            return Err(RefactorError::Unsupported(format!(
+                "'{}' declares '{}', which is already used where it is called",
+                name, local
+            )));
+        }
+    }
+
+    // A caller around the definition sees the same names
+    if !within(definition.node, scope) {
+        let bound = declared_names(scope, source, ctx.language);
+        let mut uses = Vec::new();
+        collect_identifiers(body, source, body.start_byte(), body.end_byte(), &mut uses);
+        let captured = uses.iter().map(|(_, _, n)| *n).find(|n| {
+            !definition.parameters.iter().any(|p| p == n)
+                && !locals.contains(n)
+                && !matches!(*n, "self" | "this")
+                && bound.contains(n)
+        });
+        if let Some(captured) = captured {
+            return Err(RefactorError::Unsupported(format!(
+                "'{}' reads '{}', which is bound to something else where it is called",
+                name, captured
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Names assigned below `node`, also by `++`/`--`
+fn collect_assigned<'s>(node: Node, source: &'s str, found: &mut Vec<&'s str>) {
+    let is_assignment = matches!(
+        node.kind(),
+        "assignment_expression"
+            | "augmented_assignment_expression"
+            | "assignment"
+            | "augmented_assignment"
+            | "compound_assignment_expr"
+            | "assignment_statement"
+            | "update_expression"
+            | "inc_statement"
+            | "dec_statement"
+    );
+    if is_assignment {
+        let target = node
+            .child_by_field_name("left")
+            .or_else(|| node.child_by_field_name("argument"))
+            .or_else(|| node.named_child(0));
+        if let Some(target) = target {
+            let mut targets = vec![target];
+            let mut cursor = target.walk();
+            if matches!(target.kind(), "expression_list" | "pattern_list" | "tuple_pattern") {
+                targets = target.named_children(&mut cursor).collect();
+            }
+            found.extend(targets.into_iter().filter(|t| t.kind() == "identifier").map(|t| text(source, t)));
+        }
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_assigned(child, source, found);
+    }
+}
+
+/// The single definition of the function `name`
+fn find_definition<'t>(root: Node<'t>, source: &str, name: &str, language: LanguageId) -> Result<Definition<'t>, RefactorError> {
+    let mut found = Vec::new();
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        if is_definition(node) && definition_name(node).is_some_and(|n| text(source, n) == name) {
+            found.push(node);
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.children(&mut cursor));
+    }
+    let node = match found.as_slice() {
+        [node] => *node,
+        [] => return Err(RefactorError::InvalidSelection(format!("No definition of '{}' in this document", name))),
+        _ => return Err(RefactorError::Unsupported(format!("'{}' is defined more than once", name))),
+    };
+    let name_node = definition_name(node).unwrap_or(node);
+    let body_node = node
+        .child_by_field_name("body")
+        .ok_or_else(|| RefactorError::Unsupported(format!("'{}' has no body", name)))?;
+
+    // Recursion cannot be inlined
+    let mut mentions = Vec::new();
+    collect_identifiers(body_node, source, body_node.start_byte(), body_node.end_byte(), &mut mentions);
+    if mentions.iter().any(|(_, _, n)| *n == name) {
+        return Err(RefactorError::Unsupported(format!("'{}' is recursive", name)));
+    }
+
+    let parameters = parameter_names(node, source)?;
+    let mut cursor = body_node.walk();
+    let statements: Vec<Node> = body_node
+        .named_children(&mut cursor)
+        .filter(|n| !n.kind().contains("comment"))
+        .collect();
+    let returns = count_returns(body_node);
+    let body = match (statements.as_slice(), returns) {
+        ([statement], 1) if is_return(*statement) => match statement.named_child(0) {
+            Some(expression) => Body::Expression(expression),
+            None => Body::Statements(Vec::new()),
+        },
+        // Rust: a lone tail expression
+        ([expression], 0) if language == LanguageId::Rust && !expression.kind().ends_with("statement") && !expression.kind().ends_with("declaration") => {
+            Body::Expression(*expression)
+        }
+        (_, 0) => Body::Statements(statements),
+        _ => {
+            return Err(RefactorError::Unsupported(format!(
+                "'{}' returns from more than a single 'return' statement",
+                name
+            )))
+        }
+    };
+
+    Ok(Definition {
+        node,
+        name: name_node,
+        parameters,
+        body,
+        uses_receiver: uses_receiver(body_node, source),
+    })
+}
+
+pub(crate) fn is_definition(node: Node) -> bool {
+    matches!(
+        node.kind(),
+        "function_declaration" | "method_definition" | "function_definition" | "function_item" | "method_declaration"
+    )
+}
+
+/// The name of a function definition, also through C declarators
+pub(crate) fn definition_name(node: Node) -> Option<Node> {
+    if let Some(name) = node.child_by_field_name("name") {
+        return Some(name);
+    }
+    let mut declarator = node.child_by_field_name("declarator")?;
+    while let Some(inner) = declarator.child_by_field_name("declarator") {
+        declarator = inner;
+    }
+    Some(declarator)
+}
+
+pub(crate) fn parameter_names(definition: Node, source: &str) -> Result<Vec<String>, RefactorError> {
+    let list = definition.child_by_field_name("parameters").or_else(|| {
+        // C: the parameters belong to the function declarator
+        let mut declarator = definition.child_by_field_name("declarator")?;
+        loop {
+            if let Some(parameters) = declarator.child_by_field_name("parameters") {
+                return Some(parameters);
+            }
+            declarator = declarator.child_by_field_name("declarator")?;
+        }
+    });
+    let list = match list {
+        Some(list) => list,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut names = Vec::new();
+    let mut cursor = list.walk();
+    for parameter in list.named_children(&mut cursor) {
+        let kind = parameter.kind();
+        if kind.contains("comment") || kind == "self_parameter" || kind == "this" {
+            continue;
+        }
+        if ["default", "rest", "splat", "variadic", "optional", "spread"].iter().any(|k| kind.contains(k)) {
+            return Err(RefactorError::Unsupported(
+                "Functions with default, optional or rest parameters cannot be inlined".to_string(),
+            ));
+        }
+        let mut identifiers = Vec::new();
+        match kind {
+            "identifier" => identifiers.push(parameter),
+            _ => {
+                // The name(s) of typed parameters: `a: int`, `int a`, `a, b int`
+                let mut fields = parameter.walk();
+                let named: Vec<Node> = ["pattern", "name", "declarator"]
+                    .iter()
+                    .flat_map(|field| parameter.children_by_field_name(field, &mut fields).collect::<Vec<_>>())
+                    .collect();
+                let candidates = if named.is_empty() {
+                    let mut children = parameter.walk();
+                    parameter.named_children(&mut children).take(1).collect()
+                } else {
+                    named
+                };
+                for candidate in candidates {
+                    match first_identifier(candidate) {
+                        Some(identifier) => identifiers.push(identifier),
+                        None => {
+                            return Err(RefactorError::Unsupported(
+                                "Functions with destructured parameters cannot be inlined".to_string(),
+                            ))
+                        }
+                    }
+                }
+            }
+        }
+        for identifier in identifiers {
+            let name = text(source, identifier);
+            if name != "self" {
+                names.push(name.to_string());
+            }
+        }
+    }
+    Ok(names)
+}
+
+fn first_identifier(node: Node) -> Option<Node> {
+    if node.kind() == "identifier" {
+        return Some(node);
+    }
+    if matches!(node.kind(), "object_pattern" | "array_pattern" | "tuple_pattern" | "list_pattern") {
+        return None;
+    }
+    let mut cursor = node.walk();
+    let children: Vec<Node> = node.named_children(&mut cursor).collect();
+    children.into_iter().find_map(first_identifier)
+}
+
+pub(crate) fn is_call(node: Node) -> bool {
+    matches!(node.kind(), "call_expression" | "call" | "method_invocation")
+}
+
+/// The name of the called function: `f` in `f(x)`, `this.f(x)`, `self.f(x)`
+pub(crate) fn callee_name(call: Node) -> Option<Node> {
+    if call.kind() == "method_invocation" {
+        return call.child_by_field_name("name");
+    }
+    let function = call.child_by_field_name("function")?;
+    match function.kind() {
+        "identifier" => Some(function),
+        _ => ["property", "attribute", "field"]
+            .iter()
+            .find_map(|field| function.child_by_field_name(field)),
+    }
+}
+
+/// Whether the call goes through `this`/`self`, or calls a plain function
+fn through_receiver(call: Node, source: &str) -> bool {
+    let object = match call.kind() {
+        "method_invocation" => call.child_by_field_name("object"),
+        _ => call.child_by_field_name("function").and_then(|f| {
+            ["object", "value", "operand"].iter().find_map(|field| f.child_by_field_name(field))
+        }),
+    };
+    match object {
+        Some(object) => matches!(text(source, object), "this" | "self"),
+        None => true,
+    }
+}
+
+pub(crate) fn call_arguments(call: Node) -> Result<Vec<Node>, RefactorError> {
+    let list = match call.child_by_field_name("arguments") {
+        Some(list) => list,
+        None => return Ok(Vec::new()),
+    };
+    let mut cursor = list.walk();
+    let arguments: Vec<Node> = list
+        .named_children(&mut cursor)
+        .filter(|n| !n.kind().contains("comment"))
+        .collect();
+    if arguments
+        .iter()
+        .any(|a| a.kind().contains("spread") || a.kind().contains("splat") || a.kind() == "keyword_argument")
+    {
+        return Err(RefactorError::Unsupported(
+            "Spread and keyword arguments cannot be inlined".to_string(),
+        ));
+    }
+    Ok(arguments)
+}
+
+fn collect_calls<'t>(node: Node<'t>, source: &str, name: &str, calls: &mut Vec<Node<'t>>) {
+    if is_call(node) && callee_name(node).is_some_and(|n| text(source, n) == name) {
+        calls.push(node);
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_calls(child, source, name, calls);
+    }
+}
+
+/// Identifiers in `start..end` below `node`, as `(start, end, name)` in
+/// source order; property names (`obj.name`) are not included
+fn collect_identifiers<'s>(node: Node, source: &'s str, start: usize, end: usize, found: &mut Vec<(usize, usize, &'s str)>) {
+    if node.end_byte() <= start || node.start_byte() >= end {
+        return;
+    }
+    if node.kind() == "identifier" {
+        let is_attribute = node
+            .parent()
+            .is_some_and(|p| p.kind() == "attribute" && p.child_by_field_name("attribute") == Some(node));
+        if !is_attribute {
+            found.push((node.start_byte(), node.end_byte(), &source[node.byte_range()]));
+        }
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_identifiers(child, source, start, end, found);
+    }
+}
+
+fn is_return(node: Node) -> bool {
+    matches!(node.kind(), "return_statement" | "return_expression")
+        || (node.kind() == "expression_statement" && node.named_child(0).is_some_and(|n| n.kind() == "return_expression"))
+}
+
+/// `return`s of the function itself, not of nested functions
+fn count_returns(node: Node) -> usize {
+    let mut count = 0;
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        if is_function_like(child) {
+            continue;
+        }
+        if matches!(child.kind(), "return_statement" | "return_expression") {
+            count += 1;
+        }
+        count += count_returns(child);
+    }
+    count
+}
+
+fn uses_receiver(node: Node, source: &str) -> bool {
+    if node.kind() == "this" || (node.kind() == "identifier" && text(source, node) == "self") || node.kind() == "self" {
+        return true;
+    }
+    let mut cursor = node.walk();
+    let children: Vec<Node> = node.children(&mut cursor).collect();
+    children
+        .into_iter()
+        .filter(|c| !(is_function_like(*c) && c.kind() != "arrow_function"))
+        .any(|c| uses_receiver(c, source))
+}
+
+fn is_function_like(node: Node) -> bool {
+    is_definition(node)
+        || matches!(
+            node.kind(),
+            "function_expression" | "function" | "arrow_function" | "lambda" | "closure_expression" | "func_literal" | "lambda_expression"
+        )
+}
+
+/// Whether evaluating `node` more or less often than once is harmless
+fn is_pure(node: Node, source: &str, language: LanguageId) -> bool {
+    if is_call(node) || matches!(node.kind(), "new_expression" | "await_expression" | "yield_expression") {
+        return false;
+    }
+    if has_side_effects(text(source, node), language) {
+        return false;
+    }
+    let mut cursor = node.walk();
+    let children: Vec<Node> = node.named_children(&mut cursor).collect();
+    children.into_iter().all(|c| is_pure(c, source, language))
+}
+
+/// Whether `node` can replace a parameter without parentheses
+fn is_simple(node: Node) -> bool {
+    let kind = node.kind();
+    kind.contains("identifier")
+        || kind.contains("literal")
+        || kind.contains("string")
+        || kind.contains("number")
+        || is_call(node)
+        || matches!(
+            kind,
+            "integer" | "float" | "true" | "false" | "null" | "none" | "this" | "self" | "parenthesized_expression"
+                | "member_expression" | "attribute" | "field_expression" | "selector_expression" | "subscript_expression"
+                | "subscript" | "index_expression" | "array" | "list" | "object" | "dictionary"
+        )
+}
+
+/// Whether an expression replacing `call` needs parentheses to keep its meaning
+fn needs_parentheses(call: Node) -> bool {
+    let parent = match call.parent() {
+        Some(parent) => parent,
+        None => return false,
+    };
+    !matches!(
+        parent.kind(),
+        "expression_statement" | "return_statement" | "return_expression" | "arguments" | "argument_list"
+            | "variable_declarator" | "assignment" | "assignment_expression" | "let_declaration"
+            | "parenthesized_expression" | "expression_list" | "short_var_declaration" | "init_declarator"
+            | "local_variable_declaration" | "block" | "array" | "list" | "pair"
+    )
+}
+
+pub(crate) fn enclosing<'t>(node: Node<'t>, predicate: impl Fn(Node) -> bool) -> Option<Node<'t>> {
+    let mut current = Some(node);
+    while let Some(n) = current {
+        if predicate(n) {
+            return Some(n);
+        }
+        current = n.parent();
+    }
+    None
+}
+
+pub(crate) fn within(node: Node, outer: Node) -> bool {
+    outer.start_byte() <= node.start_byte() && node.end_byte() <= outer.end_byte()
+}
+
+pub(crate) fn text<'s>(source: &'s str, node: Node) -> &'s str {
+    &source[node.byte_range()]
+}
+
+pub(crate) fn node_range(source: &str, node: Node) -> Range {
+    Range::new(
+        offset_to_position(source, node.start_byte()),
+        offset_to_position(source, node.end_byte()),
+    )
+}
+
+fn line_indent(source: &str, offset: usize) -> &str {
+    let start = source[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line = &source[start..];
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inlined(source: &str, line: u32, column: u32, language: LanguageId) -> Result<String, RefactorError> {
+        let ctx = RefactorContext::new(source, "test", Range::from_coords(line, column, line, column), language);
+        Ok(apply_edits(source, &inline(&ctx)?.edits))
+    }
+
+    #[test]
+    fn test_inline_one_line_helper() {
+        let source = "function area(width, height) {\n  return width * height;\n}\n\nfunction report(box) {\n  const size = area(box.w, box.h + 1);\n  return size * 2;\n}\n";
+        assert_eq!(
+            inlined(source, 5, 16, LanguageId::JavaScript).unwrap(),
+            "function report(box) {\n  const size = box.w * (box.h + 1);\n  return size * 2;\n}\n"
+        );
+
+        // Python, invoked on the definition, inside a larger expression
+        let source = "def double(x):\n    return x + x\n\n\nprint(double(n) * 3)\n";
+        assert_eq!(inlined(source, 0, 5, LanguageId::Python).unwrap(), "print((n + n) * 3)\n");
+    }
+
+    #[test]
+    fn test_inline_statements_keeps_other_calls() {
+        let source = "function log(message) {\n  console.log(message);\n  count++;\n}\n\nfunction run() {\n  log(\"a\");\n  log(\"b\");\n}\n";
+        assert_eq!(
+            inlined(source, 6, 3, LanguageId::JavaScript).unwrap(),
+            "function log(message) {\n  console.log(message);\n  count++;\n}\n\nfunction run() {\n  console.log(\"a\");\n  count++;\n  log(\"b\");\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_inline_refuses_clashing_local() {
+        let source = "function f(a) {\n  const t = a * 2;\n  log(t);\n}\nfunction main() {\n  const t = 1;\n  f(t);\n}\n";
+        assert!(matches!(inlined(source, 6, 3, LanguageId::JavaScript), Err(RefactorError::Unsupported(_))));
+    }
+
+    #[test]
+    fn test_inline_refuses_assigned_parameter() {
+        let source = "function inc(a) {\n  a = a + 1;\n  log(a);\n}\nfunction main() {\n  let x = 1;\n  inc(x);\n  log(x);\n}\n";
+        assert!(matches!(inlined(source, 6, 3, LanguageId::JavaScript), Err(RefactorError::Unsupported(_))));
+    }
+
+    #[test]
+    fn test_inline_refuses_captured_free_variable() {
+        let source = "const n = 10;\nfunction addN(x) {\n  return x + n;\n}\nfunction g(n) {\n  return addN(n);\n}\n";
+        assert!(matches!(inlined(source, 5, 10, LanguageId::JavaScript), Err(RefactorError::Unsupported(_))));
+
+        // Called where `n` is the same binding, it inlines
+        let source = "const n = 10;\nfunction addN(x) {\n  return x + n;\n}\nconst m = addN(2);\n";
+        assert_eq!(inlined(source, 4, 11, LanguageId::JavaScript).unwrap(), "const n = 10;\nconst m = 2 + n;\n");
+    }
+
+    #[test]
+    fn test_inline_refusals() {
+        let recursive = "function fact(n) {\n  return n <= 1 ? 1 : n * fact(n - 1);\n}\nfact(5);\n";
+        assert!(matches!(inlined(recursive, 3, 1, LanguageId::JavaScript), Err(RefactorError::Unsupported(_))));
+
+        let two_returns = "function sign(n) {\n  if (n < 0) {\n    return -1;\n  }\n  return 1;\n}\nsign(x);\n";
+        assert!(matches!(inlined(two_returns, 6, 1, LanguageId::JavaScript), Err(RefactorError::Unsupported(_))));
+
+        // `next()` would run twice
+        let twice = "function square(n) {\n  return n * n;\n}\nsquare(next());\n";
+        assert!(matches!(inlined(twice, 3, 1, LanguageId::JavaScript), Err(RefactorError::HasSideEffects)));
+    }
+}
@@ -3,15 +3,45 @@
 //! This crate provides refactoring operations like:
 //! - Extract Variable: Extract a selected expression into a new variable
 //! - Extract Method: Extract selected code into a new function/method
+//! - Extract Constant: Extract an expression that reads no variables into a constant
+//! - Extract Parameter: Turn an expression into a parameter passed by every caller
+//! - Inline Method: Replace calls with the body of the function
 //! - Safe Delete: Safely delete symbols that are not used elsewhere
+//! - Rename: Rename an identifier across documents, with a preview
+//! - Move to File: Move a top-level declaration to another file, fixing imports
+//! - Organize Imports: Drop unused imports and sort the rest into groups
+//! - Pull Member Up: Move a C++ method or field from a derived class into its base
+//! - Surround With: Wrap selected lines in an if/try/for/while scaffold
+//! - Convert Declaration: Switch `var`/`let`/`const`, add or remove Python annotations
+//! - Convert Function: Switch JS/TS functions between declarations, arrows and methods
+//! - Convert String: Turn a string concatenation into a template literal or f-string
+//! - Normalize Style: Requote the strings and add or drop the semicolons of a whole document
+//! - Split Declaration: Split a C/C++/Java declaration from its initialization, or join them
 
 pub mod analysis;
+pub mod convert_declaration;
+pub mod convert_function;
+pub mod convert_string;
+pub mod diff;
 pub mod extract_method;
+pub mod extract_parameter;
 pub mod extract_variable;
+pub mod inline_method;
+pub mod move_member;
+pub mod move_symbol;
+pub mod naming;
+pub mod operators;
+pub mod organize_imports;
+pub mod rename;
 pub mod safe_delete;
+pub mod split_declaration;
+pub mod style;
+pub mod surround_with;
 
 use logos_core::{Location, Position, Range};
+pub use logos_core::position::{byte_to_column, column_to_byte};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use thiserror::Error;
 
 /// A text edit to be applied to a document
@@ -53,6 +83,57 @@ impl TextEdit {
             new_text: text,
         }
     }
+
+    /// Whether applying this edit changes nothing regardless of the document
+    pub fn is_noop(&self) -> bool {
+        self.range.is_empty() && self.new_text.is_empty()
+    }
+
+    /// Normalize a combined edit list so it can be applied in any order:
+    /// edits are sorted by position, duplicates and no-ops are dropped, and
+    /// edits that touch (one ends where the next starts, including insertions
+    /// at the same position) are merged in list order. Edits whose ranges
+    /// truly overlap are an error.
+    pub fn normalize(edits: Vec<TextEdit>) -> Result<Vec<TextEdit>, RefactorError> {
+        let mut edits: Vec<TextEdit> = edits.into_iter().filter(|e| !e.is_noop()).collect();
+        // Stable, so insertions at the same position keep their list order
+        edits.sort_by(|a, b| {
+            a.range
+                .start
+                .cmp(&b.range.start)
+                .then_with(|| a.range.end.cmp(&b.range.end))
+        });
+        edits.dedup();
+
+        let mut normalized: Vec<TextEdit> = Vec::with_capacity(edits.len());
+        for edit in edits {
+            if let Some(last) = normalized.last_mut() {
+                if last.range.end == edit.range.start {
+                    last.range.end = edit.range.end;
+                    last.new_text.push_str(&edit.new_text);
+                    continue;
+                }
+                if last.range.end > edit.range.start {
+                    return Err(RefactorError::OverlappingEdits(last.range, edit.range));
+                }
+            }
+            normalized.push(edit);
+        }
+        Ok(normalized)
+    }
+
+    /// `normalize`, also dropping edits that replace text with itself in `source`
+    pub fn normalize_in(source: &str, edits: Vec<TextEdit>) -> Result<Vec<TextEdit>, RefactorError> {
+        let edits = edits
+            .into_iter()
+            .filter(|e| {
+                let start = position_to_offset(source, e.range.start);
+                let end = position_to_offset(source, e.range.end).max(start);
+                source[start..end] != e.new_text
+            })
+            .collect();
+        Self::normalize(edits)
+    }
 }
 
 /// Result of a refactoring operation
@@ -80,6 +161,129 @@ impl RefactorResult {
         self.generated_code = Some(code);
         self
     }
+
+    /// The edits as a workspace edit to the document `uri`
+    pub fn workspace_edit(&self, uri: &str) -> WorkspaceEdit {
+        WorkspaceEdit::single(uri, self.edits.clone())
+    }
+
+    /// Render the refactoring as a unified diff against the original source
+    pub fn diff(&self, original: &str) -> String {
+        let modified = apply_edits(original, &self.edits);
+        diff::unified_diff(original, &modified, diff::DEFAULT_CONTEXT)
+    }
+}
+
+/// Edits to several documents, keyed by URI
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceEdit {
+    pub changes: HashMap<String, Vec<TextEdit>>,
+}
+
+impl WorkspaceEdit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The edits of a single-document refactoring
+    pub fn single(uri: impl Into<String>, edits: Vec<TextEdit>) -> Self {
+        let mut edit = Self::new();
+        edit.insert(uri, edits);
+        edit
+    }
+
+    /// Add edits to a document; documents without edits are left out
+    pub fn insert(&mut self, uri: impl Into<String>, edits: Vec<TextEdit>) {
+        if !edits.is_empty() {
+            self.changes.entry(uri.into()).or_default().extend(edits);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// The LSP JSON shape: `{"changes": {uri: [{"range", "newText"}]}}`,
+    /// with `character` columns and the URIs in sorted order
+    pub fn to_json(&self) -> serde_json::Value {
+        let position = |p: Position| serde_json::json!({ "line": p.line, "character": p.column });
+        let mut uris: Vec<&String> = self.changes.keys().collect();
+        uris.sort();
+        let changes: serde_json::Map<String, serde_json::Value> = uris
+            .into_iter()
+            .map(|uri| {
+                let edits = self.changes[uri]
+                    .iter()
+                    .map(|edit| {
+                        serde_json::json!({
+                            "range": { "start": position(edit.range.start), "end": position(edit.range.end) },
+                            "newText": edit.new_text
+                        })
+                    })
+                    .collect();
+                (uri.clone(), serde_json::Value::Array(edits))
+            })
+            .collect();
+        serde_json::json!({ "changes": changes })
+    }
+}
+
+/// Apply text edits to a source string and return the edited text.
+///
+/// Edits are applied from the end of the document backwards so that earlier
+/// ranges stay valid; insertions at the same position keep their list order.
+pub fn apply_edits(source: &str, edits: &[TextEdit]) -> String {
+    let mut order: Vec<usize> = (0..edits.len()).collect();
+    order.sort_by(|&a, &b| {
+        edits[b]
+            .range
+            .start
+            .cmp(&edits[a].range.start)
+            .then_with(|| b.cmp(&a))
+    });
+
+    let mut result = source.to_string();
+    for i in order {
+        let edit = &edits[i];
+        let start = position_to_offset(source, edit.range.start);
+        let end = position_to_offset(source, edit.range.end).max(start);
+        result.replace_range(start..end, &edit.new_text);
+    }
+    result
+}
+
+/// Convert a position (UTF-16 column) to a byte offset in `source`, clamping
+/// past-the-end lines and columns
+pub fn position_to_offset(source: &str, position: Position) -> usize {
+    let mut offset = 0;
+    for (i, line) in source.split('\n').enumerate() {
+        if i == position.line as usize {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            return offset + column_to_byte(line, position.column);
+        }
+        offset += line.len() + 1;
+    }
+    source.len()
+}
+
+/// Convert a byte offset in `source` to a position with a UTF-16 column.
+///
+/// Total: offsets past the end clamp to the end of the source, offsets
+/// inside a character snap to its start. An offset at a line break is the
+/// end of that line, also between the `\r` and `\n` of a CRLF.
+pub fn offset_to_position(source: &str, offset: usize) -> Position {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    if source[..offset].ends_with('\r') && source[offset..].starts_with('\n') {
+        offset -= 1;
+    }
+    let before = &source[..offset];
+    let line = before.matches('\n').count() as u32;
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    Position::new(line, byte_to_column(&source[line_start..], offset - line_start))
 }
 
 /// Available refactoring actions for a given selection
@@ -132,8 +336,13 @@ pub enum RefactorKind {
     ExtractVariable,
     ExtractMethod,
     InlineVariable,
+    InlineMethod,
     SafeDelete,
     Rename,
+    MoveToFile,
+    SurroundWith,
+    ConvertDeclaration,
+    ConvertString,
 }
 
 /// Errors that can occur during refactoring
@@ -145,29 +354,63 @@ pub enum RefactorError {
     #[error("Cannot extract: {0}")]
     CannotExtract(String),
 
+    /// Range of the bracket or quote without a counterpart in the selection
+    #[error("Cannot extract: {0}")]
+    UnbalancedDelimiter(String, Range),
+
     #[error("Expression has side effects")]
     HasSideEffects,
 
     #[error("Symbol is still in use")]
     SymbolInUse(Vec<Location>),
 
-    #[error("No expression at selection")]
-    NoExpression,
+    /// Where the new name is already bound in a scope the rename reaches
+    #[error("'{0}' is already defined in the scope of the rename")]
+    NameCollision(String, Vec<Location>),
+
+    #[error("No expression at selection: {0}")]
+    NoExpression(String),
 
+    /// Ranges of the statement boundaries inside the selection
     #[error("Selection spans multiple statements")]
-    MultipleStatements,
+    MultipleStatements(Vec<Range>),
 
     #[error("Cannot determine expression type")]
     UnknownType,
 
+    /// Ranges of the offending `return`/`break`/`continue` keywords
     #[error("Control flow issue: {0}")]
-    ControlFlowIssue(String),
+    ControlFlowIssue(String, Vec<Range>),
 
     #[error("Parse error: {0}")]
     ParseError(String),
+
+    #[error("Cannot move: {0}")]
+    CannotMove(String),
+
+    #[error("Not supported: {0}")]
+    Unsupported(String),
+
+    #[error("Overlapping edits at line {} and line {}", .0.start.line + 1, .1.start.line + 1)]
+    OverlappingEdits(Range, Range),
+}
+
+impl RefactorError {
+    /// Source ranges the error points at, for underlining in the editor
+    pub fn ranges(&self) -> Vec<Range> {
+        match self {
+            RefactorError::MultipleStatements(ranges) | RefactorError::ControlFlowIssue(_, ranges) => ranges.clone(),
+            RefactorError::OverlappingEdits(a, b) => vec![*a, *b],
+            RefactorError::UnbalancedDelimiter(_, range) => vec![*range],
+            _ => Vec::new(),
+        }
+    }
 }
 
 /// Context for refactoring operations
+///
+/// Positions use UTF-16 columns (as in LSP); `position_to_offset` and
+/// `offset_to_position` convert to and from byte offsets.
 #[derive(Debug)]
 pub struct RefactorContext<'a> {
     /// The source code
@@ -176,8 +419,12 @@ pub struct RefactorContext<'a> {
     pub uri: &'a str,
     /// The selection range
     pub selection: Range,
+    /// The selection as byte offsets into `source`
+    pub selection_bytes: std::ops::Range<usize>,
     /// Language of the document
     pub language: logos_parser::LanguageId,
+    /// Casing of the names refactorings generate
+    pub naming: naming::NamingConfig,
 }
 
 impl<'a> RefactorContext<'a> {
@@ -187,58 +434,49 @@ impl<'a> RefactorContext<'a> {
         selection: Range,
         language: logos_parser::LanguageId,
     ) -> Self {
+        let start = position_to_offset(source, selection.start);
+        let end = position_to_offset(source, selection.end).max(start);
         Self {
             source,
             uri,
             selection,
+            selection_bytes: start..end,
             language,
+            naming: naming::NamingConfig::default(),
         }
     }
 
-    /// Get the selected text
-    pub fn selected_text(&self) -> &str {
-        self.text_in_range(self.selection)
+    /// Use `naming` for generated names
+    pub fn with_naming(mut self, naming: naming::NamingConfig) -> Self {
+        self.naming = naming;
+        self
     }
 
-    /// Get text in a given range
-    pub fn text_in_range(&self, range: Range) -> &str {
-        let lines: Vec<&str> = self.source.lines().collect();
+    /// Create a context from a byte-offset selection
+    pub fn with_byte_range(
+        source: &'a str,
+        uri: &'a str,
+        start_byte: usize,
+        end_byte: usize,
+        language: logos_parser::LanguageId,
+    ) -> Self {
+        let selection = Range::new(
+            offset_to_position(source, start_byte),
+            offset_to_position(source, end_byte.max(start_byte)),
+        );
+        Self::new(source, uri, selection, language)
+    }
 
-        if range.start.line as usize >= lines.len() {
-            return "";
-        }
+    /// Get the selected text
+    pub fn selected_text(&self) -> &'a str {
+        &self.source[self.selection_bytes.clone()]
+    }
 
-        if range.start.line == range.end.line {
-            // Single line selection
-            let line = lines[range.start.line as usize];
-            let start = (range.start.column as usize).min(line.len());
-            let end = (range.end.column as usize).min(line.len());
-            &line[start..end]
-        } else {
-            // Multi-line selection
-            let mut result = String::new();
-            for i in range.start.line..=range.end.line {
-                if i as usize >= lines.len() {
-                    break;
-                }
-                let line = lines[i as usize];
-                if i == range.start.line {
-                    let start = (range.start.column as usize).min(line.len());
-                    result.push_str(&line[start..]);
-                } else if i == range.end.line {
-                    let end = (range.end.column as usize).min(line.len());
-                    result.push_str(&line[..end]);
-                } else {
-                    result.push_str(line);
-                }
-                if i < range.end.line {
-                    result.push('\n');
-                }
-            }
-            // Leak the string to get a static lifetime - this is a workaround
-            // In practice, we'd use a different approach
-            Box::leak(result.into_boxed_str())
-        }
+    /// Get text in a given range
+    pub fn text_in_range(&self, range: Range) -> &'a str {
+        let start = position_to_offset(self.source, range.start);
+        let end = position_to_offset(self.source, range.end).max(start);
+        &self.source[start..end]
     }
 
     /// Get the line text at a given line number
@@ -255,6 +493,20 @@ impl<'a> RefactorContext<'a> {
             String::new()
         }
     }
+
+    /// Range of the innermost function, method or closure whose parameters
+    /// or body hold the selection; `None` for top-level selections
+    pub fn enclosing_function(&self) -> Option<Range> {
+        let mut parser = logos_parser::LanguageParser::new();
+        parser.set_language(self.language).ok()?;
+        let tree = parser.parse(self.source, None).ok()?;
+        let function =
+            analysis::function_around(tree.root_node(), self.selection_bytes.start, self.selection_bytes.end)?;
+        Some(Range::new(
+            offset_to_position(self.source, function.start_byte()),
+            offset_to_position(self.source, function.end_byte()),
+        ))
+    }
 }
 
 /// Main refactoring engine
@@ -265,49 +517,72 @@ impl RefactorEngine {
     pub fn get_actions(ctx: &RefactorContext) -> Vec<RefactorAction> {
         let mut actions = Vec::new();
 
-        // Check Extract Variable
-        match extract_variable::can_extract(ctx) {
-            Ok(true) => {
-                actions.push(RefactorAction::available(
-                    "extract-variable",
-                    "Extract Variable",
-                    RefactorKind::ExtractVariable,
-                ));
-            }
-            Ok(false) => {}
-            Err(e) => {
-                actions.push(RefactorAction::unavailable(
-                    "extract-variable",
-                    "Extract Variable",
-                    RefactorKind::ExtractVariable,
-                    e.to_string(),
-                ));
-            }
+        Self::probe(
+            &mut actions,
+            "extract-variable",
+            "Extract Variable",
+            RefactorKind::ExtractVariable,
+            extract_variable::can_extract(ctx),
+        );
+        Self::probe(
+            &mut actions,
+            "extract-method",
+            "Extract Method",
+            RefactorKind::ExtractMethod,
+            extract_method::can_extract(ctx),
+        );
+
+        // Only a selected name or declaration can be deleted
+        if safe_delete::is_symbol_selection(ctx) {
+            let can_delete = safe_delete::analyze(ctx).and_then(|analysis| {
+                if analysis.can_delete {
+                    Ok(true)
+                } else {
+                    Err(RefactorError::SymbolInUse(analysis.usages))
+                }
+            });
+            Self::probe(&mut actions, "safe-delete", "Safe Delete", RefactorKind::SafeDelete, can_delete);
         }
 
-        // Check Extract Method
-        match extract_method::can_extract(ctx) {
-            Ok(true) => {
-                actions.push(RefactorAction::available(
-                    "extract-method",
-                    "Extract Method",
-                    RefactorKind::ExtractMethod,
-                ));
-            }
-            Ok(false) => {}
-            Err(e) => {
-                actions.push(RefactorAction::unavailable(
-                    "extract-method",
-                    "Extract Method",
-                    RefactorKind::ExtractMethod,
-                    e.to_string(),
-                ));
-            }
-        }
+        Self::probe(
+            &mut actions,
+            "inline-method",
+            "Inline Method",
+            RefactorKind::InlineMethod,
+            inline_method::can_inline(ctx),
+        );
+
+        let title = match ctx.language {
+            logos_parser::LanguageId::Python => "Convert to f-string",
+            _ => "Convert to Template Literal",
+        };
+        Self::probe(
+            &mut actions,
+            "convert-string",
+            title,
+            RefactorKind::ConvertString,
+            convert_string::can_convert(ctx),
+        );
 
         actions
     }
 
+    /// Record the outcome of an applicability check: available, unavailable
+    /// with the error as the reason, or not offered at all
+    fn probe(
+        actions: &mut Vec<RefactorAction>,
+        id: &str,
+        title: &str,
+        kind: RefactorKind,
+        result: Result<bool, RefactorError>,
+    ) {
+        match result {
+            Ok(true) => actions.push(RefactorAction::available(id, title, kind)),
+            Ok(false) => {}
+            Err(e) => actions.push(RefactorAction::unavailable(id, title, kind, e.to_string())),
+        }
+    }
+
     /// Execute a refactoring action
     pub fn execute(
         ctx: &RefactorContext,
@@ -317,13 +592,15 @@ impl RefactorEngine {
         match action_id {
             "extract-variable" => {
                 let name = new_name.unwrap_or("extracted");
-                extract_variable::extract(ctx, name)
-            }
-            "extract-method" => {
-                let name = new_name.unwrap_or("extractedMethod");
-                extract_method::extract(ctx, name)
+                extract_variable::extract(ctx, name, false)
             }
+            "extract-method" => match new_name {
+                Some(name) => extract_method::extract(ctx, name),
+                None => extract_method::extract_with_suggestion(ctx).map(|(_, result)| result),
+            },
             "safe-delete" => safe_delete::delete(ctx),
+            "convert-string" => convert_string::convert(ctx),
+            "inline-method" => inline_method::inline(ctx),
             _ => Err(RefactorError::InvalidSelection(format!(
                 "Unknown action: {}",
                 action_id
@@ -336,6 +613,121 @@ impl RefactorEngine {
 mod tests {
     use super::*;
 
+    fn action_ids(source: &str, selection: Range) -> Vec<(String, bool)> {
+        let ctx = RefactorContext::new(source, "test.js", selection, logos_parser::LanguageId::JavaScript);
+        RefactorEngine::get_actions(&ctx)
+            .into_iter()
+            .map(|a| (a.id, a.is_available))
+            .collect()
+    }
+
+    #[test]
+    fn test_actions_for_unused_variable() {
+        let source = "function f() {\n    const unused = 1;\n    return 2;\n}\n";
+        let actions = action_ids(source, Range::from_coords(1, 10, 1, 16));
+        assert!(actions.contains(&("safe-delete".to_string(), true)));
+    }
+
+    #[test]
+    fn test_actions_for_used_variable() {
+        let source = "function f() {\n    const used = 1;\n    return used;\n}\n";
+        let ctx = RefactorContext::new(source, "test.js", Range::from_coords(1, 10, 1, 14), logos_parser::LanguageId::JavaScript);
+        let action = RefactorEngine::get_actions(&ctx)
+            .into_iter()
+            .find(|a| a.id == "safe-delete")
+            .unwrap();
+        assert!(!action.is_available);
+        assert_eq!(action.unavailable_reason.as_deref(), Some("Symbol is still in use"));
+    }
+
+    #[test]
+    fn test_actions_for_expression() {
+        let source = "function f(w, h) {\n    return w * h + 1;\n}\n";
+        let actions = action_ids(source, Range::from_coords(1, 11, 1, 16));
+        assert!(actions.contains(&("extract-variable".to_string(), true)));
+        assert!(!actions.iter().any(|(id, _)| id == "safe-delete"));
+        assert!(!actions.iter().any(|(id, _)| id == "convert-string"));
+
+        let actions = action_ids("const s = \"Hi \" + name;\n", Range::from_coords(0, 10, 0, 10));
+        assert!(actions.contains(&("convert-string".to_string(), true)));
+    }
+
+    #[test]
+    fn test_actions_for_call() {
+        let source = "function double(x) {\n    return x * 2;\n}\nconst y = double(3);\n";
+        let actions = action_ids(source, Range::from_coords(3, 11, 3, 11));
+        assert!(actions.contains(&("inline-method".to_string(), true)));
+
+        let actions = action_ids(source, Range::from_coords(1, 4, 1, 4));
+        assert!(!actions.iter().any(|(id, _)| id == "inline-method"));
+    }
+
+    #[test]
+    fn test_normalize_merges_touching_edits() {
+        let edits = vec![
+            TextEdit::replace(Range::from_coords(0, 4, 0, 7), "b".to_string()),
+            TextEdit::insert(Position::new(0, 0), "let ".to_string()),
+            TextEdit::replace(Range::from_coords(0, 0, 0, 4), "var ".to_string()),
+            TextEdit::insert(Position::new(0, 0), "// x\n".to_string()),
+            TextEdit::replace(Range::from_coords(0, 4, 0, 7), "b".to_string()),
+        ];
+        let normalized = TextEdit::normalize(edits).unwrap();
+        assert_eq!(
+            normalized,
+            vec![TextEdit::replace(Range::from_coords(0, 0, 0, 7), "let // x\nvar b".to_string())]
+        );
+        assert_eq!(apply_edits("let abc = 1;", &normalized), "let // x\nvar b = 1;");
+    }
+
+    #[test]
+    fn test_normalize_drops_noops() {
+        let source = "let a = 1;\nlet b = 2;\n";
+        let edits = vec![
+            TextEdit::insert(Position::new(1, 0), String::new()),
+            TextEdit::replace(Range::from_coords(0, 4, 0, 5), "a".to_string()),
+            TextEdit::replace(Range::from_coords(1, 4, 1, 5), "c".to_string()),
+        ];
+        assert_eq!(TextEdit::normalize(edits.clone()).unwrap().len(), 2);
+        assert_eq!(
+            TextEdit::normalize_in(source, edits).unwrap(),
+            vec![TextEdit::replace(Range::from_coords(1, 4, 1, 5), "c".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_normalize_rejects_overlaps() {
+        let edits = vec![
+            TextEdit::replace(Range::from_coords(2, 0, 3, 4), "x".to_string()),
+            TextEdit::delete(Range::from_coords(3, 2, 3, 8)),
+        ];
+        let err = TextEdit::normalize(edits).unwrap_err();
+        assert!(matches!(err, RefactorError::OverlappingEdits(a, b)
+            if a == Range::from_coords(2, 0, 3, 4) && b == Range::from_coords(3, 2, 3, 8)));
+        assert_eq!(err.to_string(), "Overlapping edits at line 3 and line 4");
+    }
+
+    #[test]
+    fn test_result_diff() {
+        let source = "function area(w, h) {\n    const scale = 2;\n    console.log(w * h * scale);\n    return w * h;\n}\n";
+        let selection = Range::from_coords(2, 16, 2, 21); // "w * h"
+        let ctx = RefactorContext::new(source, "test.js", selection, logos_parser::LanguageId::JavaScript);
+
+        let result = extract_variable::extract(&ctx, "size", false).unwrap();
+        assert_eq!(
+            result.diff(source),
+            "--- original\n\
+             +++ modified\n\
+             @@ -1,5 +1,6 @@\n\
+             \x20function area(w, h) {\n\
+             \x20    const scale = 2;\n\
+             -    console.log(w * h * scale);\n\
+             +    const size = w * h;\n\
+             +    console.log(size * scale);\n\
+             \x20    return w * h;\n\
+             \x20}\n"
+        );
+    }
+
     #[test]
     fn test_text_edit_insert() {
         let edit = TextEdit::insert(Position::new(0, 5), "hello".to_string());
@@ -348,4 +740,144 @@ mod tests {
         let edit = TextEdit::delete(Range::from_coords(0, 0, 0, 5));
         assert!(edit.new_text.is_empty());
     }
+
+    #[test]
+    fn test_offset_position_multibyte() {
+        // "é" is 2 bytes / 1 UTF-16 unit, "😀" is 4 bytes / 2 UTF-16 units
+        let source = "let é = 1;\nlet s = \"😀\" + x;\n";
+        let x = source.find('x').unwrap();
+
+        assert_eq!(offset_to_position(source, x), Position::new(1, 15));
+        assert_eq!(position_to_offset(source, Position::new(1, 15)), x);
+
+        let one = source.find('1').unwrap();
+        assert_eq!(offset_to_position(source, one), Position::new(0, 8));
+        assert_eq!(position_to_offset(source, Position::new(0, 8)), one);
+
+        // Offsets inside a multi-byte char snap to its start
+        let emoji = source.find('😀').unwrap();
+        assert_eq!(offset_to_position(source, emoji + 1), offset_to_position(source, emoji));
+
+        // Past-the-end positions clamp
+        assert_eq!(position_to_offset(source, Position::new(0, 99)), source.find('\n').unwrap());
+        assert_eq!(position_to_offset(source, Position::new(9, 0)), source.len());
+    }
+
+    const EDGE_SOURCES: &[&str] = &[
+        "",
+        "\n",
+        "\n\n",
+        "a",
+        "a\n",
+        "a\n\n",
+        "\nb",
+        "one\n\nthree",
+        "one\n\nthree\n",
+        "ab\r\ncd\r\n",
+        "\r\n\r\n",
+        "é😀\nx\n",
+    ];
+
+    #[test]
+    fn test_offset_position_round_trip() {
+        for source in EDGE_SOURCES {
+            for offset in (0..=source.len()).filter(|&o| source.is_char_boundary(o)) {
+                let position = offset_to_position(source, offset);
+                let line = source.split('\n').nth(position.line as usize);
+                assert!(line.is_some(), "{:?} @ {}: line {} out of range", source, offset, position.line);
+
+                // Only the offset inside a CRLF doesn't come back
+                let expected = if source[..offset].ends_with('\r') { offset - 1 } else { offset };
+                assert_eq!(position_to_offset(source, position), expected, "{:?} @ {}", source, offset);
+            }
+
+            // Past the end clamps to the end
+            let end = offset_to_position(source, source.len());
+            assert_eq!(offset_to_position(source, source.len() + 5), end);
+        }
+    }
+
+    #[test]
+    fn test_position_offset_round_trip() {
+        for source in EDGE_SOURCES {
+            let lines: Vec<&str> = source.split('\n').map(|l| l.trim_end_matches('\r')).collect();
+            for (line, text) in lines.iter().enumerate() {
+                let width = text.encode_utf16().count() as u32;
+                for column in 0..=width {
+                    let position = Position::new(line as u32, column);
+                    let offset = position_to_offset(source, position);
+                    // Columns inside a surrogate pair snap to the character's start
+                    let back = offset_to_position(source, offset);
+                    assert_eq!(back.line, position.line, "{:?} {:?}", source, position);
+                    assert!(back.column <= column && column - back.column <= 1, "{:?} {:?}", source, position);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_offset_at_line_breaks() {
+        let source = "let a;\n\nlet b;";
+        assert_eq!(offset_to_position(source, 6), Position::new(0, 6));
+        assert_eq!(offset_to_position(source, 7), Position::new(1, 0));
+        assert_eq!(offset_to_position(source, 8), Position::new(2, 0));
+        assert_eq!(offset_to_position(source, source.len()), Position::new(2, 6));
+        assert_eq!(offset_to_position("a\n", 2), Position::new(1, 0));
+        assert_eq!(offset_to_position("a\r\nb", 2), Position::new(0, 1));
+    }
+
+    #[test]
+    fn test_context_with_byte_range() {
+        let source = "let é = \"😀\";\nfoo(é + 1);";
+        let start = source.find("é + 1").unwrap();
+        let end = start + "é + 1".len();
+
+        let ctx = RefactorContext::with_byte_range(
+            source,
+            "test.js",
+            start,
+            end,
+            logos_parser::LanguageId::JavaScript,
+        );
+        assert_eq!(ctx.selection, Range::from_coords(1, 4, 1, 9));
+        assert_eq!(ctx.selected_text(), "é + 1");
+
+        let same = RefactorContext::new(source, "test.js", ctx.selection, ctx.language);
+        assert_eq!(same.selection_bytes, start..end);
+    }
+
+    #[test]
+    fn test_apply_edits() {
+        let source = "let a = 1;\nfoo(a + b);\n";
+        let edits = vec![
+            TextEdit::replace(Range::from_coords(1, 4, 1, 9), "sum".to_string()),
+            TextEdit::insert(Position::new(1, 0), "const sum = a + b;\n".to_string()),
+            TextEdit::delete(Range::from_coords(0, 0, 1, 0)),
+        ];
+        assert_eq!(apply_edits(source, &edits), "const sum = a + b;\nfoo(sum);\n");
+    }
+
+    #[test]
+    fn test_apply_edits_same_position_keeps_order() {
+        let edits = vec![
+            TextEdit::insert(Position::new(0, 0), "a".to_string()),
+            TextEdit::insert(Position::new(0, 0), "b".to_string()),
+        ];
+        assert_eq!(apply_edits("c", &edits), "abc");
+    }
+
+    #[test]
+    fn test_enclosing_function() {
+        let source = "class Cart {\n    total(items) {\n        return items.map(i => i.price);\n    }\n}\nconst tax = 0.2;\n";
+        let at = |selection: Range| {
+            RefactorContext::new(source, "test.js", selection, logos_parser::LanguageId::JavaScript).enclosing_function()
+        };
+
+        // `items` in the method body: the whole method
+        assert_eq!(at(Range::from_coords(2, 15, 2, 20)), Some(Range::from_coords(1, 4, 3, 5)));
+        // `i.price` in the arrow function: the arrow function
+        assert_eq!(at(Range::from_coords(2, 30, 2, 37)), Some(Range::from_coords(2, 25, 2, 37)));
+        // Top level
+        assert_eq!(at(Range::from_coords(5, 12, 5, 15)), None);
+    }
 }
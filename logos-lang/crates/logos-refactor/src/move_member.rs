@@ -0,0 +1,439 @@
+//! Pull Member Up
+//!
+//! Move a method or field of a derived class into its base class: the
+//! member leaves the derived class body, with the comments right above it,
+//! and joins the base class in a section of the same visibility, opening
+//! one if the base has none. Out-of-class definitions (`Derived::name`) are
+//! requalified with the base.
+//!
+//! The base comes from the adapter's type relations (`ClassParent`). Only
+//! C++ classes with a single base defined in the same file are supported,
+//! and a member that uses other members of the derived class stays there.
+
+use crate::inline_method::{node_range, text};
+use crate::surround_with::indent_unit;
+use crate::{offset_to_position, RefactorContext, RefactorError, RefactorResult, TextEdit};
+use logos_parser::{LanguageId, LanguageParser};
+use std::collections::HashSet;
+use tree_sitter::Node;
+
+/// A class and one of the classes it derives from, as an adapter's
+/// `TypeRelation` reports it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassParent {
+    pub class: String,
+    pub parent: String,
+}
+
+/// Pull the member at the cursor up into the base class of its class
+pub fn pull_up(ctx: &RefactorContext, parents: &[ClassParent]) -> Result<RefactorResult, RefactorError> {
+    if ctx.language != LanguageId::Cpp {
+        return Err(RefactorError::Unsupported(format!(
+            "Pull Member Up is not supported for {} files",
+            ctx.language.as_str()
+        )));
+    }
+    let source = ctx.source;
+    let mut parser = LanguageParser::new();
+    parser
+        .set_language(ctx.language)
+        .map_err(|e| RefactorError::ParseError(e.to_string()))?;
+    let tree = parser
+        .parse(source, None)
+        .map_err(|e| RefactorError::ParseError(e.to_string()))?;
+    let root = tree.root_node();
+
+    let offset = ctx.selection_bytes.start;
+    let mut member = root.descendant_for_byte_range(offset, offset);
+    while let Some(node) = member {
+        if is_member(node) {
+            break;
+        }
+        member = node.parent();
+    }
+    let member = member.ok_or_else(|| RefactorError::InvalidSelection("The cursor is not on a class member".to_string()))?;
+    let name = member_name(member, source)
+        .ok_or_else(|| RefactorError::InvalidSelection("The member has no name".to_string()))?;
+    let derived_body = member.parent().unwrap_or(member);
+    let derived = derived_body.parent().unwrap_or(derived_body);
+    let derived_name = class_name(derived, source).unwrap_or_default();
+
+    let bases: Vec<&str> = parents
+        .iter()
+        .filter(|p| p.class == derived_name)
+        .map(|p| p.parent.as_str())
+        .collect();
+    let base_name = match bases.as_slice() {
+        [] => return Err(RefactorError::CannotMove(format!("'{}' has no base class", derived_name))),
+        [base] => *base,
+        _ => {
+            return Err(RefactorError::Unsupported(format!(
+                "'{}' has more than one base class",
+                derived_name
+            )))
+        }
+    };
+    let base = find_class(root, source, base_name).ok_or_else(|| {
+        RefactorError::Unsupported(format!("'{}' is not defined in this file", base_name))
+    })?;
+    let base_body = base
+        .child_by_field_name("body")
+        .ok_or_else(|| RefactorError::Unsupported(format!("'{}' is only declared in this file", base_name)))?;
+
+    if member_names(base_body, source).contains(&name) {
+        return Err(RefactorError::CannotMove(format!(
+            "'{}' already has a member '{}'",
+            base_name, name
+        )));
+    }
+    // Members of the derived class the moved code would no longer see
+    let staying: HashSet<&str> = member_names(derived_body, source).into_iter().filter(|n| *n != name).collect();
+    let qualifiers = qualifiers(root, source, derived_name, name);
+    let mut used = Vec::new();
+    used_names(member, source, &mut used);
+    for definition in qualifiers.iter().filter_map(|q| enclosing_definition(*q)) {
+        used_names(definition, source, &mut used);
+    }
+    if let Some(dependency) = used.iter().find(|id| staying.contains(*id)) {
+        return Err(RefactorError::CannotMove(format!(
+            "'{}' uses '{}', which stays in '{}'",
+            name, dependency, derived_name
+        )));
+    }
+
+    let visibility = visibility_at(derived, derived_body, member, source);
+    let (start, end) = member_lines(member, source);
+    let moved = reindent(&source[start..end], &line_indent(source, member.start_byte()), &member_indent(base, base_body, source));
+
+    let mut edits = vec![
+        TextEdit::delete(crate::Range::new(offset_to_position(source, start), offset_to_position(source, end))),
+        insertion(base, base_body, source, visibility, &moved),
+    ];
+    for qualifier in qualifiers {
+        edits.push(TextEdit::replace(node_range(source, qualifier), base_name.to_string()));
+    }
+
+    Ok(RefactorResult::new(
+        TextEdit::normalize(edits)?,
+        format!("Pull '{}' up from '{}' into '{}'", name, derived_name, base_name),
+    ))
+}
+
+/// Whether `node` is a method or field directly in a class body
+fn is_member(node: Node) -> bool {
+    matches!(node.kind(), "function_definition" | "field_declaration" | "declaration")
+        && node.parent().is_some_and(|p| p.kind() == "field_declaration_list")
+}
+
+fn is_class(node: Node) -> bool {
+    matches!(node.kind(), "class_specifier" | "struct_specifier")
+}
+
+fn class_name<'s>(class: Node, source: &'s str) -> Option<&'s str> {
+    class.child_by_field_name("name").map(|n| text(source, n))
+}
+
+/// The class named `name` that has a body
+fn find_class<'t>(root: Node<'t>, source: &str, name: &str) -> Option<Node<'t>> {
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        if is_class(node) && class_name(node, source) == Some(name) && node.child_by_field_name("body").is_some() {
+            return Some(node);
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+    }
+    None
+}
+
+/// The name a member declares: the innermost identifier of its declarator
+fn member_name<'s>(member: Node, source: &'s str) -> Option<&'s str> {
+    let mut declarator = member.child_by_field_name("declarator")?;
+    while let Some(inner) = declarator.child_by_field_name("declarator") {
+        declarator = inner;
+    }
+    matches!(declarator.kind(), "field_identifier" | "identifier" | "destructor_name" | "operator_name")
+        .then(|| text(source, declarator))
+}
+
+fn member_names<'s>(body: Node, source: &'s str) -> Vec<&'s str> {
+    let mut cursor = body.walk();
+    body.named_children(&mut cursor)
+        .filter(|n| is_member(*n))
+        .filter_map(|n| member_name(n, source))
+        .collect()
+}
+
+/// The access of `member`: the last label before it, or the default of the
+/// class kind
+fn visibility_at<'s>(class: Node, body: Node, member: Node, source: &'s str) -> &'s str {
+    let mut visibility = default_visibility(class);
+    let mut cursor = body.walk();
+    for child in body.named_children(&mut cursor) {
+        if child.start_byte() >= member.start_byte() {
+            break;
+        }
+        if child.kind() == "access_specifier" {
+            visibility = text(source, child);
+        }
+    }
+    visibility
+}
+
+fn default_visibility(class: Node) -> &'static str {
+    if class.kind() == "struct_specifier" {
+        "public"
+    } else {
+        "private"
+    }
+}
+
+/// Byte range of the lines holding `member` and the comments right above it
+fn member_lines(member: Node, source: &str) -> (usize, usize) {
+    let mut first = member;
+    while let Some(previous) = first.prev_named_sibling() {
+        let gap = &source[previous.end_byte()..first.start_byte()];
+        if previous.kind() != "comment" || gap.matches('\n').count() != 1 {
+            break;
+        }
+        first = previous;
+    }
+    let start = source[..first.start_byte()].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let end = source[member.end_byte()..]
+        .find('\n')
+        .map(|i| member.end_byte() + i + 1)
+        .unwrap_or(source.len());
+    (start, end)
+}
+
+fn line_indent(source: &str, offset: usize) -> String {
+    let line_start = source[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    source[line_start..].chars().take_while(|c| *c == ' ' || *c == '\t').collect()
+}
+
+/// Indentation of the members of `class`: that of an existing member, or one
+/// level deeper than the class
+fn member_indent(class: Node, body: Node, source: &str) -> String {
+    let mut cursor = body.walk();
+    let existing = body
+        .named_children(&mut cursor)
+        .find(|n| is_member(*n) && source[body.start_byte()..n.start_byte()].contains('\n'))
+        .map(|n| line_indent(source, n.start_byte()));
+    existing.unwrap_or_else(|| {
+        let indent = line_indent(source, class.start_byte());
+        format!("{}{}", indent, indent_unit(source, &indent, LanguageId::Cpp))
+    })
+}
+
+/// `lines` moved from the indentation `from` to `to`
+fn reindent(lines: &str, from: &str, to: &str) -> String {
+    lines
+        .split_inclusive('\n')
+        .map(|line| match line.strip_prefix(from) {
+            Some(rest) if !line.trim().is_empty() => format!("{}{}", to, rest),
+            _ => line.to_string(),
+        })
+        .collect()
+}
+
+/// The edit adding `moved` (whole lines) to the end of the section of
+/// `body` with `visibility`, or to a new section at the end of the body
+fn insertion(class: Node, body: Node, source: &str, visibility: &str, moved: &str) -> TextEdit {
+    let close = body.end_byte() - 1;
+    let class_indent = line_indent(source, class.start_byte());
+
+    // The last section with that visibility ends at the next label
+    let mut section_end = None;
+    let mut current = default_visibility(class);
+    let mut cursor = body.walk();
+    let mut last_member_end = None;
+    for child in body.named_children(&mut cursor) {
+        if child.kind() == "access_specifier" {
+            if current == visibility && last_member_end.is_some() {
+                section_end = last_member_end;
+            }
+            current = text(source, child);
+            last_member_end = Some(label_end(child, source));
+        } else if child.kind() != "comment" {
+            last_member_end = Some(child.end_byte());
+        }
+    }
+    if current == visibility {
+        section_end = Some(last_member_end.unwrap_or(body.start_byte() + 1));
+    }
+
+    let single_line = !source[body.start_byte()..close].contains('\n');
+    let (at, text) = match section_end {
+        Some(end) if !single_line => {
+            let line_end = source[end..].find('\n').map(|i| end + i + 1).unwrap_or(source.len());
+            (line_end, moved.to_string())
+        }
+        _ => {
+            let label = format!("{}{}:\n", class_indent, visibility);
+            let label = if section_end.is_some() { String::new() } else { label };
+            if single_line {
+                // `class Base {};` opens up to hold the member
+                let inner = source[body.start_byte() + 1..close].trim();
+                let mut body_text = String::from("\n");
+                if !inner.is_empty() {
+                    body_text.push_str(&format!("{}{}\n", member_indent(class, body, source), inner));
+                }
+                body_text.push_str(&label);
+                body_text.push_str(moved);
+                body_text.push_str(&class_indent);
+                return TextEdit::replace(
+                    crate::Range::new(
+                        offset_to_position(source, body.start_byte() + 1),
+                        offset_to_position(source, close),
+                    ),
+                    body_text,
+                );
+            }
+            let line_start = source[..close].rfind('\n').map(|i| i + 1).unwrap_or(0);
+            (line_start, format!("{}{}", label, moved))
+        }
+    };
+    TextEdit::insert(offset_to_position(source, at), text)
+}
+
+/// End of an access label, after its `:`
+fn label_end(label: Node, source: &str) -> usize {
+    let rest = &source[label.end_byte()..];
+    match rest.trim_start().strip_prefix(':') {
+        Some(after) => source.len() - after.len(),
+        None => label.end_byte(),
+    }
+}
+
+/// Identifiers and member names used below `node`
+fn used_names<'s>(node: Node, source: &'s str, names: &mut Vec<&'s str>) {
+    if matches!(node.kind(), "identifier" | "field_identifier") {
+        names.push(text(source, node));
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        used_names(child, source, names);
+    }
+}
+
+fn enclosing_definition(node: Node) -> Option<Node> {
+    let mut current = node.parent();
+    while let Some(n) = current {
+        if n.kind() == "function_definition" {
+            return Some(n);
+        }
+        current = n.parent();
+    }
+    None
+}
+
+/// `Derived::name` qualifiers of out-of-class definitions, as the `Derived`
+/// part
+fn qualifiers<'t>(root: Node<'t>, source: &str, class: &str, name: &str) -> Vec<Node<'t>> {
+    let mut found = Vec::new();
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        if node.kind() == "qualified_identifier" {
+            let scope = node.child_by_field_name("scope");
+            let member = node.child_by_field_name("name");
+            if let (Some(scope), Some(member)) = (scope, member) {
+                if text(source, scope) == class && text(source, member) == name {
+                    found.push(scope);
+                    continue;
+                }
+            }
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apply_edits;
+
+    fn pulled(source: &str, at: &str) -> Result<String, RefactorError> {
+        let offset = source.find(at).unwrap();
+        let position = offset_to_position(source, offset);
+        let range = crate::Range::new(position, position);
+        let ctx = RefactorContext::new(source, "test.cpp", range, LanguageId::Cpp);
+        let parents = vec![ClassParent {
+            class: "Derived".to_string(),
+            parent: "Base".to_string(),
+        }];
+        Ok(apply_edits(source, &pull_up(&ctx, &parents)?.edits))
+    }
+
+    #[test]
+    fn test_pull_method_up() {
+        let source = "\
+class Base {
+public:
+    virtual ~Base() {}
+};
+
+class Derived : public Base {
+public:
+    // Twice the input
+    int twice(int x) const {
+        return x * 2;
+    }
+    int size;
+};
+";
+        assert_eq!(
+            pulled(source, "twice").unwrap(),
+            "\
+class Base {
+public:
+    virtual ~Base() {}
+    // Twice the input
+    int twice(int x) const {
+        return x * 2;
+    }
+};
+
+class Derived : public Base {
+public:
+    int size;
+};
+"
+        );
+    }
+
+    #[test]
+    fn test_pull_up_opens_a_section() {
+        let source = "\
+class Base {};
+
+class Derived : public Base {
+protected:
+    int count;
+    int total();
+};
+
+int Derived::total() { return count; }
+";
+        assert_eq!(
+            pulled(source, "count").unwrap(),
+            "\
+class Base {
+protected:
+    int count;
+};
+
+class Derived : public Base {
+protected:
+    int total();
+};
+
+int Derived::total() { return count; }
+"
+        );
+        // `total` reads `count`, which stays behind
+        assert!(matches!(pulled(source, "total"), Err(RefactorError::CannotMove(_))));
+    }
+}
@@ -0,0 +1,672 @@
+//! Move Symbol to File
+//!
+//! Move a top-level declaration into another file of the same language and
+//! fix the imports on both sides:
+//! - The source imports the symbol from the target if it still uses it, and
+//!   drops imports only the moved code needed
+//! - The target imports what the moved code uses from the source file and
+//!   from the source's own imports, and no longer imports the symbol itself
+//!
+//! Supported for JavaScript/TypeScript (ES modules) and Python.
+
+use std::collections::BTreeMap;
+
+use crate::rename::find_occurrences;
+use crate::safe_delete::deletion_range;
+use crate::{apply_edits, byte_to_column, offset_to_position, RefactorContext, RefactorError, TextEdit, WorkspaceEdit};
+use logos_core::{Position, Range};
+use logos_parser::LanguageId;
+use regex::Regex;
+
+/// An imported `(name, alias)` pair
+pub type ImportedItem = (String, Option<String>);
+
+/// An import statement, as found by the language adapter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleImport {
+    /// Module specifier as written (`./util`, `os.path`, `.models`)
+    pub module_path: String,
+    /// Imported items. `*` is a namespace import and `default` a default
+    /// import; a Python `import x` imports the module path itself.
+    pub items: Vec<ImportedItem>,
+    /// Range of the import statement
+    pub range: Range,
+}
+
+/// A file taking part in a move
+#[derive(Debug, Clone)]
+pub struct ModuleFile<'a> {
+    pub uri: &'a str,
+    pub source: &'a str,
+    pub imports: Vec<ModuleImport>,
+    /// Names declared at the top level
+    pub declarations: Vec<String>,
+}
+
+/// The edits of a move for one file
+#[derive(Debug, Clone)]
+pub struct FileMove {
+    pub uri: String,
+    /// Edits in document order
+    pub edits: Vec<TextEdit>,
+    /// The document text with the edits applied
+    pub new_text: String,
+}
+
+/// The edits of a move: the file the symbol leaves and the file it joins
+#[derive(Debug, Clone)]
+pub struct MovePlan {
+    pub source: FileMove,
+    pub target: FileMove,
+}
+
+impl MovePlan {
+    /// The edits of both files
+    pub fn workspace_edit(&self) -> WorkspaceEdit {
+        let mut edit = WorkspaceEdit::new();
+        edit.insert(self.source.uri.clone(), self.source.edits.clone());
+        edit.insert(self.target.uri.clone(), self.target.edits.clone());
+        edit
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ImportStyle {
+    EsModule,
+    Python,
+}
+
+impl ImportStyle {
+    pub(crate) fn for_language(language: LanguageId) -> Option<Self> {
+        match language {
+            LanguageId::JavaScript | LanguageId::TypeScript => Some(Self::EsModule),
+            LanguageId::Python => Some(Self::Python),
+            _ => None,
+        }
+    }
+}
+
+/// Plan moving the top-level declaration `name` at `declaration` from one
+/// file to another
+pub fn plan(
+    language: LanguageId,
+    from: &ModuleFile,
+    to: &ModuleFile,
+    name: &str,
+    declaration: Range,
+) -> Result<MovePlan, RefactorError> {
+    let style = ImportStyle::for_language(language).ok_or_else(|| {
+        RefactorError::CannotMove(format!("not supported for {} files", language.as_str()))
+    })?;
+    if from.uri == to.uri {
+        return Err(RefactorError::CannotMove("the target is the source file".to_string()));
+    }
+    if to.declarations.iter().any(|d| d == name) {
+        return Err(RefactorError::CannotMove(format!("'{}' is already declared in {}", name, to.uri)));
+    }
+
+    let ctx = RefactorContext::new(from.source, from.uri, declaration, language);
+    let removed = with_trailing_blank_lines(from.source, deletion_range(&ctx));
+    let moved = ctx.text_in_range(removed).trim_matches(['\r', '\n']).trim_end().to_string();
+    if moved.is_empty() {
+        return Err(RefactorError::InvalidSelection("No declaration selected".to_string()));
+    }
+
+    let format = ImportFormat::detect(style, from, to);
+    let uses = |code: &str, name: &str| !find_occurrences(code, name, Some(language)).is_empty();
+    // Occurrences in the source outside the moved code and the imports
+    let still_used_in_source = |name: &str| {
+        find_occurrences(from.source, name, Some(language)).iter().any(|range| {
+            !removed.contains_range(range) && !from.imports.iter().any(|i| on_lines(i.range, range.start.line))
+        })
+    };
+    let target_has = |name: &str| {
+        to.declarations.iter().any(|d| d == name) || to.imports.iter().any(|i| local_names(style, i).any(|n| n == name))
+    };
+
+    let mut source_imports = ImportChanges::new(style, from);
+    let mut target_imports = ImportChanges::new(style, to);
+    let mut source_edits = vec![TextEdit::delete(removed)];
+
+    // The source keeps using the symbol through an import
+    let still_used = still_used_in_source(name);
+    if still_used {
+        source_imports.add(&module_specifier(style, from.uri, &strip_extension(to.uri)), (name.to_string(), None));
+    }
+
+    // The target no longer imports the symbol from the source
+    for (index, import) in to.imports.iter().enumerate() {
+        if resolves_to(style, to.uri, &import.module_path, from.uri) {
+            target_imports.remove(index, name);
+        }
+    }
+
+    // Declarations of the source the moved code uses
+    let from_source = module_specifier(style, to.uri, &strip_extension(from.uri));
+    let mut exported = Vec::new();
+    for declaration in &from.declarations {
+        if declaration != name && uses(&moved, declaration) && !target_has(declaration) && !exported.contains(declaration) {
+            target_imports.add(&from_source, (declaration.clone(), None));
+            exported.push(declaration.clone());
+        }
+    }
+
+    // The source's imports the moved code uses
+    for (index, import) in from.imports.iter().enumerate() {
+        let module = rebase(style, &import.module_path, from.uri, to.uri);
+        for item in &import.items {
+            let local = local_name(style, import, item);
+            if !uses(&moved, local) {
+                continue;
+            }
+            if !target_has(local) && !resolves_to(style, to.uri, &module, to.uri) {
+                target_imports.add(&module, item.clone());
+            }
+            if !still_used_in_source(local) {
+                source_imports.remove(index, &item.0);
+            }
+        }
+    }
+
+    // ES modules only see what is exported
+    let mut moved_text = moved;
+    if style == ImportStyle::EsModule {
+        for declaration in &exported {
+            if let Some(line) = unexported_declaration_line(from.source, declaration) {
+                source_edits.push(TextEdit::insert(Position::new(line, 0), "export ".to_string()));
+            }
+        }
+        if still_used && !moved_text.starts_with("export ") {
+            moved_text.insert_str(0, "export ");
+        }
+    }
+
+    source_edits.extend(source_imports.edits(&format));
+    let mut target_edits = target_imports.edits(&format);
+
+    // Append the declaration after the target's last line
+    let blank_lines = if style == ImportStyle::Python { 2 } else { 1 };
+    let separator = if !to.source.trim().is_empty() {
+        let trailing_newlines = to.source.len() - to.source.trim_end_matches(['\r', '\n']).len();
+        "\n".repeat((blank_lines + 1usize).saturating_sub(trailing_newlines))
+    } else if !target_edits.is_empty() {
+        // Only the new imports precede it
+        "\n".repeat(blank_lines)
+    } else {
+        String::new()
+    };
+    target_edits.push(TextEdit::insert(
+        offset_to_position(to.source, to.source.len()),
+        format!("{}{}\n", separator, moved_text),
+    ));
+
+    Ok(MovePlan {
+        source: file_move(from, source_edits)?,
+        target: file_move(to, target_edits)?,
+    })
+}
+
+/// Extend a whole-line removal over the blank lines after it when a blank
+/// line already precedes it, so that no double blank line is left behind
+fn with_trailing_blank_lines(source: &str, range: Range) -> Range {
+    let lines: Vec<&str> = source.split('\n').collect();
+    let start = range.start.line as usize;
+    if range.start.column != 0 || range.end.column != 0 || (start > 0 && !lines[start - 1].trim().is_empty()) {
+        return range;
+    }
+    let mut end = range.end.line as usize;
+    while end + 1 < lines.len() && lines[end].trim().is_empty() {
+        end += 1;
+    }
+    Range::new(range.start, Position::new(end as u32, 0))
+}
+
+fn file_move(file: &ModuleFile, edits: Vec<TextEdit>) -> Result<FileMove, RefactorError> {
+    let edits = TextEdit::normalize(edits)?;
+    Ok(FileMove {
+        uri: file.uri.to_string(),
+        new_text: apply_edits(file.source, &edits),
+        edits,
+    })
+}
+
+/// Pending changes to a file's imports
+pub(crate) struct ImportChanges<'a> {
+    style: ImportStyle,
+    file: &'a ModuleFile<'a>,
+    /// New item lists of existing imports, by index
+    updated: BTreeMap<usize, Vec<ImportedItem>>,
+    /// New import statements
+    added: Vec<(String, Vec<ImportedItem>)>,
+}
+
+impl<'a> ImportChanges<'a> {
+    pub(crate) fn new(style: ImportStyle, file: &'a ModuleFile<'a>) -> Self {
+        Self {
+            style,
+            file,
+            updated: BTreeMap::new(),
+            added: Vec::new(),
+        }
+    }
+
+    pub(crate) fn items(&mut self, index: usize) -> &mut Vec<ImportedItem> {
+        let import = &self.file.imports[index];
+        self.updated.entry(index).or_insert_with(|| import.items.clone())
+    }
+
+    /// Drop `name` (when not renamed) from an existing import
+    pub(crate) fn remove(&mut self, index: usize, name: &str) {
+        if self.file.imports[index].items.iter().any(|(n, alias)| n == name && alias.is_none()) {
+            self.items(index).retain(|(n, alias)| n != name || alias.is_some());
+        }
+    }
+
+    /// Import `item` from `module`, extending an import of the same module
+    fn add(&mut self, module: &str, item: ImportedItem) {
+        let existing = self.file.imports.iter().position(|import| {
+            same_module(self.style, self.file.uri, &import.module_path, module) && extendable(self.style, import)
+        });
+        let items = match existing {
+            Some(index) => self.items(index),
+            None => match self.added.iter().position(|(m, _)| m == module) {
+                Some(i) => &mut self.added[i].1,
+                None => {
+                    self.added.push((module.to_string(), Vec::new()));
+                    &mut self.added.last_mut().unwrap().1
+                }
+            },
+        };
+        if !items.contains(&item) {
+            items.push(item);
+        }
+    }
+
+    pub(crate) fn edits(self, format: &ImportFormat) -> Vec<TextEdit> {
+        let lines: Vec<&str> = self.file.source.split('\n').map(|l| l.trim_end_matches('\r')).collect();
+        let mut edits = Vec::new();
+
+        for (index, items) in &self.updated {
+            let import = &self.file.imports[*index];
+            if *items == import.items {
+                continue;
+            }
+            let (start, end) = (import.range.start.line as usize, import.range.end.line as usize);
+            if items.is_empty() {
+                // Remove the statement with its line
+                let end = if end + 1 < lines.len() {
+                    Position::new(end as u32 + 1, 0)
+                } else {
+                    Position::new(end as u32, byte_to_column(lines[end], lines[end].len()))
+                };
+                edits.push(TextEdit::delete(Range::new(Position::new(start as u32, 0), end)));
+            } else {
+                let range = Range::from_coords(start as u32, 0, end as u32, byte_to_column(lines[end], lines[end].len()));
+                edits.push(TextEdit::replace(range, render(self.style, &import.module_path, items, format)));
+            }
+        }
+
+        if !self.added.is_empty() {
+            let statements: Vec<String> = self
+                .added
+                .iter()
+                .map(|(module, items)| render(self.style, module, items, format))
+                .collect();
+            let line = self.file.imports.iter().map(|i| i.range.end.line as usize + 1).max().unwrap_or(0);
+            let mut text = statements.join("\n") + "\n";
+            let position = if line < lines.len() {
+                Position::new(line as u32, 0)
+            } else {
+                // The last import ends the file without a newline
+                text.insert(0, '\n');
+                offset_to_position(self.file.source, self.file.source.len())
+            };
+            edits.push(TextEdit::insert(position, text));
+        }
+
+        edits
+    }
+}
+
+/// Quote and semicolon style of ES imports, following the existing imports
+pub(crate) struct ImportFormat {
+    quote: char,
+    semicolon: bool,
+}
+
+impl ImportFormat {
+    pub(crate) fn detect(style: ImportStyle, from: &ModuleFile, to: &ModuleFile) -> Self {
+        let statement = [to, from].iter().find_map(|file| {
+            let import = file.imports.first()?;
+            let text = file.source.lines().nth(import.range.start.line as usize)?;
+            Some(text.trim_end().to_string())
+        });
+        Self::of_statement(style, statement.as_deref())
+    }
+
+    /// The format of an import statement's text
+    pub(crate) fn of_statement(style: ImportStyle, statement: Option<&str>) -> Self {
+        match (style, statement) {
+            (ImportStyle::EsModule, Some(text)) => Self {
+                quote: if text.contains('"') { '"' } else { '\'' },
+                semicolon: text.ends_with(';'),
+            },
+            _ => Self {
+                quote: '\'',
+                semicolon: true,
+            },
+        }
+    }
+}
+
+/// Source text of an import statement
+pub(crate) fn render(style: ImportStyle, module: &str, items: &[ImportedItem], format: &ImportFormat) -> String {
+    let named = |(name, alias): &ImportedItem| match alias {
+        Some(alias) if alias != name => format!("{} as {}", name, alias),
+        _ => name.clone(),
+    };
+
+    match style {
+        ImportStyle::EsModule => {
+            let mut clauses = Vec::new();
+            if let Some((_, Some(alias))) = items.iter().find(|(n, _)| n == "default") {
+                clauses.push(alias.clone());
+            }
+            if let Some((_, Some(alias))) = items.iter().find(|(n, _)| n == "*") {
+                clauses.push(format!("* as {}", alias));
+            }
+            let names: Vec<String> = items.iter().filter(|(n, _)| n != "default" && n != "*").map(named).collect();
+            if !names.is_empty() {
+                clauses.push(format!("{{ {} }}", names.join(", ")));
+            }
+            format!(
+                "import {} from {q}{}{q}{}",
+                clauses.join(", "),
+                module,
+                if format.semicolon { ";" } else { "" },
+                q = format.quote
+            )
+        }
+        ImportStyle::Python => {
+            if items.iter().all(|(n, _)| n == module) {
+                items.iter().map(|item| format!("import {}", named(item))).collect::<Vec<_>>().join("\n")
+            } else {
+                format!("from {} import {}", module, items.iter().map(named).collect::<Vec<_>>().join(", "))
+            }
+        }
+    }
+}
+
+/// Whether more names can be added to the import statement
+fn extendable(style: ImportStyle, import: &ModuleImport) -> bool {
+    match style {
+        ImportStyle::EsModule => !import.items.iter().any(|(n, _)| n == "*"),
+        ImportStyle::Python => !import.items.iter().any(|(n, _)| *n == import.module_path),
+    }
+}
+
+pub(crate) fn local_name<'i>(style: ImportStyle, import: &ModuleImport, item: &'i ImportedItem) -> &'i str {
+    match &item.1 {
+        Some(alias) => alias,
+        // `import os.path` binds `os`
+        None if style == ImportStyle::Python && item.0 == import.module_path => {
+            item.0.split('.').next().unwrap_or(&item.0)
+        }
+        None => &item.0,
+    }
+}
+
+fn local_names(style: ImportStyle, import: &ModuleImport) -> impl Iterator<Item = &str> {
+    import.items.iter().map(move |item| local_name(style, import, item))
+}
+
+pub(crate) fn on_lines(range: Range, line: u32) -> bool {
+    range.start.line <= line && line <= range.end.line
+}
+
+/// The line of a top-level `name` declaration that isn't exported
+fn unexported_declaration_line(source: &str, name: &str) -> Option<u32> {
+    let re = Regex::new(&format!(
+        r"^(?:async\s+)?(?:function\*?|class|const|let|var|interface|type|enum)\s+{}\b",
+        regex::escape(name)
+    ))
+    .ok()?;
+    source.lines().position(|line| re.is_match(line)).map(|line| line as u32)
+}
+
+/// The URI without its file extension
+fn strip_extension(uri: &str) -> String {
+    let (dir, file) = uri.rsplit_once('/').unwrap_or(("", uri));
+    let stem = file.rsplit_once('.').map(|(stem, _)| stem).filter(|s| !s.is_empty()).unwrap_or(file);
+    if dir.is_empty() && !uri.contains('/') {
+        stem.to_string()
+    } else {
+        format!("{}/{}", dir, stem)
+    }
+}
+
+fn directory(uri: &str) -> Vec<&str> {
+    let mut segments: Vec<&str> = uri.split('/').collect();
+    segments.pop();
+    segments
+}
+
+/// How the file `importer` refers to the module at `target` (a URI without
+/// extension): `./util`, `../lib/util`; `util`, `.sub.util`, `..util` in Python
+fn module_specifier(style: ImportStyle, importer: &str, target: &str) -> String {
+    let from = directory(importer);
+    let mut to: Vec<&str> = target.split('/').collect();
+    let stem = to.pop().unwrap_or("");
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let ups = from.len() - common;
+    let downs = &to[common..];
+
+    match style {
+        ImportStyle::EsModule => {
+            let mut specifier = if ups == 0 { "./".to_string() } else { "../".repeat(ups) };
+            for segment in downs {
+                specifier.push_str(segment);
+                specifier.push('/');
+            }
+            specifier + stem
+        }
+        ImportStyle::Python => {
+            if ups == 0 && downs.is_empty() {
+                return stem.to_string();
+            }
+            let mut specifier = ".".repeat(ups + 1);
+            for segment in downs {
+                specifier.push_str(segment);
+                specifier.push('.');
+            }
+            specifier + stem
+        }
+    }
+}
+
+/// The URI (without extension) a relative module specifier refers to
+fn resolve(style: ImportStyle, importer: &str, module: &str) -> Option<String> {
+    let mut path = directory(importer);
+    match style {
+        ImportStyle::EsModule => {
+            if !module.starts_with('.') {
+                return None;
+            }
+            for segment in module.split('/') {
+                match segment {
+                    "." => {}
+                    ".." => {
+                        path.pop();
+                    }
+                    _ => path.push(segment),
+                }
+            }
+            let resolved = path.join("/");
+            let known = [".js", ".ts", ".jsx", ".tsx", ".mjs"];
+            Some(match known.iter().find_map(|ext| resolved.strip_suffix(ext)) {
+                Some(stem) => stem.to_string(),
+                None => resolved,
+            })
+        }
+        ImportStyle::Python => {
+            let rest = module.trim_start_matches('.');
+            let dots = module.len() - rest.len();
+            for _ in 1..dots {
+                path.pop();
+            }
+            path.extend(rest.split('.').filter(|s| !s.is_empty()));
+            Some(path.join("/"))
+        }
+    }
+}
+
+pub(crate) fn resolves_to(style: ImportStyle, importer: &str, module: &str, uri: &str) -> bool {
+    resolve(style, importer, module).is_some_and(|path| path == strip_extension(uri))
+}
+
+fn same_module(style: ImportStyle, importer: &str, a: &str, b: &str) -> bool {
+    a == b || matches!((resolve(style, importer, a), resolve(style, importer, b)), (Some(x), Some(y)) if x == y)
+}
+
+/// A specifier written in `from` as written in `to`: relative modules are
+/// rewritten for the new location, packages stay as they are
+fn rebase(style: ImportStyle, module: &str, from: &str, to: &str) -> String {
+    if !module.starts_with('.') {
+        return module.to_string();
+    }
+    match resolve(style, from, module) {
+        Some(path) => module_specifier(style, to, &path),
+        None => module.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import(module: &str, names: &[&str], line: u32) -> ModuleImport {
+        ModuleImport {
+            module_path: module.to_string(),
+            items: names.iter().map(|n| (n.to_string(), None)).collect(),
+            range: Range::from_coords(line, 0, line, 1),
+        }
+    }
+
+    #[test]
+    fn test_move_function_between_files() {
+        let source = "\
+import { readFile, writeFile } from './fs';
+
+const PREFIX = 'log:';
+
+function format(message) {
+  return PREFIX + readFile(message);
+}
+
+function save(message) {
+  writeFile(format(message));
+}
+";
+        let target = "import { format } from '../logger';\n\nexport function noop() {}\n";
+
+        let from = ModuleFile {
+            uri: "file:///src/logger.js",
+            source,
+            imports: vec![import("./fs", &["readFile", "writeFile"], 0)],
+            declarations: vec!["PREFIX".to_string(), "format".to_string(), "save".to_string()],
+        };
+        let to = ModuleFile {
+            uri: "file:///src/util/text.js",
+            source: target,
+            imports: vec![import("../logger", &["format"], 0)],
+            declarations: vec!["noop".to_string()],
+        };
+
+        let plan = plan(LanguageId::JavaScript, &from, &to, "format", Range::from_coords(4, 9, 4, 15)).unwrap();
+
+        assert_eq!(
+            plan.source.new_text,
+            "\
+import { writeFile } from './fs';
+import { format } from './util/text';
+
+export const PREFIX = 'log:';
+
+function save(message) {
+  writeFile(format(message));
+}
+"
+        );
+        assert_eq!(
+            plan.target.new_text,
+            "\
+import { PREFIX } from '../logger';
+import { readFile } from '../fs';
+
+export function noop() {}
+
+export function format(message) {
+  return PREFIX + readFile(message);
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_move_python_function_to_new_file() {
+        let source = "import os\n\ndef helper(path):\n    return os.path.basename(path)\n\nprint(helper('/tmp/x'))\n";
+        let from = ModuleFile {
+            uri: "file:///proj/main.py",
+            source,
+            imports: vec![import("os", &["os"], 0)],
+            declarations: vec!["helper".to_string()],
+        };
+        let to = ModuleFile {
+            uri: "file:///proj/helpers.py",
+            source: "",
+            imports: Vec::new(),
+            declarations: Vec::new(),
+        };
+
+        let plan = plan(LanguageId::Python, &from, &to, "helper", Range::from_coords(2, 4, 2, 10)).unwrap();
+        assert_eq!(plan.source.new_text, "from helpers import helper\n\nprint(helper('/tmp/x'))\n");
+        assert_eq!(
+            plan.target.new_text,
+            "import os\n\n\ndef helper(path):\n    return os.path.basename(path)\n"
+        );
+    }
+
+    #[test]
+    fn test_move_rejects_name_clash_and_unsupported_language() {
+        let file = |uri, declarations: &[&str]| ModuleFile {
+            uri,
+            source: "function a() {}\n",
+            imports: Vec::new(),
+            declarations: declarations.iter().map(|d| d.to_string()).collect(),
+        };
+        let (from, to) = (file("file:///a.js", &["a"]), file("file:///b.js", &["a"]));
+        let range = Range::from_coords(0, 9, 0, 10);
+
+        assert!(matches!(plan(LanguageId::JavaScript, &from, &to, "a", range), Err(RefactorError::CannotMove(_))));
+        assert!(matches!(
+            plan(LanguageId::Go, &from, &file("file:///b.go", &[]), "a", range),
+            Err(RefactorError::CannotMove(_))
+        ));
+    }
+
+    #[test]
+    fn test_module_specifiers() {
+        let es = ImportStyle::EsModule;
+        assert_eq!(module_specifier(es, "file:///a/b/x.ts", "file:///a/b/y"), "./y");
+        assert_eq!(module_specifier(es, "file:///a/b/x.ts", "file:///a/c/y"), "../c/y");
+        assert_eq!(rebase(es, "./lib/z", "file:///a/b/x.ts", "file:///a/y.ts"), "./b/lib/z");
+        assert_eq!(rebase(es, "react", "file:///a/b/x.ts", "file:///a/y.ts"), "react");
+        assert!(resolves_to(es, "file:///a/y.ts", "./b/x.js", "file:///a/b/x.ts"));
+
+        let py = ImportStyle::Python;
+        assert_eq!(module_specifier(py, "file:///p/a.py", "file:///p/b"), "b");
+        assert_eq!(module_specifier(py, "file:///p/a.py", "file:///p/sub/b"), ".sub.b");
+        assert_eq!(module_specifier(py, "file:///p/sub/a.py", "file:///p/b"), "..b");
+        assert!(resolves_to(py, "file:///p/sub/a.py", "..b", "file:///p/b.py"));
+    }
+}
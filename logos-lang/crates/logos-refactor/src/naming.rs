@@ -0,0 +1,142 @@
+//! Naming Conventions
+//!
+//! Names the refactorings make up themselves (suggested variables, extracted
+//! methods and constants) follow the language's usual casing: snake_case in
+//! Python and Rust, camelCase elsewhere. `NamingConfig` overrides the casing
+//! per kind of name for a team's house style, e.g. SCREAMING_SNAKE_CASE
+//! constants or exported PascalCase Go functions. Names the user types are
+//! used as written.
+
+use logos_parser::LanguageId;
+use serde::{Deserialize, Serialize};
+
+/// How the words of a name are joined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Case {
+    #[serde(rename = "camelCase")]
+    Camel,
+    #[serde(rename = "PascalCase")]
+    Pascal,
+    #[serde(rename = "snake_case")]
+    Snake,
+    #[serde(rename = "SCREAMING_SNAKE_CASE")]
+    ScreamingSnake,
+}
+
+impl Case {
+    /// The usual casing of variables and functions in a language
+    pub fn default_for(language: LanguageId) -> Self {
+        match language {
+            LanguageId::Python | LanguageId::Rust => Case::Snake,
+            _ => Case::Camel,
+        }
+    }
+
+    /// `name`, in any casing, rewritten in this one
+    pub fn apply(&self, name: &str) -> String {
+        self.join(&words(name))
+    }
+
+    /// Lowercase words joined in this casing
+    pub fn join(&self, words: &[String]) -> String {
+        match self {
+            Case::Snake => words.join("_"),
+            Case::ScreamingSnake => words.join("_").to_uppercase(),
+            Case::Camel | Case::Pascal => words
+                .iter()
+                .enumerate()
+                .map(|(i, word)| {
+                    if i == 0 && *self == Case::Camel {
+                        word.clone()
+                    } else {
+                        capitalize(word)
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Whether words are separated by `_`
+    pub fn is_snake(&self) -> bool {
+        matches!(self, Case::Snake | Case::ScreamingSnake)
+    }
+}
+
+/// Casing overrides for generated names; unset kinds use the language's
+/// default
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NamingConfig {
+    pub variable: Option<Case>,
+    pub constant: Option<Case>,
+    pub function: Option<Case>,
+}
+
+impl NamingConfig {
+    /// Casing of suggested variable names
+    pub fn variable_case(&self, language: LanguageId) -> Case {
+        self.variable.unwrap_or_else(|| Case::default_for(language))
+    }
+
+    /// Casing of suggested constant names; like variables unless overridden
+    pub fn constant_case(&self, language: LanguageId) -> Case {
+        self.constant.unwrap_or_else(|| Case::default_for(language))
+    }
+
+    /// Casing of generated function and method names
+    pub fn function_case(&self, language: LanguageId) -> Case {
+        self.function.unwrap_or_else(|| Case::default_for(language))
+    }
+}
+
+/// Split an identifier into lowercase words: `isReady` / `is_ready` /
+/// `IS_READY` -> `["is", "ready"]`
+pub(crate) fn words(name: &str) -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    let mut prev_lower = false;
+    for ch in name.chars() {
+        if ch == '_' {
+            prev_lower = false;
+            words.push(String::new());
+            continue;
+        }
+        if words.is_empty() || (ch.is_uppercase() && prev_lower) {
+            words.push(String::new());
+        }
+        words.last_mut().unwrap().push(ch.to_ascii_lowercase());
+        prev_lower = ch.is_lowercase() || ch.is_ascii_digit();
+    }
+    words.retain(|w| !w.is_empty());
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .map(|c| c.to_ascii_uppercase().to_string() + chars.as_str())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_case() {
+        assert_eq!(Case::Camel.apply("user_name"), "userName");
+        assert_eq!(Case::Pascal.apply("userName"), "UserName");
+        assert_eq!(Case::Snake.apply("HTTPRequest2"), "httprequest2");
+        assert_eq!(Case::ScreamingSnake.apply("maxRetries"), "MAX_RETRIES");
+        assert_eq!(Case::Camel.apply("value_0"), "value0");
+    }
+
+    #[test]
+    fn test_config_from_json() {
+        let config: NamingConfig =
+            serde_json::from_value(serde_json::json!({ "constant": "SCREAMING_SNAKE_CASE" })).unwrap();
+        assert_eq!(config.constant_case(LanguageId::Rust), Case::ScreamingSnake);
+        assert_eq!(config.variable_case(LanguageId::Rust), Case::Snake);
+        assert_eq!(config.function_case(LanguageId::Go), Case::Camel);
+    }
+}
@@ -0,0 +1,251 @@
+//! Operator Classification
+//!
+//! The naming and side-effect heuristics look at the operators of an
+//! expression as text. The same characters mean different things across
+//! languages: `<<` writes to a stream in C++ but shifts elsewhere, `..` builds
+//! a Rust range, and `=` inside `=>`, `..=` or `<=` assigns nothing. The
+//! operator table of each language lives here, and `operators_in` splits an
+//! expression into its operators, longest match first.
+
+use logos_parser::LanguageId;
+
+/// What an operator does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OperatorKind {
+    /// `+ - * / % **`, Python `//`
+    Arithmetic,
+    /// `& | ^ ~`, and shifts outside C++
+    Bitwise,
+    /// `== != < > <= >=`, JavaScript `=== !==`
+    Comparison,
+    /// `&& || !`, JavaScript `??`
+    Logical,
+    /// `=` and the compound assignments, Go `:=`, Python `:=`
+    Assignment,
+    /// `++ --`
+    Increment,
+    /// C++ `<<` and `>>` on streams
+    Stream,
+    /// Rust `..` and `..=`
+    Range,
+    /// Punctuation that reads as an operator but computes nothing by
+    /// itself: `=>`, `->`, `::`, `?.`, Rust `?`, Go `<-`
+    Other,
+}
+
+const COMMON: &[(&str, OperatorKind)] = &[
+    ("+", OperatorKind::Arithmetic),
+    ("-", OperatorKind::Arithmetic),
+    ("*", OperatorKind::Arithmetic),
+    ("/", OperatorKind::Arithmetic),
+    ("%", OperatorKind::Arithmetic),
+    ("&", OperatorKind::Bitwise),
+    ("|", OperatorKind::Bitwise),
+    ("^", OperatorKind::Bitwise),
+    ("~", OperatorKind::Bitwise),
+    ("==", OperatorKind::Comparison),
+    ("!=", OperatorKind::Comparison),
+    ("<", OperatorKind::Comparison),
+    (">", OperatorKind::Comparison),
+    ("<=", OperatorKind::Comparison),
+    (">=", OperatorKind::Comparison),
+    ("&&", OperatorKind::Logical),
+    ("||", OperatorKind::Logical),
+    ("!", OperatorKind::Logical),
+    ("=", OperatorKind::Assignment),
+    ("+=", OperatorKind::Assignment),
+    ("-=", OperatorKind::Assignment),
+    ("*=", OperatorKind::Assignment),
+    ("/=", OperatorKind::Assignment),
+    ("%=", OperatorKind::Assignment),
+    ("&=", OperatorKind::Assignment),
+    ("|=", OperatorKind::Assignment),
+    ("^=", OperatorKind::Assignment),
+    ("<<=", OperatorKind::Assignment),
+    (">>=", OperatorKind::Assignment),
+    ("->", OperatorKind::Other),
+    ("=>", OperatorKind::Other),
+];
+
+/// The operators of `language`, longest first
+pub fn operators(language: LanguageId) -> Vec<(&'static str, OperatorKind)> {
+    let shift = match language {
+        LanguageId::Cpp => OperatorKind::Stream,
+        _ => OperatorKind::Bitwise,
+    };
+    let mut table = COMMON.to_vec();
+    table.extend([("<<", shift), (">>", shift)]);
+
+    let extra: &[(&str, OperatorKind)] = match language {
+        LanguageId::JavaScript | LanguageId::TypeScript => &[
+            ("===", OperatorKind::Comparison),
+            ("!==", OperatorKind::Comparison),
+            ("**", OperatorKind::Arithmetic),
+            ("**=", OperatorKind::Assignment),
+            (">>>", OperatorKind::Bitwise),
+            (">>>=", OperatorKind::Assignment),
+            ("??", OperatorKind::Logical),
+            ("??=", OperatorKind::Assignment),
+            ("&&=", OperatorKind::Assignment),
+            ("||=", OperatorKind::Assignment),
+            ("++", OperatorKind::Increment),
+            ("--", OperatorKind::Increment),
+            ("?.", OperatorKind::Other),
+        ],
+        LanguageId::Python => &[
+            ("**", OperatorKind::Arithmetic),
+            ("//", OperatorKind::Arithmetic),
+            ("@", OperatorKind::Arithmetic),
+            ("**=", OperatorKind::Assignment),
+            ("//=", OperatorKind::Assignment),
+            ("@=", OperatorKind::Assignment),
+            (":=", OperatorKind::Assignment),
+        ],
+        LanguageId::Rust => &[
+            ("..", OperatorKind::Range),
+            ("..=", OperatorKind::Range),
+            ("::", OperatorKind::Other),
+            ("?", OperatorKind::Other),
+        ],
+        LanguageId::Go => &[
+            (":=", OperatorKind::Assignment),
+            ("&^", OperatorKind::Bitwise),
+            ("&^=", OperatorKind::Assignment),
+            ("++", OperatorKind::Increment),
+            ("--", OperatorKind::Increment),
+            ("<-", OperatorKind::Other),
+        ],
+        LanguageId::Java => &[
+            (">>>", OperatorKind::Bitwise),
+            (">>>=", OperatorKind::Assignment),
+            ("++", OperatorKind::Increment),
+            ("--", OperatorKind::Increment),
+            ("::", OperatorKind::Other),
+        ],
+        LanguageId::C | LanguageId::Cpp => &[
+            ("++", OperatorKind::Increment),
+            ("--", OperatorKind::Increment),
+            ("::", OperatorKind::Other),
+        ],
+    };
+    table.extend_from_slice(extra);
+
+    table.sort_by_key(|(op, _)| std::cmp::Reverse(op.len()));
+    table
+}
+
+/// The operators in `text` with their byte offsets. String literals and the
+/// word operators (`and`, `not`, `is`) are skipped.
+pub fn operators_in(text: &str, language: LanguageId) -> Vec<(usize, &'static str, OperatorKind)> {
+    let table = operators(language);
+    let mut found = Vec::new();
+    let mut quote: Option<char> = None;
+    let mut prev = ' ';
+    let mut i = 0;
+
+    while let Some(ch) = text[i..].chars().next() {
+        if let Some(q) = quote {
+            if ch == q && prev != '\\' {
+                quote = None;
+            }
+        } else if matches!(ch, '"' | '`') || (ch == '\'' && language != LanguageId::Rust) {
+            quote = Some(ch);
+        } else if let Some(len) = (language == LanguageId::Rust).then(|| rust_char_literal(&text[i..])).flatten() {
+            i += len;
+            prev = '\'';
+            continue;
+        } else if let Some(&(op, kind)) = table.iter().find(|(op, _)| text[i..].starts_with(op)) {
+            found.push((i, op, kind));
+            i += op.len();
+            prev = op.chars().last().unwrap_or(' ');
+            continue;
+        }
+        prev = ch;
+        i += ch.len_utf8();
+    }
+
+    found
+}
+
+/// The length of the Rust char literal `text` starts with (`'x'`, `'\n'`,
+/// `'\''`); `None` for a lifetime (`'a`) or anything else
+fn rust_char_literal(text: &str) -> Option<usize> {
+    let body = text.strip_prefix('\'')?;
+    let first = body.chars().next()?;
+    let end = if first == '\\' {
+        // The escaped character may itself be a quote
+        let escaped = body[1..].chars().next()?;
+        let from = 1 + escaped.len_utf8();
+        from + body[from..].find('\'')?
+    } else if body[first.len_utf8()..].starts_with('\'') {
+        first.len_utf8()
+    } else {
+        return None;
+    };
+    Some(1 + end + 1)
+}
+
+/// Whether `text` uses an operator of the kind
+pub fn has_operator(text: &str, language: LanguageId, kind: OperatorKind) -> bool {
+    operators_in(text, language)
+        .iter()
+        .any(|&(_, _, k)| k == kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(text: &str, language: LanguageId) -> Vec<(&'static str, OperatorKind)> {
+        operators_in(text, language)
+            .into_iter()
+            .map(|(_, op, kind)| (op, kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_longest_operator_wins() {
+        assert_eq!(
+            kinds("a <<= b", LanguageId::Cpp),
+            vec![("<<=", OperatorKind::Assignment)]
+        );
+        assert_eq!(
+            kinds("std::cout << x", LanguageId::Cpp),
+            vec![("::", OperatorKind::Other), ("<<", OperatorKind::Stream)]
+        );
+        assert_eq!(
+            kinds("a << b", LanguageId::Java),
+            vec![("<<", OperatorKind::Bitwise)]
+        );
+        assert_eq!(
+            kinds("0..=n", LanguageId::Rust),
+            vec![("..=", OperatorKind::Range)]
+        );
+        assert_eq!(
+            kinds("x => x === \"=\"", LanguageId::JavaScript),
+            vec![
+                ("=>", OperatorKind::Other),
+                ("===", OperatorKind::Comparison)
+            ]
+        );
+        assert_eq!(kinds("'=' if a else b", LanguageId::Python), vec![]);
+        assert_eq!(kinds("s.split('=')", LanguageId::Rust), vec![]);
+        assert_eq!(
+            kinds("c == '\\'' || c == '\\n'", LanguageId::Rust),
+            vec![
+                ("==", OperatorKind::Comparison),
+                ("||", OperatorKind::Logical),
+                ("==", OperatorKind::Comparison)
+            ]
+        );
+        // A lifetime is not a char literal
+        assert_eq!(
+            kinds("&'a str -> &'a str", LanguageId::Rust),
+            vec![
+                ("&", OperatorKind::Bitwise),
+                ("->", OperatorKind::Other),
+                ("&", OperatorKind::Bitwise)
+            ]
+        );
+    }
+}
@@ -0,0 +1,485 @@
+//! Organize Imports
+//!
+//! Rewrite the import block at the top of a document in one edit: imports
+//! none of whose names are used go, unused names leave the imports that keep
+//! others, and the remaining imports are sorted by module within groups
+//! (standard library, third-party, local) separated by a blank line.
+//! Comments between imports move with the import below them. Side-effect
+//! imports (`import './polyfill'`) stay where they are, as their order can
+//! matter; the imports between them are sorted on their own.
+//!
+//! Supported for JavaScript/TypeScript, Python and Go, from the imports the
+//! language adapter reports.
+
+use crate::move_symbol::{local_name, render, ImportFormat, ImportStyle, ImportedItem, ModuleImport};
+use crate::{offset_to_position, position_to_offset, RefactorContext, RefactorError, RefactorResult, TextEdit};
+use logos_core::{Position, Range, Symbol, SymbolKind};
+use logos_parser::LanguageId;
+use logos_semantic::{UnusedDetector, UnusedKind};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// A group of imports, by where the imported module comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportGroup {
+    /// The language's standard library (Node built-ins for JavaScript)
+    Std,
+    ThirdParty,
+    /// Relative imports
+    Local,
+}
+
+/// The order of the import groups; groups left out follow, in the default
+/// order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ImportOrder(pub Vec<ImportGroup>);
+
+impl Default for ImportOrder {
+    fn default() -> Self {
+        Self(vec![ImportGroup::Std, ImportGroup::ThirdParty, ImportGroup::Local])
+    }
+}
+
+impl ImportOrder {
+    fn rank(&self, group: ImportGroup) -> usize {
+        match self.0.iter().position(|g| *g == group) {
+            Some(rank) => rank,
+            None => self.0.len() + Self::default().rank(group),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Syntax {
+    EsModule,
+    Python,
+    Go,
+}
+
+/// An import statement (a spec in Go) as it will be written
+struct Statement {
+    module: String,
+    /// The statement with the comments above it
+    text: String,
+    /// Sort key: `from __future__` imports come before every group
+    rank: usize,
+    side_effect: bool,
+}
+
+const NODE_BUILTINS: &[&str] = &[
+    "assert", "buffer", "child_process", "cluster", "crypto", "dns", "events", "fs", "http", "http2", "https",
+    "module", "net", "os", "path", "perf_hooks", "process", "querystring", "readline", "stream", "string_decoder",
+    "timers", "tls", "url", "util", "vm", "worker_threads", "zlib",
+];
+
+const PYTHON_STDLIB: &[&str] = &[
+    "abc", "argparse", "array", "ast", "asyncio", "base64", "bisect", "builtins", "calendar", "collections",
+    "contextlib", "copy", "csv", "dataclasses", "datetime", "decimal", "difflib", "email", "enum", "fractions",
+    "functools", "gc", "glob", "gzip", "hashlib", "heapq", "hmac", "html", "http", "importlib", "inspect", "io",
+    "itertools", "json", "logging", "math", "multiprocessing", "operator", "os", "pathlib", "pickle", "platform",
+    "pprint", "queue", "random", "re", "secrets", "shlex", "shutil", "signal", "socket", "sqlite3", "statistics",
+    "string", "struct", "subprocess", "sys", "tarfile", "tempfile", "textwrap", "threading", "time", "timeit",
+    "traceback", "types", "typing", "unittest", "urllib", "uuid", "warnings", "weakref", "xml", "zipfile",
+];
+
+/// Organize the imports of `ctx`'s document; `imports` are those the
+/// language adapter found in it
+pub fn organize(ctx: &RefactorContext, imports: &[ModuleImport], order: &ImportOrder) -> Result<RefactorResult, RefactorError> {
+    let syntax = match ctx.language {
+        LanguageId::JavaScript | LanguageId::TypeScript => Syntax::EsModule,
+        LanguageId::Python => Syntax::Python,
+        LanguageId::Go => Syntax::Go,
+        _ => {
+            return Err(RefactorError::Unsupported(format!(
+                "Organize Imports is not supported for {} files",
+                ctx.language.as_str()
+            )))
+        }
+    };
+    let source = ctx.source;
+    let lines: Vec<&str> = source.split('\n').map(|l| l.trim_end_matches('\r')).collect();
+
+    let mut sorted: Vec<&ModuleImport> = imports.iter().collect();
+    sorted.sort_by_key(|import| (import.range.start.line, import.range.start.column));
+    sorted.dedup_by_key(|import| import.range);
+    let Some(first) = sorted.first() else {
+        return Ok(RefactorResult::new(Vec::new(), "No imports to organize".to_string()));
+    };
+
+    // The block: imports separated only by blank lines, comments and the
+    // parentheses of Go import declarations
+    let block_start = match syntax {
+        Syntax::Go => (0..=first.range.start.line as usize)
+            .rev()
+            .find(|&line| lines[line].trim_start().starts_with("import"))
+            .unwrap_or(first.range.start.line as usize),
+        _ => first.range.start.line as usize,
+    };
+    let mut block: Vec<&ModuleImport> = vec![first];
+    for import in &sorted[1..] {
+        let previous_end = block.last().unwrap().range.end.line as usize;
+        let start = import.range.start.line as usize;
+        if start == previous_end {
+            return Err(RefactorError::Unsupported("Several imports share a line".to_string()));
+        }
+        if !lines[previous_end + 1..start].iter().all(|line| is_gap(line, syntax)) {
+            break;
+        }
+        block.push(import);
+    }
+    let last_import_line = block.last().unwrap().range.end.line as usize;
+    let block_end = match syntax {
+        Syntax::Go if !lines[last_import_line].trim_start().starts_with("import") => (last_import_line + 1..lines.len())
+            .find(|&line| lines[line].trim() == ")")
+            .unwrap_or(last_import_line),
+        _ => last_import_line,
+    };
+
+    let unused = unused_names(ctx, &block, syntax);
+    let style = match syntax {
+        Syntax::Python => ImportStyle::Python,
+        _ => ImportStyle::EsModule,
+    };
+
+    let mut statements = Vec::new();
+    let mut comments: Vec<&str> = Vec::new();
+    let mut gap_start = block_start + usize::from(syntax == Syntax::Go && block_start < first.range.start.line as usize);
+    for import in &block {
+        let start = import.range.start.line as usize;
+        comments.extend(
+            lines[gap_start..start]
+                .iter()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty() && is_comment(line, syntax)),
+        );
+        gap_start = import.range.end.line as usize + 1;
+
+        let kept: Vec<ImportedItem> = import
+            .items
+            .iter()
+            .filter(|item| binding(syntax, import, item).is_none_or(|name| !unused.contains(name)))
+            .cloned()
+            .collect();
+        if !import.items.is_empty() && kept.is_empty() {
+            // The comments above a dropped import stay for the next one
+            continue;
+        }
+
+        let from = position_to_offset(source, import.range.start);
+        let to = position_to_offset(source, import.range.end);
+        let line_end = source[to..].find('\n').map(|i| to + i).unwrap_or(source.len());
+        let mut text = if kept.len() == import.items.len() {
+            source[from..to].to_string()
+        } else {
+            let rendered = render(style, &import.module_path, &kept, &ImportFormat::of_statement(style, Some(&source[from..to])));
+            match source[from..].strip_prefix("import type ") {
+                Some(_) => rendered.replacen("import ", "import type ", 1),
+                None => rendered,
+            }
+        };
+        text.push_str(source[to..line_end].trim_end());
+
+        let mut with_comments: Vec<&str> = std::mem::take(&mut comments);
+        with_comments.push(&text);
+        let future = syntax == Syntax::Python && import.module_path == "__future__";
+        statements.push(Statement {
+            module: import.module_path.clone(),
+            text: with_comments.join("\n"),
+            rank: if future { 0 } else { 1 + order.rank(group(syntax, &import.module_path)) },
+            side_effect: syntax == Syntax::EsModule && import.items.is_empty() && !text.contains(" from "),
+        });
+    }
+
+    let mut new_text = write_block(statements, syntax);
+    if new_text.is_empty() {
+        // Everything went: drop the blank lines that followed the block too
+        let mut end = block_end;
+        while end + 1 < lines.len() - 1 && lines[end + 1].trim().is_empty() {
+            end += 1;
+        }
+        let range = Range::new(Position::new(block_start as u32, 0), Position::new(end as u32 + 1, 0));
+        return Ok(RefactorResult::new(vec![TextEdit::delete(range)], "Remove unused imports".to_string()));
+    }
+    let block_range = Range::new(
+        Position::new(block_start as u32, 0),
+        offset_to_position(source, line_offset(source, block_end) + lines[block_end].len()),
+    );
+    let old_text = ctx.text_in_range(block_range);
+    if new_text == old_text {
+        return Ok(RefactorResult::new(Vec::new(), "Imports are already organized".to_string()));
+    }
+    if old_text.contains('\r') {
+        new_text = new_text.replace('\n', "\r\n");
+    }
+    Ok(RefactorResult::new(vec![TextEdit::replace(block_range, new_text)], "Organize imports".to_string()))
+}
+
+/// The sorted statements, groups separated by a blank line
+fn write_block(statements: Vec<Statement>, syntax: Syntax) -> String {
+    // Sort between side-effect imports
+    let mut pieces: Vec<String> = Vec::new();
+    let mut segment: Vec<Statement> = Vec::new();
+    let flush = |segment: &mut Vec<Statement>, pieces: &mut Vec<String>| {
+        segment.sort_by_key(|statement| (statement.rank, statement.module.to_lowercase()));
+        let mut text = String::new();
+        for (i, statement) in segment.iter().enumerate() {
+            if i > 0 {
+                text.push('\n');
+                if segment[i - 1].rank != statement.rank {
+                    text.push('\n');
+                }
+            }
+            text.push_str(&statement.text);
+        }
+        if !text.is_empty() {
+            pieces.push(text);
+        }
+        segment.clear();
+    };
+    for statement in statements {
+        if statement.side_effect {
+            flush(&mut segment, &mut pieces);
+            pieces.push(statement.text);
+        } else {
+            segment.push(statement);
+        }
+    }
+    flush(&mut segment, &mut pieces);
+    let text = pieces.join("\n");
+
+    match syntax {
+        Syntax::Go if text.contains('\n') => {
+            let specs: Vec<String> = text
+                .split('\n')
+                .map(|line| if line.is_empty() { String::new() } else { format!("\t{}", line) })
+                .collect();
+            format!("import (\n{}\n)", specs.join("\n"))
+        }
+        Syntax::Go if !text.is_empty() => format!("import {}", text),
+        _ => text,
+    }
+}
+
+/// Local names of the imports in `block` that the rest of the document
+/// never mentions
+fn unused_names(ctx: &RefactorContext, block: &[&ModuleImport], syntax: Syntax) -> HashSet<String> {
+    let symbols: Vec<Symbol> = block
+        .iter()
+        .flat_map(|import| {
+            import.items.iter().filter_map(move |item| {
+                Some(Symbol {
+                    name: binding(syntax, import, item)?.to_string(),
+                    kind: SymbolKind::Module,
+                    range: import.range,
+                    selection_range: import.range,
+                    detail: None,
+                    children: Vec::new(),
+                })
+            })
+        })
+        .collect();
+    UnusedDetector::new()
+        .analyze(&symbols, ctx.source)
+        .into_iter()
+        .filter(|item| item.kind == UnusedKind::Import)
+        .map(|item| item.name)
+        .collect()
+}
+
+/// The name an imported item binds; `None` for items that bind none or that
+/// are imported for their side effects
+fn binding<'i>(syntax: Syntax, import: &ModuleImport, item: &'i ImportedItem) -> Option<&'i str> {
+    match syntax {
+        Syntax::Go => match item.1.as_deref() {
+            Some("_" | ".") => None,
+            Some(alias) => Some(alias),
+            None => item.0.rsplit('/').next(),
+        },
+        Syntax::Python => Some(local_name(ImportStyle::Python, import, item)).filter(|name| *name != "*"),
+        Syntax::EsModule => Some(local_name(ImportStyle::EsModule, import, item)).filter(|name| *name != "*"),
+    }
+}
+
+fn group(syntax: Syntax, module: &str) -> ImportGroup {
+    let std = match syntax {
+        Syntax::EsModule => {
+            if module.starts_with('.') || module.starts_with('/') {
+                return ImportGroup::Local;
+            }
+            module.starts_with("node:") || NODE_BUILTINS.contains(&module.split('/').next().unwrap_or(module))
+        }
+        Syntax::Python => {
+            if module.starts_with('.') {
+                return ImportGroup::Local;
+            }
+            PYTHON_STDLIB.contains(&module.split('.').next().unwrap_or(module))
+        }
+        Syntax::Go => {
+            if module.starts_with("./") || module.starts_with("../") {
+                return ImportGroup::Local;
+            }
+            !module.split('/').next().unwrap_or(module).contains('.')
+        }
+    };
+    if std {
+        ImportGroup::Std
+    } else {
+        ImportGroup::ThirdParty
+    }
+}
+
+fn is_comment(line: &str, syntax: Syntax) -> bool {
+    match syntax {
+        Syntax::Python => line.starts_with('#'),
+        _ => line.starts_with("//"),
+    }
+}
+
+/// Whether a line between two imports keeps them in one block
+fn is_gap(line: &str, syntax: Syntax) -> bool {
+    let line = line.trim();
+    line.is_empty()
+        || is_comment(line, syntax)
+        || (syntax == Syntax::Go && (line == ")" || line == "import ("))
+}
+
+fn line_offset(source: &str, line: usize) -> usize {
+    position_to_offset(source, Position::new(line as u32, 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apply_edits;
+
+    /// The import on `line` of `source`, spanning the trimmed line
+    fn import(source: &str, line: u32, module: &str, items: &[(&str, Option<&str>)]) -> ModuleImport {
+        let text = source.lines().nth(line as usize).unwrap();
+        let indent = (text.len() - text.trim_start().len()) as u32;
+        ModuleImport {
+            module_path: module.to_string(),
+            items: items.iter().map(|(n, a)| (n.to_string(), a.map(str::to_string))).collect(),
+            range: Range::from_coords(line, indent, line, text.trim_end().len() as u32),
+        }
+    }
+
+    fn organized(source: &str, language: LanguageId, imports: &[ModuleImport]) -> String {
+        let ctx = RefactorContext::new(source, "test", Range::point(0, 0), language);
+        let result = organize(&ctx, imports, &ImportOrder::default()).unwrap();
+        apply_edits(source, &result.edits)
+    }
+
+    #[test]
+    fn test_remove_unused_python_imports() {
+        let source = "\
+import sys
+from .models import User, Order
+# paths
+import os
+
+print(os.getcwd(), User)
+";
+        let imports = [
+            import(source, 0, "sys", &[("sys", None)]),
+            import(source, 1, ".models", &[("User", None), ("Order", None)]),
+            import(source, 3, "os", &[("os", None)]),
+        ];
+        assert_eq!(
+            organized(source, LanguageId::Python, &imports),
+            "\
+# paths
+import os
+
+from .models import User
+
+print(os.getcwd(), User)
+"
+        );
+    }
+
+    #[test]
+    fn test_reorder_mixed_es_imports() {
+        let source = "\
+import { b } from './local';
+import React from 'react';
+import fs from 'node:fs';
+import './styles.css';
+import { z } from 'zod';
+import path from 'path';
+
+render(React, b, fs, z, path);
+";
+        let imports = [
+            import(source, 0, "./local", &[("b", None)]),
+            import(source, 1, "react", &[("default", Some("React"))]),
+            import(source, 2, "node:fs", &[("default", Some("fs"))]),
+            import(source, 3, "./styles.css", &[]),
+            import(source, 4, "zod", &[("z", None)]),
+            import(source, 5, "path", &[("default", Some("path"))]),
+        ];
+        assert_eq!(
+            organized(source, LanguageId::TypeScript, &imports),
+            "\
+import fs from 'node:fs';
+
+import React from 'react';
+
+import { b } from './local';
+import './styles.css';
+import path from 'path';
+
+import { z } from 'zod';
+
+render(React, b, fs, z, path);
+"
+        );
+
+        // Organizing again changes nothing
+        let again = organized(source, LanguageId::TypeScript, &imports);
+        let reordered: Vec<ModuleImport> = imports
+            .iter()
+            .map(|i| {
+                let line = again.lines().position(|l| l.contains(&format!("'{}'", i.module_path))).unwrap() as u32;
+                import(&again, line, &i.module_path, &i.items.iter().map(|(n, a)| (n.as_str(), a.as_deref())).collect::<Vec<_>>())
+            })
+            .collect();
+        let ctx = RefactorContext::new(&again, "test", Range::point(0, 0), LanguageId::TypeScript);
+        assert!(organize(&ctx, &reordered, &ImportOrder::default()).unwrap().edits.is_empty());
+    }
+
+    #[test]
+    fn test_go_import_groups() {
+        let source = "\
+package main
+
+import (
+\t\"github.com/acme/log\"
+\t\"os\"
+\t\"fmt\"
+)
+
+func main() { log.Print(os.Args) }
+";
+        let imports = [
+            import(source, 3, "github.com/acme/log", &[("github.com/acme/log", None)]),
+            import(source, 4, "os", &[("os", None)]),
+            import(source, 5, "fmt", &[("fmt", None)]),
+        ];
+        assert_eq!(
+            organized(source, LanguageId::Go, &imports),
+            "\
+package main
+
+import (
+\t\"os\"
+
+\t\"github.com/acme/log\"
+)
+
+func main() { log.Print(os.Args) }
+"
+        );
+    }
+}
@@ -0,0 +1,883 @@
+//! Rename Refactoring
+//!
+//! Rename the symbol under the cursor across a set of documents. The
+//! identifier is resolved to its declaration with the syntax tree, and only
+//! the references to that declaration change: the same name bound in another
+//! function, or used as a property, is left alone. A top-level declaration is
+//! also renamed in the documents that import it. The same plan backs both the
+//! preview and the applied rename, so the two can't diverge.
+//!
+//! Fields, methods and properties are renamed by name wherever they are
+//! accessed as members.
+//!
+//! A rename is refused when the new name is already bound in the scope of the
+//! renamed symbol, or in a scope inside it that holds one of its references:
+//! the renamed occurrences would clash with that binding or be captured by it.
+
+use std::sync::LazyLock;
+
+use crate::analysis::is_keyword;
+use crate::{apply_edits, offset_to_position, position_to_offset, RefactorError, TextEdit, WorkspaceEdit};
+use logos_core::{Location, Position, Range};
+use logos_parser::{LanguageId, LanguageParser};
+use regex::Regex;
+use tree_sitter::{Node, Tree};
+
+static IDENTIFIER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[A-Za-z_$][A-Za-z0-9_$]*$").unwrap());
+
+/// Node kinds naming a variable, function or type
+const NAME_KINDS: &[&str] = &[
+    "identifier",
+    "type_identifier",
+    "shorthand_property_identifier",
+    "shorthand_property_identifier_pattern",
+];
+
+/// Node kinds naming a field, method or property
+const MEMBER_KINDS: &[&str] = &["property_identifier", "field_identifier", "private_property_identifier"];
+
+/// Node kinds holding parameters, directly or as their name
+const PARAMETER_KINDS: &[&str] = &[
+    "formal_parameters",
+    "parameters",
+    "parameter_list",
+    "lambda_parameters",
+    "closure_parameters",
+    "formal_parameter",
+    "parameter",
+    "parameter_declaration",
+    "variadic_parameter_declaration",
+    "optional_parameter_declaration",
+    "spread_parameter",
+];
+
+/// Node kinds whose body holds members rather than locals
+const CLASS_SCOPE_KINDS: &[&str] = &[
+    "class_body",
+    "class_definition",
+    "declaration_list",
+    "field_declaration_list",
+    "enum_body",
+    "interface_body",
+];
+
+/// Node kinds importing names into a module
+const IMPORT_KINDS: &[&str] = &[
+    "import_statement",
+    "import_from_statement",
+    "import_declaration",
+    "use_declaration",
+];
+
+/// A document taking part in a rename
+#[derive(Debug, Clone, Copy)]
+pub struct RenameDocument<'a> {
+    pub uri: &'a str,
+    pub source: &'a str,
+    /// Language of the document, if known; decides the comment syntax
+    pub language: Option<LanguageId>,
+}
+
+/// The rename edits for one document
+#[derive(Debug, Clone)]
+pub struct FileRename {
+    pub uri: String,
+    /// Edits in document order
+    pub edits: Vec<TextEdit>,
+    /// The document text with the edits applied
+    pub new_text: String,
+}
+
+/// All edits of a rename, for the documents that change
+#[derive(Debug, Clone, Default)]
+pub struct RenamePlan {
+    /// Affected documents, sorted by URI
+    pub files: Vec<FileRename>,
+}
+
+impl RenamePlan {
+    /// The edits of every affected document
+    pub fn workspace_edit(&self) -> WorkspaceEdit {
+        let mut edit = WorkspaceEdit::new();
+        for file in &self.files {
+            edit.insert(file.uri.clone(), file.edits.clone());
+        }
+        edit
+    }
+
+    /// Total number of renamed occurrences
+    pub fn occurrences(&self) -> usize {
+        self.files.iter().map(|f| f.edits.len()).sum()
+    }
+}
+
+/// Find the identifier under `position` and its range
+pub fn identifier_at(source: &str, position: Position) -> Option<(String, Range)> {
+    let offset = position_to_offset(source, position);
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+
+    let start = source[..offset]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_ident(*c))
+        .last()
+        .map(|(i, _)| i)
+        .unwrap_or(offset);
+    let end = source[offset..]
+        .char_indices()
+        .find(|(_, c)| !is_ident(*c))
+        .map(|(i, _)| offset + i)
+        .unwrap_or(source.len());
+
+    if start == end {
+        return None;
+    }
+    let range = Range::new(offset_to_position(source, start), offset_to_position(source, end));
+    Some((source[start..end].to_string(), range))
+}
+
+/// Plan renaming the symbol at `position` in the document `uri` to `new_name`
+pub fn plan(
+    documents: &[RenameDocument],
+    uri: &str,
+    position: Position,
+    new_name: &str,
+) -> Result<RenamePlan, RefactorError> {
+    if !IDENTIFIER.is_match(new_name) {
+        return Err(RefactorError::InvalidSelection(format!(
+            "'{}' is not a valid identifier",
+            new_name
+        )));
+    }
+    if let Some(doc) = documents
+        .iter()
+        .find(|d| d.language.is_some_and(|l| is_keyword(new_name, l)))
+    {
+        return Err(RefactorError::InvalidSelection(format!(
+            "'{}' is a keyword in {}",
+            new_name,
+            doc.language.unwrap().as_str()
+        )));
+    }
+
+    let origin = documents
+        .iter()
+        .position(|d| d.uri == uri)
+        .ok_or_else(|| RefactorError::InvalidSelection(format!("'{}' is not open", uri)))?;
+    let (old_name, _) = identifier_at(documents[origin].source, position)
+        .ok_or_else(|| RefactorError::InvalidSelection("No identifier at the cursor".to_string()))?;
+
+    let trees: Vec<Option<Tree>> = documents.iter().map(parse).collect();
+    let renames = match &trees[origin] {
+        Some(tree) => {
+            let offset = position_to_offset(documents[origin].source, position);
+            resolve_renames(documents, &trees, origin, tree, offset, &old_name, new_name)?
+        }
+        None => textual_rename(documents, origin, &old_name, new_name)?,
+    };
+
+    let mut files: Vec<FileRename> = renames
+        .into_iter()
+        .filter(|(_, edits)| !edits.is_empty())
+        .map(|(index, edits)| {
+            let doc = &documents[index];
+            FileRename {
+                uri: doc.uri.to_string(),
+                new_text: apply_edits(doc.source, &edits),
+                edits,
+            }
+        })
+        .collect();
+
+    files.sort_by(|a, b| a.uri.cmp(&b.uri));
+    Ok(RenamePlan { files })
+}
+
+fn parse(doc: &RenameDocument) -> Option<Tree> {
+    let mut parser = LanguageParser::new();
+    parser.set_language(doc.language?).ok()?;
+    parser.parse(doc.source, None).ok()
+}
+
+/// The edits of each document, by index, renaming the symbol the name at
+/// byte `offset` of the origin document refers to
+fn resolve_renames(
+    documents: &[RenameDocument],
+    trees: &[Option<Tree>],
+    origin: usize,
+    tree: &Tree,
+    offset: usize,
+    old_name: &str,
+    new_name: &str,
+) -> Result<Vec<(usize, Vec<TextEdit>)>, RefactorError> {
+    let source = documents[origin].source;
+    let language = documents[origin].language.unwrap_or(LanguageId::JavaScript);
+    let names = Names::collect(tree.root_node(), source, old_name, language);
+    let at_cursor = names
+        .nodes
+        .iter()
+        .find(|(node, _)| node.start_byte() <= offset && offset <= node.end_byte())
+        .ok_or_else(|| RefactorError::InvalidSelection(format!("'{}' can't be renamed here", old_name)))?;
+
+    let is_member = match at_cursor.1 {
+        Role::Member => true,
+        Role::Binding(_) | Role::Reference => names.resolve(at_cursor.0).is_some_and(|s| is_class_scope(&s)),
+        Role::Other => {
+            return Err(RefactorError::InvalidSelection(format!("'{}' can't be renamed here", old_name)));
+        }
+    };
+
+    let parsed = || {
+        documents
+            .iter()
+            .zip(trees)
+            .enumerate()
+            .filter_map(|(index, (doc, tree))| Some((index, doc, tree.as_ref()?)))
+    };
+
+    if is_member {
+        let mut collisions = Vec::new();
+        let mut renames = Vec::new();
+        for (index, doc, tree) in parsed() {
+            let language = doc.language.unwrap_or(LanguageId::JavaScript);
+            let names = Names::collect(tree.root_node(), doc.source, old_name, language);
+            let members: Vec<Node> = names.nodes.iter().map(|(n, _)| *n).filter(|&n| names.is_member(n)).collect();
+            if members.is_empty() {
+                continue;
+            }
+            let taken = Names::collect(tree.root_node(), doc.source, new_name, language);
+            let classes: Vec<std::ops::Range<usize>> = names.bindings().filter(is_class_scope).map(|s| s.byte_range()).collect();
+            collisions.extend(
+                taken
+                    .first_binding_per_scope()
+                    .filter(|(_, scope)| classes.contains(&scope.byte_range()))
+                    .map(|(node, _)| location(doc, node)),
+            );
+            renames.push((index, members.into_iter().map(|n| rename_edit(doc.source, n, new_name, false)).collect()));
+        }
+        return finish(renames, collisions, old_name, new_name);
+    }
+
+    let scope = names.resolve(at_cursor.0).map(|s| s.byte_range());
+    let root = tree.root_node();
+    let mut targets = vec![(origin, scope.clone())];
+
+    // A top-level symbol is renamed where it is declared and where that is imported
+    if scope == Some(root.byte_range()) {
+        let home = match names.import(root) {
+            Some(import) => parsed().find(|&(index, doc, tree)| {
+                index != origin
+                    && mentions(&source[import.clone()], stem(doc.uri))
+                    && Names::collect(tree.root_node(), doc.source, old_name, doc.language.unwrap_or(language))
+                        .declares_at_top_level(tree.root_node())
+            }),
+            None => parsed().find(|&(index, ..)| index == origin),
+        };
+        if let Some((home, home_doc, _)) = home {
+            targets = parsed()
+                .filter(|&(index, doc, tree)| {
+                    let root = tree.root_node();
+                    index == home
+                        || Names::collect(root, doc.source, old_name, doc.language.unwrap_or(language))
+                            .import(root)
+                            .is_some_and(|import| mentions(&doc.source[import], stem(home_doc.uri)))
+                })
+                .map(|(index, _, tree)| (index, Some(tree.root_node().byte_range())))
+                .collect();
+        }
+    }
+
+    let mut collisions = Vec::new();
+    let mut renames = Vec::new();
+    for (index, scope) in targets {
+        let (doc, tree) = (&documents[index], trees[index].as_ref().expect("targets are parsed"));
+        let language = doc.language.unwrap_or(language);
+        let names = Names::collect(tree.root_node(), doc.source, old_name, language);
+        let references: Vec<Node> = names
+            .nodes
+            .iter()
+            .filter(|(node, role)| !matches!(role, Role::Member | Role::Other) && names.resolve(*node).map(|s| s.byte_range()) == scope)
+            .map(|(node, _)| *node)
+            .collect();
+        if old_name != new_name {
+            let taken = Names::collect(tree.root_node(), doc.source, new_name, language);
+            collisions.extend(scoped_collisions(doc, &references, scope.clone(), &taken));
+        }
+        let edits = references.into_iter().map(|n| rename_edit(doc.source, n, new_name, true)).collect();
+        renames.push((index, edits));
+    }
+    finish(renames, collisions, old_name, new_name)
+}
+
+/// Bindings of the new name that a rename within `scope` runs into: one in
+/// the renamed symbol's scope or in a scope inside it holding a reference,
+/// and outer bindings whose references in the scope would be shadowed
+fn scoped_collisions(
+    doc: &RenameDocument,
+    references: &[Node],
+    scope: Option<std::ops::Range<usize>>,
+    taken: &Names,
+) -> Vec<Location> {
+    let (start, end) = scope.as_ref().map_or((0, doc.source.len()), |s| (s.start, s.end));
+    let inside = |range: std::ops::Range<usize>| start <= range.start && range.end <= end;
+
+    let mut collisions: Vec<Location> = taken
+        .first_binding_per_scope()
+        .filter(|(_, binding_scope)| {
+            inside(binding_scope.byte_range())
+                && references.iter().any(|r| binding_scope.start_byte() <= r.start_byte() && r.end_byte() <= binding_scope.end_byte())
+        })
+        .map(|(node, _)| location(doc, node))
+        .collect();
+
+    // An undeclared name shadows nothing
+    if scope.is_some() {
+        let shadowed = taken
+            .nodes
+            .iter()
+            .filter(|(node, role)| !matches!(role, Role::Member | Role::Other) && inside(node.byte_range()))
+            .find(|(node, _)| taken.resolve(*node).is_none_or(|s| !inside(s.byte_range())));
+        if let Some((node, _)) = shadowed {
+            let outer = taken.resolve(*node).and_then(|s| {
+                taken.nodes.iter().find(|(_, role)| matches!(role, Role::Binding(b) if *b == s))
+            });
+            collisions.push(location(doc, outer.map_or(*node, |(n, _)| *n)));
+        }
+    }
+    collisions
+}
+
+/// Whole-word rename in a document without a syntax tree
+fn textual_rename(
+    documents: &[RenameDocument],
+    origin: usize,
+    old_name: &str,
+    new_name: &str,
+) -> Result<Vec<(usize, Vec<TextEdit>)>, RefactorError> {
+    let doc = &documents[origin];
+    let occurrences = find_occurrences(doc.source, old_name, doc.language);
+    let collisions = match find_occurrences(doc.source, new_name, doc.language).first() {
+        Some(range) if old_name != new_name && !occurrences.is_empty() => vec![Location::new(doc.uri.to_string(), *range)],
+        _ => Vec::new(),
+    };
+    let edits = occurrences
+        .into_iter()
+        .map(|range| TextEdit::replace(range, new_name.to_string()))
+        .collect();
+    finish(vec![(origin, edits)], collisions, old_name, new_name)
+}
+
+fn finish(
+    renames: Vec<(usize, Vec<TextEdit>)>,
+    collisions: Vec<Location>,
+    old_name: &str,
+    new_name: &str,
+) -> Result<Vec<(usize, Vec<TextEdit>)>, RefactorError> {
+    if old_name != new_name && !collisions.is_empty() {
+        return Err(RefactorError::NameCollision(new_name.to_string(), collisions));
+    }
+    Ok(renames)
+}
+
+/// The edit renaming `node`; a shorthand property keeps its key
+fn rename_edit(source: &str, node: Node, new_name: &str, keep_key: bool) -> TextEdit {
+    let range = Range::new(
+        offset_to_position(source, node.start_byte()),
+        offset_to_position(source, node.end_byte()),
+    );
+    let text = if keep_key && node.kind().starts_with("shorthand_property") {
+        format!("{}: {}", &source[node.byte_range()], new_name)
+    } else {
+        new_name.to_string()
+    };
+    TextEdit::replace(range, text)
+}
+
+fn location(doc: &RenameDocument, node: Node) -> Location {
+    let range = Range::new(
+        offset_to_position(doc.source, node.start_byte()),
+        offset_to_position(doc.source, node.end_byte()),
+    );
+    Location::new(doc.uri.to_string(), range)
+}
+
+/// The file name of `uri` without its extension
+fn stem(uri: &str) -> &str {
+    let name = uri.rsplit('/').next().unwrap_or(uri);
+    name.split('.').next().unwrap_or(name)
+}
+
+/// Whether `text` holds `word` as a whole word
+fn mentions(text: &str, word: &str) -> bool {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    text.match_indices(word).any(|(i, _)| {
+        !text[..i].ends_with(is_ident) && !text[i + word.len()..].starts_with(is_ident)
+    })
+}
+
+/// What an occurrence of a name does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role<'t> {
+    /// Declares the name in the given scope
+    Binding(Node<'t>),
+    /// Refers to whatever the name is bound to around it
+    Reference,
+    /// Names a field, method or property
+    Member,
+    /// Part of an import path or a keyword argument; never renamed
+    Other,
+}
+
+/// The occurrences of one name in a document
+struct Names<'t> {
+    nodes: Vec<(Node<'t>, Role<'t>)>,
+}
+
+impl<'t> Names<'t> {
+    fn collect(root: Node<'t>, source: &str, name: &str, language: LanguageId) -> Self {
+        let mut nodes = Vec::new();
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            let kind = node.kind();
+            if (NAME_KINDS.contains(&kind) || MEMBER_KINDS.contains(&kind)) && source[node.byte_range()] == *name {
+                nodes.push((node, role(node, language)));
+                continue;
+            }
+            let mut cursor = node.walk();
+            stack.extend(node.children(&mut cursor));
+        }
+        nodes.sort_by_key(|(node, _)| node.start_byte());
+        Names { nodes }
+    }
+
+    /// The scopes binding the name
+    fn bindings(&self) -> impl Iterator<Item = Node<'t>> + '_ {
+        self.nodes.iter().filter_map(|(_, role)| match role {
+            Role::Binding(scope) => Some(*scope),
+            _ => None,
+        })
+    }
+
+    /// The first binding in each scope, with its scope
+    fn first_binding_per_scope(&self) -> impl Iterator<Item = (Node<'t>, Node<'t>)> + '_ {
+        self.nodes.iter().enumerate().filter_map(|(i, (node, role))| match role {
+            Role::Binding(scope) if !self.nodes[..i].iter().any(|(_, r)| *r == Role::Binding(*scope)) => Some((*node, *scope)),
+            _ => None,
+        })
+    }
+
+    /// The innermost scope binding the name around `node`, if any
+    fn resolve(&self, node: Node<'t>) -> Option<Node<'t>> {
+        self.bindings()
+            .filter(|scope| scope.start_byte() <= node.start_byte() && node.end_byte() <= scope.end_byte())
+            .min_by_key(|scope| scope.end_byte() - scope.start_byte())
+    }
+
+    /// Whether `node` is a member name, or resolves to a class member
+    fn is_member(&self, node: Node<'t>) -> bool {
+        match self.nodes.iter().find(|(n, _)| *n == node).map(|(_, role)| *role) {
+            Some(Role::Member) => true,
+            Some(Role::Binding(_) | Role::Reference) => self.resolve(node).is_some_and(|s| is_class_scope(&s)),
+            _ => false,
+        }
+    }
+
+    /// The byte span of an import statement binding the name at top level
+    fn import(&self, root: Node<'t>) -> Option<std::ops::Range<usize>> {
+        self.nodes.iter().find_map(|(node, role)| match role {
+            Role::Binding(scope) if *scope == root => import_around(*node).map(|import| import.byte_range()),
+            _ => None,
+        })
+    }
+
+    /// Whether the name is declared at top level, not imported
+    fn declares_at_top_level(&self, root: Node<'t>) -> bool {
+        self.nodes
+            .iter()
+            .any(|(node, role)| *role == Role::Binding(root) && import_around(*node).is_none())
+    }
+}
+
+/// Names declared below `node`: variables, parameters, functions and types
+pub(crate) fn declared_names<'s>(node: Node, source: &'s str, language: LanguageId) -> Vec<&'s str> {
+    let mut names = Vec::new();
+    let mut stack = vec![node];
+    while let Some(node) = stack.pop() {
+        if NAME_KINDS.contains(&node.kind()) {
+            if binding_scope(node, language).is_some() {
+                names.push(&source[node.byte_range()]);
+            }
+            continue;
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.children(&mut cursor));
+    }
+    names
+}
+
+fn import_around(node: Node) -> Option<Node> {
+    let mut current = node.parent();
+    while let Some(n) = current {
+        if IMPORT_KINDS.contains(&n.kind()) {
+            return Some(n);
+        }
+        current = n.parent();
+    }
+    None
+}
+
+fn field_of<'t>(parent: Node<'t>, child: Node<'t>) -> Option<&'static str> {
+    let index = {
+        let mut cursor = parent.walk();
+        let index = parent.children(&mut cursor).position(|c| c == child)?;
+        index
+    };
+    parent.field_name_for_child(index as u32)
+}
+
+fn role<'t>(node: Node<'t>, language: LanguageId) -> Role<'t> {
+    if MEMBER_KINDS.contains(&node.kind()) {
+        return Role::Member;
+    }
+    if let Some(import) = import_around(node) {
+        return import_role(node, import);
+    }
+    let Some(parent) = node.parent() else {
+        return Role::Reference;
+    };
+    match (parent.kind(), field_of(parent, node)) {
+        ("attribute", Some("attribute")) | ("field_access", Some("field")) => return Role::Member,
+        ("method_invocation", Some("name")) if parent.child_by_field_name("object").is_some() => return Role::Member,
+        // `Type::name` is reached through the type
+        ("scoped_identifier" | "qualified_identifier", Some("name")) => return Role::Member,
+        ("keyword_argument", Some("name")) => return Role::Other,
+        _ => {}
+    }
+    binding_scope(node, language).map_or(Role::Reference, Role::Binding)
+}
+
+/// A name in an import binds it at top level, unless it is part of the path
+/// the name is imported from
+fn import_role<'t>(node: Node<'t>, import: Node<'t>) -> Role<'t> {
+    let mut child = node;
+    while let Some(parent) = child.parent() {
+        if parent == import {
+            break;
+        }
+        if matches!(field_of(parent, child), Some("path" | "module_name" | "source" | "scope")) {
+            return Role::Other;
+        }
+        child = parent;
+    }
+    if node.next_sibling().is_some_and(|next| matches!(next.kind(), "." | "::")) {
+        return Role::Other;
+    }
+    let mut root = import;
+    while let Some(parent) = root.parent() {
+        root = parent;
+    }
+    Role::Binding(root)
+}
+
+/// The scope `node` declares its name in, if it is a declaration
+fn binding_scope<'t>(node: Node<'t>, language: LanguageId) -> Option<Node<'t>> {
+    // Climb out of destructuring patterns, declarators and parameter wrappers
+    let mut child = node;
+    let mut parent = node.parent()?;
+    loop {
+        let field = field_of(parent, child);
+        if matches!(
+            field,
+            Some("value" | "right" | "default_value" | "type" | "size" | "key" | "function" | "body" | "arguments")
+        ) {
+            return None;
+        }
+        if !is_binding_wrapper(parent.kind()) {
+            break;
+        }
+        child = parent;
+        parent = parent.parent()?;
+    }
+
+    let python = language == LanguageId::Python;
+    let kind = parent.kind();
+    match (kind, field_of(parent, child)) {
+        ("variable_declarator", Some("name")) => {
+            let var = parent.parent().is_some_and(|p| p.kind() == "variable_declaration");
+            Some(enclosing_scope(parent, python || var))
+        }
+        ("let_declaration", Some("pattern"))
+        | ("init_declarator" | "declaration" | "function_definition", Some("declarator"))
+        | ("short_var_declaration", Some("left"))
+        | ("var_spec" | "const_spec" | "named_expression", Some("name")) => Some(enclosing_scope(parent, python)),
+        ("assignment" | "for_statement", Some("left")) if python => Some(enclosing_scope(parent, true)),
+        ("for_in_statement", Some("left"))
+        | ("for_expression" | "match_arm", Some("pattern"))
+        | ("enhanced_for_statement", Some("name"))
+        | ("catch_clause", Some("parameter"))
+        | ("arrow_function", Some("parameter")) => Some(parent),
+        ("let_condition", Some("pattern"))
+        | ("catch_formal_parameter", Some("name"))
+        | ("range_clause", Some("left")) => parent.parent(),
+        (_, None | Some("name" | "pattern" | "declarator")) if PARAMETER_KINDS.contains(&kind) => {
+            Some(function_of(parent).unwrap_or(parent))
+        }
+        (_, Some("name")) if is_definition(kind) => Some(enclosing_scope(parent, python)),
+        _ => None,
+    }
+}
+
+fn is_binding_wrapper(kind: &str) -> bool {
+    kind.contains("pattern")
+        || (kind.ends_with("_declarator") && kind != "variable_declarator" && kind != "init_declarator")
+        || matches!(
+            kind,
+            "expression_list"
+                | "typed_parameter"
+                | "typed_default_parameter"
+                | "default_parameter"
+                | "required_parameter"
+                | "optional_parameter"
+        )
+}
+
+fn is_definition(kind: &str) -> bool {
+    !kind.starts_with("export")
+        && (kind.ends_with("_declaration")
+            || kind.ends_with("_definition")
+            || kind.ends_with("_item")
+            || kind.ends_with("_specifier")
+            || kind == "type_spec")
+}
+
+/// Functions, methods and closures with a body
+fn is_function(node: &Node) -> bool {
+    let kind = node.kind();
+    ["function", "method", "constructor", "lambda", "closure", "func_literal"]
+        .iter()
+        .any(|k| kind.contains(k))
+        && !kind.ends_with("_declarator")
+        && node.child_by_field_name("body").is_some()
+}
+
+fn is_class_scope(node: &Node) -> bool {
+    CLASS_SCOPE_KINDS.contains(&node.kind())
+}
+
+fn function_of(node: Node) -> Option<Node> {
+    let mut current = Some(node);
+    while let Some(n) = current {
+        if is_function(&n) {
+            return Some(n);
+        }
+        current = n.parent();
+    }
+    None
+}
+
+/// The scope a declaration at `node` belongs to: the innermost block, or
+/// function if `function_scoped`, around it; a class body; or the document
+fn enclosing_scope(node: Node, function_scoped: bool) -> Node {
+    let is_block = |kind: &str| {
+        matches!(
+            kind,
+            "block"
+                | "statement_block"
+                | "compound_statement"
+                | "for_statement"
+                | "for_in_statement"
+                | "for_expression"
+                | "enhanced_for_statement"
+                | "catch_clause"
+                | "match_arm"
+        )
+    };
+    let mut scope = node;
+    while let Some(parent) = scope.parent() {
+        scope = parent;
+        if is_function(&scope) || is_class_scope(&scope) || (!function_scoped && is_block(scope.kind())) {
+            return scope;
+        }
+    }
+    scope
+}
+
+/// Whole-word occurrences of `name` outside strings and comments
+pub fn find_occurrences(source: &str, name: &str, language: Option<LanguageId>) -> Vec<Range> {
+    let re = match Regex::new(&format!(r"\b{}\b", regex::escape(name))) {
+        Ok(re) => re,
+        Err(_) => return Vec::new(),
+    };
+    let code = code_spans(source, language);
+
+    re.find_iter(source)
+        .filter(|m| code.iter().any(|span| span.start <= m.start() && m.end() <= span.end))
+        // `$` is an identifier character in JavaScript, so `$name` is another name
+        .filter(|m| !source[..m.start()].ends_with('$') && !source[m.end()..].starts_with('$'))
+        .map(|m| Range::new(offset_to_position(source, m.start()), offset_to_position(source, m.end())))
+        .collect()
+}
+
+/// Byte spans of `source` that are code, i.e. not strings or comments
+fn code_spans(source: &str, language: Option<LanguageId>) -> Vec<std::ops::Range<usize>> {
+    let hash_comments = language == Some(LanguageId::Python);
+    let char_quotes = language != Some(LanguageId::Rust);
+
+    let mut spans = Vec::new();
+    let mut span_start = 0;
+    let mut chars = source.char_indices().peekable();
+
+    while let Some((i, ch)) = chars.next() {
+        let next = chars.peek().map(|&(_, c)| c);
+        let skip_to = match ch {
+            '#' if hash_comments => source[i..].find('\n').map(|n| i + n).unwrap_or(source.len()),
+            '/' if !hash_comments && next == Some('/') => {
+                source[i..].find('\n').map(|n| i + n).unwrap_or(source.len())
+            }
+            '/' if !hash_comments && next == Some('*') => {
+                source[i + 2..].find("*/").map(|n| i + 2 + n + 2).unwrap_or(source.len())
+            }
+            '"' | '`' => string_end(source, i, ch),
+            '\'' if char_quotes => string_end(source, i, ch),
+            _ => continue,
+        };
+
+        if span_start < i {
+            spans.push(span_start..i);
+        }
+        span_start = skip_to;
+        while chars.peek().is_some_and(|&(j, _)| j < skip_to) {
+            chars.next();
+        }
+    }
+
+    if span_start < source.len() {
+        spans.push(span_start..source.len());
+    }
+    spans
+}
+
+/// Byte index just past the string literal opened by `quote` at `start`
+fn string_end(source: &str, start: usize, quote: char) -> usize {
+    let mut escaped = false;
+    for (i, ch) in source[start + 1..].char_indices() {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            // Only template literals span lines
+            '\n' if quote != '`' => return start + 1 + i,
+            c if c == quote => return start + 1 + i + 1,
+            _ => {}
+        }
+    }
+    source.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc<'a>(uri: &'a str, source: &'a str) -> RenameDocument<'a> {
+        RenameDocument {
+            uri,
+            source,
+            language: Some(LanguageId::JavaScript),
+        }
+    }
+
+    #[test]
+    fn test_find_occurrences_skips_strings_and_comments() {
+        let source = "let count = 1; // count things\nlog(\"count\", count, counter);\n";
+        let ranges = find_occurrences(source, "count", Some(LanguageId::JavaScript));
+        assert_eq!(
+            ranges,
+            vec![Range::from_coords(0, 4, 0, 9), Range::from_coords(1, 13, 1, 18)]
+        );
+    }
+
+    #[test]
+    fn test_identifier_at() {
+        let (name, range) = identifier_at("foo(barBaz)", Position::new(0, 6)).unwrap();
+        assert_eq!(name, "barBaz");
+        assert_eq!(range, Range::from_coords(0, 4, 0, 10));
+        assert!(identifier_at("a + b", Position::new(0, 2)).is_none());
+    }
+
+    #[test]
+    fn test_plan_two_files() {
+        let lib = "export function total(items) {\n  return items.length;\n}\n";
+        let main = "import { total } from './lib';\nconsole.log(total([1]));\n";
+        let docs = [doc("file:///main.js", main), doc("file:///lib.js", lib)];
+
+        let plan = plan(&docs, "file:///lib.js", Position::new(0, 17), "sum").unwrap();
+        assert_eq!(plan.files.len(), 2);
+        assert_eq!(plan.occurrences(), 3);
+        assert_eq!(plan.files[0].uri, "file:///lib.js");
+
+        // The preview text is exactly what applying the edits produces
+        for (file, source) in plan.files.iter().zip([lib, main]) {
+            assert_eq!(file.new_text, apply_edits(source, &file.edits));
+        }
+        assert_eq!(plan.files[1].new_text, "import { sum } from './lib';\nconsole.log(sum([1]));\n");
+    }
+
+    #[test]
+    fn test_plan_rejects_collisions() {
+        let source = "function f(a) {\n  const b = 2;\n  return a + b;\n}\nfunction g(b) {\n  return b;\n}\nfunction h(x) {\n  const a = x;\n  return a;\n}\n";
+        let docs = [doc("file:///a.js", source)];
+
+        // `b` is bound in `f`, where `a` is used; `g` only clashes with itself
+        match plan(&docs, "file:///a.js", Position::new(0, 11), "b") {
+            Err(RefactorError::NameCollision(name, locations)) => {
+                assert_eq!(name, "b");
+                assert_eq!(locations, vec![Location::new("file:///a.js".to_string(), Range::from_coords(1, 8, 1, 9))]);
+            }
+            other => panic!("expected NameCollision, got {:?}", other),
+        }
+
+        // No `b` where `x` is used: `g` is a different function
+        let plan = plan(&docs, "file:///a.js", Position::new(7, 11), "b").unwrap();
+        assert_eq!(plan.occurrences(), 2);
+        assert!(plan.files[0].new_text.contains("function h(b) {\n  const a = b;"));
+    }
+
+    #[test]
+    fn test_plan_allows_name_bound_in_other_function() {
+        let source = "function f(a) {\n  const b = 2;\n  return a + b;\n}\nfunction h(x) {\n  const a = x;\n  return a;\n}\n";
+        let docs = [doc("file:///a.js", source)];
+
+        // `f` binds both names, but renaming the `a` of `h` doesn't reach it
+        let plan = plan(&docs, "file:///a.js", Position::new(5, 8), "b").unwrap();
+        assert_eq!(plan.occurrences(), 2);
+        assert_eq!(
+            plan.files[0].new_text,
+            "function f(a) {\n  const b = 2;\n  return a + b;\n}\nfunction h(x) {\n  const b = x;\n  return b;\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_plan_renames_only_the_resolved_symbol() {
+        let a = "function f() {\n  let x = 1;\n  return { x, y: x + 1 };\n}\nfunction g() {\n  let x = 2;\n  return x;\n}\n";
+        let b = "const o = { x: 1 };\nlog(o.x);\n";
+        let docs = [doc("file:///a.js", a), doc("file:///b.js", b)];
+
+        let local = plan(&docs, "file:///a.js", Position::new(1, 6), "count").unwrap();
+        assert_eq!(local.files.len(), 1);
+        assert_eq!(
+            local.files[0].new_text,
+            "function f() {\n  let count = 1;\n  return { x: count, y: count + 1 };\n}\nfunction g() {\n  let x = 2;\n  return x;\n}\n"
+        );
+
+        // A property is renamed as a member, leaving the locals alone
+        let member = plan(&docs, "file:///b.js", Position::new(1, 6), "count").unwrap();
+        assert_eq!(member.files.len(), 1);
+        assert_eq!(member.files[0].new_text, "const o = { count: 1 };\nlog(o.count);\n");
+    }
+
+    #[test]
+    fn test_plan_rejects_invalid_names() {
+        let docs = [doc("file:///a.js", "let x = 1;")];
+        assert!(plan(&docs, "file:///a.js", Position::new(0, 4), "1x").is_err());
+        assert!(plan(&docs, "file:///a.js", Position::new(0, 4), "class").is_err());
+    }
+}
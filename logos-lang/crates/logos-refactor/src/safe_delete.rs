@@ -3,9 +3,17 @@
 //! Safely delete a symbol (variable, function, class, etc.) only if it's not used elsewhere.
 //! If the symbol is still in use, return the usage locations to inform the user.
 
-use crate::{RefactorContext, RefactorError, RefactorResult, TextEdit};
+use crate::analysis::{is_statement_kind, statement_bytes};
+use crate::move_symbol::{on_lines, resolves_to, ImportChanges, ImportFormat, ImportStyle, ModuleFile};
+use crate::rename::find_occurrences;
+use crate::{
+    apply_edits, byte_to_column, column_to_byte, offset_to_position, position_to_offset, RefactorContext, RefactorError,
+    RefactorResult, TextEdit, WorkspaceEdit,
+};
 use logos_core::{Location, Range};
+use logos_parser::{LanguageId, LanguageParser};
 use regex::Regex;
+use tree_sitter::Node;
 
 /// Result of safe delete analysis
 #[derive(Debug)]
@@ -49,8 +57,82 @@ impl SafeDeleteAnalysis {
     }
 }
 
+/// Index information about the symbol being deleted, used to attribute
+/// usages when several symbols share the same name
+#[derive(Debug, Clone, Default)]
+pub struct SymbolScope {
+    /// Fully qualified name, e.g. `demo::User::greet`
+    pub qualified_name: String,
+    /// Whether the symbol is a member of a class/struct
+    pub is_member: bool,
+    /// Range of the containing type, where members can be used unqualified
+    pub member_scope: Option<Range>,
+    /// Declarations of other symbols with the same name
+    pub other_declarations: Vec<Range>,
+}
+
+impl SymbolScope {
+    /// Whether a usage at `range` in `line` refers to this symbol
+    fn attributes(&self, line: &str, range: Range) -> bool {
+        if self.other_declarations.iter().any(|d| d.overlaps(&range)) {
+            return false;
+        }
+
+        let before = &line[..column_to_byte(line, range.start.column)];
+        let trimmed = before.trim_end();
+
+        // Member access: `obj.name`, `ptr->name`
+        if trimmed.ends_with('.') || trimmed.ends_with("->") {
+            return self.is_member;
+        }
+
+        // Path access: `User::name`, `demo::User::name`
+        if trimmed.ends_with("::") {
+            let re = Regex::new(r"(?:[A-Za-z_][A-Za-z0-9_]*\s*::\s*)+$").unwrap();
+            let path = re.find(trimmed).map(|m| m.as_str()).unwrap_or("");
+            let written: Vec<&str> = path
+                .split("::")
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .collect();
+            let qualified: Vec<&str> = self.qualified_name.split("::").collect();
+            // The written qualifier must be a suffix of the symbol's container path
+            let container = &qualified[..qualified.len().saturating_sub(1)];
+            return written.len() <= container.len()
+                && container[container.len() - written.len()..] == written[..];
+        }
+
+        // Unqualified: members are only reachable inside their type
+        if self.is_member {
+            return match self.member_scope {
+                Some(scope) => scope.start <= range.start && range.end <= scope.end,
+                None => true,
+            };
+        }
+        true
+    }
+}
+
 /// Analyze if a symbol at the given position can be safely deleted
 pub fn analyze(ctx: &RefactorContext) -> Result<SafeDeleteAnalysis, RefactorError> {
+    analyze_scoped(ctx, None)
+}
+
+/// Analyze a symbol whose qualified name and scope are known from the index.
+///
+/// Usages of same-named symbols (e.g. a free function `greet` next to a
+/// method `User::greet`) are not counted.
+pub fn analyze_with_scope(
+    ctx: &RefactorContext,
+    scope: &SymbolScope,
+) -> Result<SafeDeleteAnalysis, RefactorError> {
+    analyze_scoped(ctx, Some(scope))
+}
+
+fn analyze_scoped(
+    ctx: &RefactorContext,
+    scope: Option<&SymbolScope>,
+) -> Result<SafeDeleteAnalysis, RefactorError> {
     // For now, we use a simpler approach that doesn't require full parsing
     // We look at the selection and find usages of the symbol by name
 
@@ -71,31 +153,67 @@ pub fn analyze(ctx: &RefactorContext) -> Result<SafeDeleteAnalysis, RefactorErro
         ));
     }
 
-    // Find all usages of this symbol
-    let usages = find_usages(ctx, &symbol_name);
+    // A whole-line selection ends at the start of the next line, which must
+    // not pull the next statement into the deletion
+    let selection = trimmed_selection(ctx);
+    list_element(ctx, selection)?;
 
-    // If there's only one usage (the definition itself), it's safe to delete
-    let can_delete = usages.len() <= 1;
+    // Every other occurrence is a usage, except those deleted along with the
+    // declaration (e.g. a recursive call in the function's body)
+    let usages = find_usages(ctx, &symbol_name);
+    let definition = definition_index(&usages, selection);
+    let deleted = find_deletion_range(ctx, &SafeDeleteAnalysis::safe(symbol_name.clone(), selection));
+    let mut other_usages: Vec<Location> = usages
+        .into_iter()
+        .enumerate()
+        .filter(|(i, loc)| Some(*i) != definition && !deleted.contains_range(&loc.range))
+        .map(|(_, loc)| loc)
+        .collect();
+    if let Some(scope) = scope {
+        let lines: Vec<&str> = ctx.source.lines().collect();
+        other_usages.retain(|loc| scope.attributes(lines[loc.range.start.line as usize], loc.range));
+    }
+    other_usages.extend(parameter_callers(ctx, selection)?);
 
-    if can_delete {
-        Ok(SafeDeleteAnalysis::safe(symbol_name, ctx.selection))
+    if other_usages.is_empty() {
+        Ok(SafeDeleteAnalysis::safe(symbol_name, selection))
     } else {
-        // Filter out the definition itself from usages
-        let other_usages: Vec<Location> = usages
-            .into_iter()
-            .filter(|loc| !loc.range.overlaps(&ctx.selection))
-            .collect();
-
-        if other_usages.is_empty() {
-            Ok(SafeDeleteAnalysis::safe(symbol_name, ctx.selection))
-        } else {
-            Ok(SafeDeleteAnalysis::unsafe_with_usages(
-                symbol_name,
-                ctx.selection,
-                other_usages,
-            ))
-        }
+        Ok(SafeDeleteAnalysis::unsafe_with_usages(
+            symbol_name,
+            selection,
+            other_usages,
+        ))
+    }
+}
+
+/// The selection without surrounding whitespace
+fn trimmed_selection(ctx: &RefactorContext) -> Range {
+    let text = ctx.selected_text();
+    let start = ctx.selection_bytes.start + (text.len() - text.trim_start().len());
+    let end = ctx.selection_bytes.start + text.trim_end().len();
+    if start >= end {
+        return ctx.selection;
     }
+    Range::new(offset_to_position(ctx.source, start), offset_to_position(ctx.source, end))
+}
+
+/// The occurrence the selection declares: the first one inside the selection
+/// (an identifier or a whole statement), else one the selection cuts into
+fn definition_index(usages: &[Location], selection: Range) -> Option<usize> {
+    usages
+        .iter()
+        .position(|loc| selection.contains_range(&loc.range))
+        .or_else(|| usages.iter().position(|loc| loc.range.overlaps(&selection)))
+}
+
+/// Whether the selection is a single name or a declaration, i.e. something
+/// Safe Delete can act on
+pub fn is_symbol_selection(ctx: &RefactorContext) -> bool {
+    let text = ctx.selected_text().trim();
+    let name = Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").unwrap();
+    let declaration =
+        Regex::new(r"^(?:let|const|var|function|class|def|fn|func|type)\s+[A-Za-z_]").unwrap();
+    name.is_match(text) || declaration.is_match(text)
 }
 
 /// Extract the symbol name from selected text
@@ -130,9 +248,9 @@ fn find_usages(ctx: &RefactorContext, name: &str) -> Vec<Location> {
             for m in re.find_iter(line) {
                 let range = Range::from_coords(
                     line_num as u32,
-                    m.start() as u32,
+                    byte_to_column(line, m.start()),
                     line_num as u32,
-                    m.end() as u32,
+                    byte_to_column(line, m.end()),
                 );
                 usages.push(Location::new(ctx.uri.to_string(), range));
             }
@@ -150,7 +268,128 @@ pub fn can_delete(ctx: &RefactorContext) -> Result<bool, RefactorError> {
 
 /// Delete the symbol at the cursor position
 pub fn delete(ctx: &RefactorContext) -> Result<RefactorResult, RefactorError> {
-    let analysis = analyze(ctx)?;
+    delete_analyzed(ctx, analyze(ctx)?)
+}
+
+/// Delete a symbol whose qualified name and scope are known from the index
+pub fn delete_with_scope(
+    ctx: &RefactorContext,
+    scope: &SymbolScope,
+) -> Result<RefactorResult, RefactorError> {
+    delete_analyzed(ctx, analyze_with_scope(ctx, scope)?)
+}
+
+/// Result of a safe delete across documents
+#[derive(Debug)]
+pub struct WorkspaceDelete {
+    /// The deletion in the symbol's own document
+    pub result: RefactorResult,
+    /// Every document's edits, the deletion included
+    pub edit: WorkspaceEdit,
+}
+
+/// Delete the symbol at the selection when the other documents don't use it
+/// either, removing their imports of it.
+///
+/// For languages without module imports (C, C++, Go, ...) any occurrence
+/// of the name in another document counts as a usage.
+pub fn delete_in_workspace(
+    ctx: &RefactorContext,
+    scope: Option<&SymbolScope>,
+    others: &[ModuleFile],
+) -> Result<WorkspaceDelete, RefactorError> {
+    let analysis = analyze_scoped(ctx, scope)?;
+    if !analysis.can_delete {
+        return Err(RefactorError::SymbolInUse(analysis.usages));
+    }
+    let name = analysis.symbol_name.clone();
+    let style = ImportStyle::for_language(ctx.language);
+
+    let mut edit = WorkspaceEdit::new();
+    let mut usages = Vec::new();
+    for file in others.iter().filter(|f| f.uri != ctx.uri) {
+        let style = match style {
+            Some(style) => style,
+            None => {
+                let occurrences = find_occurrences(file.source, &name, Some(ctx.language));
+                usages.extend(occurrences.into_iter().map(|range| Location::new(file.uri.to_string(), range)));
+                continue;
+            }
+        };
+
+        // Only documents importing the symbol's module can use it
+        let outside_imports = |range: &Range| !file.imports.iter().any(|i| on_lines(i.range, range.start.line));
+        let mut changes = ImportChanges::new(style, file);
+        for (index, import) in file.imports.iter().enumerate() {
+            if !resolves_to(style, file.uri, &import.module_path, ctx.uri) {
+                continue;
+            }
+            for item in &import.items {
+                // `import * as m`, `import module`: uses are `m.name`
+                let namespace = item.0 == "*" || item.0 == import.module_path;
+                let local = match &item.1 {
+                    _ if namespace => &name,
+                    Some(alias) if item.0 == name => alias,
+                    None if item.0 == name => &name,
+                    _ => continue,
+                };
+                let used: Vec<Range> = find_occurrences(file.source, local, Some(ctx.language))
+                    .into_iter()
+                    .filter(outside_imports)
+                    .collect();
+                if !used.is_empty() {
+                    usages.extend(used.into_iter().map(|range| Location::new(file.uri.to_string(), range)));
+                } else if !namespace {
+                    changes.items(index).retain(|i| i != item);
+                }
+            }
+        }
+        let format = ImportFormat::detect(style, file, file);
+        edit.insert(file.uri, TextEdit::normalize(changes.edits(&format))?);
+    }
+
+    if !usages.is_empty() {
+        return Err(RefactorError::SymbolInUse(usages));
+    }
+    let result = delete_analyzed(ctx, analysis)?;
+    edit.insert(ctx.uri, result.edits.clone());
+    Ok(WorkspaceDelete { result, edit })
+}
+
+/// Result of a safe delete dry run
+#[derive(Debug)]
+pub struct SafeDeletePreview {
+    /// The edits that would be applied
+    pub result: RefactorResult,
+    /// The full document text after the deletion
+    pub new_text: String,
+}
+
+/// Compute the document text after deleting the symbol, without applying it
+pub fn preview(ctx: &RefactorContext) -> Result<SafeDeletePreview, RefactorError> {
+    preview_result(ctx, delete(ctx)?)
+}
+
+/// Preview the deletion of a symbol whose qualified name and scope are known
+pub fn preview_with_scope(
+    ctx: &RefactorContext,
+    scope: &SymbolScope,
+) -> Result<SafeDeletePreview, RefactorError> {
+    preview_result(ctx, delete_with_scope(ctx, scope)?)
+}
+
+fn preview_result(
+    ctx: &RefactorContext,
+    result: RefactorResult,
+) -> Result<SafeDeletePreview, RefactorError> {
+    let new_text = apply_edits(ctx.source, &result.edits);
+    Ok(SafeDeletePreview { result, new_text })
+}
+
+fn delete_analyzed(
+    ctx: &RefactorContext,
+    analysis: SafeDeleteAnalysis,
+) -> Result<RefactorResult, RefactorError> {
 
     if !analysis.can_delete {
         return Err(RefactorError::SymbolInUse(analysis.usages));
@@ -167,63 +406,379 @@ pub fn delete(ctx: &RefactorContext) -> Result<RefactorResult, RefactorError> {
     ))
 }
 
+/// The range removed when deleting the declaration at the selection: whole
+/// lines where possible, and the body of a function or class
+pub(crate) fn deletion_range(ctx: &RefactorContext) -> Range {
+    find_deletion_range(ctx, &SafeDeleteAnalysis::safe(String::new(), ctx.selection))
+}
+
 /// Find the range to delete for a symbol
 fn find_deletion_range(ctx: &RefactorContext, analysis: &SafeDeleteAnalysis) -> Range {
-    let lines: Vec<&str> = ctx.source.lines().collect();
-    let symbol_line = analysis.symbol_range.start.line as usize;
+    // Keep the trailing empty line so a final newline is distinguishable
+    let lines: Vec<&str> = ctx.source.split('\n').map(|l| l.trim_end_matches('\r')).collect();
+    let start_line = analysis.symbol_range.start.line as usize;
+    let mut end_line = analysis.symbol_range.end.line as usize;
 
-    if symbol_line >= lines.len() {
+    if end_line >= lines.len() {
         return analysis.symbol_range;
     }
 
-    let line = lines[symbol_line];
-
-    // Check if this is a single-line declaration
-    let is_single_line = analysis.symbol_range.start.line == analysis.symbol_range.end.line;
-
-    if is_single_line {
-        // Check if the entire line is just this declaration
-        let trimmed = line.trim();
-        let symbol_text = ctx.text_in_range(analysis.symbol_range);
-
-        if trimmed == symbol_text
-            || trimmed.ends_with(';')
-                && trimmed[..trimmed.len() - 1].trim() == symbol_text
-        {
-            // Delete the entire line including newline
-            if symbol_line + 1 < lines.len() {
-                Range::from_coords(
-                    symbol_line as u32,
-                    0,
-                    symbol_line as u32 + 1,
-                    0,
-                )
-            } else {
-                Range::from_coords(
-                    symbol_line as u32,
-                    0,
-                    symbol_line as u32,
-                    line.len() as u32,
-                )
+    // A parameter, or one declarator of several: only it and a comma go
+    if let Ok(Some((start, end))) = list_element(ctx, analysis.symbol_range) {
+        return Range::new(offset_to_position(ctx.source, start), offset_to_position(ctx.source, end));
+    }
+
+    let (start_line, stmt_start, end_line, stmt_end) = match ast_statement(ctx, analysis.symbol_range) {
+        Some(statement) => statement,
+        None => {
+            // Narrow the deletion to the statement(s) covering the selection
+            let first = lines[start_line];
+            let start_byte = column_to_byte(first, analysis.symbol_range.start.column);
+            let end_byte = column_to_byte(lines[end_line], analysis.symbol_range.end.column);
+            let stmt_start = statement_start(first, start_byte);
+            let mut stmt_end = statement_end(lines[end_line], end_byte);
+
+            // A block opened by the declaration (function/class body) is part of it
+            if let Some((line, col)) = find_block_end(&lines, start_line, stmt_start, end_line, stmt_end) {
+                end_line = line;
+                stmt_end = statement_end(lines[line], col);
+            }
+            (start_line, stmt_start, end_line, stmt_end)
+        }
+    };
+
+    let first = lines[start_line];
+    let last = lines[end_line];
+
+    let leading = &first[..stmt_start];
+    let trailing = &last[stmt_end..];
+
+    if leading.trim().is_empty() && trailing.trim().is_empty() {
+        // The declaration occupies whole lines: remove them with their newline
+        return whole_lines_range(&lines, start_line, end_line);
+    }
+
+    if trailing.trim().is_empty() {
+        // Declaration ends the line: also drop the whitespace separating it from
+        // the previous statement
+        let start_col = byte_to_column(first, leading.trim_end().len());
+        return Range::from_coords(
+            start_line as u32,
+            start_col,
+            end_line as u32,
+            byte_to_column(last, last.len()),
+        );
+    }
+
+    // Other code follows on the same line: drop the separator whitespace after it
+    let end_byte = last.len() - trailing.trim_start().len();
+    Range::from_coords(
+        start_line as u32,
+        byte_to_column(first, stmt_start),
+        end_line as u32,
+        byte_to_column(last, end_byte),
+    )
+}
+
+/// Parameter lists of the supported grammars
+const PARAMETER_LISTS: &[&str] = &["formal_parameters", "parameters", "parameter_list", "lambda_parameters"];
+
+/// The byte span to delete when `range` selects the name of a parameter or of
+/// one of several declarators in a statement (`let x = 1, y = 2;`): the
+/// element with the comma after it, or before it for the last one.
+/// `None` when the whole statement goes; an error for a parameter that
+/// binds other names too (`a, b int`, `{ a, b }`)
+fn list_element(ctx: &RefactorContext, range: Range) -> Result<Option<(usize, usize)>, RefactorError> {
+    let start = position_to_offset(ctx.source, range.start);
+    let end = position_to_offset(ctx.source, range.end);
+    let mut parser = LanguageParser::new();
+    if parser.set_language(ctx.language).is_err() {
+        return Ok(None);
+    }
+    let Ok(tree) = parser.parse(ctx.source, None) else {
+        return Ok(None);
+    };
+    let Some(name) = tree.root_node().descendant_for_byte_range(start, end).filter(|n| n.kind() == "identifier") else {
+        return Ok(None);
+    };
+
+    let mut child = name;
+    while let Some(parent) = child.parent() {
+        if PARAMETER_LISTS.contains(&parent.kind()) {
+            if bound_names(child) != [name] {
+                return Err(RefactorError::Unsupported(format!(
+                    "'{}' is declared together with other parameters",
+                    &ctx.source[name.byte_range()]
+                )));
+            }
+            return Ok(Some(with_comma(child)));
+        }
+
+        let mut cursor = parent.walk();
+        let mut declarators: Vec<Node> = parent.children_by_field_name("declarator", &mut cursor).collect();
+        if declarators.is_empty() && child.kind().ends_with("declarator") {
+            declarators = parent.named_children(&mut cursor).filter(|n| n.kind() == child.kind()).collect();
+        }
+        if declarators.len() > 1 && declarators.contains(&child) {
+            return Ok((bound_names(child) == [name]).then(|| with_comma(child)));
+        }
+
+        if is_statement_kind(parent.kind()) {
+            return Ok(None);
+        }
+        child = parent;
+    }
+    Ok(None)
+}
+
+/// The calls of the function whose parameter `range` names: they pass an
+/// argument for it, so the parameter is in use while any is left. Empty when
+/// `range` is not a parameter
+fn parameter_callers(ctx: &RefactorContext, range: Range) -> Result<Vec<Location>, RefactorError> {
+    let start = position_to_offset(ctx.source, range.start);
+    let end = position_to_offset(ctx.source, range.end);
+    let mut parser = LanguageParser::new();
+    if parser.set_language(ctx.language).is_err() {
+        return Ok(Vec::new());
+    }
+    let Ok(tree) = parser.parse(ctx.source, None) else {
+        return Ok(Vec::new());
+    };
+    let Some(mut child) = tree.root_node().descendant_for_byte_range(start, end).filter(|n| n.kind() == "identifier") else {
+        return Ok(Vec::new());
+    };
+    let list = loop {
+        let Some(parent) = child.parent() else {
+            return Ok(Vec::new());
+        };
+        if PARAMETER_LISTS.contains(&parent.kind()) {
+            break parent;
+        }
+        if is_statement_kind(parent.kind()) {
+            return Ok(Vec::new());
+        }
+        child = parent;
+    };
+
+    // C keeps the name and parameters in a declarator; an anonymous function
+    // is named by the variable, property or assignment holding it
+    let Some(mut function) = list.parent() else {
+        return Ok(Vec::new());
+    };
+    let mut name = function.child_by_field_name("name");
+    if function.kind() == "function_declarator" {
+        name = function.child_by_field_name("declarator");
+        function = function.parent().unwrap_or(function);
+    }
+    let name = name
+        .or_else(|| {
+            let holder = function.parent()?;
+            ["name", "left", "key"].iter().find_map(|field| holder.child_by_field_name(field))
+        })
+        .filter(|name| name.kind().contains("identifier"))
+        .ok_or_else(|| RefactorError::Unsupported("The callers of an anonymous function can't be checked".to_string()))?;
+
+    let declared = Range::new(
+        offset_to_position(ctx.source, name.start_byte()),
+        offset_to_position(ctx.source, name.end_byte()),
+    );
+    Ok(find_usages(ctx, &ctx.source[name.byte_range()])
+        .into_iter()
+        .filter(|usage| usage.range != declared)
+        .collect())
+}
+
+/// The identifiers a parameter or declarator binds, leaving out its type,
+/// default value and array size
+fn bound_names(node: Node) -> Vec<Node> {
+    if node.kind() == "identifier" {
+        return vec![node];
+    }
+    let mut names = Vec::new();
+    let mut cursor = node.walk();
+    if cursor.goto_first_child() {
+        loop {
+            if !matches!(cursor.field_name(), Some("value" | "right" | "default_value" | "type" | "size")) {
+                names.extend(bound_names(cursor.node()));
+            }
+            if !cursor.goto_next_sibling() {
+                break;
+            }
+        }
+    }
+    names
+}
+
+/// The span of a list element with the comma after it and the space up to
+/// the next element, or for the last element the comma before it
+fn with_comma(element: Node) -> (usize, usize) {
+    let is_comma = |sibling: &Node| sibling.kind() == ",";
+    if let Some(next) = element.next_sibling().filter(is_comma).and_then(|c| c.next_named_sibling()) {
+        return (element.start_byte(), next.start_byte());
+    }
+    if let Some(previous) = element.prev_sibling().filter(is_comma).and_then(|c| c.prev_named_sibling()) {
+        return (previous.end_byte(), element.end_byte());
+    }
+    (element.start_byte(), element.end_byte())
+}
+
+/// The declaration statement around `range` from the syntax tree, as
+/// `(start_line, start_byte, end_line, end_byte)` with bytes within the line
+fn ast_statement(ctx: &RefactorContext, range: Range) -> Option<(usize, usize, usize, usize)> {
+    let start = position_to_offset(ctx.source, range.start);
+    let end = position_to_offset(ctx.source, range.end);
+    let (mut stmt_start, stmt_end) = statement_bytes(ctx.source, start, ctx.language)?;
+    if stmt_end < end {
+        return None;
+    }
+    // `export function f() {}`: the export goes with the declaration
+    if matches!(ctx.language, LanguageId::JavaScript | LanguageId::TypeScript) {
+        if let Some(rest) = ctx.source[..stmt_start].trim_end().strip_suffix("export") {
+            if !rest.ends_with(|c: char| c.is_alphanumeric() || c == '_' || c == '$') {
+                stmt_start = rest.len();
             }
-        } else {
-            // Just delete the symbol portion
-            analysis.symbol_range
         }
+    }
+
+    let line_and_byte = |offset: usize| {
+        let line_start = ctx.source[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        (ctx.source[..offset].matches('\n').count(), offset - line_start)
+    };
+    let (start_line, start_byte) = line_and_byte(stmt_start);
+    let (end_line, end_byte) = line_and_byte(stmt_end);
+    Some((start_line, start_byte, end_line, end_byte))
+}
+
+/// If the declaration leaves brackets open, find where they are closed
+fn find_block_end(
+    lines: &[&str],
+    start_line: usize,
+    start_col: usize,
+    end_line: usize,
+    end_col: usize,
+) -> Option<(usize, usize)> {
+    let mut depth = 0i32;
+    for (line_num, line) in lines.iter().enumerate().take(end_line + 1).skip(start_line) {
+        let from = if line_num == start_line { start_col } else { 0 };
+        let to = if line_num == end_line { end_col } else { line.len() };
+        depth += bracket_depth_change(&line[from..to.max(from)]);
+    }
+
+    if depth <= 0 {
+        return None;
+    }
+
+    let tail = &lines[end_line][end_col..];
+    let mut offset = end_col;
+    let mut line_num = end_line;
+    let mut text = tail;
+    loop {
+        for (i, ch) in text.char_indices() {
+            match ch {
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some((line_num, offset + i + 1));
+                    }
+                }
+                _ => {}
+            }
+        }
+        line_num += 1;
+        if line_num >= lines.len() {
+            return None;
+        }
+        offset = 0;
+        text = lines[line_num];
+    }
+}
+
+/// Net change in bracket nesting over a piece of text
+fn bracket_depth_change(text: &str) -> i32 {
+    text.chars()
+        .map(|ch| match ch {
+            '(' | '[' | '{' => 1,
+            ')' | ']' | '}' => -1,
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Range covering whole lines `start..=end`, including one line terminator
+fn whole_lines_range(lines: &[&str], start: usize, end: usize) -> Range {
+    if end + 1 < lines.len() {
+        Range::from_coords(start as u32, 0, end as u32 + 1, 0)
+    } else if start > 0 {
+        // Last line without a trailing newline: consume the preceding one instead
+        Range::from_coords(
+            start as u32 - 1,
+            byte_to_column(lines[start - 1], lines[start - 1].len()),
+            end as u32,
+            byte_to_column(lines[end], lines[end].len()),
+        )
     } else {
-        // Multi-line: delete from start to end including trailing newline
-        let end_line = analysis.symbol_range.end.line as usize;
-        if end_line + 1 < lines.len() {
-            Range::from_coords(
-                symbol_line as u32,
-                0,
-                end_line as u32 + 1,
-                0,
-            )
+        Range::from_coords(start as u32, 0, end as u32, byte_to_column(lines[end], lines[end].len()))
+    }
+}
+
+/// Find the byte index where the statement containing byte `col` starts
+fn statement_start(line: &str, col: usize) -> usize {
+    let col = col.min(line.len());
+    let separators = top_level_separators(line);
+    let start = separators
+        .iter()
+        .rev()
+        .find(|&&sep| sep < col)
+        .map(|&sep| sep + 1)
+        .unwrap_or(0);
+
+    // Skip the whitespace between the previous separator and the statement
+    start + (line[start..].len() - line[start..].trim_start().len())
+}
+
+/// Find the byte index just past the statement ending at or after byte `col`,
+/// including its `;` terminator
+fn statement_end(line: &str, col: usize) -> usize {
+    let col = col.min(line.len());
+
+    // A selection that already ends with the terminator is complete
+    if col > 0 && line.as_bytes()[col - 1] == b';' {
+        return col;
+    }
+
+    top_level_separators(line)
+        .into_iter()
+        .find(|&sep| sep >= col)
+        .map(|sep| sep + 1)
+        .unwrap_or(line.len())
+}
+
+/// Byte offsets of `;` separators outside strings and brackets
+fn top_level_separators(line: &str) -> Vec<usize> {
+    let mut separators = Vec::new();
+    let mut depth = 0i32;
+    let mut in_string: Option<char> = None;
+    let mut prev = ' ';
+
+    for (i, ch) in line.char_indices() {
+        if let Some(quote) = in_string {
+            if ch == quote && prev != '\\' {
+                in_string = None;
+            }
         } else {
-            analysis.symbol_range
+            match ch {
+                '"' | '\'' | '`' => in_string = Some(ch),
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth -= 1,
+                ';' if depth <= 0 => separators.push(i),
+                _ => {}
+            }
         }
+        prev = ch;
     }
+
+    separators
 }
 
 /// Get the delete confirmation message
@@ -247,7 +802,6 @@ pub fn get_confirmation_message(ctx: &RefactorContext) -> Result<String, Refacto
 #[cfg(test)]
 mod tests {
     use super::*;
-    use logos_parser::LanguageId;
 
     fn make_ctx<'a>(source: &'a str, selection: Range, language: LanguageId) -> RefactorContext<'a> {
         RefactorContext::new(source, "test.js", selection, language)
@@ -279,4 +833,247 @@ mod tests {
         let result = analyze(&ctx);
         assert!(result.is_ok());
     }
+
+    fn deletion_range_for(source: &str, selection: Range) -> Range {
+        let ctx = make_ctx(source, selection, LanguageId::JavaScript);
+        let analysis = analyze(&ctx).unwrap();
+        find_deletion_range(&ctx, &analysis)
+    }
+
+    #[test]
+    fn test_method_and_free_function_with_same_name() {
+        let source = "class User {\n  public:\n    void greet() {}\n};\n\nvoid greet() {}\n\nint main() {\n  greet();\n}\n";
+        let method = Range::from_coords(2, 9, 2, 14);
+        let free = Range::from_coords(5, 5, 5, 10);
+
+        let method_scope = SymbolScope {
+            qualified_name: "User::greet".to_string(),
+            is_member: true,
+            member_scope: Some(Range::from_coords(0, 0, 3, 1)),
+            other_declarations: vec![free],
+        };
+        let ctx = make_ctx(source, method, LanguageId::Cpp);
+        let analysis = analyze_with_scope(&ctx, &method_scope).unwrap();
+        assert!(analysis.can_delete, "free call must not block the method");
+        assert!(delete_with_scope(&ctx, &method_scope).is_ok());
+
+        let free_scope = SymbolScope {
+            qualified_name: "greet".to_string(),
+            is_member: false,
+            member_scope: None,
+            other_declarations: vec![method],
+        };
+        let ctx = make_ctx(source, free, LanguageId::Cpp);
+        let analysis = analyze_with_scope(&ctx, &free_scope).unwrap();
+        assert!(!analysis.can_delete);
+        assert_eq!(analysis.usages.len(), 1);
+        assert_eq!(analysis.usages[0].range.start.line, 8);
+    }
+
+    #[test]
+    fn test_delete_in_workspace_removes_imports() {
+        use crate::move_symbol::ModuleImport;
+
+        let file = |uri: &'static str, source: &'static str, imports: Vec<(&str, Vec<&str>, u32)>| ModuleFile {
+            uri,
+            source,
+            imports: imports
+                .into_iter()
+                .map(|(module, names, line)| ModuleImport {
+                    module_path: module.to_string(),
+                    items: names.iter().map(|n| (n.to_string(), None)).collect(),
+                    range: Range::from_coords(line, 0, line, source.lines().nth(line as usize).unwrap().len() as u32),
+                })
+                .collect(),
+            declarations: Vec::new(),
+        };
+
+        let source = "export function helper() {}\nexport function keep() {}\n";
+        let ctx = RefactorContext::new(source, "file:///src/a.js", Range::from_coords(0, 16, 0, 22), LanguageId::JavaScript);
+        let others = vec![
+            file("file:///src/b.js", "import { helper, keep } from './a';\nkeep();\n", vec![("./a", vec!["helper", "keep"], 0)]),
+            file("file:///src/c.js", "import { helper } from './a';\nrun();\n", vec![("./a", vec!["helper"], 0)]),
+            // A different `helper`
+            file("file:///src/d.js", "import { helper } from './other';\nhelper();\n", vec![("./other", vec!["helper"], 0)]),
+        ];
+
+        let edit = delete_in_workspace(&ctx, None, &others).unwrap().edit;
+        let mut uris: Vec<&String> = edit.changes.keys().collect();
+        uris.sort();
+        assert_eq!(uris, vec!["file:///src/a.js", "file:///src/b.js", "file:///src/c.js"]);
+        let applied = |uri: &str, source: &str| apply_edits(source, &edit.changes[uri]);
+        assert_eq!(applied("file:///src/a.js", source), "export function keep() {}\n");
+        assert_eq!(applied("file:///src/b.js", others[0].source), "import { keep } from './a';\nkeep();\n");
+        assert_eq!(applied("file:///src/c.js", others[1].source), "run();\n");
+
+        let json = edit.to_json();
+        assert_eq!(json["changes"]["file:///src/c.js"][0]["range"]["end"], serde_json::json!({ "line": 1, "character": 0 }));
+        assert_eq!(json["changes"]["file:///src/c.js"][0]["newText"], "");
+
+        // A use in another document blocks the delete
+        let others = vec![file("file:///src/e.js", "import { helper } from './a';\nhelper();\n", vec![("./a", vec!["helper"], 0)])];
+        match delete_in_workspace(&ctx, None, &others) {
+            Err(RefactorError::SymbolInUse(usages)) => {
+                assert_eq!(usages, vec![Location::new("file:///src/e.js".to_string(), Range::from_coords(1, 0, 1, 6))]);
+            }
+            other => panic!("expected SymbolInUse, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_preview() {
+        let source = "let unused = 1; run();\nlet other = 2;\nconsole.log(other);\n";
+        let ctx = make_ctx(source, Range::from_coords(0, 4, 0, 10), LanguageId::JavaScript);
+
+        let preview = preview(&ctx).unwrap();
+        assert_eq!(preview.new_text, "run();\nlet other = 2;\nconsole.log(other);\n");
+        assert_eq!(preview.result.edits.len(), 1);
+    }
+
+    #[test]
+    fn test_preview_symbol_in_use() {
+        let source = "let other = 2;\nconsole.log(other);\n";
+        let ctx = make_ctx(source, Range::from_coords(0, 4, 0, 9), LanguageId::JavaScript);
+
+        assert!(matches!(preview(&ctx), Err(RefactorError::SymbolInUse(_))));
+    }
+
+    #[test]
+    fn test_definition_excluded_for_both_selection_shapes() {
+        let source = "let total = 0;\nlet count = 0;\ncount += 1;\n";
+        let selections = |line: u32, name: &str| {
+            let col = source.lines().nth(line as usize).unwrap().find(name).unwrap() as u32;
+            [
+                Range::from_coords(line, col, line, col + name.len() as u32),
+                Range::from_coords(line, 0, line, 14),
+                Range::from_coords(line, 0, line + 1, 0),
+            ]
+        };
+
+        for selection in selections(0, "total") {
+            let ctx = make_ctx(source, selection, LanguageId::JavaScript);
+            assert!(analyze(&ctx).unwrap().can_delete, "{:?}", selection);
+            assert_eq!(preview(&ctx).unwrap().new_text, "let count = 0;\ncount += 1;\n");
+        }
+        for selection in selections(1, "count") {
+            let analysis = analyze(&make_ctx(source, selection, LanguageId::JavaScript)).unwrap();
+            assert!(!analysis.can_delete, "{:?}", selection);
+            assert_eq!(analysis.usages.len(), 1);
+            assert_eq!(analysis.usages[0].range, Range::from_coords(2, 0, 2, 5));
+        }
+    }
+
+    #[test]
+    fn test_usage_outside_selection_is_not_taken_for_definition() {
+        // The only other occurrence must not be mistaken for the declaration
+        let source = "let total = 0;\nlog(total);\n";
+        let ctx = make_ctx(source, Range::from_coords(0, 0, 0, 9), LanguageId::JavaScript);
+        let analysis = analyze(&ctx).unwrap();
+        assert!(!analysis.can_delete);
+        assert_eq!(analysis.usages[0].range.start.line, 1);
+    }
+
+    #[test]
+    fn test_recursive_call_does_not_block_delete() {
+        let source = "function fact(n) {\n  return n * fact(n - 1);\n}\nrun();\n";
+        for selection in [Range::from_coords(0, 9, 0, 13), Range::from_coords(0, 0, 2, 1)] {
+            let analysis = analyze(&make_ctx(source, selection, LanguageId::JavaScript)).unwrap();
+            assert!(analysis.can_delete, "{:?}", selection);
+        }
+    }
+
+    #[test]
+    fn test_qualified_usages() {
+        let scope = SymbolScope {
+            qualified_name: "demo::User::greet".to_string(),
+            is_member: true,
+            ..SymbolScope::default()
+        };
+        let at = |line: &str| {
+            let col = line.find("greet").unwrap() as u32;
+            scope.attributes(line, Range::from_coords(0, col, 0, col + 5))
+        };
+        assert!(at("user.greet();"));
+        assert!(at("ptr->greet();"));
+        assert!(at("User::greet();"));
+        assert!(at("demo::User::greet();"));
+        assert!(!at("other::greet();"));
+    }
+
+    #[test]
+    fn test_deletion_range_whole_line() {
+        let source = "let x = 1;\nlet y = 2;\n";
+        let range = deletion_range_for(source, Range::from_coords(0, 4, 0, 5));
+        assert_eq!(range, Range::from_coords(0, 0, 1, 0));
+    }
+
+    #[test]
+    fn test_deletion_range_shared_line() {
+        let source = "let x = 1; doThing();\n";
+        let range = deletion_range_for(source, Range::from_coords(0, 4, 0, 5));
+        // Deletes "let x = 1; " and keeps "doThing();"
+        assert_eq!(range, Range::from_coords(0, 0, 0, 11));
+    }
+
+    #[test]
+    fn test_deletion_range_after_other_statement() {
+        let source = "doThing(); let x = 1;\n";
+        let range = deletion_range_for(source, Range::from_coords(0, 15, 0, 16));
+        // Deletes " let x = 1;" and keeps "doThing();"
+        assert_eq!(range, Range::from_coords(0, 10, 0, 21));
+    }
+
+    #[test]
+    fn test_deletion_range_block() {
+        let source = "function unused() {\n  return 1;\n}\nused();\n";
+        let range = deletion_range_for(source, Range::from_coords(0, 9, 0, 15));
+        assert_eq!(range, Range::from_coords(0, 0, 3, 0));
+    }
+
+    #[test]
+    fn test_delete_parameter() {
+        let source = "function f(a, b) {\n  return b;\n}\n";
+        let ctx = make_ctx(source, Range::from_coords(0, 11, 0, 12), LanguageId::JavaScript);
+        let result = delete(&ctx).unwrap();
+        assert_eq!(apply_edits(source, &result.edits), "function f(b) {\n  return b;\n}\n");
+
+        let ctx = make_ctx(source, Range::from_coords(0, 14, 0, 15), LanguageId::JavaScript);
+        assert!(matches!(delete(&ctx), Err(RefactorError::SymbolInUse(_))));
+
+        // A caller still passes an argument for `a`
+        let called = "function f(a, b) {\n  return b;\n}\nf(1, 2);\n";
+        let ctx = make_ctx(called, Range::from_coords(0, 11, 0, 12), LanguageId::JavaScript);
+        match delete(&ctx) {
+            Err(RefactorError::SymbolInUse(usages)) => {
+                assert_eq!(usages, vec![Location::new("test.js".to_string(), Range::from_coords(3, 0, 3, 1))]);
+            }
+            other => panic!("expected SymbolInUse, got {:?}", other),
+        }
+
+        // `a` and `b` share one declaration
+        let source = "package main\n\nfunc f(a, b int) int {\n\treturn b\n}\n";
+        let ctx = make_ctx(source, Range::from_coords(2, 7, 2, 8), LanguageId::Go);
+        assert!(matches!(delete(&ctx), Err(RefactorError::Unsupported(_))));
+    }
+
+    #[test]
+    fn test_delete_one_of_several_declarators() {
+        let source = "let x = 1, y = 2;\nconsole.log(y);\n";
+        let ctx = make_ctx(source, Range::from_coords(0, 4, 0, 5), LanguageId::JavaScript);
+        let result = delete(&ctx).unwrap();
+        assert_eq!(apply_edits(source, &result.edits), "let y = 2;\nconsole.log(y);\n");
+
+        let source = "void f() {\n  int x = 1, y = 2;\n  g(x);\n}\n";
+        let ctx = make_ctx(source, Range::from_coords(1, 13, 1, 14), LanguageId::C);
+        let result = delete(&ctx).unwrap();
+        assert_eq!(apply_edits(source, &result.edits), "void f() {\n  int x = 1;\n  g(x);\n}\n");
+    }
+
+    #[test]
+    fn test_deletion_range_last_line_without_newline() {
+        let source = "let y = 2;\nlet x = 1;";
+        let range = deletion_range_for(source, Range::from_coords(1, 4, 1, 5));
+        // Consumes the newline before the final line
+        assert_eq!(range, Range::from_coords(0, 10, 1, 10));
+    }
 }
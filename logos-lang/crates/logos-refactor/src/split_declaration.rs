@@ -0,0 +1,347 @@
+//! Split Declaration Refactoring
+//!
+//! Splits an initialized local variable declaration of C, C++ or Java into
+//! the declaration and an assignment, and joins them back:
+//! `int a = 1, b = f();` ⇄ `int a, b;` `a = 1;` `b = f();`
+//! - The type and its modifiers stay in front of the declarators; pointer
+//!   stars and array brackets stay with their declarator
+//! - Declarations whose meaning depends on the initializer are left alone:
+//!   `auto`/`var`, `const` values, references, `static` locals, arrays and
+//!   brace or constructor initialization
+//! - Join takes the plain `=` assignments right after the declaration, in
+//!   declarator order, while no value reads a name the declaration has not
+//!   bound yet
+
+use crate::{offset_to_position, RefactorContext, RefactorError, RefactorResult, TextEdit};
+use logos_core::Range;
+use logos_parser::{LanguageId, LanguageParser};
+use tree_sitter::{Node, Tree};
+
+/// Split the declaration at the cursor into a declaration without
+/// initializers followed by one assignment per initialized declarator
+pub fn split(ctx: &RefactorContext) -> Result<RefactorResult, RefactorError> {
+    let tree = parse(ctx)?;
+    let declaration = declaration_at(&tree, ctx)?;
+    check_splittable(declaration, ctx.source)?;
+
+    let declarators = declarators(declaration, ctx.language);
+    let mut written = Vec::new();
+    let mut assignments = Vec::new();
+    for declarator in &declarators {
+        match value_of(*declarator, ctx.language) {
+            Some(value) => {
+                check_value(*declarator, value, ctx.source)?;
+                let head = ctx.source[declarator.start_byte()..value.start_byte()].trim_end();
+                written.push(head.trim_end_matches('=').trim_end().to_string());
+                let name = name_of(*declarator, ctx.language)
+                    .ok_or_else(|| RefactorError::Unsupported("The declarator has no plain name".to_string()))?;
+                assignments.push(format!("{} = {};", &ctx.source[name.byte_range()], &ctx.source[value.byte_range()]));
+            }
+            None => written.push(ctx.source[declarator.byte_range()].to_string()),
+        }
+    }
+    if assignments.is_empty() {
+        return Err(RefactorError::Unsupported("The declaration has no initializer to split".to_string()));
+    }
+
+    let prefix = &ctx.source[declaration.start_byte()..declarators[0].start_byte()];
+    let indent = ctx.indentation_at(offset_to_position(ctx.source, declaration.start_byte()).line);
+    let mut text = format!("{}{};", prefix, written.join(", "));
+    for assignment in &assignments {
+        text.push('\n');
+        text.push_str(&indent);
+        text.push_str(assignment);
+    }
+
+    Ok(RefactorResult::new(
+        vec![TextEdit::replace(node_range(ctx.source, declaration), text)],
+        "Split declaration and initialization".to_string(),
+    ))
+}
+
+/// Join the declaration at the cursor, or before the assignment at the
+/// cursor, with the assignments that follow it
+pub fn join(ctx: &RefactorContext) -> Result<RefactorResult, RefactorError> {
+    let tree = parse(ctx)?;
+    let declaration = declaration_at(&tree, ctx)?;
+    check_splittable(declaration, ctx.source)?;
+
+    let declarators = declarators(declaration, ctx.language);
+    let names: Vec<Option<&str>> = declarators
+        .iter()
+        .map(|d| name_of(*d, ctx.language).map(|name| &ctx.source[name.byte_range()]))
+        .collect();
+
+    let mut edits = Vec::new();
+    let mut next = 0;
+    let mut last = declaration;
+    while let Some(statement) = last.next_named_sibling() {
+        let Some((target, value)) = plain_assignment(statement, ctx.source) else {
+            break;
+        };
+        // The next uninitialized declarator assigned to, in order
+        let Some(at) = (next..declarators.len())
+            .find(|&i| names[i] == Some(target) && value_of(declarators[i], ctx.language).is_none())
+        else {
+            break;
+        };
+        let unbound: Vec<&str> = names[at..].iter().flatten().copied().collect();
+        if reads_any(value, ctx.source, &unbound) {
+            break;
+        }
+        edits.push(TextEdit::insert(
+            offset_to_position(ctx.source, declarators[at].end_byte()),
+            format!(" = {}", &ctx.source[value.byte_range()]),
+        ));
+        next = at + 1;
+        last = statement;
+    }
+    if edits.is_empty() {
+        return Err(RefactorError::Unsupported(
+            "No assignment to a declared variable follows the declaration".to_string(),
+        ));
+    }
+    edits.push(TextEdit::delete(Range::new(
+        offset_to_position(ctx.source, declaration.end_byte()),
+        offset_to_position(ctx.source, last.end_byte()),
+    )));
+
+    Ok(RefactorResult::new(
+        TextEdit::normalize(edits)?,
+        "Join declaration and initialization".to_string(),
+    ))
+}
+
+fn parse(ctx: &RefactorContext) -> Result<Tree, RefactorError> {
+    if !matches!(ctx.language, LanguageId::C | LanguageId::Cpp | LanguageId::Java) {
+        return Err(RefactorError::Unsupported(format!(
+            "Splitting declarations is not available for {}",
+            ctx.language.as_str()
+        )));
+    }
+    let mut parser = LanguageParser::new();
+    parser
+        .set_language(ctx.language)
+        .map_err(|e| RefactorError::ParseError(e.to_string()))?;
+    parser
+        .parse(ctx.source, None)
+        .map_err(|e| RefactorError::ParseError(e.to_string()))
+}
+
+/// The local declaration around the cursor, or the one the assignments
+/// around the cursor follow
+fn declaration_at<'t>(tree: &'t Tree, ctx: &RefactorContext) -> Result<Node<'t>, RefactorError> {
+    let kind = match ctx.language {
+        LanguageId::Java => "local_variable_declaration",
+        _ => "declaration",
+    };
+    let offset = ctx.selection_bytes.start;
+    let mut current = tree.root_node().descendant_for_byte_range(offset, offset);
+    while let Some(node) = current {
+        if node.kind() == kind {
+            break;
+        }
+        if node.kind() == "expression_statement" {
+            // Back over the assignments to the declaration
+            let mut previous = node.prev_named_sibling();
+            while let Some(statement) = previous.filter(|s| s.kind() == "expression_statement") {
+                previous = statement.prev_named_sibling();
+            }
+            current = previous.filter(|p| p.kind() == kind);
+            break;
+        }
+        current = node.parent();
+    }
+
+    let declaration =
+        current.ok_or_else(|| RefactorError::InvalidSelection("No variable declaration at the cursor".to_string()))?;
+    // Only locals can be followed by assignments
+    let local = declaration
+        .parent()
+        .is_some_and(|parent| matches!(parent.kind(), "compound_statement" | "block" | "case_statement"));
+    if !local {
+        return Err(RefactorError::Unsupported("Only local variable declarations can be split".to_string()));
+    }
+    if declaration.has_error() {
+        return Err(RefactorError::ParseError("The declaration has syntax errors".to_string()));
+    }
+    Ok(declaration)
+}
+
+/// Reject declarations whose type or storage depends on the initializer
+fn check_splittable(declaration: Node, source: &str) -> Result<(), RefactorError> {
+    if let Some(ty) = declaration.child_by_field_name("type") {
+        let text = &source[ty.byte_range()];
+        if ty.kind() == "placeholder_type_specifier" || text == "auto" || text == "var" || text.starts_with("decltype") {
+            return Err(RefactorError::Unsupported(format!("The type '{}' comes from the initializer", text)));
+        }
+    }
+    let mut cursor = declaration.walk();
+    for child in declaration.children(&mut cursor) {
+        if child.kind() == "storage_class_specifier" && &source[child.byte_range()] != "register" {
+            return Err(RefactorError::Unsupported(format!(
+                "A '{}' variable is initialized once, not assigned",
+                &source[child.byte_range()]
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Reject initializers that cannot become an assignment
+fn check_value(declarator: Node, value: Node, source: &str) -> Result<(), RefactorError> {
+    if matches!(value.kind(), "initializer_list" | "argument_list" | "array_initializer") {
+        return Err(RefactorError::Unsupported(format!(
+            "'{}' only works as an initializer",
+            &source[value.byte_range()]
+        )));
+    }
+    // `const int n = 1`, but `const char *s = ...` points to constants
+    let is_const = declarator.parent().is_some_and(|declaration| {
+        let mut cursor = declaration.walk();
+        let constant = declaration
+            .children(&mut cursor)
+            .any(|c| matches!(&source[c.byte_range()], "const" | "constexpr"));
+        constant
+    });
+    let inner = declarator.child_by_field_name("declarator");
+    match inner.map(|d| d.kind()) {
+        Some("reference_declarator") => Err(RefactorError::Unsupported("A reference must be bound where it is declared".to_string())),
+        Some("array_declarator") => Err(RefactorError::Unsupported("Arrays cannot be assigned".to_string())),
+        Some("pointer_declarator") => Ok(()),
+        _ if is_const => Err(RefactorError::Unsupported("A constant cannot be assigned".to_string())),
+        _ => Ok(()),
+    }
+}
+
+/// The declarators of a declaration, in order
+fn declarators(declaration: Node, language: LanguageId) -> Vec<Node> {
+    let mut cursor = declaration.walk();
+    let declarators: Vec<Node> = declaration.children_by_field_name("declarator", &mut cursor).collect();
+    match language {
+        LanguageId::Java => declarators.into_iter().filter(|d| d.kind() == "variable_declarator").collect(),
+        _ => declarators,
+    }
+}
+
+/// The initializer of a declarator
+fn value_of(declarator: Node, language: LanguageId) -> Option<Node> {
+    match (language, declarator.kind()) {
+        (LanguageId::Java, _) | (_, "init_declarator") => declarator.child_by_field_name("value"),
+        _ => None,
+    }
+}
+
+/// The identifier a declarator declares, through pointers; `None` for arrays,
+/// functions and references
+fn name_of(declarator: Node, language: LanguageId) -> Option<Node> {
+    if language == LanguageId::Java {
+        return declarator.child_by_field_name("name");
+    }
+    let mut current = declarator;
+    loop {
+        match current.kind() {
+            "identifier" => return Some(current),
+            "init_declarator" | "pointer_declarator" => current = current.child_by_field_name("declarator")?,
+            _ => return None,
+        }
+    }
+}
+
+/// The target and value of a statement `name = value;`
+fn plain_assignment<'t, 's>(statement: Node<'t>, source: &'s str) -> Option<(&'s str, Node<'t>)> {
+    if statement.kind() != "expression_statement" {
+        return None;
+    }
+    let assignment = statement.named_child(0).filter(|a| a.kind() == "assignment_expression")?;
+    let left = assignment.child_by_field_name("left").filter(|l| l.kind() == "identifier")?;
+    let right = assignment.child_by_field_name("right")?;
+    if source[left.end_byte()..right.start_byte()].trim() != "=" {
+        return None;
+    }
+    Some((&source[left.byte_range()], right))
+}
+
+/// Whether `node` reads one of `names`
+fn reads_any(node: Node, source: &str, names: &[&str]) -> bool {
+    if node.kind() == "identifier" {
+        return names.contains(&&source[node.byte_range()]);
+    }
+    let mut cursor = node.walk();
+    let found = node.named_children(&mut cursor).any(|child| reads_any(child, source, names));
+    found
+}
+
+fn node_range(source: &str, node: Node) -> Range {
+    Range::new(
+        offset_to_position(source, node.start_byte()),
+        offset_to_position(source, node.end_byte()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(
+        refactor: fn(&RefactorContext) -> Result<RefactorResult, RefactorError>,
+        source: &str,
+        language: LanguageId,
+        line: u32,
+        column: u32,
+    ) -> Result<String, RefactorError> {
+        let ctx = RefactorContext::new(source, "file:///test", Range::point(line, column), language);
+        let result = refactor(&ctx)?;
+        Ok(crate::apply_edits(source, &result.edits))
+    }
+
+    #[test]
+    fn test_split_declaration() {
+        let source = "int main() {\n    int *p = make(), n;\n    return 0;\n}\n";
+        assert_eq!(
+            run(split, source, LanguageId::C, 1, 10).unwrap(),
+            "int main() {\n    int *p, n;\n    p = make();\n    return 0;\n}\n"
+        );
+
+        let source = "class A {\n  void f() {\n    final String s = name(), t[] = null;\n  }\n}\n";
+        assert_eq!(
+            run(split, source, LanguageId::Java, 2, 4).unwrap(),
+            "class A {\n  void f() {\n    final String s, t[];\n    s = name();\n    t = null;\n  }\n}\n"
+        );
+
+        for rejected in ["auto x = f();", "const int n = 1;", "int &r = x;", "static int c = 0;", "Point p(1, 2);", "int a[] = {1, 2};"] {
+            let source = format!("void f() {{\n  {}\n}}\n", rejected);
+            assert!(run(split, &source, LanguageId::Cpp, 1, 4).is_err(), "{}", rejected);
+        }
+        assert!(run(split, "int g = 1;\n", LanguageId::C, 0, 4).is_err());
+    }
+
+    #[test]
+    fn test_join_declaration() {
+        let source = "void f() {\n  int x;\n  x = compute();\n  use(x);\n}\n";
+        let joined = "void f() {\n  int x = compute();\n  use(x);\n}\n";
+        assert_eq!(run(join, source, LanguageId::Cpp, 1, 6).unwrap(), joined);
+        // From the assignment as well
+        assert_eq!(run(join, source, LanguageId::Cpp, 2, 2).unwrap(), joined);
+        assert_eq!(run(split, joined, LanguageId::Cpp, 1, 6).unwrap(), source);
+
+        // Not a plain `=`, or reading itself
+        assert!(run(join, "void f() {\n  int x;\n  x += 1;\n}\n", LanguageId::C, 1, 6).is_err());
+        assert!(run(join, "void f() {\n  int x;\n  x = x + 1;\n}\n", LanguageId::C, 1, 6).is_err());
+    }
+
+    #[test]
+    fn test_multiple_declarators() {
+        let source = "void f() {\n  int a = 1, b = a + 1, c;\n}\n";
+        let split_source = "void f() {\n  int a, b, c;\n  a = 1;\n  b = a + 1;\n}\n";
+        assert_eq!(run(split, source, LanguageId::C, 1, 2).unwrap(), split_source);
+        assert_eq!(run(join, split_source, LanguageId::C, 1, 2).unwrap(), "void f() {\n  int a = 1, b = a + 1, c;\n}\n");
+
+        // Out of declarator order, `a` would read `b` before it is declared
+        let source = "void f() {\n  int a, b;\n  b = 2;\n  a = b;\n}\n";
+        assert_eq!(
+            run(join, source, LanguageId::C, 1, 2).unwrap(),
+            "void f() {\n  int a, b = 2;\n  a = b;\n}\n"
+        );
+    }
+}
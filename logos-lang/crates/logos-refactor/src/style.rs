@@ -0,0 +1,415 @@
+//! Code Style
+//!
+//! Generated declarations and functions are written with fixed punctuation.
+//! `CodeStyle` samples the file they go into for its quote character and
+//! whether statements end with `;` (JavaScript and TypeScript files may rely
+//! on automatic semicolon insertion), and rewrites generated code to match.
+//! Only what is certain to mean the same is rewritten: string literals
+//! without quotes or escapes inside, and semicolons that ASI would put back.
+//!
+//! `normalize` applies a chosen style to a whole document instead: every
+//! plain string literal is requoted, escaping the chosen quote inside and
+//! unescaping the other, and statement-ending `;` are added or removed.
+//! Template literals, JSX attributes and prefixed or triple-quoted Python
+//! strings keep their quotes.
+
+use crate::analysis::literal_spans;
+use crate::{offset_to_position, RefactorContext, RefactorError, RefactorResult, TextEdit};
+use logos_core::Range;
+use logos_parser::{LanguageId, LanguageParser};
+use serde::{Deserialize, Serialize};
+use tree_sitter::Node;
+
+/// The punctuation style of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodeStyle {
+    /// Whether statements end with `;`
+    pub semicolons: bool,
+    /// The quote of most string literals, if the file has a clear preference
+    pub quote: Option<char>,
+}
+
+impl Default for CodeStyle {
+    fn default() -> Self {
+        Self {
+            semicolons: true,
+            quote: None,
+        }
+    }
+}
+
+impl CodeStyle {
+    /// The style of `source`
+    pub fn detect(source: &str, language: LanguageId) -> Self {
+        let semicolons = match language {
+            LanguageId::Python | LanguageId::Go => false,
+            LanguageId::JavaScript | LanguageId::TypeScript => {
+                let spans = literal_spans(source, 0, source.len(), language);
+                let mut code = code_lines(source, &spans).filter(|(_, line)| !line.is_empty()).peekable();
+                // Semicolon-free style: code, but no line ends with `;`
+                code.peek().is_none() || code.any(|(_, line)| line.ends_with(';'))
+            }
+            _ => true,
+        };
+
+        let quote = match language {
+            LanguageId::JavaScript | LanguageId::TypeScript | LanguageId::Python => {
+                let (mut single, mut double) = (0, 0);
+                for (start, _) in literal_spans(source, 0, source.len(), language) {
+                    match source.as_bytes()[start] {
+                        b'\'' => single += 1,
+                        b'"' => double += 1,
+                        _ => {}
+                    }
+                }
+                // A clear majority only
+                if single > 2 * double {
+                    Some('\'')
+                } else if double > 2 * single {
+                    Some('"')
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+
+        Self { semicolons, quote }
+    }
+
+    /// The statement terminator of the style
+    pub fn terminator(&self) -> &'static str {
+        if self.semicolons {
+            ";"
+        } else {
+            ""
+        }
+    }
+
+    /// `code` rewritten to the style
+    pub fn apply(&self, code: &str, language: LanguageId) -> String {
+        let mut code = code.to_string();
+        if let Some(quote) = self.quote {
+            code = requote(&code, quote, language);
+        }
+        if !self.semicolons && matches!(language, LanguageId::JavaScript | LanguageId::TypeScript) {
+            code = strip_semicolons(&code, language);
+        }
+        code
+    }
+}
+
+/// The quote `normalize` gives string literals
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuoteStyle {
+    Single,
+    Double,
+}
+
+impl QuoteStyle {
+    fn as_char(self) -> char {
+        match self {
+            QuoteStyle::Single => '\'',
+            QuoteStyle::Double => '"',
+        }
+    }
+}
+
+/// What `normalize` rewrites; `None` leaves that part of the style alone
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NormalizeOptions {
+    #[serde(default)]
+    pub quotes: Option<QuoteStyle>,
+    /// Whether JavaScript/TypeScript statements end with `;`
+    #[serde(default)]
+    pub semicolons: Option<bool>,
+}
+
+/// Statements that end with `;` or rely on automatic semicolon insertion
+const TERMINATED_STATEMENTS: &[&str] = &[
+    "expression_statement",
+    "variable_declaration",
+    "lexical_declaration",
+    "import_statement",
+    "export_statement",
+    "return_statement",
+    "throw_statement",
+    "break_statement",
+    "continue_statement",
+    "debugger_statement",
+    "do_statement",
+    "type_alias_declaration",
+];
+
+/// Rewrite the quotes and semicolons of `ctx`'s whole document to `options`
+pub fn normalize(ctx: &RefactorContext, options: &NormalizeOptions) -> Result<RefactorResult, RefactorError> {
+    let script = matches!(ctx.language, LanguageId::JavaScript | LanguageId::TypeScript);
+    if !script && ctx.language != LanguageId::Python {
+        return Err(RefactorError::Unsupported(format!(
+            "Normalize Style is not supported for {} files",
+            ctx.language.as_str()
+        )));
+    }
+    let source = ctx.source;
+    let mut parser = LanguageParser::new();
+    parser
+        .set_language(ctx.language)
+        .map_err(|e| RefactorError::ParseError(e.to_string()))?;
+    let tree = parser
+        .parse(source, None)
+        .map_err(|e| RefactorError::ParseError(e.to_string()))?;
+    let range = |start: usize, end: usize| Range::new(offset_to_position(source, start), offset_to_position(source, end));
+
+    let mut edits = Vec::new();
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        let kind = node.kind();
+        if kind.contains("comment") || kind == "template_string" {
+            continue;
+        }
+        if kind == "string" {
+            let in_jsx = node.parent().is_some_and(|parent| parent.kind() == "jsx_attribute");
+            let literal = &source[node.byte_range()];
+            if let (Some(quote), false) = (options.quotes, in_jsx) {
+                if let Some(text) = requote_literal(literal, quote.as_char()) {
+                    edits.push(TextEdit::replace(range(node.start_byte(), node.end_byte()), text));
+                }
+            }
+            continue;
+        }
+        if script && options.semicolons == Some(true) && TERMINATED_STATEMENTS.contains(&kind) {
+            if let Some(at) = missing_semicolon(node, source) {
+                edits.push(TextEdit::insert(offset_to_position(source, at), ";".to_string()));
+            }
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.children(&mut cursor));
+    }
+    if script && options.semicolons == Some(false) {
+        for at in semicolon_removals(source, ctx.language) {
+            edits.push(TextEdit::delete(range(at, at + 1)));
+        }
+    }
+
+    let edits = TextEdit::normalize(edits)?;
+    let description = if edits.is_empty() { "Style is already normalized" } else { "Normalize style" };
+    Ok(RefactorResult::new(edits, description.to_string()))
+}
+
+/// `literal` quoted with `quote` instead, or `None` if it already is, is not
+/// a plain string literal, or would need more escapes than it has now (so
+/// `"it's"` keeps its double quotes)
+fn requote_literal(literal: &str, quote: char) -> Option<String> {
+    let current = literal.chars().next().filter(|c| matches!(c, '\'' | '"'))?;
+    if current == quote || literal.len() < 2 || !literal.ends_with(current) || literal.starts_with("\"\"\"") || literal.starts_with("'''") {
+        return None;
+    }
+    let inner = &literal[1..literal.len() - 1];
+    if inner.matches(quote).count() > inner.matches(current).count() {
+        return None;
+    }
+
+    let mut body = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(next) if next == current => body.push(next),
+                Some(next) => {
+                    body.push(c);
+                    body.push(next);
+                }
+                None => body.push(c),
+            },
+            _ if c == quote => {
+                body.push('\\');
+                body.push(c);
+            }
+            _ => body.push(c),
+        }
+    }
+    Some(format!("{}{}{}", quote, body, quote))
+}
+
+/// Where the `;` of a statement that relies on automatic semicolon insertion
+/// goes; `None` if it has one, or does not take one
+fn missing_semicolon(node: Node, source: &str) -> Option<usize> {
+    if node.has_error() {
+        return None;
+    }
+    if node.kind() == "export_statement" {
+        // `export function f() {}`; `export const` gets its `;` from the
+        // declaration
+        let value = node.child_by_field_name("value");
+        if node.child_by_field_name("declaration").is_some()
+            || value.is_some_and(|v| matches!(v.kind(), "function_expression" | "function" | "class"))
+        {
+            return None;
+        }
+    }
+    let mut cursor = node.walk();
+    let last = node.children(&mut cursor).filter(|child| !child.kind().contains("comment")).last()?;
+    if last.kind() == ";" || source[last.byte_range()].ends_with(';') {
+        return None;
+    }
+    Some(last.end_byte())
+}
+
+/// Each line with its byte offset, cut at the first comment and trimmed
+fn code_lines<'a>(source: &'a str, spans: &'a [(usize, usize)]) -> impl Iterator<Item = (usize, &'a str)> + 'a {
+    let mut offset = 0;
+    source.split('\n').map(move |line| {
+        let start = offset;
+        offset += line.len() + 1;
+        let end = spans
+            .iter()
+            .filter(|(s, e)| *s < start + line.len() && *e > start && source[*s..].starts_with(['/', '#']))
+            .map(|(s, _)| (*s).max(start) - start)
+            .min()
+            .unwrap_or(line.len());
+        (start, line[..end].trim())
+    })
+}
+
+/// String literals of `code` quoted with `quote`, where nothing inside
+/// them would need escaping
+fn requote(code: &str, quote: char, language: LanguageId) -> String {
+    let mut result = code.to_string();
+    for (start, end) in literal_spans(code, 0, code.len(), language).into_iter().rev() {
+        let literal = &code[start..end];
+        let current = match literal.chars().next() {
+            Some(c @ ('\'' | '"')) if c != quote => c,
+            _ => continue,
+        };
+        if literal.len() < 2 || !literal.ends_with(current) {
+            continue;
+        }
+        let inner = &literal[1..literal.len() - 1];
+        if inner.contains(['\'', '"', '\\', '\n']) {
+            continue;
+        }
+        result.replace_range(start..end, &format!("{}{}{}", quote, inner, quote));
+    }
+    result
+}
+
+/// `code` without the `;` ending its lines, where automatic semicolon
+/// insertion restores them
+fn strip_semicolons(code: &str, language: LanguageId) -> String {
+    let mut result = code.to_string();
+    for at in semicolon_removals(code, language).into_iter().rev() {
+        result.remove(at);
+    }
+    result
+}
+
+/// Byte offsets of the `;` ending lines of `code` that automatic semicolon
+/// insertion restores
+fn semicolon_removals(code: &str, language: LanguageId) -> Vec<usize> {
+    let spans = literal_spans(code, 0, code.len(), language);
+    let lines: Vec<(usize, &str)> = code_lines(code, &spans).collect();
+    let mut removals = Vec::new();
+
+    for (i, (start, line)) in lines.iter().enumerate() {
+        if !line.ends_with(';') || *line == ";" || line.starts_with("for") {
+            continue;
+        }
+        // The next line would continue the statement without the `;`
+        let next = lines[i + 1..].iter().map(|(_, l)| *l).find(|l| !l.is_empty());
+        if next.is_some_and(|l| l.starts_with(['(', '[', '`', '+', '-', '/'])) {
+            continue;
+        }
+        let at = start + code[*start..].find(line).unwrap_or(0) + line.len() - 1;
+        if spans.iter().any(|(s, e)| *s <= at && at < *e) {
+            continue;
+        }
+        removals.push(at);
+    }
+    removals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_style() {
+        let asi = "import { a } from 'a'\nconst b = a('x') // done;\nconsole.log(b, 'y')\n";
+        assert_eq!(
+            CodeStyle::detect(asi, LanguageId::JavaScript),
+            CodeStyle {
+                semicolons: false,
+                quote: Some('\''),
+            }
+        );
+
+        let semi = "const a = \"x\";\nconst b = 'y';\n";
+        assert_eq!(CodeStyle::detect(semi, LanguageId::TypeScript), CodeStyle::default());
+    }
+
+    #[test]
+    fn test_apply_style() {
+        let style = CodeStyle {
+            semicolons: false,
+            quote: Some('\''),
+        };
+        let code = "function f() {\n  const s = \"it's\" + \"a;b\";\n  log(\"x\");\n  [1].map(g);\n  for (;;) {}\n}\n";
+        assert_eq!(
+            style.apply(code, LanguageId::JavaScript),
+            "function f() {\n  const s = \"it's\" + 'a;b'\n  log('x');\n  [1].map(g)\n  for (;;) {}\n}\n"
+        );
+    }
+
+    fn normalized(source: &str, language: LanguageId, options: NormalizeOptions) -> String {
+        let ctx = RefactorContext::new(source, "file:///test", Range::point(0, 0), language);
+        let result = normalize(&ctx, &options).unwrap();
+        crate::apply_edits(source, &result.edits)
+    }
+
+    #[test]
+    fn test_normalize_quotes() {
+        let single = NormalizeOptions {
+            quotes: Some(QuoteStyle::Single),
+            semicolons: None,
+        };
+        let source = "import a from \"a\";\nconst b = \"x\" + 'y';\nconst c = \"say \\\"hi\\\"\";\nconst d = \"it's\";\nconst e = `${b} ${names[\"k\"]}`;\nconst f = <a href=\"x\" />;\n";
+        assert_eq!(
+            normalized(source, LanguageId::JavaScript, single),
+            "import a from 'a';\nconst b = 'x' + 'y';\nconst c = 'say \"hi\"';\nconst d = \"it's\";\nconst e = `${b} ${names[\"k\"]}`;\nconst f = <a href=\"x\" />;\n"
+        );
+
+        let double = NormalizeOptions {
+            quotes: Some(QuoteStyle::Double),
+            semicolons: None,
+        };
+        let source = "a = 'x'\nb = f'{a}'\nc = '''doc'''\nd = 'tab\\t'\n";
+        assert_eq!(
+            normalized(source, LanguageId::Python, double),
+            "a = \"x\"\nb = f'{a}'\nc = '''doc'''\nd = \"tab\\t\"\n"
+        );
+    }
+
+    #[test]
+    fn test_normalize_semicolons() {
+        let add = NormalizeOptions {
+            quotes: None,
+            semicolons: Some(true),
+        };
+        let source = "import { a } from 'a'\nlet x = a() // call\nexport function f() {\n  if (x) return\n  do { x-- } while (x)\n}\nexport default { x }\nfor (;;) {}\n";
+        let with = "import { a } from 'a';\nlet x = a(); // call\nexport function f() {\n  if (x) return;\n  do { x--; } while (x);\n}\nexport default { x };\nfor (;;) {}\n";
+        assert_eq!(normalized(source, LanguageId::JavaScript, add), with);
+
+        let remove = NormalizeOptions {
+            quotes: None,
+            semicolons: Some(false),
+        };
+        let without = normalized(with, LanguageId::JavaScript, remove);
+        assert!(!without.contains("'a';"));
+        assert_eq!(normalized(&without, LanguageId::JavaScript, add), with);
+
+        let ctx = RefactorContext::new("int x;", "file:///a.c", Range::point(0, 0), LanguageId::C);
+        assert!(matches!(normalize(&ctx, &add), Err(RefactorError::Unsupported(_))));
+    }
+}
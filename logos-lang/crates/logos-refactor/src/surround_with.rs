@@ -0,0 +1,276 @@
+//! Surround With Refactoring
+//!
+//! Wrap the selected lines in an `if`, `try`, `for` or `while` scaffold in
+//! the syntax of the language. The selection is widened to whole lines,
+//! which move one indentation level deeper; the scaffold's placeholders
+//! (condition, loop variable, ...) are reported so the editor can place the
+//! cursor on them.
+
+use crate::{offset_to_position, RefactorContext, RefactorError, RefactorResult, TextEdit};
+use logos_core::Range;
+use logos_parser::LanguageId;
+use serde::{Deserialize, Serialize};
+
+/// The control structure to surround the selection with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SurroundKind {
+    If,
+    Try,
+    For,
+    While,
+}
+
+impl SurroundKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SurroundKind::If => "if",
+            SurroundKind::Try => "try",
+            SurroundKind::For => "for",
+            SurroundKind::While => "while",
+        }
+    }
+}
+
+/// The result of surrounding a selection
+#[derive(Debug, Clone)]
+pub struct Surrounded {
+    pub result: RefactorResult,
+    /// Placeholder text in the edited document, in scaffold order
+    pub placeholders: Vec<Range>,
+}
+
+/// Scaffold text with the byte spans of its placeholders
+#[derive(Default)]
+struct Scaffold {
+    text: String,
+    placeholders: Vec<(usize, usize)>,
+}
+
+impl Scaffold {
+    fn push(&mut self, text: &str) -> &mut Self {
+        self.text.push_str(text);
+        self
+    }
+
+    fn placeholder(&mut self, text: &str) -> &mut Self {
+        let start = self.text.len();
+        self.text.push_str(text);
+        self.placeholders.push((start, self.text.len()));
+        self
+    }
+}
+
+/// Surround the lines of the selection with `kind`
+pub fn surround(ctx: &RefactorContext, kind: SurroundKind) -> Result<Surrounded, RefactorError> {
+    let (start, end) = line_span(ctx);
+    let selected = &ctx.source[start..end];
+    let newline = if ctx.source.contains("\r\n") { "\r\n" } else { "\n" };
+    let lines: Vec<&str> = selected.split('\n').map(|l| l.strip_suffix('\r').unwrap_or(l)).collect();
+    if lines.iter().all(|l| l.trim().is_empty()) {
+        return Err(RefactorError::InvalidSelection("Nothing to surround".to_string()));
+    }
+
+    // The scaffold goes at the shallowest indentation of the selection
+    let base_indent = lines
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| &l[..l.len() - l.trim_start().len()])
+        .min_by_key(|indent| indent.len())
+        .unwrap_or("");
+    let unit = indent_unit(ctx.source, base_indent, ctx.language);
+    let body: Vec<String> = lines
+        .iter()
+        .map(|l| if l.trim().is_empty() { String::new() } else { format!("{}{}", unit, l) })
+        .collect();
+    let body = body.join(newline);
+
+    let mut scaffold = Scaffold::default();
+    write_scaffold(&mut scaffold, kind, ctx.language, base_indent, &unit, &body, newline)?;
+
+    let range = Range::new(offset_to_position(ctx.source, start), offset_to_position(ctx.source, end));
+    let placeholders = scaffold
+        .placeholders
+        .iter()
+        .map(|&(s, e)| Range::new(range.start.advanced(&scaffold.text[..s]), range.start.advanced(&scaffold.text[..e])))
+        .collect();
+
+    let result = RefactorResult::new(
+        vec![TextEdit::replace(range, scaffold.text.clone())],
+        format!("Surround with '{}'", kind.as_str()),
+    )
+    .with_generated_code(scaffold.text);
+    Ok(Surrounded { result, placeholders })
+}
+
+/// Byte span of the whole lines the selection touches. A selection ending at
+/// the start of a line does not include that line.
+fn line_span(ctx: &RefactorContext) -> (usize, usize) {
+    let source = ctx.source;
+    let sel = ctx.selection_bytes.clone();
+    let start = source[..sel.start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+
+    let mut end = sel.end;
+    if end > sel.start && source[..end].ends_with('\n') {
+        end -= 1;
+    } else {
+        end = source[end..].find('\n').map(|i| end + i).unwrap_or(source.len());
+    }
+    if source[..end].ends_with('\r') {
+        end -= 1;
+    }
+    (start, end.max(start))
+}
+
+/// One level of indentation: tabs if the selection is tab-indented (or the
+/// language is Go), else the narrowest space indentation in the file
+pub(crate) fn indent_unit(source: &str, base_indent: &str, language: LanguageId) -> String {
+    if base_indent.contains('\t') || language == LanguageId::Go {
+        return "\t".to_string();
+    }
+    let width = source
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start_matches(' ').len())
+        .filter(|&n| n > 0)
+        .min()
+        .unwrap_or(4);
+    " ".repeat(width)
+}
+
+fn write_scaffold(
+    s: &mut Scaffold,
+    kind: SurroundKind,
+    language: LanguageId,
+    indent: &str,
+    unit: &str,
+    body: &str,
+    nl: &str,
+) -> Result<(), RefactorError> {
+    let unsupported = || {
+        Err(RefactorError::Unsupported(format!(
+            "{} has no '{}' statement",
+            language.as_str(),
+            kind.as_str()
+        )))
+    };
+
+    if language == LanguageId::Python {
+        s.push(indent);
+        match kind {
+            SurroundKind::If => s.push("if ").placeholder("condition").push(":"),
+            SurroundKind::Try => s.push("try:"),
+            SurroundKind::For => s.push("for ").placeholder("item").push(" in ").placeholder("items").push(":"),
+            SurroundKind::While => s.push("while ").placeholder("condition").push(":"),
+        };
+        s.push(nl).push(body);
+        if kind == SurroundKind::Try {
+            s.push(nl).push(indent).push("except ").placeholder("Exception").push(":");
+            s.push(nl).push(indent).push(unit).placeholder("pass");
+        }
+        return Ok(());
+    }
+
+    s.push(indent);
+    match (kind, language) {
+        (SurroundKind::If, LanguageId::Go | LanguageId::Rust) => s.push("if ").placeholder("condition").push(" {"),
+        (SurroundKind::If, _) => s.push("if (").placeholder("condition").push(") {"),
+        (SurroundKind::While, LanguageId::Go) => s.push("for ").placeholder("condition").push(" {"),
+        (SurroundKind::While, LanguageId::Rust) => s.push("while ").placeholder("condition").push(" {"),
+        (SurroundKind::While, _) => s.push("while (").placeholder("condition").push(") {"),
+        (SurroundKind::For, LanguageId::JavaScript | LanguageId::TypeScript) => {
+            s.push("for (const ").placeholder("item").push(" of ").placeholder("items").push(") {")
+        }
+        (SurroundKind::For, LanguageId::Java) => {
+            s.push("for (var ").placeholder("item").push(" : ").placeholder("items").push(") {")
+        }
+        (SurroundKind::For, LanguageId::Cpp) => {
+            s.push("for (auto& ").placeholder("item").push(" : ").placeholder("items").push(") {")
+        }
+        (SurroundKind::For, LanguageId::C) => {
+            s.push("for (int i = 0; i < ").placeholder("count").push("; i++) {")
+        }
+        (SurroundKind::For, LanguageId::Go) => {
+            s.push("for _, ").placeholder("item").push(" := range ").placeholder("items").push(" {")
+        }
+        (SurroundKind::For, _) => s.push("for ").placeholder("item").push(" in ").placeholder("items").push(" {"),
+        (SurroundKind::Try, LanguageId::JavaScript | LanguageId::TypeScript | LanguageId::Java | LanguageId::Cpp) => {
+            s.push("try {")
+        }
+        (SurroundKind::Try, _) => return unsupported(),
+    };
+    s.push(nl).push(body).push(nl).push(indent).push("}");
+
+    if kind == SurroundKind::Try {
+        s.push(" catch (");
+        match language {
+            LanguageId::Java => s.placeholder("Exception").push(" e"),
+            LanguageId::Cpp => s.push("const ").placeholder("std::exception").push("& e"),
+            _ => s.placeholder("error"),
+        };
+        s.push(") {").push(nl).push(indent).push("}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apply_edits;
+
+    fn surrounded(source: &str, selection: Range, language: LanguageId, kind: SurroundKind) -> (String, Vec<String>) {
+        let ctx = RefactorContext::new(source, "test", selection, language);
+        let surrounded = surround(&ctx, kind).unwrap();
+        let output = apply_edits(source, &surrounded.result.edits);
+        let placeholders = surrounded
+            .placeholders
+            .iter()
+            .map(|&range| RefactorContext::new(&output, "test", range, language).selected_text().to_string())
+            .collect();
+        (output, placeholders)
+    }
+
+    #[test]
+    fn test_surround_with_try_python() {
+        let source = "def load(path):\n    data = read(path)\n    if data:\n        return parse(data)\n    return None\n";
+        let (output, placeholders) =
+            surrounded(source, Range::from_coords(1, 4, 4, 0), LanguageId::Python, SurroundKind::Try);
+        assert_eq!(
+            output,
+            "def load(path):\n    try:\n        data = read(path)\n        if data:\n            return parse(data)\n    except Exception:\n        pass\n    return None\n"
+        );
+        assert_eq!(placeholders, vec!["Exception", "pass"]);
+    }
+
+    #[test]
+    fn test_surround_with_try_javascript() {
+        let source = "function load(path) {\n  const data = read(path);\n\n  return parse(data);\n}\n";
+        let (output, placeholders) =
+            surrounded(source, Range::from_coords(1, 2, 3, 20), LanguageId::JavaScript, SurroundKind::Try);
+        assert_eq!(
+            output,
+            "function load(path) {\n  try {\n    const data = read(path);\n\n    return parse(data);\n  } catch (error) {\n  }\n}\n"
+        );
+        assert_eq!(placeholders, vec!["error"]);
+    }
+
+    #[test]
+    fn test_surround_with_loops_and_conditions() {
+        let source = "fn main() {\n    step();\n}\n";
+        let (output, placeholders) =
+            surrounded(source, Range::from_coords(1, 6, 1, 6), LanguageId::Rust, SurroundKind::For);
+        assert_eq!(output, "fn main() {\n    for item in items {\n        step();\n    }\n}\n");
+        assert_eq!(placeholders, vec!["item", "items"]);
+
+        let source = "func main() {\n\tstep()\n}\n";
+        let (output, _) = surrounded(source, Range::from_coords(1, 1, 1, 7), LanguageId::Go, SurroundKind::While);
+        assert_eq!(output, "func main() {\n\tfor condition {\n\t\tstep()\n\t}\n}\n");
+
+        let ctx = RefactorContext::new(source, "test", Range::from_coords(1, 1, 1, 7), LanguageId::Go);
+        assert!(matches!(surround(&ctx, SurroundKind::Try), Err(RefactorError::Unsupported(_))));
+
+        let source = "int main() {\n\n  return 0;\n}\n";
+        let ctx = RefactorContext::new(source, "test", Range::from_coords(1, 0, 1, 0), LanguageId::C);
+        assert!(matches!(surround(&ctx, SurroundKind::If), Err(RefactorError::InvalidSelection(_))));
+    }
+}
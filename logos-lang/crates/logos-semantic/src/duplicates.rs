@@ -0,0 +1,328 @@
+//! Duplicate Code Detection
+//!
+//! Finds copy-pasted runs of statements, as candidates for Extract Method.
+//! - Statements are compared as token sequences in which identifiers and
+//!   literals are normalized away, so renamed copies still match
+//! - Runs of consecutive statements in the same block are hashed; the
+//!   longest runs win and the statements they cover are not reported again
+//! - Each group's similarity is the share of tokens, identifiers and
+//!   literals included, that are exactly the same in every copy
+//!
+//! Ranges use UTF-16 columns, like the refactoring selections they feed.
+
+use logos_core::{CancellationToken, Location, Position, Range};
+use logos_parser::{LanguageId, LanguageParser};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use tree_sitter::Node;
+
+/// Copies of the same run of statements
+#[derive(Debug, Clone)]
+pub struct DuplicateGroup {
+    /// The copies, in source order
+    pub locations: Vec<Location>,
+    /// Tokens in each copy
+    pub token_count: usize,
+    /// 1.0 for verbatim copies, lower the more identifiers and literals differ
+    pub similarity: f64,
+}
+
+struct Statement {
+    start: usize,
+    end: usize,
+    /// Hash of the normalized tokens
+    hash: u64,
+    tokens: Vec<String>,
+}
+
+struct File {
+    uri: String,
+    source: String,
+    /// The statements of each block
+    blocks: Vec<Vec<Statement>>,
+}
+
+/// Duplicate detector over one or more files
+pub struct DuplicateDetector {
+    min_tokens: usize,
+    files: Vec<File>,
+    cancellation: CancellationToken,
+}
+
+impl DuplicateDetector {
+    /// Report only runs of at least `min_tokens` tokens
+    pub fn new(min_tokens: usize) -> Self {
+        Self {
+            min_tokens: min_tokens.max(1),
+            files: Vec::new(),
+            cancellation: CancellationToken::new(),
+        }
+    }
+
+    /// Stop `find` once `token` is raised, with the groups found so far
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+
+    /// Add a file; files that cannot be parsed are skipped
+    pub fn add_file(&mut self, uri: &str, source: &str, language: LanguageId) {
+        let mut parser = LanguageParser::new();
+        if parser.set_language(language).is_err() {
+            return;
+        }
+        let tree = match parser.parse(source, None) {
+            Ok(tree) => tree,
+            Err(_) => return,
+        };
+
+        let mut blocks = Vec::new();
+        collect_blocks(tree.root_node(), source, &mut blocks);
+        self.files.push(File {
+            uri: uri.to_string(),
+            source: source.to_string(),
+            blocks,
+        });
+    }
+
+    /// Groups of duplicated statement runs, in source order of their first copy
+    pub fn find(&self) -> Vec<DuplicateGroup> {
+        let mut covered: Vec<Vec<(usize, usize)>> = vec![Vec::new(); self.files.len()];
+        let mut groups = Vec::new();
+
+        let longest = self.files.iter().flat_map(|f| f.blocks.iter().map(|b| b.len())).max().unwrap_or(0);
+        for len in (1..=longest).rev() {
+            if self.cancellation.is_cancelled() {
+                break;
+            }
+            // Windows of `len` statements by normalized content, in source order
+            let mut order: Vec<Vec<u64>> = Vec::new();
+            let mut windows: HashMap<Vec<u64>, Vec<(usize, &[Statement])>> = HashMap::new();
+            for (file_index, file) in self.files.iter().enumerate() {
+                for block in &file.blocks {
+                    for window in block.windows(len) {
+                        let tokens: usize = window.iter().map(|s| s.tokens.len()).sum();
+                        if tokens < self.min_tokens {
+                            continue;
+                        }
+                        let key: Vec<u64> = window.iter().map(|s| s.hash).collect();
+                        let entry = windows.entry(key.clone()).or_default();
+                        if entry.is_empty() {
+                            order.push(key);
+                        }
+                        entry.push((file_index, window));
+                    }
+                }
+            }
+
+            for key in order {
+                let mut copies: Vec<(usize, &[Statement])> = Vec::new();
+                for (file_index, window) in &windows[&key] {
+                    let span = (window[0].start, window[len - 1].end);
+                    let overlaps = |&(s, e): &(usize, usize)| s < span.1 && span.0 < e;
+                    let taken = copies
+                        .iter()
+                        .any(|(f, w)| f == file_index && overlaps(&(w[0].start, w[len - 1].end)));
+                    if !taken && !covered[*file_index].iter().any(overlaps) {
+                        copies.push((*file_index, window));
+                    }
+                }
+                if copies.len() < 2 {
+                    continue;
+                }
+
+                for (file_index, window) in &copies {
+                    covered[*file_index].push((window[0].start, window[len - 1].end));
+                }
+                groups.push(self.group(&copies));
+            }
+        }
+
+        groups.sort_by(|a, b| {
+            let (a, b) = (&a.locations[0], &b.locations[0]);
+            a.uri.cmp(&b.uri).then(a.range.start.cmp(&b.range.start))
+        });
+        groups
+    }
+
+    fn group(&self, copies: &[(usize, &[Statement])]) -> DuplicateGroup {
+        let tokens = |window: &[Statement]| -> Vec<String> { window.iter().flat_map(|s| s.tokens.clone()).collect() };
+        let first = tokens(copies[0].1);
+        let same = (0..first.len())
+            .filter(|&i| copies[1..].iter().all(|(_, w)| tokens(w)[i] == first[i]))
+            .count();
+
+        let locations = copies
+            .iter()
+            .map(|(file_index, window)| {
+                let file = &self.files[*file_index];
+                let range = Range::new(
+                    position_at(&file.source, window[0].start),
+                    position_at(&file.source, window[window.len() - 1].end),
+                );
+                Location::new(file.uri.clone(), range)
+            })
+            .collect();
+
+        DuplicateGroup {
+            locations,
+            token_count: first.len(),
+            similarity: if first.is_empty() { 1.0 } else { same as f64 / first.len() as f64 },
+        }
+    }
+}
+
+/// Collect the statements of every block below `node`
+fn collect_blocks(node: Node, source: &str, blocks: &mut Vec<Vec<Statement>>) {
+    if matches!(node.kind(), "statement_block" | "block" | "compound_statement") {
+        let mut cursor = node.walk();
+        let statements: Vec<Statement> = node
+            .named_children(&mut cursor)
+            .filter(|child| !child.kind().contains("comment"))
+            .map(|child| statement(child, source))
+            .collect();
+        if !statements.is_empty() {
+            blocks.push(statements);
+        }
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_blocks(child, source, blocks);
+    }
+}
+
+fn statement(node: Node, source: &str) -> Statement {
+    let mut tokens = Vec::new();
+    let mut normalized = Vec::new();
+    leaf_tokens(node, source, &mut tokens, &mut normalized);
+
+    let mut hasher = DefaultHasher::new();
+    normalized.hash(&mut hasher);
+    Statement {
+        start: node.start_byte(),
+        end: node.end_byte(),
+        hash: hasher.finish(),
+        tokens,
+    }
+}
+
+/// The leaf tokens below `node`, as written and with identifiers and
+/// literals replaced by their class
+fn leaf_tokens<'s>(node: Node, source: &'s str, tokens: &mut Vec<String>, normalized: &mut Vec<&'s str>) {
+    let kind = node.kind();
+    if kind.contains("comment") {
+        return;
+    }
+    let is_literal = kind.contains("string") || kind.contains("number") || kind == "integer" || kind == "float";
+    if node.child_count() == 0 || is_literal {
+        let text = &source[node.byte_range()];
+        tokens.push(text.to_string());
+        normalized.push(if kind.contains("identifier") {
+            "$id"
+        } else if is_literal {
+            "$lit"
+        } else {
+            node.kind()
+        });
+        return;
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        leaf_tokens(child, source, tokens, normalized);
+    }
+}
+
+/// Position of a byte offset, with a UTF-16 column
+fn position_at(source: &str, offset: usize) -> Position {
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    Position::new(
+        before.matches('\n').count() as u32,
+        before[line_start..].encode_utf16().count() as u32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"function totalPrice(items) {
+  let sum = 0;
+  for (const item of items) {
+    if (item.visible) {
+      sum += item.price * item.count;
+    }
+  }
+  return sum;
+}
+
+function totalWeight(parcels) {
+  log("weighing");
+  let sum = 0;
+  for (const parcel of parcels) {
+    if (parcel.visible) {
+      sum += parcel.weight * parcel.count;
+    }
+  }
+  return sum;
+}
+
+function count(items) {
+  return items.length;
+}
+"#;
+
+    #[test]
+    fn test_copy_pasted_loops_are_grouped() {
+        let mut detector = DuplicateDetector::new(20);
+        detector.add_file("file:///cart.js", SOURCE, LanguageId::JavaScript);
+        let groups = detector.find();
+
+        assert_eq!(groups.len(), 1);
+        let group = &groups[0];
+        let ranges: Vec<Range> = group.locations.iter().map(|l| l.range).collect();
+        // `let sum = 0;` through `return sum;` in both functions
+        assert_eq!(ranges, vec![Range::from_coords(1, 2, 7, 13), Range::from_coords(12, 2, 18, 13)]);
+        assert!(group.token_count >= 20);
+        assert!(group.similarity > 0.5 && group.similarity < 1.0);
+    }
+
+    #[test]
+    fn test_duplicates_across_files() {
+        let mut detector = DuplicateDetector::new(20);
+        detector.add_file("file:///a.js", SOURCE, LanguageId::JavaScript);
+        detector.add_file("file:///b.js", &SOURCE[..SOURCE.find("\n\n").unwrap()], LanguageId::JavaScript);
+        let groups = detector.find();
+
+        // Both loops of `a.js` and the copy of the first in `b.js`
+        assert_eq!(groups.len(), 1);
+        let uris: Vec<&str> = groups[0].locations.iter().map(|l| l.uri.as_str()).collect();
+        assert_eq!(uris, vec!["file:///a.js", "file:///a.js", "file:///b.js"]);
+
+        // Verbatim copies of a single statement
+        let mut detector = DuplicateDetector::new(5);
+        detector.add_file("file:///c.py", "def f():\n    print(a + b)\n\ndef g():\n    print(a + b)\n", LanguageId::Python);
+        let groups = detector.find();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].similarity, 1.0);
+
+        // Too short to report
+        assert!(DuplicateDetector::new(50).find().is_empty());
+    }
+
+    #[test]
+    fn test_cancelled_search_stops_early() {
+        let token = CancellationToken::new();
+        let mut detector = DuplicateDetector::new(20).with_cancellation(token.clone());
+        for i in 0..50 {
+            detector.add_file(&format!("file:///cart{}.js", i), SOURCE, LanguageId::JavaScript);
+        }
+        assert!(!detector.find().is_empty());
+
+        token.cancel();
+        assert!(detector.find().is_empty());
+    }
+}
@@ -0,0 +1,295 @@
+//! Include Order
+//!
+//! Checks that the `#include` directives of a C/C++ file come in the groups
+//! of a policy, e.g. the file's own header first, then system headers, then
+//! third-party libraries, then other project headers.
+//! - Includes separated only by blank lines and comments form a block; each
+//!   block is checked on its own, so conditional includes keep their place
+//! - Within a group the order is left alone
+//! - Each include that comes after one of a later group gets a hint whose
+//!   quick fix rewrites the block in one edit, as Organize Imports does:
+//!   groups in policy order, separated by a blank line, comments moving with
+//!   the include below them
+//!
+//! Works from the includes the language adapter reports.
+
+use logos_core::{Diagnostic, DiagnosticFix, Position, Range};
+use logos_index::ImportInfo;
+use logos_parser::LanguageId;
+use serde::{Deserialize, Serialize};
+
+/// A group of includes, by what the included header belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IncludeGroup {
+    /// The header of the file itself: `"widget.h"` in `widget.cpp`
+    Own,
+    /// The C and C++ standard libraries and POSIX: `<vector>`, `<stdio.h>`
+    System,
+    /// Other `<...>` includes
+    ThirdParty,
+    /// Other `"..."` includes
+    Local,
+}
+
+impl IncludeGroup {
+    fn as_str(&self) -> &'static str {
+        match self {
+            IncludeGroup::Own => "own",
+            IncludeGroup::System => "system",
+            IncludeGroup::ThirdParty => "third-party",
+            IncludeGroup::Local => "local",
+        }
+    }
+}
+
+/// The order of the include groups; groups left out follow, in the default
+/// order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct IncludeOrder(pub Vec<IncludeGroup>);
+
+impl Default for IncludeOrder {
+    fn default() -> Self {
+        Self(vec![IncludeGroup::Own, IncludeGroup::System, IncludeGroup::ThirdParty, IncludeGroup::Local])
+    }
+}
+
+impl IncludeOrder {
+    fn rank(&self, group: IncludeGroup) -> usize {
+        match self.0.iter().position(|g| *g == group) {
+            Some(rank) => rank,
+            None => self.0.len() + Self::default().rank(group),
+        }
+    }
+}
+
+const C_STDLIB: &[&str] = &[
+    "assert.h", "complex.h", "ctype.h", "errno.h", "fenv.h", "float.h", "inttypes.h", "iso646.h", "limits.h",
+    "locale.h", "math.h", "setjmp.h", "signal.h", "stdalign.h", "stdarg.h", "stdatomic.h", "stdbool.h",
+    "stddef.h", "stdint.h", "stdio.h", "stdlib.h", "stdnoreturn.h", "string.h", "tgmath.h", "threads.h",
+    "time.h", "uchar.h", "wchar.h", "wctype.h", "dirent.h", "dlfcn.h", "fcntl.h", "poll.h", "pthread.h",
+    "sched.h", "semaphore.h", "strings.h", "termios.h", "unistd.h",
+];
+
+/// An include line of a block, with the comment lines above it
+struct Include {
+    path: String,
+    group: IncludeGroup,
+    line: usize,
+    text: String,
+}
+
+/// Hints for the includes of `source` that break `order`; `imports` are
+/// those the language adapter found in it. Only C and C++ are checked.
+pub fn check_include_order(
+    uri: &str,
+    source: &str,
+    language: LanguageId,
+    imports: &[ImportInfo],
+    order: &IncludeOrder,
+) -> Vec<Diagnostic> {
+    if !matches!(language, LanguageId::C | LanguageId::Cpp) {
+        return Vec::new();
+    }
+    let lines: Vec<&str> = source.split('\n').map(|l| l.trim_end_matches('\r')).collect();
+    let stem = file_stem(uri);
+
+    let mut include_lines: Vec<usize> = imports
+        .iter()
+        .map(|import| import.location.start.line as usize)
+        .filter(|&line| lines.get(line).is_some_and(|text| text.trim_start().starts_with("#include")))
+        .collect();
+    include_lines.sort_unstable();
+    include_lines.dedup();
+
+    let mut diagnostics = Vec::new();
+    let mut block: Vec<Include> = Vec::new();
+    let mut gap_start = 0;
+    for line in include_lines {
+        if !block.is_empty() && !lines[gap_start..line].iter().all(|text| is_gap(text)) {
+            diagnostics.extend(check_block(&block, &lines, order));
+            block.clear();
+        }
+        if block.is_empty() {
+            gap_start = line;
+        }
+        let Some(path) = include_path(lines[line]) else {
+            continue;
+        };
+        let comments: Vec<&str> = lines[gap_start..line]
+            .iter()
+            .map(|text| text.trim())
+            .filter(|text| !text.is_empty())
+            .collect();
+        let mut text = comments.join("\n");
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(lines[line].trim_end());
+        block.push(Include {
+            group: group(&path, stem),
+            path,
+            line,
+            text,
+        });
+        gap_start = line + 1;
+    }
+    diagnostics.extend(check_block(&block, &lines, order));
+    diagnostics
+}
+
+/// Hints for the misplaced includes of one block, each with the fix
+/// rewriting the whole block
+fn check_block(block: &[Include], lines: &[&str], order: &IncludeOrder) -> Vec<Diagnostic> {
+    let mut latest: Option<&Include> = None;
+    let mut misplaced = Vec::new();
+    for include in block {
+        match latest {
+            Some(previous) if order.rank(previous.group) > order.rank(include.group) => {
+                misplaced.push((include, previous.group));
+            }
+            _ => latest = Some(include),
+        }
+    }
+    if misplaced.is_empty() {
+        return Vec::new();
+    }
+
+    let mut sorted: Vec<&Include> = block.iter().collect();
+    sorted.sort_by_key(|include| order.rank(include.group));
+    let mut new_text = String::new();
+    for (i, include) in sorted.iter().enumerate() {
+        if i > 0 {
+            new_text.push('\n');
+            if sorted[i - 1].group != include.group {
+                new_text.push('\n');
+            }
+        }
+        new_text.push_str(&include.text);
+    }
+    let (first, last) = (block[0].line, block[block.len() - 1].line);
+    let range = Range::new(
+        Position::new(first as u32, 0),
+        Position::new(last as u32, lines[last].encode_utf16().count() as u32),
+    );
+    let fix = DiagnosticFix {
+        title: "Sort includes into groups".to_string(),
+        range,
+        new_text,
+    };
+
+    misplaced
+        .into_iter()
+        .map(|(include, after)| {
+            let line = lines[include.line];
+            let indent = line.len() - line.trim_start().len();
+            let range = Range::new(
+                Position::new(include.line as u32, indent as u32),
+                Position::new(include.line as u32, line.trim_end().len() as u32),
+            );
+            Diagnostic::hint(
+                range,
+                format!(
+                    "{} ({} header) should come before the {} headers",
+                    include.path,
+                    include.group.as_str(),
+                    after.as_str()
+                ),
+            )
+            .with_code("include-order".to_string())
+            .with_source("logos-semantic".to_string())
+            .with_fix(fix.clone())
+        })
+        .collect()
+}
+
+/// The path of an include with its delimiters: `<vector>`, `"widget.h"`
+fn include_path(line: &str) -> Option<String> {
+    let rest = line.trim_start().strip_prefix("#include")?.trim_start();
+    let close = match rest.chars().next()? {
+        '<' => '>',
+        '"' => '"',
+        _ => return None,
+    };
+    let end = rest[1..].find(close)? + 2;
+    Some(rest[..end].to_string())
+}
+
+fn group(path: &str, stem: &str) -> IncludeGroup {
+    let name = &path[1..path.len() - 1];
+    if path.starts_with('"') {
+        let file = name.rsplit('/').next().unwrap_or(name);
+        let own = !stem.is_empty() && file.rsplit_once('.').map_or(file, |(base, _)| base) == stem;
+        return if own { IncludeGroup::Own } else { IncludeGroup::Local };
+    }
+    if !name.contains('.') || C_STDLIB.contains(&name) || name.starts_with("sys/") {
+        IncludeGroup::System
+    } else {
+        IncludeGroup::ThirdParty
+    }
+}
+
+/// The file name of `uri` without its extension
+fn file_stem(uri: &str) -> &str {
+    let file = uri.rsplit('/').next().unwrap_or(uri);
+    file.rsplit_once('.').map_or(file, |(base, _)| base)
+}
+
+/// Whether a line between two includes keeps them in one block
+fn is_gap(line: &str) -> bool {
+    let line = line.trim();
+    line.is_empty() || line.starts_with("//") || (line.starts_with("/*") && line.ends_with("*/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use logos_index::AdapterRegistry;
+
+    fn check(uri: &str, source: &str, order: &IncludeOrder) -> Vec<Diagnostic> {
+        let registry = AdapterRegistry::with_builtins();
+        let imports = registry.get("cpp").unwrap().analyze(uri, source).imports;
+        check_include_order(uri, source, LanguageId::Cpp, &imports, order)
+    }
+
+    #[test]
+    fn test_misordered_includes_get_a_hint() {
+        let source = "#include \"util.h\"\n#include <vector>\n// Parser\n#include \"widget.h\"\n#include <boost/any.hpp>\n\nint main() {}\n";
+        let diagnostics = check("file:///src/widget.cpp", source, &IncludeOrder::default());
+
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "<vector> (system header) should come before the local headers",
+                "\"widget.h\" (own header) should come before the local headers",
+                "<boost/any.hpp> (third-party header) should come before the local headers",
+            ]
+        );
+        assert_eq!(diagnostics[0].range, Range::from_coords(1, 0, 1, 17));
+        assert_eq!(diagnostics[0].code.as_deref(), Some("include-order"));
+
+        let fix = &diagnostics[0].fixes[0];
+        assert_eq!(fix.range, Range::from_coords(0, 0, 4, 24));
+        assert_eq!(
+            fix.new_text,
+            "// Parser\n#include \"widget.h\"\n\n#include <vector>\n\n#include <boost/any.hpp>\n\n#include \"util.h\""
+        );
+    }
+
+    #[test]
+    fn test_ordered_includes_pass() {
+        let source = "#include \"widget.h\"\n\n#include <stdio.h>\n#include <vector>\n\n#include <gtest/gtest.h>\n\n#include \"util.h\"\n";
+        assert!(check("file:///src/widget.cpp", source, &IncludeOrder::default()).is_empty());
+
+        // Blocks are checked on their own
+        let source = "#include \"util.h\"\n#ifdef _WIN32\n#include <windows.h>\n#endif\n";
+        assert!(check("file:///src/widget.cpp", source, &IncludeOrder::default()).is_empty());
+
+        // A policy putting local headers first
+        let order = IncludeOrder(vec![IncludeGroup::Local]);
+        let source = "#include \"util.h\"\n#include <vector>\n";
+        assert!(check("file:///src/widget.cpp", source, &order).is_empty());
+    }
+}
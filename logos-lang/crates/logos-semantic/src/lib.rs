@@ -1,14 +1,18 @@
 //! Logos Semantic - Semantic analysis for the language service
 
+pub mod duplicates;
+pub mod include_order;
 pub mod resolver;
 pub mod scope;
 pub mod type_check;
 pub mod type_infer;
 pub mod unused;
 
+pub use duplicates::{DuplicateDetector, DuplicateGroup};
+pub use include_order::{check_include_order, IncludeGroup, IncludeOrder};
 pub use type_check::{TypeCheckConfig, TypeCheckError, TypeCheckErrorKind, TypeChecker};
 pub use type_infer::{LiteralType, Type, TypeContext, TypeError};
-pub use unused::{UnusedDetector, UnusedItem, UnusedKind};
+pub use unused::{analyze_workspace, UnusedDetector, UnusedItem, UnusedKind, WorkspaceOptions};
 
 use logos_core::{Diagnostic, Position, Range, Symbol, SymbolKind};
 use logos_parser::LanguageId;
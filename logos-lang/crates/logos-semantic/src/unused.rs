@@ -2,12 +2,13 @@
 //!
 //! Detects unused variables, functions, imports, and parameters in source code.
 
-use logos_core::{Diagnostic, DiagnosticSeverity, Range, Symbol, SymbolKind};
+use logos_core::{CancellationToken, Diagnostic, DiagnosticFix, DiagnosticSeverity, Position, Range, Symbol, SymbolKind};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
-/// The kind of unused item
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// The kind of unused item; ordered as declared
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum UnusedKind {
     Variable,
@@ -17,6 +18,7 @@ pub enum UnusedKind {
     Class,
     Constant,
     TypeAlias,
+    Field,
 }
 
 impl UnusedKind {
@@ -29,6 +31,7 @@ impl UnusedKind {
             UnusedKind::Class => "class",
             UnusedKind::Constant => "constant",
             UnusedKind::TypeAlias => "type alias",
+            UnusedKind::Field => "field",
         }
     }
 
@@ -40,7 +43,7 @@ impl UnusedKind {
             UnusedKind::Function | UnusedKind::Class | UnusedKind::TypeAlias => {
                 DiagnosticSeverity::Hint
             }
-            UnusedKind::Constant => DiagnosticSeverity::Hint,
+            UnusedKind::Constant | UnusedKind::Field => DiagnosticSeverity::Hint,
         }
     }
 }
@@ -58,10 +61,36 @@ pub struct UnusedItem {
     pub can_remove: bool,
     /// Suggested fix action
     pub fix_action: Option<String>,
+    /// The edit carrying out the fix, where there is a precise one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fix: Option<UnusedFix>,
+}
+
+/// Replace `range` with `new_text`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnusedFix {
+    pub range: Range,
+    pub new_text: String,
 }
 
 impl UnusedItem {
-    /// Create a diagnostic for this unused item
+    /// The edit carrying out `fix_action`: the precise fix when there is
+    /// one, otherwise prefixing an unused variable or parameter with `_`
+    pub fn quick_fix(&self) -> Option<UnusedFix> {
+        if self.fix.is_some() {
+            return self.fix.clone();
+        }
+        match self.kind {
+            UnusedKind::Variable | UnusedKind::Parameter => Some(UnusedFix {
+                range: self.range,
+                new_text: format!("_{}", self.name),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Create a diagnostic for this unused item, with its quick fix
     pub fn to_diagnostic(&self) -> Diagnostic {
         let message = format!("Unused {}: '{}'", self.kind.as_str(), self.name);
         let mut diagnostic = match self.kind.severity() {
@@ -72,6 +101,13 @@ impl UnusedItem {
         };
         diagnostic.source = Some("logos-semantic".to_string());
         diagnostic.code = Some(format!("unused-{}", self.kind.as_str()));
+        if let (Some(title), Some(fix)) = (&self.fix_action, self.quick_fix()) {
+            diagnostic = diagnostic.with_fix(DiagnosticFix {
+                title: title.clone(),
+                range: fix.range,
+                new_text: fix.new_text,
+            });
+        }
         diagnostic
     }
 }
@@ -84,6 +120,21 @@ pub struct UnusedDetector {
     references: HashSet<String>,
     /// Names that should be ignored (e.g., starting with _)
     ignore_patterns: Vec<String>,
+    /// Names matching any of these are ignored too
+    ignore_regexes: Vec<Regex>,
+    /// Names that are part of the public API (exported or public)
+    exported: HashSet<String>,
+    /// Whether exported items are reported too (workspace dead-code analysis)
+    include_exported: bool,
+    /// Function parameters never used in their function's body
+    unused_parameters: Vec<(String, Range)>,
+    /// Full ranges of functions and methods, whose own mentions (recursive
+    /// calls) don't count as uses; `None` when the name is defined twice
+    self_ranges: HashMap<String, Option<Range>>,
+    /// Names bound by destructuring, with the fix for each
+    destructured: HashMap<String, PatternFix>,
+    /// The statement of each import binding, for removing it
+    import_statements: HashMap<String, Range>,
 }
 
 impl Default for UnusedDetector {
@@ -98,14 +149,46 @@ impl UnusedDetector {
             defined_symbols: HashMap::new(),
             references: HashSet::new(),
             ignore_patterns: vec!["_".to_string()],
+            ignore_regexes: Vec::new(),
+            exported: HashSet::new(),
+            include_exported: false,
+            unused_parameters: Vec::new(),
+            self_ranges: HashMap::new(),
+            destructured: HashMap::new(),
+            import_statements: HashMap::new(),
         }
     }
 
+    /// Also report exported/public items. Within a single file these are
+    /// usually public API, so they are skipped by default.
+    pub fn with_include_exported(mut self, include: bool) -> Self {
+        self.include_exported = include;
+        self
+    }
+
+    /// Mark a name as exported or public, as reported by the language adapter
+    pub fn mark_exported(&mut self, name: &str) {
+        self.exported.insert(name.to_string());
+    }
+
+    /// Record the statement that imports `name` (the whole statement, or its
+    /// spec in Go), so an unused import comes with the edit removing it
+    pub fn mark_import_statement(&mut self, name: &str, statement: Range) {
+        self.import_statements.insert(name.to_string(), statement);
+    }
+
     /// Add a pattern to ignore (e.g., names starting with _)
     pub fn ignore_pattern(&mut self, pattern: &str) {
         self.ignore_patterns.push(pattern.to_string());
     }
 
+    /// Ignore names matching a regular expression (e.g. `_unused$` or
+    /// `^[A-Z][A-Z0-9_]*$`); unanchored patterns match anywhere in the name
+    pub fn ignore_regex(&mut self, pattern: &str) -> Result<(), regex::Error> {
+        self.ignore_regexes.push(Regex::new(pattern)?);
+        Ok(())
+    }
+
     /// Check if a name should be ignored
     fn should_ignore(&self, name: &str) -> bool {
         for pattern in &self.ignore_patterns {
@@ -113,10 +196,17 @@ impl UnusedDetector {
                 return true;
             }
         }
+        if self.ignore_regexes.iter().any(|re| re.is_match(name)) {
+            return true;
+        }
+        // Python dunders (`__repr__`, `__eq__`, ...) are called by the runtime
+        if is_dunder(name) {
+            return true;
+        }
         // Also ignore common special names
         matches!(
             name,
-            "self" | "cls" | "this" | "super" | "main" | "init" | "__init__" | "new"
+            "self" | "cls" | "this" | "super" | "main" | "init" | "new"
         )
     }
 
@@ -129,6 +219,7 @@ impl UnusedDetector {
             SymbolKind::Constant => Some(UnusedKind::Constant),
             SymbolKind::TypeParameter => Some(UnusedKind::TypeAlias),
             SymbolKind::Module => Some(UnusedKind::Import),
+            SymbolKind::Field | SymbolKind::Property => Some(UnusedKind::Field),
             _ => None,
         }
     }
@@ -162,10 +253,19 @@ impl UnusedDetector {
 
         // First pass: collect all defined symbols
         self.collect_definitions(symbols);
+        self.collect_destructuring(source);
 
         // Second pass: collect references from source
         self.collect_references(source);
 
+        // Python's `__all__` lists the public names of a module
+        for name in dunder_all_names(source) {
+            self.mark_used(name);
+        }
+
+        // Parameters are only referenced within their own function
+        self.collect_parameters(symbols, source);
+
         // Mark referenced symbols as used
         // Clone references to avoid borrow conflict
         let refs: Vec<String> = self.references.iter().cloned().collect();
@@ -174,48 +274,251 @@ impl UnusedDetector {
         }
 
         // Report unused items
-        self.report_unused()
+        self.report_unused(source)
     }
 
     /// Clear internal state
     fn clear(&mut self) {
         self.defined_symbols.clear();
         self.references.clear();
+        self.unused_parameters.clear();
+        self.self_ranges.clear();
+        self.destructured.clear();
     }
 
     /// Recursively collect all symbol definitions
     fn collect_definitions(&mut self, symbols: &[Symbol]) {
         for symbol in symbols {
-            self.register_definition(&symbol.name, symbol.selection_range, symbol.kind);
+            // A destructuring pattern (`{ a, b }`) is split into its names
+            // by `collect_destructuring`
+            if !symbol.name.starts_with(['{', '[', '(']) {
+                self.register_definition(&symbol.name, symbol.selection_range, symbol.kind);
+            }
+            if matches!(symbol.kind, SymbolKind::Function | SymbolKind::Method)
+                && symbol.range.contains_range(&symbol.selection_range)
+            {
+                self.self_ranges
+                    .entry(symbol.name.clone())
+                    .and_modify(|range| *range = None)
+                    .or_insert(Some(symbol.range));
+            }
             // Recursively collect child symbols
             self.collect_definitions(&symbol.children);
         }
     }
 
+    /// Register each name bound by a destructuring declaration
+    /// (`const { a, b } = obj`, `let (x, y) = pair`) on its own
+    fn collect_destructuring(&mut self, source: &str) {
+        for binding in destructured_bindings(source) {
+            if self.should_ignore(binding.name) {
+                continue;
+            }
+            let range = Range {
+                start: offset_to_position(source, binding.offset),
+                end: offset_to_position(source, binding.offset + binding.name.len()),
+            };
+            self.register_definition(binding.name, range, SymbolKind::Variable);
+            let (start, end, new_text) = binding.edit;
+            let fix = UnusedFix {
+                range: Range {
+                    start: offset_to_position(source, start),
+                    end: offset_to_position(source, end),
+                },
+                new_text,
+            };
+            self.destructured.insert(
+                binding.name.to_string(),
+                PatternFix { action: binding.action, fix, removes: binding.removes },
+            );
+        }
+    }
+
+    /// Recursively find the unused parameters of functions and methods
+    fn collect_parameters(&mut self, symbols: &[Symbol], source: &str) {
+        for symbol in symbols {
+            if matches!(symbol.kind, SymbolKind::Function | SymbolKind::Method) {
+                self.check_parameters(symbol, source);
+            }
+            self.collect_parameters(&symbol.children, source);
+        }
+    }
+
+    /// Record the parameters of `symbol` that its body doesn't mention.
+    /// Declarations without a body and signatures required by a trait or
+    /// base class (`impl Trait for T`, `@Override`, `override`) are skipped.
+    fn check_parameters(&mut self, symbol: &Symbol, source: &str) {
+        let start = position_to_offset(source, symbol.selection_range.end);
+        let end = position_to_offset(source, symbol.range.end).max(start);
+        let text = &source[start..end];
+
+        let open = match text.find('(') {
+            Some(i) => i,
+            None => return,
+        };
+        let close = match matching_paren(text, open) {
+            Some(i) => i,
+            None => return,
+        };
+
+        let after = &text[close + 1..];
+        let first_line = after.split('\n').next().unwrap_or("");
+        let (signature_rest, body) = if first_line.trim_end().ends_with(':') {
+            // Python: the body follows the `def ...:` line
+            (first_line, &after[first_line.len()..])
+        } else {
+            match after.find(['{', ';']) {
+                Some(i) if after[i..].starts_with('{') => (&after[..i], &after[i..]),
+                _ => return,
+            }
+        };
+
+        let declaration_line = source.lines().nth(symbol.range.start.line as usize).unwrap_or("");
+        let annotated_override = symbol.range.start.line > 0
+            && source
+                .lines()
+                .nth(symbol.range.start.line as usize - 1)
+                .is_some_and(|l| l.contains("@Override"));
+        if annotated_override
+            || has_word(declaration_line, "override")
+            || has_word(signature_rest, "override")
+            || in_trait_impl(source, start)
+        {
+            return;
+        }
+
+        // Go puts the name before the type: `func f(name string)`
+        let name_column = (symbol.selection_range.start.column as usize).min(declaration_line.len());
+        let name_first = has_word(&declaration_line[..name_column], "func");
+        let params_offset = start + open + 1;
+        for (name, offset) in parameter_names(&text[open + 1..close], name_first) {
+            if self.should_ignore(name) || has_word(body, name) {
+                continue;
+            }
+            let range = Range {
+                start: offset_to_position(source, params_offset + offset),
+                end: offset_to_position(source, params_offset + offset + name.len()),
+            };
+            self.unused_parameters.push((name.to_string(), range));
+        }
+    }
+
     /// Collect references from source code
     /// This is a simple heuristic-based approach
     fn collect_references(&mut self, source: &str) {
         // Simple word-based reference detection
         // A more accurate approach would use the AST
-        for word in source.split(|c: char| !c.is_alphanumeric() && c != '_') {
-            if !word.is_empty() && !self.should_ignore(word) {
-                // Check if this word is a defined symbol
-                if self.defined_symbols.contains_key(word) {
-                    // Count occurrences - if more than 1, it's used
-                    let count = source.matches(word).count();
-                    if count > 1 {
-                        self.mark_used(word);
-                    }
-                }
+        let self_ranges: HashMap<&str, (usize, usize)> = self
+            .self_ranges
+            .iter()
+            .filter_map(|(name, range)| {
+                let range = (*range)?;
+                Some((name.as_str(), (position_to_offset(source, range.start), position_to_offset(source, range.end))))
+            })
+            .collect();
+
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        let mut start = None;
+        for (i, c) in source.char_indices().chain(std::iter::once((source.len(), ' '))) {
+            if is_ident_char(c) {
+                start.get_or_insert(i);
+                continue;
+            }
+            let Some(s) = start.take() else { continue };
+            let word = &source[s..i];
+            if !self.defined_symbols.contains_key(word) {
+                continue;
+            }
+            // A function mentioning itself (recursion) doesn't keep it alive
+            if self_ranges.get(word).is_some_and(|&(from, to)| from <= s && i <= to) {
+                continue;
             }
+            // `obj.b` and the key of `{ b: 1 }` are not the variable `b`
+            let is_key = source[i..].starts_with(':') && !source[i..].starts_with("::");
+            let is_member = source[..s].ends_with('.') && !source[..s].ends_with("...");
+            if self.destructured.contains_key(word) && (is_member || is_key) {
+                continue;
+            }
+            *counts.entry(word).or_insert(0) += 1;
+        }
+
+        // The definition itself is one occurrence (unless it lies in the
+        // function's own range); any other is a use
+        let used: Vec<&str> = counts
+            .into_iter()
+            .filter(|&(word, count)| count > if self_ranges.contains_key(word) { 0 } else { 1 })
+            .map(|(word, _)| word)
+            .collect();
+        for word in used {
+            self.mark_used(word);
         }
     }
 
     /// Report all unused items
-    fn report_unused(&self) -> Vec<UnusedItem> {
+    /// The edit removing the unused import `name` at `range`: its whole
+    /// statement (with its lines, if it has them to itself) when it binds
+    /// nothing else, otherwise the name and the comma next to it
+    fn import_removal(&self, name: &str, range: Range, source: &str) -> Option<UnusedFix> {
+        let statement = *self.import_statements.get(name)?;
+        let start = position_to_offset(source, statement.start);
+        let end = position_to_offset(source, statement.end).max(start);
+        let shared = self.import_statements.values().filter(|s| **s == statement).count() > 1;
+
+        let (from, to) = if shared {
+            let name_start = position_to_offset(source, range.start).clamp(start, end);
+            let name_end = position_to_offset(source, range.end).clamp(name_start, end);
+            let line_start = source[..name_start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+            if let Some(rest) = source[name_end..end].trim_start().strip_prefix(',') {
+                match rest.find('\n') {
+                    // A name on a line of its own goes with the line
+                    Some(newline) if rest[..newline].trim().is_empty() && source[line_start..name_start].trim().is_empty() => {
+                        (line_start, end - rest.len() + newline + 1)
+                    }
+                    _ => (name_start, end - rest.trim_start().len()),
+                }
+            } else if let Some(kept) = source[start..name_start].trim_end().strip_suffix(',') {
+                (start + kept.len(), name_end)
+            } else {
+                (name_start, name_end)
+            }
+        } else {
+            let line_start = source[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+            let line_end = source[end..].find('\n').map(|i| end + i + 1).unwrap_or(source.len());
+            let alone = source[line_start..start].trim().is_empty()
+                && source[end..line_end].trim().trim_start_matches(';').is_empty();
+            if alone {
+                (line_start, line_end)
+            } else {
+                (start, end)
+            }
+        };
+        Some(UnusedFix {
+            range: Range {
+                start: offset_to_position(source, from),
+                end: offset_to_position(source, to),
+            },
+            new_text: String::new(),
+        })
+    }
+
+    fn report_unused(&self, source: &str) -> Vec<UnusedItem> {
         let mut unused = Vec::new();
         for (name, (range, kind, used)) in &self.defined_symbols {
+            if self.exported.contains(name) && !self.include_exported {
+                continue;
+            }
             if !used {
+                if let Some(pattern) = self.destructured.get(name) {
+                    unused.push(UnusedItem {
+                        kind: *kind,
+                        name: name.clone(),
+                        range: *range,
+                        can_remove: pattern.removes,
+                        fix_action: Some(pattern.action.clone()),
+                        fix: Some(pattern.fix.clone()),
+                    });
+                    continue;
+                }
                 let fix_action = match kind {
                     UnusedKind::Variable | UnusedKind::Parameter => {
                         Some(format!("Prefix with underscore: _{}", name))
@@ -235,13 +538,30 @@ impl UnusedDetector {
                         UnusedKind::Variable | UnusedKind::Import | UnusedKind::Constant
                     ),
                     fix_action,
+                    fix: match kind {
+                        UnusedKind::Import => self.import_removal(name, *range, source),
+                        _ => None,
+                    },
                 });
             }
         }
-        // Sort by range position
+        for (name, range) in &self.unused_parameters {
+            unused.push(UnusedItem {
+                kind: UnusedKind::Parameter,
+                name: name.clone(),
+                range: *range,
+                can_remove: false,
+                fix_action: Some(format!("Prefix with underscore: _{}", name)),
+                fix: None,
+            });
+        }
+        // Sort by range position, then name and kind so items at the same
+        // position come out in the same order on every run
         unused.sort_by(|a, b| {
             a.range.start.line.cmp(&b.range.start.line)
                 .then_with(|| a.range.start.column.cmp(&b.range.start.column))
+                .then_with(|| a.name.cmp(&b.name))
+                .then_with(|| a.kind.cmp(&b.kind))
         });
         unused
     }
@@ -255,10 +575,390 @@ impl UnusedDetector {
     }
 }
 
+/// Byte offset of a position (byte columns, as reported by the parsers)
+/// Options for [`analyze_workspace`]
+#[derive(Debug, Clone, Default)]
+pub struct WorkspaceOptions {
+    /// Also report exported items that no document references
+    pub include_exported: bool,
+    /// Names each document exports or makes public, by URI
+    pub exported: HashMap<String, HashSet<String>>,
+    /// Stops the analysis between documents once raised
+    pub cancellation: CancellationToken,
+}
+
+/// Dead code across a workspace, by URI.
+///
+/// Each document is analyzed as by [`UnusedDetector::analyze`], with the
+/// identifiers of `refs_by_uri` (the text of each document) as references.
+/// An exported item only counts as dead if no other document mentions it
+/// either, and is reported only with `include_exported`. Documents without
+/// dead code are left out. Once `options.cancellation` is raised, the
+/// documents analyzed so far are returned.
+pub fn analyze_workspace(
+    symbols_by_uri: &HashMap<String, Vec<Symbol>>,
+    refs_by_uri: &HashMap<String, String>,
+    options: &WorkspaceOptions,
+) -> BTreeMap<String, Vec<UnusedItem>> {
+    let no_exports = HashSet::new();
+    let mut dead = BTreeMap::new();
+
+    for (uri, symbols) in symbols_by_uri {
+        if options.cancellation.is_cancelled() {
+            break;
+        }
+        let source = refs_by_uri.get(uri).map(String::as_str).unwrap_or("");
+        let exported = options.exported.get(uri).unwrap_or(&no_exports);
+
+        let mut detector = UnusedDetector::new().with_include_exported(true);
+        for name in exported {
+            detector.mark_exported(name);
+        }
+        let items: Vec<UnusedItem> = detector
+            .analyze(symbols, source)
+            .into_iter()
+            .filter(|item| {
+                if !exported.contains(&item.name) {
+                    return true;
+                }
+                options.include_exported
+                    && !refs_by_uri
+                        .iter()
+                        .any(|(other, text)| other != uri && has_word(text, &item.name))
+            })
+            .collect();
+        if !items.is_empty() {
+            dead.insert(uri.clone(), items);
+        }
+    }
+    dead
+}
+
+fn position_to_offset(source: &str, position: Position) -> usize {
+    let mut offset = 0;
+    for (i, line) in source.split('\n').enumerate() {
+        if i == position.line as usize {
+            return offset + (position.column as usize).min(line.len());
+        }
+        offset += line.len() + 1;
+    }
+    source.len()
+}
+
+fn offset_to_position(source: &str, offset: usize) -> Position {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() as u32;
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    Position::new(line, (before.len() - line_start) as u32)
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Whether `name` is a Python special name such as `__repr__`
+fn is_dunder(name: &str) -> bool {
+    name.len() > 4 && name.starts_with("__") && name.ends_with("__")
+}
+
+/// The names listed in `__all__ = [...]` (or a tuple, or `+=`) assignments
+fn dunder_all_names(source: &str) -> Vec<&str> {
+    let assignment = Regex::new(r"(?m)^__all__\s*\+?=\s*[\[(]([^\])]*)[\])]").unwrap();
+    let entry = Regex::new(r#"["']([A-Za-z_][A-Za-z0-9_]*)["']"#).unwrap();
+    assignment
+        .captures_iter(source)
+        .filter_map(|c| c.get(1))
+        .flat_map(|list| entry.captures_iter(list.as_str()).filter_map(|c| c.get(1)).map(|m| m.as_str()))
+        .collect()
+}
+
+/// Whether `word` occurs in `text` as a whole word
+fn has_word(text: &str, word: &str) -> bool {
+    text.split(|c: char| !is_ident_char(c)).any(|w| w == word)
+}
+
+/// How to fix an unused name bound by destructuring
+#[derive(Debug, Clone)]
+struct PatternFix {
+    action: String,
+    fix: UnusedFix,
+    /// Whether the fix drops the binding rather than renaming it
+    removes: bool,
+}
+
+/// A name bound by a destructuring pattern, at byte `offset`, with the edit
+/// that fixes it if unused: replace `start..end` with the text
+struct PatternBinding<'s> {
+    name: &'s str,
+    offset: usize,
+    edit: (usize, usize, String),
+    action: String,
+    removes: bool,
+}
+
+/// The names bound by `const`/`let`/`var` declarations of an object, array
+/// or tuple pattern. Nested patterns are left out. An unused object element
+/// is dropped with its comma, or renamed with a `_` prefix when a rest
+/// element would then collect it or it is the only one; an array element
+/// leaves a hole unless it is the last; a tuple element becomes `_`.
+fn destructured_bindings(source: &str) -> Vec<PatternBinding<'_>> {
+    let declaration = Regex::new(r"\b(?:const|let|var)\s+(?:mut\s+)?[\[{(]").unwrap();
+    let mut bindings = Vec::new();
+    for m in declaration.find_iter(source) {
+        let open = m.end() - 1;
+        let Some(close) = matching_bracket(source, open) else { continue };
+        // Go's `var ( ... )` groups declarations; a pattern is assigned,
+        // annotated or iterated over
+        let after = source[close + 1..].trim_start();
+        let assigned = (after.starts_with('=') && !after.starts_with("=="))
+            || after.starts_with(':')
+            || has_word(after.split_whitespace().next().unwrap_or(""), "of")
+            || has_word(after.split_whitespace().next().unwrap_or(""), "in");
+        if !assigned {
+            continue;
+        }
+
+        let kind = source.as_bytes()[open];
+        let elements: Vec<(usize, usize)> = split_top_level(source, open + 1, close)
+            .into_iter()
+            .filter_map(|(start, end)| {
+                let text = &source[start..end];
+                let leading = text.len() - text.trim_start().len();
+                let trimmed = text.trim();
+                (!trimmed.is_empty()).then_some((start + leading, start + leading + trimmed.len()))
+            })
+            .collect();
+        let has_rest = elements.iter().any(|&(start, _)| source[start..].starts_with("..."));
+
+        for (index, &(start, end)) in elements.iter().enumerate() {
+            let Some((name, offset)) = element_name(source, start, end, kind) else { continue };
+            let is_rest = source[start..].starts_with("...");
+            let previous = index.checked_sub(1).map(|i| elements[i]);
+            let next = elements.get(index + 1).copied();
+            let dropped = match (kind, previous, next) {
+                (b'(', _, _) => None,
+                (b'{', _, _) if has_rest && !is_rest => None,
+                (_, Some((_, previous_end)), None) => Some((previous_end, end)),
+                (b'[', _, Some(_)) => Some((start, end)),
+                (_, None, Some((next_start, _))) => Some((start, next_start)),
+                (_, Some((_, previous_end)), Some(_)) => Some((previous_end, end)),
+                (_, None, None) => None,
+            };
+            let (edit, action, removes) = match (kind, dropped) {
+                (b'(', _) => ((start, end, "_".to_string()), format!("Replace '{}' with '_'", name), true),
+                (_, Some((from, to))) => (
+                    (from, to, String::new()),
+                    format!("Remove '{}' from the destructuring pattern", name),
+                    true,
+                ),
+                // `{ a }` reads property `a`, so it must stay as `a: _a`
+                (_, None) => {
+                    let shorthand = kind == b'{' && offset == start;
+                    let renamed = match shorthand {
+                        true => format!("{}: _{}", name, name),
+                        false => format!("_{}", name),
+                    };
+                    ((offset, offset + name.len(), renamed), format!("Prefix with underscore: _{}", name), false)
+                }
+            };
+            bindings.push(PatternBinding { name, offset, edit, action, removes });
+        }
+    }
+    bindings
+}
+
+/// The name an element of a pattern binds, and its offset: `b`, `b = 1`,
+/// `key: b`, `...b`, and in tuples `mut b` / `ref b`
+fn element_name(source: &str, start: usize, end: usize, kind: u8) -> Option<(&str, usize)> {
+    let mut from = start;
+    let mut text = &source[start..end];
+    if let Some(rest) = text.strip_prefix("...") {
+        from += 3;
+        text = rest;
+    }
+    if kind == b'{' {
+        if let Some(colon) = text.find(':') {
+            from += colon + 1;
+            text = &text[colon + 1..];
+        }
+    }
+    if let Some(default) = text.find('=') {
+        text = &text[..default];
+    }
+    if kind == b'(' {
+        for modifier in ["ref ", "mut "] {
+            if let Some(rest) = text.trim_start().strip_prefix(modifier) {
+                from += text.len() - rest.len();
+                text = rest;
+            }
+        }
+    }
+    let leading = text.len() - text.trim_start().len();
+    let name = text.trim();
+    let is_name = !name.is_empty() && name.chars().all(is_ident_char) && !name.starts_with(|c: char| c.is_ascii_digit());
+    is_name.then_some((name, from + leading))
+}
+
+/// Byte ranges of the comma-separated pieces of `source[start..end]`,
+/// ignoring commas inside brackets
+fn split_top_level(source: &str, start: usize, end: usize) -> Vec<(usize, usize)> {
+    let mut pieces = Vec::new();
+    let (mut depth, mut piece_start) = (0i32, start);
+    for (i, ch) in source[start..end].char_indices() {
+        match ch {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                pieces.push((piece_start, start + i));
+                piece_start = start + i + 1;
+            }
+            _ => {}
+        }
+    }
+    pieces.push((piece_start, end));
+    pieces
+}
+
+/// Index of the bracket closing the `(`, `[` or `{` at `open`
+fn matching_bracket(text: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, ch) in text[open..].char_indices() {
+        match ch {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Index of the `)` closing the `(` at `open`
+fn matching_paren(text: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, ch) in text[open..].char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Names declared by a parameter list, with their byte offsets in it.
+/// `name: Type` and `Type name` forms are recognized; `name_first` selects
+/// `name Type` (Go) for parameters without a colon. Destructuring patterns
+/// are skipped.
+fn parameter_names(params: &str, name_first: bool) -> Vec<(&str, usize)> {
+    let mut pieces = Vec::new();
+    let (mut depth, mut piece_start) = (0i32, 0);
+    for (i, ch) in params.char_indices() {
+        match ch {
+            '(' | '[' | '{' | '<' => depth += 1,
+            ')' | ']' | '}' | '>' => depth -= 1,
+            ',' if depth == 0 => {
+                pieces.push((piece_start, i));
+                piece_start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    pieces.push((piece_start, params.len()));
+
+    let mut names = Vec::new();
+    for (piece_start, piece_end) in pieces {
+        let piece = &params[piece_start..piece_end];
+        // Drop a default value
+        let piece = match piece.find('=') {
+            Some(i) => &piece[..i],
+            None => piece,
+        };
+        if piece.trim_start().starts_with(['{', '[', '(']) {
+            continue;
+        }
+
+        // A single `:` separates the name from its type; `::` is a path
+        let colon = piece.char_indices().find(|&(i, c)| {
+            c == ':' && !piece[i + 1..].starts_with(':') && !piece[..i].ends_with(':')
+        });
+        let words: Vec<(usize, &str)> = {
+            let head = colon.map(|(i, _)| &piece[..i]).unwrap_or(piece);
+            let mut words = Vec::new();
+            let mut start = None;
+            for (i, c) in head.char_indices().chain(std::iter::once((head.len(), ' '))) {
+                match (is_ident_char(c), start) {
+                    (true, None) => start = Some(i),
+                    (false, Some(s)) => {
+                        words.push((s, &head[s..i]));
+                        start = None;
+                    }
+                    _ => {}
+                }
+            }
+            words
+        };
+
+        let word = if colon.is_some() || !name_first {
+            words.last()
+        } else {
+            words.first()
+        };
+        if let Some(&(offset, name)) = word {
+            if !name.starts_with(|c: char| c.is_ascii_digit()) {
+                names.push((name, piece_start + offset));
+            }
+        }
+    }
+    names
+}
+
+/// Whether `offset` lies inside a Rust `impl Trait for Type { ... }` block
+fn in_trait_impl(source: &str, offset: usize) -> bool {
+    let before = &source[..offset];
+    let mut search = 0;
+    while let Some(found) = before[search..].find("impl") {
+        let at = search + found;
+        search = at + 4;
+        let bounded = !before[..at].ends_with(is_ident_char) && !before[at + 4..].starts_with(is_ident_char);
+        if !bounded {
+            continue;
+        }
+        let open = match before[at..].find(['{', ';']) {
+            Some(i) if before[at + i..].starts_with('{') => at + i,
+            _ => continue,
+        };
+        if !has_word(&before[at..open], "for") {
+            continue;
+        }
+        let mut depth = 0;
+        let closed = source[open..].char_indices().any(|(i, ch)| {
+            match ch {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => {}
+            }
+            depth == 0 && open + i < offset
+        });
+        if !closed {
+            return true;
+        }
+    }
+    false
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use logos_core::Position;
 
     fn make_symbol(name: &str, kind: SymbolKind, line: u32) -> Symbol {
         Symbol {
@@ -291,6 +991,131 @@ mod tests {
         assert_eq!(unused[0].name, "unused_var");
     }
 
+    #[test]
+    fn test_report_order_is_deterministic() {
+        // Generated ranges: everything starts at line 0, column 0
+        let symbols = vec![
+            make_symbol("zeta", SymbolKind::Variable, 0),
+            make_symbol("alpha", SymbolKind::Constant, 0),
+            make_symbol("mid", SymbolKind::Constant, 0),
+            make_symbol("beta", SymbolKind::Variable, 0),
+            make_symbol("later", SymbolKind::Variable, 1),
+        ];
+        let source = "zeta alpha mid beta\nlater";
+
+        for _ in 0..10 {
+            // A new detector hashes with a new seed each time
+            let mut detector = UnusedDetector::new();
+            let names: Vec<String> = detector.analyze(&symbols, source).into_iter().map(|u| u.name).collect();
+            assert_eq!(names, vec!["alpha", "beta", "mid", "zeta", "later"]);
+        }
+    }
+
+    /// Each unused item's name, with the source after its fix
+    fn unused_with_fixes(source: &str) -> Vec<(String, Option<String>)> {
+        UnusedDetector::new()
+            .analyze(&[], source)
+            .into_iter()
+            .map(|item| {
+                let fixed = item.fix.map(|fix| {
+                    let start = position_to_offset(source, fix.range.start);
+                    let end = position_to_offset(source, fix.range.end);
+                    format!("{}{}{}", &source[..start], fix.new_text, &source[end..])
+                });
+                (item.name, fixed)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_partially_unused_object_destructure() {
+        let source = "const obj = { a: 1, b: 2 };\nconst { a, b } = obj;\nconsole.log(a, obj.b);\n";
+        assert_eq!(
+            unused_with_fixes(source),
+            vec![(
+                "b".to_string(),
+                Some("const obj = { a: 1, b: 2 };\nconst { a } = obj;\nconsole.log(a, obj.b);\n".to_string())
+            )]
+        );
+
+        // Dropping `a` would put it into `rest`
+        assert_eq!(
+            unused_with_fixes("const { a, key: b = 2, ...rest } = obj;\nuse(b, rest);\n"),
+            vec![(
+                "a".to_string(),
+                Some("const { a: _a, key: b = 2, ...rest } = obj;\nuse(b, rest);\n".to_string())
+            )]
+        );
+    }
+
+    #[test]
+    fn test_partially_unused_tuple_binding() {
+        let source = "fn main() {\n    let (x, mut y) = pair();\n    println!(\"{}\", x);\n}\n";
+        assert_eq!(
+            unused_with_fixes(source),
+            vec![(
+                "y".to_string(),
+                Some("fn main() {\n    let (x, _) = pair();\n    println!(\"{}\", x);\n}\n".to_string())
+            )]
+        );
+        // Positions matter in arrays: an element before the last leaves a hole
+        assert_eq!(
+            unused_with_fixes("const [first, second] = items;\nuse(second);\n"),
+            vec![("first".to_string(), Some("const [, second] = items;\nuse(second);\n".to_string()))]
+        );
+    }
+
+    #[test]
+    fn test_unused_import_fixes() {
+        let source = "import { a, b } from './m';\nimport util from './helpers';\nlet x = 1;\nconsole.log(a);\n";
+        let at = |name: &str, kind: SymbolKind, line: u32, column: u32| {
+            let mut symbol = make_symbol(name, kind, line);
+            symbol.selection_range = Range::from_coords(line, column, line, column + name.len() as u32);
+            symbol.range = symbol.selection_range;
+            symbol
+        };
+        let mut detector = UnusedDetector::new();
+        detector.mark_import_statement("a", Range::from_coords(0, 0, 0, 27));
+        detector.mark_import_statement("b", Range::from_coords(0, 0, 0, 27));
+        detector.mark_import_statement("util", Range::from_coords(1, 0, 1, 29));
+        let symbols = vec![
+            at("a", SymbolKind::Module, 0, 9),
+            at("b", SymbolKind::Module, 0, 12),
+            at("util", SymbolKind::Module, 1, 7),
+            at("x", SymbolKind::Variable, 2, 4),
+        ];
+
+        let fixed: Vec<(String, String)> = detector
+            .analyze(&symbols, source)
+            .into_iter()
+            .map(|item| {
+                let diagnostic = item.to_diagnostic();
+                let fix = &diagnostic.fixes[0];
+                let start = position_to_offset(source, fix.range.start);
+                let end = position_to_offset(source, fix.range.end);
+                (fix.title.clone(), format!("{}{}{}", &source[..start], fix.new_text, &source[end..]))
+            })
+            .collect();
+        assert_eq!(
+            fixed,
+            vec![
+                (
+                    "Remove unused import".to_string(),
+                    "import { a } from './m';\nimport util from './helpers';\nlet x = 1;\nconsole.log(a);\n".to_string()
+                ),
+                // The statement goes with its line
+                (
+                    "Remove unused import".to_string(),
+                    "import { a, b } from './m';\nlet x = 1;\nconsole.log(a);\n".to_string()
+                ),
+                (
+                    "Prefix with underscore: _x".to_string(),
+                    "import { a, b } from './m';\nimport util from './helpers';\nlet _x = 1;\nconsole.log(a);\n".to_string()
+                ),
+            ]
+        );
+    }
+
     #[test]
     fn test_ignore_underscore() {
         let mut detector = UnusedDetector::new();
@@ -305,6 +1130,239 @@ mod tests {
         assert_eq!(unused[0].name, "unused");
     }
 
+    #[test]
+    fn test_ignore_regex_suffix() {
+        let mut detector = UnusedDetector::new();
+        detector.ignore_regex("_unused$").unwrap();
+        let symbols = vec![
+            make_symbol("value_unused", SymbolKind::Variable, 0),
+            make_symbol("unused_value", SymbolKind::Variable, 1),
+        ];
+        let source = "let value_unused = 1;\nlet unused_value = 2;";
+
+        let unused = detector.analyze(&symbols, source);
+        assert_eq!(unused.len(), 1);
+        assert_eq!(unused[0].name, "unused_value");
+    }
+
+    #[test]
+    fn test_ignore_regex_all_caps() {
+        let mut detector = UnusedDetector::new();
+        detector.ignore_regex("^[A-Z][A-Z0-9_]*$").unwrap();
+        detector.ignore_pattern("tmp");
+        let symbols = vec![
+            make_symbol("MAX_SIZE", SymbolKind::Constant, 0),
+            make_symbol("MaxSize", SymbolKind::Constant, 1),
+            make_symbol("tmp_value", SymbolKind::Variable, 2),
+        ];
+        let source = "const MAX_SIZE = 1;\nconst MaxSize = 2;\nlet tmp_value = 3;";
+
+        let unused = detector.analyze(&symbols, source);
+        assert_eq!(unused.len(), 1);
+        assert_eq!(unused[0].name, "MaxSize");
+
+        assert!(detector.ignore_regex("(unclosed").is_err());
+    }
+
+    #[test]
+    fn test_exported_items_not_reported() {
+        use logos_index::{LanguageAdapter, RustAdapter};
+
+        let source = "pub fn api() {}\n\nfn helper() {}\n\nstruct Point {\n    x: i32,\n}\n";
+        let analysis = RustAdapter::new().unwrap().analyze("file:///lib.rs", source);
+        let symbols: Vec<Symbol> = analysis
+            .symbols
+            .iter()
+            .map(|s| Symbol {
+                name: s.name.clone(),
+                kind: s.kind,
+                range: s.location.range,
+                selection_range: s.location.selection_range,
+                detail: None,
+                children: Vec::new(),
+            })
+            .collect();
+
+        let mut detector = UnusedDetector::new();
+        for symbol in analysis.symbols.iter().filter(|s| s.exported) {
+            detector.mark_exported(&symbol.name);
+        }
+        let names: Vec<String> = detector.analyze(&symbols, source).into_iter().map(|i| i.name).collect();
+        assert!(!names.contains(&"api".to_string()));
+        assert!(names.contains(&"helper".to_string()));
+
+        let mut detector = UnusedDetector::new().with_include_exported(true);
+        detector.mark_exported("api");
+        let names: Vec<String> = detector.analyze(&symbols, source).into_iter().map(|i| i.name).collect();
+        assert!(names.contains(&"api".to_string()));
+    }
+
+    #[test]
+    fn test_unused_private_field() {
+        let mut detector = UnusedDetector::new();
+        let symbols = vec![
+            make_symbol("count", SymbolKind::Field, 1),
+            make_symbol("total", SymbolKind::Field, 2),
+        ];
+        let source = "class A {\n  int count;\n  int total;\n  int get() { return total; }\n}";
+
+        let unused = detector.analyze(&symbols, source);
+        assert_eq!(unused.len(), 1);
+        assert_eq!(unused[0].name, "count");
+        assert_eq!(unused[0].kind, UnusedKind::Field);
+    }
+
+    fn function_symbol(source: &str, name: &str, kind: SymbolKind) -> Symbol {
+        let line = source.lines().position(|l| has_word(l, name) && l.contains('(')).unwrap() as u32;
+        let column = source.lines().nth(line as usize).unwrap().find(name).unwrap() as u32;
+        // The function ends at the first closing brace at the start of a line
+        let end = match source.lines().skip(line as usize).position(|l| l.starts_with('}')) {
+            Some(i) => Position::new(line + i as u32, 1),
+            None => Position::new(source.lines().count() as u32, 0),
+        };
+        Symbol {
+            name: name.to_string(),
+            kind,
+            range: Range {
+                start: Position::new(line, 0),
+                end,
+            },
+            selection_range: Range {
+                start: Position::new(line, column),
+                end: Position::new(line, column + name.len() as u32),
+            },
+            detail: None,
+            children: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_unused_parameter() {
+        let source = "function scale(value, factor, _unused) {\n  return value * 2;\n}\nscale(1, 2, 3);\n";
+        let symbols = vec![function_symbol(source, "scale", SymbolKind::Function)];
+
+        let unused = UnusedDetector::new().analyze(&symbols, source);
+        assert_eq!(unused.len(), 1);
+        assert_eq!(unused[0].kind, UnusedKind::Parameter);
+        assert_eq!(unused[0].name, "factor");
+        assert_eq!(unused[0].range, Range::from_coords(0, 22, 0, 28));
+        assert_eq!(unused[0].fix_action.as_deref(), Some("Prefix with underscore: _factor"));
+    }
+
+    #[test]
+    fn test_parameter_styles() {
+        assert_eq!(
+            parameter_names("&self, mut count: usize, map: HashMap<K, V>", false)
+                .into_iter()
+                .map(|(n, _)| n)
+                .collect::<Vec<_>>(),
+            vec!["self", "count", "map"]
+        );
+        assert_eq!(
+            parameter_names("const std::string& name, int times = 2", false)
+                .into_iter()
+                .map(|(n, _)| n)
+                .collect::<Vec<_>>(),
+            vec!["name", "times"]
+        );
+        assert_eq!(
+            parameter_names("a, b int, opts ...Option", true)
+                .into_iter()
+                .map(|(n, _)| n)
+                .collect::<Vec<_>>(),
+            vec!["a", "b", "opts"]
+        );
+    }
+
+    #[test]
+    fn test_trait_impl_parameters_not_reported() {
+        let source = "impl Display for User {\n    fn fmt(&self, f: &mut Formatter) -> Result {\n        Ok(())\n    }\n}\n\nfn helper(f: i32) {\n}\n";
+        let fmt = Symbol {
+            range: Range::from_coords(1, 4, 3, 5),
+            ..function_symbol(source, "fmt", SymbolKind::Method)
+        };
+        let helper = Symbol {
+            range: Range::from_coords(6, 0, 7, 1),
+            ..function_symbol(source, "helper", SymbolKind::Function)
+        };
+
+        let unused = UnusedDetector::new().analyze(&[fmt, helper], source);
+        let params: Vec<(&str, u32)> = unused
+            .iter()
+            .filter(|i| i.kind == UnusedKind::Parameter)
+            .map(|i| (i.name.as_str(), i.range.start.line))
+            .collect();
+        assert_eq!(params, vec![("f", 6)]);
+    }
+
+    #[test]
+    fn test_recursion_does_not_count_as_use() {
+        let source = "fn walk(n: u32) -> u32 {\n    if n == 0 { 0 } else { walk(n - 1) }\n}\n\nfn depth(n: u32) -> u32 {\n    if n == 0 { 0 } else { depth(n - 1) + 1 }\n}\n\nfn is_even(n: u32) -> bool {\n    n == 0 || is_odd(n - 1)\n}\n\nfn is_odd(n: u32) -> bool {\n    n != 0 && is_even(n - 1)\n}\n\nfn main() {\n    depth(3);\n}\n";
+        let function = |name: &str, start: u32, end: u32| {
+            let column = source.lines().nth(start as usize).unwrap().find(name).unwrap() as u32;
+            Symbol {
+                name: name.to_string(),
+                kind: SymbolKind::Function,
+                range: Range::from_coords(start, 0, end, 1),
+                selection_range: Range::from_coords(start, column, start, column + name.len() as u32),
+                detail: None,
+                children: Vec::new(),
+            }
+        };
+        let symbols = vec![
+            function("walk", 0, 2),
+            function("depth", 4, 6),
+            function("is_even", 8, 10),
+            function("is_odd", 12, 14),
+            function("main", 16, 18),
+        ];
+
+        let names: Vec<String> = UnusedDetector::new().analyze(&symbols, source).into_iter().map(|i| i.name).collect();
+        // `depth` is called from `main`; mutually recursive functions use each other
+        assert_eq!(names, vec!["walk"]);
+    }
+
+    #[test]
+    fn test_workspace_dead_code() {
+        let lib = "export function helper() {\n  return 1;\n}\n\nexport function orphan() {\n  return 2;\n}\n\nfunction local() {\n  return 3;\n}\n";
+        let app = "import { helper } from './lib';\nconsole.log(helper());\n";
+        let symbols: HashMap<String, Vec<Symbol>> = [
+            (
+                "lib.ts".to_string(),
+                ["helper", "orphan", "local"]
+                    .iter()
+                    .map(|name| function_symbol(lib, name, SymbolKind::Function))
+                    .collect(),
+            ),
+            ("app.ts".to_string(), Vec::new()),
+        ]
+        .into_iter()
+        .collect();
+        let refs: HashMap<String, String> =
+            [("lib.ts".to_string(), lib.to_string()), ("app.ts".to_string(), app.to_string())].into_iter().collect();
+        let mut options = WorkspaceOptions::default();
+        options
+            .exported
+            .insert("lib.ts".to_string(), ["helper", "orphan"].iter().map(|s| s.to_string()).collect());
+
+        let names = |options: &WorkspaceOptions| -> Vec<(String, String)> {
+            analyze_workspace(&symbols, &refs, options)
+                .into_iter()
+                .flat_map(|(uri, items)| items.into_iter().map(move |item| (uri.clone(), item.name)))
+                .collect()
+        };
+
+        // Only the local function without exported items
+        assert_eq!(names(&options), vec![("lib.ts".to_string(), "local".to_string())]);
+
+        // `helper` is used from `app.ts`; `orphan` from nowhere
+        options.include_exported = true;
+        assert_eq!(
+            names(&options),
+            vec![("lib.ts".to_string(), "orphan".to_string()), ("lib.ts".to_string(), "local".to_string())]
+        );
+    }
+
     #[test]
     fn test_ignore_special_names() {
         let mut detector = UnusedDetector::new();
@@ -317,4 +1375,29 @@ mod tests {
         let unused = detector.analyze(&symbols, source);
         assert!(unused.is_empty());
     }
+
+    #[test]
+    fn test_python_dunders_and_all() {
+        use logos_index::{LanguageAdapter, PythonAdapter};
+
+        let source = "__all__ = [\n    \"load\",\n    'Config',\n]\n\nclass Config:\n    def __repr__(self):\n        return \"Config\"\n\n    def __eq__(self, other):\n        return other is self\n\ndef load(path):\n    return path\n\ndef helper():\n    pass\n";
+        let analysis = PythonAdapter::new().unwrap().analyze("file:///config.py", source);
+        let symbols: Vec<Symbol> = analysis
+            .symbols
+            .iter()
+            .map(|s| Symbol {
+                name: s.name.clone(),
+                kind: s.kind,
+                range: s.location.range,
+                selection_range: s.location.selection_range,
+                detail: None,
+                children: Vec::new(),
+            })
+            .collect();
+        assert!(symbols.iter().any(|s| s.name == "__repr__"));
+
+        let names: Vec<String> = UnusedDetector::new().analyze(&symbols, source).into_iter().map(|i| i.name).collect();
+        assert_eq!(names, vec!["helper"]);
+        assert_eq!(dunder_all_names("__all__ += (\"a\", 'b')\nx = ['c']\n"), vec!["a", "b"]);
+    }
 }